@@ -0,0 +1,87 @@
+// Time taken to build the grid uploaded to the gpu from a full screen of styled text
+// Glyphs are given a fixed atlas position since the glyph cache needs a font
+// Run with: cargo bench -p wgpu_terminal --features bench
+use cgmath::{Vector2, ElementWise};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use terminal::{Terminal, TerminalBuilder, TerminalSettings, TerminalUserEvent};
+use terminal::bench::create_criterion;
+use terminal::terminal_renderer::TerminalRenderer;
+use tile_renderer::CellData;
+use vt100::common::WindowAction;
+use wgpu_terminal::app_window::set_cell_data_pen;
+use std::time::Duration;
+
+const GRID_SIZE: Vector2<usize> = Vector2::new(200, 50);
+const GLYPH_SIZE: Vector2<usize> = Vector2::new(8, 16);
+
+// Terminal is resized before its process exits so the display can be written to afterwards
+fn create_terminal() -> Terminal {
+    let (action_tx, action_rx) = std::sync::mpsc::channel();
+    let (exit_tx, exit_rx) = std::sync::mpsc::channel::<()>();
+    let mut terminal = Terminal::new(TerminalBuilder {
+        process_read: Box::new(move |_| {
+            let _ = exit_rx.recv();
+            0
+        }),
+        process_write: Box::new(|_| {}),
+        process_ioctl: Box::new(|_| {}),
+        window_action: Box::new(move |action| {
+            let _ = action_tx.send(action);
+        }),
+        is_newline_carriage_return: false,
+        settings: TerminalSettings::default(),
+        record_output: None,
+        process_resize: None,
+        on_parser_exit: None,
+    });
+    let user_events = terminal.get_user_event_handler();
+    let window_size = GRID_SIZE.mul_element_wise(GLYPH_SIZE);
+    user_events.send(TerminalUserEvent::Resize { window_size, grid_size: GRID_SIZE }).unwrap();
+    user_events.send(TerminalUserEvent::ForceRedraw).unwrap();
+    assert_eq!(action_rx.recv_timeout(Duration::from_secs(5)).unwrap(), WindowAction::Refresh);
+    drop(exit_tx);
+    terminal.join_parser_thread();
+    terminal
+}
+
+// Every cell has its own colour so the display looks like the output of a colour test
+fn create_screen_contents() -> Vec<u8> {
+    let mut data = Vec::new();
+    for y in 0..GRID_SIZE.y {
+        for x in 0..GRID_SIZE.x {
+            let colour = (x + y) % 256;
+            let style = if x % 16 == 0 { "\x1b[4m" } else { "\x1b[24m" };
+            data.extend_from_slice(format!("\x1b[38;5;{}m{}{}", colour, style, (b'a' + (x % 26) as u8) as char).as_bytes());
+        }
+    }
+    data
+}
+
+fn bench_renderer(c: &mut Criterion) {
+    let mut terminal = create_terminal();
+    terminal.write_to_display(create_screen_contents().as_slice());
+    let display = terminal.get_display();
+    let mut renderer = TerminalRenderer::default();
+    let mut grid = Vec::new();
+    let mut group = c.benchmark_group("renderer_throughput");
+    group.throughput(Throughput::Elements((GRID_SIZE.x*GRID_SIZE.y) as u64));
+    group.bench_function("cell_data_200x50", |b| {
+        b.iter(|| {
+            renderer.render_display(&display);
+            let cells = renderer.get_cells();
+            grid.resize(cells.len(), CellData::default());
+            for (dst, src) in grid.iter_mut().zip(cells.iter()) {
+                dst.set_atlas_index(Vector2::new(src.character as usize % 16, 0), 0);
+                set_cell_data_pen(dst, &src.pen);
+            }
+        });
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = create_criterion();
+    targets = bench_renderer
+}
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+use terminal::terminal_display::AccessibleRow;
+
+// Changes are collected for this long before the tree is rebuilt so busy output doesn't rebuild it every frame
+pub const ACCESSIBILITY_DEBOUNCE: Duration = Duration::from_millis(100);
+
+// Called with the rows of the active tab whenever they change
+pub type AccessibilityListener = Box<dyn FnMut(&[AccessibleRow])>;
+
+// Text of the visible rows of the active tab which is only generated when a listener is set since it adds overhead
+#[derive(Clone,Debug,Default)]
+pub struct AccessibilityTree {
+    rows: Vec<AccessibleRow>,
+    // rebuilt once this passes
+    deadline: Option<Instant>,
+}
+
+impl AccessibilityTree {
+    // Deadline isn't pushed back by later changes so the tree still updates during continuous output
+    pub fn mark_changed(&mut self, now: Instant) {
+        self.deadline.get_or_insert(now + ACCESSIBILITY_DEBOUNCE);
+    }
+
+    pub fn get_deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn is_update_due(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    // Returns true if the rows are different from the last update
+    pub fn update(&mut self, rows: Vec<AccessibleRow>) -> bool {
+        self.deadline = None;
+        if self.rows == rows {
+            return false;
+        }
+        self.rows = rows;
+        true
+    }
+
+    pub fn get_rows(&self) -> &[AccessibleRow] {
+        self.rows.as_slice()
+    }
+}
+
+// Listener used by --accessibility which dumps the rows to the debug log
+pub fn log_accessibility_tree(rows: &[AccessibleRow]) {
+    for (index, row) in rows.iter().enumerate() {
+        log::debug!("[accessibility] row={} focused={} link={} text={:?}", index, row.is_focused_row, row.has_link, row.text);
+    }
+}
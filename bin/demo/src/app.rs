@@ -2,22 +2,194 @@ use terminal::{
     Terminal, 
     TerminalBuilder,
     TerminalIOControl,
+    TerminalSettings,
 };
-use terminal::terminal_renderer::TerminalRenderer;
 use terminal_process::TerminalProcess;
-use vt100::common::WindowAction;
+use vt100::common::{Rgb8, WindowAction};
+use crate::accessibility::AccessibilityListener;
 use crate::app_events::AppEvent;
-use crate::app_window::AppWindow;
+use cgmath::Vector2;
+use crate::app_window::{AppWindow, DEFAULT_UNFOCUSED_DIM};
+use crate::bell::BellMode;
+use crate::font_loader::FontSource;
+use crate::headless::{HeadlessExit, HeadlessOptions, run_headless};
+use crate::keyboard_shortcuts::KeyboardShortcuts;
+use crate::process_environment::{DEFAULT_GRID_SIZE, ProcessEnvironment};
+use crate::window_icon::load_default_icon;
+use crate::window_geometry::{MonitorArea, WindowGeometry};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::EventLoop;
 use std::io::{Read, Write};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use terminal::terminal_display::DEFAULT_WORD_CHARACTERS;
+
+pub const DEFAULT_FONT_FILENAME: &str = "./res/Iosevka-custom-regular.ttf";
+pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+
+// Spawns the process of a newly opened tab or pane
+pub type ProcessFactory = Box<dyn Fn() -> anyhow::Result<Box<dyn TerminalProcess + Send>>>;
+// Creates the window action callback of the terminal in the pane with the given id
+// refreshes are skipped while the flag is set and the pane clears it once the refresh is received
+pub type WindowActionFactory = Box<dyn Fn(usize, Arc<AtomicBool>) -> Box<dyn FnMut(WindowAction) + Send>>;
+// Called with the pane id and exit code when the process of a pane exits
+pub type ProcessExitNotifier = Arc<dyn Fn(usize, Option<i32>) + Send + Sync>;
 
 pub struct AppBuilder {
-    pub font_filename: String,
+    pub font: FontSource,
     pub font_size: f32,
-    pub process: Arc<Mutex<Box<dyn TerminalProcess + Send>>>,
+    pub prefer_hdr: bool,
+    // AutoVsync waits for the display while Immediate and Mailbox render as fast as possible
+    // at the cost of tearing (Immediate) or extra gpu work on frames that are never shown (Mailbox)
+    pub present_mode: wgpu::PresentMode,
+    // caps the frame rate when rendering isn't limited by vsync so the gpu isn't kept busy
+    pub max_fps: Option<f64>,
+    pub minimum_contrast: f32,
+    pub cursor_colour: Option<Rgb8>,
+    // selected cells are inverted if unset
+    pub selection_colour: Option<Rgb8>,
+    pub builtin_box_drawing: bool,
+    pub unfocused_dim: f32,
+    pub max_atlas_memory_bytes: Option<usize>,
+    pub dpi_scale: Option<f32>,
+    pub padding: Vector2<usize>,
+    // window is created transparent when below 1.0 so the cell backgrounds can be drawn with this opacity
+    pub background_opacity: f32,
+    pub word_characters: String,
+    pub keyboard_shortcuts: KeyboardShortcuts,
+    // Only supported on macOS
+    pub native_menu: bool,
+    pub terminal_settings: TerminalSettings,
+    // parser metrics of each terminal are logged when it is closed
+    pub print_metrics: bool,
+    // tabs show the exit code and wait for a key press instead of closing when the process exits
+    pub hold_on_exit: bool,
+    pub bell: BellMode,
+    // scrollback shortcuts are sent to the process while it is using the alternate screen
+    pub alternate_screen_scroll_keys: bool,
+    // processes can iconify, raise, lower and resize the window with CSI t
+    pub allow_window_control: bool,
+    // window is resized to fit this many columns and rows when it is created which takes precedence over the saved window size
+    // otherwise the saved window size is restored or the window fits the default grid size
+    pub grid_size: Option<Vector2<usize>>,
+    // window size and position are restored from this file instead of fitting the grid size and saved on exit
+    pub window_state_path: Option<std::path::PathBuf>,
+    // raw output and resizes of the first tab are recorded to this file
+    pub record_path: Option<std::path::PathBuf>,
+    // text of the visible rows of the active tab is given to this which adds overhead so it is unset by default
+    pub accessibility_listener: Option<AccessibilityListener>,
+    pub process: Box<dyn TerminalProcess + Send>,
+    // new tabs cannot be opened if unset
+    pub process_factory: Option<ProcessFactory>,
+}
+
+impl AppBuilder {
+    // Recommended entry point for simple use cases
+    // Spawns the command inside the platform's pseudoterminal with everything else left as the defaults
+    pub fn from_command(mut command: std::process::Command) -> anyhow::Result<Self> {
+        ProcessEnvironment::default().apply(&mut command);
+        let process = spawn_platform_process(command)?;
+        Ok(Self::from_process(process, None))
+    }
+
+    // Runs $SHELL on unix and cmd.exe on windows with new tabs running another instance of it
+    pub fn from_shell() -> anyhow::Result<Self> {
+        let create_command = || {
+            let mut command = std::process::Command::new(get_default_shell());
+            ProcessEnvironment::default().apply(&mut command);
+            command
+        };
+        let process = spawn_platform_process(create_command())?;
+        let process_factory: ProcessFactory = Box::new(move || spawn_platform_process(create_command()));
+        Ok(Self::from_process(process, Some(process_factory)))
+    }
+
+    fn from_process(process: Box<dyn TerminalProcess + Send>, process_factory: Option<ProcessFactory>) -> Self {
+        Self {
+            font: FontSource::File(DEFAULT_FONT_FILENAME.to_owned()),
+            font_size: DEFAULT_FONT_SIZE,
+            prefer_hdr: false,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            max_fps: None,
+            minimum_contrast: 1.0,
+            cursor_colour: None,
+            selection_colour: None,
+            builtin_box_drawing: true,
+            unfocused_dim: DEFAULT_UNFOCUSED_DIM,
+            max_atlas_memory_bytes: None,
+            dpi_scale: None,
+            padding: Vector2::new(0,0),
+            background_opacity: 1.0,
+            word_characters: DEFAULT_WORD_CHARACTERS.to_owned(),
+            keyboard_shortcuts: KeyboardShortcuts::default(),
+            native_menu: cfg!(target_os = "macos"),
+            terminal_settings: TerminalSettings::default(),
+            print_metrics: false,
+            hold_on_exit: false,
+            bell: BellMode::default(),
+            alternate_screen_scroll_keys: true,
+            allow_window_control: false,
+            grid_size: None,
+            window_state_path: None,
+            record_path: None,
+            accessibility_listener: None,
+            process,
+            process_factory,
+        }
+    }
+}
+
+fn get_default_shell() -> String {
+    #[cfg(windows)] {
+        "cmd.exe".to_owned()
+    }
+    #[cfg(not(windows))] {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_owned())
+    }
+}
+
+#[cfg(unix)]
+fn spawn_platform_process(command: std::process::Command) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let builder = unix_pty::process::ProcessBuilder { size: DEFAULT_GRID_SIZE.cast::<u16>().unwrap() };
+    let process = unix_pty::process::PtyProcess::spawn(command, Some(builder))?;
+    Ok(Box::new(terminal_process::UnixPtyProcess::new(process)))
+}
+
+#[cfg(windows)]
+fn spawn_platform_process(command: std::process::Command) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let size = DEFAULT_GRID_SIZE.cast::<i16>().unwrap();
+    let window_size = conpty::process::Size::new(size.x, size.y);
+    let builder = conpty::process::ConptyProcessBuilder { window_size, ..Default::default() };
+    let process = conpty::process::ConptyProcess::spawn(command, Some(builder))?;
+    Ok(Box::new(terminal_process::ConptyProcess::new(process)))
+}
+
+// Without a pseudoterminal the process is given pipes and can't be resized
+#[cfg(not(any(unix, windows)))]
+fn spawn_platform_process(mut command: std::process::Command) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let size = DEFAULT_GRID_SIZE;
+    command.env("COLUMNS", size.x.to_string());
+    command.env("LINES", size.y.to_string());
+    command.stdin(std::process::Stdio::piped());
+    command.stdout(std::process::Stdio::piped());
+    command.stderr(std::process::Stdio::null());
+    let process = command.spawn()?;
+    Ok(Box::new(terminal_process::RawProcess::new(process)))
+}
+
+pub(crate) fn log_parser_metrics(terminal: &Terminal) {
+    log::info!("Parser metrics {}", terminal.get_parser_metrics());
+}
+
+fn create_record_output(path: &std::path::Path) -> anyhow::Result<Box<dyn Write + Send>> {
+    let file = std::fs::File::create(path)
+        .map_err(|err| anyhow::format_err!("Failed to create recording '{}': {}", path.display(), err))?;
+    Ok(Box::new(std::io::BufWriter::new(file)))
 }
 
-fn create_default_terminal_builder(process: Arc<Mutex<Box<dyn TerminalProcess + Send>>>) -> anyhow::Result<TerminalBuilder> {
+pub(crate) fn create_default_terminal_builder(
+    process: Arc<Mutex<Box<dyn TerminalProcess + Send>>>, settings: TerminalSettings,
+) -> anyhow::Result<TerminalBuilder> {
     let process_read = {
         let mut read_pipe = process.lock().unwrap().get_read_pipe()?;
         move |data: &mut [u8]| {
@@ -47,86 +219,143 @@ fn create_default_terminal_builder(process: Arc<Mutex<Box<dyn TerminalProcess +
     };
     let window_action = |_action: WindowAction| {};
     let is_newline_carriage_return = process.lock().unwrap().is_newline_carriage_return();
+    let process_resize = process.lock().unwrap().get_resize_receiver();
     Ok(TerminalBuilder {
         process_read: Box::new(process_read),
         process_write: Box::new(process_write),
         process_ioctl: Box::new(process_ioctl),
         window_action: Box::new(window_action),
         is_newline_carriage_return,
+        settings,
+        record_output: None,
+        process_resize,
+        on_parser_exit: None,
     })
 }
 
+// Missing state file is expected the first time the app is run
+fn load_window_geometry(path: &std::path::Path, event_loop: &EventLoop<AppEvent>) -> Option<WindowGeometry> {
+    if !path.exists() {
+        return None;
+    }
+    let geometry = match WindowGeometry::load(path) {
+        Ok(geometry) => geometry,
+        Err(err) => {
+            log::warn!("Failed to load window geometry from '{}': {}", path.display(), err);
+            return None;
+        },
+    };
+    let monitors: Vec<MonitorArea> = event_loop
+        .available_monitors()
+        .map(|monitor| MonitorArea {
+            position: Vector2::new(monitor.position().x, monitor.position().y),
+            size: Vector2::new(monitor.size().width, monitor.size().height),
+        })
+        .collect();
+    Some(geometry.clamp_to_monitors(monitors.as_slice()))
+}
+
 pub fn start_app(builder: AppBuilder) -> anyhow::Result<()> {
-    let process = builder.process;
-    let mut terminal_builder = create_default_terminal_builder(process.clone())?;
     let event_loop = winit::event_loop::EventLoopBuilder::<AppEvent>::with_user_event().build()?;
-    use std::sync::atomic::{AtomicBool, Ordering};
-    let is_refresh_trigger = Arc::new(AtomicBool::new(false));
-    let window_action = {
+    #[cfg(target_os = "macos")]
+    let _native_menu = if builder.native_menu {
+        Some(crate::native_menu::NativeMenu::new(event_loop.create_proxy())?)
+    } else {
+        None
+    };
+    #[cfg(not(target_os = "macos"))]
+    if builder.native_menu {
+        log::warn!("Native menu is only supported on macOS");
+    }
+    // each pane only has one refresh queued at a time
+    // a shared flag would let a refresh of a background tab swallow refreshes of the active tab
+    let window_action_factory: WindowActionFactory = {
         let event_loop_proxy = event_loop.create_proxy();
-        let is_refresh_trigger = is_refresh_trigger.clone();
-        move |action: WindowAction| {
-            if action == WindowAction::Refresh && is_refresh_trigger.fetch_or(true, Ordering::SeqCst) {
-                return;
-            }
-            let _ = event_loop_proxy.send_event(AppEvent::WindowAction(action));
-        }
+        Box::new(move |pane_id: usize, is_refresh_queued: Arc<AtomicBool>| {
+            let event_loop_proxy = event_loop_proxy.clone();
+            Box::new(move |action: WindowAction| {
+                if action == WindowAction::Refresh && is_refresh_queued.fetch_or(true, Ordering::SeqCst) {
+                    return;
+                }
+                let _ = event_loop_proxy.send_event(AppEvent::WindowAction(pane_id, action));
+            })
+        })
     };
-    terminal_builder.window_action = Box::new(window_action);
-    let terminal = Terminal::new(terminal_builder);
-    let window = winit::window::WindowBuilder::new().build(&event_loop)?;
+    let process_exit_notifier: ProcessExitNotifier = {
+        let event_loop_proxy = Mutex::new(event_loop.create_proxy());
+        Arc::new(move |pane_id: usize, exit_code: Option<i32>| {
+            let _ = event_loop_proxy.lock().unwrap().send_event(AppEvent::ProcessExited(pane_id, exit_code));
+        })
+    };
+    let font = builder.font.load()?;
+    let window_geometry = builder.window_state_path.as_deref().and_then(|path| load_window_geometry(path, &event_loop));
+    let mut window_builder = winit::window::WindowBuilder::new()
+        .with_window_icon(load_default_icon())
+        .with_transparent(builder.background_opacity < 1.0);
+    if let Some(geometry) = window_geometry.as_ref() {
+        if let Some([x, y]) = geometry.position {
+            window_builder = window_builder.with_position(PhysicalPosition::new(x, y));
+        }
+        if builder.grid_size.is_none() {
+            let [width, height] = geometry.size;
+            window_builder = window_builder
+                .with_inner_size(PhysicalSize::new(width, height))
+                .with_maximized(geometry.is_maximised);
+        }
+    }
+    let window = window_builder.build(&event_loop)?;
     let mut window_size = window.inner_size();
     window_size.width = window_size.width.max(1);
     window_size.height = window_size.height.max(1);
     let mut terminal_window = pollster::block_on(AppWindow::new(
         &window,
-        terminal,
-        builder.font_filename, builder.font_size,
+        window_action_factory,
+        font, builder.font_size,
+        builder.prefer_hdr,
+        builder.present_mode,
+        builder.background_opacity,
+        builder.max_atlas_memory_bytes,
     ))?;
-    event_loop.run({
-        let is_refresh_trigger = is_refresh_trigger.clone();
-        use winit::event::{Event, WindowEvent};
-        move |event, target| {
-            if let Event::WindowEvent { ref event, .. } = event { 
-                if event == &WindowEvent::RedrawRequested {
-                    is_refresh_trigger.store(false, Ordering::SeqCst);
-                }
-            }
-            terminal_window.on_winit_event(event, target);
-        }
-    })?;
-    match process.lock().unwrap().terminate() {
-        Ok(()) => log::info!("Process terminated successfully"), 
-        Err(err) => log::error!("Process failed to be terminated: {:?}", err),
+    terminal_window.set_minimum_contrast(builder.minimum_contrast);
+    terminal_window.set_cursor_colour(builder.cursor_colour);
+    terminal_window.set_selection_colour(builder.selection_colour);
+    terminal_window.set_builtin_box_drawing(builder.builtin_box_drawing);
+    terminal_window.set_unfocused_dim(builder.unfocused_dim);
+    terminal_window.set_max_fps(builder.max_fps);
+    if builder.dpi_scale.is_some() {
+        terminal_window.set_dpi_scale_override(builder.dpi_scale);
+    }
+    terminal_window.set_padding(builder.padding);
+    match builder.grid_size {
+        Some(grid_size) => terminal_window.request_grid_size(grid_size),
+        None if window_geometry.is_none() => terminal_window.request_grid_size(DEFAULT_GRID_SIZE),
+        None => {},
     }
+    terminal_window.set_window_state_path(builder.window_state_path);
+    terminal_window.set_word_characters(builder.word_characters.as_str());
+    terminal_window.set_keyboard_shortcuts(builder.keyboard_shortcuts);
+    terminal_window.set_terminal_settings(builder.terminal_settings);
+    terminal_window.set_print_metrics(builder.print_metrics);
+    terminal_window.set_hold_on_exit(builder.hold_on_exit);
+    terminal_window.set_bell_mode(builder.bell);
+    terminal_window.set_alternate_screen_scroll_keys(builder.alternate_screen_scroll_keys);
+    terminal_window.set_allow_window_control(builder.allow_window_control);
+    terminal_window.set_process_exit_notifier(Some(process_exit_notifier));
+    terminal_window.set_record_output(builder.record_path.as_deref().map(create_record_output).transpose()?);
+    terminal_window.set_accessibility_listener(builder.accessibility_listener);
+    terminal_window.set_process_factory(builder.process_factory);
+    terminal_window.new_tab(builder.process)?;
+    event_loop.run(move |event, target| {
+        terminal_window.on_winit_event(event, target);
+    })?;
     Ok(())
 }
 
-pub fn start_headless(builder: AppBuilder) -> anyhow::Result<()> {
-    let process = builder.process;
-    let terminal_builder = create_default_terminal_builder(process.clone())?;
-    let mut terminal = Terminal::new(terminal_builder);
-    terminal.join_parser_thread();
-    match process.lock().unwrap().terminate() {
-        Ok(()) => log::info!("Process terminated successfully"),
-        Err(err) => log::error!("Process failed to be terminated: {:?}", err),
-    }
-
-    let mut terminal_renderer = TerminalRenderer::default();
-    let display = terminal.get_display();
-    terminal_renderer.render_display(&display);
-    let size = terminal_renderer.get_size();
-    let cells = terminal_renderer.get_cells();
-    let mut tmp_buf = [0u8; 4];
+pub fn start_headless(builder: AppBuilder, options: HeadlessOptions) -> anyhow::Result<HeadlessExit> {
+    let record_output = builder.record_path.as_deref().map(create_record_output).transpose()?;
+    let (exit, output) = run_headless(builder.process, builder.terminal_settings, builder.print_metrics, record_output, &options)?;
     let mut stdout = std::io::stdout();
-    for y in 0..size.y {
-        let index = y*size.x;
-        let row = &cells[index..(index+size.x)];
-        for cell in row {
-            let data = cell.character.encode_utf8(&mut tmp_buf);
-            let _ = stdout.write(data.as_bytes());
-        }
-        let _ = stdout.write(b"\n");
-    }
-    Ok(())
+    stdout.write_all(output.as_bytes())?;
+    stdout.flush()?;
+    Ok(exit)
 }
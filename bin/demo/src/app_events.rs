@@ -1,6 +1,12 @@
 use vt100::common::WindowAction;
+use crate::keyboard_shortcuts::ShortcutAction;
 
 #[derive(Clone,Debug)]
 pub enum AppEvent {
-    WindowAction(WindowAction),
+    // Sent by the terminal of the pane with the given id
+    WindowAction(usize, WindowAction),
+    // Sent from the read thread of the pane with the given id once its process exits
+    ProcessExited(usize, Option<i32>),
+    // Sent by the native menu
+    ShortcutAction(ShortcutAction),
 }
@@ -1,33 +1,108 @@
-use std::io::Read;
-use cgmath::{Vector2,Vector4,ElementWise};
+use cgmath::{Vector2,Vector3,Vector4,ElementWise};
 use tile_renderer::{
     Renderer,
     GlyphCache,
+    GlyphIndex,
     FontdueGlyphGenerator,
     CellData,
+    UnderlineStyle,
+    CursorStyle,
+    CursorData,
+    ImageQuad,
+    FrameTimings,
+    RenderStats,
+    calculate_scrollbar_thumb,
 };
 use terminal::{
     Terminal,
     TerminalUserEvent,
-    terminal_renderer::TerminalRenderer,
+    TerminalSettings,
+    Cell,
+    Pen,
+    StyleFlags,
+    terminal_display::DEFAULT_WORD_CHARACTERS,
+    terminal_renderer::{RenderCursor, TerminalRenderer},
+    url_detector::{HyperlinkSpan, UrlCache, UrlSpan, get_hyperlink_span},
 };
 use winit::{
-    event::{Event, WindowEvent, ElementState, MouseButton},
-    keyboard::{KeyCode,PhysicalKey,Key},
-    event_loop::EventLoopWindowTarget,
-    window::Window,
+    event::{Event, WindowEvent, ElementState, MouseButton, Ime},
+    keyboard::{KeyCode,PhysicalKey,Key,ModifiersState},
+    event_loop::{ControlFlow, EventLoopWindowTarget},
+    window::{CursorIcon, Fullscreen, Window, WindowLevel},
+    dpi::{PhysicalPosition, PhysicalSize},
 };
+use crate::accessibility::{AccessibilityListener, AccessibilityTree};
 use crate::app_events::AppEvent;
-use crate::frame_counter::FrameCounter;
-use vt100::common::WindowAction;
-use crossbeam_channel::Sender;
+use crate::bell::{BellMode, BellRateLimiter, VISUAL_BELL_DURATION, play_system_beep};
+use crate::debug_overlay::{DebugStats, get_debug_lines, get_overlay_cells};
+use crate::frame_counter::{FrameCounter, ThroughputCounter};
+use crate::font_loader::FontData;
+use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutAction, ShortcutTarget, get_shortcut_target};
+use crate::app::{ProcessFactory, ProcessExitNotifier, WindowActionFactory, create_default_terminal_builder, log_parser_metrics};
+use crate::pane_layout::{PaneRect, SplitDirection, copy_pane_cells, get_divider_character};
+use crate::tab_bar::{PaneState, TabBar, TabState};
+use crate::window_geometry::WindowGeometry;
+use crate::window_report::{WindowMetrics, encode_window_report, encode_icon_label_report, get_requested_grid_size};
+use terminal_process::TerminalProcess;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use vt100::common::{
+    Rgb8,
+    BellVolume,
+    WindowAction,
+    PromptMark,
+    CursorStyle as TerminalCursorStyle,
+    UnderlineStyle as TerminalUnderlineStyle,
+};
 
 pub struct AppWindow<'a> {
-    terminal: Terminal,
-    terminal_user_events: Sender<TerminalUserEvent>, 
-    terminal_renderer: TerminalRenderer,
+    tabs: Vec<TabState>,
+    active_tab: usize,
+    // panes of every tab are given ids from this so window actions can be routed to them
+    next_pane_id: usize,
+    tab_bar: TabBar,
+    tab_bar_cells: Vec<CellData>,
+    window_action_factory: WindowActionFactory,
+    // new tabs can't be opened without this
+    process_factory: Option<ProcessFactory>,
+    terminal_settings: TerminalSettings,
+    print_metrics: bool,
+    hold_on_exit: bool,
+    process_exit_notifier: Option<ProcessExitNotifier>,
+    // taken by the next pane that is opened
+    record_output: Option<Box<dyn std::io::Write + Send>>,
+    // tree is only generated while there is a listener
+    accessibility_tree: Option<AccessibilityTree>,
+    accessibility_listener: Option<AccessibilityListener>,
+    bell_mode: BellMode,
+    bell_rate_limiter: BellRateLimiter,
+    // grid colours are inverted while the visual bell is showing
+    bell_flash_instant: Option<std::time::Instant>,
+    // pixels between the window edge and the grid on each side
+    padding: Vector2<usize>,
+    // applied to the renderer of each tab
+    minimum_contrast: f32,
+    cursor_colour: Option<Rgb8>,
     glyph_grid: Vec<CellData>,
+    // size of the grid that all panes of a tab are laid out in
+    grid_size: Vector2<usize>,
+    // panes of a split tab are composed into this before it is uploaded
+    split_grid: Vec<CellData>,
+    pane_glyph_grid: Vec<CellData>,
     glyph_cache: GlyphCache,
+    default_font_size: f32,
+    // font size before dpi scaling
+    font_size: f32,
+    dpi_scale: f32,
+    dpi_scale_override: Option<f32>,
+    // dpi scale that the glyph atlas was rasterised at
+    glyph_dpi_scale: f32,
+    modifiers: ModifiersState,
+    keyboard_shortcuts: KeyboardShortcuts,
+    alternate_screen_scroll_keys: bool,
+    allow_window_control: bool,
+    // window geometry is saved here on exit
+    window_state_path: Option<std::path::PathBuf>,
     winit_window: &'a Window,
     wgpu_config: wgpu::SurfaceConfiguration,
     wgpu_surface: wgpu::Surface<'a>,
@@ -35,8 +110,231 @@ pub struct AppWindow<'a> {
     wgpu_queue: wgpu::Queue,
     renderer: Renderer,
     is_redraw_requested: bool,
+    // redraws are held back until the frame deadline when the frame rate is capped
+    is_redraw_deferred: bool,
+    max_fps: Option<f64>,
+    last_frame_instant: std::time::Instant,
+    last_scroll_instant: Option<std::time::Instant>,
+    // scrollback line of the prompt that was jumped to is inverted briefly
+    prompt_highlight: Option<(usize, std::time::Instant)>,
     current_frame: usize,
     frame_counter: FrameCounter,
+    // bytes read from the process of the active tab
+    throughput_counter: ThroughputCounter,
+    is_debug_overlay: bool,
+    render_stats: RenderStats,
+    last_render_stats_instant: std::time::Instant,
+    last_force_redraw_instant: std::time::Instant,
+    is_focused: bool,
+    unfocused_dim: f32,
+    clipboard: Option<arboard::Clipboard>,
+    // pixel position of the mouse inside the window
+    mouse_position: Vector2<usize>,
+    selection: Option<Selection>,
+    is_selecting: bool,
+    // bounds of the word or line that was initially clicked
+    selection_anchor: (Vector2<usize>, Vector2<usize>),
+    selection_mode: SelectionMode,
+    selection_colour: Option<Rgb8>,
+    click_counter: ClickCounter,
+    selection_autoscroll: SelectionAutoscroll,
+    // pixels that the mouse is above (negative) or below (positive) the grid
+    mouse_overshoot: isize,
+    word_characters: String,
+    url_cache: UrlCache,
+    // url under the mouse while ctrl is held
+    hovered_url: Option<UrlSpan>,
+    // OSC 8 hyperlink under the mouse which is underlined without holding ctrl
+    hovered_hyperlink: Option<HyperlinkSpan>,
+    // only looked up from the display when the hovered hyperlink changes
+    hovered_hyperlink_url: Option<String>,
+    // in progress input method composition which is only drawn over the grid
+    ime_preedit: Option<String>,
+    // pixel position and size that the input method candidate window was last placed at
+    ime_cursor_area: Option<(Vector2<usize>, Vector2<usize>)>,
+    // winit sends an event per file so files dropped together are pasted once all events are received
+    dropped_paths: Vec<std::path::PathBuf>,
+}
+
+// Cells in line coordinates from the renderer where both ends are inclusive
+// These stay on the same text as the view is scrolled so a selection can extend into the scrollback
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Selection {
+    pub start: Vector2<usize>,
+    pub end: Vector2<usize>,
+}
+
+impl Selection {
+    // Returns the start and exclusive end in reading order
+    pub fn get_bounds(&self) -> (Vector2<usize>, Vector2<usize>) {
+        let (start, end) = if (self.start.y, self.start.x) > (self.end.y, self.end.x) {
+            (self.end, self.start)
+        } else {
+            (self.start, self.end)
+        };
+        (start, Vector2::new(end.x+1, end.y))
+    }
+}
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum SelectionMode {
+    #[default]
+    Cell,
+    Word,
+    Line,
+}
+
+const MULTI_CLICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(400);
+
+// Repeated clicks on the same cell cycle between cell, word and line selection
+#[derive(Clone,Copy,Debug,Default)]
+pub struct ClickCounter {
+    last_click: Option<(std::time::Instant, Vector2<usize>)>,
+    total_clicks: usize,
+}
+
+impl ClickCounter {
+    pub fn on_click(&mut self, instant: std::time::Instant, cell: Vector2<usize>) -> SelectionMode {
+        let is_repeat = self.last_click.is_some_and(|(last_instant, last_cell)| {
+            instant.saturating_duration_since(last_instant) <= MULTI_CLICK_INTERVAL &&
+            last_cell.x.abs_diff(cell.x) <= 1 &&
+            last_cell.y.abs_diff(cell.y) <= 1
+        });
+        self.total_clicks = if is_repeat { self.total_clicks % 3 + 1 } else { 1 };
+        self.last_click = Some((instant, cell));
+        match self.total_clicks {
+            2 => SelectionMode::Word,
+            3 => SelectionMode::Line,
+            _ => SelectionMode::Cell,
+        }
+    }
+}
+
+const AUTOSCROLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+const AUTOSCROLL_MAX_ROWS: usize = 8;
+
+// Rows to scroll each step for a mouse that is the given pixels above (negative) or below (positive) the grid
+// Starts at a row per step and speeds up by a row for every row height past the edge
+pub fn get_autoscroll_rows(overshoot: isize, glyph_height: usize) -> isize {
+    if overshoot == 0 {
+        return 0;
+    }
+    let rows = 1 + overshoot.unsigned_abs() / glyph_height.max(1);
+    rows.min(AUTOSCROLL_MAX_ROWS) as isize * overshoot.signum()
+}
+
+// Scrolls in fixed steps while a selection is dragged past the top or bottom of the grid
+#[derive(Clone,Copy,Debug,Default)]
+pub struct SelectionAutoscroll {
+    last_step: Option<std::time::Instant>,
+}
+
+impl SelectionAutoscroll {
+    // Returns the rows to scroll where negative scrolls up into the scrollback
+    pub fn update(&mut self, instant: std::time::Instant, overshoot: isize, glyph_height: usize) -> isize {
+        if overshoot == 0 {
+            self.stop();
+            return 0;
+        }
+        if self.last_step.is_some_and(|last_step| instant.saturating_duration_since(last_step) < AUTOSCROLL_INTERVAL) {
+            return 0;
+        }
+        self.last_step = Some(instant);
+        get_autoscroll_rows(overshoot, glyph_height)
+    }
+
+    pub fn stop(&mut self) {
+        self.last_step = None;
+    }
+
+    // Instant of the next step if the mouse is still past the edge
+    pub fn get_next_step(&self) -> Option<std::time::Instant> {
+        self.last_step.map(|last_step| last_step + AUTOSCROLL_INTERVAL)
+    }
+}
+
+// Columns of a rendered row that starts at the line coordinates inside a selection with an exclusive end
+pub fn get_selected_columns(
+    row_position: Vector2<usize>, width: usize, start: Vector2<usize>, end: Vector2<usize>,
+) -> std::ops::Range<usize> {
+    let get_column = |p: Vector2<usize>| {
+        match p.y.cmp(&row_position.y) {
+            std::cmp::Ordering::Less => 0,
+            std::cmp::Ordering::Greater => width,
+            std::cmp::Ordering::Equal => p.x.saturating_sub(row_position.x).min(width),
+        }
+    };
+    let start_column = get_column(start);
+    start_column..get_column(end).max(start_column)
+}
+
+const RENDER_STATS_LOG_INTERVAL_SECONDS: f32 = 5.0;
+pub const DEFAULT_UNFOCUSED_DIM: f32 = 0.85;
+// Redraw periodically in case a refresh from the parser thread was missed
+const FORCE_REDRAW_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+// Frame rate and throughput are measured over a second so the debug overlay is redrawn at least this often
+const DEBUG_OVERLAY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+const SCROLLBAR_WIDTH_PIXELS: f32 = 6.0;
+const SCROLLBAR_MIN_LENGTH: f32 = 0.05;
+const SCROLLBAR_FADE_DELAY_SECONDS: f32 = 1.0;
+const PROMPT_HIGHLIGHT_DURATION: std::time::Duration = std::time::Duration::from_millis(400);
+const SCROLLBAR_FADE_DURATION_SECONDS: f32 = 0.25;
+const SCROLLBAR_COLOUR: Vector4<f32> = Vector4::new(0.8, 0.8, 0.8, 0.6);
+
+// Avoid srgb formats since colours are written directly without conversion
+const PREFERRED_SURFACE_FORMATS: [wgpu::TextureFormat; 3] = [
+    wgpu::TextureFormat::Rgb10a2Unorm,
+    wgpu::TextureFormat::Bgra8Unorm,
+    wgpu::TextureFormat::Rgba8Unorm,
+];
+
+pub fn select_surface_format(supported: &[wgpu::TextureFormat], prefer_hdr: bool) -> Option<wgpu::TextureFormat> {
+    if prefer_hdr && supported.contains(&wgpu::TextureFormat::Rgba16Float) {
+        return Some(wgpu::TextureFormat::Rgba16Float);
+    }
+    PREFERRED_SURFACE_FORMATS
+        .iter()
+        .find(|format| supported.contains(format))
+        .or_else(|| supported.first())
+        .copied()
+}
+
+// AutoVsync and AutoNoVsync are supported everywhere so they are used when the requested mode isn't
+pub fn select_present_mode(supported: &[wgpu::PresentMode], requested: wgpu::PresentMode) -> wgpu::PresentMode {
+    match requested {
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => requested,
+        wgpu::PresentMode::Immediate | wgpu::PresentMode::Mailbox if !supported.contains(&requested) => {
+            wgpu::PresentMode::AutoNoVsync
+        },
+        _ if !supported.contains(&requested) => wgpu::PresentMode::AutoVsync,
+        _ => requested,
+    }
+}
+
+// Transparent windows need the compositor to blend with alpha which not every surface supports
+pub fn select_alpha_mode(supported: &[wgpu::CompositeAlphaMode], is_transparent: bool) -> Option<wgpu::CompositeAlphaMode> {
+    if !is_transparent {
+        return None;
+    }
+    [wgpu::CompositeAlphaMode::PreMultiplied, wgpu::CompositeAlphaMode::PostMultiplied]
+        .into_iter()
+        .find(|mode| supported.contains(mode))
+}
+
+// Redraw is delayed until this long after the last frame was rendered
+pub fn get_frame_deadline(last_frame: std::time::Instant, max_fps: Option<f64>) -> Option<std::time::Instant> {
+    let max_fps = max_fps.filter(|fps| fps.is_finite() && *fps > 0.0)?;
+    Some(last_frame + std::time::Duration::from_secs_f64(1.0 / max_fps))
+}
+
+// Converts a pixel position in the window to a cell in the rendered grid
+// Rendered grid is shifted up by the pixel offset when partially scrolled
+pub fn convert_pixel_to_cell(
+    position: Vector2<usize>, glyph_size: Vector2<usize>, pixel_offset: usize, grid_size: Vector2<usize>,
+) -> Vector2<usize> {
+    let x = position.x / glyph_size.x.max(1);
+    let y = (position.y + pixel_offset) / glyph_size.y.max(1);
+    Vector2::new(x.min(grid_size.x.saturating_sub(1)), y.min(grid_size.y.saturating_sub(1)))
 }
 
 fn get_default_wgpu_backends() -> wgpu::Backends {
@@ -48,14 +346,184 @@ fn get_default_wgpu_backends() -> wgpu::Backends {
     backends
 }
 
+// Area left for the grid after padding each side and reserving rows at the top such as the tab bar
+pub fn calculate_grid_area(window_size: Vector2<usize>, padding: Vector2<usize>, top_offset: usize) -> Vector2<usize> {
+    let width = window_size.x.saturating_sub(2*padding.x);
+    let height = window_size.y.saturating_sub(2*padding.y + top_offset);
+    Vector2::new(width.max(1), height.max(1))
+}
+
+// Window size needed to fit the grid which is the inverse of calculate_grid_area and calculate_grid_size
+pub fn calculate_window_size(grid_size: Vector2<usize>, glyph_size: Vector2<usize>, padding: Vector2<usize>, top_offset: usize) -> Vector2<usize> {
+    let render_size = grid_size.mul_element_wise(glyph_size);
+    Vector2::new(render_size.x + 2*padding.x, render_size.y + 2*padding.y + top_offset)
+}
+
+// Keeps the same tab active unless it was the one closed, in which case the tab after it is used
+pub fn get_active_tab_after_close(active_tab: usize, closed_tab: usize, total_tabs: usize) -> usize {
+    let active_tab = if closed_tab < active_tab { active_tab-1 } else { active_tab };
+    active_tab.min(total_tabs.saturating_sub(1))
+}
+
+// Switching past either end wraps around
+pub fn get_adjacent_tab(active_tab: usize, total_tabs: usize, is_next: bool) -> usize {
+    if total_tabs == 0 {
+        return 0;
+    }
+    if is_next {
+        (active_tab+1) % total_tabs
+    } else {
+        (active_tab+total_tabs-1) % total_tabs
+    }
+}
+
+// Only unix desktops besides macOS have a primary selection
+pub const IS_PRIMARY_SELECTION_SUPPORTED: bool = cfg!(all(unix, not(target_os = "macos")));
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum MiddleClickAction {
+    PastePrimarySelection,
+    SendToProcess,
+    Ignore,
+}
+
+// Iconifying, resizing or restacking the window could be abused by any program writing to the terminal
+pub fn is_window_control_action(action: &WindowAction) -> bool {
+    matches!(
+        action,
+        WindowAction::SetMinimised(_) | WindowAction::SendToFront | WindowAction::SendToBack | WindowAction::ResizeTextArea(_),
+    )
+}
+
+pub fn is_window_action_allowed(action: &WindowAction, allow_window_control: bool) -> bool {
+    allow_window_control || !is_window_control_action(action)
+}
+
+// Middle click pastes the primary selection unless the process is tracking the mouse
+pub fn get_middle_click_action(is_local_selection: bool, is_primary_selection_supported: bool) -> MiddleClickAction {
+    match (is_local_selection, is_primary_selection_supported) {
+        (false, _) => MiddleClickAction::SendToProcess,
+        (true, true) => MiddleClickAction::PastePrimarySelection,
+        (true, false) => MiddleClickAction::Ignore,
+    }
+}
+
+// Written to the display of a tab when its process exits and the window is held open
+pub fn get_process_exit_message(exit_code: Option<i32>) -> String {
+    let status = match exit_code {
+        Some(code) => format!("process exited with code {}", code),
+        None => "process exited".to_owned(),
+    };
+    // pen left by the process is reset so the message is readable
+    format!("\x1b[0m\r\n\x1b[7m[{}, press any key to close]\x1b[0m", status)
+}
+
+// Single quoted so the shell doesn't expand anything where embedded quotes are closed, escaped and reopened
+pub fn quote_unix_path(path: &str) -> String {
+    format!("'{}'", path.replace('\'', "'\\''"))
+}
+
+// Windows paths can't contain double quotes so they never need escaping
+pub fn quote_windows_path(path: &str) -> String {
+    format!("\"{}\"", path)
+}
+
+// Paths of files dropped onto the window as they would be typed into the shell
+pub fn get_dropped_paths_text(paths: &[std::path::PathBuf]) -> String {
+    let quote = if cfg!(windows) { quote_windows_path } else { quote_unix_path };
+    paths.iter()
+        .map(|path| quote(path.to_string_lossy().as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Position relative to the top left of the grid, clamped to the grid origin when over the padding
+pub fn convert_window_to_grid_position(position: Vector2<usize>, grid_origin: Vector2<usize>) -> Vector2<usize> {
+    Vector2::new(position.x.saturating_sub(grid_origin.x), position.y.saturating_sub(grid_origin.y))
+}
+
+// Returns the grid size and the pixel size that the grid occupies
+pub fn calculate_grid_size(window_size: Vector2<usize>, glyph_size: Vector2<usize>) -> (Vector2<usize>, Vector2<usize>) {
+    let grid_size = window_size.div_element_wise(glyph_size);
+    let grid_size = Vector2::new(grid_size.x.max(1), grid_size.y.max(1));
+    let render_size = grid_size.mul_element_wise(glyph_size);
+    (grid_size, render_size)
+}
+
+// Everything except the glyph which depends on the glyph cache
+pub fn set_cell_data_pen(dst: &mut CellData, pen: &Pen) {
+    let foreground_colour = pen.foreground_colour;
+    let background_colour = pen.background_colour;
+    dst.set_foreground_colour(Vector3::new(foreground_colour.r, foreground_colour.g, foreground_colour.b));
+    dst.set_background_colour(Vector3::new(background_colour.r, background_colour.g, background_colour.b));
+    let underline_style = match pen.underline_style {
+        TerminalUnderlineStyle::None => UnderlineStyle::None,
+        TerminalUnderlineStyle::Straight => UnderlineStyle::Straight,
+        TerminalUnderlineStyle::Double => UnderlineStyle::Double,
+        TerminalUnderlineStyle::Curly => UnderlineStyle::Curly,
+        TerminalUnderlineStyle::Dotted => UnderlineStyle::Dotted,
+        TerminalUnderlineStyle::Dashed => UnderlineStyle::Dashed,
+    };
+    let underline_colour = pen.underline_colour.map(|c| Vector3::new(c.r, c.g, c.b));
+    dst.set_underline(underline_style, underline_colour);
+    dst.set_inverse(pen.style_flags.contains(StyleFlags::Inverse));
+    // inline images are drawn over the cells they cover
+    dst.set_hidden(pen.style_flags.intersects(StyleFlags::Hidden | StyleFlags::Image));
+    dst.set_cursor(pen.style_flags.contains(StyleFlags::Cursor));
+}
+
+// Grid is resized to fit the cells which are given glyphs from the cache
+pub fn update_glyph_grid(
+    grid: &mut Vec<CellData>, cells: &[Cell], size: Vector2<usize>,
+    glyph_cache: &mut GlyphCache, current_frame: usize,
+) {
+    let total_glyphs_in_block = glyph_cache.get_glyph_atlas().get_total_glyphs_in_block();
+    grid.resize(size.x*size.y, CellData::default());
+    // wide glyph whose right half is drawn into the following spacer cell
+    let mut wide_glyph: Option<GlyphIndex> = None;
+    for (i, (dst, src)) in grid.iter_mut().zip(cells.iter()).enumerate() {
+        let is_row_start = i % size.x == 0;
+        let (atlas_position, atlas_layer) = match wide_glyph.take() {
+            // terminal marks the cell after a wide character as its spacer
+            Some(glyph) if !is_row_start && src.pen.style_flags.contains(StyleFlags::WideSpacer) => {
+                (glyph.get_atlas_position(total_glyphs_in_block, 1), glyph.layer)
+            },
+            _ => {
+                let glyph = glyph_cache.get_glyph_location(src.character, current_frame);
+                if glyph.span > 1 {
+                    wide_glyph = Some(glyph);
+                }
+                (glyph.get_atlas_position(total_glyphs_in_block, 0), glyph.layer)
+            },
+        };
+        dst.set_atlas_index(atlas_position, atlas_layer);
+        set_cell_data_pen(dst, &src.pen);
+    }
+}
+
+pub fn get_cursor_data(cursor: &RenderCursor) -> CursorData {
+    CursorData {
+        style: match cursor.style {
+            TerminalCursorStyle::Block => CursorStyle::Block,
+            TerminalCursorStyle::Underline => CursorStyle::Underline,
+            TerminalCursorStyle::Bar => CursorStyle::Bar,
+        },
+        colour: Vector3::new(cursor.colour.r, cursor.colour.g, cursor.colour.b),
+    }
+}
+
 impl<'a> AppWindow<'a> {
+    // Tabs are added with new_tab() before the event loop is started
     pub async fn new(
         winit_window: &'a Window,
-        terminal: Terminal, 
-        font_filename: String, font_size: f32,
+        window_action_factory: WindowActionFactory,
+        font: FontData, font_size: f32,
+        prefer_hdr: bool,
+        present_mode: wgpu::PresentMode,
+        background_opacity: f32,
+        max_atlas_memory_bytes: Option<usize>,
     ) -> anyhow::Result<Self> 
     {
-        let terminal_user_events = terminal.get_user_event_handler();
         // wgpu
         let wgpu_instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::util::backend_bits_from_env().unwrap_or_else(get_default_wgpu_backends),
@@ -77,7 +545,8 @@ impl<'a> AppWindow<'a> {
         let (wgpu_device, wgpu_queue) = wgpu_adapter
             .request_device(&wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                // timestamp queries are only used for frame timing if available
+                required_features: wgpu_adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                 required_limits: wgpu::Limits::downlevel_defaults().using_resolution(wgpu_adapter.limits()),
             }, None)
             .await?;
@@ -87,30 +556,91 @@ impl<'a> AppWindow<'a> {
             .get_default_config(&wgpu_adapter, initial_window_size.width, initial_window_size.height)
             .ok_or("Failed to get default config for wgpu surface")
             .map_err(anyhow::Error::msg)?;
-        // @TODO: avoid srgb conversion to represent colours directly, but is this correct?
-        wgpu_config.format = wgpu::TextureFormat::Bgra8Unorm;
-        wgpu_config.present_mode = wgpu::PresentMode::AutoVsync;
+        let surface_capabilities = wgpu_surface.get_capabilities(&wgpu_adapter);
+        let surface_format = select_surface_format(surface_capabilities.formats.as_slice(), prefer_hdr)
+            .ok_or("Failed to find supported wgpu surface format")
+            .map_err(anyhow::Error::msg)?;
+        log::info!("Selected surface format: {:?} (prefer_hdr={})", surface_format, prefer_hdr);
+        wgpu_config.format = surface_format;
+        wgpu_config.present_mode = select_present_mode(surface_capabilities.present_modes.as_slice(), present_mode);
+        if wgpu_config.present_mode != present_mode {
+            log::warn!("Present mode {:?} is unsupported so {:?} is used instead", present_mode, wgpu_config.present_mode);
+        }
+        let is_transparent = background_opacity < 1.0;
+        let alpha_mode = select_alpha_mode(surface_capabilities.alpha_modes.as_slice(), is_transparent);
+        if let Some(alpha_mode) = alpha_mode {
+            wgpu_config.alpha_mode = alpha_mode;
+        } else if is_transparent {
+            log::warn!("Transparent windows are unsupported by the surface (alpha_modes={:?})", surface_capabilities.alpha_modes);
+        }
         wgpu_surface.configure(&wgpu_device, &wgpu_config);
-        let renderer = Renderer::new(&wgpu_config, &wgpu_device);
+        let mut renderer = Renderer::with_preferred_format(&wgpu_device, surface_format);
+        if alpha_mode.is_some() {
+            let is_alpha_premultiplied = alpha_mode == Some(wgpu::CompositeAlphaMode::PreMultiplied);
+            renderer.set_background_opacity(&wgpu_queue, background_opacity, is_alpha_premultiplied);
+        }
+        if !renderer.enable_gpu_timing(&wgpu_device, &wgpu_queue) {
+            log::info!("Timestamp queries are unsupported so render stats will exclude gpu timings");
+        }
         // glyph cache
-        let mut font_file = std::fs::File::open(font_filename.clone())?;
-        let mut font_data = Vec::<u8>::new();
-        let total_bytes_read = font_file.read_to_end(&mut font_data)?;
-        let font_data = &font_data[..total_bytes_read];
-        let font_settings = fontdue::FontSettings::default();
-        let font = fontdue::Font::from_bytes(font_data, font_settings).map_err(anyhow::Error::msg)?;
+        let font_settings = fontdue::FontSettings {
+            collection_index: font.collection_index,
+            ..Default::default()
+        };
+        let font = fontdue::Font::from_bytes(font.data.as_slice(), font_settings).map_err(anyhow::Error::msg)?;
         let wgpu_limits = wgpu_adapter.limits();
         let max_texture_size = wgpu_limits.max_texture_dimension_2d as usize;
         let max_texture_size = Vector2::new(max_texture_size, max_texture_size);
-        let glyph_generator = Box::new(FontdueGlyphGenerator::new(font, font_size));
-        let glyph_cache = GlyphCache::new(glyph_generator, max_texture_size);
+        let dpi_scale = winit_window.scale_factor() as f32;
+        let glyph_generator = Box::new(FontdueGlyphGenerator::new(font, font_size*dpi_scale));
+        let max_texture_layers = wgpu_limits.max_texture_array_layers as usize;
+        let glyph_cache = GlyphCache::new(glyph_generator, max_texture_size, max_texture_layers, max_atlas_memory_bytes);
+        // copy and paste are disabled if the clipboard is unavailable
+        winit_window.set_ime_allowed(true);
+        let clipboard = match arboard::Clipboard::new() {
+            Ok(clipboard) => Some(clipboard),
+            Err(err) => {
+                log::warn!("Failed to open clipboard: {:?}", err);
+                None
+            },
+        };
 
         Ok(Self {
-            terminal,
-            terminal_user_events,
-            terminal_renderer: TerminalRenderer::default(),
+            tabs: Vec::new(),
+            active_tab: 0,
+            next_pane_id: 0,
+            tab_bar: TabBar::default(),
+            tab_bar_cells: Vec::new(),
+            window_action_factory,
+            process_factory: None,
+            terminal_settings: TerminalSettings::default(),
+            print_metrics: false,
+            hold_on_exit: false,
+            process_exit_notifier: None,
+            record_output: None,
+            accessibility_tree: None,
+            accessibility_listener: None,
+            bell_mode: BellMode::default(),
+            bell_rate_limiter: BellRateLimiter::default(),
+            bell_flash_instant: None,
+            padding: Vector2::new(0,0),
+            minimum_contrast: TerminalRenderer::default().get_minimum_contrast(),
+            cursor_colour: None,
             glyph_grid: Vec::new(),
+            grid_size: Vector2::new(1,1),
+            split_grid: Vec::new(),
+            pane_glyph_grid: Vec::new(),
             glyph_cache,
+            default_font_size: font_size,
+            font_size,
+            dpi_scale,
+            dpi_scale_override: None,
+            glyph_dpi_scale: dpi_scale,
+            modifiers: ModifiersState::empty(),
+            keyboard_shortcuts: KeyboardShortcuts::default(),
+            alternate_screen_scroll_keys: true,
+            allow_window_control: false,
+            window_state_path: None,
             winit_window,
             wgpu_config,
             wgpu_surface,
@@ -118,13 +648,48 @@ impl<'a> AppWindow<'a> {
             wgpu_queue,
             renderer,
             is_redraw_requested: false,
+            is_redraw_deferred: false,
+            max_fps: None,
+            last_frame_instant: std::time::Instant::now(),
+            last_scroll_instant: None,
+            prompt_highlight: None,
             current_frame: 0,
             frame_counter: FrameCounter::default(),
+            throughput_counter: ThroughputCounter::default(),
+            is_debug_overlay: false,
+            render_stats: RenderStats::default(),
+            last_render_stats_instant: std::time::Instant::now(),
+            last_force_redraw_instant: std::time::Instant::now(),
+            is_focused: true,
+            unfocused_dim: DEFAULT_UNFOCUSED_DIM,
+            clipboard,
+            mouse_position: Vector2::new(0,0),
+            selection: None,
+            is_selecting: false,
+            selection_anchor: (Vector2::new(0,0), Vector2::new(0,0)),
+            selection_mode: SelectionMode::Cell,
+            selection_colour: None,
+            click_counter: ClickCounter::default(),
+            selection_autoscroll: SelectionAutoscroll::default(),
+            mouse_overshoot: 0,
+            word_characters: DEFAULT_WORD_CHARACTERS.to_owned(),
+            url_cache: UrlCache::default(),
+            hovered_url: None,
+            hovered_hyperlink: None,
+            hovered_hyperlink_url: None,
+            ime_preedit: None,
+            ime_cursor_area: None,
+            dropped_paths: Vec::new(),
         })
     }
 
     pub fn on_winit_event(
         &mut self, event: Event<AppEvent>, target: &EventLoopWindowTarget<AppEvent>) {
+        // last tab was closed
+        if self.tabs.is_empty() {
+            target.exit();
+            return;
+        }
         match event {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::CloseRequested => target.exit(),
@@ -132,239 +697,1715 @@ impl<'a> AppWindow<'a> {
                 WindowEvent::KeyboardInput { event, .. } => self.on_keyboard_input(event),
                 WindowEvent::MouseInput { state, button, .. } => self.on_mouse_input(button, state),
                 WindowEvent::Focused(is_focused) => self.on_focus(is_focused),
+                WindowEvent::ScaleFactorChanged { scale_factor, .. } => self.on_scale_factor_changed(scale_factor as f32),
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    let is_ctrl_changed = self.modifiers.control_key() != modifiers.state().control_key();
+                    self.modifiers = modifiers.state();
+                    // show or hide the url under the mouse
+                    if is_ctrl_changed {
+                        self.update_cursor_icon();
+                        self.trigger_redraw();
+                    }
+                },
                 WindowEvent::Resized(new_size) => {
                     let new_size = Vector2::new(new_size.width as usize, new_size.height as usize);
                     self.on_resize(new_size);
                 },
                 WindowEvent::CursorMoved { position, .. } => {
+                    self.mouse_overshoot = self.get_grid_overshoot(position.y);
                     let position = Vector2::new(position.x.max(0.0) as usize, position.y.max(0.0) as usize);
                     self.on_cursor_move(position);
                 },
                 WindowEvent::RedrawRequested => self.on_redraw_requested(),
+                WindowEvent::Ime(ime) => self.on_ime(ime),
+                WindowEvent::DroppedFile(path) => self.dropped_paths.push(path),
                 _ => {
                     // log::info!("Unhandled: {:?}", event);
                 },
             },
             Event::UserEvent(event) => match event {
-                AppEvent::WindowAction(action) => self.on_window_action(action),
+                AppEvent::WindowAction(pane_id, action) => self.on_window_action(pane_id, action),
+                AppEvent::ShortcutAction(action) => self.on_shortcut_action(action),
+                AppEvent::ProcessExited(pane_id, exit_code) => self.on_process_exited(pane_id, exit_code),
+            },
+            Event::AboutToWait => {
+                self.paste_dropped_paths();
+                self.update_selection_autoscroll();
+                self.update_accessibility_tree();
+                self.update_force_redraw_timer(target);
             },
+            Event::LoopExiting => self.on_exit(),
             _ => {
                 // log::info!("Unhandled: {:?}", event);
             },
         }
     }
 
-    fn trigger_redraw(&mut self) {
-        if !self.is_redraw_requested {
-            self.is_redraw_requested = true;
-            self.winit_window.request_redraw();
-        }
+    fn get_active_tab(&self) -> &TabState {
+        &self.tabs[self.active_tab]
     }
 
-    fn on_mouse_input(&mut self, button: MouseButton, state: ElementState) {
-        use vt100::encoder::MouseButton as TMouseButton;
-        let button = match button {
-            MouseButton::Left => TMouseButton::LeftClick,
-            MouseButton::Right => TMouseButton::RightClick,
-            MouseButton::Middle => TMouseButton::MiddleClick,
-            _ => return,
-        };
-        let event = match state {
-            ElementState::Pressed => TerminalUserEvent::MousePress(button),
-            ElementState::Released => TerminalUserEvent::MouseRelease(button),
-        };
-        self.terminal_user_events.send(event).unwrap();
+    fn get_active_tab_mut(&mut self) -> &mut TabState {
+        &mut self.tabs[self.active_tab]
     }
 
-    fn on_window_action(&mut self, action: WindowAction) {
-        match action {
-            WindowAction::SetWindowTitle(title) => self.winit_window.set_title(title.as_str()),
-            WindowAction::Refresh => self.trigger_redraw(),
-            _ => {
-                log::info!("Unhandled: {:?}", action);
-            }
-        }
+    fn get_active_pane(&self) -> &PaneState {
+        self.get_active_tab().get_active_pane()
     }
 
-    fn on_cursor_move(&mut self, pos: Vector2<usize>) {
-        self.terminal_user_events.send(TerminalUserEvent::MouseMove(pos)).unwrap();
+    fn get_active_pane_mut(&mut self) -> &mut PaneState {
+        self.get_active_tab_mut().get_active_pane_mut()
     }
 
-    fn on_focus(&mut self, is_focus: bool) {
-        self.terminal_user_events.send(TerminalUserEvent::WindowFocus(is_focus)).unwrap();
+    // Returns the index of the tab and the index of the pane within it
+    fn find_pane(&self, id: usize) -> Option<(usize, usize)> {
+        self.tabs.iter().enumerate().find_map(|(tab_index, tab)| Some((tab_index, tab.get_pane_index(id)?)))
     }
 
-    fn on_mouse_wheel(&mut self, delta: winit::event::MouseScrollDelta) {
-        use winit::event::MouseScrollDelta as Delta;
-        match delta {
-            Delta::LineDelta(_x, y) => {
-                if y > 0.0 {
-                    self.terminal_renderer.scroll_up(1);
-                } else {
-                    self.terminal_renderer.scroll_down(1);
-                }
-            },
-            Delta::PixelDelta(delta) => {
-                if delta.y > 0.0 {
-                    self.terminal_renderer.scroll_up(1);
-                } else {
-                    self.terminal_renderer.scroll_down(1);
-                }
-            },
-        }
-        self.trigger_redraw();
+    fn is_split(&self) -> bool {
+        self.get_active_tab().panes.len() > 1
     }
 
-    pub fn on_resize(&mut self, new_size: Vector2<usize>) {
-        let new_size = Vector2::new(new_size.x.max(1), new_size.y.max(1));
-        self.wgpu_config.width = new_size.x as u32;
-        self.wgpu_config.height = new_size.y as u32;
-        self.wgpu_surface.configure(&self.wgpu_device, &self.wgpu_config);
-        // calculate new terminal grid size
-        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
-        let new_grid_size = new_size.div_element_wise(glyph_size);
-        let new_grid_size = Vector2::new(new_grid_size.x.max(1), new_grid_size.y.max(1));
-        let actual_render_size = new_grid_size.mul_element_wise(glyph_size);
-        let new_render_scale = actual_render_size.cast::<f32>().unwrap().div_element_wise(new_size.cast::<f32>().unwrap());
-        // update gpu
-        self.renderer.update_render_scale(&self.wgpu_queue, new_render_scale);
-        self.terminal_user_events.send(TerminalUserEvent::WindowResize(actual_render_size)).unwrap();
-        self.terminal_user_events.send(TerminalUserEvent::GridResize(new_grid_size)).unwrap();
-        self.trigger_redraw();
+    // Cells of the grid covered by the active pane which is the whole grid unless the tab is split
+    fn get_active_pane_rect(&self) -> PaneRect {
+        let tab = self.get_active_tab();
+        tab.layout
+            .get_pane_rect(self.grid_size, tab.get_active_pane().id)
+            .unwrap_or(PaneRect { position: Vector2::new(0,0), size: self.grid_size })
     }
 
-    fn on_redraw_requested(&mut self) {
-        self.is_redraw_requested = false;
-        self.update_grid_from_terminal();
-        let frame = self.wgpu_surface.get_current_texture().expect("Failed to acquire next swap chain texture");
-        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.wgpu_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("render_text_commands"),
-        });
-        self.renderer.generate_commands(&mut encoder, &view, &self.wgpu_device);
-        self.wgpu_queue.submit(Some(encoder.finish()));
-        frame.present();
+    fn send_user_event(&self, event: TerminalUserEvent) {
+        self.get_active_pane().terminal.get_user_event_handler().send(event).unwrap();
     }
 
-    fn update_grid_from_terminal(&mut self) {
-        self.current_frame += 1;
-        self.frame_counter.update();
-        let display = self.terminal.get_display();
-        self.terminal_renderer.render_display(&display);
- 
-        let size = self.terminal_renderer.get_size();
-        let cells = self.terminal_renderer.get_cells();
-        let glyph_atlas = self.glyph_cache.get_glyph_atlas();
-        let total_glyphs_in_block = glyph_atlas.get_total_glyphs_in_block();
-        self.glyph_grid.resize(size.x*size.y, CellData::default());
-        for (dst, src) in self.glyph_grid.iter_mut().zip(cells.iter()) {
-            let atlas_index = self.glyph_cache.get_glyph_location(src.character, self.current_frame);
-            let atlas_index = Vector2::new(
-                atlas_index.block.x*total_glyphs_in_block.x + atlas_index.position.x,
-                atlas_index.block.y*total_glyphs_in_block.y + atlas_index.position.y,
-            );
-            dst.atlas_index = atlas_index.cast::<u16>().unwrap();
-            dst.colour_foreground = Vector4::new(
-                src.pen.foreground_colour.r,
-                src.pen.foreground_colour.g,
-                src.pen.foreground_colour.b,
-                255,
-            );
-            dst.colour_background = Vector4::new(
-                src.pen.background_colour.r,
-                src.pen.background_colour.g,
-                src.pen.background_colour.b,
-                255,
-            );
-            dst.style_flags = 0u32;
-        }
-        self.renderer.update_grid(&self.wgpu_device, &self.wgpu_queue, self.glyph_grid.as_slice(), size);
-        let glyph_atlas = self.glyph_cache.get_glyph_atlas_mut();
-        self.renderer.update_atlas(&self.wgpu_device, &self.wgpu_queue, glyph_atlas);
+    pub fn set_terminal_settings(&mut self, settings: TerminalSettings) {
+        self.terminal_settings = settings;
     }
 
-    fn on_keyboard_input(&mut self, event: winit::event::KeyEvent) {
-        use vt100::encoder::{KeyCode as TKey, ModifierKey, ArrowKey, FunctionKey};
+    pub fn set_print_metrics(&mut self, print_metrics: bool) {
+        self.print_metrics = print_metrics;
+    }
 
-        let key_press = &mut |key: TKey| self.terminal_user_events.send(TerminalUserEvent::KeyPress(key)).unwrap();
-        let key_release = &mut |key: TKey| self.terminal_user_events.send(TerminalUserEvent::KeyRelease(key)).unwrap();
-        // modifier keys listen to press/release
-        if let PhysicalKey::Code(code) = event.physical_key {
-            match event.state {
-                ElementState::Pressed => match code {
-                    KeyCode::AltLeft      => return key_press(TKey::ModifierKey(ModifierKey::Alt)),
-                    KeyCode::AltRight     => return key_press(TKey::ModifierKey(ModifierKey::Alt)),
-                    KeyCode::ControlLeft  => return key_press(TKey::ModifierKey(ModifierKey::Ctrl)),
-                    KeyCode::ControlRight => return key_press(TKey::ModifierKey(ModifierKey::Ctrl)),
-                    KeyCode::ShiftLeft    => return key_press(TKey::ModifierKey(ModifierKey::Shift)),
-                    KeyCode::ShiftRight   => return key_press(TKey::ModifierKey(ModifierKey::Shift)),
-                    _ => {},
-                },
-                ElementState::Released => match code {
-                    KeyCode::AltLeft      => return key_release(TKey::ModifierKey(ModifierKey::Alt)),
-                    KeyCode::AltRight     => return key_release(TKey::ModifierKey(ModifierKey::Alt)),
-                    KeyCode::ControlLeft  => return key_release(TKey::ModifierKey(ModifierKey::Ctrl)),
-                    KeyCode::ControlRight => return key_release(TKey::ModifierKey(ModifierKey::Ctrl)),
-                    KeyCode::ShiftLeft    => return key_release(TKey::ModifierKey(ModifierKey::Shift)),
-                    KeyCode::ShiftRight   => return key_release(TKey::ModifierKey(ModifierKey::Shift)),
-                    _ => {},
-                },
-            };
-        };
+    pub fn set_hold_on_exit(&mut self, hold_on_exit: bool) {
+        self.hold_on_exit = hold_on_exit;
+    }
 
-        if event.state != ElementState::Pressed {
-            return;
+    // Tabs stay open after their process exits if unset
+    pub fn set_process_exit_notifier(&mut self, notifier: Option<ProcessExitNotifier>) {
+        self.process_exit_notifier = notifier;
+    }
+
+    // Listener is called with the rows of the active pane shortly after they change
+    pub fn set_accessibility_listener(&mut self, listener: Option<AccessibilityListener>) {
+        self.accessibility_tree = listener.as_ref().map(|_| AccessibilityTree::default());
+        self.accessibility_listener = listener;
+        if let Some(tree) = self.accessibility_tree.as_mut() {
+            tree.mark_changed(std::time::Instant::now());
         }
+    }
 
-        if let PhysicalKey::Code(code) = event.physical_key {
-            match code {
-                KeyCode::ArrowUp    => return key_press(TKey::ArrowKey(ArrowKey::Up)),
-                KeyCode::ArrowDown  => return key_press(TKey::ArrowKey(ArrowKey::Down)),
-                KeyCode::ArrowLeft  => return key_press(TKey::ArrowKey(ArrowKey::Left)),
-                KeyCode::ArrowRight => return key_press(TKey::ArrowKey(ArrowKey::Right)),
-                _ => {},
+    pub fn get_accessibility_tree(&self) -> Option<&AccessibilityTree> {
+        self.accessibility_tree.as_ref()
+    }
+
+    // Only the next pane that is opened is recorded since each recording needs its own file
+    pub fn set_record_output(&mut self, record_output: Option<Box<dyn std::io::Write + Send>>) {
+        self.record_output = record_output;
+    }
+
+    pub fn set_bell_mode(&mut self, bell_mode: BellMode) {
+        self.bell_mode = bell_mode;
+    }
+
+    fn on_exit(&mut self) {
+        if self.print_metrics {
+            for pane in self.tabs.iter().flat_map(|tab| tab.panes.iter()) {
+                log_parser_metrics(&pane.terminal);
             }
         }
-
-        if let PhysicalKey::Code(code) = event.physical_key {
-            match code {
-                KeyCode::Escape    => return key_press(TKey::FunctionKey(FunctionKey::Escape)),
-                KeyCode::Tab       => return key_press(TKey::FunctionKey(FunctionKey::Tab)),
-                KeyCode::Backspace => return key_press(TKey::FunctionKey(FunctionKey::Backspace)),
-                KeyCode::Enter     => return key_press(TKey::FunctionKey(FunctionKey::Enter)),
-                KeyCode::Delete    => return key_press(TKey::FunctionKey(FunctionKey::Delete)),
-                KeyCode::Home      => return key_press(TKey::FunctionKey(FunctionKey::Home)),
-                KeyCode::End       => return key_press(TKey::FunctionKey(FunctionKey::End)),
-                _ => {},
+        if let Some(path) = self.window_state_path.as_deref() {
+            let geometry = self.get_window_geometry();
+            if let Err(err) = geometry.save(path) {
+                log::warn!("Failed to save window geometry to '{}': {}", path.display(), err);
             }
         }
+    }
 
-        if let PhysicalKey::Code(code) = event.physical_key {
-            let size = self.terminal_renderer.get_size();
-            let mut is_render = true;
-            match code {
-                KeyCode::PageDown => self.terminal_renderer.scroll_down(size.y),
-                KeyCode::PageUp   => self.terminal_renderer.scroll_up(size.y),
-                _ => { is_render = false; },
-            }
-            if is_render {
-                self.trigger_redraw();
-                return;
-            }
+    fn get_window_geometry(&self) -> WindowGeometry {
+        let size = self.winit_window.inner_size();
+        WindowGeometry {
+            position: self.winit_window.outer_position().ok().map(|position| [position.x, position.y]),
+            size: [size.width, size.height],
+            is_maximised: self.winit_window.is_maximized(),
         }
+    }
 
-        if event.physical_key == PhysicalKey::Code(KeyCode::Space) {
-            key_press(TKey::Char(' '));
-            self.terminal_renderer.scroll_to_bottom();
-            self.trigger_redraw();
+    pub fn set_window_state_path(&mut self, window_state_path: Option<std::path::PathBuf>) {
+        self.window_state_path = window_state_path;
+    }
+
+    // Used to spawn the process of tabs and panes opened with the new tab and split shortcuts
+    pub fn set_process_factory(&mut self, process_factory: Option<ProcessFactory>) {
+        self.process_factory = process_factory;
+    }
+
+    fn create_pane(&mut self, process: Box<dyn TerminalProcess + Send>) -> anyhow::Result<PaneState> {
+        let process = Arc::new(Mutex::new(process));
+        let id = self.next_pane_id;
+        let is_refresh_queued = Arc::new(AtomicBool::new(false));
+        let mut terminal_builder = create_default_terminal_builder(process.clone(), self.terminal_settings)?;
+        terminal_builder.window_action = (self.window_action_factory)(id, is_refresh_queued.clone());
+        terminal_builder.record_output = self.record_output.take();
+        if let Some(notifier) = self.process_exit_notifier.clone() {
+            let process = process.clone();
+            // output read before the process exited is parsed before the exit message is written after it
+            terminal_builder.on_parser_exit = Some(Box::new(move || {
+                notifier(id, wait_for_exit_code(&process));
+            }));
+        }
+        let terminal = Terminal::new(terminal_builder);
+        let mut renderer = TerminalRenderer::default();
+        renderer.set_minimum_contrast(self.minimum_contrast);
+        renderer.set_default_cursor_colour(self.cursor_colour);
+        self.next_pane_id += 1;
+        Ok(PaneState {
+            terminal,
+            renderer,
+            id,
+            process,
+            is_exited: false,
+            is_refresh_queued,
+        })
+    }
+
+    pub fn new_tab(&mut self, process: Box<dyn TerminalProcess + Send>) -> anyhow::Result<()> {
+        let pane = self.create_pane(process)?;
+        self.tabs.push(TabState::new(pane));
+        self.switch_tab(self.tabs.len()-1);
+        Ok(())
+    }
+
+    // New pane takes the right or bottom half of the active pane and receives input
+    pub fn split_pane(&mut self, process: Box<dyn TerminalProcess + Send>, direction: SplitDirection) -> anyhow::Result<()> {
+        let pane = self.create_pane(process)?;
+        let tab = self.get_active_tab_mut();
+        let active_id = tab.get_active_pane().id;
+        tab.layout.split(active_id, direction, pane.id);
+        tab.panes.push(pane);
+        let index = tab.panes.len()-1;
+        self.switch_pane(index);
+        // existing pane is shrunk to make room
+        let window_size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        self.on_resize(window_size);
+        Ok(())
+    }
+
+    // Exits once the last tab is closed
+    pub fn close_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
             return;
         }
-        if let Key::Character(string) = event.logical_key {
-            for c in string.chars() {
-                key_press(TKey::Char(c));
+        if self.print_metrics {
+            for pane in &self.tabs[index].panes {
+                log_parser_metrics(&pane.terminal);
             }
-            self.terminal_renderer.scroll_to_bottom();
-            self.trigger_redraw();
         }
+        // terminates the process of each pane
+        self.tabs.remove(index);
+        if self.tabs.is_empty() {
+            self.winit_window.request_redraw();
+            return;
+        }
+        self.active_tab = get_active_tab_after_close(self.active_tab, index, self.tabs.len());
+        self.on_tab_changed();
     }
+
+    // Tab is closed with its last pane
+    pub fn close_pane(&mut self, tab_index: usize, pane_index: usize) {
+        let Some(tab) = self.tabs.get_mut(tab_index) else {
+            return;
+        };
+        if tab.panes.len() <= 1 {
+            self.close_tab(tab_index);
+            return;
+        }
+        if pane_index >= tab.panes.len() {
+            return;
+        }
+        if self.print_metrics {
+            log_parser_metrics(&tab.panes[pane_index].terminal);
+        }
+        let is_active_pane = tab_index == self.active_tab && pane_index == tab.active_pane;
+        // sibling takes over the area of the pane
+        tab.layout.remove(tab.panes[pane_index].id);
+        // terminates the process
+        tab.panes.remove(pane_index);
+        // same as closing a tab where the pane after it becomes active
+        tab.active_pane = get_active_tab_after_close(tab.active_pane, pane_index, tab.panes.len());
+        if is_active_pane {
+            self.on_pane_changed();
+        }
+        let window_size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        self.on_resize(window_size);
+    }
+
+    fn on_process_exited(&mut self, pane_id: usize, exit_code: Option<i32>) {
+        let Some((tab_index, pane_index)) = self.find_pane(pane_id) else {
+            return;
+        };
+        log::info!("Process of pane {} exited with code {:?}", pane_id, exit_code);
+        if !self.hold_on_exit {
+            self.close_pane(tab_index, pane_index);
+            return;
+        }
+        let pane = &mut self.tabs[tab_index].panes[pane_index];
+        pane.is_exited = true;
+        // exit is only sent once the parser thread has finished so it isn't joined here
+        pane.terminal.write_to_display(get_process_exit_message(exit_code).as_bytes());
+        self.trigger_redraw();
+    }
+
+    pub fn switch_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        if self.is_focused && index != self.active_tab && self.active_tab < self.tabs.len() {
+            self.send_user_event(TerminalUserEvent::WindowFocus(false));
+        }
+        self.active_tab = index;
+        self.on_tab_changed();
+    }
+
+    fn switch_pane(&mut self, index: usize) {
+        if index >= self.get_active_tab().panes.len() || index == self.get_active_tab().active_pane {
+            return;
+        }
+        if self.is_focused {
+            self.send_user_event(TerminalUserEvent::WindowFocus(false));
+        }
+        self.get_active_tab_mut().active_pane = index;
+        self.on_pane_changed();
+        self.trigger_redraw();
+    }
+
+    fn on_tab_changed(&mut self) {
+        self.on_pane_changed();
+        let title = self.get_active_tab().title.clone();
+        if !title.is_empty() {
+            self.winit_window.set_title(title.as_str());
+        }
+        // tab bar is only shown with multiple tabs which changes the grid size
+        let window_size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        self.on_resize(window_size);
+    }
+
+    // Selection, hover and input state belong to the terminal of the active pane
+    fn on_pane_changed(&mut self) {
+        self.selection = None;
+        self.is_selecting = false;
+        self.hovered_url = None;
+        // hyperlink ids are only unique within a terminal
+        self.hovered_hyperlink = None;
+        self.hovered_hyperlink_url = None;
+        self.update_cursor_icon();
+        self.url_cache = UrlCache::default();
+        self.ime_preedit = None;
+        self.ime_cursor_area = None;
+        self.prompt_highlight = None;
+        self.throughput_counter = ThroughputCounter::default();
+        if self.is_focused {
+            self.send_user_event(TerminalUserEvent::WindowFocus(true));
+        }
+    }
+
+    // Tab bar is a single row of cells shown when there is more than one tab
+    fn get_tab_bar_height(&self) -> usize {
+        if self.tabs.len() > 1 {
+            self.glyph_cache.get_glyph_atlas().get_glyph_size().y
+        } else {
+            0
+        }
+    }
+
+    // Top left pixel of the grid which is below the tab bar
+    fn get_grid_origin(&self) -> Vector2<usize> {
+        Vector2::new(self.padding.x, self.padding.y + self.get_tab_bar_height())
+    }
+
+    // Top left pixel of the active pane which is the grid origin unless the tab is split
+    fn get_pane_origin(&self) -> Vector2<usize> {
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        self.get_grid_origin() + self.get_active_pane_rect().position.mul_element_wise(glyph_size)
+    }
+
+    // Panes of a split tab are composed into one grid so they can only scroll by whole rows
+    fn get_pixel_offset(&self) -> usize {
+        if self.is_split() {
+            0
+        } else {
+            self.get_active_pane().renderer.get_pixel_offset()
+        }
+    }
+
+    // Active tab uses the terminal colours and the rest of the bar is inverted
+    fn update_tab_bar_cells(&mut self, pen: Pen) {
+        self.tab_bar_cells.clear();
+        if self.get_tab_bar_height() == 0 {
+            return;
+        }
+        let width = self.grid_size.x;
+        let titles: Vec<&str> = self.tabs.iter().map(|tab| tab.title.as_str()).collect();
+        self.tab_bar.update(titles.as_slice(), width);
+        let total_glyphs_in_block = self.glyph_cache.get_glyph_atlas().get_total_glyphs_in_block();
+        let foreground_colour = Vector3::new(pen.foreground_colour.r, pen.foreground_colour.g, pen.foreground_colour.b);
+        let background_colour = Vector3::new(pen.background_colour.r, pen.background_colour.g, pen.background_colour.b);
+        for x in 0..width {
+            let tab_index = self.tab_bar.get_tab_at_column(x);
+            let c = tab_index
+                .and_then(|index| {
+                    let span = self.tab_bar.get_spans()[index];
+                    self.tab_bar.get_label(index)?.chars().nth(x-span.start)
+                })
+                .unwrap_or(' ');
+            let glyph = self.glyph_cache.get_glyph_location(c, self.current_frame);
+            let mut cell = CellData::default();
+            cell.set_atlas_index(glyph.get_atlas_position(total_glyphs_in_block, 0), glyph.layer);
+            cell.set_foreground_colour(foreground_colour);
+            cell.set_background_colour(background_colour);
+            cell.set_inverse(tab_index != Some(self.active_tab));
+            self.tab_bar_cells.push(cell);
+        }
+    }
+
+    // Called after the atlas is updated since the tab bar shares it with the grid
+    fn update_tab_bar(&mut self) {
+        if self.tab_bar_cells.is_empty() {
+            self.renderer.clear_tab_bar();
+            return;
+        }
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let size = Vector2::new(self.tab_bar_cells.len(), 1);
+        let window_size = Vector2::new(self.wgpu_config.width as f32, self.wgpu_config.height as f32);
+        let render_scale = size.mul_element_wise(glyph_size).cast::<f32>().unwrap().div_element_wise(window_size);
+        self.renderer.update_tab_bar(&self.wgpu_device, &self.wgpu_queue, self.tab_bar_cells.as_slice(), size, render_scale);
+    }
+
+    pub fn force_redraw(&mut self) {
+        self.last_force_redraw_instant = std::time::Instant::now();
+        self.send_user_event(TerminalUserEvent::ForceRedraw);
+    }
+
+    fn update_force_redraw_timer(&mut self, target: &EventLoopWindowTarget<AppEvent>) {
+        if self.last_force_redraw_instant.elapsed() >= FORCE_REDRAW_INTERVAL {
+            self.force_redraw();
+        }
+        let mut deadline = self.last_force_redraw_instant + FORCE_REDRAW_INTERVAL;
+        if let Some(next_step) = self.selection_autoscroll.get_next_step() {
+            deadline = deadline.min(next_step);
+        }
+        if let Some(next_update) = self.accessibility_tree.as_ref().and_then(|tree| tree.get_deadline()) {
+            deadline = deadline.min(next_update);
+        }
+        if self.is_debug_overlay {
+            let overlay_deadline = self.last_frame_instant + DEBUG_OVERLAY_INTERVAL;
+            if overlay_deadline <= std::time::Instant::now() {
+                self.trigger_redraw();
+            } else {
+                deadline = deadline.min(overlay_deadline);
+            }
+        }
+        if self.is_redraw_deferred {
+            match get_frame_deadline(self.last_frame_instant, self.max_fps) {
+                Some(frame_deadline) if frame_deadline > std::time::Instant::now() => {
+                    deadline = deadline.min(frame_deadline);
+                },
+                _ => {
+                    self.is_redraw_deferred = false;
+                    self.trigger_redraw();
+                },
+            }
+        }
+        target.set_control_flow(ControlFlow::WaitUntil(deadline));
+    }
+
+    // Rebuilt from the display of the active tab once changes have settled
+    fn update_accessibility_tree(&mut self) {
+        let Some(tree) = self.accessibility_tree.as_mut() else {
+            return;
+        };
+        if !tree.is_update_due(std::time::Instant::now()) {
+            return;
+        }
+        let rows = self.tabs[self.active_tab].get_active_pane().terminal.get_display().to_accessibility_tree();
+        if tree.update(rows) {
+            if let Some(listener) = self.accessibility_listener.as_mut() {
+                listener(tree.get_rows());
+            }
+        }
+    }
+
+    fn trigger_redraw(&mut self) {
+        if let Some(frame_deadline) = get_frame_deadline(self.last_frame_instant, self.max_fps) {
+            if frame_deadline > std::time::Instant::now() {
+                self.is_redraw_deferred = true;
+                return;
+            }
+        }
+        if !self.is_redraw_requested {
+            self.is_redraw_requested = true;
+            self.winit_window.request_redraw();
+        }
+    }
+
+    fn on_mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        use vt100::encoder::{MouseButton as TMouseButton, MouseTrackingMode};
+        // shift forces local selection even if the process is tracking the mouse
+        let is_local_selection =
+            self.get_active_pane().terminal.get_mouse_tracking_mode() == MouseTrackingMode::Disabled ||
+            self.modifiers.shift_key() ||
+            self.is_selecting;
+        let bar_height = self.get_tab_bar_height();
+        if self.mouse_position.y < bar_height && !self.is_selecting {
+            if button == MouseButton::Left && state == ElementState::Pressed {
+                let glyph_width = self.glyph_cache.get_glyph_atlas().get_glyph_size().x;
+                if let Some(index) = self.tab_bar.get_tab_at_column(self.mouse_position.x / glyph_width) {
+                    self.switch_tab(index);
+                }
+            }
+            return;
+        }
+        if state == ElementState::Pressed && !self.is_selecting && self.is_split() {
+            let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+            let position = convert_window_to_grid_position(self.mouse_position, self.get_grid_origin());
+            let cell = convert_pixel_to_cell(position, glyph_size, 0, self.grid_size);
+            let tab = self.get_active_tab();
+            match tab.layout.get_pane_at(self.grid_size, cell).and_then(|id| tab.get_pane_index(id)) {
+                Some(index) if index == tab.active_pane => {},
+                // clicking another pane only moves input to it
+                Some(index) => return self.switch_pane(index),
+                // divider between panes
+                None => return,
+            }
+        }
+        if button == MouseButton::Left && state == ElementState::Pressed && self.modifiers.control_key() {
+            // url cache is only updated when the next frame is rendered after ctrl is pressed
+            let url = self.hovered_url.as_ref().map(|url| url.url.as_str()).or(self.hovered_hyperlink_url.as_deref());
+            if let Some(url) = url {
+                log::info!("Opening url: {}", url);
+                if let Err(err) = opener::open(url) {
+                    log::error!("Failed to open url '{}': {:?}", url, err);
+                }
+                return;
+            }
+        }
+        if button == MouseButton::Middle {
+            match get_middle_click_action(is_local_selection, IS_PRIMARY_SELECTION_SUPPORTED) {
+                MiddleClickAction::PastePrimarySelection => {
+                    if state == ElementState::Pressed {
+                        self.paste_primary_selection();
+                    }
+                    return;
+                },
+                MiddleClickAction::Ignore => return,
+                MiddleClickAction::SendToProcess => {},
+            }
+        }
+        if button == MouseButton::Left && is_local_selection {
+            match state {
+                ElementState::Pressed => {
+                    let cell = self.get_cell_under_mouse();
+                    self.selection_mode = self.click_counter.on_click(std::time::Instant::now(), cell);
+                    self.selection_anchor = self.get_selection_unit(cell);
+                    let (start, end) = self.selection_anchor;
+                    self.selection = Some(Selection { start, end });
+                    self.is_selecting = true;
+                },
+                ElementState::Released => {
+                    self.is_selecting = false;
+                    self.selection_autoscroll.stop();
+                    // a single click clears the selection
+                    let is_click = self.selection.is_some_and(|selection| selection.start == selection.end);
+                    if is_click && self.selection_mode == SelectionMode::Cell {
+                        self.selection = None;
+                    } else {
+                        self.copy_selection_to_primary();
+                    }
+                },
+            }
+            self.trigger_redraw();
+            return;
+        }
+        let button = match button {
+            MouseButton::Left => TMouseButton::LeftClick,
+            MouseButton::Right => TMouseButton::RightClick,
+            MouseButton::Middle => TMouseButton::MiddleClick,
+            _ => return,
+        };
+        let event = match state {
+            ElementState::Pressed => TerminalUserEvent::MousePress(button),
+            ElementState::Released => TerminalUserEvent::MouseRelease(button),
+        };
+        self.send_user_event(event);
+    }
+
+    fn on_window_action(&mut self, pane_id: usize, action: WindowAction) {
+        // actions from closed panes are dropped
+        let Some((index, pane_index)) = self.find_pane(pane_id) else {
+            return;
+        };
+        if action == WindowAction::Refresh {
+            self.tabs[index].panes[pane_index].is_refresh_queued.store(false, Ordering::SeqCst);
+        }
+        // every pane of the active tab is visible
+        let is_active = index == self.active_tab;
+        if !is_window_action_allowed(&action, self.allow_window_control) {
+            log::info!("Ignoring {:?} since allow_window_control is disabled", action);
+            return;
+        }
+        match action {
+            WindowAction::SetWindowTitle(title) => self.set_tab_title(index, title),
+            WindowAction::SetIconTitle(label) => self.tabs[index].icon_label = label,
+            WindowAction::SaveWindowTitle(_) => {
+                let tab = &mut self.tabs[index];
+                tab.title_stack.push(tab.title.as_str());
+            },
+            WindowAction::RestoreWindowTitle(_) => {
+                if let Some(title) = self.tabs[index].title_stack.pop() {
+                    self.set_tab_title(index, title);
+                }
+            },
+            WindowAction::SaveIconTitle(_) => {
+                let tab = &mut self.tabs[index];
+                tab.icon_label_stack.push(tab.icon_label.as_str());
+            },
+            WindowAction::RestoreIconTitle(_) => {
+                let tab = &mut self.tabs[index];
+                if let Some(label) = tab.icon_label_stack.pop() {
+                    tab.icon_label = label;
+                }
+            },
+            WindowAction::GetWindowIconLabel => {
+                let reply = encode_icon_label_report(self.tabs[index].icon_label.as_str());
+                let user_events = self.tabs[index].panes[pane_index].terminal.get_user_event_handler();
+                user_events.send(TerminalUserEvent::WriteReply(reply.into_bytes())).unwrap();
+            },
+            WindowAction::Refresh if is_active => self.trigger_redraw(),
+            WindowAction::Refresh => {},
+            WindowAction::Bell => self.on_bell(index, pane_index),
+            // resulting resize events update the grid size
+            WindowAction::SetFullscreen(is_fullscreen) => self.set_fullscreen(is_fullscreen),
+            WindowAction::ToggleFullscreen => self.toggle_fullscreen(),
+            WindowAction::Maximise(Vector2 { x: true, y: true }) => self.winit_window.set_maximized(true),
+            WindowAction::Maximise(axes) => {
+                log::warn!("Maximising only one axis is unsupported: {:?}", axes);
+            },
+            WindowAction::RestoreMaximised => self.winit_window.set_maximized(false),
+            WindowAction::ResizeTextArea(size) => {
+                let grid_size = get_requested_grid_size(size, self.get_window_metrics().grid_size);
+                self.request_grid_size(grid_size);
+            },
+            WindowAction::SetMinimised(is_minimised) => self.winit_window.set_minimized(is_minimised),
+            WindowAction::SendToFront => self.winit_window.focus_window(),
+            // briefly keeping the window below others moves it to the bottom of the stack
+            WindowAction::SendToBack => {
+                self.winit_window.set_window_level(WindowLevel::AlwaysOnBottom);
+                self.winit_window.set_window_level(WindowLevel::Normal);
+            },
+            WindowAction::GetWindowState | WindowAction::GetWindowPosition | WindowAction::GetTextAreaPosition |
+            WindowAction::GetTextAreaSize | WindowAction::GetWindowSize | WindowAction::GetScreenSize |
+            WindowAction::GetCellSize | WindowAction::GetTextAreaGridSize | WindowAction::GetScreenGridSize => {
+                let Some(reply) = encode_window_report(&action, &self.get_window_metrics()) else {
+                    return;
+                };
+                let user_events = self.tabs[index].panes[pane_index].terminal.get_user_event_handler();
+                user_events.send(TerminalUserEvent::WriteReply(reply.into_bytes())).unwrap();
+            },
+            _ => {
+                log::info!("Unhandled: {:?}", action);
+            }
+        }
+    }
+
+    fn set_tab_title(&mut self, index: usize, title: String) {
+        if index == self.active_tab {
+            self.winit_window.set_title(title.as_str());
+        }
+        self.tabs[index].title = title;
+        self.trigger_redraw();
+    }
+
+    fn on_bell(&mut self, index: usize, pane_index: usize) {
+        if self.bell_mode == BellMode::None || !self.bell_rate_limiter.try_ring(std::time::Instant::now()) {
+            return;
+        }
+        if self.bell_mode.is_visual() {
+            if index == self.active_tab {
+                self.bell_flash_instant = Some(std::time::Instant::now());
+                self.trigger_redraw();
+            }
+            if !self.is_focused {
+                self.winit_window.request_user_attention(Some(winit::window::UserAttentionType::Informational));
+            }
+        }
+        // process can mute the bell with DECSWBV
+        let volume = self.tabs[index].panes[pane_index].terminal.get_display().get_bell_volume();
+        if self.bell_mode.is_audible() && volume != BellVolume::Off {
+            play_system_beep();
+        }
+    }
+
+    fn get_window_metrics(&self) -> WindowMetrics {
+        let to_vector = |size: PhysicalSize<u32>| Vector2::new(size.width as usize, size.height as usize);
+        let window_size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        let cell_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let grid_area = calculate_grid_area(window_size, self.padding, self.get_tab_bar_height());
+        let (grid_size, text_area_size) = calculate_grid_size(grid_area, cell_size);
+        // platforms like wayland don't expose the window position
+        let window_position = self.winit_window.outer_position()
+            .map(|position| Vector2::new(position.x, position.y))
+            .unwrap_or(Vector2::new(0,0));
+        let screen_size = self.winit_window.current_monitor()
+            .map(|monitor| to_vector(monitor.size()))
+            .unwrap_or(window_size);
+        WindowMetrics {
+            window_position,
+            window_size: to_vector(self.winit_window.outer_size()),
+            text_area_position: self.get_grid_origin(),
+            text_area_size,
+            screen_size,
+            cell_size,
+            grid_size,
+            is_minimised: self.winit_window.is_minimized().unwrap_or(false),
+        }
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        self.winit_window.fullscreen().is_some()
+    }
+
+    fn set_fullscreen(&mut self, is_fullscreen: bool) {
+        if is_fullscreen == self.is_fullscreen() {
+            return;
+        }
+        let fullscreen = is_fullscreen.then_some(Fullscreen::Borderless(None));
+        self.winit_window.set_fullscreen(fullscreen);
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        self.set_fullscreen(!self.is_fullscreen());
+    }
+
+    fn on_cursor_move(&mut self, pos: Vector2<usize>) {
+        self.mouse_position = pos;
+        if self.update_hovered_hyperlink() {
+            self.trigger_redraw();
+        }
+        if self.modifiers.control_key() {
+            let cell = self.get_cell_under_mouse();
+            let is_hover_changed = match self.hovered_url.as_ref() {
+                Some(url) => !url.contains(cell),
+                None => true,
+            };
+            if is_hover_changed {
+                self.trigger_redraw();
+            }
+        }
+        if self.is_selecting {
+            self.extend_selection_to_mouse();
+        }
+        self.send_user_event(TerminalUserEvent::MouseMove(self.get_grid_mouse_position()));
+    }
+
+    fn is_mouse_over_grid(&self) -> bool {
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let origin = self.get_pane_origin();
+        let render_size = self.get_active_pane().renderer.get_size().mul_element_wise(glyph_size);
+        let position = self.mouse_position;
+        position.x >= origin.x && position.y >= origin.y &&
+            position.x < origin.x + render_size.x && position.y < origin.y + render_size.y
+    }
+
+    // Returns true if the hovered hyperlink changed
+    fn update_hovered_hyperlink(&mut self) -> bool {
+        let hyperlink = if self.is_mouse_over_grid() && !self.is_selecting {
+            get_hyperlink_span(&self.get_active_pane().renderer, self.get_cell_under_mouse())
+        } else {
+            None
+        };
+        if hyperlink == self.hovered_hyperlink {
+            return false;
+        }
+        let id = hyperlink.map(|link| link.id);
+        if id != self.hovered_hyperlink.map(|link| link.id) {
+            self.hovered_hyperlink_url = id.and_then(|id| {
+                let display = self.get_active_pane().terminal.get_display();
+                display.get_hyperlink(id).map(str::to_owned)
+            });
+        }
+        self.hovered_hyperlink = hyperlink;
+        self.update_cursor_icon();
+        true
+    }
+
+    // Links are only opened with ctrl+click so a plain click keeps the default cursor
+    fn update_cursor_icon(&mut self) {
+        let is_link = self.hovered_hyperlink.is_some() && self.modifiers.control_key();
+        let cursor_icon = if is_link { CursorIcon::Pointer } else { CursorIcon::Default };
+        self.winit_window.set_cursor_icon(cursor_icon);
+    }
+
+    fn extend_selection_to_mouse(&mut self) {
+        // selection is extended by whole words or lines after a multi-click
+        let (start, end) = self.get_selection_unit(self.get_cell_under_mouse());
+        let (anchor_start, anchor_end) = self.selection_anchor;
+        let get_order = |p: Vector2<usize>| (p.y, p.x);
+        let selection = Selection {
+            start: std::cmp::min_by_key(anchor_start, start, |&p| get_order(p)),
+            end: std::cmp::max_by_key(anchor_end, end, |&p| get_order(p)),
+        };
+        if self.selection != Some(selection) {
+            self.selection = Some(selection);
+            self.trigger_redraw();
+        }
+    }
+
+    fn get_grid_overshoot(&self, mouse_y: f64) -> isize {
+        let glyph_height = self.glyph_cache.get_glyph_atlas().get_glyph_size().y;
+        let grid_top = self.get_pane_origin().y as f64;
+        let grid_bottom = grid_top + (self.get_active_pane().renderer.get_size().y*glyph_height) as f64;
+        if mouse_y < grid_top {
+            (mouse_y - grid_top).floor() as isize
+        } else if mouse_y >= grid_bottom {
+            (mouse_y - grid_bottom).floor() as isize + 1
+        } else {
+            0
+        }
+    }
+
+    // Selection is extended into the scrollback while it is dragged past the top or bottom of the grid
+    fn update_selection_autoscroll(&mut self) {
+        if !self.is_selecting {
+            self.selection_autoscroll.stop();
+            return;
+        }
+        let now = std::time::Instant::now();
+        let glyph_height = self.glyph_cache.get_glyph_atlas().get_glyph_size().y;
+        let rows = self.selection_autoscroll.update(now, self.mouse_overshoot, glyph_height);
+        let renderer = &mut self.get_active_pane_mut().renderer;
+        // alternate screen has no scrollback
+        let Some(old_status) = renderer.get_scroll_status() else {
+            return;
+        };
+        match rows {
+            0 => return,
+            rows if rows < 0 => renderer.scroll_up(rows.unsigned_abs()),
+            rows => renderer.scroll_down(rows as usize),
+        }
+        let Some(new_status) = renderer.get_scroll_status() else {
+            return;
+        };
+        if new_status.scroll_offset == old_status.scroll_offset {
+            return;
+        }
+        // selection is extended to the mouse once the scrolled rows are rendered
+        self.last_scroll_instant = Some(now);
+        self.trigger_redraw();
+    }
+
+    fn get_grid_mouse_position(&self) -> Vector2<usize> {
+        convert_window_to_grid_position(self.mouse_position, self.get_pane_origin())
+    }
+
+    fn get_cell_under_mouse(&self) -> Vector2<usize> {
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let size = self.get_active_pane().renderer.get_size();
+        let grid_size = Vector2::new(size.x, self.get_active_pane().renderer.get_total_render_rows().max(size.y));
+        convert_pixel_to_cell(self.get_grid_mouse_position(), glyph_size, self.get_pixel_offset(), grid_size)
+    }
+
+    // Inclusive bounds in line coordinates of the cell, word or line at the cell depending on the selection mode
+    fn get_selection_unit(&self, cell: Vector2<usize>) -> (Vector2<usize>, Vector2<usize>) {
+        let renderer = &self.get_active_pane().renderer;
+        let (start, end) = match self.selection_mode {
+            SelectionMode::Cell => (cell, cell),
+            SelectionMode::Word => renderer.get_word_bounds(cell, self.word_characters.as_str()),
+            SelectionMode::Line => {
+                let (start, end) = renderer.get_line_bounds(cell.y);
+                let width = renderer.get_size().x;
+                (Vector2::new(0, start), Vector2::new(width.saturating_sub(1), end))
+            },
+        };
+        (renderer.get_line_position(start), renderer.get_line_position(end))
+    }
+
+    // Characters besides alphanumerics that are selected as part of a word
+    pub fn set_word_characters(&mut self, word_characters: &str) {
+        self.word_characters = word_characters.to_owned();
+    }
+
+    fn copy_selection(&mut self) {
+        let Some(text) = self.get_selection_text() else {
+            return;
+        };
+        let Some(clipboard) = self.clipboard.as_mut() else {
+            log::warn!("Cannot copy selection since clipboard is unavailable");
+            return;
+        };
+        if let Err(err) = clipboard.set_text(text) {
+            log::error!("Failed to copy selection to clipboard: {:?}", err);
+        }
+    }
+
+    // Read from the display since the renderer only has the rows that are on screen
+    fn get_selection_text(&self) -> Option<String> {
+        let (start, end) = self.selection?.get_bounds();
+        let display = self.get_active_pane().terminal.get_display();
+        Some(display.get_selection_text(start, end))
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn copy_selection_to_primary(&mut self) {
+        use arboard::{LinuxClipboardKind, SetExtLinux};
+        let Some(text) = self.get_selection_text() else {
+            return;
+        };
+        let Some(clipboard) = self.clipboard.as_mut() else {
+            return;
+        };
+        if let Err(err) = clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text) {
+            log::error!("Failed to copy selection to primary selection: {:?}", err);
+        }
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    fn copy_selection_to_primary(&mut self) {}
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn paste_primary_selection(&mut self) {
+        use arboard::{GetExtLinux, LinuxClipboardKind};
+        let Some(clipboard) = self.clipboard.as_mut() else {
+            log::warn!("Cannot paste primary selection since clipboard is unavailable");
+            return;
+        };
+        match clipboard.get().clipboard(LinuxClipboardKind::Primary).text() {
+            Ok(text) => self.send_user_event(TerminalUserEvent::PasteText(text)),
+            Err(err) => log::error!("Failed to paste from primary selection: {:?}", err),
+        }
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    fn paste_primary_selection(&mut self) {}
+
+    fn paste_clipboard(&mut self) {
+        let Some(clipboard) = self.clipboard.as_mut() else {
+            log::warn!("Cannot paste since clipboard is unavailable");
+            return;
+        };
+        match clipboard.get_text() {
+            Ok(text) => self.send_user_event(TerminalUserEvent::PasteText(text)),
+            Err(err) => log::error!("Failed to paste from clipboard: {:?}", err),
+        }
+    }
+
+    // Sent as a paste so bracketed paste mode applies
+    fn paste_dropped_paths(&mut self) {
+        if self.dropped_paths.is_empty() {
+            return;
+        }
+        let text = get_dropped_paths_text(self.dropped_paths.as_slice());
+        self.dropped_paths.clear();
+        self.send_user_event(TerminalUserEvent::PasteText(text));
+    }
+
+    fn on_focus(&mut self, is_focus: bool) {
+        self.send_user_event(TerminalUserEvent::WindowFocus(is_focus));
+        self.is_focused = is_focus;
+        self.renderer.update_focus(&self.wgpu_queue, self.is_focused, self.unfocused_dim);
+        self.trigger_redraw();
+    }
+
+    // Brightness of the grid when the window is unfocused where 1.0 disables dimming
+    pub fn set_unfocused_dim(&mut self, unfocused_dim: f32) {
+        self.unfocused_dim = unfocused_dim;
+        self.renderer.update_focus(&self.wgpu_queue, self.is_focused, self.unfocused_dim);
+        self.trigger_redraw();
+    }
+
+    fn on_scale_factor_changed(&mut self, scale_factor: f32) {
+        if self.dpi_scale_override.is_some() {
+            return;
+        }
+        self.dpi_scale = scale_factor;
+        self.update_dpi_scale();
+    }
+
+    // Overrides the scale factor reported by the window if some
+    pub fn set_dpi_scale_override(&mut self, dpi_scale: Option<f32>) {
+        self.dpi_scale_override = dpi_scale;
+        self.dpi_scale = dpi_scale.unwrap_or(self.winit_window.scale_factor() as f32);
+        self.update_dpi_scale();
+    }
+
+    // Glyphs scaled in the shader are blurry so they are rasterised again at any new scale
+    fn update_dpi_scale(&mut self) {
+        if self.dpi_scale == self.glyph_dpi_scale {
+            return;
+        }
+        log::info!("Regenerating glyphs for dpi scale {:.2}", self.dpi_scale);
+        self.set_font_size(self.font_size);
+        // glyphs are only scaled if the atlas couldn't be created at the new size
+        if self.dpi_scale != self.glyph_dpi_scale {
+            self.renderer.set_font_scale(&self.wgpu_queue, self.dpi_scale / self.glyph_dpi_scale);
+            self.trigger_redraw();
+        }
+    }
+
+    fn on_mouse_wheel(&mut self, delta: winit::event::MouseScrollDelta) {
+        use winit::event::MouseScrollDelta as Delta;
+        let cell_height = self.glyph_cache.get_glyph_atlas().get_glyph_size().y;
+        let total_pixels = match delta {
+            Delta::LineDelta(_x, y) => y*cell_height as f32,
+            Delta::PixelDelta(delta) => delta.y as f32,
+        };
+        let total_pixels_abs = total_pixels.abs().round() as usize;
+        if total_pixels > 0.0 {
+            self.get_active_pane_mut().renderer.scroll_up_pixels(total_pixels_abs, cell_height);
+        } else {
+            self.get_active_pane_mut().renderer.scroll_down_pixels(total_pixels_abs, cell_height);
+        }
+        self.last_scroll_instant = Some(std::time::Instant::now());
+        self.trigger_redraw();
+    }
+
+    pub fn on_resize(&mut self, new_size: Vector2<usize>) {
+        let new_size = Vector2::new(new_size.x.max(1), new_size.y.max(1));
+        self.wgpu_config.width = new_size.x as u32;
+        self.wgpu_config.height = new_size.y as u32;
+        self.wgpu_surface.configure(&self.wgpu_device, &self.wgpu_config);
+        // calculate new terminal grid size
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let grid_area = calculate_grid_area(new_size, self.padding, self.get_tab_bar_height());
+        let (new_grid_size, _) = calculate_grid_size(grid_area, glyph_size);
+        self.grid_size = new_grid_size;
+        // inactive tabs are resized too so they are ready when switched to
+        for tab in &self.tabs {
+            for pane in &tab.panes {
+                let rect = tab.layout.get_pane_rect(new_grid_size, pane.id);
+                let grid_size = rect.map(|rect| rect.size).unwrap_or(new_grid_size);
+                let grid_size = Vector2::new(grid_size.x.max(1), grid_size.y.max(1));
+                let window_size = grid_size.mul_element_wise(glyph_size);
+                let user_events = pane.terminal.get_user_event_handler();
+                user_events.send(TerminalUserEvent::Resize { window_size, grid_size }).unwrap();
+            }
+        }
+        self.selection = None;
+        self.trigger_redraw();
+    }
+
+    // Tabs are resized straight away in case the platform resizes the window later or ignores the request
+    pub fn request_grid_size(&mut self, grid_size: Vector2<usize>) {
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let window_size = calculate_window_size(grid_size, glyph_size, self.padding, self.get_tab_bar_height());
+        let new_size = self.winit_window
+            .request_inner_size(PhysicalSize::new(window_size.x as u32, window_size.y as u32))
+            .map(|size| Vector2::new(size.width as usize, size.height as usize))
+            .unwrap_or(window_size);
+        self.on_resize(new_size);
+    }
+
+    pub fn set_max_fps(&mut self, max_fps: Option<f64>) {
+        self.max_fps = max_fps;
+    }
+
+    pub fn set_padding(&mut self, padding: Vector2<usize>) {
+        self.padding = padding;
+        let window_size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        self.on_resize(window_size);
+    }
+
+    pub fn set_keyboard_shortcuts(&mut self, keyboard_shortcuts: KeyboardShortcuts) {
+        self.keyboard_shortcuts = keyboard_shortcuts;
+    }
+
+    pub fn set_alternate_screen_scroll_keys(&mut self, alternate_screen_scroll_keys: bool) {
+        self.alternate_screen_scroll_keys = alternate_screen_scroll_keys;
+    }
+
+    pub fn set_allow_window_control(&mut self, allow_window_control: bool) {
+        self.allow_window_control = allow_window_control;
+    }
+
+    pub fn set_minimum_contrast(&mut self, ratio: f32) {
+        self.minimum_contrast = ratio;
+        for pane in self.tabs.iter_mut().flat_map(|tab| tab.panes.iter_mut()) {
+            pane.renderer.set_minimum_contrast(ratio);
+        }
+    }
+
+    // Uses foreground colour of the cell under the cursor if none
+    pub fn set_cursor_colour(&mut self, colour: Option<Rgb8>) {
+        self.cursor_colour = colour;
+        for pane in self.tabs.iter_mut().flat_map(|tab| tab.panes.iter_mut()) {
+            pane.renderer.set_default_cursor_colour(colour);
+        }
+        self.trigger_redraw();
+    }
+
+    // Inverts selected cells if none
+    pub fn set_selection_colour(&mut self, colour: Option<Rgb8>) {
+        self.selection_colour = colour;
+        self.trigger_redraw();
+    }
+
+    pub fn set_builtin_box_drawing(&mut self, builtin_box_drawing: bool) {
+        self.glyph_cache.set_builtin_box_drawing(builtin_box_drawing);
+        self.trigger_redraw();
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32) {
+        // glyph atlas is recreated at the current dpi scale
+        if !self.glyph_cache.set_font_size(font_size*self.dpi_scale) {
+            log::warn!("Ignoring invalid font size: {} at dpi scale {:.2}", font_size, self.dpi_scale);
+            return;
+        }
+        log::info!("Setting font size to {} at dpi scale {:.2}", font_size, self.dpi_scale);
+        self.font_size = font_size;
+        self.glyph_dpi_scale = self.dpi_scale;
+        self.renderer.set_font_scale(&self.wgpu_queue, 1.0);
+        // recompute grid with new glyph size
+        let window_size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        self.on_resize(window_size);
+    }
+
+    fn on_redraw_requested(&mut self) {
+        use std::time::Instant;
+        self.is_redraw_requested = false;
+        self.last_frame_instant = Instant::now();
+        if let Some(gpu_render) = self.renderer.poll_gpu_time(&self.wgpu_device) {
+            self.render_stats.push_gpu_render(gpu_render);
+        }
+        let grid_start = Instant::now();
+        self.update_grid_from_terminal();
+        let atlas_start = Instant::now();
+        self.update_atlas();
+        self.update_tab_bar();
+        self.update_bell_flash();
+        let atlas_end = Instant::now();
+        let frame = self.wgpu_surface.get_current_texture().expect("Failed to acquire next swap chain texture");
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let submit_start = Instant::now();
+        let mut encoder = self.wgpu_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("render_text_commands"),
+        });
+        self.renderer.generate_commands(&mut encoder, &view, &self.wgpu_device);
+        self.wgpu_queue.submit(Some(encoder.finish()));
+        self.renderer.request_gpu_time();
+        let submit_end = Instant::now();
+        frame.present();
+        self.render_stats.push_frame(FrameTimings {
+            grid_update: atlas_start - grid_start,
+            atlas_upload: atlas_end - atlas_start,
+            queue_submit: submit_end - submit_start,
+        });
+        self.log_render_stats();
+        // keep redrawing while the scrollbar fades out, the bell is showing or a prompt is highlighted
+        if self.last_scroll_instant.is_some() || self.bell_flash_instant.is_some() || self.prompt_highlight.is_some() {
+            self.trigger_redraw();
+        }
+    }
+
+    fn update_bell_flash(&mut self) {
+        let is_flash = self.bell_flash_instant.is_some_and(|instant| instant.elapsed() < VISUAL_BELL_DURATION);
+        if !is_flash {
+            self.bell_flash_instant = None;
+        }
+        self.renderer.update_bell_flash(&self.wgpu_queue, if is_flash { 1.0 } else { 0.0 });
+    }
+
+    fn log_render_stats(&mut self) {
+        let elapsed = self.last_render_stats_instant.elapsed().as_secs_f32();
+        if elapsed < RENDER_STATS_LOG_INTERVAL_SECONDS {
+            return;
+        }
+        log::debug!("Render stats {} atlas_memory={}KB atlas_utilization={:.1}%", 
+            self.render_stats, self.glyph_cache.atlas_memory_bytes()/1024, self.glyph_cache.atlas_utilization()*100.0);
+        self.render_stats.reset();
+        self.last_render_stats_instant = std::time::Instant::now();
+    }
+
+    fn update_scrollbar(&mut self, grid_size: Vector2<usize>, glyph_size: Vector2<usize>) {
+        let alpha = match self.last_scroll_instant {
+            Some(instant) => {
+                let elapsed = instant.elapsed().as_secs_f32();
+                let fade = (elapsed - SCROLLBAR_FADE_DELAY_SECONDS) / SCROLLBAR_FADE_DURATION_SECONDS;
+                1.0 - fade.clamp(0.0, 1.0)
+            },
+            None => 0.0,
+        };
+        if alpha <= 0.0 {
+            self.last_scroll_instant = None;
+        }
+        // scrollbar is hidden for alternate buffer since it has no scrollback
+        let thumb = self.get_active_pane().renderer
+            .get_scroll_status()
+            .and_then(|status| calculate_scrollbar_thumb(
+                status.scroll_offset, status.total_lines, status.viewport_lines, SCROLLBAR_MIN_LENGTH,
+            ));
+        let thumb = match thumb {
+            Some(thumb) if alpha > 0.0 => thumb,
+            _ => {
+                let empty = Vector4::new(0.0, 0.0, 0.0, 0.0);
+                self.renderer.update_scrollbar(&self.wgpu_queue, empty, empty);
+                return;
+            },
+        };
+        let window_size = Vector2::new(self.wgpu_config.width as f32, self.wgpu_config.height as f32);
+        let track_size = grid_size.mul_element_wise(glyph_size).cast::<f32>().unwrap().div_element_wise(window_size);
+        let track_offset = self.get_pane_origin().cast::<f32>().unwrap().div_element_wise(window_size);
+        let rect = Vector4::new(
+            track_offset.x + track_size.x - SCROLLBAR_WIDTH_PIXELS/window_size.x,
+            track_offset.y + thumb.start*track_size.y,
+            track_offset.x + track_size.x,
+            track_offset.y + thumb.end*track_size.y,
+        );
+        let mut colour = SCROLLBAR_COLOUR;
+        colour.w *= alpha;
+        self.renderer.update_scrollbar(&self.wgpu_queue, rect, colour);
+    }
+
+    fn draw_prompt_highlight(&mut self, size: Vector2<usize>) {
+        let Some((line, instant)) = self.prompt_highlight else {
+            return;
+        };
+        if instant.elapsed() >= PROMPT_HIGHLIGHT_DURATION {
+            self.prompt_highlight = None;
+            return;
+        }
+        let Some(row) = self.get_active_pane().renderer.get_line_render_row(line) else {
+            return;
+        };
+        let start_index = (row*size.x).min(self.glyph_grid.len());
+        let end_index = (start_index + size.x).min(self.glyph_grid.len());
+        for cell in &mut self.glyph_grid[start_index..end_index] {
+            cell.set_inverse(!cell.is_inverse());
+        }
+    }
+
+    fn update_grid_from_terminal(&mut self) {
+        self.current_frame += 1;
+        if let Some(tree) = self.accessibility_tree.as_mut() {
+            tree.mark_changed(std::time::Instant::now());
+        }
+        self.frame_counter.update();
+        let total_bytes_read = self.get_active_pane().terminal.get_total_bytes_read();
+        self.throughput_counter.update(total_bytes_read);
+        // avoid stalling the parser by rendering the front buffer unless scrolled back
+        let pane = self.tabs[self.active_tab].get_active_pane_mut();
+        let front_buffer = pane.terminal.get_front_buffer();
+        let mut background_colour = front_buffer.get_default_background_colour();
+        let default_pen = front_buffer.get_default_pen();
+        let is_rendered = pane.renderer.render_front_buffer(&front_buffer);
+        drop(front_buffer);
+        if !is_rendered {
+            let display = pane.terminal.get_display();
+            pane.renderer.render_display(&display);
+            background_colour = display.get_default_background_colour();
+        }
+        // cell under the mouse moves to other text when the view scrolls while dragging
+        if self.is_selecting {
+            self.extend_selection_to_mouse();
+        }
+        // urls are only detected while ctrl is held
+        self.hovered_url = None;
+        if self.modifiers.control_key() {
+            let cell = self.get_cell_under_mouse();
+            let pane = self.tabs[self.active_tab].get_active_pane_mut();
+            let display = pane.terminal.get_display();
+            self.url_cache.update(&pane.renderer, &display);
+            self.hovered_url = self.url_cache.get_url_at(cell).cloned();
+        }
+        self.renderer.set_clear_colour(Vector3::new(background_colour.r, background_colour.g, background_colour.b));
+ 
+        let pane = self.tabs[self.active_tab].get_active_pane();
+        let size = pane.renderer.get_size();
+        let size = Vector2::new(size.x, pane.renderer.get_total_render_rows());
+        let cells = pane.renderer.get_cells();
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        update_glyph_grid(&mut self.glyph_grid, cells, size, &mut self.glyph_cache, self.current_frame);
+        if let Some(url) = self.hovered_url.as_ref() {
+            let start_index = url.start.y*size.x + url.start.x;
+            let end_index = (url.end.y*size.x + url.end.x + 1).min(self.glyph_grid.len());
+            for cell in &mut self.glyph_grid[start_index.min(end_index)..end_index] {
+                cell.set_underline(UnderlineStyle::Straight, None);
+            }
+        }
+        if let Some(selection) = self.selection {
+            let (start, end) = selection.get_bounds();
+            let row_positions = self.tabs[self.active_tab].get_active_pane().renderer.get_row_positions();
+            let cells = row_positions.iter().take(size.y).enumerate().flat_map(|(y, &row_position)| {
+                get_selected_columns(row_position, size.x, start, end).map(move |x| y*size.x + x)
+            });
+            for index in cells {
+                let cell = &mut self.glyph_grid[index];
+                match self.selection_colour {
+                    Some(colour) => {
+                        // inverted cells would draw the selection colour as text
+                        if cell.is_inverse() {
+                            cell.set_foreground_colour(cell.get_background_colour());
+                            cell.set_inverse(false);
+                        }
+                        cell.set_background_colour(Vector3::new(colour.r, colour.g, colour.b));
+                    },
+                    None => cell.set_inverse(!cell.is_inverse()),
+                }
+            }
+        }
+        self.draw_prompt_highlight(size);
+        let cursor_position = self.get_active_pane().renderer.get_cursor().map(|cursor| cursor.position);
+        if let Some(cursor_position) = cursor_position {
+            self.draw_ime_preedit(cursor_position, size);
+        }
+        if self.is_debug_overlay {
+            self.draw_debug_overlay(size, default_pen);
+        }
+        self.update_ime_cursor_area(cursor_position, glyph_size);
+        // active pane is drawn at its position in the grid that the panes of a split tab are composed into
+        let pane_position = self.get_active_pane_rect().position;
+        let (grid, size) = if self.is_split() {
+            self.compose_panes(size, default_pen);
+            (self.split_grid.as_slice(), self.grid_size)
+        } else {
+            (self.glyph_grid.as_slice(), size)
+        };
+        self.renderer.update_grid(&self.wgpu_device, &self.wgpu_queue, grid, size);
+        self.update_images(pane_position);
+        let cursor = self.get_active_pane().renderer.get_cursor().as_ref().map(get_cursor_data);
+        self.renderer.update_cursor(&self.wgpu_queue, cursor);
+        // rendered cells may have changed or scrolled under the mouse
+        self.update_hovered_hyperlink();
+        let hover = self.hovered_hyperlink.map(|link| (link.start + pane_position, link.end + pane_position));
+        self.renderer.update_hover(&self.wgpu_queue, hover);
+        // scale grid to pixels and shift by partial scroll amount
+        // grid is moved to its origin past the padding and below the tab bar
+        let window_size = Vector2::new(self.wgpu_config.width as f32, self.wgpu_config.height as f32);
+        let render_size = size.mul_element_wise(glyph_size).cast::<f32>().unwrap();
+        let render_scale = render_size.div_element_wise(window_size);
+        let grid_origin = self.get_grid_origin().cast::<f32>().unwrap();
+        let pixel_offset = Vector2::new(0.0, self.get_pixel_offset() as f32) - grid_origin;
+        let scroll_offset = pixel_offset.div_element_wise(window_size);
+        self.renderer.update_render_scale(&self.wgpu_queue, render_scale);
+        self.renderer.update_scroll_offset(&self.wgpu_queue, scroll_offset);
+        self.update_scrollbar(self.get_active_pane().renderer.get_size(), glyph_size);
+        self.update_tab_bar_cells(default_pen);
+    }
+
+    // Background panes are rendered here since only the active pane is rendered before its overlays are drawn
+    fn compose_panes(&mut self, active_size: Vector2<usize>, default_pen: Pen) {
+        let grid_size = self.grid_size;
+        let total_glyphs_in_block = self.glyph_cache.get_glyph_atlas().get_total_glyphs_in_block();
+        let glyph = self.glyph_cache.get_glyph_location(' ', self.current_frame);
+        let mut blank = CellData::default();
+        blank.set_atlas_index(glyph.get_atlas_position(total_glyphs_in_block, 0), glyph.layer);
+        set_cell_data_pen(&mut blank, &default_pen);
+        // panes that haven't been resized to their area yet leave blank cells
+        self.split_grid.clear();
+        self.split_grid.resize(grid_size.x*grid_size.y, blank);
+        let tab = &mut self.tabs[self.active_tab];
+        let active_id = tab.get_active_pane().id;
+        for (id, rect) in tab.layout.get_pane_rects(grid_size) {
+            if id == active_id {
+                copy_pane_cells(self.split_grid.as_mut_slice(), grid_size.x, self.glyph_grid.as_slice(), active_size, rect);
+                continue;
+            }
+            let Some(pane) = tab.panes.iter_mut().find(|pane| pane.id == id) else {
+                continue;
+            };
+            let front_buffer = pane.terminal.get_front_buffer();
+            let is_rendered = pane.renderer.render_front_buffer(&front_buffer);
+            drop(front_buffer);
+            if !is_rendered {
+                let display = pane.terminal.get_display();
+                pane.renderer.render_display(&display);
+            }
+            let size = Vector2::new(pane.renderer.get_size().x, pane.renderer.get_total_render_rows());
+            update_glyph_grid(&mut self.pane_glyph_grid, pane.renderer.get_cells(), size, &mut self.glyph_cache, self.current_frame);
+            // only the pane that receives input shows its cursor
+            for cell in &mut self.pane_glyph_grid {
+                cell.set_cursor(false);
+            }
+            copy_pane_cells(self.split_grid.as_mut_slice(), grid_size.x, self.pane_glyph_grid.as_slice(), size, rect);
+        }
+        for divider in tab.layout.get_dividers(grid_size) {
+            let glyph = self.glyph_cache.get_glyph_location(get_divider_character(divider), self.current_frame);
+            let mut cell = blank;
+            cell.set_atlas_index(glyph.get_atlas_position(total_glyphs_in_block, 0), glyph.layer);
+            for y in divider.position.y..divider.position.y+divider.size.y {
+                let start = y*grid_size.x + divider.position.x;
+                self.split_grid[start..start+divider.size.x].fill(cell);
+            }
+        }
+    }
+
+    // Images are uploaded when they scroll into view and freed once they scroll out of it
+    // Only images of the active pane are drawn since image ids are only unique within a terminal
+    fn update_images(&mut self, pane_position: Vector2<usize>) {
+        let images = self.tabs[self.active_tab].get_active_pane().renderer.get_images();
+        self.renderer.retain_images(|id| images.iter().any(|image| image.id == id));
+        for image in images {
+            if !self.renderer.has_image(image.id) {
+                let pixels = image.image.pixels.as_slice();
+                self.renderer.upload_image(&self.wgpu_device, &self.wgpu_queue, image.id, image.image.size, pixels);
+            }
+        }
+        let quads: Vec<ImageQuad> = images
+            .iter()
+            .map(|image| ImageQuad {
+                id: image.id,
+                position: Vector2::new((image.column + pane_position.x) as isize, image.row + pane_position.y as isize),
+                size: image.cells,
+            })
+            .collect();
+        self.renderer.update_images(&self.wgpu_queue, quads.as_slice());
+    }
+
+    fn update_atlas(&mut self) {
+        let glyph_atlas = self.glyph_cache.get_glyph_atlas_mut();
+        self.renderer.update_atlas(&self.wgpu_device, &self.wgpu_queue, glyph_atlas);
+    }
+
+    fn on_shortcut_action(&mut self, action: ShortcutAction) {
+        const FONT_SIZE_STEP: f32 = 1.0;
+        let font_size = self.font_size;
+        match action {
+            ShortcutAction::Copy => self.copy_selection(),
+            ShortcutAction::Paste => self.paste_clipboard(),
+            ShortcutAction::ScrollUp | ShortcutAction::ScrollDown => {
+                let size = self.get_active_pane().renderer.get_size();
+                if action == ShortcutAction::ScrollUp {
+                    self.get_active_pane_mut().renderer.scroll_up(size.y);
+                } else {
+                    self.get_active_pane_mut().renderer.scroll_down(size.y);
+                }
+                self.last_scroll_instant = Some(std::time::Instant::now());
+                self.trigger_redraw();
+            },
+            ShortcutAction::ScrollToTop | ShortcutAction::ScrollToBottom => {
+                if action == ShortcutAction::ScrollToTop {
+                    self.get_active_pane_mut().renderer.scroll_to_top();
+                } else {
+                    self.get_active_pane_mut().renderer.scroll_to_bottom();
+                }
+                self.last_scroll_instant = Some(std::time::Instant::now());
+                self.trigger_redraw();
+            },
+            ShortcutAction::FontSizeIncrease => self.set_font_size(font_size + FONT_SIZE_STEP),
+            ShortcutAction::FontSizeDecrease => self.set_font_size(font_size - FONT_SIZE_STEP),
+            ShortcutAction::FontSizeReset => self.set_font_size(self.default_font_size),
+            ShortcutAction::Screenshot => self.save_screenshot(),
+            ShortcutAction::ClearScrollback => {
+                self.send_user_event(TerminalUserEvent::ClearScrollback);
+            },
+            ShortcutAction::ToggleFullscreen => self.toggle_fullscreen(),
+            ShortcutAction::NewTab => {
+                let Some(process_factory) = self.process_factory.as_ref() else {
+                    log::warn!("Cannot open new tab since no process factory was provided");
+                    return;
+                };
+                let result = process_factory();
+                if let Err(err) = result.and_then(|process| self.new_tab(process)) {
+                    log::error!("Failed to open new tab: {:?}", err);
+                }
+            },
+            ShortcutAction::CloseTab => self.close_tab(self.active_tab),
+            ShortcutAction::NextTab => self.switch_tab(get_adjacent_tab(self.active_tab, self.tabs.len(), true)),
+            ShortcutAction::PreviousTab => self.switch_tab(get_adjacent_tab(self.active_tab, self.tabs.len(), false)),
+            ShortcutAction::SplitVertical | ShortcutAction::SplitHorizontal => {
+                let Some(process_factory) = self.process_factory.as_ref() else {
+                    log::warn!("Cannot split pane since no process factory was provided");
+                    return;
+                };
+                let direction = if action == ShortcutAction::SplitVertical {
+                    SplitDirection::Vertical
+                } else {
+                    SplitDirection::Horizontal
+                };
+                let result = process_factory();
+                if let Err(err) = result.and_then(|process| self.split_pane(process, direction)) {
+                    log::error!("Failed to split pane: {:?}", err);
+                }
+            },
+            ShortcutAction::ToggleDebugOverlay => {
+                self.is_debug_overlay = !self.is_debug_overlay;
+                self.trigger_redraw();
+            },
+            ShortcutAction::ScrollToPreviousPrompt | ShortcutAction::ScrollToNextPrompt => {
+                let pane = self.tabs[self.active_tab].get_active_pane_mut();
+                let prompt_lines: Vec<usize> = pane.terminal.get_display()
+                    .get_prompt_marks()
+                    .iter()
+                    .filter(|position| position.mark == PromptMark::PromptStart)
+                    .map(|position| position.line)
+                    .collect();
+                let line = if action == ShortcutAction::ScrollToPreviousPrompt {
+                    pane.renderer.scroll_to_previous_prompt(prompt_lines.as_slice())
+                } else {
+                    pane.renderer.scroll_to_next_prompt(prompt_lines.as_slice())
+                };
+                let Some(line) = line else {
+                    return;
+                };
+                self.prompt_highlight = Some((line, std::time::Instant::now()));
+                self.last_scroll_instant = Some(std::time::Instant::now());
+                self.trigger_redraw();
+            },
+            ShortcutAction::CopyLastCommandOutput => self.copy_last_command_output(),
+        }
+    }
+
+    fn copy_last_command_output(&mut self) {
+        let Some(text) = self.get_active_pane().terminal.get_display().get_last_command_output() else {
+            log::info!("No finished command output to copy");
+            return;
+        };
+        let Some(clipboard) = self.clipboard.as_mut() else {
+            log::warn!("Cannot copy command output since clipboard is unavailable");
+            return;
+        };
+        if let Err(err) = clipboard.set_text(text) {
+            log::error!("Failed to copy command output to clipboard: {:?}", err);
+        }
+    }
+
+    fn save_screenshot(&mut self) {
+        self.update_grid_from_terminal();
+        self.update_atlas();
+        self.update_tab_bar();
+        let size = Vector2::new(self.wgpu_config.width as usize, self.wgpu_config.height as usize);
+        let pixels = self.renderer.render_to_buffer(&self.wgpu_device, &self.wgpu_queue, size);
+        let filename = crate::screenshot::get_default_filename();
+        match crate::screenshot::save_png(filename.as_str(), size, pixels.as_slice()) {
+            Ok(()) => log::info!("Saved screenshot to {}", filename),
+            Err(err) => log::error!("Failed to save screenshot to {}: {}", filename, err),
+        }
+    }
+
+    fn on_ime(&mut self, ime: Ime) {
+        match ime {
+            Ime::Enabled => {},
+            Ime::Preedit(text, _) => {
+                self.ime_preedit = if text.is_empty() { None } else { Some(text) };
+                self.trigger_redraw();
+            },
+            Ime::Commit(text) => {
+                self.ime_preedit = None;
+                self.send_user_event(TerminalUserEvent::InputText(text));
+                self.get_active_pane_mut().renderer.scroll_to_bottom();
+                self.trigger_redraw();
+            },
+            Ime::Disabled => {
+                self.ime_preedit = None;
+                self.trigger_redraw();
+            },
+        }
+    }
+
+    // Draws the composition underlined from the cursor and clips it at the end of the row
+    fn draw_ime_preedit(&mut self, cursor: Vector2<usize>, size: Vector2<usize>) {
+        let Some(preedit) = self.ime_preedit.as_ref() else {
+            return;
+        };
+        if cursor.x >= size.x || cursor.y >= size.y {
+            return;
+        }
+        let total_glyphs_in_block = self.glyph_cache.get_glyph_atlas().get_total_glyphs_in_block();
+        let mut x = cursor.x;
+        for c in preedit.chars() {
+            let glyph = self.glyph_cache.get_glyph_location(c, self.current_frame);
+            for span_index in 0..glyph.span {
+                if x >= size.x {
+                    return;
+                }
+                let cell = &mut self.glyph_grid[cursor.y*size.x + x];
+                cell.set_atlas_index(glyph.get_atlas_position(total_glyphs_in_block, span_index), glyph.layer);
+                cell.set_underline(UnderlineStyle::Straight, None);
+                cell.set_hidden(false);
+                x += 1;
+            }
+        }
+    }
+
+    // Stats are drawn over the grid with inverted default colours without touching the terminal
+    fn draw_debug_overlay(&mut self, size: Vector2<usize>, default_pen: Pen) {
+        let atlas_memory_bytes = self.glyph_cache.atlas_memory_bytes();
+        let atlas_utilization = self.glyph_cache.atlas_utilization();
+        let pane = self.tabs[self.active_tab].get_active_pane();
+        let scrollback_lines = pane.terminal.get_front_buffer().get_scrollback_line_count();
+        let stats = DebugStats {
+            fps: self.frame_counter.get_fps(),
+            frame_time_ms: self.frame_counter.get_frame_time_ms(),
+            grid_size: pane.renderer.get_size(),
+            atlas_utilization,
+            atlas_memory_bytes,
+            scrollback_lines,
+            read_bytes_per_second: self.throughput_counter.get_bytes_per_second(),
+        };
+        let foreground_colour = default_pen.background_colour;
+        let background_colour = default_pen.foreground_colour;
+        let foreground_colour = Vector3::new(foreground_colour.r, foreground_colour.g, foreground_colour.b);
+        let background_colour = Vector3::new(background_colour.r, background_colour.g, background_colour.b);
+        let total_glyphs_in_block = self.glyph_cache.get_glyph_atlas().get_total_glyphs_in_block();
+        for (position, c) in get_overlay_cells(get_debug_lines(&stats).as_slice(), size) {
+            let glyph = self.glyph_cache.get_glyph_location(c, self.current_frame);
+            let cell = &mut self.glyph_grid[position.y*size.x + position.x];
+            cell.set_atlas_index(glyph.get_atlas_position(total_glyphs_in_block, 0), glyph.layer);
+            cell.set_foreground_colour(foreground_colour);
+            cell.set_background_colour(background_colour);
+            cell.set_underline(UnderlineStyle::None, None);
+            cell.set_inverse(false);
+            cell.set_hidden(false);
+        }
+    }
+
+    // Places the candidate window below the cursor cell
+    fn update_ime_cursor_area(&mut self, cursor: Option<Vector2<usize>>, glyph_size: Vector2<usize>) {
+        let Some(cursor) = cursor else {
+            return;
+        };
+        // area moves with the font size, padding and tab bar even if the cursor cell doesn't
+        let pixel_offset = self.get_pixel_offset();
+        let grid_origin = self.get_pane_origin();
+        let position = Vector2::new(
+            cursor.x*glyph_size.x + grid_origin.x,
+            (cursor.y*glyph_size.y).saturating_sub(pixel_offset) + grid_origin.y,
+        );
+        let area = (position, glyph_size);
+        if self.ime_cursor_area == Some(area) {
+            return;
+        }
+        self.ime_cursor_area = Some(area);
+        self.winit_window.set_ime_cursor_area(
+            PhysicalPosition::new(position.x as u32, position.y as u32),
+            PhysicalSize::new(glyph_size.x as u32, glyph_size.y as u32),
+        );
+    }
+
+    fn on_keyboard_input(&mut self, event: winit::event::KeyEvent) {
+        use vt100::encoder::{KeyCode as TKey, ModifierKey, ArrowKey, FunctionKey};
+
+        if event.state == ElementState::Pressed {
+            if let PhysicalKey::Code(code) = event.physical_key {
+                if let Some(action) = self.keyboard_shortcuts.get_action(self.modifiers.into(), code) {
+                    let is_alternate_screen = self.get_active_pane_mut().terminal.get_display().is_alternate_viewport();
+                    let target = get_shortcut_target(action, is_alternate_screen, self.alternate_screen_scroll_keys);
+                    if target == ShortcutTarget::Window {
+                        return self.on_shortcut_action(action);
+                    }
+                }
+            }
+        }
+
+        // panes held open after their process exits are closed by any key except modifiers
+        if self.get_active_pane().is_exited {
+            if event.state == ElementState::Pressed && !is_modifier_key(event.physical_key) {
+                self.close_pane(self.active_tab, self.get_active_tab().active_pane);
+            }
+            return;
+        }
+
+        let user_events = self.get_active_pane().terminal.get_user_event_handler();
+        let key_press = &mut |key: TKey| user_events.send(TerminalUserEvent::KeyPress(key)).unwrap();
+        let key_release = &mut |key: TKey| user_events.send(TerminalUserEvent::KeyRelease(key)).unwrap();
+        // modifier keys listen to press/release
+        if let PhysicalKey::Code(code) = event.physical_key {
+            match event.state {
+                ElementState::Pressed => match code {
+                    KeyCode::AltLeft      => return key_press(TKey::ModifierKey(ModifierKey::Alt)),
+                    KeyCode::AltRight     => return key_press(TKey::ModifierKey(ModifierKey::Alt)),
+                    KeyCode::ControlLeft  => return key_press(TKey::ModifierKey(ModifierKey::Ctrl)),
+                    KeyCode::ControlRight => return key_press(TKey::ModifierKey(ModifierKey::Ctrl)),
+                    KeyCode::ShiftLeft    => return key_press(TKey::ModifierKey(ModifierKey::Shift)),
+                    KeyCode::ShiftRight   => return key_press(TKey::ModifierKey(ModifierKey::Shift)),
+                    _ => {},
+                },
+                ElementState::Released => match code {
+                    KeyCode::AltLeft      => return key_release(TKey::ModifierKey(ModifierKey::Alt)),
+                    KeyCode::AltRight     => return key_release(TKey::ModifierKey(ModifierKey::Alt)),
+                    KeyCode::ControlLeft  => return key_release(TKey::ModifierKey(ModifierKey::Ctrl)),
+                    KeyCode::ControlRight => return key_release(TKey::ModifierKey(ModifierKey::Ctrl)),
+                    KeyCode::ShiftLeft    => return key_release(TKey::ModifierKey(ModifierKey::Shift)),
+                    KeyCode::ShiftRight   => return key_release(TKey::ModifierKey(ModifierKey::Shift)),
+                    _ => {},
+                },
+            };
+        };
+
+        if event.state != ElementState::Pressed {
+            return;
+        }
+
+        if let PhysicalKey::Code(code) = event.physical_key {
+            match code {
+                KeyCode::ArrowUp    => return key_press(TKey::ArrowKey(ArrowKey::Up)),
+                KeyCode::ArrowDown  => return key_press(TKey::ArrowKey(ArrowKey::Down)),
+                KeyCode::ArrowLeft  => return key_press(TKey::ArrowKey(ArrowKey::Left)),
+                KeyCode::ArrowRight => return key_press(TKey::ArrowKey(ArrowKey::Right)),
+                _ => {},
+            }
+        }
+
+        if let PhysicalKey::Code(code) = event.physical_key {
+            match code {
+                KeyCode::Escape    => return key_press(TKey::FunctionKey(FunctionKey::Escape)),
+                KeyCode::Tab       => return key_press(TKey::FunctionKey(FunctionKey::Tab)),
+                KeyCode::Backspace => return key_press(TKey::FunctionKey(FunctionKey::Backspace)),
+                KeyCode::Enter     => return key_press(TKey::FunctionKey(FunctionKey::Enter)),
+                KeyCode::Delete    => return key_press(TKey::FunctionKey(FunctionKey::Delete)),
+                KeyCode::Home      => return key_press(TKey::FunctionKey(FunctionKey::Home)),
+                KeyCode::End       => return key_press(TKey::FunctionKey(FunctionKey::End)),
+                KeyCode::PageUp    => return key_press(TKey::FunctionKey(FunctionKey::PageUp)),
+                KeyCode::PageDown  => return key_press(TKey::FunctionKey(FunctionKey::PageDown)),
+                _ => {},
+            }
+        }
+
+        if event.physical_key == PhysicalKey::Code(KeyCode::Space) {
+            key_press(TKey::Char(' '));
+            self.get_active_pane_mut().renderer.scroll_to_bottom();
+            self.trigger_redraw();
+            return;
+        }
+        if let Key::Character(string) = event.logical_key {
+            for c in string.chars() {
+                key_press(TKey::Char(c));
+            }
+            self.get_active_pane_mut().renderer.scroll_to_bottom();
+            self.trigger_redraw();
+        }
+    }
+}
+
+// The process may close its pipes slightly before it can be waited on
+pub fn wait_for_exit_code(process: &Mutex<Box<dyn TerminalProcess + Send>>) -> Option<i32> {
+    const EXIT_CODE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+    let start = std::time::Instant::now();
+    loop {
+        let mut process = process.lock().unwrap();
+        if let Some(exit_code) = process.get_exit_code() {
+            return Some(exit_code);
+        }
+        if !process.is_alive() || start.elapsed() > EXIT_CODE_TIMEOUT {
+            return None;
+        }
+        drop(process);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+fn is_modifier_key(key: PhysicalKey) -> bool {
+    matches!(key, PhysicalKey::Code(
+        KeyCode::AltLeft | KeyCode::AltRight |
+        KeyCode::ControlLeft | KeyCode::ControlRight |
+        KeyCode::ShiftLeft | KeyCode::ShiftRight |
+        KeyCode::SuperLeft | KeyCode::SuperRight
+    ))
 }
@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+// How long the grid colours stay inverted for the visual bell
+pub const VISUAL_BELL_DURATION: Duration = Duration::from_millis(100);
+// Bells arriving faster than this are dropped so a flood of BEL characters doesn't strobe the window
+pub const BELL_MIN_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum BellMode {
+    None,
+    #[default]
+    Visual,
+    Audible,
+    Both,
+}
+
+impl BellMode {
+    pub fn is_visual(&self) -> bool {
+        matches!(self, Self::Visual | Self::Both)
+    }
+
+    pub fn is_audible(&self) -> bool {
+        matches!(self, Self::Audible | Self::Both)
+    }
+}
+
+#[derive(Clone,Debug)]
+pub struct BellRateLimiter {
+    min_interval: Duration,
+    last_bell: Option<Instant>,
+}
+
+impl Default for BellRateLimiter {
+    fn default() -> Self {
+        Self::new(BELL_MIN_INTERVAL)
+    }
+}
+
+impl BellRateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_bell: None,
+        }
+    }
+
+    // Returns true if the bell should ring at this instant
+    pub fn try_ring(&mut self, now: Instant) -> bool {
+        if let Some(last_bell) = self.last_bell {
+            if now.saturating_duration_since(last_bell) < self.min_interval {
+                return false;
+            }
+        }
+        self.last_bell = Some(now);
+        true
+    }
+}
+
+#[cfg(windows)]
+pub fn play_system_beep() {
+    use windows::Win32::{
+        System::Diagnostics::Debug::MessageBeep,
+        UI::WindowsAndMessaging::MB_OK,
+    };
+    if let Err(err) = unsafe { MessageBeep(MB_OK) } {
+        log::warn!("Failed to play bell: {:?}", err);
+    }
+}
+
+// Forward the bell to the terminal we were launched from which plays the system sound
+#[cfg(not(windows))]
+pub fn play_system_beep() {
+    use std::io::Write;
+    let mut stderr = std::io::stderr();
+    if let Err(err) = stderr.write_all(b"\x07").and_then(|_| stderr.flush()) {
+        log::warn!("Failed to play bell: {:?}", err);
+    }
+}
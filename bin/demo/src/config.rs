@@ -0,0 +1,312 @@
+use crate::bell::BellMode;
+use crate::keyboard_shortcuts::KeyboardShortcuts;
+use crate::process_environment::{DEFAULT_GRID_SIZE, MAX_GRID_SIZE};
+use crate::themes::get_theme;
+use cgmath::Vector2;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use terminal::terminal_display::DEFAULT_WORD_CHARACTERS;
+use vt100::common::{CursorStyle, Rgb8};
+
+// Default config file and saved window state are stored in wgpu_terminal inside this directory
+pub fn get_config_dir() -> Option<std::path::PathBuf> {
+    #[cfg(windows)]
+    let config_dir = std::env::var_os("APPDATA").map(std::path::PathBuf::from);
+    #[cfg(not(windows))]
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::PathBuf::from(home).join(".config")));
+    config_dir
+}
+
+pub fn parse_hex_colour(value: &str) -> Result<Rgb8, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let rgb = match hex.len() {
+        6 if hex.bytes().all(|b| b.is_ascii_hexdigit()) => u32::from_str_radix(hex, 16).ok(),
+        _ => None,
+    };
+    let rgb = rgb.ok_or_else(|| format!("Expected colour of the form #rrggbb, got '{}'", value))?;
+    Ok(Rgb8 {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
+    })
+}
+
+// Either a single value for all sides or x,y for the left/right and top/bottom sides
+pub fn parse_padding(value: &str) -> Result<Vector2<u32>, String> {
+    let parse = |v: &str| v.trim().parse::<u32>().map_err(|_| format!("Expected padding of the form x or x,y, got '{}'", value));
+    match value.split_once(',') {
+        Some((x, y)) => Ok(Vector2::new(parse(x)?, parse(y)?)),
+        None => {
+            let v = parse(value)?;
+            Ok(Vector2::new(v, v))
+        },
+    }
+}
+
+pub fn parse_opacity(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(opacity),
+        _ => Err(format!("Expected opacity between 0.0 and 1.0, got '{}'", value)),
+    }
+}
+
+fn deserialize_padding<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vector2<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Padding {
+        Uniform(u32),
+        Axes([u32; 2]),
+    }
+    match Padding::deserialize(deserializer)? {
+        Padding::Uniform(v) => Ok(Vector2::new(v, v)),
+        Padding::Axes([x, y]) => Ok(Vector2::new(x, y)),
+    }
+}
+
+fn deserialize_opacity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value = f32::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(serde::de::Error::custom(format!("Expected opacity between 0.0 and 1.0, got {}", value)));
+    }
+    Ok(value)
+}
+
+fn deserialize_colour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Rgb8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_hex_colour(value.as_str()).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_palette<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[Rgb8; 16]>, D::Error> {
+    let values = Vec::<String>::deserialize(deserializer)?;
+    if values.len() != 16 {
+        return Err(serde::de::Error::custom(format!("Expected 16 colours, got {}", values.len())));
+    }
+    let mut palette = [Rgb8::default(); 16];
+    for (dst, value) in palette.iter_mut().zip(values.iter()) {
+        *dst = parse_hex_colour(value.as_str()).map_err(serde::de::Error::custom)?;
+    }
+    Ok(Some(palette))
+}
+
+fn deserialize_cursor_style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<CursorStyle>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    match value.as_str() {
+        "block" => Ok(Some(CursorStyle::Block)),
+        "underline" => Ok(Some(CursorStyle::Underline)),
+        "bar" => Ok(Some(CursorStyle::Bar)),
+        _ => Err(serde::de::Error::custom(format!("Expected one of block, underline or bar, got '{}'", value))),
+    }
+}
+
+fn deserialize_bell<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BellMode, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    match value.as_str() {
+        "none" => Ok(BellMode::None),
+        "visual" => Ok(BellMode::Visual),
+        "audible" => Ok(BellMode::Audible),
+        "both" => Ok(BellMode::Both),
+        _ => Err(serde::de::Error::custom(format!("Expected one of none, visual, audible or both, got '{}'", value))),
+    }
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    // font family resolved from installed fonts
+    pub family: Option<String>,
+    // overrides family
+    pub filename: Option<String>,
+    pub size: Option<f32>,
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Eq,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColourConfig {
+    #[serde(deserialize_with = "deserialize_palette")]
+    pub palette: Option<[Rgb8; 16]>,
+    #[serde(deserialize_with = "deserialize_colour")]
+    pub foreground: Option<Rgb8>,
+    #[serde(deserialize_with = "deserialize_colour")]
+    pub background: Option<Rgb8>,
+    #[serde(deserialize_with = "deserialize_colour")]
+    pub cursor: Option<Rgb8>,
+    #[serde(deserialize_with = "deserialize_colour")]
+    pub selection: Option<Rgb8>,
+}
+
+#[derive(Clone,Debug,PartialEq,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub shell: Option<String>,
+    pub shell_arguments: Vec<String>,
+    pub scrollback_lines: Option<usize>,
+    #[serde(deserialize_with = "deserialize_cursor_style")]
+    pub cursor_style: Option<CursorStyle>,
+    // what happens when a process writes BEL
+    #[serde(deserialize_with = "deserialize_bell")]
+    pub bell: BellMode,
+    // scrollback shortcuts are sent to programs using the alternate screen such as less or vim
+    pub alternate_screen_scroll_keys: bool,
+    // programs can iconify, raise, lower and resize the window
+    pub allow_window_control: bool,
+    // window size and position are saved on exit and restored on the next start
+    pub remember_window_geometry: bool,
+    // characters besides alphanumerics that are selected as part of a word when double clicking
+    pub word_characters: String,
+    // pixels between the window edge and the grid given as a single value or [x, y]
+    #[serde(deserialize_with = "deserialize_padding")]
+    pub padding: Vector2<u32>,
+    // opacity of the background from 0.0 to 1.0 which requires a compositor that supports transparent windows
+    #[serde(deserialize_with = "deserialize_opacity")]
+    pub opacity: f32,
+    // initial grid size of the window and pseudoterminal
+    pub columns: Option<u16>,
+    pub rows: Option<u16>,
+    pub font: FontConfig,
+    // name of a bundled theme whose colours can be individually overridden in colours
+    pub theme: Option<String>,
+    pub colours: ColourConfig,
+    pub keyboard_shortcuts: KeyboardShortcuts,
+    // overrides keyboard_shortcuts with entries of the form "ctrl+shift+c" = "copy"
+    pub keybindings: BTreeMap<String, String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            shell: None,
+            shell_arguments: Vec::new(),
+            scrollback_lines: None,
+            cursor_style: None,
+            bell: BellMode::default(),
+            alternate_screen_scroll_keys: true,
+            allow_window_control: false,
+            remember_window_geometry: true,
+            word_characters: DEFAULT_WORD_CHARACTERS.to_owned(),
+            padding: Vector2::new(0,0),
+            opacity: 1.0,
+            columns: None,
+            rows: None,
+            font: FontConfig::default(),
+            theme: None,
+            colours: ColourConfig::default(),
+            keyboard_shortcuts: KeyboardShortcuts::default(),
+            keybindings: BTreeMap::new(),
+        }
+    }
+}
+
+// Values given on the command line which take precedence over the config file
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct ConfigOverrides {
+    pub theme: Option<String>,
+    pub shell: Option<String>,
+    pub shell_arguments: Vec<String>,
+    pub font_family: Option<String>,
+    pub font_filename: Option<String>,
+    pub font_size: Option<f32>,
+    pub cursor_colour: Option<Rgb8>,
+    pub padding: Option<Vector2<u32>>,
+    pub opacity: Option<f32>,
+    pub columns: Option<u16>,
+    pub rows: Option<u16>,
+}
+
+impl AppConfig {
+    pub fn from_toml(data: &str) -> anyhow::Result<Self> {
+        let config = toml::from_str(data)?;
+        Ok(config)
+    }
+
+    pub fn load(filename: &str) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(filename)
+            .map_err(|err| anyhow::format_err!("Failed to read config file '{}': {}", filename, err))?;
+        Self::from_toml(data.as_str())
+            .map_err(|err| anyhow::format_err!("Failed to parse config file '{}': {}", filename, err))
+    }
+
+    pub fn apply_overrides(&mut self, overrides: ConfigOverrides) {
+        if let Some(theme) = overrides.theme {
+            self.theme = Some(theme);
+        }
+        if let Some(shell) = overrides.shell {
+            // arguments from the config file belong to the config file's shell
+            self.shell = Some(shell);
+            self.shell_arguments = overrides.shell_arguments;
+        }
+        if let Some(family) = overrides.font_family {
+            // otherwise a filename from the config file would take precedence
+            self.font.family = Some(family);
+            self.font.filename = None;
+        }
+        if let Some(filename) = overrides.font_filename {
+            self.font.filename = Some(filename);
+        }
+        if let Some(size) = overrides.font_size {
+            self.font.size = Some(size);
+        }
+        if let Some(colour) = overrides.cursor_colour {
+            self.colours.cursor = Some(colour);
+        }
+        if let Some(padding) = overrides.padding {
+            self.padding = padding;
+        }
+        if let Some(opacity) = overrides.opacity {
+            self.opacity = opacity;
+        }
+        if let Some(columns) = overrides.columns {
+            self.columns = Some(columns);
+        }
+        if let Some(rows) = overrides.rows {
+            self.rows = Some(rows);
+        }
+    }
+
+    // Unset columns or rows keep their default size
+    pub fn get_grid_size(&self) -> anyhow::Result<Vector2<usize>> {
+        let columns = self.columns.map_or(DEFAULT_GRID_SIZE.x, usize::from);
+        let rows = self.rows.map_or(DEFAULT_GRID_SIZE.y, usize::from);
+        if columns == 0 || rows == 0 {
+            return Err(anyhow::format_err!("Grid size must be at least 1x1, got {}x{}", columns, rows));
+        }
+        if columns > MAX_GRID_SIZE || rows > MAX_GRID_SIZE {
+            return Err(anyhow::format_err!("Grid size must be at most {0}x{0}, got {1}x{2}", MAX_GRID_SIZE, columns, rows));
+        }
+        Ok(Vector2::new(columns, rows))
+    }
+
+    // Colours from the theme with any colours that were set explicitly
+    pub fn get_colours(&self) -> anyhow::Result<ColourConfig> {
+        let mut colours = match self.theme.as_ref() {
+            Some(name) => {
+                let theme = get_theme(name.as_str())
+                    .ok_or_else(|| anyhow::format_err!("Unknown theme '{}', use --list-themes to show available themes", name))?;
+                ColourConfig {
+                    palette: Some(theme.palette),
+                    foreground: Some(theme.foreground),
+                    background: Some(theme.background),
+                    cursor: theme.cursor,
+                    selection: theme.selection,
+                }
+            },
+            None => ColourConfig::default(),
+        };
+        let overrides = &self.colours;
+        colours.palette = overrides.palette.or(colours.palette);
+        colours.foreground = overrides.foreground.or(colours.foreground);
+        colours.background = overrides.background.or(colours.background);
+        colours.cursor = overrides.cursor.or(colours.cursor);
+        colours.selection = overrides.selection.or(colours.selection);
+        Ok(colours)
+    }
+
+    // Shortcuts with keybindings applied, shortcuts bound to multiple actions are an error
+    pub fn get_keyboard_shortcuts(&self) -> anyhow::Result<KeyboardShortcuts> {
+        let mut shortcuts = self.keyboard_shortcuts.clone();
+        shortcuts.check_conflicts()?;
+        shortcuts.apply_keybindings(&self.keybindings)?;
+        Ok(shortcuts)
+    }
+}
@@ -0,0 +1,63 @@
+use cgmath::Vector2;
+
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct DebugStats {
+    pub fps: f32,
+    pub frame_time_ms: f32,
+    pub grid_size: Vector2<usize>,
+    // fraction of the glyph atlas that is occupied
+    pub atlas_utilization: f32,
+    pub atlas_memory_bytes: usize,
+    pub scrollback_lines: usize,
+    pub read_bytes_per_second: f32,
+}
+
+fn format_bytes(bytes: f32) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len()-1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0}{}", value, UNITS[unit])
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
+
+pub fn get_debug_lines(stats: &DebugStats) -> Vec<String> {
+    vec![
+        format!("fps {:.1} ({:.2}ms)", stats.fps, stats.frame_time_ms),
+        format!("grid {}x{}", stats.grid_size.x, stats.grid_size.y),
+        format!("atlas {:.1}% {}", stats.atlas_utilization*100.0, format_bytes(stats.atlas_memory_bytes as f32)),
+        format!("scrollback {}", stats.scrollback_lines),
+        format!("read {}/s", format_bytes(stats.read_bytes_per_second)),
+    ]
+}
+
+// Lines are padded to the same width and placed in the top right corner of the grid
+// Columns and rows that don't fit in the grid are left out
+pub fn get_overlay_cells(lines: &[String], grid_size: Vector2<usize>) -> Vec<(Vector2<usize>, char)> {
+    let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    // single column of padding on either side
+    let width = width + 2;
+    let start_x = grid_size.x.saturating_sub(width);
+    let mut cells = Vec::new();
+    for (y, line) in lines.iter().enumerate().take(grid_size.y) {
+        let mut chars = line.chars();
+        for i in 0..width {
+            let x = start_x + i;
+            if x >= grid_size.x {
+                break;
+            }
+            let c = match i {
+                0 => ' ',
+                _ => chars.next().unwrap_or(' '),
+            };
+            cells.push((Vector2::new(x, y), c));
+        }
+    }
+    cells
+}
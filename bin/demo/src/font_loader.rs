@@ -0,0 +1,162 @@
+#[derive(Clone,Debug)]
+pub struct FontData {
+    pub data: Vec<u8>,
+    pub collection_index: u32,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum FontSource {
+    File(String),
+    #[cfg(feature = "system-fonts")]
+    System(FontQuery),
+}
+
+impl FontSource {
+    pub fn load(&self) -> anyhow::Result<FontData> {
+        match self {
+            FontSource::File(filename) => load_font_file(filename.as_str()),
+            #[cfg(feature = "system-fonts")]
+            FontSource::System(query) => SystemFonts::new().find_font(query),
+        }
+    }
+}
+
+pub fn load_font_file(filename: &str) -> anyhow::Result<FontData> {
+    let data = std::fs::read(filename)
+        .map_err(|err| anyhow::format_err!("Failed to read font file '{}': {}", filename, err))?;
+    Ok(FontData { data, collection_index: 0 })
+}
+
+#[cfg(feature = "system-fonts")]
+pub use system_fonts::{FontQuery, SystemFonts};
+
+#[cfg(feature = "system-fonts")]
+mod system_fonts {
+    use super::FontData;
+    use std::path::Path;
+
+    const MAX_CLOSE_MATCHES: usize = 5;
+
+    #[derive(Clone,Debug,PartialEq)]
+    pub struct FontQuery {
+        pub family: String,
+        pub weight: u16,
+        pub is_italic: bool,
+    }
+
+    impl FontQuery {
+        pub fn new(family: &str) -> Self {
+            Self {
+                family: family.to_owned(),
+                weight: fontdb::Weight::NORMAL.0,
+                is_italic: false,
+            }
+        }
+    }
+
+    pub struct SystemFonts {
+        database: fontdb::Database,
+    }
+
+    impl Default for SystemFonts {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl SystemFonts {
+        pub fn new() -> Self {
+            let mut database = fontdb::Database::new();
+            database.load_system_fonts();
+            Self { database }
+        }
+
+        pub fn from_directory(path: impl AsRef<Path>) -> Self {
+            let mut database = fontdb::Database::new();
+            database.load_fonts_dir(path);
+            Self { database }
+        }
+
+        pub fn get_families(&self) -> Vec<String> {
+            let mut families: Vec<String> = self.database
+                .faces()
+                .flat_map(|face| face.families.iter().map(|(name, _language)| name.clone()))
+                .collect();
+            families.sort();
+            families.dedup();
+            families
+        }
+
+        pub fn find_font(&self, query: &FontQuery) -> anyhow::Result<FontData> {
+            let families = [fontdb::Family::Name(query.family.as_str())];
+            let style = if query.is_italic { fontdb::Style::Italic } else { fontdb::Style::Normal };
+            let id = self.database.query(&fontdb::Query {
+                families: &families,
+                weight: fontdb::Weight(query.weight),
+                stretch: fontdb::Stretch::Normal,
+                style,
+            });
+            let Some(id) = id else {
+                let close_matches = self.get_close_matches(query.family.as_str());
+                if close_matches.is_empty() {
+                    return Err(anyhow::format_err!("Font family '{}' was not found", query.family));
+                }
+                return Err(anyhow::format_err!(
+                    "Font family '{}' was not found, did you mean: {}", 
+                    query.family, close_matches.join(", "),
+                ));
+            };
+            self.database
+                .with_face_data(id, |data, collection_index| FontData { data: data.to_vec(), collection_index })
+                .ok_or_else(|| anyhow::format_err!("Failed to read font data for family '{}'", query.family))
+        }
+
+        // Resolve a fallback chain of fonts in order of priority
+        pub fn find_fonts(&self, queries: &[FontQuery]) -> anyhow::Result<Vec<FontData>> {
+            queries.iter().map(|query| self.find_font(query)).collect()
+        }
+
+        pub fn get_close_matches(&self, family: &str) -> Vec<String> {
+            let family = normalise_family_name(family);
+            let mut matches: Vec<(usize, String)> = self.get_families()
+                .into_iter()
+                .filter_map(|name| {
+                    let other = normalise_family_name(name.as_str());
+                    if other.contains(family.as_str()) || family.contains(other.as_str()) {
+                        return Some((0, name));
+                    }
+                    let distance = get_edit_distance(family.as_str(), other.as_str());
+                    if distance <= 3 {
+                        return Some((distance, name));
+                    }
+                    None
+                })
+                .collect();
+            matches.sort();
+            matches.into_iter().take(MAX_CLOSE_MATCHES).map(|(_, name)| name).collect()
+        }
+    }
+
+    fn normalise_family_name(name: &str) -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect()
+    }
+
+    fn get_edit_distance(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.chars().enumerate() {
+            let mut prev_diagonal = row[0];
+            row[0] = i+1;
+            for (j, cb) in b.iter().enumerate() {
+                let cost = if ca == *cb { 0 } else { 1 };
+                let value = (row[j]+1).min(row[j+1]+1).min(prev_diagonal+cost);
+                prev_diagonal = row[j+1];
+                row[j+1] = value;
+            }
+        }
+        row[b.len()]
+    }
+}
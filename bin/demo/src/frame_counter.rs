@@ -1,32 +1,83 @@
+use std::time::Instant;
+
 pub struct FrameCounter {
     // Instant of the last time we printed the frame time.
-    last_printed_instant: std::time::Instant,
+    last_printed_instant: Instant,
     // Number of frames since the last time we printed the frame time.
     frame_count: u32,
+    // Measured over the last second
+    fps: f32,
+    frame_time_ms: f32,
 }
 
 impl Default for FrameCounter {
     fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl FrameCounter {
+    pub fn new(instant: Instant) -> Self {
         Self {
-            last_printed_instant: std::time::Instant::now(),
+            last_printed_instant: instant,
             frame_count: 0,
+            fps: 0.0,
+            frame_time_ms: 0.0,
         }
     }
-}
 
-impl FrameCounter {
     pub fn update(&mut self) {
+        self.update_at(Instant::now());
+    }
+
+    pub fn update_at(&mut self, new_instant: Instant) {
         self.frame_count += 1;
-        let new_instant = std::time::Instant::now();
         let elapsed_secs = (new_instant - self.last_printed_instant).as_secs_f32();
         if elapsed_secs > 1.0 {
             let elapsed_ms = elapsed_secs * 1000.0;
-            let frame_time = elapsed_ms / self.frame_count as f32;
-            let fps = self.frame_count as f32 / elapsed_secs;
-            log::debug!("Frame time {:.2}ms ({:.1} FPS)", frame_time, fps);
+            self.frame_time_ms = elapsed_ms / self.frame_count as f32;
+            self.fps = self.frame_count as f32 / elapsed_secs;
+            log::debug!("Frame time {:.2}ms ({:.1} FPS)", self.frame_time_ms, self.fps);
             self.last_printed_instant = new_instant;
             self.frame_count = 0;
         }
     }
+
+    pub fn get_fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn get_frame_time_ms(&self) -> f32 {
+        self.frame_time_ms
+    }
+}
+
+// Rate of a running byte total measured over the last second
+#[derive(Default)]
+pub struct ThroughputCounter {
+    // first update only sets the starting total
+    last_sample: Option<(Instant, u64)>,
+    bytes_per_second: f32,
 }
 
+impl ThroughputCounter {
+    pub fn update(&mut self, total_bytes: u64) {
+        self.update_at(Instant::now(), total_bytes);
+    }
+
+    pub fn update_at(&mut self, new_instant: Instant, total_bytes: u64) {
+        let Some((last_instant, last_total)) = self.last_sample else {
+            self.last_sample = Some((new_instant, total_bytes));
+            return;
+        };
+        let elapsed_secs = (new_instant - last_instant).as_secs_f32();
+        if elapsed_secs > 1.0 {
+            self.bytes_per_second = total_bytes.saturating_sub(last_total) as f32 / elapsed_secs;
+            self.last_sample = Some((new_instant, total_bytes));
+        }
+    }
+
+    pub fn get_bytes_per_second(&self) -> f32 {
+        self.bytes_per_second
+    }
+}
@@ -0,0 +1,209 @@
+use cgmath::Vector2;
+use std::fmt::Write;
+use terminal::{Cell, Pen, StyleFlags};
+use vt100::common::{Rgb8, UnderlineStyle};
+
+// How the cells of a grid are written out as text
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,clap::ValueEnum)]
+pub enum GridFormat {
+    // characters only
+    #[default]
+    Plain,
+    // SGR sequences for viewing with less -R or diffing
+    Ansi,
+    // <pre> block with inline span styles
+    Html,
+}
+
+pub fn format_grid(cells: &[Cell], size: Vector2<usize>, default_pen: &Pen, format: GridFormat) -> String {
+    let mut output = String::new();
+    match format {
+        GridFormat::Plain => write_plain(cells, size, &mut output),
+        GridFormat::Ansi => write_ansi(cells, size, default_pen, &mut output),
+        GridFormat::Html => write_html(cells, size, default_pen, &mut output),
+    }
+    output
+}
+
+fn get_rows(cells: &[Cell], size: Vector2<usize>) -> impl Iterator<Item=&[Cell]> {
+    cells.chunks(size.x.max(1)).take(size.y)
+}
+
+// Hyperlinks don't change how a cell looks
+fn is_same_style(a: &Pen, b: &Pen) -> bool {
+    // cursor and image flags are only used when drawing the grid
+    let strip = |pen: &Pen| Pen {
+        hyperlink: None,
+        background_index: None,
+        foreground_index: None,
+        underline_index: None,
+        style_flags: pen.style_flags - StyleFlags::Cursor - StyleFlags::Image,
+        ..*pen
+    };
+    strip(a) == strip(b)
+}
+
+pub fn write_plain(cells: &[Cell], size: Vector2<usize>, output: &mut String) {
+    for row in get_rows(cells, size) {
+        output.extend(row.iter().map(|cell| cell.character));
+        output.push('\n');
+    }
+}
+
+// Each row starts from the default pen so rows can be viewed independently
+pub fn write_ansi(cells: &[Cell], size: Vector2<usize>, default_pen: &Pen, output: &mut String) {
+    let mut parameters: Vec<String> = Vec::new();
+    for row in get_rows(cells, size) {
+        let mut pen = *default_pen;
+        for cell in row {
+            if !is_same_style(&pen, &cell.pen) {
+                parameters.clear();
+                get_sgr_changes(&pen, &cell.pen, default_pen, &mut parameters);
+                let _ = write!(output, "\x1b[{}m", parameters.join(";"));
+                pen = cell.pen;
+            }
+            output.push(cell.character);
+        }
+        if !is_same_style(&pen, default_pen) {
+            output.push_str("\x1b[0m");
+        }
+        output.push('\n');
+    }
+}
+
+// https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h4-Functions-using-CSI-_-ordered-by-the-final-character-lparen-s-rparen:CSI-Pm-m.1CA7
+fn get_sgr_changes(old: &Pen, new: &Pen, default_pen: &Pen, parameters: &mut Vec<String>) {
+    let removed = old.style_flags & !new.style_flags;
+    let added = new.style_flags & !old.style_flags;
+    // bold and dim are both cleared by the same code
+    let mut added_intensity = added & (StyleFlags::Bold | StyleFlags::Dim);
+    if removed.intersects(StyleFlags::Bold | StyleFlags::Dim) {
+        parameters.push("22".to_owned());
+        added_intensity = new.style_flags & (StyleFlags::Bold | StyleFlags::Dim);
+    }
+    if added_intensity.contains(StyleFlags::Bold) { parameters.push("1".to_owned()); }
+    if added_intensity.contains(StyleFlags::Dim) { parameters.push("2".to_owned()); }
+    let flag_codes = [
+        (StyleFlags::Italic, "3", "23"),
+        (StyleFlags::Blinking, "5", "25"),
+        (StyleFlags::Inverse, "7", "27"),
+        (StyleFlags::Hidden, "8", "28"),
+        (StyleFlags::Strikethrough, "9", "29"),
+    ];
+    for (flag, on, off) in flag_codes {
+        if removed.contains(flag) { parameters.push(off.to_owned()); }
+        if added.contains(flag) { parameters.push(on.to_owned()); }
+    }
+    if old.underline_style != new.underline_style {
+        let code = match new.underline_style {
+            UnderlineStyle::None => "24",
+            UnderlineStyle::Straight => "4",
+            UnderlineStyle::Double => "4:2",
+            UnderlineStyle::Curly => "4:3",
+            UnderlineStyle::Dotted => "4:4",
+            UnderlineStyle::Dashed => "4:5",
+        };
+        parameters.push(code.to_owned());
+    }
+    if old.foreground_colour != new.foreground_colour {
+        parameters.push(get_sgr_colour(new.foreground_colour, default_pen.foreground_colour, 38, 39));
+    }
+    if old.background_colour != new.background_colour {
+        parameters.push(get_sgr_colour(new.background_colour, default_pen.background_colour, 48, 49));
+    }
+    if old.underline_colour != new.underline_colour {
+        let code = match new.underline_colour {
+            Some(Rgb8 { r, g, b }) => format!("58;2;{};{};{}", r, g, b),
+            None => "59".to_owned(),
+        };
+        parameters.push(code);
+    }
+}
+
+// Default colours are restored instead of written out so the output follows the viewer's colour scheme
+fn get_sgr_colour(colour: Rgb8, default_colour: Rgb8, set_code: u8, reset_code: u8) -> String {
+    if colour == default_colour {
+        format!("{}", reset_code)
+    } else {
+        format!("{};2;{};{};{}", set_code, colour.r, colour.g, colour.b)
+    }
+}
+
+fn get_css_colour(colour: Rgb8) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.r, colour.g, colour.b)
+}
+
+fn get_css_style(pen: &Pen) -> String {
+    let flags = pen.style_flags;
+    // css has no inverse so the colours are swapped instead
+    let (foreground, background) = if flags.contains(StyleFlags::Inverse) {
+        (pen.background_colour, pen.foreground_colour)
+    } else {
+        (pen.foreground_colour, pen.background_colour)
+    };
+    let mut style = format!("color:{};background-color:{}", get_css_colour(foreground), get_css_colour(background));
+    if flags.contains(StyleFlags::Bold) { style.push_str(";font-weight:bold"); }
+    if flags.contains(StyleFlags::Dim) { style.push_str(";opacity:0.5"); }
+    if flags.contains(StyleFlags::Italic) { style.push_str(";font-style:italic"); }
+    if flags.contains(StyleFlags::Hidden) { style.push_str(";visibility:hidden"); }
+    let mut decorations = Vec::new();
+    if pen.underline_style != UnderlineStyle::None { decorations.push("underline"); }
+    if flags.contains(StyleFlags::Strikethrough) { decorations.push("line-through"); }
+    if !decorations.is_empty() {
+        let _ = write!(&mut style, ";text-decoration:{}", decorations.join(" "));
+        let decoration_style = match pen.underline_style {
+            UnderlineStyle::Double => Some("double"),
+            UnderlineStyle::Curly => Some("wavy"),
+            UnderlineStyle::Dotted => Some("dotted"),
+            UnderlineStyle::Dashed => Some("dashed"),
+            UnderlineStyle::None | UnderlineStyle::Straight => None,
+        };
+        if let Some(decoration_style) = decoration_style {
+            let _ = write!(&mut style, ";text-decoration-style:{}", decoration_style);
+        }
+        if let Some(colour) = pen.underline_colour {
+            let _ = write!(&mut style, ";text-decoration-color:{}", get_css_colour(colour));
+        }
+    }
+    style
+}
+
+fn push_html_escaped(c: char, output: &mut String) {
+    match c {
+        '&' => output.push_str("&amp;"),
+        '<' => output.push_str("&lt;"),
+        '>' => output.push_str("&gt;"),
+        '"' => output.push_str("&quot;"),
+        c => output.push(c),
+    }
+}
+
+// Runs of cells with the same style share a span and cells with the default pen are left unstyled
+pub fn write_html(cells: &[Cell], size: Vector2<usize>, default_pen: &Pen, output: &mut String) {
+    let _ = writeln!(output, "<pre style=\"{}\">", get_css_style(default_pen));
+    for row in get_rows(cells, size) {
+        let mut span_pen: Option<Pen> = None;
+        for cell in row {
+            let is_default = is_same_style(&cell.pen, default_pen);
+            let is_span_changed = match span_pen {
+                Some(pen) => is_default || !is_same_style(&pen, &cell.pen),
+                None => !is_default,
+            };
+            if is_span_changed {
+                if span_pen.take().is_some() {
+                    output.push_str("</span>");
+                }
+                if !is_default {
+                    let _ = write!(output, "<span style=\"{}\">", get_css_style(&cell.pen));
+                    span_pen = Some(cell.pen);
+                }
+            }
+            push_html_escaped(cell.character, output);
+        }
+        if span_pen.is_some() {
+            output.push_str("</span>");
+        }
+        output.push('\n');
+    }
+    output.push_str("</pre>\n");
+}
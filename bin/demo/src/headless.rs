@@ -0,0 +1,235 @@
+use crate::app::{create_default_terminal_builder, log_parser_metrics};
+use crate::app_window::{get_cursor_data, update_glyph_grid, wait_for_exit_code};
+use crate::font_loader::FontData;
+use crate::grid_format::{GridFormat, format_grid};
+use cgmath::{Vector2, ElementWise};
+use regex::Regex;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use terminal::{Terminal, TerminalSettings};
+use terminal::terminal_renderer::TerminalRenderer;
+use terminal_process::TerminalProcess;
+use tile_renderer::{FontdueGlyphGenerator, GlyphCache, Renderer};
+use vt100::common::WindowAction;
+
+#[derive(Clone,Debug,Default)]
+pub struct HeadlessOptions {
+    pub format: GridFormat,
+    // process is terminated and the screen printed once exceeded
+    pub timeout: Option<Duration>,
+    // exits as soon as the pattern appears anywhere on the screen
+    pub wait_for: Option<Regex>,
+    // written to the process once it has started
+    pub send: Option<Vec<u8>>,
+    // final screen is also rendered to a png
+    pub screenshot: Option<HeadlessScreenshot>,
+}
+
+#[derive(Clone,Debug)]
+pub struct HeadlessScreenshot {
+    pub filename: String,
+    pub font: FontData,
+    pub font_size: f32,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum HeadlessExit {
+    // exit code of the process which is none if it was killed by a signal or couldn't be read
+    ProcessExited(Option<i32>),
+    // process exited before the pattern appeared
+    PatternNotFound,
+    PatternFound,
+    Timeout,
+}
+
+impl HeadlessExit {
+    // Timeout uses the same exit code as coreutils timeout
+    pub fn get_exit_code(&self) -> i32 {
+        match self {
+            Self::ProcessExited(exit_code) => exit_code.unwrap_or(1),
+            Self::PatternFound => 0,
+            Self::PatternNotFound => 2,
+            Self::Timeout => 124,
+        }
+    }
+}
+
+enum HeadlessEvent {
+    Refresh,
+    ProcessExited,
+}
+
+// Converts \n, \r, \t, \e, \xHH and \\ into their bytes so control codes can be given on the command line
+pub fn parse_escaped_input(value: &str) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    let mut chars = value.chars();
+    let mut utf8_buffer = [0u8; 4];
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            data.extend_from_slice(c.encode_utf8(&mut utf8_buffer).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => data.push(b'\n'),
+            Some('r') => data.push(b'\r'),
+            Some('t') => data.push(b'\t'),
+            Some('e') => data.push(b'\x1b'),
+            Some('\\') => data.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = match hex.len() {
+                    2 => u8::from_str_radix(hex.as_str(), 16).ok(),
+                    _ => None,
+                };
+                let byte = byte.ok_or_else(|| format!("Expected escape of the form \\xHH, got '\\x{}'", hex))?;
+                data.push(byte);
+            },
+            Some(c) => return Err(format!("Unknown escape '\\{}' in '{}'", c, value)),
+            None => return Err(format!("Trailing backslash in '{}'", value)),
+        }
+    }
+    Ok(data)
+}
+
+fn get_screen_text(terminal: &mut Terminal) -> String {
+    let mut terminal_renderer = TerminalRenderer::default();
+    terminal_renderer.render_display(&terminal.get_display());
+    format_grid(terminal_renderer.get_cells(), terminal_renderer.get_size(), &Default::default(), GridFormat::Plain)
+}
+
+// Runs the process until it exits, the pattern is found or the timeout is reached and returns the formatted screen
+pub fn run_headless(
+    process: Box<dyn TerminalProcess + Send>, settings: TerminalSettings, print_metrics: bool,
+    record_output: Option<Box<dyn std::io::Write + Send>>, options: &HeadlessOptions,
+) -> anyhow::Result<(HeadlessExit, String)> {
+    let process = Arc::new(Mutex::new(process));
+    let (event_tx, event_rx) = std::sync::mpsc::channel();
+    let mut terminal_builder = create_default_terminal_builder(process.clone(), settings)?;
+    terminal_builder.record_output = record_output;
+    terminal_builder.window_action = Box::new({
+        let event_tx = event_tx.clone();
+        move |action: WindowAction| {
+            if action == WindowAction::Refresh {
+                let _ = event_tx.send(HeadlessEvent::Refresh);
+            }
+        }
+    });
+    // the reader thread stops once the read pipe is closed
+    let mut process_read = terminal_builder.process_read;
+    terminal_builder.process_read = Box::new(move |data: &mut [u8]| {
+        let total = process_read(data);
+        if total == 0 {
+            let _ = event_tx.send(HeadlessEvent::ProcessExited);
+        }
+        total
+    });
+    let mut terminal = Terminal::new(terminal_builder);
+    if let Some(data) = options.send.as_ref() {
+        terminal.send_raw(data.as_slice());
+    }
+
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let exit = loop {
+        let event = match deadline {
+            Some(deadline) => event_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => event_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let is_exited = match event {
+            Ok(HeadlessEvent::Refresh) => false,
+            Ok(HeadlessEvent::ProcessExited) | Err(RecvTimeoutError::Disconnected) => true,
+            Err(RecvTimeoutError::Timeout) => break HeadlessExit::Timeout,
+        };
+        // output read before the process exited may still be waiting to be parsed
+        if is_exited {
+            terminal.join_parser_thread();
+        }
+        if let Some(pattern) = options.wait_for.as_ref() {
+            if pattern.is_match(get_screen_text(&mut terminal).as_str()) {
+                break HeadlessExit::PatternFound;
+            }
+        }
+        if is_exited {
+            break match options.wait_for {
+                Some(_) => HeadlessExit::PatternNotFound,
+                None => HeadlessExit::ProcessExited(wait_for_exit_code(&process)),
+            };
+        }
+    };
+
+    if print_metrics {
+        log_parser_metrics(&terminal);
+    }
+    log::info!("Headless terminal finished with {:?}", exit);
+    log::info!("Process exited with code {:?}", process.lock().unwrap().get_exit_code());
+    match process.lock().unwrap().terminate() {
+        Ok(()) => log::info!("Process terminated successfully"),
+        Err(err) => log::error!("Process failed to be terminated: {:?}", err),
+    }
+
+    let mut terminal_renderer = TerminalRenderer::default();
+    let display = terminal.get_display();
+    terminal_renderer.render_display(&display);
+    let default_pen = display.get_default_pen();
+    let output = format_grid(terminal_renderer.get_cells(), terminal_renderer.get_size(), &default_pen, options.format);
+    if let Some(screenshot) = options.screenshot.as_ref() {
+        let (device, queue) = create_headless_device()?;
+        let (size, pixels) = render_screenshot(&device, &queue, &terminal_renderer, &screenshot.font, screenshot.font_size)?;
+        crate::screenshot::save_png(screenshot.filename.as_str(), size, pixels.as_slice())
+            .map_err(|err| anyhow::format_err!("Failed to save screenshot to '{}': {}", screenshot.filename, err))?;
+        log::info!("Saved screenshot to {}", screenshot.filename);
+    }
+    Ok((exit, output))
+}
+
+// Software adapters are used when there is no gpu, e.g. in CI
+pub fn create_headless_device() -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let request_adapter = |force_fallback_adapter: bool| {
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter,
+            compatible_surface: None,
+        }))
+    };
+    let adapter = request_adapter(false).or_else(|| request_adapter(true))
+        .ok_or_else(|| anyhow::format_err!("Failed to find wgpu adapter for headless rendering"))?;
+    let device = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        label: None,
+        required_features: wgpu::Features::empty(),
+        required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+    }, None))?;
+    Ok(device)
+}
+
+// Draws the rendered cells offscreen and returns the image size and its tightly packed rgba8 pixels
+pub fn render_screenshot(
+    device: &wgpu::Device, queue: &wgpu::Queue,
+    terminal_renderer: &TerminalRenderer, font: &FontData, font_size: f32,
+) -> anyhow::Result<(Vector2<usize>, Vec<u8>)> {
+    let font_settings = fontdue::FontSettings {
+        collection_index: font.collection_index,
+        ..Default::default()
+    };
+    let font = fontdue::Font::from_bytes(font.data.as_slice(), font_settings).map_err(anyhow::Error::msg)?;
+    let max_texture_size = device.limits().max_texture_dimension_2d as usize;
+    let max_texture_layers = device.limits().max_texture_array_layers as usize;
+    let mut glyph_cache = GlyphCache::new(
+        Box::new(FontdueGlyphGenerator::new(font, font_size)),
+        Vector2::new(max_texture_size, max_texture_size),
+        max_texture_layers,
+        None,
+    );
+    let size = terminal_renderer.get_size();
+    let size = Vector2::new(size.x, terminal_renderer.get_total_render_rows());
+    let mut grid = Vec::new();
+    update_glyph_grid(&mut grid, terminal_renderer.get_cells(), size, &mut glyph_cache, 0);
+    let mut renderer = Renderer::new_headless(device, wgpu::TextureFormat::Rgba8Unorm);
+    renderer.update_grid(device, queue, grid.as_slice(), size);
+    renderer.update_atlas(device, queue, glyph_cache.get_glyph_atlas_mut());
+    renderer.update_render_scale(queue, Vector2::new(1.0,1.0));
+    renderer.update_cursor(queue, terminal_renderer.get_cursor().as_ref().map(get_cursor_data));
+    let image_size = size.mul_element_wise(glyph_cache.get_glyph_atlas().get_glyph_size());
+    let pixels = renderer.render_to_buffer(device, queue, image_size);
+    Ok((image_size, pixels))
+}
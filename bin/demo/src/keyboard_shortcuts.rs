@@ -0,0 +1,446 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ShortcutAction {
+    Copy,
+    Paste,
+    ScrollUp,
+    ScrollDown,
+    ScrollToTop,
+    ScrollToBottom,
+    FontSizeIncrease,
+    FontSizeDecrease,
+    FontSizeReset,
+    Screenshot,
+    ClearScrollback,
+    ToggleFullscreen,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    SplitVertical,
+    SplitHorizontal,
+    ToggleDebugOverlay,
+    ScrollToPreviousPrompt,
+    ScrollToNextPrompt,
+    CopyLastCommandOutput,
+}
+
+impl ShortcutAction {
+    pub fn is_scrollback(&self) -> bool {
+        matches!(self,
+            Self::ScrollUp | Self::ScrollDown | Self::ScrollToTop | Self::ScrollToBottom |
+            Self::ScrollToPreviousPrompt | Self::ScrollToNextPrompt
+        )
+    }
+
+    // Same names as the fields in KeyboardShortcuts
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Paste => "paste",
+            Self::ScrollUp => "scroll_up",
+            Self::ScrollDown => "scroll_down",
+            Self::ScrollToTop => "scroll_to_top",
+            Self::ScrollToBottom => "scroll_to_bottom",
+            Self::FontSizeIncrease => "font_size_increase",
+            Self::FontSizeDecrease => "font_size_decrease",
+            Self::FontSizeReset => "font_size_reset",
+            Self::Screenshot => "screenshot",
+            Self::ClearScrollback => "clear_scrollback",
+            Self::ToggleFullscreen => "toggle_fullscreen",
+            Self::NewTab => "new_tab",
+            Self::CloseTab => "close_tab",
+            Self::NextTab => "next_tab",
+            Self::PreviousTab => "previous_tab",
+            Self::SplitVertical => "split_vertical",
+            Self::SplitHorizontal => "split_horizontal",
+            Self::ToggleDebugOverlay => "toggle_debug_overlay",
+            Self::ScrollToPreviousPrompt => "scroll_to_previous_prompt",
+            Self::ScrollToNextPrompt => "scroll_to_next_prompt",
+            Self::CopyLastCommandOutput => "copy_last_command_output",
+        }
+    }
+}
+
+// Where a key press that matches a shortcut is handled
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ShortcutTarget {
+    Window,
+    Process,
+}
+
+// The alternate screen has no scrollback so scrollback shortcuts can optionally be sent to the process instead
+pub fn get_shortcut_target(action: ShortcutAction, is_alternate_screen: bool, is_alternate_screen_passthrough: bool) -> ShortcutTarget {
+    if action.is_scrollback() && is_alternate_screen && is_alternate_screen_passthrough {
+        ShortcutTarget::Process
+    } else {
+        ShortcutTarget::Window
+    }
+}
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct ShortcutModifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl From<ModifiersState> for ShortcutModifiers {
+    fn from(state: ModifiersState) -> Self {
+        Self {
+            ctrl: state.control_key(),
+            shift: state.shift_key(),
+            alt: state.alt_key(),
+            logo: state.super_key(),
+        }
+    }
+}
+
+// Parsed from strings of the form "Ctrl+Shift+C"
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Deserialize)]
+#[serde(try_from = "String")]
+pub struct ShortcutCombo {
+    pub modifiers: ShortcutModifiers,
+    pub key: KeyCode,
+}
+
+impl ShortcutCombo {
+    pub const fn new(key: KeyCode) -> Self {
+        Self {
+            modifiers: ShortcutModifiers { ctrl: false, shift: false, alt: false, logo: false },
+            key,
+        }
+    }
+
+    pub const fn ctrl(key: KeyCode) -> Self {
+        Self {
+            modifiers: ShortcutModifiers { ctrl: true, shift: false, alt: false, logo: false },
+            key,
+        }
+    }
+
+    pub const fn shift(key: KeyCode) -> Self {
+        Self {
+            modifiers: ShortcutModifiers { ctrl: false, shift: true, alt: false, logo: false },
+            key,
+        }
+    }
+
+    pub const fn ctrl_shift(key: KeyCode) -> Self {
+        Self {
+            modifiers: ShortcutModifiers { ctrl: true, shift: true, alt: false, logo: false },
+            key,
+        }
+    }
+
+    pub fn is_match(&self, modifiers: ShortcutModifiers, key: KeyCode) -> bool {
+        self.modifiers == modifiers && self.key == key
+    }
+}
+
+impl std::fmt::Display for ShortcutCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let modifiers = [
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.logo, "Super"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(is_set, _)| *is_set) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,thiserror::Error)]
+pub enum ShortcutParseError {
+    #[error("Shortcut is missing a key")]
+    MissingKey,
+    #[error("Unknown modifier '{0}'")]
+    UnknownModifier(String),
+    #[error("Unknown key '{0}'")]
+    UnknownKey(String),
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,thiserror::Error)]
+pub enum KeybindingError {
+    #[error("Invalid keybinding '{0}': {1}")]
+    InvalidShortcut(String, ShortcutParseError),
+    #[error("Unknown action '{1}' for keybinding '{0}'")]
+    UnknownAction(String, String),
+    #[error("Shortcut {combo} is bound to both {first} and {second}")]
+    Conflict { combo: ShortcutCombo, first: &'static str, second: &'static str },
+}
+
+impl std::str::FromStr for ShortcutCombo {
+    type Err = ShortcutParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(|part| part.trim()).collect();
+        // allow "Ctrl++" to bind the plus key
+        if s.trim_end().ends_with("++") {
+            parts.truncate(parts.len().saturating_sub(2));
+            parts.push("+");
+        }
+        let key = parts.pop().filter(|key| !key.is_empty()).ok_or(ShortcutParseError::MissingKey)?;
+        let key = parse_key_code(key).ok_or_else(|| ShortcutParseError::UnknownKey(key.to_owned()))?;
+        let mut modifiers = ShortcutModifiers::default();
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" => modifiers.alt = true,
+                "super" | "logo" | "cmd" | "win" => modifiers.logo = true,
+                _ => return Err(ShortcutParseError::UnknownModifier(part.to_owned())),
+            }
+        }
+        Ok(Self { modifiers, key })
+    }
+}
+
+impl TryFrom<String> for ShortcutCombo {
+    type Error = ShortcutParseError;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+        KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+        KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+        KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+        KeyCode::KeyY, KeyCode::KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+        KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+    const FUNCTION_KEYS: [KeyCode; 12] = [
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+        KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    ];
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let c = c.to_ascii_uppercase();
+        if c.is_ascii_uppercase() {
+            return Some(LETTERS[(c as u8 - b'A') as usize]);
+        }
+        if c.is_ascii_digit() {
+            return Some(DIGITS[(c as u8 - b'0') as usize]);
+        }
+    }
+    if let Some(n) = name.strip_prefix(['F', 'f']).and_then(|n| n.parse::<usize>().ok()) {
+        return FUNCTION_KEYS.get(n.wrapping_sub(1)).copied();
+    }
+    let key = match name.to_ascii_lowercase().as_str() {
+        "=" | "equal" => KeyCode::Equal,
+        "-" | "minus" => KeyCode::Minus,
+        "+" | "plus" | "numpadadd" => KeyCode::NumpadAdd,
+        "numpadsubtract" => KeyCode::NumpadSubtract,
+        "numpad0" => KeyCode::Numpad0,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "insert" => KeyCode::Insert,
+        "delete" => KeyCode::Delete,
+        "space" => KeyCode::Space,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "escape" | "esc" => KeyCode::Escape,
+        "up" => KeyCode::ArrowUp,
+        "down" => KeyCode::ArrowDown,
+        "left" => KeyCode::ArrowLeft,
+        "right" => KeyCode::ArrowRight,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[derive(Clone,Debug,PartialEq,Eq,Deserialize)]
+#[serde(default)]
+pub struct KeyboardShortcuts {
+    pub copy: Vec<ShortcutCombo>,
+    pub paste: Vec<ShortcutCombo>,
+    pub scroll_up: Vec<ShortcutCombo>,
+    pub scroll_down: Vec<ShortcutCombo>,
+    pub scroll_to_top: Vec<ShortcutCombo>,
+    pub scroll_to_bottom: Vec<ShortcutCombo>,
+    pub font_size_increase: Vec<ShortcutCombo>,
+    pub font_size_decrease: Vec<ShortcutCombo>,
+    pub font_size_reset: Vec<ShortcutCombo>,
+    pub screenshot: Vec<ShortcutCombo>,
+    pub clear_scrollback: Vec<ShortcutCombo>,
+    pub toggle_fullscreen: Vec<ShortcutCombo>,
+    pub new_tab: Vec<ShortcutCombo>,
+    pub close_tab: Vec<ShortcutCombo>,
+    pub next_tab: Vec<ShortcutCombo>,
+    pub previous_tab: Vec<ShortcutCombo>,
+    pub split_vertical: Vec<ShortcutCombo>,
+    pub split_horizontal: Vec<ShortcutCombo>,
+    pub toggle_debug_overlay: Vec<ShortcutCombo>,
+    pub scroll_to_previous_prompt: Vec<ShortcutCombo>,
+    pub scroll_to_next_prompt: Vec<ShortcutCombo>,
+    pub copy_last_command_output: Vec<ShortcutCombo>,
+}
+
+impl Default for KeyboardShortcuts {
+    fn default() -> Self {
+        Self {
+            copy: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyC)],
+            paste: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyV), ShortcutCombo::shift(KeyCode::Insert)],
+            // unshifted navigation keys are sent to the process for pagers and editors
+            scroll_up: vec![ShortcutCombo::shift(KeyCode::PageUp)],
+            scroll_down: vec![ShortcutCombo::shift(KeyCode::PageDown)],
+            scroll_to_top: vec![ShortcutCombo::shift(KeyCode::Home), ShortcutCombo::ctrl_shift(KeyCode::Home)],
+            scroll_to_bottom: vec![ShortcutCombo::shift(KeyCode::End), ShortcutCombo::ctrl_shift(KeyCode::End)],
+            font_size_increase: vec![ShortcutCombo::ctrl(KeyCode::Equal), ShortcutCombo::ctrl(KeyCode::NumpadAdd)],
+            font_size_decrease: vec![ShortcutCombo::ctrl(KeyCode::Minus), ShortcutCombo::ctrl(KeyCode::NumpadSubtract)],
+            font_size_reset: vec![ShortcutCombo::ctrl(KeyCode::Digit0), ShortcutCombo::ctrl(KeyCode::Numpad0)],
+            screenshot: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyS)],
+            clear_scrollback: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyK)],
+            toggle_fullscreen: vec![ShortcutCombo::new(KeyCode::F11)],
+            new_tab: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyT)],
+            close_tab: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyW)],
+            next_tab: vec![ShortcutCombo::ctrl(KeyCode::Tab), ShortcutCombo::ctrl(KeyCode::PageDown)],
+            previous_tab: vec![ShortcutCombo::ctrl_shift(KeyCode::Tab), ShortcutCombo::ctrl(KeyCode::PageUp)],
+            split_vertical: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyE)],
+            split_horizontal: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyO)],
+            toggle_debug_overlay: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyD)],
+            // prompts are found from the shell integration marks written by the shell
+            scroll_to_previous_prompt: vec![ShortcutCombo::ctrl_shift(KeyCode::ArrowUp)],
+            scroll_to_next_prompt: vec![ShortcutCombo::ctrl_shift(KeyCode::ArrowDown)],
+            copy_last_command_output: vec![ShortcutCombo::ctrl_shift(KeyCode::KeyL)],
+        }
+    }
+}
+
+impl KeyboardShortcuts {
+    fn get_bindings(&self) -> [(&Vec<ShortcutCombo>, ShortcutAction); 22] {
+        [
+            (&self.copy, ShortcutAction::Copy),
+            (&self.paste, ShortcutAction::Paste),
+            (&self.scroll_up, ShortcutAction::ScrollUp),
+            (&self.scroll_down, ShortcutAction::ScrollDown),
+            (&self.scroll_to_top, ShortcutAction::ScrollToTop),
+            (&self.scroll_to_bottom, ShortcutAction::ScrollToBottom),
+            (&self.font_size_increase, ShortcutAction::FontSizeIncrease),
+            (&self.font_size_decrease, ShortcutAction::FontSizeDecrease),
+            (&self.font_size_reset, ShortcutAction::FontSizeReset),
+            (&self.screenshot, ShortcutAction::Screenshot),
+            (&self.clear_scrollback, ShortcutAction::ClearScrollback),
+            (&self.toggle_fullscreen, ShortcutAction::ToggleFullscreen),
+            (&self.new_tab, ShortcutAction::NewTab),
+            (&self.close_tab, ShortcutAction::CloseTab),
+            (&self.next_tab, ShortcutAction::NextTab),
+            (&self.previous_tab, ShortcutAction::PreviousTab),
+            (&self.split_vertical, ShortcutAction::SplitVertical),
+            (&self.split_horizontal, ShortcutAction::SplitHorizontal),
+            (&self.toggle_debug_overlay, ShortcutAction::ToggleDebugOverlay),
+            (&self.scroll_to_previous_prompt, ShortcutAction::ScrollToPreviousPrompt),
+            (&self.scroll_to_next_prompt, ShortcutAction::ScrollToNextPrompt),
+            (&self.copy_last_command_output, ShortcutAction::CopyLastCommandOutput),
+        ]
+    }
+
+    fn get_bindings_mut(&mut self) -> [(&mut Vec<ShortcutCombo>, ShortcutAction); 22] {
+        [
+            (&mut self.copy, ShortcutAction::Copy),
+            (&mut self.paste, ShortcutAction::Paste),
+            (&mut self.scroll_up, ShortcutAction::ScrollUp),
+            (&mut self.scroll_down, ShortcutAction::ScrollDown),
+            (&mut self.scroll_to_top, ShortcutAction::ScrollToTop),
+            (&mut self.scroll_to_bottom, ShortcutAction::ScrollToBottom),
+            (&mut self.font_size_increase, ShortcutAction::FontSizeIncrease),
+            (&mut self.font_size_decrease, ShortcutAction::FontSizeDecrease),
+            (&mut self.font_size_reset, ShortcutAction::FontSizeReset),
+            (&mut self.screenshot, ShortcutAction::Screenshot),
+            (&mut self.clear_scrollback, ShortcutAction::ClearScrollback),
+            (&mut self.toggle_fullscreen, ShortcutAction::ToggleFullscreen),
+            (&mut self.new_tab, ShortcutAction::NewTab),
+            (&mut self.close_tab, ShortcutAction::CloseTab),
+            (&mut self.next_tab, ShortcutAction::NextTab),
+            (&mut self.previous_tab, ShortcutAction::PreviousTab),
+            (&mut self.split_vertical, ShortcutAction::SplitVertical),
+            (&mut self.split_horizontal, ShortcutAction::SplitHorizontal),
+            (&mut self.toggle_debug_overlay, ShortcutAction::ToggleDebugOverlay),
+            (&mut self.scroll_to_previous_prompt, ShortcutAction::ScrollToPreviousPrompt),
+            (&mut self.scroll_to_next_prompt, ShortcutAction::ScrollToNextPrompt),
+            (&mut self.copy_last_command_output, ShortcutAction::CopyLastCommandOutput),
+        ]
+    }
+
+    pub fn get_action(&self, modifiers: ShortcutModifiers, key: KeyCode) -> Option<ShortcutAction> {
+        self.get_bindings()
+            .iter()
+            .find(|(combos, _)| combos.iter().any(|combo| combo.is_match(modifiers, key)))
+            .map(|(_, action)| *action)
+    }
+
+    // Replaces any existing binding for the combo, passing none unbinds it
+    pub fn bind(&mut self, combo: ShortcutCombo, action: Option<ShortcutAction>) {
+        for (combos, binding_action) in self.get_bindings_mut() {
+            combos.retain(|other| *other != combo);
+            if Some(binding_action) == action {
+                combos.push(combo);
+            }
+        }
+    }
+
+    // Applies bindings of the form "ctrl+shift+c" = "copy", where "none" removes the binding
+    pub fn apply_keybindings(&mut self, keybindings: &BTreeMap<String, String>) -> Result<(), KeybindingError> {
+        let mut combos: Vec<(ShortcutCombo, Option<ShortcutAction>)> = Vec::new();
+        for (shortcut, name) in keybindings {
+            let combo: ShortcutCombo = shortcut.parse()
+                .map_err(|err| KeybindingError::InvalidShortcut(shortcut.clone(), err))?;
+            let action = match name.as_str() {
+                "none" => None,
+                _ => {
+                    let action = self.get_bindings().iter()
+                        .map(|(_, action)| *action)
+                        .find(|action| action.get_name() == name.as_str())
+                        .ok_or_else(|| KeybindingError::UnknownAction(shortcut.clone(), name.clone()))?;
+                    Some(action)
+                },
+            };
+            // differently written shortcuts can refer to the same combo, e.g. "ctrl+c" and "Ctrl+C"
+            if let Some((_, other)) = combos.iter().find(|(other, _)| *other == combo) {
+                if *other != action {
+                    return Err(KeybindingError::Conflict {
+                        combo,
+                        first: other.map_or("none", |action| action.get_name()),
+                        second: action.map_or("none", |action| action.get_name()),
+                    });
+                }
+            }
+            combos.push((combo, action));
+        }
+        for (combo, action) in combos {
+            self.bind(combo, action);
+        }
+        Ok(())
+    }
+
+    // Returns the first combo that is bound to more than one action
+    pub fn check_conflicts(&self) -> Result<(), KeybindingError> {
+        let bindings = self.get_bindings();
+        for (i, (combos, first)) in bindings.iter().enumerate() {
+            for (other_combos, second) in &bindings[i+1..] {
+                if let Some(combo) = combos.iter().find(|combo| other_combos.contains(combo)) {
+                    return Err(KeybindingError::Conflict {
+                        combo: *combo,
+                        first: first.get_name(),
+                        second: second.get_name(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
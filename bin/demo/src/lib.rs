@@ -1,4 +1,1406 @@
 pub mod frame_counter;
+pub mod debug_overlay;
 pub mod app_window;
 pub mod app_events;
+pub mod bell;
 pub mod app;
+pub mod font_loader;
+pub mod keyboard_shortcuts;
+pub mod config;
+pub mod themes;
+pub mod tab_bar;
+pub mod pane_layout;
+pub mod window_report;
+pub mod grid_format;
+pub mod headless;
+pub mod process_environment;
+pub mod logging;
+pub mod title_stack;
+pub mod window_icon;
+pub mod window_geometry;
+pub mod accessibility;
+pub mod screenshot;
+#[cfg(target_os = "macos")]
+pub mod native_menu;
+
+#[cfg(test)]
+mod test {
+    use crate::app_window::calculate_grid_size;
+    use cgmath::Vector2;
+
+    #[test]
+    fn grid_size_follows_glyph_size() {
+        let window_size = Vector2::new(800,600);
+        let (grid, render) = calculate_grid_size(window_size, Vector2::new(8,16));
+        assert_eq!(grid, Vector2::new(100,37));
+        assert_eq!(render, Vector2::new(800,592));
+        // larger font gives smaller grid
+        let (grid, render) = calculate_grid_size(window_size, Vector2::new(16,32));
+        assert_eq!(grid, Vector2::new(50,18));
+        assert_eq!(render, Vector2::new(800,576));
+        // grid is never empty
+        let (grid, _) = calculate_grid_size(Vector2::new(4,4), Vector2::new(16,32));
+        assert_eq!(grid, Vector2::new(1,1));
+    }
+
+    #[test]
+    fn pixel_to_cell_mapping() {
+        use crate::app_window::convert_pixel_to_cell;
+        let glyph_size = Vector2::new(8,16);
+        let grid_size = Vector2::new(10,5);
+        assert_eq!(convert_pixel_to_cell(Vector2::new(0,0), glyph_size, 0, grid_size), Vector2::new(0,0));
+        assert_eq!(convert_pixel_to_cell(Vector2::new(7,15), glyph_size, 0, grid_size), Vector2::new(0,0));
+        assert_eq!(convert_pixel_to_cell(Vector2::new(8,16), glyph_size, 0, grid_size), Vector2::new(1,1));
+        assert_eq!(convert_pixel_to_cell(Vector2::new(35,70), glyph_size, 0, grid_size), Vector2::new(4,4));
+        // clamped to the grid when outside of the rendered area
+        assert_eq!(convert_pixel_to_cell(Vector2::new(1000,1000), glyph_size, 0, grid_size), Vector2::new(9,4));
+    }
+
+    #[test]
+    fn pixel_to_cell_mapping_while_scrolled_back() {
+        use crate::app_window::convert_pixel_to_cell;
+        let glyph_size = Vector2::new(8,16);
+        // an extra row is rendered when partially scrolled
+        let grid_size = Vector2::new(10,6);
+        let pixel_offset = 10;
+        assert_eq!(convert_pixel_to_cell(Vector2::new(0,0), glyph_size, pixel_offset, grid_size), Vector2::new(0,0));
+        assert_eq!(convert_pixel_to_cell(Vector2::new(0,5), glyph_size, pixel_offset, grid_size), Vector2::new(0,0));
+        assert_eq!(convert_pixel_to_cell(Vector2::new(0,6), glyph_size, pixel_offset, grid_size), Vector2::new(0,1));
+        assert_eq!(convert_pixel_to_cell(Vector2::new(0,79), glyph_size, pixel_offset, grid_size), Vector2::new(0,5));
+    }
+
+    #[test]
+    fn grid_size_with_padding() {
+        use crate::app_window::calculate_grid_area;
+        let glyph_size = Vector2::new(8,16);
+        let area = calculate_grid_area(Vector2::new(800,600), Vector2::new(10,4), 0);
+        assert_eq!(area, Vector2::new(780,592));
+        let (grid, render) = calculate_grid_size(area, glyph_size);
+        assert_eq!(grid, Vector2::new(97,37));
+        assert_eq!(render, Vector2::new(776,592));
+        // rows reserved for the tab bar are taken from the top
+        let area = calculate_grid_area(Vector2::new(800,600), Vector2::new(10,4), 16);
+        assert_eq!(calculate_grid_size(area, glyph_size).0, Vector2::new(97,36));
+        // padding larger than the window still leaves a single cell
+        let area = calculate_grid_area(Vector2::new(20,20), Vector2::new(16,16), 0);
+        assert_eq!(calculate_grid_size(area, glyph_size).0, Vector2::new(1,1));
+    }
+
+    #[test]
+    fn window_size_for_grid_size() {
+        use crate::app_window::{calculate_grid_area, calculate_window_size};
+        let glyph_size = Vector2::new(8,16);
+        assert_eq!(calculate_window_size(Vector2::new(80,24), glyph_size, Vector2::new(0,0), 0), Vector2::new(640,384));
+        assert_eq!(calculate_window_size(Vector2::new(80,24), glyph_size, Vector2::new(10,4), 16), Vector2::new(660,408));
+        // requested window fits exactly the requested grid
+        for (padding, top_offset) in [(Vector2::new(0,0), 0), (Vector2::new(10,4), 0), (Vector2::new(3,7), 20)] {
+            let grid_size = Vector2::new(120,40);
+            let window_size = calculate_window_size(grid_size, glyph_size, padding, top_offset);
+            let area = calculate_grid_area(window_size, padding, top_offset);
+            assert_eq!(calculate_grid_size(area, glyph_size), (grid_size, area));
+        }
+    }
+
+    #[test]
+    fn load_grid_size_from_toml() {
+        use crate::config::{AppConfig, ConfigOverrides};
+        assert_eq!(AppConfig::default().get_grid_size().unwrap(), Vector2::new(80,24));
+        let mut config = AppConfig::from_toml("columns = 120\n").unwrap();
+        assert_eq!(config.get_grid_size().unwrap(), Vector2::new(120,24));
+        config.apply_overrides(ConfigOverrides { rows: Some(40), ..ConfigOverrides::default() });
+        assert_eq!(config.get_grid_size().unwrap(), Vector2::new(120,40));
+        assert!(AppConfig::from_toml("rows = 0\n").unwrap().get_grid_size().is_err());
+        // conpty sizes are signed 16 bit
+        assert!(AppConfig::from_toml("columns = 32768\n").unwrap().get_grid_size().is_err());
+        assert_eq!(AppConfig::from_toml("columns = 32767\n").unwrap().get_grid_size().unwrap(), Vector2::new(32767,24));
+        assert!(AppConfig::from_toml("rows = -1\n").is_err());
+    }
+
+    #[test]
+    fn pixel_to_cell_mapping_with_padding() {
+        use crate::app_window::{convert_pixel_to_cell, convert_window_to_grid_position};
+        let glyph_size = Vector2::new(8,16);
+        let grid_size = Vector2::new(10,5);
+        let origin = Vector2::new(12,6);
+        let to_cell = |position: Vector2<usize>| {
+            convert_pixel_to_cell(convert_window_to_grid_position(position, origin), glyph_size, 0, grid_size)
+        };
+        assert_eq!(convert_window_to_grid_position(Vector2::new(20,22), origin), Vector2::new(8,16));
+        assert_eq!(to_cell(Vector2::new(12,6)), Vector2::new(0,0));
+        assert_eq!(to_cell(Vector2::new(19,21)), Vector2::new(0,0));
+        assert_eq!(to_cell(Vector2::new(20,22)), Vector2::new(1,1));
+        // padding before the grid maps to the first cell
+        assert_eq!(to_cell(Vector2::new(0,0)), Vector2::new(0,0));
+        assert_eq!(to_cell(Vector2::new(5,30)), Vector2::new(0,1));
+    }
+
+    #[test]
+    fn load_padding_from_toml() {
+        use crate::config::{AppConfig, ConfigOverrides, parse_padding};
+        assert_eq!(AppConfig::default().padding, Vector2::new(0,0));
+        assert_eq!(AppConfig::from_toml("padding = 8\n").unwrap().padding, Vector2::new(8,8));
+        let mut config = AppConfig::from_toml("padding = [8, 4]\n").unwrap();
+        assert_eq!(config.padding, Vector2::new(8,4));
+        assert!(AppConfig::from_toml("padding = [8]\n").is_err());
+        assert!(AppConfig::from_toml("padding = -1\n").is_err());
+        assert_eq!(parse_padding("6"), Ok(Vector2::new(6,6)));
+        assert_eq!(parse_padding("6, 2"), Ok(Vector2::new(6,2)));
+        assert!(parse_padding("6,").is_err());
+        config.apply_overrides(ConfigOverrides { padding: Some(Vector2::new(2,2)), ..ConfigOverrides::default() });
+        assert_eq!(config.padding, Vector2::new(2,2));
+    }
+
+    #[test]
+    fn multi_click_selection_mode() {
+        use crate::app_window::{ClickCounter, SelectionMode};
+        use std::time::{Duration, Instant};
+        let mut counter = ClickCounter::default();
+        let start = Instant::now();
+        let cell = Vector2::new(4,2);
+        assert_eq!(counter.on_click(start, cell), SelectionMode::Cell);
+        assert_eq!(counter.on_click(start + Duration::from_millis(200), Vector2::new(5,2)), SelectionMode::Word);
+        assert_eq!(counter.on_click(start + Duration::from_millis(400), cell), SelectionMode::Line);
+        // cycles back to a single click
+        assert_eq!(counter.on_click(start + Duration::from_millis(500), cell), SelectionMode::Cell);
+        // too slow or too far away
+        assert_eq!(counter.on_click(start + Duration::from_millis(1000), cell), SelectionMode::Cell);
+        assert_eq!(counter.on_click(start + Duration::from_millis(1100), Vector2::new(6,2)), SelectionMode::Cell);
+        assert_eq!(counter.on_click(start + Duration::from_millis(1200), Vector2::new(6,2)), SelectionMode::Word);
+    }
+
+    #[test]
+    fn middle_click_dispatch() {
+        use crate::app_window::{MiddleClickAction, get_middle_click_action};
+        // mouse tracking is on and shift isn't held
+        assert_eq!(get_middle_click_action(false, true), MiddleClickAction::SendToProcess);
+        assert_eq!(get_middle_click_action(false, false), MiddleClickAction::SendToProcess);
+        // mouse tracking is off
+        assert_eq!(get_middle_click_action(true, true), MiddleClickAction::PastePrimarySelection);
+        assert_eq!(get_middle_click_action(true, false), MiddleClickAction::Ignore);
+    }
+
+    #[test]
+    fn log_filter_parsing() {
+        use crate::logging::{LogFilter, parse_log_filter};
+        use log::LevelFilter;
+        let filter = parse_log_filter("vt100=trace, wgpu=warn").unwrap();
+        assert_eq!(filter, LogFilter {
+            default_level: None,
+            module_levels: vec![("vt100".to_owned(), LevelFilter::Trace), ("wgpu".to_owned(), LevelFilter::Warn)],
+        });
+        // level on its own is the default and empty entries are skipped
+        let filter = parse_log_filter("info,,terminal::terminal=DEBUG,").unwrap();
+        assert_eq!(filter.default_level, Some(LevelFilter::Info));
+        assert_eq!(filter.module_levels, vec![("terminal::terminal".to_owned(), LevelFilter::Debug)]);
+        assert_eq!(parse_log_filter(""), Ok(LogFilter::default()));
+        assert!(parse_log_filter("vt100=loud").is_err());
+        assert!(parse_log_filter("=trace").is_err());
+        assert!(parse_log_filter("verbose").is_err());
+    }
+
+    #[test]
+    fn log_filter_levels() {
+        use crate::logging::{LogFilter, parse_log_filter};
+        use log::LevelFilter;
+        let mut filter = LogFilter {
+            default_level: Some(LevelFilter::Error),
+            module_levels: vec![("wgpu_terminal::app".to_owned(), LevelFilter::Info)],
+        };
+        filter.extend(parse_log_filter("vt100=trace,wgpu=warn,wgpu_core::device=off").unwrap());
+        assert_eq!(filter.get_level("vt100"), LevelFilter::Trace);
+        assert_eq!(filter.get_level("vt100::parser"), LevelFilter::Trace);
+        assert_eq!(filter.get_level("wgpu::backend"), LevelFilter::Warn);
+        // module names are matched on path segments
+        assert_eq!(filter.get_level("wgpu_core"), LevelFilter::Error);
+        assert_eq!(filter.get_level("wgpu_core::device::life"), LevelFilter::Off);
+        assert_eq!(filter.get_level("wgpu_terminal::app"), LevelFilter::Info);
+        assert_eq!(filter.get_max_level(), LevelFilter::Trace);
+        // longest module path wins and later entries override earlier ones
+        filter.extend(parse_log_filter("debug,vt100::parser=info,vt100=error").unwrap());
+        assert_eq!(filter.get_level("vt100::parser::csi"), LevelFilter::Info);
+        assert_eq!(filter.get_level("vt100::common"), LevelFilter::Error);
+        assert_eq!(filter.get_level("winit"), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn window_control_permission() {
+        use crate::app_window::is_window_action_allowed;
+        use crate::config::AppConfig;
+        use vt100::common::WindowAction;
+        let controls = [
+            WindowAction::SetMinimised(true), WindowAction::SetMinimised(false), WindowAction::SendToFront, WindowAction::SendToBack,
+            WindowAction::ResizeTextArea(Vector2::new(80,24)),
+        ];
+        for action in controls.iter() {
+            assert!(!is_window_action_allowed(action, false));
+            assert!(is_window_action_allowed(action, true));
+        }
+        // other actions aren't affected
+        let others = [WindowAction::Refresh, WindowAction::SetWindowTitle("title".to_owned()), WindowAction::Maximise(Vector2::new(true,true))];
+        for action in others.iter() {
+            assert!(is_window_action_allowed(action, false));
+        }
+        // disabled unless opted into
+        assert!(!AppConfig::default().allow_window_control);
+        assert!(AppConfig::from_toml("allow_window_control = true\n").unwrap().allow_window_control);
+    }
+
+    #[test]
+    fn selection_autoscroll_speed() {
+        use crate::app_window::get_autoscroll_rows;
+        let glyph_height = 16;
+        assert_eq!(get_autoscroll_rows(0, glyph_height), 0);
+        assert_eq!(get_autoscroll_rows(-1, glyph_height), -1);
+        assert_eq!(get_autoscroll_rows(15, glyph_height), 1);
+        assert_eq!(get_autoscroll_rows(16, glyph_height), 2);
+        assert_eq!(get_autoscroll_rows(-40, glyph_height), -3);
+        // capped far past the edge
+        assert_eq!(get_autoscroll_rows(10_000, glyph_height), 8);
+        assert_eq!(get_autoscroll_rows(-10_000, glyph_height), -8);
+    }
+
+    #[test]
+    fn selection_autoscroll_steps() {
+        use crate::app_window::SelectionAutoscroll;
+        use std::time::{Duration, Instant};
+        let start = Instant::now();
+        let mut autoscroll = SelectionAutoscroll::default();
+        assert_eq!(autoscroll.get_next_step(), None);
+        // first step is taken as soon as the mouse leaves the grid
+        assert_eq!(autoscroll.update(start, -20, 16), -2);
+        assert_eq!(autoscroll.get_next_step(), Some(start + Duration::from_millis(50)));
+        assert_eq!(autoscroll.update(start + Duration::from_millis(20), -20, 16), 0);
+        assert_eq!(autoscroll.update(start + Duration::from_millis(50), 4, 16), 1);
+        // stops once the mouse is back inside the grid
+        assert_eq!(autoscroll.update(start + Duration::from_millis(60), 0, 16), 0);
+        assert_eq!(autoscroll.get_next_step(), None);
+        assert_eq!(autoscroll.update(start + Duration::from_millis(70), 4, 16), 1);
+        // or the mouse is released
+        autoscroll.stop();
+        assert_eq!(autoscroll.get_next_step(), None);
+    }
+
+    #[test]
+    fn selected_columns_of_rendered_row() {
+        use crate::app_window::get_selected_columns;
+        // row continues a soft wrapped line from offset 10
+        let row_position = Vector2::new(10,3);
+        assert_eq!(get_selected_columns(row_position, 10, Vector2::new(5,3), Vector2::new(15,3)), 0..5);
+        assert_eq!(get_selected_columns(row_position, 10, Vector2::new(12,3), Vector2::new(0,5)), 2..10);
+        // selection in the scrollback above the row
+        assert_eq!(get_selected_columns(row_position, 10, Vector2::new(0,1), Vector2::new(4,2)), 0..0);
+        assert_eq!(get_selected_columns(row_position, 10, Vector2::new(0,1), Vector2::new(4,4)), 0..10);
+        assert_eq!(get_selected_columns(row_position, 10, Vector2::new(25,3), Vector2::new(30,3)), 10..10);
+    }
+
+    #[test]
+    fn load_word_characters_from_toml() {
+        use crate::config::AppConfig;
+        assert_eq!(AppConfig::default().word_characters, "_-./");
+        let config = AppConfig::from_toml("word_characters = \"_\"\n").unwrap();
+        assert_eq!(config.word_characters, "_");
+        assert_eq!(config.keyboard_shortcuts, AppConfig::default().keyboard_shortcuts);
+    }
+
+    #[test]
+    fn surface_format_selection() {
+        use crate::app_window::select_surface_format;
+        use wgpu::TextureFormat;
+        let formats = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm, TextureFormat::Rgba16Float];
+        assert_eq!(select_surface_format(&formats, false), Some(TextureFormat::Bgra8Unorm));
+        assert_eq!(select_surface_format(&formats, true), Some(TextureFormat::Rgba16Float));
+        // higher bit depth is preferred
+        let formats = [TextureFormat::Bgra8Unorm, TextureFormat::Rgb10a2Unorm];
+        assert_eq!(select_surface_format(&formats, true), Some(TextureFormat::Rgb10a2Unorm));
+        // fallback to whatever is supported
+        let formats = [TextureFormat::Bgra8UnormSrgb];
+        assert_eq!(select_surface_format(&formats, false), Some(TextureFormat::Bgra8UnormSrgb));
+        assert_eq!(select_surface_format(&[], false), None);
+    }
+
+    #[test]
+    fn present_mode_selection() {
+        use crate::app_window::select_present_mode;
+        use wgpu::PresentMode;
+        let supported = [PresentMode::Fifo, PresentMode::Immediate];
+        assert_eq!(select_present_mode(&supported, PresentMode::AutoVsync), PresentMode::AutoVsync);
+        assert_eq!(select_present_mode(&supported, PresentMode::Immediate), PresentMode::Immediate);
+        assert_eq!(select_present_mode(&supported, PresentMode::Fifo), PresentMode::Fifo);
+        // unsupported modes fall back to whether vsync was wanted
+        assert_eq!(select_present_mode(&supported, PresentMode::Mailbox), PresentMode::AutoNoVsync);
+        assert_eq!(select_present_mode(&[PresentMode::Immediate], PresentMode::FifoRelaxed), PresentMode::AutoVsync);
+    }
+
+    #[test]
+    fn alpha_mode_selection() {
+        use crate::app_window::select_alpha_mode;
+        use wgpu::CompositeAlphaMode;
+        let supported = [CompositeAlphaMode::Opaque, CompositeAlphaMode::PostMultiplied, CompositeAlphaMode::PreMultiplied];
+        assert_eq!(select_alpha_mode(&supported, false), None);
+        assert_eq!(select_alpha_mode(&supported, true), Some(CompositeAlphaMode::PreMultiplied));
+        assert_eq!(select_alpha_mode(&supported[..2], true), Some(CompositeAlphaMode::PostMultiplied));
+        // opaque surfaces can't be made transparent
+        assert_eq!(select_alpha_mode(&[CompositeAlphaMode::Opaque, CompositeAlphaMode::Auto], true), None);
+    }
+
+    #[test]
+    fn frame_rate_cap() {
+        use crate::app_window::get_frame_deadline;
+        use std::time::{Duration, Instant};
+        let last_frame = Instant::now();
+        assert_eq!(get_frame_deadline(last_frame, None), None);
+        assert_eq!(get_frame_deadline(last_frame, Some(0.0)), None);
+        assert_eq!(get_frame_deadline(last_frame, Some(f64::INFINITY)), None);
+        assert_eq!(get_frame_deadline(last_frame, Some(50.0)), Some(last_frame + Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn reconfigure_copy_shortcut() {
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutAction, ShortcutCombo, ShortcutModifiers};
+        use winit::keyboard::KeyCode;
+        let ctrl = ShortcutModifiers { ctrl: true, ..Default::default() };
+        let ctrl_shift = ShortcutModifiers { ctrl: true, shift: true, ..Default::default() };
+        let mut shortcuts = KeyboardShortcuts::default();
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyC), Some(ShortcutAction::Copy));
+        assert_eq!(shortcuts.get_action(ctrl, KeyCode::KeyY), None);
+        shortcuts.copy = vec!["Ctrl+Y".parse().unwrap()];
+        assert_eq!(shortcuts.copy, vec![ShortcutCombo::ctrl(KeyCode::KeyY)]);
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyC), None);
+        assert_eq!(shortcuts.get_action(ctrl, KeyCode::KeyY), Some(ShortcutAction::Copy));
+        // modifiers must match exactly
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyY), None);
+    }
+
+    #[test]
+    fn tab_shortcuts() {
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutAction, ShortcutModifiers};
+        use winit::keyboard::KeyCode;
+        let ctrl = ShortcutModifiers { ctrl: true, ..Default::default() };
+        let ctrl_shift = ShortcutModifiers { ctrl: true, shift: true, ..Default::default() };
+        let shortcuts = KeyboardShortcuts::default();
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyT), Some(ShortcutAction::NewTab));
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyW), Some(ShortcutAction::CloseTab));
+        assert_eq!(shortcuts.get_action(ctrl, KeyCode::PageDown), Some(ShortcutAction::NextTab));
+        assert_eq!(shortcuts.get_action(ctrl, KeyCode::PageUp), Some(ShortcutAction::PreviousTab));
+        // page keys with shift scroll
+        let shift = ShortcutModifiers { shift: true, ..Default::default() };
+        assert_eq!(shortcuts.get_action(shift, KeyCode::PageUp), Some(ShortcutAction::ScrollUp));
+    }
+
+    #[test]
+    fn navigation_keys_dispatch() {
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutAction, ShortcutModifiers, ShortcutTarget, get_shortcut_target};
+        use winit::keyboard::KeyCode;
+        let none = ShortcutModifiers::default();
+        let shift = ShortcutModifiers { shift: true, ..Default::default() };
+        let shortcuts = KeyboardShortcuts::default();
+        // plain navigation keys go to the process
+        for key in [KeyCode::PageUp, KeyCode::PageDown, KeyCode::Home, KeyCode::End] {
+            assert_eq!(shortcuts.get_action(none, key), None);
+        }
+        assert_eq!(shortcuts.get_action(shift, KeyCode::PageUp), Some(ShortcutAction::ScrollUp));
+        assert_eq!(shortcuts.get_action(shift, KeyCode::PageDown), Some(ShortcutAction::ScrollDown));
+        assert_eq!(shortcuts.get_action(shift, KeyCode::Home), Some(ShortcutAction::ScrollToTop));
+        assert_eq!(shortcuts.get_action(shift, KeyCode::End), Some(ShortcutAction::ScrollToBottom));
+        // (action, is_alternate_screen, is_alternate_screen_passthrough) => target
+        let table = [
+            (ShortcutAction::ScrollUp, false, false, ShortcutTarget::Window),
+            (ShortcutAction::ScrollUp, false, true, ShortcutTarget::Window),
+            (ShortcutAction::ScrollUp, true, false, ShortcutTarget::Window),
+            (ShortcutAction::ScrollUp, true, true, ShortcutTarget::Process),
+            (ShortcutAction::ScrollToBottom, true, true, ShortcutTarget::Process),
+            (ShortcutAction::Copy, true, true, ShortcutTarget::Window),
+            (ShortcutAction::NextTab, true, true, ShortcutTarget::Window),
+        ];
+        for (action, is_alternate_screen, is_passthrough, target) in table {
+            assert_eq!(get_shortcut_target(action, is_alternate_screen, is_passthrough), target, "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn tab_switching_and_closing() {
+        use crate::app_window::{get_active_tab_after_close, get_adjacent_tab};
+        assert_eq!(get_adjacent_tab(0, 3, true), 1);
+        assert_eq!(get_adjacent_tab(2, 3, true), 0);
+        assert_eq!(get_adjacent_tab(0, 3, false), 2);
+        assert_eq!(get_adjacent_tab(0, 1, true), 0);
+        // closing a tab before the active one keeps the same tab active
+        assert_eq!(get_active_tab_after_close(2, 0, 3), 1);
+        // closing a tab after the active one leaves it unchanged
+        assert_eq!(get_active_tab_after_close(1, 2, 2), 1);
+        // closing the active tab moves to the tab after it or the new last tab
+        assert_eq!(get_active_tab_after_close(1, 1, 3), 1);
+        assert_eq!(get_active_tab_after_close(2, 2, 2), 1);
+        assert_eq!(get_active_tab_after_close(0, 0, 0), 0);
+    }
+
+    #[test]
+    fn load_shortcuts_from_toml() {
+        use crate::config::AppConfig;
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutCombo};
+        use winit::keyboard::KeyCode;
+        let config = AppConfig::from_toml("[keyboard_shortcuts]\ncopy = [\"Ctrl+Y\", \"Ctrl+Insert\"]\n").unwrap();
+        let shortcuts = config.keyboard_shortcuts;
+        assert_eq!(shortcuts.copy, vec![ShortcutCombo::ctrl(KeyCode::KeyY), ShortcutCombo::ctrl(KeyCode::Insert)]);
+        // unspecified shortcuts keep their defaults
+        assert_eq!(shortcuts.paste, KeyboardShortcuts::default().paste);
+        assert!(AppConfig::from_toml("[keyboard_shortcuts]\ncopy = [\"Ctrl+Banana\"]\n").is_err());
+        assert!(AppConfig::from_toml("[keyboard_shortcuts]\ncopy = [\"Hyper+C\"]\n").is_err());
+    }
+
+    #[test]
+    fn parse_keybindings() {
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, KeybindingError, ShortcutAction, ShortcutModifiers};
+        use std::collections::BTreeMap;
+        use winit::keyboard::KeyCode;
+        let ctrl_shift = ShortcutModifiers { ctrl: true, shift: true, ..Default::default() };
+        let mut shortcuts = KeyboardShortcuts::default();
+        let keybindings = BTreeMap::from([
+            ("ctrl+shift+f".to_owned(), "toggle_fullscreen".to_owned()),
+            ("ctrl+shift+c".to_owned(), "paste".to_owned()),
+            ("F11".to_owned(), "none".to_owned()),
+        ]);
+        shortcuts.apply_keybindings(&keybindings).unwrap();
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyF), Some(ShortcutAction::ToggleFullscreen));
+        // rebinding a combo removes it from its previous action
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyC), Some(ShortcutAction::Paste));
+        assert!(shortcuts.copy.is_empty());
+        assert_eq!(shortcuts.get_action(ShortcutModifiers::default(), KeyCode::F11), None);
+        // other defaults are kept
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyV), Some(ShortcutAction::Paste));
+
+        let mut shortcuts = KeyboardShortcuts::default();
+        let keybindings = BTreeMap::from([("ctrl+banana".to_owned(), "copy".to_owned())]);
+        assert!(matches!(shortcuts.apply_keybindings(&keybindings), Err(KeybindingError::InvalidShortcut(..))));
+        let keybindings = BTreeMap::from([("ctrl+c".to_owned(), "launch".to_owned())]);
+        assert!(matches!(shortcuts.apply_keybindings(&keybindings), Err(KeybindingError::UnknownAction(..))));
+        // failed bindings leave the shortcuts untouched
+        assert_eq!(shortcuts, KeyboardShortcuts::default());
+    }
+
+    #[test]
+    fn keybinding_conflicts() {
+        use crate::config::AppConfig;
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, KeybindingError, ShortcutCombo};
+        use std::collections::BTreeMap;
+        use winit::keyboard::KeyCode;
+        assert_eq!(KeyboardShortcuts::default().check_conflicts(), Ok(()));
+        // same combo written differently
+        let mut shortcuts = KeyboardShortcuts::default();
+        let keybindings = BTreeMap::from([
+            ("Ctrl+Y".to_owned(), "copy".to_owned()),
+            ("ctrl+y".to_owned(), "paste".to_owned()),
+        ]);
+        assert_eq!(shortcuts.apply_keybindings(&keybindings), Err(KeybindingError::Conflict {
+            combo: ShortcutCombo::ctrl(KeyCode::KeyY),
+            first: "copy",
+            second: "paste",
+        }));
+        let keybindings = BTreeMap::from([
+            ("Ctrl+Y".to_owned(), "copy".to_owned()),
+            ("ctrl+y".to_owned(), "copy".to_owned()),
+        ]);
+        assert_eq!(shortcuts.apply_keybindings(&keybindings), Ok(()));
+
+        let config = AppConfig::from_toml("[keyboard_shortcuts]\ncopy = [\"PageUp\"]\n").unwrap();
+        let err = config.get_keyboard_shortcuts().unwrap_err().to_string();
+        assert!(err.contains("copy") && err.contains("scroll_up"), "{}", err);
+        // keybindings resolve the conflict by moving the combo
+        let config = AppConfig::from_toml("[keybindings]\n\"pageup\" = \"copy\"\n").unwrap();
+        let shortcuts = config.get_keyboard_shortcuts().unwrap();
+        assert_eq!(shortcuts.copy.last(), Some(&ShortcutCombo::new(KeyCode::PageUp)));
+        assert!(shortcuts.scroll_up.is_empty());
+    }
+
+    #[test]
+    fn tab_bar_layout() {
+        use crate::tab_bar::{TabBar, TabSpan};
+        let mut tab_bar = TabBar::default();
+        tab_bar.update(&["bash", ""], 80);
+        // tabs are capped in width instead of stretching across the window
+        assert_eq!(tab_bar.get_spans(), &[TabSpan { start: 0, end: 24 }, TabSpan { start: 24, end: 48 }]);
+        assert_eq!(tab_bar.get_label(0), Some(" 1: bash"));
+        assert_eq!(tab_bar.get_label(1), Some(" 2: shell"));
+        assert_eq!(tab_bar.get_tab_at_column(0), Some(0));
+        assert_eq!(tab_bar.get_tab_at_column(24), Some(1));
+        assert_eq!(tab_bar.get_tab_at_column(48), None);
+        // labels are truncated to leave a blank separator column
+        tab_bar.update(&["vim src/main.rs", "htop"], 12);
+        assert_eq!(tab_bar.get_spans(), &[TabSpan { start: 0, end: 6 }, TabSpan { start: 6, end: 12 }]);
+        assert_eq!(tab_bar.get_label(0), Some(" 1: v"));
+        // tabs that don't fit are dropped
+        tab_bar.update(&["a", "b", "c"], 2);
+        assert_eq!(tab_bar.get_spans().len(), 2);
+        assert_eq!(tab_bar.get_label(2), None);
+        assert_eq!(tab_bar.get_tab_at_column(1), Some(1));
+    }
+
+    const SAMPLE_CONFIG: &str = r##"
+shell = "/usr/bin/zsh"
+shell_arguments = ["--login"]
+scrollback_lines = 20000
+cursor_style = "underline"
+opacity = 0.9
+
+[font]
+filename = "./fonts/mono.ttf"
+size = 18.0
+
+[colours]
+foreground = "#c0c0c0"
+background = "#101010"
+cursor = "#ff8000"
+"##;
+
+    #[test]
+    fn load_sample_config() {
+        use crate::config::AppConfig;
+        use vt100::common::{CursorStyle, Rgb8};
+        let config = AppConfig::from_toml(SAMPLE_CONFIG).unwrap();
+        assert_eq!(config.shell.as_deref(), Some("/usr/bin/zsh"));
+        assert_eq!(config.shell_arguments, vec!["--login".to_owned()]);
+        assert_eq!(config.scrollback_lines, Some(20000));
+        assert_eq!(config.cursor_style, Some(CursorStyle::Underline));
+        assert_eq!(config.opacity, 0.9);
+        assert_eq!(config.font.filename.as_deref(), Some("./fonts/mono.ttf"));
+        assert_eq!(config.font.size, Some(18.0));
+        assert_eq!(config.colours.foreground, Some(Rgb8 { r: 0xC0, g: 0xC0, b: 0xC0 }));
+        assert_eq!(config.colours.background, Some(Rgb8 { r: 0x10, g: 0x10, b: 0x10 }));
+        assert_eq!(config.colours.cursor, Some(Rgb8 { r: 0xFF, g: 0x80, b: 0x00 }));
+        // unspecified values keep their defaults
+        assert_eq!(config.word_characters, AppConfig::default().word_characters);
+        assert_eq!(AppConfig::from_toml("").unwrap(), AppConfig::default());
+    }
+
+    #[test]
+    fn config_errors_name_the_key() {
+        use crate::config::AppConfig;
+        let err = AppConfig::from_toml("scrollback = 100\n").unwrap_err().to_string();
+        assert!(err.contains("scrollback"), "{}", err);
+        let err = AppConfig::from_toml("[font]\nsize = \"big\"\n").unwrap_err().to_string();
+        assert!(err.contains("size"), "{}", err);
+        let err = AppConfig::from_toml("cursor_style = \"beam\"\n").unwrap_err().to_string();
+        assert!(err.contains("beam"), "{}", err);
+        let err = AppConfig::from_toml("[colours]\nforeground = \"red\"\n").unwrap_err().to_string();
+        assert!(err.contains("foreground"), "{}", err);
+        let err = AppConfig::from_toml("opacity = 1.5\n").unwrap_err().to_string();
+        assert!(err.contains("opacity"), "{}", err);
+    }
+
+    #[test]
+    fn bell_config() {
+        use crate::bell::BellMode;
+        use crate::config::AppConfig;
+        assert_eq!(AppConfig::default().bell, BellMode::Visual);
+        for (name, mode) in [("none", BellMode::None), ("visual", BellMode::Visual), ("audible", BellMode::Audible), ("both", BellMode::Both)] {
+            let config = AppConfig::from_toml(format!("bell = \"{}\"\n", name).as_str()).unwrap();
+            assert_eq!(config.bell, mode);
+        }
+        assert!(BellMode::Both.is_visual() && BellMode::Both.is_audible());
+        assert!(!BellMode::None.is_visual() && !BellMode::None.is_audible());
+        let err = AppConfig::from_toml("bell = \"loud\"\n").unwrap_err().to_string();
+        assert!(err.contains("loud"), "{}", err);
+        assert!(err.contains("none, visual, audible or both"), "{}", err);
+    }
+
+    #[test]
+    fn bell_rate_limiter() {
+        use crate::bell::BellRateLimiter;
+        use std::time::{Duration, Instant};
+        let mut limiter = BellRateLimiter::new(Duration::from_millis(500));
+        let start = Instant::now();
+        assert!(limiter.try_ring(start));
+        // bells within the interval are dropped
+        assert!(!limiter.try_ring(start));
+        assert!(!limiter.try_ring(start + Duration::from_millis(499)));
+        assert!(limiter.try_ring(start + Duration::from_millis(500)));
+        // dropped bells don't extend the interval
+        assert!(!limiter.try_ring(start + Duration::from_millis(900)));
+        assert!(limiter.try_ring(start + Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        use crate::config::{AppConfig, ConfigOverrides, parse_opacity};
+        use vt100::common::Rgb8;
+        let mut config = AppConfig::from_toml(SAMPLE_CONFIG).unwrap();
+        config.apply_overrides(ConfigOverrides::default());
+        assert_eq!(config, AppConfig::from_toml(SAMPLE_CONFIG).unwrap());
+
+        let cursor = Rgb8 { r: 1, g: 2, b: 3 };
+        config.apply_overrides(ConfigOverrides {
+            shell: Some("/bin/sh".to_owned()),
+            font_family: Some("Consolas".to_owned()),
+            font_size: Some(12.0),
+            cursor_colour: Some(cursor),
+            opacity: Some(0.5),
+            ..ConfigOverrides::default()
+        });
+        assert_eq!(config.shell.as_deref(), Some("/bin/sh"));
+        // arguments from the config file aren't passed to a different shell
+        assert!(config.shell_arguments.is_empty());
+        // font family from the command line replaces the font file from the config file
+        assert_eq!(config.font.family.as_deref(), Some("Consolas"));
+        assert_eq!(config.font.filename, None);
+        assert_eq!(config.font.size, Some(12.0));
+        assert_eq!(config.colours.cursor, Some(cursor));
+        assert_eq!(config.opacity, 0.5);
+        assert_eq!(parse_opacity("0.25"), Ok(0.25));
+        assert!(parse_opacity("-0.1").is_err());
+        // values without a command line flag are kept
+        assert_eq!(config.scrollback_lines, Some(20000));
+    }
+
+    #[test]
+    fn bundled_themes() {
+        use crate::themes::{get_theme, get_theme_names};
+        let names: Vec<&str> = get_theme_names().collect();
+        assert!(names.contains(&"solarized-dark"));
+        assert!(names.contains(&"gruvbox"));
+        for name in names {
+            assert!(get_theme(name).is_some(), "{}", name);
+        }
+        assert!(get_theme("missing").is_none());
+        // default theme matches the built in colour table
+        let theme = get_theme("one-half-dark").unwrap();
+        assert_eq!(theme.palette, terminal::colour_table::get_default_palette());
+    }
+
+    #[test]
+    fn theme_colours_can_be_overridden() {
+        use crate::config::{AppConfig, ConfigOverrides};
+        use crate::themes::get_theme;
+        use vt100::common::Rgb8;
+        let gruvbox = get_theme("gruvbox").unwrap();
+        let red = Rgb8 { r: 0xFF, g: 0, b: 0 };
+        let mut config = AppConfig::from_toml("theme = \"gruvbox\"\n[colours]\nbackground = \"#ff0000\"\n").unwrap();
+        let colours = config.get_colours().unwrap();
+        assert_eq!(colours.palette, Some(gruvbox.palette));
+        assert_eq!(colours.foreground, Some(gruvbox.foreground));
+        assert_eq!(colours.background, Some(red));
+        assert_eq!(colours.selection, gruvbox.selection);
+        // theme on the command line replaces the theme from the config file
+        config.apply_overrides(ConfigOverrides { theme: Some("solarized-dark".to_owned()), ..ConfigOverrides::default() });
+        let solarized = get_theme("solarized-dark").unwrap();
+        let colours = config.get_colours().unwrap();
+        assert_eq!(colours.palette, Some(solarized.palette));
+        assert_eq!(colours.background, Some(red));
+        // no theme leaves the terminal defaults
+        assert_eq!(AppConfig::default().get_colours().unwrap(), crate::config::ColourConfig::default());
+        let config = AppConfig::from_toml("theme = \"missing\"\n").unwrap();
+        assert!(config.get_colours().unwrap_err().to_string().contains("missing"));
+    }
+
+    #[test]
+    fn load_palette_from_toml() {
+        use crate::config::AppConfig;
+        use vt100::common::Rgb8;
+        let palette: Vec<String> = (0..16).map(|i| format!("\"#0000{:02x}\"", i)).collect();
+        let data = format!("[colours]\npalette = [{}]\nselection = \"#202020\"\n", palette.join(", "));
+        let colours = AppConfig::from_toml(data.as_str()).unwrap().get_colours().unwrap();
+        let palette = colours.palette.unwrap();
+        assert_eq!(palette[0], Rgb8 { r: 0, g: 0, b: 0 });
+        assert_eq!(palette[15], Rgb8 { r: 0, g: 0, b: 15 });
+        assert_eq!(colours.selection, Some(Rgb8 { r: 0x20, g: 0x20, b: 0x20 }));
+        let err = AppConfig::from_toml("[colours]\npalette = [\"#000000\"]\n").unwrap_err().to_string();
+        assert!(err.contains("Expected 16 colours"), "{}", err);
+    }
+
+    #[test]
+    fn default_paste_shortcuts() {
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutAction, ShortcutModifiers};
+        use winit::keyboard::KeyCode;
+        let shift = ShortcutModifiers { shift: true, ..Default::default() };
+        let ctrl_shift = ShortcutModifiers { ctrl: true, shift: true, ..Default::default() };
+        let shortcuts = KeyboardShortcuts::default();
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyV), Some(ShortcutAction::Paste));
+        assert_eq!(shortcuts.get_action(shift, KeyCode::Insert), Some(ShortcutAction::Paste));
+    }
+
+    #[test]
+    fn selection_bounds_are_ordered() {
+        use crate::app_window::Selection;
+        let selection = Selection { start: Vector2::new(2,1), end: Vector2::new(4,3) };
+        assert_eq!(selection.get_bounds(), (Vector2::new(2,1), Vector2::new(5,3)));
+        // dragging backwards gives the same bounds
+        let selection = Selection { start: Vector2::new(4,3), end: Vector2::new(2,1) };
+        assert_eq!(selection.get_bounds(), (Vector2::new(2,1), Vector2::new(5,3)));
+        // same row is ordered by column
+        let selection = Selection { start: Vector2::new(7,0), end: Vector2::new(3,0) };
+        assert_eq!(selection.get_bounds(), (Vector2::new(3,0), Vector2::new(8,0)));
+    }
+
+    #[test]
+    fn process_exit_message() {
+        use crate::app_window::get_process_exit_message;
+        assert_eq!(get_process_exit_message(Some(0)), "\x1b[0m\r\n\x1b[7m[process exited with code 0, press any key to close]\x1b[0m");
+        // killed by a signal
+        assert_eq!(get_process_exit_message(None), "\x1b[0m\r\n\x1b[7m[process exited, press any key to close]\x1b[0m");
+    }
+
+    #[test]
+    fn dropped_path_quoting() {
+        use crate::app_window::{quote_unix_path, quote_windows_path};
+        assert_eq!(quote_unix_path("/home/user/file.txt"), "'/home/user/file.txt'");
+        assert_eq!(quote_unix_path("/tmp/my file"), "'/tmp/my file'");
+        assert_eq!(quote_unix_path("/tmp/it's here"), "'/tmp/it'\\''s here'");
+        assert_eq!(quote_unix_path("/tmp/$HOME `ls` \"x\""), "'/tmp/$HOME `ls` \"x\"'");
+        assert_eq!(quote_windows_path("C:\\Program Files\\app.exe"), "\"C:\\Program Files\\app.exe\"");
+        assert_eq!(quote_windows_path("C:\\it's"), "\"C:\\it's\"");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dropped_paths_are_space_separated() {
+        use crate::app_window::get_dropped_paths_text;
+        use std::path::PathBuf;
+        let paths = [PathBuf::from("/tmp/a b"), PathBuf::from("/tmp/c")];
+        assert_eq!(get_dropped_paths_text(&paths), "'/tmp/a b' '/tmp/c'");
+    }
+
+    #[test]
+    fn single_vertical_split_layout() {
+        use crate::pane_layout::{PaneLayout, PaneRect, SplitDirection};
+        let grid_size = Vector2::new(81,24);
+        let mut layout = PaneLayout::new(0);
+        assert_eq!(layout.get_pane_rects(grid_size), vec![(0, PaneRect { position: Vector2::new(0,0), size: grid_size })]);
+        assert!(layout.get_dividers(grid_size).is_empty());
+        assert!(layout.split(0, SplitDirection::Vertical, 1));
+        // one column is taken by the divider
+        assert_eq!(layout.get_pane_rects(grid_size), vec![
+            (0, PaneRect { position: Vector2::new(0,0), size: Vector2::new(40,24) }),
+            (1, PaneRect { position: Vector2::new(41,0), size: Vector2::new(40,24) }),
+        ]);
+        assert_eq!(layout.get_dividers(grid_size), vec![PaneRect { position: Vector2::new(40,0), size: Vector2::new(1,24) }]);
+        // odd widths give the extra column to the second pane
+        let rects = layout.get_pane_rects(Vector2::new(80,24));
+        assert_eq!(rects[0].1.size, Vector2::new(39,24));
+        assert_eq!(rects[1].1, PaneRect { position: Vector2::new(40,0), size: Vector2::new(40,24) });
+        assert_eq!(layout.get_pane_at(grid_size, Vector2::new(39,10)), Some(0));
+        assert_eq!(layout.get_pane_at(grid_size, Vector2::new(40,10)), None);
+        assert_eq!(layout.get_pane_at(grid_size, Vector2::new(41,10)), Some(1));
+        assert_eq!(layout.get_pane_at(grid_size, Vector2::new(81,0)), None);
+    }
+
+    #[test]
+    fn nested_split_layout() {
+        use crate::pane_layout::{PaneDirection, PaneLayout, PaneRect, SplitDirection};
+        let grid_size = Vector2::new(81,25);
+        let mut layout = PaneLayout::new(0);
+        assert!(layout.split(0, SplitDirection::Vertical, 1));
+        assert!(layout.split(1, SplitDirection::Horizontal, 2));
+        // ids must be unique and the split pane must exist
+        assert!(!layout.split(0, SplitDirection::Vertical, 2));
+        assert!(!layout.split(5, SplitDirection::Vertical, 6));
+        assert_eq!(layout.get_pane_ids(), vec![0, 1, 2]);
+        assert_eq!(layout.get_pane_rect(grid_size, 1), Some(PaneRect { position: Vector2::new(41,0), size: Vector2::new(40,12) }));
+        assert_eq!(layout.get_pane_rect(grid_size, 2), Some(PaneRect { position: Vector2::new(41,13), size: Vector2::new(40,12) }));
+        assert_eq!(layout.get_dividers(grid_size).len(), 2);
+        // navigation crosses the divider
+        assert_eq!(layout.get_neighbour(grid_size, 0, PaneDirection::Right), Some(1));
+        assert_eq!(layout.get_neighbour(grid_size, 2, PaneDirection::Left), Some(0));
+        assert_eq!(layout.get_neighbour(grid_size, 1, PaneDirection::Down), Some(2));
+        assert_eq!(layout.get_neighbour(grid_size, 2, PaneDirection::Up), Some(1));
+        assert_eq!(layout.get_neighbour(grid_size, 0, PaneDirection::Left), None);
+        assert_eq!(layout.get_neighbour(grid_size, 0, PaneDirection::Up), None);
+        assert_eq!(layout.get_neighbour(grid_size, 2, PaneDirection::Down), None);
+        // sibling takes over the area of a removed pane
+        assert!(layout.remove(1));
+        assert_eq!(layout.get_pane_rect(grid_size, 2), Some(PaneRect { position: Vector2::new(41,0), size: Vector2::new(40,25) }));
+        assert!(layout.remove(0));
+        assert_eq!(layout, PaneLayout::new(2));
+        assert!(!layout.remove(2));
+        assert_eq!(layout.get_total_panes(), 1);
+    }
+
+    #[test]
+    fn split_layout_of_tiny_grid() {
+        use crate::pane_layout::{PaneLayout, SplitDirection};
+        let mut layout = PaneLayout::new(0);
+        layout.split(0, SplitDirection::Vertical, 1);
+        let rects = layout.get_pane_rects(Vector2::new(2,1));
+        assert_eq!(rects[0].1.size, Vector2::new(0,1));
+        assert_eq!(rects[1].1.size, Vector2::new(1,1));
+        let rects = layout.get_pane_rects(Vector2::new(0,0));
+        assert!(rects.iter().all(|(_, rect)| rect.size == Vector2::new(0,0)));
+    }
+
+    #[test]
+    fn compose_vertical_split_grid() {
+        use crate::pane_layout::{PaneLayout, SplitDirection, copy_pane_cells, get_divider_character};
+        let grid_size = Vector2::new(5,2);
+        let mut layout = PaneLayout::new(0);
+        layout.split(0, SplitDirection::Vertical, 1);
+        let rects = layout.get_pane_rects(grid_size);
+        let mut grid = vec!['.'; grid_size.x*grid_size.y];
+        // left pane has an extra render row for partial scrolling which is clipped
+        copy_pane_cells(&mut grid, grid_size.x, &['a','b','c','d','e','f'], Vector2::new(2,3), rects[0].1);
+        // right pane hasn't been resized to its area yet
+        copy_pane_cells(&mut grid, grid_size.x, &['x','y','z'], Vector2::new(3,1), rects[1].1);
+        let divider = layout.get_dividers(grid_size)[0];
+        assert_eq!(get_divider_character(divider), '│');
+        grid[divider.position.x] = '|';
+        grid[grid_size.x + divider.position.x] = '|';
+        assert_eq!(grid.iter().collect::<String>(), "ab|xycd|..");
+        layout.split(1, SplitDirection::Horizontal, 2);
+        assert_eq!(get_divider_character(layout.get_dividers(Vector2::new(5,5))[1]), '─');
+    }
+
+    #[test]
+    fn split_shortcuts() {
+        use crate::keyboard_shortcuts::{KeyboardShortcuts, ShortcutAction, ShortcutModifiers};
+        use winit::keyboard::KeyCode;
+        let ctrl_shift = ShortcutModifiers { ctrl: true, shift: true, ..Default::default() };
+        let shortcuts = KeyboardShortcuts::default();
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyE), Some(ShortcutAction::SplitVertical));
+        assert_eq!(shortcuts.get_action(ctrl_shift, KeyCode::KeyO), Some(ShortcutAction::SplitHorizontal));
+        assert_eq!(ShortcutAction::SplitVertical.get_name(), "split_vertical");
+    }
+
+    fn create_window_metrics() -> crate::window_report::WindowMetrics {
+        crate::window_report::WindowMetrics {
+            window_position: Vector2::new(100,50),
+            window_size: Vector2::new(660,404),
+            text_area_position: Vector2::new(10,10),
+            text_area_size: Vector2::new(640,384),
+            screen_size: Vector2::new(1920,1080),
+            cell_size: Vector2::new(8,16),
+            grid_size: Vector2::new(80,24),
+            is_minimised: false,
+        }
+    }
+
+    #[test]
+    fn window_size_reports() {
+        use crate::window_report::encode_window_report;
+        use vt100::common::WindowAction;
+        let metrics = create_window_metrics();
+        let get_reply = |action: WindowAction| encode_window_report(&action, &metrics).unwrap();
+        // sizes are height then width
+        assert_eq!(get_reply(WindowAction::GetTextAreaSize), "\x1b[4;384;640t");
+        assert_eq!(get_reply(WindowAction::GetWindowSize), "\x1b[4;404;660t");
+        assert_eq!(get_reply(WindowAction::GetScreenSize), "\x1b[5;1080;1920t");
+        assert_eq!(get_reply(WindowAction::GetCellSize), "\x1b[6;16;8t");
+        assert_eq!(get_reply(WindowAction::GetTextAreaGridSize), "\x1b[8;24;80t");
+        assert_eq!(get_reply(WindowAction::GetScreenGridSize), "\x1b[9;67;240t");
+    }
+
+    #[test]
+    fn window_state_and_position_reports() {
+        use crate::window_report::encode_window_report;
+        use vt100::common::WindowAction;
+        let mut metrics = create_window_metrics();
+        // positions are x then y
+        assert_eq!(encode_window_report(&WindowAction::GetWindowPosition, &metrics).unwrap(), "\x1b[3;100;50t");
+        assert_eq!(encode_window_report(&WindowAction::GetTextAreaPosition, &metrics).unwrap(), "\x1b[3;10;10t");
+        assert_eq!(encode_window_report(&WindowAction::GetWindowState, &metrics).unwrap(), "\x1b[1t");
+        metrics.is_minimised = true;
+        assert_eq!(encode_window_report(&WindowAction::GetWindowState, &metrics).unwrap(), "\x1b[2t");
+        // other actions aren't reports
+        assert_eq!(encode_window_report(&WindowAction::Refresh, &metrics), None);
+    }
+
+    #[test]
+    fn title_stack_push_and_pop() {
+        use crate::title_stack::{TitleStack, MAX_TITLE_STACK_SIZE};
+        let mut stack = TitleStack::default();
+        assert_eq!(stack.pop(), None);
+        stack.push("bash");
+        stack.push("vim main.rs");
+        assert_eq!(stack.pop().as_deref(), Some("vim main.rs"));
+        assert_eq!(stack.pop().as_deref(), Some("bash"));
+        assert!(stack.is_empty());
+        // oldest titles are dropped once full
+        for i in 0..MAX_TITLE_STACK_SIZE+2 {
+            stack.push(format!("title {}", i).as_str());
+        }
+        assert_eq!(stack.len(), MAX_TITLE_STACK_SIZE);
+        assert_eq!(stack.pop(), Some(format!("title {}", MAX_TITLE_STACK_SIZE+1)));
+        let mut last = None;
+        while let Some(title) = stack.pop() {
+            last = Some(title);
+        }
+        assert_eq!(last.as_deref(), Some("title 2"));
+    }
+
+    #[test]
+    fn icon_label_report() {
+        use crate::window_report::encode_icon_label_report;
+        assert_eq!(encode_icon_label_report("vim"), "\x1b]Lvim\x1b\\");
+        assert_eq!(encode_icon_label_report(""), "\x1b]L\x1b\\");
+        // control characters can't end the reply early
+        assert_eq!(encode_icon_label_report("a\x1b\\b\x07"), "\x1b]La\\b\x1b\\");
+    }
+
+    #[test]
+    fn requested_grid_size() {
+        use crate::window_report::get_requested_grid_size;
+        // rows are given first
+        assert_eq!(get_requested_grid_size(Vector2::new(24,80), Vector2::new(120,40)), Vector2::new(80,24));
+        // zero keeps the current size
+        assert_eq!(get_requested_grid_size(Vector2::new(0,100), Vector2::new(120,40)), Vector2::new(100,40));
+        assert_eq!(get_requested_grid_size(Vector2::new(30,0), Vector2::new(120,40)), Vector2::new(120,30));
+    }
+
+    #[test]
+    fn window_geometry_round_trip() {
+        use crate::config::AppConfig;
+        use crate::window_geometry::WindowGeometry;
+        let geometry = WindowGeometry { position: Some([-20, 40]), size: [800, 600], is_maximised: true };
+        assert_eq!(WindowGeometry::from_toml(geometry.to_toml().unwrap().as_str()).unwrap(), geometry);
+        // position is left out if the platform didn't report it
+        let geometry = WindowGeometry { position: None, ..geometry };
+        assert_eq!(WindowGeometry::from_toml(geometry.to_toml().unwrap().as_str()).unwrap(), geometry);
+        assert!(WindowGeometry::from_toml("size = [800]\nis_maximised = false\n").is_err());
+        assert!(AppConfig::default().remember_window_geometry);
+        assert!(!AppConfig::from_toml("remember_window_geometry = false\n").unwrap().remember_window_geometry);
+    }
+
+    #[test]
+    fn window_geometry_clamped_to_monitors() {
+        use crate::window_geometry::{MonitorArea, WindowGeometry};
+        let monitors = [
+            MonitorArea { position: Vector2::new(0,0), size: Vector2::new(1920,1080) },
+            MonitorArea { position: Vector2::new(1920,0), size: Vector2::new(1280,1024) },
+        ];
+        let create = |x: i32, y: i32, width: u32, height: u32| {
+            WindowGeometry { position: Some([x, y]), size: [width, height], is_maximised: false }
+        };
+        // visible windows are left alone even if partially offscreen
+        assert_eq!(create(100, 100, 800, 600).clamp_to_monitors(&monitors), create(100, 100, 800, 600));
+        assert_eq!(create(-400, 900, 800, 600).clamp_to_monitors(&monitors), create(-400, 900, 800, 600));
+        assert_eq!(create(2000, 50, 800, 600).clamp_to_monitors(&monitors), create(2000, 50, 800, 600));
+        // window on an unplugged monitor is moved inside the first monitor
+        assert_eq!(create(4000, 200, 800, 600).clamp_to_monitors(&monitors), create(1120, 200, 800, 600));
+        assert_eq!(create(-900, -700, 800, 600).clamp_to_monitors(&monitors), create(0, 0, 800, 600));
+        // barely visible windows are moved as well
+        assert_eq!(create(1900, 1070, 800, 600).clamp_to_monitors(&monitors), create(1120, 480, 800, 600));
+        // window is shrunk to fit the monitor it ends up on
+        assert_eq!(create(1920, 0, 2560, 1440).clamp_to_monitors(&monitors), create(1920, 0, 1280, 1024));
+        assert_eq!(create(5000, 0, 2560, 1440).clamp_to_monitors(&monitors), create(0, 0, 1920, 1080));
+        // without a position only the size is checked
+        let geometry = WindowGeometry { position: None, size: [4000, 500], is_maximised: true };
+        assert_eq!(geometry.clamp_to_monitors(&monitors), WindowGeometry { size: [1920, 500], ..geometry });
+        // nothing is known about the display
+        assert_eq!(create(-5000, 0, 800, 600).clamp_to_monitors(&[]), create(-5000, 0, 800, 600));
+    }
+
+    #[test]
+    fn accessibility_tree_debounce() {
+        use crate::accessibility::{AccessibilityTree, ACCESSIBILITY_DEBOUNCE};
+        use terminal::terminal_display::AccessibleRow;
+        use std::time::{Duration, Instant};
+        let create_rows = |text: &str| vec![AccessibleRow { text: text.to_owned(), has_link: false, is_focused_row: true }];
+        let mut tree = AccessibilityTree::default();
+        let start = Instant::now();
+        assert_eq!(tree.get_deadline(), None);
+        assert!(!tree.is_update_due(start + ACCESSIBILITY_DEBOUNCE));
+        // later changes don't push back the deadline
+        tree.mark_changed(start);
+        tree.mark_changed(start + Duration::from_millis(50));
+        assert_eq!(tree.get_deadline(), Some(start + ACCESSIBILITY_DEBOUNCE));
+        assert!(!tree.is_update_due(start + Duration::from_millis(99)));
+        assert!(tree.is_update_due(start + ACCESSIBILITY_DEBOUNCE));
+        assert!(tree.update(create_rows("$ ls")));
+        assert_eq!(tree.get_deadline(), None);
+        assert_eq!(tree.get_rows(), create_rows("$ ls").as_slice());
+        // listener isn't called again if nothing visible changed
+        tree.mark_changed(start + Duration::from_millis(200));
+        assert!(!tree.update(create_rows("$ ls")));
+        assert_eq!(tree.get_deadline(), None);
+        assert!(tree.update(create_rows("$ ls -l")));
+    }
+
+    #[test]
+    fn window_icon_decoding() {
+        use crate::window_icon::{decode_icon, load_default_icon};
+        assert!(load_default_icon().is_some());
+        assert!(decode_icon(b"not a png").is_err());
+        assert!(decode_icon(&[]).is_err());
+    }
+
+    // 3x2 grid with a bold red word followed by a default space and an underlined row
+    fn create_styled_grid() -> (Vec<terminal::Cell>, Vector2<usize>, terminal::Pen) {
+        use terminal::{Cell, Pen, StyleFlags};
+        use vt100::common::{Rgb8, UnderlineStyle};
+        let default_pen = Pen::default();
+        let red = Pen {
+            foreground_colour: Rgb8 { r: 255, g: 0, b: 0 },
+            style_flags: StyleFlags::Bold,
+            ..default_pen
+        };
+        let underline = Pen {
+            style_flags: StyleFlags::Underline,
+            underline_style: UnderlineStyle::Curly,
+            ..default_pen
+        };
+        let cells = vec![
+            Cell { character: 'a', pen: red },
+            Cell { character: '<', pen: red },
+            Cell { character: ' ', pen: default_pen },
+            Cell { character: 'b', pen: underline },
+            Cell { character: 'c', pen: underline },
+            Cell { character: 'd', pen: underline },
+        ];
+        (cells, Vector2::new(3,2), default_pen)
+    }
+
+    #[test]
+    fn format_grid_as_plain_text() {
+        use crate::grid_format::{GridFormat, format_grid};
+        let (cells, size, default_pen) = create_styled_grid();
+        assert_eq!(format_grid(&cells, size, &default_pen, GridFormat::Plain), "a< \nbcd\n");
+    }
+
+    #[test]
+    fn format_grid_as_ansi() {
+        use crate::grid_format::{GridFormat, format_grid};
+        use terminal::{Cell, Pen, StyleFlags};
+        let (cells, size, default_pen) = create_styled_grid();
+        // codes are only written when the style changes and rows end with a reset
+        let expected = "\x1b[1;38;2;255;0;0ma<\x1b[22;39m \n\x1b[4:3mbcd\x1b[0m\n";
+        assert_eq!(format_grid(&cells, size, &default_pen, GridFormat::Ansi), expected);
+        // dropping bold keeps dim
+        let bold_dim = Pen { style_flags: StyleFlags::Bold | StyleFlags::Dim, ..default_pen };
+        let dim = Pen { style_flags: StyleFlags::Dim, ..default_pen };
+        let cells = [Cell { character: 'x', pen: bold_dim }, Cell { character: 'y', pen: dim }];
+        assert_eq!(format_grid(&cells, Vector2::new(2,1), &default_pen, GridFormat::Ansi), "\x1b[1;2mx\x1b[22;2my\x1b[0m\n");
+        // hyperlinks don't change the style
+        let link = Pen { hyperlink: Some(0), ..default_pen };
+        let cells = [Cell { character: 'x', pen: link }];
+        assert_eq!(format_grid(&cells, Vector2::new(1,1), &default_pen, GridFormat::Ansi), "x\n");
+    }
+
+    #[test]
+    fn format_grid_as_html() {
+        use crate::grid_format::{GridFormat, format_grid};
+        let (cells, size, default_pen) = create_styled_grid();
+        let expected = concat!(
+            "<pre style=\"color:#ffffff;background-color:#000000\">\n",
+            "<span style=\"color:#ff0000;background-color:#000000;font-weight:bold\">a&lt;</span> \n",
+            "<span style=\"color:#ffffff;background-color:#000000;text-decoration:underline;text-decoration-style:wavy\">bcd</span>\n",
+            "</pre>\n",
+        );
+        assert_eq!(format_grid(&cells, size, &default_pen, GridFormat::Html), expected);
+    }
+
+    #[test]
+    fn parse_headless_input_escapes() {
+        use crate::headless::parse_escaped_input;
+        assert_eq!(parse_escaped_input("ls\\n").unwrap(), b"ls\n");
+        assert_eq!(parse_escaped_input("\\e[A\\r").unwrap(), b"\x1b[A\r");
+        assert_eq!(parse_escaped_input("\\x1b\\x03\\\\\\t").unwrap(), b"\x1b\x03\\\t");
+        assert_eq!(parse_escaped_input("日本").unwrap(), "日本".as_bytes());
+        assert!(parse_escaped_input("\\x1").is_err());
+        assert!(parse_escaped_input("\\xzz").is_err());
+        assert!(parse_escaped_input("\\q").is_err());
+        assert!(parse_escaped_input("trailing\\").is_err());
+    }
+
+    #[test]
+    fn headless_exit_codes() {
+        use crate::headless::HeadlessExit;
+        let codes = [
+            HeadlessExit::PatternFound.get_exit_code(),
+            HeadlessExit::PatternNotFound.get_exit_code(),
+            HeadlessExit::Timeout.get_exit_code(),
+        ];
+        assert_eq!(codes, [0, 2, 124]);
+        // process exit code is passed through
+        assert_eq!(HeadlessExit::ProcessExited(Some(0)).get_exit_code(), 0);
+        assert_eq!(HeadlessExit::ProcessExited(Some(3)).get_exit_code(), 3);
+        assert_eq!(HeadlessExit::ProcessExited(None).get_exit_code(), 1);
+    }
+
+    #[test]
+    fn frame_counter_accumulation() {
+        use crate::frame_counter::FrameCounter;
+        use std::time::{Duration, Instant};
+        let start = Instant::now();
+        let mut counter = FrameCounter::new(start);
+        for i in 1..=40 {
+            counter.update_at(start + Duration::from_millis(25*i));
+        }
+        // nothing is measured until more than a second has passed
+        assert_eq!(counter.get_fps(), 0.0);
+        counter.update_at(start + Duration::from_millis(1025));
+        assert!((counter.get_fps() - 40.0).abs() < 0.01, "{}", counter.get_fps());
+        assert!((counter.get_frame_time_ms() - 25.0).abs() < 0.01, "{}", counter.get_frame_time_ms());
+        // frames are counted again from the last measurement
+        counter.update_at(start + Duration::from_millis(3050));
+        assert!((counter.get_fps() - 0.49).abs() < 0.01, "{}", counter.get_fps());
+    }
+
+    #[test]
+    fn throughput_counter_accumulation() {
+        use crate::frame_counter::ThroughputCounter;
+        use std::time::{Duration, Instant};
+        let start = Instant::now();
+        let mut counter = ThroughputCounter::default();
+        // bytes read before the first update aren't counted
+        counter.update_at(start, 5000);
+        counter.update_at(start + Duration::from_millis(500), 6000);
+        assert_eq!(counter.get_bytes_per_second(), 0.0);
+        counter.update_at(start + Duration::from_secs(2), 9000);
+        assert!((counter.get_bytes_per_second() - 2000.0).abs() < 0.01);
+        // total going backwards is treated as no bytes read
+        counter.update_at(start + Duration::from_secs(4), 100);
+        assert_eq!(counter.get_bytes_per_second(), 0.0);
+    }
+
+    #[test]
+    fn debug_overlay_cells() {
+        use crate::debug_overlay::{DebugStats, get_debug_lines, get_overlay_cells};
+        let stats = DebugStats {
+            fps: 59.94,
+            frame_time_ms: 16.68,
+            grid_size: Vector2::new(80,24),
+            atlas_utilization: 0.125,
+            atlas_memory_bytes: 4*1024*1024,
+            scrollback_lines: 1234,
+            read_bytes_per_second: 1536.0,
+        };
+        let lines = get_debug_lines(&stats);
+        assert_eq!(lines, [
+            "fps 59.9 (16.68ms)",
+            "grid 80x24",
+            "atlas 12.5% 4.0MB",
+            "scrollback 1234",
+            "read 1.5KB/s",
+        ]);
+        let lines = ["ab".to_owned(), "abcd".to_owned()];
+        let get_row = |cells: &[(Vector2<usize>, char)], y: usize| -> (usize, String) {
+            let row: Vec<_> = cells.iter().filter(|(position, _)| position.y == y).collect();
+            (row[0].0.x, row.iter().map(|(_, c)| *c).collect())
+        };
+        // rows are padded to the widest line and placed against the right edge
+        let cells = get_overlay_cells(&lines, Vector2::new(10,5));
+        assert_eq!(cells.len(), 12);
+        assert_eq!(get_row(&cells, 0), (4, " ab   ".to_owned()));
+        assert_eq!(get_row(&cells, 1), (4, " abcd ".to_owned()));
+        // clipped to the grid
+        let cells = get_overlay_cells(&lines, Vector2::new(4,1));
+        assert_eq!(cells.len(), 4);
+        assert_eq!(get_row(&cells, 0), (0, " ab ".to_owned()));
+    }
+
+    #[test]
+    fn headless_screenshot() {
+        use crate::font_loader::load_font_file;
+        use crate::headless::{create_headless_device, render_screenshot};
+        use terminal::{Terminal, TerminalBuilder, TerminalSettings};
+        use terminal::terminal_renderer::TerminalRenderer;
+        let Ok((device, queue)) = create_headless_device() else {
+            eprintln!("Skipping headless screenshot test since no graphics adapter is available");
+            return;
+        };
+        let (resize_tx, resize_rx) = std::sync::mpsc::channel();
+        resize_tx.send(Vector2::new(4,1)).unwrap();
+        let mut data = Some(b"\x1b[?25l\x1b[31m\xe2\x96\x88".to_vec());
+        let mut terminal = Terminal::new(TerminalBuilder {
+            process_read: Box::new(move |buf: &mut [u8]| {
+                let Some(data) = data.take() else {
+                    return 0;
+                };
+                buf[..data.len()].copy_from_slice(data.as_slice());
+                data.len()
+            }),
+            process_write: Box::new(|_| {}),
+            process_ioctl: Box::new(|_| {}),
+            window_action: Box::new(|_| {}),
+            is_newline_carriage_return: false,
+            settings: TerminalSettings::default(),
+            record_output: None,
+            process_resize: Some(resize_rx),
+            on_parser_exit: None,
+        });
+        terminal.join_parser_thread();
+        let mut terminal_renderer = TerminalRenderer::default();
+        terminal_renderer.render_display(&terminal.get_display());
+        let font = load_font_file(concat!(env!("CARGO_MANIFEST_DIR"), "/../../res/Iosevka-custom-regular.ttf")).unwrap();
+        let (size, pixels) = render_screenshot(&device, &queue, &terminal_renderer, &font, 16.0).unwrap();
+        assert_eq!(terminal_renderer.get_size(), Vector2::new(4,1));
+        assert_eq!(pixels.len(), size.x*size.y*4);
+        let glyph_width = size.x / 4;
+        // full block fills the first cell with red while the next cell only has the background
+        let get_pixel = |x: usize, y: usize| &pixels[(y*size.x + x)*4..(y*size.x + x + 1)*4];
+        let block = get_pixel(0,0);
+        let background = get_pixel(glyph_width,0);
+        assert!(block[0] > block[1] && block[0] > block[2], "expected red block but got {:?}", block);
+        assert_ne!(block, background);
+        assert!((0..glyph_width).all(|x| get_pixel(x,0) == block));
+    }
+
+    #[cfg(unix)]
+    mod headless {
+        use crate::headless::{HeadlessExit, HeadlessOptions, run_headless};
+        use regex::Regex;
+        use std::time::Duration;
+        use terminal::TerminalSettings;
+        use terminal_process::{TerminalProcess, UnixPtyProcess};
+
+        // Interactive shell with a fixed prompt that doesn't depend on the user's profile
+        fn spawn_shell(script: Option<&str>) -> Box<dyn TerminalProcess + Send> {
+            let mut command = std::process::Command::new("/bin/sh");
+            command.env("PS1", "ready> ");
+            match script {
+                Some(script) => command.args(["-c", script]),
+                None => command.arg("-i"),
+            };
+            let process = unix_pty::process::PtyProcess::spawn(command, None).unwrap();
+            Box::new(UnixPtyProcess::new(process))
+        }
+
+        fn run(process: Box<dyn TerminalProcess + Send>, options: HeadlessOptions) -> (HeadlessExit, String) {
+            run_headless(process, TerminalSettings::default(), false, None, &options).unwrap()
+        }
+
+        #[test]
+        fn wait_for_prompt_after_sending_input() {
+            let options = HeadlessOptions {
+                timeout: Some(Duration::from_secs(10)),
+                wait_for: Some(Regex::new(r"hello world\s+ready> ").unwrap()),
+                send: Some(b"echo hello world\n".to_vec()),
+                ..Default::default()
+            };
+            let (exit, output) = run(spawn_shell(None), options);
+            assert_eq!(exit, HeadlessExit::PatternFound, "{}", output);
+            assert!(output.contains("echo hello world"), "{}", output);
+        }
+
+        #[test]
+        fn timeout_terminates_process() {
+            let options = HeadlessOptions {
+                timeout: Some(Duration::from_millis(500)),
+                wait_for: Some(Regex::new("never printed").unwrap()),
+                ..Default::default()
+            };
+            let (exit, output) = run(spawn_shell(None), options);
+            assert_eq!(exit, HeadlessExit::Timeout, "{}", output);
+            assert!(output.contains("ready> "), "{}", output);
+        }
+
+        #[test]
+        fn process_exit_before_pattern() {
+            let options = HeadlessOptions {
+                timeout: Some(Duration::from_secs(10)),
+                wait_for: Some(Regex::new("never printed").unwrap()),
+                ..Default::default()
+            };
+            let (exit, output) = run(spawn_shell(Some("echo done")), options);
+            assert_eq!(exit, HeadlessExit::PatternNotFound, "{}", output);
+            assert!(output.contains("done"), "{}", output);
+            let options = HeadlessOptions { timeout: Some(Duration::from_secs(10)), ..Default::default() };
+            let (exit, _) = run(spawn_shell(Some("echo done")), options);
+            assert_eq!(exit, HeadlessExit::ProcessExited(Some(0)));
+        }
+
+        #[test]
+        fn process_exit_code_is_propagated() {
+            let options = HeadlessOptions { timeout: Some(Duration::from_secs(10)), ..Default::default() };
+            let (exit, output) = run(spawn_shell(Some("echo failed; exit 3")), options);
+            assert_eq!(exit, HeadlessExit::ProcessExited(Some(3)), "{}", output);
+            assert_eq!(exit.get_exit_code(), 3);
+        }
+
+        #[test]
+        fn app_builder_from_command() {
+            use crate::app::{AppBuilder, DEFAULT_FONT_SIZE};
+            let mut command = std::process::Command::new("/bin/sh");
+            command.args(["-c", "echo term=$TERM"]);
+            let builder = AppBuilder::from_command(command).unwrap();
+            assert_eq!(builder.font_size, DEFAULT_FONT_SIZE);
+            assert!(builder.process_factory.is_none());
+            let options = HeadlessOptions { timeout: Some(Duration::from_secs(10)), ..Default::default() };
+            let (exit, output) = run(builder.process, options);
+            assert_eq!(exit, HeadlessExit::ProcessExited(Some(0)), "{}", output);
+            assert!(output.contains("term=xterm-256color"), "{}", output);
+        }
+    }
+
+    #[cfg(unix)]
+    mod process_environment {
+        use crate::process_environment::ProcessEnvironment;
+        use cgmath::Vector2;
+        use std::io::Read;
+        use std::process::Command;
+        use terminal_process::{TerminalProcess, UnixPtyProcess};
+
+        // Reads everything the process writes until the pty is closed
+        fn run_under_pty(mut command: Command, environment: &ProcessEnvironment) -> String {
+            environment.apply(&mut command);
+            let process = unix_pty::process::PtyProcess::spawn(command, None).unwrap();
+            let mut process = UnixPtyProcess::new(process);
+            let mut read_pipe = process.get_read_pipe().unwrap();
+            let mut output = Vec::new();
+            let mut buffer = [0u8; 4096];
+            // reading a closed pty fails instead of returning 0 on linux
+            while let Ok(total) = read_pipe.read(&mut buffer) {
+                if total == 0 {
+                    break;
+                }
+                output.extend_from_slice(&buffer[..total]);
+            }
+            String::from_utf8_lossy(output.as_slice()).into_owned()
+        }
+
+        #[test]
+        fn terminal_variables_are_set() {
+            let output = run_under_pty(Command::new("env"), &ProcessEnvironment::default());
+            assert!(output.contains("TERM=xterm-256color"), "{}", output);
+            assert!(output.contains("COLORTERM=truecolor"), "{}", output);
+            assert!(!output.contains("COLUMNS="), "{}", output);
+            let environment = ProcessEnvironment {
+                term: "xterm".to_owned(),
+                grid_size: Some(Vector2::new(100,30)),
+                ..Default::default()
+            };
+            let output = run_under_pty(Command::new("env"), &environment);
+            assert!(output.lines().any(|line| line.trim_end() == "TERM=xterm"), "{}", output);
+            assert!(output.contains("COLUMNS=100"), "{}", output);
+            assert!(output.contains("LINES=30"), "{}", output);
+        }
+
+        #[test]
+        fn working_directory_is_set() {
+            let directory = std::env::temp_dir().canonicalize().unwrap();
+            let environment = ProcessEnvironment {
+                working_directory: Some(directory.clone()),
+                ..Default::default()
+            };
+            let output = run_under_pty(Command::new("pwd"), &environment);
+            assert_eq!(output.trim_end(), directory.to_str().unwrap());
+        }
+    }
+
+    #[cfg(feature = "system-fonts")]
+    mod system_fonts {
+        use crate::font_loader::{FontQuery, SystemFonts};
+
+        const TEST_FONT_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../res");
+
+        #[test]
+        fn find_font_by_family() {
+            let fonts = SystemFonts::from_directory(TEST_FONT_DIRECTORY);
+            let families = fonts.get_families();
+            assert!(!families.is_empty());
+            let font = fonts.find_font(&FontQuery::new(families[0].as_str())).unwrap();
+            assert!(!font.data.is_empty());
+            assert!(fontdue::Font::from_bytes(font.data.as_slice(), fontdue::FontSettings::default()).is_ok());
+        }
+
+        #[test]
+        fn missing_family_lists_close_matches() {
+            let fonts = SystemFonts::from_directory(TEST_FONT_DIRECTORY);
+            let family = fonts.get_families()[0].clone();
+            // drop the last character to get a near miss
+            let mut misspelled = family.clone();
+            misspelled.pop();
+            let error = fonts.find_font(&FontQuery::new(misspelled.as_str())).unwrap_err();
+            let message = format!("{}", error);
+            assert!(message.contains(family.as_str()), "{}", message);
+            // unrelated names have no suggestions
+            assert!(fonts.get_close_matches("zzzzzzzzzzzzzzzz").is_empty());
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Log level of each module where the longest matching module path is used
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct LogFilter {
+    // used for modules without an entry
+    pub default_level: Option<LevelFilter>,
+    pub module_levels: Vec<(String, LevelFilter)>,
+}
+
+fn parse_level(value: &str) -> Result<LevelFilter, String> {
+    value.trim().parse::<LevelFilter>()
+        .map_err(|_| format!("Expected a log level of off, error, warn, info, debug or trace, got '{}'", value.trim()))
+}
+
+// Comma separated list of module=level pairs where a level on its own sets the default (e.g. vt100=trace,wgpu=warn)
+pub fn parse_log_filter(value: &str) -> Result<LogFilter, String> {
+    let mut filter = LogFilter::default();
+    for entry in value.split(',').map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+        match entry.split_once('=') {
+            Some((module, level)) => {
+                let module = module.trim();
+                if module.is_empty() {
+                    return Err(format!("Expected a module name before '=' in '{}'", entry));
+                }
+                filter.module_levels.push((module.to_owned(), parse_level(level)?));
+            },
+            None => filter.default_level = Some(parse_level(entry)?),
+        }
+    }
+    Ok(filter)
+}
+
+impl LogFilter {
+    // Entries from other take precedence
+    pub fn extend(&mut self, other: LogFilter) {
+        if other.default_level.is_some() {
+            self.default_level = other.default_level;
+        }
+        self.module_levels.extend(other.module_levels);
+    }
+
+    pub fn get_default_level(&self) -> LevelFilter {
+        self.default_level.unwrap_or(LevelFilter::Error)
+    }
+
+    // A module matches itself and its submodules with later entries winning ties
+    pub fn get_level(&self, target: &str) -> LevelFilter {
+        let is_match = |module: &str| {
+            target.strip_prefix(module).is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        };
+        self.module_levels
+            .iter()
+            .filter(|(module, _)| is_match(module.as_str()))
+            .fold(None, |best: Option<(usize, LevelFilter)>, (module, level)| match best {
+                Some((length, _)) if length > module.len() => best,
+                _ => Some((module.len(), *level)),
+            })
+            .map_or(self.get_default_level(), |(_, level)| level)
+    }
+
+    pub fn get_max_level(&self) -> LevelFilter {
+        self.module_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.get_default_level(), |a, b| a.max(b))
+    }
+}
+
+struct FileLogger {
+    file: Mutex<std::fs::File>,
+    filter: LogFilter,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.get_level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let thread = std::thread::current();
+        let mut file = self.file.lock().unwrap();
+        let _ = writeln!(file, "{}.{:03} {:<5} [{}] ({}) {}",
+            timestamp.as_secs(), timestamp.subsec_millis(),
+            record.level(), record.target(), thread.name().unwrap_or("?"), record.args(),
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().flush();
+    }
+}
+
+// Logs are appended to the file instead of being written to stderr if a filepath is given
+pub fn init_logging(filter: LogFilter, log_file: Option<&Path>) -> anyhow::Result<()> {
+    let Some(filepath) = log_file else {
+        let mut logger = simple_logger::SimpleLogger::new().with_level(filter.get_default_level());
+        for (module, level) in filter.module_levels.iter() {
+            logger = logger.with_module_level(module.as_str(), *level);
+        }
+        logger
+            .env()
+            .with_colors(true)
+            .without_timestamps()
+            .init()?;
+        return Ok(());
+    };
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filepath)
+        .map_err(|err| anyhow::format_err!("Failed to open log file '{}': {}", filepath.display(), err))?;
+    let max_level = filter.get_max_level();
+    let logger = FileLogger { file: Mutex::new(file), filter };
+    log::set_logger(Box::leak(Box::new(logger)))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
@@ -1,7 +1,24 @@
 use clap::Parser;
 use terminal_process::*;
-use wgpu_terminal::app::{AppBuilder, start_app, start_headless};
-use std::sync::{Arc, Mutex};
+use wgpu_terminal::app::{AppBuilder, DEFAULT_FONT_FILENAME, DEFAULT_FONT_SIZE, ProcessFactory, start_app, start_headless};
+use wgpu_terminal::app_window::DEFAULT_UNFOCUSED_DIM;
+use wgpu_terminal::font_loader::FontSource;
+use wgpu_terminal::grid_format::GridFormat;
+use wgpu_terminal::headless::{HeadlessOptions, parse_escaped_input};
+use wgpu_terminal::headless::HeadlessScreenshot;
+use wgpu_terminal::process_environment::{DEFAULT_TERM, MAX_GRID_SIZE, ProcessEnvironment};
+use wgpu_terminal::config::{AppConfig, ColourConfig, ConfigOverrides, get_config_dir, parse_hex_colour, parse_opacity, parse_padding};
+use wgpu_terminal::keyboard_shortcuts::KeyboardShortcuts;
+use wgpu_terminal::logging::{LogFilter, init_logging, parse_log_filter};
+use wgpu_terminal::themes::get_theme_names;
+use wgpu_terminal::window_geometry::get_window_state_path;
+use wgpu_terminal::accessibility::log_accessibility_tree;
+use std::path::PathBuf;
+use std::time::Duration;
+use regex::Regex;
+use terminal::TerminalSettings;
+use vt100::common::Rgb8;
+use cgmath::Vector2;
 
 #[derive(Clone,Copy,Debug,Default,clap::ValueEnum)]
 enum Mode {
@@ -15,27 +32,178 @@ enum Mode {
     Pty,
 }
 
+#[derive(Clone,Copy,Debug,Default,clap::ValueEnum)]
+enum PresentMode {
+    /// Wait for the display to refresh which avoids tearing but adds up to a frame of latency
+    #[default]
+    Vsync,
+    /// Present straight away for the lowest latency at the cost of tearing
+    Immediate,
+    /// Replace the queued frame so there is no tearing but frames that are never shown are still rendered
+    Mailbox,
+    /// Queue frames for the display which is vsync without falling back to another mode
+    Fifo,
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Vsync => wgpu::PresentMode::AutoVsync,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,Default,clap::ValueEnum)]
+enum ReplaySpeed {
+    /// Wait between output with the delays it was recorded with
+    #[default]
+    Realtime,
+    /// Replay as fast as the terminal can parse for benchmarking
+    Max,
+}
+
+impl From<ReplaySpeed> for terminal_process::ReplaySpeed {
+    fn from(speed: ReplaySpeed) -> Self {
+        match speed {
+            ReplaySpeed::Realtime => terminal_process::ReplaySpeed::Realtime,
+            ReplaySpeed::Max => terminal_process::ReplaySpeed::Max,
+        }
+    }
+}
+
 #[derive(Clone,Debug,Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Filepath of shell executable
-    #[cfg_attr(windows, arg(default_value = "cmd.exe"))]
-    #[cfg_attr(unix, arg(default_value = "/usr/bin/bash"))]
-    filename: String,
+    /// Filepath of shell executable (overrides config file)
+    filename: Option<String>,
     /// Executable arguments
     arguments: Vec<String>,
-    /// Font size
-    #[arg(long, default_value_t = 14.0)]
-    font_size: f32,
-    /// Font filename 
-    #[arg(long, default_value = "./res/Iosevka-custom-regular.ttf")]
-    font_filename: String,
+    /// Font size (overrides config file)
+    #[arg(long)]
+    font_size: Option<f32>,
+    /// Font filename (overrides --font)
+    #[arg(long)]
+    font_filename: Option<String>,
+    /// Font family name resolved from installed fonts
+    #[cfg(feature = "system-fonts")]
+    #[arg(long)]
+    font: Option<String>,
+    /// Font weight used when resolving font family
+    #[cfg(feature = "system-fonts")]
+    #[arg(long, default_value_t = 400)]
+    font_weight: u16,
+    /// Use italic style when resolving font family
+    #[cfg(feature = "system-fonts")]
+    #[arg(long, default_value_t = false)]
+    font_italic: bool,
+    /// Directory the shell is started in
+    #[arg(long)]
+    working_directory: Option<PathBuf>,
+    /// Value of TERM given to the shell
+    #[arg(long, default_value_t = DEFAULT_TERM.to_owned())]
+    term: String,
     /// Type of process to launch
     #[arg(value_enum, long, default_value_t = Mode::default())]
     mode: Mode,
-    /// Run without window by printing results to stdout
+    /// Prefer high dynamic range surface format if available
+    #[arg(long, default_value_t = false)]
+    prefer_hdr: bool,
+    /// How rendered frames are shown on the display
+    #[arg(value_enum, long, default_value_t = PresentMode::default())]
+    present_mode: PresentMode,
+    /// Maximum frames per second which stops immediate or mailbox present modes from using all of the gpu
+    #[arg(long)]
+    max_fps: Option<f64>,
+    /// Minimum contrast ratio between foreground and background (1.0 to 21.0)
+    #[arg(long, default_value_t = 1.0)]
+    minimum_contrast: f32,
+    /// Cursor colour as #rrggbb (defaults to text colour under cursor)
+    #[arg(long, value_parser = parse_hex_colour)]
+    cursor_colour: Option<Rgb8>,
+    /// Pixels between the window edge and the grid as x or x,y (overrides config file)
+    #[arg(long, value_parser = parse_padding)]
+    padding: Option<Vector2<u32>>,
+    /// Opacity of the background from 0.0 to 1.0 if the compositor supports transparent windows (overrides config file)
+    #[arg(long, value_parser = parse_opacity)]
+    opacity: Option<f32>,
+    /// Number of columns the window and shell start with (overrides config file, defaults to 80)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..=MAX_GRID_SIZE as i64))]
+    columns: Option<u16>,
+    /// Number of rows the window and shell start with (overrides config file, defaults to 24)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..=MAX_GRID_SIZE as i64))]
+    rows: Option<u16>,
+    /// Brightness of the terminal when the window is unfocused (0.0 to 1.0)
+    #[arg(long, default_value_t = DEFAULT_UNFOCUSED_DIM)]
+    unfocused_dim: f32,
+    /// Maximum glyph atlas size in megabytes before glyphs are evicted
+    #[arg(long)]
+    max_atlas_memory_mb: Option<usize>,
+    /// Scale applied to the font size instead of the display scale factor (e.g. 2.0 for HiDPI)
+    #[arg(long)]
+    dpi_scale: Option<f32>,
+    /// Use font glyphs for box drawing characters instead of drawing them procedurally
+    #[arg(long, default_value_t = false)]
+    font_box_drawing: bool,
+    /// Name of bundled colour theme (overrides config file)
+    #[arg(long)]
+    theme: Option<String>,
+    /// List bundled colour themes and exit
+    #[arg(long, default_value_t = false)]
+    list_themes: bool,
+    /// Filepath of TOML config file (defaults to wgpu_terminal/config.toml in the user's config directory if present)
+    #[arg(long)]
+    config: Option<String>,
+    /// Connect to a process over a tcp address (host:port) or unix socket path instead of spawning a shell
+    #[arg(long)]
+    socket: Option<String>,
+    /// Play back a file made with --record instead of spawning a shell (input is discarded)
+    #[arg(long)]
+    replay: Option<PathBuf>,
+    /// Speed that --replay is played back at
+    #[arg(value_enum, long, default_value_t = ReplaySpeed::default())]
+    replay_speed: ReplaySpeed,
+    /// Restart shell automatically if it exits with an error or is killed
+    #[arg(long, default_value_t = false)]
+    auto_restart: bool,
+    /// Append logs to this file instead of writing them to stderr
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+    /// Log levels as module=level pairs where a level on its own sets the default (e.g. vt100=trace,wgpu=warn)
+    #[arg(long, value_parser = parse_log_filter)]
+    log_filter: Option<LogFilter>,
+    /// Log parser metrics of each terminal when it exits
+    #[arg(long, default_value_t = false)]
+    print_metrics: bool,
+    /// Record the raw output and resizes of the shell to a file for reproducing bugs
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Dump the text of the visible rows to the debug log whenever the screen changes (debugging aid, not screen reader support)
+    #[arg(long, default_value_t = false)]
+    accessibility: bool,
+    /// Keep the window open after the shell exits and show its exit code until a key is pressed
+    #[arg(long, default_value_t = false)]
+    hold: bool,
+    /// Run without window by printing results to stdout and exiting with the exit code of the process
     #[arg(long, default_value_t = false)]
     headless: bool,
+    /// Format of the grid printed in headless mode
+    #[arg(value_enum, long, default_value_t = GridFormat::default())]
+    style: GridFormat,
+    /// Seconds before the process is terminated in headless mode (exits with code 124)
+    #[arg(long)]
+    timeout: Option<f32>,
+    /// Regex that exits headless mode once it appears on the screen (exits with code 2 if the process exits first)
+    #[arg(long)]
+    wait_for: Option<Regex>,
+    /// Input written to the process in headless mode with escapes such as \n, \r, \e and \xHH
+    #[arg(long)]
+    send: Option<String>,
+    /// Render the final screen in headless mode to a png
+    #[arg(long)]
+    screenshot: Option<String>,
     /// Show console window
     #[cfg(windows)]
     #[cfg_attr(debug_assertions, arg(long = "hide-console", default_value_t = true))]
@@ -45,45 +213,186 @@ struct Args {
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    if args.font_size <= 1.0 {
-        return Err(anyhow::format_err!("Font size must be greater than 1.0, got {:.2}", args.font_size));
+    if args.list_themes {
+        for name in get_theme_names() {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    if !(1.0..=21.0).contains(&args.minimum_contrast) {
+        return Err(anyhow::format_err!("Minimum contrast must be between 1.0 and 21.0, got {:.2}", args.minimum_contrast));
+    }
+    if !(0.0..=1.0).contains(&args.unfocused_dim) {
+        return Err(anyhow::format_err!("Unfocused dim must be between 0.0 and 1.0, got {:.2}", args.unfocused_dim));
+    }
+    if let Some(timeout) = args.timeout {
+        if !(timeout.is_finite() && timeout > 0.0) {
+            return Err(anyhow::format_err!("Timeout must be greater than 0.0 seconds, got {:.2}", timeout));
+        }
+    }
+    if let Some(dpi_scale) = args.dpi_scale {
+        if !(dpi_scale.is_finite() && dpi_scale > 0.0) {
+            return Err(anyhow::format_err!("DPI scale must be greater than 0.0, got {:.2}", dpi_scale));
+        }
+    }
+
+    let app_log_level = if args.print_metrics { log::LevelFilter::Info } else { log::LevelFilter::Error };
+    let mut log_filter = LogFilter {
+        default_level: Some(log::LevelFilter::Error),
+        module_levels: vec![("wgpu_terminal::app".to_owned(), app_log_level)],
+    };
+    if args.accessibility {
+        log_filter.module_levels.push(("wgpu_terminal::accessibility".to_owned(), log::LevelFilter::Debug));
+    }
+    if let Some(filter) = args.log_filter.clone() {
+        log_filter.extend(filter);
     }
+    init_logging(log_filter, args.log_file.as_deref())?;
 
-    simple_logger::SimpleLogger::new()
-        .with_level(log::LevelFilter::Error)
-        .env()
-        .with_colors(true)
-        .without_timestamps()
-        .init()?;
+    let config = load_config(&args)?;
+    // checked before the process is spawned with it
+    config.get_grid_size()?;
+    let colours = config.get_colours()?;
+    let keyboard_shortcuts = config.get_keyboard_shortcuts()?;
+    let font_size = config.font.size.unwrap_or(DEFAULT_FONT_SIZE);
+    if font_size <= 1.0 {
+        return Err(anyhow::format_err!("Font size must be greater than 1.0, got {:.2}", font_size));
+    }
+
+    #[cfg(windows)]
+    show_console_window(args.show_console);
+    let process = create_process(&args, &config)?;
+    start_terminal(args, config, colours, keyboard_shortcuts, process)
+}
+
+const AUTO_RESTART_DELAY: Duration = Duration::from_secs(1);
+#[cfg(windows)]
+const DEFAULT_SHELL: &str = "cmd.exe";
+#[cfg(not(windows))]
+const DEFAULT_SHELL: &str = "/usr/bin/bash";
 
+fn get_default_config_path() -> Option<std::path::PathBuf> {
+    let path = get_config_dir()?.join("wgpu_terminal").join("config.toml");
+    path.is_file().then_some(path)
+}
+
+fn load_config(args: &Args) -> anyhow::Result<AppConfig> {
+    let mut config = match args.config.as_ref() {
+        Some(filename) => AppConfig::load(filename.as_str())?,
+        None => match get_default_config_path() {
+            Some(path) => AppConfig::load(path.to_string_lossy().as_ref())?,
+            None => AppConfig::default(),
+        },
+    };
+    config.apply_overrides(ConfigOverrides {
+        theme: args.theme.clone(),
+        shell: args.filename.clone(),
+        shell_arguments: args.arguments.clone(),
+        #[cfg(feature = "system-fonts")]
+        font_family: args.font.clone(),
+        #[cfg(not(feature = "system-fonts"))]
+        font_family: None,
+        font_filename: args.font_filename.clone(),
+        font_size: args.font_size,
+        cursor_colour: args.cursor_colour,
+        padding: args.padding,
+        opacity: args.opacity,
+        columns: args.columns,
+        rows: args.rows,
+    });
+    Ok(config)
+}
+
+fn create_shell_command(args: &Args, config: &AppConfig, grid_size: Option<Vector2<usize>>) -> std::process::Command {
+    let mut command = std::process::Command::new(config.shell.as_deref().unwrap_or(DEFAULT_SHELL));
+    command.args(config.shell_arguments.as_slice());
+    let environment = ProcessEnvironment {
+        working_directory: args.working_directory.clone(),
+        term: args.term.clone(),
+        grid_size,
+    };
+    environment.apply(&mut command);
+    command
+}
+
+type SpawnProcess = fn(&Args, &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>>;
+
+fn create_process(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    create_restartable_process(args, config, spawn_process)
+}
+
+// Replays and sockets only have one stream so new tabs and panes get a shell instead
+fn create_shell_process(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    create_restartable_process(args, config, spawn_shell)
+}
+
+// Restarts the process after it fails if auto restart is enabled
+fn create_restartable_process(args: &Args, config: &AppConfig, spawn: SpawnProcess) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let process = spawn(args, config)?;
+    if !args.auto_restart {
+        return Ok(process);
+    }
+    let factory = {
+        let args = args.clone();
+        let config = config.clone();
+        move || spawn(&args, &config)
+    };
+    Ok(Box::new(WatchdogProcess::new(process, Box::new(factory), AUTO_RESTART_DELAY)))
+}
+
+fn spawn_process(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    if let Some(path) = args.replay.as_ref() {
+        let process = ReplayProcess::open(path, args.replay_speed.into())
+            .map_err(|err| anyhow::format_err!("Failed to open replay '{}': {}", path.display(), err))?;
+        return Ok(Box::new(process));
+    }
+    if let Some(socket) = args.socket.as_ref() {
+        return connect_socket(socket.as_str());
+    }
+    spawn_shell(args, config)
+}
+
+fn spawn_shell(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
     match args.mode { 
-        Mode::Raw => start_raw_shell(&args),
+        Mode::Raw => spawn_raw_shell(args, config),
         #[cfg(unix)]
-        Mode::Pty => start_unix_pty(&args),
+        Mode::Pty => spawn_unix_pty(args, config),
         #[cfg(windows)]
-        Mode::Conpty => start_conpty(&args),
+        Mode::Conpty => spawn_conpty(args, config),
+    }
+}
+
+fn connect_socket(socket: &str) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    if let Ok(addr) = socket.parse::<std::net::SocketAddr>() {
+        return Ok(Box::new(SocketProcess::connect_tcp(addr)?));
+    }
+    #[cfg(unix)] {
+        Ok(Box::new(SocketProcess::connect_unix(std::path::Path::new(socket))?))
+    }
+    #[cfg(not(unix))] {
+        Err(anyhow::format_err!("Expected tcp socket address of the form host:port, got '{}'", socket))
     }
 }
 
 #[cfg(unix)]
-fn start_unix_pty(args: &Args) -> anyhow::Result<()> {
-    let mut command = std::process::Command::new(&args.filename);
-    command.args(args.arguments.as_slice());
-    let process = unix_pty::process::PtyProcess::spawn(command, None)?;
-    let process = UnixPtyProcess::new(process);
-    start_terminal(args.clone(), Arc::new(Mutex::new(Box::new(process))))?;
-    Ok(())
+fn spawn_unix_pty(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let command = create_shell_command(args, config, None);
+    let size = config.get_grid_size()?.cast::<u16>().unwrap();
+    let process = unix_pty::process::PtyProcess::spawn(command, Some(unix_pty::process::ProcessBuilder { size }))?;
+    Ok(Box::new(UnixPtyProcess::new(process)))
 }
 
 #[cfg(windows)]
-fn start_conpty(args: &Args) -> anyhow::Result<()> {
-    let mut command = std::process::Command::new(&args.filename);
-    command.args(args.arguments.as_slice());
-    let process = conpty::process::ConptyProcess::spawn(command, None)?;
-    let process = ConptyProcess::new(process);
-    show_console_window(args.show_console);
-    start_terminal(args.clone(), Arc::new(Mutex::new(Box::new(process))))?;
-    Ok(())
+fn spawn_conpty(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let command = create_shell_command(args, config, None);
+    let size = config.get_grid_size()?;
+    let to_i16 = |v: usize| i16::try_from(v).map_err(|_| anyhow::format_err!("Grid size {}x{} is too large for conpty", size.x, size.y));
+    let builder = conpty::process::ConptyProcessBuilder {
+        window_size: conpty::process::Size::new(to_i16(size.x)?, to_i16(size.y)?),
+        ..Default::default()
+    };
+    let process = conpty::process::ConptyProcess::spawn(command, Some(builder))?;
+    Ok(Box::new(ConptyProcess::new(process)))
 }
 
 #[cfg(windows)]
@@ -97,26 +406,94 @@ fn show_console_window(is_show: bool) {
     let _ = unsafe { ShowWindow(window, command) };
 }
 
-fn start_raw_shell(args: &Args) -> anyhow::Result<()> {
-    let mut command = std::process::Command::new(&args.filename);
-    command.args(args.arguments.as_slice());
+fn spawn_raw_shell(args: &Args, config: &AppConfig) -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+    let mut command = create_shell_command(args, config, Some(config.get_grid_size()?));
     command.stdin(std::process::Stdio::piped());
     command.stdout(std::process::Stdio::piped());
     command.stderr(std::process::Stdio::null());
     let process = command.spawn()?;
-    let process = RawProcess::new(process);
-    start_terminal(args.clone(), Arc::new(Mutex::new(Box::new(process))))?;
-    Ok(())
+    Ok(Box::new(RawProcess::new(process)))
 }
 
-fn start_terminal(args: Args, process: Arc<Mutex<Box<dyn TerminalProcess + Send>>>) -> anyhow::Result<()> {
+#[cfg_attr(not(feature = "system-fonts"), allow(unused_variables))]
+fn get_font_source(args: &Args, config: &AppConfig) -> FontSource {
+    if let Some(filename) = config.font.filename.as_ref() {
+        return FontSource::File(filename.to_owned());
+    }
+    #[cfg(feature = "system-fonts")]
+    if let Some(family) = config.font.family.as_ref() {
+        let mut query = wgpu_terminal::font_loader::FontQuery::new(family.as_str());
+        query.weight = args.font_weight;
+        query.is_italic = args.font_italic;
+        return FontSource::System(query);
+    }
+    #[cfg(not(feature = "system-fonts"))]
+    if let Some(family) = config.font.family.as_ref() {
+        log::warn!("Font family '{}' ignored since system fonts are not supported in this build", family);
+    }
+    FontSource::File(DEFAULT_FONT_FILENAME.to_owned())
+}
+
+fn start_terminal(
+    args: Args, config: AppConfig, colours: ColourConfig, keyboard_shortcuts: KeyboardShortcuts,
+    process: Box<dyn TerminalProcess + Send>,
+) -> anyhow::Result<()> {
+    let process_factory: ProcessFactory = {
+        let args = args.clone();
+        let config = config.clone();
+        Box::new(move || create_shell_process(&args, &config))
+    };
     let builder = AppBuilder {
-        font_filename: args.font_filename.to_owned(),
-        font_size: args.font_size,
+        font: get_font_source(&args, &config),
+        font_size: config.font.size.unwrap_or(DEFAULT_FONT_SIZE),
+        prefer_hdr: args.prefer_hdr,
+        present_mode: args.present_mode.into(),
+        max_fps: args.max_fps,
+        minimum_contrast: args.minimum_contrast,
+        cursor_colour: colours.cursor,
+        selection_colour: colours.selection,
+        builtin_box_drawing: !args.font_box_drawing,
+        unfocused_dim: args.unfocused_dim,
+        max_atlas_memory_bytes: args.max_atlas_memory_mb.map(|mb| mb*1024*1024),
+        dpi_scale: args.dpi_scale,
+        padding: config.padding.cast::<usize>().unwrap(),
+        background_opacity: config.opacity,
+        word_characters: config.word_characters,
+        keyboard_shortcuts,
+        native_menu: cfg!(target_os = "macos"),
+        terminal_settings: TerminalSettings {
+            scrollback_lines: config.scrollback_lines,
+            cursor_style: config.cursor_style,
+            palette: colours.palette,
+            foreground_colour: colours.foreground,
+            background_colour: colours.background,
+        },
+        print_metrics: args.print_metrics,
+        hold_on_exit: args.hold,
+        bell: config.bell,
+        alternate_screen_scroll_keys: config.alternate_screen_scroll_keys,
+        allow_window_control: config.allow_window_control,
+        // saved window size is only restored if the grid size wasn't given
+        grid_size: if config.columns.is_some() || config.rows.is_some() { Some(config.get_grid_size()?) } else { None },
+        window_state_path: if config.remember_window_geometry { get_window_state_path() } else { None },
+        record_path: args.record.clone(),
+        accessibility_listener: if args.accessibility { Some(Box::new(log_accessibility_tree)) } else { None },
         process,
+        process_factory: Some(process_factory),
     };
     if args.headless {
-        start_headless(builder)
+        let options = HeadlessOptions {
+            format: args.style,
+            timeout: args.timeout.map(Duration::from_secs_f32),
+            wait_for: args.wait_for.clone(),
+            send: args.send.as_deref().map(parse_escaped_input).transpose().map_err(anyhow::Error::msg)?,
+            screenshot: match args.screenshot.clone() {
+                Some(filename) => Some(HeadlessScreenshot { filename, font: builder.font.load()?, font_size: builder.font_size }),
+                None => None,
+            },
+        };
+        let exit = start_headless(builder, options)?;
+        std::process::exit(exit.get_exit_code());
     } else {
         start_app(builder)
     }
@@ -0,0 +1,61 @@
+use crate::app_events::AppEvent;
+use crate::keyboard_shortcuts::ShortcutAction;
+use muda::{
+    accelerator::{Accelerator, Code, Modifiers},
+    Menu,
+    MenuEvent,
+    MenuItem,
+    PredefinedMenuItem,
+    Submenu,
+};
+use std::sync::Mutex;
+use winit::event_loop::EventLoopProxy;
+
+const MENU_ITEMS: [(&str, &str, Code, ShortcutAction); 4] = [
+    ("copy", "Copy", Code::KeyC, ShortcutAction::Copy),
+    ("paste", "Paste", Code::KeyV, ShortcutAction::Paste),
+    ("zoom_in", "Zoom In", Code::Equal, ShortcutAction::FontSizeIncrease),
+    ("zoom_out", "Zoom Out", Code::Minus, ShortcutAction::FontSizeDecrease),
+];
+
+fn create_menu_item(id: &str) -> MenuItem {
+    let (id, text, code, _) = MENU_ITEMS.iter().find(|item| item.0 == id).expect("Unknown menu item");
+    let accelerator = Accelerator::new(Some(Modifiers::SUPER), *code);
+    MenuItem::with_id(*id, *text, true, Some(accelerator))
+}
+
+// Menu is removed when dropped so it must outlive the event loop
+pub struct NativeMenu {
+    _menu: Menu,
+}
+
+impl NativeMenu {
+    pub fn new(event_loop_proxy: EventLoopProxy<AppEvent>) -> anyhow::Result<Self> {
+        let menu = Menu::new();
+        // first submenu is used as the application menu
+        let app_menu = Submenu::with_items("App", true, &[
+            &PredefinedMenuItem::about(None, None),
+            &PredefinedMenuItem::separator(),
+            &PredefinedMenuItem::quit(None),
+        ])?;
+        let edit_menu = Submenu::with_items("Edit", true, &[
+            &create_menu_item("copy"),
+            &create_menu_item("paste"),
+        ])?;
+        let view_menu = Submenu::with_items("View", true, &[
+            &create_menu_item("zoom_in"),
+            &create_menu_item("zoom_out"),
+        ])?;
+        menu.append_items(&[&app_menu, &edit_menu, &view_menu])?;
+        menu.init_for_nsapp();
+        // menu events are sent from the main thread but the handler must be sync
+        let event_loop_proxy = Mutex::new(event_loop_proxy);
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            let Some(action) = MENU_ITEMS.iter().find(|item| item.0 == event.id.0.as_str()).map(|item| item.3) else {
+                return;
+            };
+            let _ = event_loop_proxy.lock().unwrap().send_event(AppEvent::ShortcutAction(action));
+        }));
+        Ok(Self { _menu: menu })
+    }
+}
@@ -0,0 +1,195 @@
+use cgmath::Vector2;
+
+// Vertical splits place panes side by side and horizontal splits stack them
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PaneDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+// Area in cells where the position is the top left corner
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct PaneRect {
+    pub position: Vector2<usize>,
+    pub size: Vector2<usize>,
+}
+
+impl PaneRect {
+    pub fn contains(&self, cell: Vector2<usize>) -> bool {
+        cell.x >= self.position.x && cell.x < self.position.x+self.size.x &&
+        cell.y >= self.position.y && cell.y < self.position.y+self.size.y
+    }
+
+    // Splits along the direction leaving a single cell divider between both halves
+    fn split(&self, direction: SplitDirection) -> (PaneRect, PaneRect, PaneRect) {
+        let axis = match direction {
+            SplitDirection::Vertical => 0,
+            SplitDirection::Horizontal => 1,
+        };
+        let total = self.size[axis];
+        let first_length = total.saturating_sub(1) / 2;
+        let second_length = total.saturating_sub(first_length+1);
+        let mut first = *self;
+        first.size[axis] = first_length;
+        let mut divider = *self;
+        divider.position[axis] += first_length;
+        divider.size[axis] = total.min(1);
+        let mut second = *self;
+        second.position[axis] += first_length + divider.size[axis];
+        second.size[axis] = second_length;
+        (first, divider, second)
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+enum PaneNode {
+    Pane(usize),
+    Split {
+        direction: SplitDirection,
+        first: Box<PaneNode>,
+        second: Box<PaneNode>,
+    },
+}
+
+impl PaneNode {
+    fn contains(&self, id: usize) -> bool {
+        match self {
+            Self::Pane(pane) => *pane == id,
+            Self::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    fn visit(&self, rect: PaneRect, on_pane: &mut impl FnMut(usize, PaneRect), on_divider: &mut impl FnMut(PaneRect)) {
+        match self {
+            Self::Pane(id) => on_pane(*id, rect),
+            Self::Split { direction, first, second } => {
+                let (first_rect, divider, second_rect) = rect.split(*direction);
+                first.visit(first_rect, on_pane, on_divider);
+                on_divider(divider);
+                second.visit(second_rect, on_pane, on_divider);
+            },
+        }
+    }
+}
+
+// Binary tree of panes where each split divides its area in half
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct PaneLayout {
+    root: PaneNode,
+}
+
+impl PaneLayout {
+    pub fn new(id: usize) -> Self {
+        Self { root: PaneNode::Pane(id) }
+    }
+
+    // Existing pane keeps the left or top half
+    pub fn split(&mut self, id: usize, direction: SplitDirection, new_id: usize) -> bool {
+        if self.root.contains(new_id) {
+            return false;
+        }
+        fn split_node(node: &mut PaneNode, id: usize, direction: SplitDirection, new_id: usize) -> bool {
+            match node {
+                PaneNode::Pane(pane) if *pane == id => {
+                    *node = PaneNode::Split {
+                        direction,
+                        first: Box::new(PaneNode::Pane(id)),
+                        second: Box::new(PaneNode::Pane(new_id)),
+                    };
+                    true
+                },
+                PaneNode::Pane(_) => false,
+                PaneNode::Split { first, second, .. } => {
+                    split_node(first, id, direction, new_id) || split_node(second, id, direction, new_id)
+                },
+            }
+        }
+        split_node(&mut self.root, id, direction, new_id)
+    }
+
+    // Sibling of the removed pane takes over its area, the last pane can't be removed
+    pub fn remove(&mut self, id: usize) -> bool {
+        fn remove_node(node: &mut PaneNode, id: usize) -> bool {
+            let PaneNode::Split { first, second, .. } = node else {
+                return false;
+            };
+            let remaining = match (first.as_ref(), second.as_ref()) {
+                (PaneNode::Pane(pane), _) if *pane == id => second.as_ref().clone(),
+                (_, PaneNode::Pane(pane)) if *pane == id => first.as_ref().clone(),
+                _ => return remove_node(first, id) || remove_node(second, id),
+            };
+            *node = remaining;
+            true
+        }
+        remove_node(&mut self.root, id)
+    }
+
+    pub fn get_total_panes(&self) -> usize {
+        self.get_pane_ids().len()
+    }
+
+    // Panes in left to right then top to bottom order of the tree
+    pub fn get_pane_ids(&self) -> Vec<usize> {
+        self.get_pane_rects(Vector2::new(0,0)).into_iter().map(|(id, _)| id).collect()
+    }
+
+    pub fn get_pane_rects(&self, grid_size: Vector2<usize>) -> Vec<(usize, PaneRect)> {
+        let mut rects = Vec::new();
+        let rect = PaneRect { position: Vector2::new(0,0), size: grid_size };
+        self.root.visit(rect, &mut |id, rect| rects.push((id, rect)), &mut |_| {});
+        rects
+    }
+
+    pub fn get_pane_rect(&self, grid_size: Vector2<usize>, id: usize) -> Option<PaneRect> {
+        self.get_pane_rects(grid_size).into_iter().find(|(pane, _)| *pane == id).map(|(_, rect)| rect)
+    }
+
+    // Cells between panes which are drawn as divider lines
+    pub fn get_dividers(&self, grid_size: Vector2<usize>) -> Vec<PaneRect> {
+        let mut dividers = Vec::new();
+        let rect = PaneRect { position: Vector2::new(0,0), size: grid_size };
+        self.root.visit(rect, &mut |_, _| {}, &mut |divider| dividers.push(divider));
+        dividers
+    }
+
+    // Used to route mouse events and focus the pane that was clicked
+    pub fn get_pane_at(&self, grid_size: Vector2<usize>, cell: Vector2<usize>) -> Option<usize> {
+        self.get_pane_rects(grid_size).into_iter().find(|(_, rect)| rect.contains(cell)).map(|(id, _)| id)
+    }
+
+    // Pane across the divider from the top left corner of the given pane
+    pub fn get_neighbour(&self, grid_size: Vector2<usize>, id: usize, direction: PaneDirection) -> Option<usize> {
+        let rect = self.get_pane_rect(grid_size, id)?;
+        let position = rect.position;
+        let probe = match direction {
+            PaneDirection::Left => Vector2::new(position.x.checked_sub(2)?, position.y),
+            PaneDirection::Right => Vector2::new(position.x+rect.size.x+1, position.y),
+            PaneDirection::Up => Vector2::new(position.x, position.y.checked_sub(2)?),
+            PaneDirection::Down => Vector2::new(position.x, position.y+rect.size.y+1),
+        };
+        self.get_pane_at(grid_size, probe).filter(|neighbour| *neighbour != id)
+    }
+}
+
+// Copies the top left of a grid into the area of a pane in a larger grid and clips whatever doesn't fit
+pub fn copy_pane_cells<T: Copy>(dst: &mut [T], dst_width: usize, src: &[T], src_size: Vector2<usize>, rect: PaneRect) {
+    let width = src_size.x.min(rect.size.x);
+    for y in 0..src_size.y.min(rect.size.y) {
+        let src_start = y*src_size.x;
+        let dst_start = (rect.position.y+y)*dst_width + rect.position.x;
+        dst[dst_start..dst_start+width].copy_from_slice(&src[src_start..src_start+width]);
+    }
+}
+
+// Panes side by side are divided by a column and stacked panes by a row
+pub fn get_divider_character(divider: PaneRect) -> char {
+    if divider.size.y > divider.size.x { '│' } else { '─' }
+}
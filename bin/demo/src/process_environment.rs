@@ -0,0 +1,45 @@
+use cgmath::Vector2;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Terminfo entry whose capabilities the parser supports
+pub const DEFAULT_TERM: &str = "xterm-256color";
+// Initial size of the window grid and the size processes are spawned with
+// Processes without a pseudoterminal keep this size since they can't be told when the grid is resized
+pub const DEFAULT_GRID_SIZE: Vector2<usize> = Vector2::new(80,24);
+// Conpty sizes are signed 16 bit
+pub const MAX_GRID_SIZE: usize = i16::MAX as usize;
+
+// Applied to the command of every spawned shell
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct ProcessEnvironment {
+    // inherits the working directory of the terminal if unset
+    pub working_directory: Option<PathBuf>,
+    pub term: String,
+    // sets COLUMNS and LINES for processes that can't query the grid size
+    pub grid_size: Option<Vector2<usize>>,
+}
+
+impl Default for ProcessEnvironment {
+    fn default() -> Self {
+        Self {
+            working_directory: None,
+            term: DEFAULT_TERM.to_owned(),
+            grid_size: None,
+        }
+    }
+}
+
+impl ProcessEnvironment {
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(directory) = self.working_directory.as_ref() {
+            command.current_dir(directory);
+        }
+        command.env("TERM", self.term.as_str());
+        command.env("COLORTERM", "truecolor");
+        if let Some(size) = self.grid_size {
+            command.env("COLUMNS", size.x.to_string());
+            command.env("LINES", size.y.to_string());
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use cgmath::Vector2;
+
+pub fn get_default_filename() -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    format!("screenshot_{}.png", timestamp)
+}
+
+// Expects tightly packed rgba8 pixels
+pub fn save_png(filename: &str, size: Vector2<usize>, pixels: &[u8]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        pixels.len() == size.x*size.y*4,
+        "Expected {} bytes for {}x{} image but got {}", size.x*size.y*4, size.x, size.y, pixels.len(),
+    );
+    image::save_buffer(filename, pixels, size.x as u32, size.y as u32, image::ColorType::Rgba8)?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use terminal::Terminal;
+use terminal::terminal_renderer::TerminalRenderer;
+use terminal_process::TerminalProcess;
+use crate::pane_layout::PaneLayout;
+use crate::title_stack::TitleStack;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+
+pub struct PaneState {
+    pub terminal: Terminal,
+    pub renderer: TerminalRenderer,
+    // window actions from the terminal are tagged with this so they can be routed to the pane
+    pub(crate) id: usize,
+    pub(crate) process: Arc<Mutex<Box<dyn TerminalProcess + Send>>>,
+    // process has exited but the pane is held open until a key is pressed
+    pub(crate) is_exited: bool,
+    // set by the terminal when it queues a refresh and cleared once the refresh is received
+    pub(crate) is_refresh_queued: Arc<AtomicBool>,
+}
+
+impl Drop for PaneState {
+    fn drop(&mut self) {
+        match self.process.lock().unwrap().terminate() {
+            Ok(()) => log::info!("Process terminated successfully"),
+            Err(err) => log::error!("Process failed to be terminated: {:?}", err),
+        }
+    }
+}
+
+// Panes of a tab share its title which is set by whichever pane changed it last
+pub struct TabState {
+    pub panes: Vec<PaneState>,
+    pub(crate) layout: PaneLayout,
+    // index of the pane that receives input
+    pub(crate) active_pane: usize,
+    pub title: String,
+    // set by OSC 1 and only reported back to the process since winit can't show it
+    pub icon_label: String,
+    pub(crate) title_stack: TitleStack,
+    pub(crate) icon_label_stack: TitleStack,
+}
+
+impl TabState {
+    pub fn new(pane: PaneState) -> Self {
+        Self {
+            layout: PaneLayout::new(pane.id),
+            panes: vec![pane],
+            active_pane: 0,
+            title: String::new(),
+            icon_label: String::new(),
+            title_stack: TitleStack::default(),
+            icon_label_stack: TitleStack::default(),
+        }
+    }
+
+    pub fn get_active_pane(&self) -> &PaneState {
+        &self.panes[self.active_pane]
+    }
+
+    pub fn get_active_pane_mut(&mut self) -> &mut PaneState {
+        &mut self.panes[self.active_pane]
+    }
+
+    pub fn get_pane_index(&self, id: usize) -> Option<usize> {
+        self.panes.iter().position(|pane| pane.id == id)
+    }
+}
+
+const MAX_TAB_WIDTH: usize = 24;
+const DEFAULT_TAB_TITLE: &str = "shell";
+
+// Columns occupied by a tab where end is exclusive
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct TabSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+// Single row of equally sized tabs which are shrunk to fit the width of the window
+#[derive(Clone,Debug,Default)]
+pub struct TabBar {
+    spans: Vec<TabSpan>,
+    labels: Vec<String>,
+    width: usize,
+}
+
+impl TabBar {
+    pub fn update(&mut self, titles: &[&str], width: usize) {
+        self.width = width;
+        self.spans.clear();
+        self.labels.clear();
+        if titles.is_empty() || width == 0 {
+            return;
+        }
+        let tab_width = (width / titles.len()).clamp(1, MAX_TAB_WIDTH);
+        for (index, title) in titles.iter().enumerate() {
+            let start = index*tab_width;
+            // tabs that don't fit are dropped
+            if start >= width {
+                break;
+            }
+            let end = (start+tab_width).min(width);
+            let title = if title.is_empty() { DEFAULT_TAB_TITLE } else { title };
+            // last column is left blank to separate tabs
+            let label: String = format!(" {}: {}", index+1, title).chars().take((end-start).saturating_sub(1)).collect();
+            self.spans.push(TabSpan { start, end });
+            self.labels.push(label);
+        }
+    }
+
+    pub fn get_spans(&self) -> &[TabSpan] {
+        self.spans.as_slice()
+    }
+
+    pub fn get_label(&self, index: usize) -> Option<&str> {
+        self.labels.get(index).map(|label| label.as_str())
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_tab_at_column(&self, column: usize) -> Option<usize> {
+        self.spans.iter().position(|span| span.start <= column && column < span.end)
+    }
+}
@@ -0,0 +1,106 @@
+use terminal::colour_table::convert_u32_to_rgb;
+use vt100::common::Rgb8;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Theme {
+    // 16 base colours, the rest of the 256 colour table is generated from these
+    pub palette: [Rgb8; 16],
+    pub foreground: Rgb8,
+    pub background: Rgb8,
+    // defaults to the text colour under the cursor
+    pub cursor: Option<Rgb8>,
+    // defaults to inverting the selected cells
+    pub selection: Option<Rgb8>,
+}
+
+struct ThemePreset {
+    name: &'static str,
+    palette: [u32; 16],
+    foreground: u32,
+    background: u32,
+    cursor: Option<u32>,
+    selection: Option<u32>,
+}
+
+const SOLARIZED_PALETTE: [u32; 16] = [
+    0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198, 0xeee8d5,
+    0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4, 0x93a1a1, 0xfdf6e3,
+];
+
+const THEME_PRESETS: [ThemePreset; 6] = [
+    ThemePreset {
+        name: "one-half-dark",
+        palette: [
+            0x282c34, 0xe06c75, 0x98c379, 0xe5c07b, 0x61afef, 0xc678dd, 0x56b6c2, 0xdcdfe4,
+            0x282c34, 0xe06c75, 0x98c379, 0xe5c07b, 0x61afef, 0xc678dd, 0x56b6c2, 0xdcdfe4,
+        ],
+        foreground: 0xdcdfe4,
+        background: 0x282c34,
+        cursor: None,
+        selection: None,
+    },
+    ThemePreset {
+        name: "xterm",
+        palette: [
+            0x000000, 0x800000, 0x008000, 0x808000, 0x000080, 0x800080, 0x008080, 0xc0c0c0,
+            0x808080, 0xff0000, 0x00ff00, 0xffff00, 0x0000ff, 0xff00ff, 0x00ffff, 0xffffff,
+        ],
+        foreground: 0xc0c0c0,
+        background: 0x000000,
+        cursor: None,
+        selection: None,
+    },
+    ThemePreset {
+        name: "solarized-dark",
+        palette: SOLARIZED_PALETTE,
+        foreground: 0x839496,
+        background: 0x002b36,
+        cursor: Some(0x93a1a1),
+        selection: Some(0x073642),
+    },
+    ThemePreset {
+        name: "solarized-light",
+        palette: SOLARIZED_PALETTE,
+        foreground: 0x657b83,
+        background: 0xfdf6e3,
+        cursor: Some(0x586e75),
+        selection: Some(0xeee8d5),
+    },
+    ThemePreset {
+        name: "gruvbox",
+        palette: [
+            0x282828, 0xcc241d, 0x98971a, 0xd79921, 0x458588, 0xb16286, 0x689d6a, 0xa89984,
+            0x928374, 0xfb4934, 0xb8bb26, 0xfabd2f, 0x83a598, 0xd3869b, 0x8ec07c, 0xebdbb2,
+        ],
+        foreground: 0xebdbb2,
+        background: 0x282828,
+        cursor: Some(0xebdbb2),
+        selection: Some(0x504945),
+    },
+    ThemePreset {
+        name: "dracula",
+        palette: [
+            0x21222c, 0xff5555, 0x50fa7b, 0xf1fa8c, 0xbd93f9, 0xff79c6, 0x8be9fd, 0xf8f8f2,
+            0x6272a4, 0xff6e6e, 0x69ff94, 0xffffa5, 0xd6acff, 0xff92df, 0xa4ffff, 0xffffff,
+        ],
+        foreground: 0xf8f8f2,
+        background: 0x282a36,
+        cursor: Some(0xf8f8f2),
+        selection: Some(0x44475a),
+    },
+];
+
+pub fn get_theme_names() -> impl Iterator<Item = &'static str> {
+    THEME_PRESETS.iter().map(|preset| preset.name)
+}
+
+pub fn get_theme(name: &str) -> Option<Theme> {
+    let preset = THEME_PRESETS.iter().find(|preset| preset.name == name)?;
+    Some(Theme {
+        palette: preset.palette.map(convert_u32_to_rgb),
+        foreground: convert_u32_to_rgb(preset.foreground),
+        background: convert_u32_to_rgb(preset.background),
+        cursor: preset.cursor.map(convert_u32_to_rgb),
+        selection: preset.selection.map(convert_u32_to_rgb),
+    })
+}
@@ -0,0 +1,31 @@
+// Same limit as xterm so a process can't grow the stack without bound
+pub const MAX_TITLE_STACK_SIZE: usize = 10;
+
+// Window titles or icon labels saved by CSI 22 t and restored by CSI 23 t
+// The optional stack index isn't supported so titles are always pushed to and popped from the top
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct TitleStack {
+    titles: Vec<String>,
+}
+
+impl TitleStack {
+    // Oldest title is dropped once the stack is full
+    pub fn push(&mut self, title: &str) {
+        if self.titles.len() >= MAX_TITLE_STACK_SIZE {
+            self.titles.remove(0);
+        }
+        self.titles.push(title.to_owned());
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.titles.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty()
+    }
+}
@@ -0,0 +1,102 @@
+use cgmath::Vector2;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Pixels of the window that must overlap a monitor for it to be left where it was
+pub const MIN_VISIBLE_SIZE: Vector2<i32> = Vector2::new(64, 32);
+
+// Window as it was when the app last exited in physical pixels
+// Position is unset on platforms like wayland that don't expose it
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Serialize,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WindowGeometry {
+    pub position: Option<[i32; 2]>,
+    pub size: [u32; 2],
+    pub is_maximised: bool,
+}
+
+// Area covered by a monitor in the virtual desktop
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct MonitorArea {
+    pub position: Vector2<i32>,
+    pub size: Vector2<u32>,
+}
+
+impl MonitorArea {
+    fn get_overlap(&self, position: Vector2<i32>, size: Vector2<u32>) -> Vector2<i32> {
+        let start = Vector2::new(position.x.max(self.position.x), position.y.max(self.position.y));
+        let end = Vector2::new(
+            (position.x + size.x as i32).min(self.position.x + self.size.x as i32),
+            (position.y + size.y as i32).min(self.position.y + self.size.y as i32),
+        );
+        Vector2::new((end.x - start.x).max(0), (end.y - start.y).max(0))
+    }
+}
+
+// Stored next to the default config file
+pub fn get_window_state_path() -> Option<PathBuf> {
+    Some(crate::config::get_config_dir()?.join("wgpu_terminal").join("window_state.toml"))
+}
+
+impl WindowGeometry {
+    pub fn from_toml(data: &str) -> anyhow::Result<Self> {
+        let geometry = toml::from_str(data)?;
+        Ok(geometry)
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        let data = toml::to_string(self)?;
+        Ok(data)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Self::from_toml(data.as_str())
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    // Monitors may have been unplugged or rearranged since the geometry was saved
+    // Window is shrunk to fit the monitor it is moved onto and moved onto the first monitor if barely visible
+    pub fn clamp_to_monitors(&self, monitors: &[MonitorArea]) -> Self {
+        let Some(first_monitor) = monitors.first() else {
+            return *self;
+        };
+        let size = Vector2::new(self.size[0].max(1), self.size[1].max(1));
+        let Some(position) = self.position else {
+            let size = Vector2::new(size.x.min(first_monitor.size.x), size.y.min(first_monitor.size.y));
+            return Self { size: size.into(), ..*self };
+        };
+        let position = Vector2::new(position[0], position[1]);
+        let (monitor, overlap) = monitors
+            .iter()
+            .map(|monitor| (monitor, monitor.get_overlap(position, size)))
+            .max_by_key(|(_, overlap)| overlap.x as i64 * overlap.y as i64)
+            .unwrap();
+        let min_visible = Vector2::new(MIN_VISIBLE_SIZE.x.min(size.x as i32), MIN_VISIBLE_SIZE.y.min(size.y as i32));
+        let is_visible = overlap.x >= min_visible.x && overlap.y >= min_visible.y;
+        let monitor = if is_visible { monitor } else { first_monitor };
+        let size = Vector2::new(size.x.min(monitor.size.x), size.y.min(monitor.size.y));
+        let position = if is_visible {
+            position
+        } else {
+            // keep the window fully inside the monitor it is moved onto
+            let max_position = monitor.position + (monitor.size - size).cast::<i32>().unwrap();
+            Vector2::new(
+                position.x.clamp(monitor.position.x, max_position.x),
+                position.y.clamp(monitor.position.y, max_position.y),
+            )
+        };
+        Self {
+            position: Some(position.into()),
+            size: size.into(),
+            is_maximised: self.is_maximised,
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use winit::window::Icon;
+
+// Bundled so the icon doesn't depend on the working directory like the default font does
+const DEFAULT_ICON_PNG: &[u8] = include_bytes!("../../../res/icon.png");
+
+pub fn decode_icon(data: &[u8]) -> anyhow::Result<Icon> {
+    let image = image::load_from_memory_with_format(data, image::ImageFormat::Png)?.into_rgba8();
+    let (width, height) = image.dimensions();
+    let icon = Icon::from_rgba(image.into_raw(), width, height)?;
+    Ok(icon)
+}
+
+// Window keeps the platform default icon if the bundled icon can't be decoded
+pub fn load_default_icon() -> Option<Icon> {
+    match decode_icon(DEFAULT_ICON_PNG) {
+        Ok(icon) => Some(icon),
+        Err(err) => {
+            log::warn!("Failed to load window icon: {}", err);
+            None
+        },
+    }
+}
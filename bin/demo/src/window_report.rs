@@ -0,0 +1,75 @@
+use cgmath::Vector2;
+use vt100::common::WindowAction;
+
+// Snapshot of the window geometry in pixels used to answer CSI t reports
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct WindowMetrics {
+    pub window_position: Vector2<i32>,
+    pub window_size: Vector2<usize>,
+    // relative to the window origin
+    pub text_area_position: Vector2<usize>,
+    pub text_area_size: Vector2<usize>,
+    pub screen_size: Vector2<usize>,
+    pub cell_size: Vector2<usize>,
+    pub grid_size: Vector2<usize>,
+    pub is_minimised: bool,
+}
+
+impl WindowMetrics {
+    // Number of whole cells that fit on the screen
+    pub fn get_screen_grid_size(&self) -> Vector2<usize> {
+        Vector2::new(
+            self.screen_size.x / self.cell_size.x.max(1),
+            self.screen_size.y / self.cell_size.y.max(1),
+        )
+    }
+}
+
+// Reply to a window report request or None if the action isn't a geometry report
+// https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Functions-using-CSI-_-ordered-by-the-final-character_s_
+pub fn encode_window_report(action: &WindowAction, metrics: &WindowMetrics) -> Option<String> {
+    // sizes are always given as height then width
+    let reply = match action {
+        WindowAction::GetWindowState => format!("\x1b[{}t", if metrics.is_minimised { 2 } else { 1 }),
+        WindowAction::GetWindowPosition => {
+            format!("\x1b[3;{};{}t", metrics.window_position.x, metrics.window_position.y)
+        },
+        WindowAction::GetTextAreaPosition => {
+            format!("\x1b[3;{};{}t", metrics.text_area_position.x, metrics.text_area_position.y)
+        },
+        WindowAction::GetTextAreaSize => {
+            format!("\x1b[4;{};{}t", metrics.text_area_size.y, metrics.text_area_size.x)
+        },
+        WindowAction::GetWindowSize => {
+            format!("\x1b[4;{};{}t", metrics.window_size.y, metrics.window_size.x)
+        },
+        WindowAction::GetScreenSize => {
+            format!("\x1b[5;{};{}t", metrics.screen_size.y, metrics.screen_size.x)
+        },
+        WindowAction::GetCellSize => {
+            format!("\x1b[6;{};{}t", metrics.cell_size.y, metrics.cell_size.x)
+        },
+        WindowAction::GetTextAreaGridSize => {
+            format!("\x1b[8;{};{}t", metrics.grid_size.y, metrics.grid_size.x)
+        },
+        WindowAction::GetScreenGridSize => {
+            let size = metrics.get_screen_grid_size();
+            format!("\x1b[9;{};{}t", size.y, size.x)
+        },
+        _ => return None,
+    };
+    Some(reply)
+}
+
+// XTWINOPS gives rows before columns and zero keeps the current size of that axis
+pub fn get_requested_grid_size(size: Vector2<u16>, current_size: Vector2<usize>) -> Vector2<usize> {
+    let rows = if size.x == 0 { current_size.y } else { size.x as usize };
+    let columns = if size.y == 0 { current_size.x } else { size.y as usize };
+    Vector2::new(columns, rows)
+}
+
+// Reply to CSI 20 t with control characters removed so the label can't inject sequences into the reply
+pub fn encode_icon_label_report(label: &str) -> String {
+    let label: String = label.chars().filter(|c| !c.is_control()).collect();
+    format!("\x1b]L{}\x1b\\", label)
+}
@@ -0,0 +1,36 @@
+use wgpu_terminal::headless::{HeadlessExit, HeadlessOptions, run_headless};
+use terminal::TerminalSettings;
+use terminal_process::{ReplayProcess, ReplaySpeed};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// 20x4 grid which is widened to 30 columns before a line that would otherwise wrap
+const RESIZE_AFTER_OUTPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/resize_after_output.rec");
+
+fn replay(speed: ReplaySpeed) -> (HeadlessExit, Vec<String>) {
+    let process = ReplayProcess::open(Path::new(RESIZE_AFTER_OUTPUT), speed).unwrap();
+    let options = HeadlessOptions {
+        timeout: Some(Duration::from_secs(10)),
+        ..Default::default()
+    };
+    let (exit, output) = run_headless(Box::new(process), TerminalSettings::default(), false, None, &options).unwrap();
+    let lines = output.lines().map(|line| line.trim_end().to_owned()).collect();
+    (exit, lines)
+}
+
+#[test]
+fn replay_final_screen() {
+    let (exit, lines) = replay(ReplaySpeed::Max);
+    assert_eq!(exit, HeadlessExit::ProcessExited(Some(0)));
+    assert_eq!(lines, vec!["$ echo hello", "hello", "the quick brown fox jumps", "$"]);
+}
+
+#[test]
+fn realtime_replay_follows_timestamps() {
+    let start = Instant::now();
+    let (exit, lines) = replay(ReplaySpeed::Realtime);
+    // last frame was recorded 200ms after the first
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(exit, HeadlessExit::ProcessExited(Some(0)));
+    assert_eq!(lines.last().map(String::as_str), Some("$"));
+}
@@ -0,0 +1,338 @@
+use cgmath::Vector2;
+
+const BOX_DRAWING_START: char = '\u{2500}';
+const BOX_DRAWING_END: char = '\u{257F}';
+const BLOCK_ELEMENTS_START: char = '\u{2580}';
+const BLOCK_ELEMENTS_END: char = '\u{259F}';
+
+const WEIGHT_NONE: u8 = 0;
+const WEIGHT_LIGHT: u8 = 1;
+const WEIGHT_HEAVY: u8 = 2;
+const WEIGHT_DOUBLE: u8 = 3;
+
+const ARM_UP: usize = 0;
+const ARM_RIGHT: usize = 1;
+const ARM_DOWN: usize = 2;
+const ARM_LEFT: usize = 3;
+
+// Weight of (up, right, down, left) arms for U+2500 to U+257F
+// 0 = none, 1 = light, 2 = heavy, 3 = double
+const LINE_ARMS: [[u8; 4]; 128] = [
+    [0,1,0,1], [0,2,0,2], [1,0,1,0], [2,0,2,0], [0,1,0,1], [0,2,0,2], [1,0,1,0], [2,0,2,0], // ─━│┃┄┅┆┇
+    [0,1,0,1], [0,2,0,2], [1,0,1,0], [2,0,2,0], [0,1,1,0], [0,2,1,0], [0,1,2,0], [0,2,2,0], // ┈┉┊┋┌┍┎┏
+    [0,0,1,1], [0,0,1,2], [0,0,2,1], [0,0,2,2], [1,1,0,0], [1,2,0,0], [2,1,0,0], [2,2,0,0], // ┐┑┒┓└┕┖┗
+    [1,0,0,1], [1,0,0,2], [2,0,0,1], [2,0,0,2], [1,1,1,0], [1,2,1,0], [2,1,1,0], [1,1,2,0], // ┘┙┚┛├┝┞┟
+    [2,1,2,0], [2,2,1,0], [1,2,2,0], [2,2,2,0], [1,0,1,1], [1,0,1,2], [2,0,1,1], [1,0,2,1], // ┠┡┢┣┤┥┦┧
+    [2,0,2,1], [2,0,1,2], [1,0,2,2], [2,0,2,2], [0,1,1,1], [0,1,1,2], [0,2,1,1], [0,2,1,2], // ┨┩┪┫┬┭┮┯
+    [0,1,2,1], [0,1,2,2], [0,2,2,1], [0,2,2,2], [1,1,0,1], [1,1,0,2], [1,2,0,1], [1,2,0,2], // ┰┱┲┳┴┵┶┷
+    [2,1,0,1], [2,1,0,2], [2,2,0,1], [2,2,0,2], [1,1,1,1], [1,1,1,2], [1,2,1,1], [1,2,1,2], // ┸┹┺┻┼┽┾┿
+    [2,1,1,1], [1,1,2,1], [2,1,2,1], [2,1,1,2], [2,2,1,1], [1,1,2,2], [1,2,2,1], [2,2,1,2], // ╀╁╂╃╄╅╆╇
+    [1,2,2,2], [2,1,2,2], [2,2,2,1], [2,2,2,2], [0,1,0,1], [0,2,0,2], [1,0,1,0], [2,0,2,0], // ╈╉╊╋╌╍╎╏
+    [0,3,0,3], [3,0,3,0], [0,3,1,0], [0,1,3,0], [0,3,3,0], [0,0,1,3], [0,0,3,1], [0,0,3,3], // ═║╒╓╔╕╖╗
+    [1,3,0,0], [3,1,0,0], [3,3,0,0], [1,0,0,3], [3,0,0,1], [3,0,0,3], [1,3,1,0], [3,1,3,0], // ╘╙╚╛╜╝╞╟
+    [3,3,3,0], [1,0,1,3], [3,0,3,1], [3,0,3,3], [0,3,1,3], [0,1,3,1], [0,3,3,3], [1,3,0,3], // ╠╡╢╣╤╥╦╧
+    [3,1,0,1], [3,3,0,3], [1,3,1,3], [3,1,3,1], [3,3,3,3], [0,0,0,0], [0,0,0,0], [0,0,0,0], // ╨╩╪╫╬╭╮╯
+    [0,0,0,0], [0,0,0,0], [0,0,0,0], [0,0,0,0], [0,0,0,1], [1,0,0,0], [0,1,0,0], [0,0,1,0], // ╰╱╲╳╴╵╶╷
+    [0,0,0,2], [2,0,0,0], [0,2,0,0], [0,0,2,0], [0,2,0,1], [1,0,2,0], [0,1,0,2], [2,0,1,0], // ╸╹╺╻╼╽╾╿
+];
+
+pub fn is_box_drawing_character(c: char) -> bool {
+    (BOX_DRAWING_START..=BLOCK_ELEMENTS_END).contains(&c)
+}
+
+// Draws glyph spanning the full cell so lines join up exactly with neighbouring cells
+// Returns false if the character is not a box drawing or block element character
+pub fn generate_box_drawing_glyph(c: char, size: Vector2<usize>, data: &mut Vec<u8>) -> bool {
+    data.clear();
+    data.resize(size.x*size.y, 0u8);
+    let mut canvas = Canvas { data: data.as_mut_slice(), size };
+    match c {
+        '\u{2504}'..='\u{250B}' | '\u{254C}'..='\u{254F}' => canvas.draw_dashed_line(c),
+        '\u{256D}'..='\u{2570}' => canvas.draw_arc(c),
+        '\u{2571}'..='\u{2573}' => canvas.draw_diagonal(c),
+        BOX_DRAWING_START..=BOX_DRAWING_END => {
+            let arms = LINE_ARMS[(c as usize) - (BOX_DRAWING_START as usize)];
+            canvas.draw_lines(arms);
+        },
+        BLOCK_ELEMENTS_START..=BLOCK_ELEMENTS_END => canvas.draw_block_element(c),
+        _ => return false,
+    }
+    true
+}
+
+// Offset that centres a line of given thickness
+fn get_centred_start(length: usize, thickness: usize) -> usize {
+    length.saturating_sub(thickness) / 2
+}
+
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    size: Vector2<usize>,
+}
+
+impl Canvas<'_> {
+    fn get_light_thickness(&self) -> usize {
+        (self.size.x.min(self.size.y) / 8).max(1)
+    }
+
+    // Double lines are two light lines separated by a light sized gap
+    fn get_thickness(&self, weight: u8) -> usize {
+        let light = self.get_light_thickness();
+        match weight {
+            WEIGHT_LIGHT => light,
+            WEIGHT_HEAVY => 2*light,
+            WEIGHT_DOUBLE => 3*light,
+            _ => 0,
+        }
+    }
+
+    fn fill_rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        let x1 = x1.min(self.size.x);
+        let y1 = y1.min(self.size.y);
+        for y in y0..y1 {
+            let row = &mut self.data[y*self.size.x..(y+1)*self.size.x];
+            for v in &mut row[x0.min(x1)..x1] {
+                *v = 255;
+            }
+        }
+    }
+
+    // Adds coverage so that adjacent rectangles with fractional edges sum up to a solid fill
+    fn add_coverage(&mut self, x: usize, y: usize, coverage: f32) {
+        let v = &mut self.data[y*self.size.x + x];
+        *v = (*v as f32 + coverage*255.0).round().min(255.0) as u8;
+    }
+
+    fn fill_rect_coverage(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, alpha: f32) {
+        let (x0, x1) = (x0.min(x1).max(0.0), x0.max(x1).min(self.size.x as f32));
+        let (y0, y1) = (y0.min(y1).max(0.0), y0.max(y1).min(self.size.y as f32));
+        for y in (y0.floor() as usize)..(y1.ceil() as usize) {
+            let coverage_y = (y1.min(y as f32 + 1.0) - y0.max(y as f32)).clamp(0.0, 1.0);
+            for x in (x0.floor() as usize)..(x1.ceil() as usize) {
+                let coverage_x = (x1.min(x as f32 + 1.0) - x0.max(x as f32)).clamp(0.0, 1.0);
+                self.add_coverage(x, y, coverage_x*coverage_y*alpha);
+            }
+        }
+    }
+
+    fn draw_lines(&mut self, arms: [u8; 4]) {
+        for arm in [ARM_UP, ARM_RIGHT, ARM_DOWN, ARM_LEFT] {
+            if arms[arm] != WEIGHT_NONE {
+                self.draw_arm(arms, arm);
+            }
+        }
+    }
+
+    // Draws line from edge of cell to the junction with perpendicular lines
+    fn draw_arm(&mut self, arms: [u8; 4], arm: usize) {
+        let is_vertical = arm == ARM_UP || arm == ARM_DOWN;
+        let (length, across) = if is_vertical { (self.size.y, self.size.x) } else { (self.size.x, self.size.y) };
+        let is_end = arm == ARM_RIGHT || arm == ARM_DOWN;
+        let weight = arms[arm];
+        let opposite = arms[(arm+2) % 4];
+        // perpendicular arms on the low and high side of the across axis
+        let (side_low, side_high) = if is_vertical { (arms[ARM_LEFT], arms[ARM_RIGHT]) } else { (arms[ARM_UP], arms[ARM_DOWN]) };
+        let light = self.get_light_thickness();
+
+        // (start, end, side) of each line across the arm
+        let mut strips = [(0, 0, WEIGHT_NONE); 2];
+        let total_strips = if weight == WEIGHT_DOUBLE {
+            let start = get_centred_start(across, 3*light);
+            strips[0] = (start, start+light, side_low);
+            strips[1] = (start+2*light, start+3*light, side_high);
+            2
+        } else {
+            let thickness = self.get_thickness(weight);
+            let start = get_centred_start(across, thickness);
+            strips[0] = (start, start+thickness, WEIGHT_NONE);
+            1
+        };
+
+        let is_perpendicular_double = side_low == WEIGHT_DOUBLE || side_high == WEIGHT_DOUBLE;
+        for &(start, end, side) in &strips[..total_strips] {
+            // region along the arm that the junction occupies
+            let (junction_start, junction_end) = if is_perpendicular_double {
+                // stop at the near line of the perpendicular double line or continue to the far line
+                let is_near = if weight == WEIGHT_DOUBLE {
+                    side != WEIGHT_NONE
+                } else {
+                    let is_continuous = side_low != WEIGHT_NONE && side_high != WEIGHT_NONE;
+                    is_continuous && opposite == WEIGHT_NONE
+                };
+                let double_start = get_centred_start(length, 3*light);
+                match (is_near, is_end) {
+                    (true, true) => (double_start+2*light, double_start+3*light),
+                    (true, false) => (double_start, double_start+light),
+                    (false, _) => (double_start, double_start+3*light),
+                }
+            } else {
+                let junction_weight = if side_low != WEIGHT_NONE || side_high != WEIGHT_NONE {
+                    side_low.max(side_high)
+                } else {
+                    weight
+                };
+                let thickness = self.get_thickness(junction_weight);
+                let junction_start = get_centred_start(length, thickness);
+                (junction_start, junction_start+thickness)
+            };
+            let (along_start, along_end) = if is_end { (junction_start, length) } else { (0, junction_end) };
+            if is_vertical {
+                self.fill_rect(start, along_start, end, along_end);
+            } else {
+                self.fill_rect(along_start, start, along_end, end);
+            }
+        }
+    }
+
+    fn draw_dashed_line(&mut self, c: char) {
+        let (total_dashes, index) = match c {
+            '\u{2504}'..='\u{2507}' => (3, c as usize - 0x2504),
+            '\u{2508}'..='\u{250B}' => (4, c as usize - 0x2508),
+            _ => (2, c as usize - 0x254C),
+        };
+        let weight = if index % 2 == 0 { WEIGHT_LIGHT } else { WEIGHT_HEAVY };
+        let is_vertical = index >= 2;
+        let (length, across) = if is_vertical { (self.size.y, self.size.x) } else { (self.size.x, self.size.y) };
+        let thickness = self.get_thickness(weight);
+        let start = get_centred_start(across, thickness);
+        let end = start+thickness;
+        for i in 0..total_dashes {
+            let dash_start = i*length/total_dashes;
+            let dash_end = (i+1)*length/total_dashes;
+            let dash_length = dash_end-dash_start;
+            // gap is split evenly on both sides so dashes are evenly spaced across cells
+            let gap = (dash_length/3).max(1).min(dash_length.saturating_sub(1));
+            let dash_start = dash_start + gap/2;
+            let dash_end = dash_end - (gap-gap/2);
+            if is_vertical {
+                self.fill_rect(start, dash_start, end, dash_end);
+            } else {
+                self.fill_rect(dash_start, start, dash_end, end);
+            }
+        }
+    }
+
+    fn draw_arc(&mut self, c: char) {
+        // direction from centre of cell towards the arms
+        let (dx, dy) = match c {
+            '\u{256D}' => (1.0, 1.0),
+            '\u{256E}' => (-1.0, 1.0),
+            '\u{256F}' => (-1.0, -1.0),
+            _ => (1.0, -1.0),
+        };
+        let thickness = self.get_light_thickness();
+        let width = self.size.x as f32;
+        let height = self.size.y as f32;
+        let line_x = get_centred_start(self.size.x, thickness) as f32;
+        let line_y = get_centred_start(self.size.y, thickness) as f32;
+        let half_thickness = thickness as f32 * 0.5;
+        let centre_x = line_x + half_thickness;
+        let centre_y = line_y + half_thickness;
+        let radius = (width.min(height) * 0.5 - half_thickness).max(half_thickness);
+        let arc_x = centre_x + dx*radius;
+        let arc_y = centre_y + dy*radius;
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                if (px - arc_x)*dx > 0.0 || (py - arc_y)*dy > 0.0 {
+                    continue;
+                }
+                let distance = ((px - arc_x).powi(2) + (py - arc_y).powi(2)).sqrt();
+                let coverage = (half_thickness + 0.5 - (distance - radius).abs()).clamp(0.0, 1.0);
+                self.add_coverage(x, y, coverage);
+            }
+        }
+        // straight lines from the ends of the arc to the edges of the cell
+        let edge_x = if dx > 0.0 { width } else { 0.0 };
+        let edge_y = if dy > 0.0 { height } else { 0.0 };
+        self.fill_rect_coverage(line_x, arc_y, line_x + thickness as f32, edge_y, 1.0);
+        self.fill_rect_coverage(arc_x, line_y, edge_x, line_y + thickness as f32, 1.0);
+    }
+
+    fn draw_diagonal(&mut self, c: char) {
+        let width = self.size.x as f32;
+        let height = self.size.y as f32;
+        let half_thickness = self.get_light_thickness() as f32 * 0.5;
+        let is_forward = c == '\u{2571}' || c == '\u{2573}';
+        let is_backward = c == '\u{2572}' || c == '\u{2573}';
+        let length = (width*width + height*height).sqrt();
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let mut coverage: f32 = 0.0;
+                // distance from line between opposite corners of the cell
+                if is_forward {
+                    let distance = (px*height + py*width - width*height).abs() / length;
+                    coverage = coverage.max(half_thickness + 0.5 - distance);
+                }
+                if is_backward {
+                    let distance = (px*height - py*width).abs() / length;
+                    coverage = coverage.max(half_thickness + 0.5 - distance);
+                }
+                self.add_coverage(x, y, coverage.clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    fn draw_block_element(&mut self, c: char) {
+        const QUADRANT_UPPER_LEFT: u8 = 0b0001;
+        const QUADRANT_UPPER_RIGHT: u8 = 0b0010;
+        const QUADRANT_LOWER_LEFT: u8 = 0b0100;
+        const QUADRANT_LOWER_RIGHT: u8 = 0b1000;
+        // U+2596 to U+259F
+        const QUADRANTS: [u8; 10] = [
+            QUADRANT_LOWER_LEFT,
+            QUADRANT_LOWER_RIGHT,
+            QUADRANT_UPPER_LEFT,
+            QUADRANT_UPPER_LEFT | QUADRANT_LOWER_LEFT | QUADRANT_LOWER_RIGHT,
+            QUADRANT_UPPER_LEFT | QUADRANT_LOWER_RIGHT,
+            QUADRANT_UPPER_LEFT | QUADRANT_UPPER_RIGHT | QUADRANT_LOWER_LEFT,
+            QUADRANT_UPPER_LEFT | QUADRANT_UPPER_RIGHT | QUADRANT_LOWER_RIGHT,
+            QUADRANT_UPPER_RIGHT,
+            QUADRANT_UPPER_RIGHT | QUADRANT_LOWER_LEFT,
+            QUADRANT_UPPER_RIGHT | QUADRANT_LOWER_LEFT | QUADRANT_LOWER_RIGHT,
+        ];
+        let w = self.size.x as f32;
+        let h = self.size.y as f32;
+        let code = c as u32;
+        match c {
+            '\u{2580}' => self.fill_rect_coverage(0.0, 0.0, w, h*0.5, 1.0),
+            // lower one eighth to full block
+            '\u{2581}'..='\u{2588}' => {
+                let fraction = (code - 0x2580) as f32 / 8.0;
+                self.fill_rect_coverage(0.0, h*(1.0-fraction), w, h, 1.0);
+            },
+            // left seven eighths to left one eighth
+            '\u{2589}'..='\u{258F}' => {
+                let fraction = (0x2590 - code) as f32 / 8.0;
+                self.fill_rect_coverage(0.0, 0.0, w*fraction, h, 1.0);
+            },
+            '\u{2590}' => self.fill_rect_coverage(w*0.5, 0.0, w, h, 1.0),
+            // light, medium and dark shade
+            '\u{2591}'..='\u{2593}' => {
+                let alpha = (code - 0x2590) as f32 / 4.0;
+                self.fill_rect_coverage(0.0, 0.0, w, h, alpha);
+            },
+            '\u{2594}' => self.fill_rect_coverage(0.0, 0.0, w, h/8.0, 1.0),
+            '\u{2595}' => self.fill_rect_coverage(w*7.0/8.0, 0.0, w, h, 1.0),
+            _ => {
+                let quadrants = QUADRANTS[(code - 0x2596) as usize];
+                let (cx, cy) = (w*0.5, h*0.5);
+                if quadrants & QUADRANT_UPPER_LEFT != 0 {
+                    self.fill_rect_coverage(0.0, 0.0, cx, cy, 1.0);
+                }
+                if quadrants & QUADRANT_UPPER_RIGHT != 0 {
+                    self.fill_rect_coverage(cx, 0.0, w, cy, 1.0);
+                }
+                if quadrants & QUADRANT_LOWER_LEFT != 0 {
+                    self.fill_rect_coverage(0.0, cy, cx, h, 1.0);
+                }
+                if quadrants & QUADRANT_LOWER_RIGHT != 0 {
+                    self.fill_rect_coverage(cx, cy, w, h, 1.0);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Vector2, Vector3};
+
+// Layout of a cell in the Rgba32Uint grid texture
+// r: [0:15] atlas column, [16:27] atlas row, [28:31] atlas layer
+// g: [0:23] foreground rgb, [24:31] style flags
+// b: [0:23] background rgb, [24:26] underline style, [27:31] reserved
+// a: [0:23] underline rgb, [24] has underline colour, [25:31] reserved
+// Constants are prepended to shader.wgsl so both sides share the same layout
+const ATLAS_WORD: usize = 0;
+const FOREGROUND_WORD: usize = 1;
+const BACKGROUND_WORD: usize = 2;
+const UNDERLINE_WORD: usize = 3;
+
+pub(crate) const ATLAS_COLUMN_MASK: u32 = 0xFFFF;
+pub(crate) const ATLAS_ROW_SHIFT: u32 = 16;
+pub(crate) const ATLAS_ROW_MASK: u32 = 0x0FFF;
+pub(crate) const ATLAS_LAYER_SHIFT: u32 = 28;
+pub(crate) const ATLAS_LAYER_MASK: u32 = 0xF;
+pub(crate) const COLOUR_MASK: u32 = 0x00FF_FFFF;
+pub(crate) const INVERSE_FLAG: u32 = 1 << 24;
+pub(crate) const HIDDEN_FLAG: u32 = 1 << 25;
+pub(crate) const CURSOR_FLAG: u32 = 1 << 26;
+pub(crate) const UNDERLINE_STYLE_SHIFT: u32 = 24;
+pub(crate) const UNDERLINE_STYLE_MASK: u32 = 0b111;
+pub(crate) const UNDERLINE_COLOUR_FLAG: u32 = 1 << 24;
+
+const WGSL_CONSTANTS: [(&str, u32); 12] = [
+    ("CELL_ATLAS_COLUMN_MASK", ATLAS_COLUMN_MASK),
+    ("CELL_ATLAS_ROW_SHIFT", ATLAS_ROW_SHIFT),
+    ("CELL_ATLAS_ROW_MASK", ATLAS_ROW_MASK),
+    ("CELL_ATLAS_LAYER_SHIFT", ATLAS_LAYER_SHIFT),
+    ("CELL_ATLAS_LAYER_MASK", ATLAS_LAYER_MASK),
+    ("CELL_COLOUR_MASK", COLOUR_MASK),
+    ("CELL_INVERSE_FLAG", INVERSE_FLAG),
+    ("CELL_HIDDEN_FLAG", HIDDEN_FLAG),
+    ("CELL_CURSOR_FLAG", CURSOR_FLAG),
+    ("CELL_UNDERLINE_STYLE_SHIFT", UNDERLINE_STYLE_SHIFT),
+    ("CELL_UNDERLINE_STYLE_MASK", UNDERLINE_STYLE_MASK),
+    ("CELL_UNDERLINE_COLOUR_FLAG", UNDERLINE_COLOUR_FLAG),
+];
+
+pub(crate) fn get_wgsl_constants() -> String {
+    WGSL_CONSTANTS
+        .iter()
+        .map(|(name, value)| format!("const {}: u32 = 0x{:08X}u;\n", name, value))
+        .collect()
+}
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum UnderlineStyle {
+    #[default]
+    None,
+    Straight,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+#[repr(C)]
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq,Pod,Zeroable)]
+pub struct CellData {
+    pub(crate) data: [u32; 4],
+}
+
+fn pack_rgb(c: Vector3<u8>) -> u32 {
+    (c.x as u32) | ((c.y as u32) << 8) | ((c.z as u32) << 16)
+}
+
+fn unpack_rgb(v: u32) -> Vector3<u8> {
+    Vector3::new(v as u8, (v >> 8) as u8, (v >> 16) as u8)
+}
+
+impl CellData {
+    fn set_bits(&mut self, word: usize, mask: u32, value: u32) {
+        assert!(value & !mask == 0);
+        self.data[word] = (self.data[word] & !mask) | value;
+    }
+
+    fn set_flag(&mut self, word: usize, flag: u32, is_set: bool) {
+        self.set_bits(word, flag, if is_set { flag } else { 0 });
+    }
+
+    pub fn set_atlas_index(&mut self, position: Vector2<usize>, layer: usize) {
+        assert!(position.x <= ATLAS_COLUMN_MASK as usize);
+        assert!(position.y <= ATLAS_ROW_MASK as usize);
+        assert!(layer <= ATLAS_LAYER_MASK as usize);
+        self.data[ATLAS_WORD] =
+            (position.x as u32) |
+            ((position.y as u32) << ATLAS_ROW_SHIFT) |
+            ((layer as u32) << ATLAS_LAYER_SHIFT);
+    }
+
+    pub fn get_atlas_position(&self) -> Vector2<usize> {
+        let v = self.data[ATLAS_WORD];
+        Vector2::new(
+            (v & ATLAS_COLUMN_MASK) as usize,
+            ((v >> ATLAS_ROW_SHIFT) & ATLAS_ROW_MASK) as usize,
+        )
+    }
+
+    pub fn get_atlas_layer(&self) -> usize {
+        ((self.data[ATLAS_WORD] >> ATLAS_LAYER_SHIFT) & ATLAS_LAYER_MASK) as usize
+    }
+
+    pub fn set_foreground_colour(&mut self, colour: Vector3<u8>) {
+        self.set_bits(FOREGROUND_WORD, COLOUR_MASK, pack_rgb(colour));
+    }
+
+    pub fn get_foreground_colour(&self) -> Vector3<u8> {
+        unpack_rgb(self.data[FOREGROUND_WORD])
+    }
+
+    pub fn set_background_colour(&mut self, colour: Vector3<u8>) {
+        self.set_bits(BACKGROUND_WORD, COLOUR_MASK, pack_rgb(colour));
+    }
+
+    pub fn get_background_colour(&self) -> Vector3<u8> {
+        unpack_rgb(self.data[BACKGROUND_WORD])
+    }
+
+    pub fn set_underline(&mut self, style: UnderlineStyle, colour: Option<Vector3<u8>>) {
+        let style = match style {
+            UnderlineStyle::None => 0,
+            UnderlineStyle::Straight => 1,
+            UnderlineStyle::Double => 2,
+            UnderlineStyle::Curly => 3,
+            UnderlineStyle::Dotted => 4,
+            UnderlineStyle::Dashed => 5,
+        };
+        self.set_bits(BACKGROUND_WORD, UNDERLINE_STYLE_MASK << UNDERLINE_STYLE_SHIFT, style << UNDERLINE_STYLE_SHIFT);
+        let colour = match colour {
+            Some(c) => UNDERLINE_COLOUR_FLAG | pack_rgb(c),
+            None => 0,
+        };
+        self.set_bits(UNDERLINE_WORD, UNDERLINE_COLOUR_FLAG | COLOUR_MASK, colour);
+    }
+
+    pub fn get_underline_style(&self) -> UnderlineStyle {
+        match (self.data[BACKGROUND_WORD] >> UNDERLINE_STYLE_SHIFT) & UNDERLINE_STYLE_MASK {
+            1 => UnderlineStyle::Straight,
+            2 => UnderlineStyle::Double,
+            3 => UnderlineStyle::Curly,
+            4 => UnderlineStyle::Dotted,
+            5 => UnderlineStyle::Dashed,
+            _ => UnderlineStyle::None,
+        }
+    }
+
+    pub fn get_underline_colour(&self) -> Option<Vector3<u8>> {
+        let v = self.data[UNDERLINE_WORD];
+        if v & UNDERLINE_COLOUR_FLAG == 0 {
+            return None;
+        }
+        Some(unpack_rgb(v))
+    }
+
+    // Foreground and background are swapped when drawn
+    pub fn set_inverse(&mut self, is_inverse: bool) {
+        self.set_flag(FOREGROUND_WORD, INVERSE_FLAG, is_inverse);
+    }
+
+    pub fn is_inverse(&self) -> bool {
+        self.data[FOREGROUND_WORD] & INVERSE_FLAG != 0
+    }
+
+    // Only the background is drawn
+    pub fn set_hidden(&mut self, is_hidden: bool) {
+        self.set_flag(FOREGROUND_WORD, HIDDEN_FLAG, is_hidden);
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.data[FOREGROUND_WORD] & HIDDEN_FLAG != 0
+    }
+
+    // Cursor is drawn over this cell using the style from the global parameters
+    pub fn set_cursor(&mut self, is_cursor: bool) {
+        self.set_flag(FOREGROUND_WORD, CURSOR_FLAG, is_cursor);
+    }
+
+    pub fn is_cursor(&self) -> bool {
+        self.data[FOREGROUND_WORD] & CURSOR_FLAG != 0
+    }
+}
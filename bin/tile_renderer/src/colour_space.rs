@@ -0,0 +1,23 @@
+// Transfer functions for a single colour channel in the range [0,1]
+pub fn convert_srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn convert_linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055*v.powf(1.0/2.4) - 0.055
+    }
+}
+
+// Shader blends in linear space so formats without hardware sRGB encoding need it applied in the shader
+// Float formats are treated as extended linear so they are written to directly
+pub fn is_srgb_encode_required(format: wgpu::TextureFormat) -> bool {
+    use wgpu::TextureFormat as F;
+    !format.is_srgb() && !matches!(format, F::Rgba16Float | F::Rgba32Float)
+}
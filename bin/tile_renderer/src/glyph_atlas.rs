@@ -1,20 +1,42 @@
-use cgmath::Vector2;
+use cgmath::{Vector2, ElementWise};
+use crate::cell_data::{ATLAS_LAYER_MASK, ATLAS_ROW_MASK};
 
-#[derive(Clone,Copy,Debug)]
+// Limited by the bits available to store the layer and row in CellData
+pub const MAX_ATLAS_LAYERS: usize = ATLAS_LAYER_MASK as usize + 1;
+pub const MAX_ATLAS_ROWS: usize = ATLAS_ROW_MASK as usize + 1;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
 pub struct GlyphIndex {
+    // texture array layer once the first layer is full
+    pub layer: usize,
     pub block: Vector2<usize>,
     pub position: Vector2<usize>, 
+    // number of consecutive positions occupied by glyph (wide glyphs take up 2)
+    pub span: usize,
 }
 
 impl Default for GlyphIndex {
     fn default() -> Self {
         Self {
+            layer: 0,
             block: Vector2::new(0,0),
             position: Vector2::new(0,0),
+            span: 1,
         }
     }
 }
 
+impl GlyphIndex {
+    // Position in atlas layer in units of glyphs, offset selects a cell within a wide glyph
+    pub fn get_atlas_position(&self, total_glyphs_in_block: Vector2<usize>, offset: usize) -> Vector2<usize> {
+        assert!(offset < self.span);
+        Vector2::new(
+            self.block.x*total_glyphs_in_block.x + self.position.x + offset,
+            self.block.y*total_glyphs_in_block.y + self.position.y,
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct GlyphAtlas {
     data: Vec<u8>,
@@ -22,17 +44,25 @@ pub struct GlyphAtlas {
     total_glyphs_in_block: Vector2<usize>,
     total_blocks: Vector2<usize>,
     max_blocks: Vector2<usize>,
+    total_layers: usize,
+    max_layers: usize,
     free_index: GlyphIndex,
+    // indexed by layer then block
     total_modified_glyphs_per_block: Vec<usize>,
 }
 
 impl GlyphAtlas {
-    pub(crate) fn new(glyph_size: Vector2<usize>, max_texture_size: Vector2<usize>) -> Self {
+    // Memory limit reduces the maximum number of blocks so glyphs are evicted before it is exceeded
+    pub(crate) fn new(
+        glyph_size: Vector2<usize>, max_texture_size: Vector2<usize>, 
+        max_layers: usize, max_memory_bytes: Option<usize>,
+    ) -> Self {
         // determine best block size
         let max_grid_size = Vector2::new(
             max_texture_size.x / glyph_size.x,
-            max_texture_size.y / glyph_size.y,
+            (max_texture_size.y / glyph_size.y).min(MAX_ATLAS_ROWS),
         );
+        let mut max_layers = max_layers.clamp(1, MAX_ATLAS_LAYERS);
         const DESIRED_GLYPHS_IN_BLOCK: Vector2<usize> = Vector2::new(16,8);
         let max_blocks = Vector2::new(
             (max_grid_size.x/DESIRED_GLYPHS_IN_BLOCK.x).max(1),
@@ -42,12 +72,40 @@ impl GlyphAtlas {
             max_grid_size.x/max_blocks.x,
             max_grid_size.y/max_blocks.y,
         );
+        let max_blocks = match max_memory_bytes {
+            Some(max_memory_bytes) => {
+                let block_size = glyph_size.mul_element_wise(total_glyphs_in_block);
+                let block_bytes = block_size.x*block_size.y;
+                let mut max_total_blocks = max_memory_bytes / block_bytes;
+                // ascii glyphs take up the first block and the remaining glyphs need at least one more
+                const MIN_TOTAL_BLOCKS: usize = 2;
+                if max_total_blocks < MIN_TOTAL_BLOCKS {
+                    log::warn!("Glyph atlas memory limit of {} bytes is below the minimum of {} bytes", 
+                        max_memory_bytes, MIN_TOTAL_BLOCKS*block_bytes);
+                    max_total_blocks = MIN_TOTAL_BLOCKS;
+                }
+                // blocks are filled row by row then layer by layer
+                let blocks_per_layer = max_blocks.x*max_blocks.y;
+                if max_total_blocks >= blocks_per_layer {
+                    max_layers = max_layers.min(max_total_blocks/blocks_per_layer);
+                    max_blocks
+                } else if max_total_blocks < max_blocks.x {
+                    max_layers = 1;
+                    Vector2::new(max_total_blocks, 1)
+                } else {
+                    max_layers = 1;
+                    Vector2::new(max_blocks.x, max_total_blocks/max_blocks.x)
+                }
+            },
+            None => max_blocks,
+        };
         log::info!(
-            "Creating glyph atlas with: glyph_size=({},{}) glyphs_in_block=({},{}) max_blocks=({},{}) max_size=({},{})",
+            "Creating glyph atlas with: glyph_size=({},{}) glyphs_in_block=({},{}) max_blocks=({},{}) max_size=({},{}) max_layers={}",
             glyph_size.x, glyph_size.y,
             total_glyphs_in_block.x, total_glyphs_in_block.y,
             max_blocks.x, max_blocks.y,
             glyph_size.x*total_glyphs_in_block.x*max_blocks.x, glyph_size.y*total_glyphs_in_block.y*max_blocks.y,
+            max_layers,
         );
         let mut atlas = Self {
             data: Vec::new(),
@@ -55,46 +113,64 @@ impl GlyphAtlas {
             total_glyphs_in_block,
             total_blocks: Vector2::new(0,0),
             max_blocks,
+            total_layers: 0,
+            max_layers,
             free_index: GlyphIndex::default(),
             total_modified_glyphs_per_block: Vec::new(),
         };
-        atlas.resize(Vector2::new(1,1));
+        atlas.resize(Vector2::new(1,1), 1);
         atlas
     }
 
-    fn resize(&mut self, total_blocks: Vector2<usize>) {
-        let length = total_blocks.x*total_blocks.y;
+    // Layers are only added once the first layer is at its maximum size
+    fn resize(&mut self, total_blocks: Vector2<usize>, total_layers: usize) {
+        let length = total_blocks.x*total_blocks.y*total_layers;
         self.total_blocks = total_blocks;
+        self.total_layers = total_layers;
         self.total_modified_glyphs_per_block.resize(length, 0);
         self.total_modified_glyphs_per_block.fill(1);
+        self.data.resize(self.get_layer_stride()*total_layers, 0u8);
+        log::info!("Resizing glyph atlas to {}x{}x{}", total_blocks.x, total_blocks.y, total_layers);
+    }
+
+    fn get_block_stride(&self) -> usize {
+        let glyph_stride = self.glyph_size.x*self.glyph_size.y;
+        let total_glyphs_in_block = self.total_glyphs_in_block.x*self.total_glyphs_in_block.y;
+        total_glyphs_in_block*glyph_stride
+    }
+
+    fn get_layer_stride(&self) -> usize {
         let texture_size = self.get_texture_size();
-        self.data.resize(texture_size.x*texture_size.y, 0u8);
-        log::info!("Resizing glyph atlas to {}x{}", total_blocks.x, total_blocks.y);
+        texture_size.x*texture_size.y
+    }
+
+    fn get_block_index(&self, layer: usize, block: Vector2<usize>) -> usize {
+        let total_blocks_in_layer = self.total_blocks.x*self.total_blocks.y;
+        layer*total_blocks_in_layer + block.y*self.total_blocks.x + block.x
     }
 
-    pub fn get_block(&self, block: Vector2<usize>) -> &[u8] {
+    pub fn get_block(&self, layer: usize, block: Vector2<usize>) -> &[u8] {
+        assert!(layer < self.total_layers);
         assert!(block.x < self.total_blocks.x);
         assert!(block.y < self.total_blocks.y);
-        let glyph_stride = self.glyph_size.x*self.glyph_size.y;
-        let total_glyphs_in_block = self.total_glyphs_in_block.x*self.total_glyphs_in_block.y;
-        let block_stride = total_glyphs_in_block*glyph_stride;
-        let block_index = block.x + block.y*self.total_blocks.x;
-        let block_offset = block_index*block_stride;
+        let block_stride = self.get_block_stride();
+        let block_offset = self.get_block_index(layer, block)*block_stride;
         &self.data[block_offset..(block_offset+block_stride)]
     }
 
     pub(crate) fn write_glyph(&mut self, index: GlyphIndex, data: &[u8]) {
+        assert!(index.layer < self.total_layers);
         assert!(index.block.x < self.total_blocks.x);
         assert!(index.block.y < self.total_blocks.y);
-        assert!(index.position.x < self.total_glyphs_in_block.x);
+        assert!(index.span > 0);
+        assert!((index.position.x + index.span) <= self.total_glyphs_in_block.x);
         assert!(index.position.y < self.total_glyphs_in_block.y);
-        assert!(data.len() == (self.glyph_size.y*self.glyph_size.x));
+        let glyph_width = self.glyph_size.x*index.span;
+        assert!(data.len() == (self.glyph_size.y*glyph_width));
 
-        let glyph_stride = self.glyph_size.x*self.glyph_size.y;
-        let total_glyphs_in_block = self.total_glyphs_in_block.x*self.total_glyphs_in_block.y;
+        let block_index = self.get_block_index(index.layer, index.block);
         let dst_block = {
-            let block_stride = total_glyphs_in_block*glyph_stride;
-            let block_index = index.block.x + index.block.y*self.total_blocks.x;
+            let block_stride = self.get_block_stride();
             let block_offset = block_index*block_stride;
             &mut self.data[block_offset..(block_offset+block_stride)]
         };
@@ -106,33 +182,51 @@ impl GlyphAtlas {
 
         for y in 0..self.glyph_size.y {
             let i_dst = glyph_offset + y*row_stride;
-            let i_src = y*self.glyph_size.x;
-            let src_buf = &data[i_src..(i_src+self.glyph_size.x)];
-            let dst_buf = &mut dst_block[i_dst..(i_dst+self.glyph_size.x)];
+            let i_src = y*glyph_width;
+            let src_buf = &data[i_src..(i_src+glyph_width)];
+            let dst_buf = &mut dst_block[i_dst..(i_dst+glyph_width)];
             dst_buf.copy_from_slice(src_buf);
         }
-        let block_index = index.block.y*self.total_blocks.x + index.block.x;
         self.total_modified_glyphs_per_block[block_index] += 1;
     }
 
     pub(crate) fn get_free_index(&mut self) -> Option<GlyphIndex> {
-        if self.free_index.block.y >= self.max_blocks.y {
+        self.get_free_index_with_span(1)
+    }
+
+    // Skips to the next row if the glyph doesn't fit in the remainder of the current row
+    pub(crate) fn get_free_index_with_span(&mut self, span: usize) -> Option<GlyphIndex> {
+        if span == 0 || span > self.total_glyphs_in_block.x {
+            return None;
+        }
+        while (self.free_index.position.x + span) > self.total_glyphs_in_block.x {
+            if !self.increment_free_index() {
+                return None;
+            }
+        }
+        if self.free_index.layer >= self.max_layers {
             return None;
         }
         assert!(self.free_index.block.x < self.max_blocks.x);
+        assert!(self.free_index.block.y < self.max_blocks.y);
         // resize to fit free index
         let new_total_blocks = Vector2::new(
             self.total_blocks.x.max(self.free_index.block.x+1),
             self.total_blocks.y.max(self.free_index.block.y+1),
         );
-        if new_total_blocks != self.total_blocks {
-            self.resize(new_total_blocks);
+        let new_total_layers = self.total_layers.max(self.free_index.layer+1);
+        if new_total_blocks != self.total_blocks || new_total_layers != self.total_layers {
+            self.resize(new_total_blocks, new_total_layers);
         }
-        Some(self.free_index)
+        Some(GlyphIndex { span, ..self.free_index })
+    }
+
+    pub(crate) fn increment_free_index_by(&mut self, total: usize) -> bool {
+        (0..total).all(|_| self.increment_free_index())
     }
 
     pub(crate) fn increment_free_index(&mut self) -> bool {
-        if self.free_index.block.y >= self.max_blocks.y {
+        if self.free_index.layer >= self.max_layers {
             return false;
         }
         self.free_index.position.x += 1;
@@ -148,20 +242,28 @@ impl GlyphAtlas {
             self.free_index.block.x = 0;
             self.free_index.block.y += 1;
         }
+        if self.free_index.block.y >= self.max_blocks.y {
+            self.free_index.block.y = 0;
+            self.free_index.layer += 1;
+        }
         true
     }
 
-    pub fn get_modified_blocks(&self) -> impl Iterator<Item=Vector2<usize>> + '_ {
+    // Returns (layer, block) of each modified block
+    pub fn get_modified_blocks(&self) -> impl Iterator<Item=(usize, Vector2<usize>)> + '_ {
+        let total_blocks_in_layer = self.total_blocks.x*self.total_blocks.y;
         self.total_modified_glyphs_per_block
             .iter()
             .enumerate()
             .filter(|(_i,count)| {
                 **count > 0usize
             })
-            .map(|(i,_count)| {
+            .map(move |(i,_count)| {
+                let layer = i / total_blocks_in_layer;
+                let i = i % total_blocks_in_layer;
                 let row = i / self.total_blocks.x;
                 let col = i % self.total_blocks.x;
-                Vector2::<usize>::new(col, row)
+                (layer, Vector2::<usize>::new(col, row))
             })
     }
 
@@ -181,6 +283,30 @@ impl GlyphAtlas {
         self.total_blocks
     }
 
+    pub fn get_total_layers(&self) -> usize {
+        self.total_layers
+    }
+
+    // Texture is R8Unorm so each pixel is one byte
+    pub fn get_memory_usage_bytes(&self) -> usize {
+        self.get_layer_stride()*self.total_layers
+    }
+
+    // Fraction of glyph slots up to the free index out of the maximum the atlas can grow to
+    pub fn get_block_utilization(&self) -> f32 {
+        let glyphs_per_block = self.total_glyphs_in_block.x*self.total_glyphs_in_block.y;
+        let blocks_per_layer = self.max_blocks.x*self.max_blocks.y;
+        let total_slots = self.max_layers*blocks_per_layer*glyphs_per_block;
+        if total_slots == 0 {
+            return 1.0;
+        }
+        let index = self.free_index;
+        let block_index = index.layer*blocks_per_layer + index.block.y*self.max_blocks.x + index.block.x;
+        let occupied_slots = block_index*glyphs_per_block + index.position.y*self.total_glyphs_in_block.x + index.position.x;
+        occupied_slots.min(total_slots) as f32 / total_slots as f32
+    }
+
+    // Size of a single layer
     pub fn get_texture_size(&self) -> Vector2<usize> {
         Vector2::new(
             self.glyph_size.x*self.total_glyphs_in_block.x*self.total_blocks.x,
@@ -1,6 +1,7 @@
 use crate::lru_list::LruList;
 use crate::glyph_atlas::{GlyphAtlas,GlyphIndex};
 use crate::glyph_generator::GlyphGenerator;
+use crate::box_drawing::{is_box_drawing_character, generate_box_drawing_glyph};
 use cgmath::Vector2;
 use std::collections::HashMap;
 
@@ -15,37 +16,112 @@ struct GlyphEntry {
 pub struct GlyphCache {
     glyph_generator: Box<dyn GlyphGenerator>,
     glyph_atlas: GlyphAtlas,
+    max_texture_size: Vector2<usize>,
+    max_texture_layers: usize,
+    max_atlas_memory_bytes: Option<usize>,
+    is_utilization_warned: bool,
     fallback_atlas_index: GlyphIndex,
     ascii_atlas_index: Vec<GlyphIndex>,
     lru_glyph_index: HashMap<char,usize>,
     lru_glyph_list: LruList<GlyphEntry>,
+    // wide glyphs are evicted separately so that they only replace slots of the same width
+    lru_wide_glyph_index: HashMap<char,usize>,
+    lru_wide_glyph_list: LruList<GlyphEntry>,
+    // box drawing characters are drawn procedurally so they join up between cells
+    builtin_box_drawing: bool,
+    box_drawing_buffer: Vec<u8>,
 }
 
 const ASCII_GLYPH_START: char = ' ';
 const ASCII_GLYPH_END: char = '~';
+pub const MIN_FONT_SIZE: f32 = 4.0;
 
 impl GlyphCache {
-    pub fn new(glyph_generator: Box<dyn GlyphGenerator>, max_texture_size: Vector2<usize>) -> Self {
+    pub fn new(
+        glyph_generator: Box<dyn GlyphGenerator>, 
+        max_texture_size: Vector2<usize>,
+        max_texture_layers: usize,
+        max_atlas_memory_bytes: Option<usize>,
+    ) -> Self {
         let glyph_size = glyph_generator.get_glyph_size();
-        let glyph_atlas = GlyphAtlas::new(glyph_size, max_texture_size);
+        let glyph_atlas = GlyphAtlas::new(glyph_size, max_texture_size, max_texture_layers, max_atlas_memory_bytes);
  
         let mut cache = Self {
             glyph_generator,
             glyph_atlas,
+            max_texture_size,
+            max_texture_layers,
+            max_atlas_memory_bytes,
+            is_utilization_warned: false,
             fallback_atlas_index: GlyphIndex::default(),
             ascii_atlas_index: Vec::new(),
             lru_glyph_index: HashMap::new(),
             lru_glyph_list: LruList::default(),
+            lru_wide_glyph_index: HashMap::new(),
+            lru_wide_glyph_list: LruList::default(),
+            builtin_box_drawing: true,
+            box_drawing_buffer: Vec::new(),
         };
         cache.generate_fallback_glyph();
         cache.generate_ascii_glyphs();
         cache
     }
 
+    pub fn get_font_size(&self) -> f32 {
+        self.glyph_generator.get_font_size()
+    }
+
+    // Returns false if the font size is rejected, in which case the cache is left untouched
+    pub fn set_font_size(&mut self, font_size: f32) -> bool {
+        if !font_size.is_finite() || font_size < MIN_FONT_SIZE {
+            return false;
+        }
+        let old_font_size = self.glyph_generator.get_font_size();
+        self.glyph_generator.set_font_size(font_size);
+        let glyph_size = self.glyph_generator.get_glyph_size();
+        if glyph_size.x > self.max_texture_size.x || glyph_size.y > self.max_texture_size.y {
+            self.glyph_generator.set_font_size(old_font_size);
+            return false;
+        }
+        self.reset_atlas();
+        true
+    }
+
+    pub fn get_builtin_box_drawing(&self) -> bool {
+        self.builtin_box_drawing
+    }
+
+    pub fn set_builtin_box_drawing(&mut self, builtin_box_drawing: bool) {
+        if self.builtin_box_drawing == builtin_box_drawing {
+            return;
+        }
+        self.builtin_box_drawing = builtin_box_drawing;
+        self.reset_atlas();
+    }
+
+    fn reset_atlas(&mut self) {
+        // atlas is recreated so all blocks are marked as modified and reuploaded
+        let glyph_size = self.glyph_generator.get_glyph_size();
+        self.glyph_atlas = GlyphAtlas::new(
+            glyph_size, self.max_texture_size, 
+            self.max_texture_layers, self.max_atlas_memory_bytes,
+        );
+        self.is_utilization_warned = false;
+        self.fallback_atlas_index = GlyphIndex::default();
+        self.ascii_atlas_index.clear();
+        self.lru_glyph_index.clear();
+        self.lru_glyph_list = LruList::default();
+        self.lru_wide_glyph_index.clear();
+        self.lru_wide_glyph_list = LruList::default();
+        self.generate_fallback_glyph();
+        self.generate_ascii_glyphs();
+    }
+
     fn generate_fallback_glyph(&mut self) {
-        let atlas_index = self.glyph_atlas.get_free_index();
+        let span = self.glyph_generator.get_glyph_span('\0');
+        let atlas_index = self.glyph_atlas.get_free_index_with_span(span);
         let atlas_index = atlas_index.expect("Cache should have enough room for fallback");
-        assert!(self.glyph_atlas.increment_free_index());
+        assert!(self.glyph_atlas.increment_free_index_by(span));
         let glyph_data = self.glyph_generator.generate_glyph('\0');
         self.glyph_atlas.write_glyph(atlas_index, glyph_data);
         self.fallback_atlas_index = atlas_index;
@@ -53,9 +129,10 @@ impl GlyphCache {
 
     fn generate_ascii_glyphs(&mut self) {
         for c in ASCII_GLYPH_START..=ASCII_GLYPH_END {
-            let atlas_index = self.glyph_atlas.get_free_index();
+            let span = self.glyph_generator.get_glyph_span(c);
+            let atlas_index = self.glyph_atlas.get_free_index_with_span(span);
             let atlas_index = atlas_index.expect("Glyph atlas should have enough room for ascii characters");
-            assert!(self.glyph_atlas.increment_free_index());
+            assert!(self.glyph_atlas.increment_free_index_by(span));
             let glyph_data = self.glyph_generator.generate_glyph(c);
             self.glyph_atlas.write_glyph(atlas_index, glyph_data);
             self.ascii_atlas_index.push(atlas_index);
@@ -65,7 +142,7 @@ impl GlyphCache {
         loop {
             let curr_index = self.glyph_atlas.get_free_index();
             let curr_index = curr_index.expect("Cache should have enough room to allocate start of non-ascii blocks");
-            if last_index.block != curr_index.block {
+            if last_index.layer != curr_index.layer || last_index.block != curr_index.block {
                 break;
             }
             assert!(self.glyph_atlas.increment_free_index());
@@ -77,6 +154,27 @@ impl GlyphCache {
         self.ascii_atlas_index[glyph_index]
     }
 
+    pub fn atlas_memory_bytes(&self) -> usize {
+        self.glyph_atlas.get_memory_usage_bytes()
+    }
+
+    pub fn atlas_utilization(&self) -> f32 {
+        self.glyph_atlas.get_block_utilization()
+    }
+
+    fn check_atlas_utilization(&mut self) {
+        const UTILIZATION_WARNING_THRESHOLD: f32 = 0.9;
+        if self.is_utilization_warned {
+            return;
+        }
+        let utilization = self.atlas_utilization();
+        if utilization > UTILIZATION_WARNING_THRESHOLD {
+            self.is_utilization_warned = true;
+            log::warn!("Glyph atlas is {:.1}% full using {} bytes, glyphs will be evicted once full", 
+                utilization*100.0, self.atlas_memory_bytes());
+        }
+    }
+
     pub fn get_glyph_atlas(&self) -> &GlyphAtlas {
         &self.glyph_atlas
     }
@@ -90,32 +188,43 @@ impl GlyphCache {
         if (ASCII_GLYPH_START..=ASCII_GLYPH_END).contains(&c) {
             return self.get_ascii_atlas_index(c);
         }
+        let is_builtin = self.builtin_box_drawing && is_box_drawing_character(c);
         // fallback glyph
-        if !self.glyph_generator.has_glyph(c) { 
+        if !is_builtin && !self.glyph_generator.has_glyph(c) { 
             return self.fallback_atlas_index;
         };
+        let span = if is_builtin { 1 } else { self.glyph_generator.get_glyph_span(c) };
+        let (lru_index, lru_list) = if span > 1 {
+            (&mut self.lru_wide_glyph_index, &mut self.lru_wide_glyph_list)
+        } else {
+            (&mut self.lru_glyph_index, &mut self.lru_glyph_list)
+        };
         // check if glyph in atlas
-        if let Some(glyph_index) = self.lru_glyph_index.get(&c) {
-            let glyph_entry = self.lru_glyph_list.get_mut_data(*glyph_index);
+        if let Some(glyph_index) = lru_index.get(&c) {
+            let glyph_entry = lru_list.get_mut_data(*glyph_index);
             glyph_entry.render_id = render_id;
             return glyph_entry.atlas_index;
         }
         // write glyph into atlas
-        let atlas_index = match self.glyph_atlas.get_free_index() {
+        let atlas_index = match self.glyph_atlas.get_free_index_with_span(span) {
             Some(atlas_index) => {
-                assert!(self.glyph_atlas.increment_free_index());
-                let glyph_index = self.lru_glyph_list.push(&GlyphEntry { 
+                assert!(self.glyph_atlas.increment_free_index_by(span));
+                let glyph_index = lru_list.push(&GlyphEntry { 
                     character: c, 
                     atlas_index,
                     render_id,
                 });
-                self.lru_glyph_index.insert(c, glyph_index);
+                lru_index.insert(c, glyph_index);
+                self.check_atlas_utilization();
                 atlas_index
             },
             None => {
-                let glyph_index = self.lru_glyph_list.get_oldest();
-                let glyph_index = glyph_index.expect("Glyph cache should already be populated with old entries");
-                let glyph_entry = self.lru_glyph_list.get_mut_data(glyph_index);
+                let Some(glyph_index) = lru_list.get_oldest() else {
+                    // atlas filled up before any glyphs of this width were allocated
+                    log::warn!("no room in glyph atlas for '{}' with span={}", c, span);
+                    return self.fallback_atlas_index;
+                };
+                let glyph_entry = lru_list.get_mut_data(glyph_index);
                 let old_character = glyph_entry.character;
                 let atlas_index = glyph_entry.atlas_index;
                 if glyph_entry.render_id == render_id {
@@ -124,15 +233,21 @@ impl GlyphCache {
                 }
                 glyph_entry.character = c;
                 glyph_entry.render_id = render_id;
-                let _is_promoted = self.lru_glyph_list.promote(glyph_index);
-                self.lru_glyph_index.remove(&old_character);
-                self.lru_glyph_index.insert(c, glyph_index);
+                let _is_promoted = lru_list.promote(glyph_index);
+                lru_index.remove(&old_character);
+                lru_index.insert(c, glyph_index);
                 atlas_index
             },
         };
 
-        let glyph_data = self.glyph_generator.generate_glyph(c);
-        self.glyph_atlas.write_glyph(atlas_index, glyph_data);
+        if is_builtin {
+            let glyph_size = self.glyph_generator.get_glyph_size();
+            generate_box_drawing_glyph(c, glyph_size, &mut self.box_drawing_buffer);
+            self.glyph_atlas.write_glyph(atlas_index, self.box_drawing_buffer.as_slice());
+        } else {
+            let glyph_data = self.glyph_generator.generate_glyph(c);
+            self.glyph_atlas.write_glyph(atlas_index, glyph_data);
+        }
         atlas_index
     }
 }
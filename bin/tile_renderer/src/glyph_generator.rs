@@ -2,8 +2,12 @@ use cgmath::Vector2;
 
 pub trait GlyphGenerator {
     fn get_glyph_size(&self) -> Vector2<usize>;
+    // Number of cells glyph occupies, glyph data is span*glyph_size.x pixels wide
+    fn get_glyph_span(&self, character: char) -> usize;
     fn generate_glyph(&mut self, character: char) -> &[u8];
     fn has_glyph(&self, character: char) -> bool;
+    fn get_font_size(&self) -> f32;
+    fn set_font_size(&mut self, font_size_em: f32);
 }
 
 #[derive(Clone,Debug)]
@@ -17,18 +21,29 @@ pub struct FontdueGlyphGenerator {
 
 impl FontdueGlyphGenerator {
     pub fn new(font: fontdue::Font, font_size_em: f32) -> Self {
-        let font_line_metrics = font.horizontal_line_metrics(font_size_em).expect("Horizontal font expected");
-        let glyph_baseline = font_line_metrics.ascent as usize;
-        let glyph_height = (font_line_metrics.ascent - font_line_metrics.descent) as usize;
-        let glyph_width = font.metrics(' ', font_size_em).advance_width as usize;
-        let glyph_size = Vector2::<usize>::new(glyph_width, glyph_height);
-        Self {
+        let mut generator = Self {
             font,
             font_size_em,
-            glyph_baseline,
-            glyph_size,
-            temp_glyph_buffer: vec![0u8; glyph_size.x*glyph_size.y],
-        }
+            glyph_baseline: 0,
+            glyph_size: Vector2::new(0,0),
+            temp_glyph_buffer: Vec::new(),
+        };
+        generator.update_metrics();
+        generator
+    }
+
+    fn update_metrics(&mut self) {
+        let font_line_metrics = self.font.horizontal_line_metrics(self.font_size_em).expect("Horizontal font expected");
+        let glyph_baseline = font_line_metrics.ascent as usize;
+        let glyph_height = (font_line_metrics.ascent - font_line_metrics.descent) as usize;
+        let glyph_width = self.font.metrics(' ', self.font_size_em).advance_width as usize;
+        self.glyph_baseline = glyph_baseline;
+        self.glyph_size = Vector2::<usize>::new(glyph_width.max(1), glyph_height.max(1));
+        self.temp_glyph_buffer.resize(self.glyph_size.x*self.glyph_size.y, 0u8);
+    }
+
+    fn get_span_from_width(&self, width: usize) -> usize {
+        if width > self.glyph_size.x { 2 } else { 1 }
     }
 }
 
@@ -37,23 +52,31 @@ impl GlyphGenerator for FontdueGlyphGenerator {
         self.glyph_size
     }
 
+    fn get_glyph_span(&self, character: char) -> usize {
+        let metrics = self.font.metrics(character, self.font_size_em);
+        self.get_span_from_width(metrics.width)
+    }
+
     fn generate_glyph(&mut self, character: char) -> &[u8] {
         let (metrics, bitmap) = self.font.rasterize(character, self.font_size_em);
+        // wide glyphs are rendered across two consecutive cells
+        let glyph_width = self.glyph_size.x*self.get_span_from_width(metrics.width);
         // determine position of glyph from baseline
         let y_offset = self.glyph_baseline as i32 - metrics.ymin - metrics.height as i32;
         let x_offset = metrics.xmin;
         // If glyph overflows or underflows view we shifted it and clamp its location and size
         let y_offset = y_offset.clamp(0, self.glyph_size.y as i32) as usize;
-        let x_offset = x_offset.clamp(0, self.glyph_size.x as i32) as usize;
+        let x_offset = x_offset.clamp(0, glyph_width as i32) as usize;
         let height = metrics.height.min(self.glyph_size.y-y_offset);
-        let width = metrics.width.min(self.glyph_size.x-x_offset);
+        let width = metrics.width.min(glyph_width-x_offset);
 
+        self.temp_glyph_buffer.resize(glyph_width*self.glyph_size.y, 0u8);
         self.temp_glyph_buffer.fill(0u8);
-        assert!((x_offset + width) <= self.glyph_size.x);
+        assert!((x_offset + width) <= glyph_width);
         assert!((y_offset + height) <= self.glyph_size.y);
         for y in 0..height {
             let i_src = y*metrics.width;
-            let i_dst = (y+y_offset)*self.glyph_size.x + x_offset;
+            let i_dst = (y+y_offset)*glyph_width + x_offset;
             let row_src = &bitmap[i_src..(i_src+width)];
             let row_dst = &mut self.temp_glyph_buffer[i_dst..(i_dst+width)];
             row_dst.copy_from_slice(row_src);
@@ -64,4 +87,13 @@ impl GlyphGenerator for FontdueGlyphGenerator {
     fn has_glyph(&self, character: char) -> bool {
         self.font.has_glyph(character)
     }
+
+    fn get_font_size(&self) -> f32 {
+        self.font_size_em
+    }
+
+    fn set_font_size(&mut self, font_size_em: f32) {
+        self.font_size_em = font_size_em;
+        self.update_metrics();
+    }
 }
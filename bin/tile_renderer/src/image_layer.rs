@@ -0,0 +1,134 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::Vector2;
+
+// Image drawn over the grid where position and size are in cells
+// Position is relative to the first rendered row and is negative if the image starts above it
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct ImageQuad {
+    pub id: u64,
+    pub position: Vector2<isize>,
+    pub size: Vector2<usize>,
+}
+
+#[repr(C)]
+#[derive(Clone,Copy,Debug,Default,Pod,Zeroable)]
+pub(crate) struct ImageParameters {
+    pub(crate) position: Vector2<f32>,
+    pub(crate) size: Vector2<f32>,
+}
+
+impl ImageParameters {
+    pub(crate) fn from_quad(quad: &ImageQuad) -> Self {
+        Self {
+            position: quad.position.cast::<f32>().unwrap(),
+            size: quad.size.cast::<f32>().unwrap(),
+        }
+    }
+}
+
+// Texture of an uploaded image which is only drawn while it has a quad
+pub(crate) struct ImageLayer {
+    _texture: wgpu::Texture,
+    parameters_uniform: wgpu::Buffer,
+    pub(crate) bind_group: wgpu::BindGroup,
+    pub(crate) is_visible: bool,
+}
+
+pub(crate) fn create_image_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("image_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+impl ImageLayer {
+    // Pixels are tightly packed rgba8 in srgb
+    pub(crate) fn new(
+        device: &wgpu::Device, queue: &wgpu::Queue, layout: &wgpu::BindGroupLayout,
+        size: Vector2<usize>, pixels: &[u8],
+    ) -> Self {
+        assert!(pixels.len() == size.x*size.y*4);
+        let extent = wgpu::Extent3d {
+            width: size.x.max(1) as u32,
+            height: size.y.max(1) as u32,
+            depth_or_array_layers: 1,
+        };
+        // sampled as linear colours so they are blended the same way as the grid
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("image_texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        if !pixels.is_empty() {
+            queue.write_texture(
+                texture.as_image_copy(),
+                pixels,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some((size.x*4) as u32),
+                    rows_per_image: Some(size.y as u32),
+                },
+                extent,
+            );
+        }
+        let parameters_uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("image_parameters"),
+            size: std::mem::size_of::<ImageParameters>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("image_bind_group"),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: parameters_uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+            ],
+            layout,
+        });
+        Self {
+            _texture: texture,
+            parameters_uniform,
+            bind_group,
+            is_visible: false,
+        }
+    }
+
+    pub(crate) fn set_quad(&mut self, queue: &wgpu::Queue, quad: &ImageQuad) {
+        let parameters = ImageParameters::from_quad(quad);
+        queue.write_buffer(&self.parameters_uniform, 0, bytemuck::cast_slice(&[parameters]));
+        self.is_visible = true;
+    }
+}
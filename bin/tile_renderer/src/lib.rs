@@ -1,11 +1,694 @@
+mod box_drawing;
+mod cell_data;
+mod colour_space;
 mod glyph_atlas;
 mod glyph_cache;
 mod glyph_generator;
+mod image_layer;
 mod lru_list;
+mod render_stats;
 mod renderer;
+mod scrollbar;
 
+pub use box_drawing::is_box_drawing_character;
+pub use cell_data::{CellData, UnderlineStyle};
+pub use colour_space::{convert_srgb_to_linear, convert_linear_to_srgb, is_srgb_encode_required};
 pub use glyph_atlas::{GlyphAtlas, GlyphIndex};
-pub use glyph_cache::GlyphCache;
+pub use glyph_cache::{GlyphCache, MIN_FONT_SIZE};
 pub use glyph_generator::{GlyphGenerator, FontdueGlyphGenerator};
-pub use renderer::{CellData, Renderer};
+pub use image_layer::ImageQuad;
+pub use renderer::{Renderer, CursorStyle, CursorData};
 pub use lru_list::LruList;
+pub use render_stats::{FrameTimings, RenderStats};
+pub use scrollbar::{ScrollbarThumb, calculate_scrollbar_thumb};
+
+#[cfg(test)]
+mod test {
+    use crate::{GlyphCache, GlyphGenerator, GlyphAtlas, GlyphIndex};
+    use cgmath::Vector2;
+    use std::{rc::Rc, cell::Cell};
+
+    struct TestGlyphGenerator {
+        font_size: f32,
+        glyph_buffer: Vec<u8>,
+        total_generated: Rc<Cell<usize>>,
+    }
+
+    impl TestGlyphGenerator {
+        fn new(font_size: f32, total_generated: Rc<Cell<usize>>) -> Self {
+            let mut generator = Self { font_size, glyph_buffer: Vec::new(), total_generated };
+            generator.set_font_size(font_size);
+            generator
+        }
+    }
+
+    impl GlyphGenerator for TestGlyphGenerator {
+        fn get_glyph_size(&self) -> Vector2<usize> {
+            let height = self.font_size as usize;
+            Vector2::new(height/2, height)
+        }
+
+        fn get_glyph_span(&self, character: char) -> usize {
+            // treat cjk as wide
+            if character >= '\u{3000}' { 2 } else { 1 }
+        }
+
+        fn generate_glyph(&mut self, character: char) -> &[u8] {
+            self.total_generated.set(self.total_generated.get()+1);
+            let size = self.get_glyph_size();
+            let span = self.get_glyph_span(character);
+            self.glyph_buffer.resize(span*size.x*size.y, 0u8);
+            self.glyph_buffer.as_slice()
+        }
+
+        fn has_glyph(&self, _character: char) -> bool {
+            true
+        }
+
+        fn get_font_size(&self) -> f32 {
+            self.font_size
+        }
+
+        fn set_font_size(&mut self, font_size: f32) {
+            self.font_size = font_size;
+            let size = self.get_glyph_size();
+            self.glyph_buffer.resize(size.x*size.y, 0u8);
+        }
+    }
+
+    #[test]
+    fn font_size_change_invalidates_cache() {
+        let total_generated = Rc::new(Cell::new(0));
+        let generator = Box::new(TestGlyphGenerator::new(8.0, total_generated.clone()));
+        let mut cache = GlyphCache::new(generator, Vector2::new(256,256), 1, None);
+        assert_eq!(cache.get_glyph_atlas().get_glyph_size(), Vector2::new(4,8));
+        // cached glyphs are only generated once
+        let _ = cache.get_glyph_location('α', 0);
+        let total_before = total_generated.get();
+        let _ = cache.get_glyph_location('α', 1);
+        assert_eq!(total_generated.get(), total_before);
+        // new font size regenerates glyphs into a new atlas
+        assert!(cache.set_font_size(16.0));
+        assert_eq!(cache.get_glyph_atlas().get_glyph_size(), Vector2::new(8,16));
+        let total_before = total_generated.get();
+        let _ = cache.get_glyph_location('α', 2);
+        assert_eq!(total_generated.get(), total_before+1);
+        assert!(cache.get_glyph_atlas().get_modified_blocks().count() > 0);
+    }
+
+    #[test]
+    fn font_size_out_of_bounds_is_rejected() {
+        let total_generated = Rc::new(Cell::new(0));
+        let generator = Box::new(TestGlyphGenerator::new(8.0, total_generated));
+        let mut cache = GlyphCache::new(generator, Vector2::new(256,256), 1, None);
+        assert!(!cache.set_font_size(2.0));
+        assert!(!cache.set_font_size(1024.0));
+        assert_eq!(cache.get_font_size(), 8.0);
+        assert_eq!(cache.get_glyph_atlas().get_glyph_size(), Vector2::new(4,8));
+    }
+
+    #[test]
+    fn atlas_mixed_glyph_spans() {
+        // single block of 16x8 glyphs
+        let mut atlas = GlyphAtlas::new(Vector2::new(4,8), Vector2::new(64,64), 1, None);
+        let total_glyphs_in_block = atlas.get_total_glyphs_in_block();
+        assert_eq!(total_glyphs_in_block, Vector2::new(16,8));
+        for _ in 0..15 {
+            let index = atlas.get_free_index().unwrap();
+            assert_eq!(index.span, 1);
+            assert!(atlas.increment_free_index());
+        }
+        // wide glyph doesn't fit in last position of row and moves to the next row
+        let wide = atlas.get_free_index_with_span(2).unwrap();
+        assert_eq!(wide.position, Vector2::new(0,1));
+        assert_eq!(wide.span, 2);
+        assert!(atlas.increment_free_index_by(2));
+        atlas.write_glyph(wide, &[255u8; 2*4*8]);
+        let single = atlas.get_free_index().unwrap();
+        assert_eq!(single.position, Vector2::new(2,1));
+        // wide glyph occupies two consecutive positions
+        assert_eq!(wide.get_atlas_position(total_glyphs_in_block, 0), Vector2::new(0,1));
+        assert_eq!(wide.get_atlas_position(total_glyphs_in_block, 1), Vector2::new(1,1));
+        assert_eq!(single.get_atlas_position(total_glyphs_in_block, 0), Vector2::new(2,1));
+        // glyph wider than block is rejected
+        assert_eq!(atlas.get_free_index_with_span(17), None);
+    }
+
+    #[test]
+    fn atlas_memory_usage() {
+        // single block of 16x8 glyphs
+        let mut atlas = GlyphAtlas::new(Vector2::new(4,8), Vector2::new(64,64), 1, None);
+        assert_eq!(atlas.get_memory_usage_bytes(), 64*64);
+        assert_eq!(atlas.get_block_utilization(), 0.0);
+        assert!(atlas.increment_free_index_by(32));
+        assert_eq!(atlas.get_block_utilization(), 0.25);
+        assert!(atlas.increment_free_index_by(96));
+        assert_eq!(atlas.get_block_utilization(), 1.0);
+        assert_eq!(atlas.get_free_index(), None);
+    }
+
+    #[test]
+    fn cache_memory_limit_evicts() {
+        let total_generated = Rc::new(Cell::new(0));
+        let generator = Box::new(TestGlyphGenerator::new(8.0, total_generated));
+        // 4x8 glyphs in blocks of 16x8 glyphs take up 4096 bytes per block
+        let max_memory_bytes = 2*4096;
+        let mut cache = GlyphCache::new(generator, Vector2::new(256,256), 1, Some(max_memory_bytes));
+        // ascii glyphs occupy the first block
+        assert_eq!(cache.atlas_utilization(), 0.5);
+        let characters: Vec<char> = (0..128u32).map(|i| char::from_u32(0x100 + i).unwrap()).collect();
+        let first = cache.get_glyph_location(characters[0], 0);
+        for (i, &c) in characters.iter().enumerate().skip(1) {
+            let _ = cache.get_glyph_location(c, i);
+        }
+        assert_eq!(cache.atlas_utilization(), 1.0);
+        assert_eq!(cache.atlas_memory_bytes(), max_memory_bytes);
+        // oldest glyph is evicted instead of growing the atlas
+        let evicted = cache.get_glyph_location('\u{200}', 128);
+        assert_eq!(evicted, first);
+        assert_eq!(cache.atlas_memory_bytes(), max_memory_bytes);
+    }
+
+    #[test]
+    fn atlas_overflows_into_layers() {
+        // single block of 16x8 glyphs per layer
+        let mut atlas = GlyphAtlas::new(Vector2::new(4,8), Vector2::new(64,64), 2, None);
+        assert_eq!(atlas.get_total_layers(), 1);
+        assert!(atlas.increment_free_index_by(128));
+        let index = atlas.get_free_index().unwrap();
+        assert_eq!(index.layer, 1);
+        assert_eq!(index.block, Vector2::new(0,0));
+        assert_eq!(index.position, Vector2::new(0,0));
+        assert_eq!(atlas.get_total_layers(), 2);
+        assert_eq!(atlas.get_memory_usage_bytes(), 2*64*64);
+        atlas.clear_modified_count();
+        atlas.write_glyph(index, &[255u8; 4*8]);
+        assert_eq!(atlas.get_modified_blocks().collect::<Vec<_>>(), vec![(1, Vector2::new(0,0))]);
+        assert_eq!(atlas.get_block(1, Vector2::new(0,0))[0], 255);
+        assert_eq!(atlas.get_block(0, Vector2::new(0,0))[0], 0);
+        assert_eq!(atlas.get_block_utilization(), 0.5);
+        // no more layers available
+        assert!(atlas.increment_free_index_by(128));
+        assert_eq!(atlas.get_free_index(), None);
+    }
+
+    #[test]
+    fn cache_evicts_across_layers() {
+        let total_generated = Rc::new(Cell::new(0));
+        let generator = Box::new(TestGlyphGenerator::new(8.0, total_generated));
+        // 4x8 glyphs fill a 64x64 layer with a single block
+        let mut cache = GlyphCache::new(generator, Vector2::new(64,64), 2, None);
+        let characters: Vec<char> = (0..128u32).map(|i| char::from_u32(0x100 + i).unwrap()).collect();
+        let first = cache.get_glyph_location(characters[0], 0);
+        assert_eq!(first.layer, 1);
+        for (i, &c) in characters.iter().enumerate().skip(1) {
+            let _ = cache.get_glyph_location(c, i);
+        }
+        assert_eq!(cache.atlas_utilization(), 1.0);
+        let evicted = cache.get_glyph_location('\u{200}', 128);
+        assert_eq!(evicted, first);
+    }
+
+    #[test]
+    fn atlas_position_across_blocks() {
+        let index = GlyphIndex { layer: 0, block: Vector2::new(1,2), position: Vector2::new(3,4), span: 2 };
+        let total_glyphs_in_block = Vector2::new(16,8);
+        assert_eq!(index.get_atlas_position(total_glyphs_in_block, 0), Vector2::new(19,20));
+        assert_eq!(index.get_atlas_position(total_glyphs_in_block, 1), Vector2::new(20,20));
+    }
+
+    #[test]
+    fn cache_allocates_wide_glyphs() {
+        let total_generated = Rc::new(Cell::new(0));
+        let generator = Box::new(TestGlyphGenerator::new(8.0, total_generated));
+        let mut cache = GlyphCache::new(generator, Vector2::new(256,256), 1, None);
+        let narrow = cache.get_glyph_location('α', 0);
+        let wide = cache.get_glyph_location('漢', 0);
+        assert_eq!(narrow.span, 1);
+        assert_eq!(wide.span, 2);
+        assert_eq!(cache.get_glyph_location('漢', 1), wide);
+        let total_glyphs_in_block = cache.get_glyph_atlas().get_total_glyphs_in_block();
+        assert!(wide.position.x + wide.span <= total_glyphs_in_block.x);
+    }
+
+    #[test]
+    fn cell_data_atlas_index_packing() {
+        use crate::CellData;
+        let mut cell = CellData::default();
+        cell.set_atlas_index(Vector2::new(0xFFFF, 0x0FFF), 15);
+        assert_eq!(cell.data, [0xFFFF_FFFF, 0, 0, 0]);
+        assert_eq!(cell.get_atlas_position(), Vector2::new(0xFFFF, 0x0FFF));
+        assert_eq!(cell.get_atlas_layer(), 15);
+        cell.set_atlas_index(Vector2::new(0x1234, 0x567), 8);
+        assert_eq!(cell.data, [0x8567_1234, 0, 0, 0]);
+        assert_eq!(cell.get_atlas_position(), Vector2::new(0x1234, 0x567));
+        assert_eq!(cell.get_atlas_layer(), 8);
+    }
+
+    #[test]
+    fn cell_data_word_layout() {
+        use crate::{CellData, UnderlineStyle};
+        use cgmath::Vector3;
+        let mut cell = CellData::default();
+        cell.set_foreground_colour(Vector3::new(0x12,0x34,0x56));
+        cell.set_background_colour(Vector3::new(0x9A,0xBC,0xDE));
+        cell.set_underline(UnderlineStyle::Dashed, Some(Vector3::new(0x11,0x22,0x33)));
+        cell.set_inverse(true);
+        assert_eq!(cell.data, [0, 0x0156_3412, 0x05DE_BC9A, 0x0133_2211]);
+        cell.set_hidden(true);
+        cell.set_inverse(false);
+        assert_eq!(cell.data[1], 0x0256_3412);
+        cell.set_cursor(true);
+        assert_eq!(cell.data[1], 0x0656_3412);
+        cell.set_cursor(false);
+        cell.set_underline(UnderlineStyle::None, None);
+        assert_eq!(cell.data, [0, 0x0256_3412, 0x00DE_BC9A, 0]);
+    }
+
+    #[test]
+    fn cell_data_fields_are_independent() {
+        use crate::{CellData, UnderlineStyle};
+        use cgmath::Vector3;
+        let styles = [
+            UnderlineStyle::None, UnderlineStyle::Straight, UnderlineStyle::Double,
+            UnderlineStyle::Curly, UnderlineStyle::Dotted, UnderlineStyle::Dashed,
+        ];
+        let colours = [Vector3::new(0,0,0), Vector3::new(255,255,255), Vector3::new(0x12,0x34,0x56)];
+        let underline_colours = [None, Some(Vector3::new(0,0,0)), Some(Vector3::new(255,255,255))];
+        let atlas_indices = [(Vector2::new(0,0), 0), (Vector2::new(0xFFFF,0x0FFF), 15), (Vector2::new(7,9), 3)];
+        let flags = [(false,false,false), (true,false,true), (false,true,false), (true,true,true), (false,false,true)];
+        for (position, layer) in atlas_indices {
+            for foreground in colours {
+                for background in colours {
+                    for style in styles {
+                        for underline_colour in underline_colours {
+                            for (is_inverse, is_hidden, is_cursor) in flags {
+                                // start from every bit set so stale bits would be caught
+                                let mut cell = CellData { data: [u32::MAX; 4] };
+                                cell.set_atlas_index(position, layer);
+                                cell.set_foreground_colour(foreground);
+                                cell.set_background_colour(background);
+                                cell.set_underline(style, underline_colour);
+                                cell.set_inverse(is_inverse);
+                                cell.set_hidden(is_hidden);
+                                cell.set_cursor(is_cursor);
+                                assert_eq!(cell.get_atlas_position(), position);
+                                assert_eq!(cell.get_atlas_layer(), layer);
+                                assert_eq!(cell.get_foreground_colour(), foreground);
+                                assert_eq!(cell.get_background_colour(), background);
+                                assert_eq!(cell.get_underline_style(), style);
+                                assert_eq!(cell.get_underline_colour(), underline_colour);
+                                assert_eq!(cell.is_inverse(), is_inverse);
+                                assert_eq!(cell.is_hidden(), is_hidden);
+                                assert_eq!(cell.is_cursor(), is_cursor);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cell_data_shader_constants() {
+        use crate::cell_data::get_wgsl_constants;
+        let constants = get_wgsl_constants();
+        assert!(constants.contains("const CELL_ATLAS_LAYER_SHIFT: u32 = 0x0000001Cu;\n"));
+        assert!(constants.contains("const CELL_INVERSE_FLAG: u32 = 0x01000000u;\n"));
+        // every constant is used by the shader so the layout can't drift
+        let shader = include_str!("shader.wgsl");
+        for line in constants.lines() {
+            let name = line.trim_start_matches("const ").split(':').next().unwrap();
+            assert!(shader.contains(name), "{} is unused in shader.wgsl", name);
+        }
+    }
+
+    #[test]
+    fn cursor_uniform_contents() {
+        use crate::renderer::GlobalParameters;
+        use crate::{CursorData, CursorStyle};
+        use cgmath::{Vector3, Vector4};
+        let mut params = GlobalParameters::default();
+        assert_eq!(params.is_cursor_visible, 0);
+        params.set_cursor(Some(CursorData {
+            style: CursorStyle::Bar,
+            colour: Vector3::new(255,0,51),
+        }));
+        assert_eq!(params.is_cursor_visible, 1);
+        assert_eq!(params.cursor_style, 2);
+        assert_eq!(params.cursor_colour, Vector4::new(1.0,0.0,0.2,1.0));
+        // uniform layout must match shader with 16 byte alignment
+        assert_eq!(std::mem::size_of::<GlobalParameters>(), 144);
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[80..84], &2u32.to_ne_bytes());
+        assert_eq!(&bytes[84..88], &1u32.to_ne_bytes());
+        params.set_cursor(None);
+        assert_eq!(params.is_cursor_visible, 0);
+    }
+
+    #[test]
+    fn focus_uniform_contents() {
+        use crate::renderer::GlobalParameters;
+        let mut params = GlobalParameters::default();
+        // focused with no dimming by default
+        assert_eq!(params.is_focused, 1);
+        assert_eq!(params.unfocused_dim, 1.0);
+        params.set_focus(false, 0.85);
+        assert_eq!(params.is_focused, 0);
+        assert_eq!(params.unfocused_dim, 0.85);
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[92..96], &0u32.to_ne_bytes());
+        assert_eq!(&bytes[96..100], &0.85f32.to_ne_bytes());
+        // dim factor is clamped so colours can't be brightened
+        params.set_focus(true, 1.5);
+        assert_eq!(params.is_focused, 1);
+        assert_eq!(params.unfocused_dim, 1.0);
+        params.set_focus(false, -1.0);
+        assert_eq!(params.unfocused_dim, 0.0);
+        assert_eq!(std::mem::size_of::<GlobalParameters>(), 144);
+    }
+
+    #[test]
+    fn font_scale_uniform_contents() {
+        use crate::renderer::GlobalParameters;
+        let mut params = GlobalParameters::default();
+        assert_eq!(params.font_scale, 1.0);
+        params.set_font_scale(1.05);
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[100..104], &1.05f32.to_ne_bytes());
+        // invalid scales fall back to unscaled glyphs
+        params.set_font_scale(0.0);
+        assert_eq!(params.font_scale, 1.0);
+        params.set_font_scale(f32::NAN);
+        assert_eq!(params.font_scale, 1.0);
+    }
+
+    #[test]
+    fn hover_uniform_contents() {
+        use crate::renderer::GlobalParameters;
+        use cgmath::Vector2;
+        let mut params = GlobalParameters::default();
+        assert_eq!(params.is_hover_visible, 0);
+        params.set_hover(Some((Vector2::new(4,1), Vector2::new(2,3))));
+        assert_eq!(params.is_hover_visible, 1);
+        // vec2 fields are 8 byte aligned in the shader
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[108..112], &1u32.to_ne_bytes());
+        assert_eq!(&bytes[112..116], &4u32.to_ne_bytes());
+        assert_eq!(&bytes[116..120], &1u32.to_ne_bytes());
+        assert_eq!(&bytes[120..124], &2u32.to_ne_bytes());
+        assert_eq!(&bytes[124..128], &3u32.to_ne_bytes());
+        params.set_hover(None);
+        assert_eq!(params.is_hover_visible, 0);
+        assert_eq!(std::mem::size_of::<GlobalParameters>(), 144);
+    }
+
+    #[test]
+    fn background_opacity_uniform_contents() {
+        use crate::renderer::{GlobalParameters, apply_clear_opacity};
+        let mut params = GlobalParameters::default();
+        assert_eq!(params.background_opacity, 1.0);
+        params.set_background_opacity(0.75, true);
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[128..132], &0.75f32.to_ne_bytes());
+        assert_eq!(&bytes[132..136], &1u32.to_ne_bytes());
+        params.set_background_opacity(2.0, false);
+        assert_eq!(params.background_opacity, 1.0);
+        assert_eq!(params.is_alpha_premultiplied, 0);
+        params.set_background_opacity(f32::NAN, false);
+        assert_eq!(params.background_opacity, 1.0);
+        // clear colour is only scaled by its alpha for premultiplied surfaces
+        let colour = wgpu::Color { r: 1.0, g: 0.5, b: 0.0, a: 1.0 };
+        assert_eq!(apply_clear_opacity(colour, 0.5, false), wgpu::Color { r: 1.0, g: 0.5, b: 0.0, a: 0.5 });
+        assert_eq!(apply_clear_opacity(colour, 0.5, true), wgpu::Color { r: 0.5, g: 0.25, b: 0.0, a: 0.5 });
+    }
+
+    #[test]
+    fn image_uniform_contents() {
+        use crate::image_layer::ImageParameters;
+        use crate::ImageQuad;
+        use cgmath::Vector2;
+        let quad = ImageQuad { id: 3, position: Vector2::new(2,-1), size: Vector2::new(4,3) };
+        let params = ImageParameters::from_quad(&quad);
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[0..4], &2.0f32.to_ne_bytes());
+        assert_eq!(&bytes[4..8], &(-1.0f32).to_ne_bytes());
+        assert_eq!(&bytes[8..12], &4.0f32.to_ne_bytes());
+        assert_eq!(&bytes[12..16], &3.0f32.to_ne_bytes());
+        assert_eq!(std::mem::size_of::<ImageParameters>(), 16);
+    }
+
+    #[test]
+    fn bell_flash_uniform_contents() {
+        use crate::renderer::GlobalParameters;
+        let mut params = GlobalParameters::default();
+        assert_eq!(params.bell_flash, 0.0);
+        params.set_bell_flash(0.5);
+        let bytes: &[u8] = bytemuck::bytes_of(&params);
+        assert_eq!(&bytes[112..116], &0.5f32.to_ne_bytes());
+        // intensity is clamped to a full inversion
+        params.set_bell_flash(2.0);
+        assert_eq!(params.bell_flash, 1.0);
+        params.set_bell_flash(-1.0);
+        assert_eq!(params.bell_flash, 0.0);
+    }
+
+    #[test]
+    fn clear_colour_conversion() {
+        use crate::renderer::convert_clear_colour;
+        use cgmath::Vector3;
+        let colour = Vector3::new(255,128,0);
+        // written as is when the shader encodes to srgb
+        let c = convert_clear_colour(wgpu::TextureFormat::Bgra8Unorm, colour);
+        assert_eq!((c.r, c.g, c.b, c.a), (1.0, 128.0/255.0, 0.0, 1.0));
+        // linear for formats that are encoded by hardware or stored as linear
+        for format in [wgpu::TextureFormat::Bgra8UnormSrgb, wgpu::TextureFormat::Rgba16Float] {
+            let c = convert_clear_colour(format, colour);
+            assert_eq!(c.r, 1.0);
+            assert!((c.g - 0.2158).abs() < 1e-3);
+            assert_eq!(c.b, 0.0);
+        }
+    }
+
+    #[test]
+    fn readback_texel_conversion() {
+        use crate::renderer::convert_texel_to_rgba8;
+        use wgpu::TextureFormat;
+        assert_eq!(convert_texel_to_rgba8(TextureFormat::Rgba8Unorm, &[1,2,3,4]), [1,2,3,4]);
+        assert_eq!(convert_texel_to_rgba8(TextureFormat::Bgra8Unorm, &[1,2,3,4]), [3,2,1,4]);
+        // r=1023, g=0, b=512, a=3
+        let v: u32 = 1023 | (512 << 20) | (3 << 30);
+        assert_eq!(convert_texel_to_rgba8(TextureFormat::Rgb10a2Unorm, &v.to_le_bytes()), [255,0,128,255]);
+        // 1.0, 0.0, 0.5, 2.0 in half precision
+        let halfs: [u16; 4] = [0x3C00, 0x0000, 0x3800, 0x4000];
+        let texel: Vec<u8> = halfs.iter().flat_map(|h| h.to_le_bytes()).collect();
+        // float formats are linear and get encoded to srgb
+        assert_eq!(convert_texel_to_rgba8(TextureFormat::Rgba16Float, &texel), [255,0,188,255]);
+    }
+
+    #[test]
+    fn srgb_linear_conversion() {
+        use crate::{convert_srgb_to_linear, convert_linear_to_srgb, is_srgb_encode_required};
+        use wgpu::TextureFormat;
+        let assert_close = |a: f32, b: f32| assert!((a-b).abs() < 1e-4, "{} != {}", a, b);
+        // known pairs on both sides of the linear segment
+        let pairs: [(f32, f32); 5] = [(0.0, 0.0), (0.04, 0.0030960), (0.5, 0.2140411), (128.0/255.0, 0.2158605), (1.0, 1.0)];
+        for (srgb, linear) in pairs {
+            assert_close(convert_srgb_to_linear(srgb), linear);
+            assert_close(convert_linear_to_srgb(linear), srgb);
+        }
+        for i in 0..=255 {
+            let v = i as f32 / 255.0;
+            assert_close(convert_linear_to_srgb(convert_srgb_to_linear(v)), v);
+        }
+        assert!(is_srgb_encode_required(TextureFormat::Bgra8Unorm));
+        assert!(is_srgb_encode_required(TextureFormat::Rgb10a2Unorm));
+        assert!(!is_srgb_encode_required(TextureFormat::Bgra8UnormSrgb));
+        assert!(!is_srgb_encode_required(TextureFormat::Rgba16Float));
+    }
+
+    #[test]
+    fn render_stats_accumulation() {
+        use crate::{FrameTimings, RenderStats};
+        use std::time::Duration;
+        let mut stats = RenderStats::default();
+        assert_eq!(stats.get_average_timings(), None);
+        assert_eq!(stats.get_average_gpu_render(), None);
+        assert_eq!(format!("{}", stats), "frames=0");
+        stats.push_frame(FrameTimings {
+            grid_update: Duration::from_millis(2),
+            atlas_upload: Duration::from_millis(1),
+            queue_submit: Duration::from_micros(500),
+        });
+        stats.push_frame(FrameTimings {
+            grid_update: Duration::from_millis(4),
+            atlas_upload: Duration::from_millis(0),
+            queue_submit: Duration::from_micros(1500),
+        });
+        assert_eq!(stats.get_total_frames(), 2);
+        assert_eq!(stats.get_average_timings(), Some(FrameTimings {
+            grid_update: Duration::from_millis(3),
+            atlas_upload: Duration::from_micros(500),
+            queue_submit: Duration::from_millis(1),
+        }));
+        // gpu timings are optional
+        assert_eq!(stats.get_average_gpu_render(), None);
+        assert_eq!(format!("{}", stats), "frames=2 grid=3.00ms atlas=0.50ms submit=1.00ms gpu=n/a");
+        stats.push_gpu_render(Duration::from_micros(250));
+        assert_eq!(stats.get_average_gpu_render(), Some(Duration::from_micros(250)));
+        assert_eq!(format!("{}", stats), "frames=2 grid=3.00ms atlas=0.50ms submit=1.00ms gpu=0.25ms");
+        stats.reset();
+        assert_eq!(stats.get_total_frames(), 0);
+        assert_eq!(stats.get_average_gpu_render(), None);
+    }
+
+    #[test]
+    fn scrollbar_thumb_geometry() {
+        use crate::{calculate_scrollbar_thumb, ScrollbarThumb};
+        // nothing to scroll
+        assert_eq!(calculate_scrollbar_thumb(0, 10, 10, 0.0), None);
+        assert_eq!(calculate_scrollbar_thumb(0, 5, 10, 0.0), None);
+        // thumb size is proportional to visible lines
+        assert_eq!(calculate_scrollbar_thumb(0, 40, 10, 0.0), Some(ScrollbarThumb { start: 0.0, end: 0.25 }));
+        assert_eq!(calculate_scrollbar_thumb(30, 40, 10, 0.0), Some(ScrollbarThumb { start: 0.75, end: 1.0 }));
+        assert_eq!(calculate_scrollbar_thumb(15, 40, 10, 0.0), Some(ScrollbarThumb { start: 0.375, end: 0.625 }));
+        // offset past bottom is clamped
+        assert_eq!(calculate_scrollbar_thumb(100, 40, 10, 0.0), Some(ScrollbarThumb { start: 0.75, end: 1.0 }));
+        // minimum thumb length for long scrollback
+        let thumb = calculate_scrollbar_thumb(0, 100_000, 10, 0.05).unwrap();
+        assert_eq!(thumb, ScrollbarThumb { start: 0.0, end: 0.05 });
+        let thumb = calculate_scrollbar_thumb(99_990, 100_000, 10, 0.05).unwrap();
+        assert!((thumb.end - 1.0).abs() < 1e-6);
+    }
+
+    fn render_box_drawing(c: char, width: usize, height: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        assert!(crate::box_drawing::generate_box_drawing_glyph(c, Vector2::new(width, height), &mut data));
+        assert_eq!(data.len(), width*height);
+        data
+    }
+
+    // converts bitmap to rows of '#' for solid and '.' for empty pixels
+    fn box_drawing_to_ascii(data: &[u8], width: usize) -> Vec<String> {
+        data.chunks(width)
+            .map(|row| row.iter().map(|&v| match v { 0 => '.', 255 => '#', _ => '?' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn box_drawing_lines() {
+        let data = render_box_drawing('─', 8, 16);
+        for (y, row) in box_drawing_to_ascii(&data, 8).iter().enumerate() {
+            assert_eq!(row, if y == 7 { "########" } else { "........" });
+        }
+        let data = render_box_drawing('━', 8, 16);
+        for (y, row) in box_drawing_to_ascii(&data, 8).iter().enumerate() {
+            assert_eq!(row, if y == 7 || y == 8 { "########" } else { "........" });
+        }
+        let data = render_box_drawing('┼', 8, 16);
+        for (y, row) in box_drawing_to_ascii(&data, 8).iter().enumerate() {
+            assert_eq!(row, if y == 7 { "########" } else { "...#...." });
+        }
+    }
+
+    #[test]
+    fn box_drawing_double_corner() {
+        let data = render_box_drawing('╔', 9, 9);
+        let expected = [
+            ".........",
+            ".........",
+            ".........",
+            "...######",
+            "...#.....",
+            "...#.####",
+            "...#.#...",
+            "...#.#...",
+            "...#.#...",
+        ];
+        assert_eq!(box_drawing_to_ascii(&data, 9), expected);
+    }
+
+    #[test]
+    fn box_drawing_block_elements() {
+        let data = render_box_drawing('▄', 8, 16);
+        for (y, row) in data.chunks(8).enumerate() {
+            let expected = if y >= 8 { 255 } else { 0 };
+            assert!(row.iter().all(|&v| v == expected));
+        }
+        // partial coverage for edges that land between pixels
+        let data = render_box_drawing('▁', 8, 12);
+        for (y, row) in data.chunks(8).enumerate() {
+            let expected = match y { 11 => 255, 10 => 128, _ => 0 };
+            assert!(row.iter().all(|&v| v == expected));
+        }
+        let data = render_box_drawing('░', 8, 16);
+        assert!(data.iter().all(|&v| v == 64));
+        let data = render_box_drawing('█', 7, 13);
+        assert!(data.iter().all(|&v| v == 255));
+        // quadrants on odd sizes sum up to a solid fill
+        let data = render_box_drawing('▚', 8, 16);
+        for (y, row) in box_drawing_to_ascii(&data, 8).iter().enumerate() {
+            assert_eq!(row, if y < 8 { "####...." } else { "....####" });
+        }
+        let data = render_box_drawing('▛', 7, 13);
+        assert_eq!(data[3*7 + 3], 255);
+    }
+
+    #[test]
+    fn box_drawing_all_characters() {
+        for c in '\u{2500}'..='\u{259F}' {
+            assert!(crate::is_box_drawing_character(c));
+            for size in [Vector2::new(1,1), Vector2::new(5,11), Vector2::new(8,16)] {
+                let data = render_box_drawing(c, size.x, size.y);
+                if size.x > 1 {
+                    assert!(data.iter().any(|&v| v > 0), "'{}' is empty at {:?}", c, size);
+                }
+            }
+        }
+        assert!(!crate::is_box_drawing_character('a'));
+        assert!(!crate::is_box_drawing_character('\u{25A0}'));
+    }
+
+    #[test]
+    fn cache_builtin_box_drawing() {
+        let total_generated = Rc::new(Cell::new(0));
+        let generator = Box::new(TestGlyphGenerator::new(8.0, total_generated.clone()));
+        let mut cache = GlyphCache::new(generator, Vector2::new(256,256), 1, None);
+        assert!(cache.get_builtin_box_drawing());
+        let total_before = total_generated.get();
+        let _ = cache.get_glyph_location('─', 0);
+        assert_eq!(total_generated.get(), total_before);
+        // font glyph is used when builtin is disabled
+        cache.set_builtin_box_drawing(false);
+        assert!(!cache.get_builtin_box_drawing());
+        let total_before = total_generated.get();
+        let _ = cache.get_glyph_location('─', 1);
+        assert_eq!(total_generated.get(), total_before+1);
+    }
+
+    #[test]
+    fn lru_list_peek() {
+        use crate::LruList;
+        let mut list = LruList::<u32>::default();
+        assert_eq!(list.peek_front(), None);
+        assert_eq!(list.peek_back(), None);
+        // single element is both ends
+        let a = list.push(&1);
+        assert_eq!(list.peek_front(), Some(&1));
+        assert_eq!(list.peek_back(), Some(&1));
+        assert!(!list.promote(a));
+        assert_eq!(list.peek_front(), Some(&1));
+        assert_eq!(list.peek_back(), Some(&1));
+        let _b = list.push(&2);
+        let c = list.push(&3);
+        assert_eq!(list.peek_front(), Some(&3));
+        assert_eq!(list.peek_back(), Some(&1));
+        // promoting the oldest moves it to the front
+        assert!(list.promote(a));
+        assert_eq!(list.peek_front(), Some(&1));
+        assert_eq!(list.peek_back(), Some(&2));
+        assert!(list.promote(c));
+        assert_eq!(list.peek_front(), Some(&3));
+        assert_eq!(list.peek_back(), Some(&2));
+    }
+}
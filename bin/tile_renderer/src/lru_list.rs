@@ -68,6 +68,16 @@ impl<T> LruList<T> {
         self.tail_index
     }
 
+    // Most recently used
+    pub fn peek_front(&self) -> Option<&T> {
+        self.head_index.map(|index| &self.nodes[index].data)
+    }
+
+    // Least recently used
+    pub fn peek_back(&self) -> Option<&T> {
+        self.tail_index.map(|index| &self.nodes[index].data)
+    }
+
     pub fn push(&mut self, data: &T) -> usize 
     where T: Copy 
     {
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+// CPU side timings of each stage of a single frame
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct FrameTimings {
+    pub grid_update: Duration,
+    pub atlas_upload: Duration,
+    pub queue_submit: Duration,
+}
+
+// Accumulates frame timings over a reporting interval
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct RenderStats {
+    total_frames: u32,
+    total_timings: FrameTimings,
+    // gpu timings are read back a few frames late and may be unsupported
+    total_gpu_frames: u32,
+    total_gpu_render: Duration,
+}
+
+impl RenderStats {
+    pub fn push_frame(&mut self, timings: FrameTimings) {
+        self.total_frames += 1;
+        self.total_timings.grid_update += timings.grid_update;
+        self.total_timings.atlas_upload += timings.atlas_upload;
+        self.total_timings.queue_submit += timings.queue_submit;
+    }
+
+    pub fn push_gpu_render(&mut self, duration: Duration) {
+        self.total_gpu_frames += 1;
+        self.total_gpu_render += duration;
+    }
+
+    pub fn get_total_frames(&self) -> u32 {
+        self.total_frames
+    }
+
+    pub fn get_average_timings(&self) -> Option<FrameTimings> {
+        if self.total_frames == 0 {
+            return None;
+        }
+        Some(FrameTimings {
+            grid_update: self.total_timings.grid_update / self.total_frames,
+            atlas_upload: self.total_timings.atlas_upload / self.total_frames,
+            queue_submit: self.total_timings.queue_submit / self.total_frames,
+        })
+    }
+
+    pub fn get_average_gpu_render(&self) -> Option<Duration> {
+        if self.total_gpu_frames == 0 {
+            return None;
+        }
+        Some(self.total_gpu_render / self.total_gpu_frames)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl std::fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to_ms = |duration: Duration| duration.as_secs_f64()*1000.0;
+        let Some(timings) = self.get_average_timings() else {
+            return write!(f, "frames=0");
+        };
+        write!(f, "frames={} grid={:.2}ms atlas={:.2}ms submit={:.2}ms",
+            self.total_frames, to_ms(timings.grid_update), to_ms(timings.atlas_upload), to_ms(timings.queue_submit),
+        )?;
+        match self.get_average_gpu_render() {
+            Some(gpu_render) => write!(f, " gpu={:.2}ms", to_ms(gpu_render)),
+            None => write!(f, " gpu=n/a"),
+        }
+    }
+}
@@ -1,30 +1,100 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 use bytemuck::{Pod, Zeroable};
-use cgmath::{Vector2, Vector4};
+use cgmath::{Vector2, Vector3, Vector4};
 use wgpu::util::DeviceExt;
 use crate::glyph_atlas::GlyphAtlas;
+use crate::cell_data::{CellData, get_wgsl_constants};
+use crate::colour_space::{convert_linear_to_srgb, convert_srgb_to_linear, is_srgb_encode_required};
+use crate::image_layer::{ImageLayer, ImageQuad, create_image_bind_group_layout};
 
-#[repr(C)]
-#[derive(Clone,Copy,Debug,Pod,Zeroable)]
-pub struct CellData {
-    pub atlas_index: Vector2<u16>,
-    pub colour_foreground: Vector4<u8>,
-    pub colour_background: Vector4<u8>,
-    pub style_flags: u32,
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum CursorStyle {
+    #[default]
+    Block,
+    Underline,
+    Bar,
 }
 
-impl Default for CellData {
-    fn default() -> Self {
-        Self::zeroed()
-    }
+// Cell under the cursor is marked in the grid with CellData::set_cursor()
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct CursorData {
+    pub style: CursorStyle,
+    pub colour: Vector3<u8>,
 }
 
 #[repr(C)]
 #[derive(Clone,Copy,Debug,Pod,Zeroable)]
-struct GlobalParameters {
-    render_scale: Vector2<f32>,
-    grid_size: Vector2<u32>,
-    atlas_size: Vector2<u32>,
+pub(crate) struct GlobalParameters {
+    pub(crate) render_scale: Vector2<f32>,
+    pub(crate) grid_size: Vector2<u32>,
+    pub(crate) atlas_size: Vector2<u32>,
+    pub(crate) scroll_offset: Vector2<f32>,
+    pub(crate) scrollbar_rect: Vector4<f32>,
+    pub(crate) scrollbar_colour: Vector4<f32>,
+    pub(crate) cursor_colour: Vector4<f32>,
+    pub(crate) cursor_style: u32,
+    pub(crate) is_cursor_visible: u32,
+    pub(crate) is_srgb_encode_required: u32,
+    pub(crate) is_focused: u32,
+    pub(crate) unfocused_dim: f32,
+    pub(crate) font_scale: f32,
+    // colours are inverted by this amount for the visual bell
+    pub(crate) bell_flash: f32,
+    pub(crate) is_hover_visible: u32,
+    // inclusive range of cells in reading order that are underlined while the mouse is over a hyperlink
+    pub(crate) hover_start: Vector2<u32>,
+    pub(crate) hover_end: Vector2<u32>,
+    // alpha of cell backgrounds which is multiplied into the colour when the compositor expects it
+    pub(crate) background_opacity: f32,
+    pub(crate) is_alpha_premultiplied: u32,
+    // uniform buffers are a multiple of 16 bytes
+    pub(crate) _padding: [u32; 2],
+}
+
+impl GlobalParameters {
+    pub(crate) fn set_cursor(&mut self, cursor: Option<CursorData>) {
+        let Some(cursor) = cursor else {
+            self.is_cursor_visible = 0;
+            return;
+        };
+        self.is_cursor_visible = 1;
+        self.cursor_style = match cursor.style {
+            CursorStyle::Block => 0,
+            CursorStyle::Underline => 1,
+            CursorStyle::Bar => 2,
+        };
+        let colour = cursor.colour.cast::<f32>().unwrap() / 255.0;
+        self.cursor_colour = Vector4::new(colour.x, colour.y, colour.z, 1.0);
+    }
+
+    pub(crate) fn set_focus(&mut self, is_focused: bool, unfocused_dim: f32) {
+        self.is_focused = is_focused as u32;
+        self.unfocused_dim = unfocused_dim.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn set_bell_flash(&mut self, intensity: f32) {
+        self.bell_flash = intensity.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn set_font_scale(&mut self, font_scale: f32) {
+        self.font_scale = if font_scale.is_finite() && font_scale > 0.0 { font_scale } else { 1.0 };
+    }
+
+    pub(crate) fn set_hover(&mut self, hover: Option<(Vector2<usize>, Vector2<usize>)>) {
+        let Some((start, end)) = hover else {
+            self.is_hover_visible = 0;
+            return;
+        };
+        self.is_hover_visible = 1;
+        self.hover_start = start.cast::<u32>().unwrap();
+        self.hover_end = end.cast::<u32>().unwrap();
+    }
+
+    pub(crate) fn set_background_opacity(&mut self, opacity: f32, is_alpha_premultiplied: bool) {
+        self.background_opacity = if opacity.is_finite() { opacity.clamp(0.0, 1.0) } else { 1.0 };
+        self.is_alpha_premultiplied = is_alpha_premultiplied as u32;
+    }
 }
 
 impl Default for GlobalParameters {
@@ -33,12 +103,125 @@ impl Default for GlobalParameters {
             render_scale: Vector2::new(1.0,1.0),
             grid_size: Vector2::new(1,1),
             atlas_size: Vector2::new(1,1),
+            scroll_offset: Vector2::new(0.0,0.0),
+            scrollbar_rect: Vector4::new(0.0,0.0,0.0,0.0),
+            scrollbar_colour: Vector4::new(0.0,0.0,0.0,0.0),
+            cursor_colour: Vector4::new(1.0,1.0,1.0,1.0),
+            cursor_style: 0,
+            is_cursor_visible: 0,
+            is_srgb_encode_required: 0,
+            is_focused: 1,
+            unfocused_dim: 1.0,
+            font_scale: 1.0,
+            bell_flash: 0.0,
+            is_hover_visible: 0,
+            hover_start: Vector2::new(0,0),
+            hover_end: Vector2::new(0,0),
+            background_opacity: 1.0,
+            is_alpha_premultiplied: 0,
+            _padding: [0; 2],
         }
     }
 }
 
 type Vertex = Vector2<f32>;
 
+const TOTAL_TIMESTAMPS: u32 = 2;
+const TIMESTAMP_BUFFER_SIZE: u64 = (TOTAL_TIMESTAMPS as u64)*wgpu::QUERY_SIZE as u64;
+
+// Measures duration of the render pass using timestamp queries
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // nanoseconds per timestamp tick
+    timestamp_period: f32,
+    is_written: bool,
+    // readback buffer cannot be written to until the pending map has completed
+    pending_map: Option<std::sync::mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu_timer_query_set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: TOTAL_TIMESTAMPS,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_timer_resolve_buffer"),
+            size: TIMESTAMP_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu_timer_readback_buffer"),
+            size: TIMESTAMP_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            timestamp_period: queue.get_timestamp_period(),
+            is_written: false,
+            pending_map: None,
+        }
+    }
+}
+
+// Row of cells drawn over the top of the grid with its own parameters so it isn't scrolled with the grid
+struct TabBarLayer {
+    global_parameters: GlobalParameters,
+    global_parameters_uniform: wgpu::Buffer,
+    grid_texture: wgpu::Texture,
+}
+
+fn create_grid_texture(device: &wgpu::Device, size: wgpu::Extent3d) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("grid_texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Uint,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+// Recreates the texture if the grid size has changed
+fn write_grid_texture(
+    device: &wgpu::Device, queue: &wgpu::Queue, texture: &mut wgpu::Texture, cells: &[CellData], size: Vector2<usize>,
+) {
+    assert!(cells.len() == (size.x*size.y));
+    let pixel_size_bytes = std::mem::size_of::<CellData>();
+    assert!(pixel_size_bytes == 16);
+    let extent = wgpu::Extent3d {
+        width: size.x as u32,
+        height: size.y as u32,
+        depth_or_array_layers: 1,
+    };
+    let old_size = Vector2::new(
+        texture.width() as usize, 
+        texture.height() as usize,
+    );
+    if size != old_size {
+        *texture = create_grid_texture(device, extent);
+    }
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(cells),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some((size.x*pixel_size_bytes) as u32),
+            rows_per_image: Some(size.y as u32),
+        },
+        extent,
+    );
+}
+
 pub struct Renderer {
     _shader_module: wgpu::ShaderModule,
     global_parameters: GlobalParameters,
@@ -49,8 +232,13 @@ pub struct Renderer {
     mesh: Mesh,
     bind_group_layout: wgpu::BindGroupLayout,
     render_pipeline: wgpu::RenderPipeline,
-    _surface_texture_format: wgpu::TextureFormat,
+    surface_texture_format: wgpu::TextureFormat,
     clear_colour: wgpu::Color,
+    gpu_timer: Option<GpuTimer>,
+    tab_bar: Option<TabBarLayer>,
+    image_bind_group_layout: wgpu::BindGroupLayout,
+    image_pipeline: wgpu::RenderPipeline,
+    images: HashMap<u64, ImageLayer>,
 }
 
 impl Renderer {
@@ -58,13 +246,27 @@ impl Renderer {
         config: &wgpu::SurfaceConfiguration,
         device: &wgpu::Device,
     ) -> Self {
+        Self::with_preferred_format(device, config.format)
+    }
+
+    pub fn with_preferred_format(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        Self::new_headless(device, format)
+    }
+
+    // Create renderer without a surface for offscreen rendering with render_to_buffer()
+    pub fn new_headless(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
         // global shader parameters
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("render_text_shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(get_wgsl_constants() + include_str!("shader.wgsl"))),
         });
         // global parameters
-        let global_parameters = GlobalParameters::default();
+        let mut global_parameters = GlobalParameters::default();
+        global_parameters.is_srgb_encode_required = is_srgb_encode_required(output_format) as u32;
+        log::info!("Render format {:?} (is_srgb_encode_required={})", output_format, global_parameters.is_srgb_encode_required != 0);
         let global_parameters_uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("global_parameters"),
             contents: bytemuck::cast_slice(&[global_parameters]),
@@ -87,19 +289,10 @@ impl Renderer {
             view_formats: &[],
         });
         // grid texture
-        let grid_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("grid_texture"),
-            size: wgpu::Extent3d {
-                width: 1,
-                height: 1,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba32Uint,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
+        let grid_texture = create_grid_texture(device, wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
         });
         // mesh
         let mesh = Mesh::new(device);
@@ -128,7 +321,7 @@ impl Renderer {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
                         multisampled: false,
                     },
                     count: None,
@@ -146,7 +339,7 @@ impl Renderer {
             ],
         });
         // shader pipeline
-        let surface_texture_format = config.format;
+        let surface_texture_format = output_format;
         let clear_colour = wgpu::Color::BLACK; 
         // render pipeline
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -167,6 +360,40 @@ impl Renderer {
             fragment: Some(wgpu::FragmentState {
                 module: &shader_module,
                 entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: surface_texture_format,
+                        // grid writes its own alpha so transparent backgrounds aren't blended with the clear colour
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        // images are drawn with the grid parameters so they are scaled and scrolled with it
+        let image_bind_group_layout = create_image_bind_group_layout(device);
+        let image_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("image_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout, &image_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let image_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("image_pipeline"),
+            layout: Some(&image_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vs_image",
+                buffers: &[
+                    mesh.get_vertex_buffer_layout(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: "fs_image",
                 targets: &[
                     Some(wgpu::ColorTargetState {
                         format: surface_texture_format,
@@ -191,56 +418,145 @@ impl Renderer {
             mesh,
             bind_group_layout,
             render_pipeline,
-            _surface_texture_format: surface_texture_format,
+            surface_texture_format,
             clear_colour,
+            gpu_timer: None,
+            tab_bar: None,
+            image_bind_group_layout,
+            image_pipeline,
+            images: HashMap::new(),
         }
     }
 
-    pub fn update_grid(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, cells: &[CellData], size: Vector2<usize>) {
-        assert!(cells.len() == (size.x*size.y));
-        let pixel_size_bytes = std::mem::size_of::<CellData>();
-        assert!(pixel_size_bytes == 16);
-        let extent = wgpu::Extent3d {
-            width: size.x as u32,
-            height: size.y as u32,
-            depth_or_array_layers: 1,
+    // Returns false if the device doesn't support timestamp queries
+    pub fn enable_gpu_timing(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return false;
+        }
+        if self.gpu_timer.is_none() {
+            self.gpu_timer = Some(GpuTimer::new(device, queue));
+        }
+        true
+    }
+
+    // Call after the commands from generate_commands() have been submitted to the queue
+    pub fn request_gpu_time(&mut self) {
+        let Some(timer) = self.gpu_timer.as_mut() else {
+            return;
         };
-        let old_size = Vector2::new(
-            self.grid_texture.width() as usize, 
-            self.grid_texture.height() as usize,
-        );
-        if size != old_size {
-            self.grid_texture = device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("grid_texture"),
-                size: extent,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba32Uint,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
+        if !timer.is_written || timer.pending_map.is_some() {
+            return;
         }
-        queue.write_texture(
-            self.grid_texture.as_image_copy(),
-            bytemuck::cast_slice(cells),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some((size.x*pixel_size_bytes) as u32),
-                rows_per_image: Some(size.y as u32),
-            },
-            extent,
-        );
+        timer.is_written = false;
+        let (tx, rx) = std::sync::mpsc::channel();
+        timer.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        timer.pending_map = Some(rx);
+    }
+
+    // Duration of a previous render pass once its timestamps have been read back
+    pub fn poll_gpu_time(&mut self, device: &wgpu::Device) -> Option<std::time::Duration> {
+        let timer = self.gpu_timer.as_mut()?;
+        let rx = timer.pending_map.as_ref()?;
+        let _ = device.poll(wgpu::Maintain::Poll);
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(std::sync::mpsc::TryRecvError::Empty) => return None,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err(wgpu::BufferAsyncError),
+        };
+        timer.pending_map = None;
+        if let Err(err) = result {
+            log::warn!("Failed to map gpu timer readback buffer: {:?}", err);
+            return None;
+        }
+        let ticks = {
+            let data = timer.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            timestamps[1].saturating_sub(timestamps[0])
+        };
+        timer.readback_buffer.unmap();
+        let nanoseconds = (ticks as f64)*(timer.timestamp_period as f64);
+        Some(std::time::Duration::from_nanos(nanoseconds as u64))
+    }
+
+    pub fn update_grid(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, cells: &[CellData], size: Vector2<usize>) {
+        write_grid_texture(device, queue, &mut self.grid_texture, cells, size);
         self.global_parameters.grid_size = size.cast::<u32>().unwrap();
         queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
     }
 
+    // Draws the cells over the top of the window where render scale is the size of the cells relative to the window
+    // Should be called after update_atlas since it shares the atlas with the grid
+    pub fn update_tab_bar(
+        &mut self, device: &wgpu::Device, queue: &wgpu::Queue,
+        cells: &[CellData], size: Vector2<usize>, render_scale: Vector2<f32>,
+    ) {
+        let tab_bar = self.tab_bar.get_or_insert_with(|| TabBarLayer {
+            global_parameters: GlobalParameters::default(),
+            global_parameters_uniform: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("tab_bar_global_parameters"),
+                size: std::mem::size_of::<GlobalParameters>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            grid_texture: create_grid_texture(device, wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            }),
+        });
+        write_grid_texture(device, queue, &mut tab_bar.grid_texture, cells, size);
+        // no cursor, scrollbar or scrolling
+        tab_bar.global_parameters = GlobalParameters {
+            render_scale,
+            grid_size: size.cast::<u32>().unwrap(),
+            atlas_size: self.global_parameters.atlas_size,
+            is_srgb_encode_required: self.global_parameters.is_srgb_encode_required,
+            is_focused: self.global_parameters.is_focused,
+            unfocused_dim: self.global_parameters.unfocused_dim,
+            font_scale: self.global_parameters.font_scale,
+            ..GlobalParameters::default()
+        };
+        queue.write_buffer(&tab_bar.global_parameters_uniform, 0, bytemuck::cast_slice(&[tab_bar.global_parameters]));
+    }
+
+    pub fn clear_tab_bar(&mut self) {
+        self.tab_bar = None;
+    }
+
+    // Pixels are tightly packed rgba8 and the image isn't drawn until it is given a quad by update_images
+    pub fn upload_image(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, id: u64, size: Vector2<usize>, pixels: &[u8]) {
+        let image = ImageLayer::new(device, queue, &self.image_bind_group_layout, size, pixels);
+        self.images.insert(id, image);
+    }
+
+    pub fn has_image(&self, id: u64) -> bool {
+        self.images.contains_key(&id)
+    }
+
+    // Frees the textures of images that aren't kept
+    pub fn retain_images(&mut self, mut is_kept: impl FnMut(u64) -> bool) {
+        self.images.retain(|&id, _| is_kept(id));
+    }
+
+    // Uploaded images without a quad are hidden
+    pub fn update_images(&mut self, queue: &wgpu::Queue, quads: &[ImageQuad]) {
+        self.images.values_mut().for_each(|image| image.is_visible = false);
+        for quad in quads {
+            if let Some(image) = self.images.get_mut(&quad.id) {
+                image.set_quad(queue, quad);
+            }
+        }
+    }
+
     pub fn update_atlas(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, atlas: &mut GlyphAtlas) {
         let pixel_size_bytes = 1;
         let texture_size = atlas.get_texture_size();
         let glyph_size = atlas.get_glyph_size();
         let total_glyphs_in_block = atlas.get_total_glyphs_in_block();
         let total_blocks = atlas.get_total_blocks();
+        let total_layers = atlas.get_total_layers();
         let atlas_size = Vector2::new(
             total_glyphs_in_block.x*total_blocks.x,
             total_glyphs_in_block.y*total_blocks.y,
@@ -249,13 +565,14 @@ impl Renderer {
             self.atlas_texture.width() as usize, 
             self.atlas_texture.height() as usize,
         );
-        if texture_size != old_size {
+        let old_total_layers = self.atlas_texture.depth_or_array_layers() as usize;
+        if texture_size != old_size || total_layers != old_total_layers {
             self.atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
                 label: Some("atlas_texture"),
                 size: wgpu::Extent3d {
                     width: texture_size.x as u32,
                     height: texture_size.y as u32,
-                    depth_or_array_layers: 1,
+                    depth_or_array_layers: total_layers as u32,
                 },
                 mip_level_count: 1,
                 sample_count: 1,
@@ -265,8 +582,8 @@ impl Renderer {
                 view_formats: &[],
             });
         }
-        for block_index in atlas.get_modified_blocks() {
-            let block_data = atlas.get_block(block_index);
+        for (layer, block_index) in atlas.get_modified_blocks() {
+            let block_data = atlas.get_block(layer, block_index);
             let block_size = Vector2::new(
                 glyph_size.x*total_glyphs_in_block.x,
                 glyph_size.y*total_glyphs_in_block.y,
@@ -277,7 +594,7 @@ impl Renderer {
                 origin: wgpu::Origin3d {
                     x: (block_size.x*block_index.x) as u32,
                     y: (block_size.y*block_index.y) as u32,
-                    z: 0,
+                    z: layer as u32,
                 },
                 aspect: wgpu::TextureAspect::All,
             };
@@ -307,20 +624,225 @@ impl Renderer {
         queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
     }
 
+    // Offset is in normalised screen coordinates where positive y moves the grid up
+    pub fn update_scroll_offset(&mut self, queue: &wgpu::Queue, scroll_offset: Vector2<f32>) {
+        self.global_parameters.scroll_offset = scroll_offset;
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Rect is (left, top, right, bottom) in normalised screen coordinates
+    // Colour alpha controls the opacity of the overlay
+    pub fn update_scrollbar(&mut self, queue: &wgpu::Queue, rect: Vector4<f32>, colour: Vector4<f32>) {
+        self.global_parameters.scrollbar_rect = rect;
+        self.global_parameters.scrollbar_colour = colour;
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Cursor is hidden if none
+    pub fn update_cursor(&mut self, queue: &wgpu::Queue, cursor: Option<CursorData>) {
+        self.global_parameters.set_cursor(cursor);
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Cells between start and end inclusive are drawn with an underline in the cursor colour
+    pub fn update_hover(&mut self, queue: &wgpu::Queue, hover: Option<(Vector2<usize>, Vector2<usize>)>) {
+        self.global_parameters.set_hover(hover);
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Grid colours are multiplied by unfocused_dim when the window isn't focused
+    pub fn update_focus(&mut self, queue: &wgpu::Queue, is_focused: bool, unfocused_dim: f32) {
+        self.global_parameters.set_focus(is_focused, unfocused_dim);
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Inverts the grid colours by the given amount between 0 and 1 for the visual bell
+    pub fn update_bell_flash(&mut self, queue: &wgpu::Queue, intensity: f32) {
+        self.global_parameters.set_bell_flash(intensity);
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Glyphs are sampled at this scale within each cell to follow small dpi changes without regenerating the atlas
+    pub fn set_font_scale(&mut self, queue: &wgpu::Queue, scale: f32) {
+        self.global_parameters.set_font_scale(scale);
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    // Colour of the area outside of the grid
+    pub fn set_clear_colour(&mut self, colour: Vector3<u8>) {
+        let params = &self.global_parameters;
+        self.clear_colour = convert_clear_colour(self.surface_texture_format, colour);
+        self.clear_colour = apply_clear_opacity(self.clear_colour, params.background_opacity, params.is_alpha_premultiplied != 0);
+    }
+
+    // Backgrounds are drawn with this opacity when the window is transparent
+    // Alpha is multiplied into the colour if the surface was configured with a premultiplied alpha mode
+    pub fn set_background_opacity(&mut self, queue: &wgpu::Queue, opacity: f32, is_alpha_premultiplied: bool) {
+        self.global_parameters.set_background_opacity(opacity, is_alpha_premultiplied);
+        queue.write_buffer(&self.global_parameters_uniform, 0, bytemuck::cast_slice(&[self.global_parameters]));
+    }
+
+    pub fn get_texture_format(&self) -> wgpu::TextureFormat {
+        self.surface_texture_format
+    }
+
+    // Renders into an offscreen texture and reads back tightly packed rgba8 pixels
+    pub fn render_to_buffer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: Vector2<usize>) -> Vec<u8> {
+        let size = Vector2::new(size.x.max(1), size.y.max(1));
+        let format = self.surface_texture_format;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_texture"),
+            size: wgpu::Extent3d {
+                width: size.x as u32,
+                height: size.y as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // rows in buffer copies need to be aligned
+        let bytes_per_texel = format.block_size(None).expect("Render target should be a colour format") as usize;
+        let bytes_per_row = size.x*bytes_per_texel;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+        let padded_bytes_per_row = bytes_per_row.div_ceil(alignment)*alignment;
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen_readback_buffer"),
+            size: (padded_bytes_per_row*size.y) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("offscreen_encoder"),
+        });
+        self.generate_commands(&mut encoder, &texture_view, device);
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row as u32),
+                    rows_per_image: Some(size.y as u32),
+                },
+            },
+            wgpu::Extent3d {
+                width: size.x as u32,
+                height: size.y as u32,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = readback_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        let _ = device.poll(wgpu::Maintain::Wait);
+        let is_mapped = matches!(rx.recv(), Ok(Ok(())));
+        assert!(is_mapped, "Offscreen readback buffer failed to map");
+
+        let mut pixels = vec![0u8; size.x*size.y*4];
+        {
+            let data = buffer_slice.get_mapped_range();
+            for y in 0..size.y {
+                let src_row = &data[(y*padded_bytes_per_row)..(y*padded_bytes_per_row + bytes_per_row)];
+                let dst_row = &mut pixels[(y*size.x*4)..((y+1)*size.x*4)];
+                for (src, dst) in src_row.chunks_exact(bytes_per_texel).zip(dst_row.chunks_exact_mut(4)) {
+                    dst.copy_from_slice(&convert_texel_to_rgba8(format, src));
+                }
+            }
+        }
+        readback_buffer.unmap();
+        pixels
+    }
+
     pub fn generate_commands(
         &mut self, 
         encoder: &mut wgpu::CommandEncoder, 
         render_output_view: &wgpu::TextureView,
         device: &wgpu::Device,
     ) {
-        let atlas_texture_view = self.atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // single layer textures default to a D2 view so the array dimension is explicit
+        let atlas_texture_view = self.atlas_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
         let grid_texture_view = self.grid_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let bind_group = self.create_bind_group(device, &self.global_parameters_uniform, &atlas_texture_view, &grid_texture_view);
+        let tab_bar_bind_group = self.tab_bar.as_ref().map(|tab_bar| {
+            let grid_texture_view = tab_bar.grid_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.create_bind_group(device, &tab_bar.global_parameters_uniform, &atlas_texture_view, &grid_texture_view)
+        });
+        // skip timing this pass if the previous timestamps are still being read back
+        let gpu_timer = self.gpu_timer.as_mut().filter(|timer| timer.pending_map.is_none());
+        let timestamp_writes = gpu_timer.as_ref().map(|timer| wgpu::RenderPassTimestampWrites {
+            query_set: &timer.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: render_output_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_colour),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes,
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
+            rpass.set_index_buffer(
+                self.mesh.index_buffer.slice(..), 
+                self.mesh.index_format,
+            );
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw_indexed(0..self.mesh.total_indices as u32, 0, 0..1);
+            // images cover the blank cells reserved for them in the grid
+            let visible_images: Vec<&ImageLayer> = self.images.values().filter(|image| image.is_visible).collect();
+            if !visible_images.is_empty() {
+                rpass.set_pipeline(&self.image_pipeline);
+                for image in visible_images {
+                    rpass.set_bind_group(1, &image.bind_group, &[]);
+                    rpass.draw_indexed(0..self.mesh.total_indices as u32, 0, 0..1);
+                }
+                rpass.set_pipeline(&self.render_pipeline);
+            }
+            // tab bar uses the same pipeline and covers any rows scrolled underneath it
+            if let Some(bind_group) = tab_bar_bind_group.as_ref() {
+                rpass.set_bind_group(0, bind_group, &[]);
+                rpass.draw_indexed(0..self.mesh.total_indices as u32, 0, 0..1);
+            }
+        }
+        if let Some(timer) = gpu_timer {
+            encoder.resolve_query_set(&timer.query_set, 0..TOTAL_TIMESTAMPS, &timer.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(&timer.resolve_buffer, 0, &timer.readback_buffer, 0, TIMESTAMP_BUFFER_SIZE);
+            timer.is_written = true;
+        }
+    }
+
+    fn create_bind_group(
+        &self, device: &wgpu::Device, global_parameters_uniform: &wgpu::Buffer,
+        atlas_texture_view: &wgpu::TextureView, grid_texture_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("bind_group"),
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: self.global_parameters_uniform.as_entire_binding(),
+                    resource: global_parameters_uniform.as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
@@ -328,37 +850,77 @@ impl Renderer {
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&atlas_texture_view),
+                    resource: wgpu::BindingResource::TextureView(atlas_texture_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::TextureView(&grid_texture_view),
+                    resource: wgpu::BindingResource::TextureView(grid_texture_view),
                 },
             ],
             layout: &self.bind_group_layout,
-        });
-        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: render_output_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(self.clear_colour),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-        });
-        rpass.set_pipeline(&self.render_pipeline);
-        rpass.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
-        rpass.set_index_buffer(
-            self.mesh.index_buffer.slice(..), 
-            self.mesh.index_format,
-        );
-        rpass.set_bind_group(0, &bind_group, &[]);
-        rpass.draw_indexed(0..self.mesh.total_indices as u32, 0, 0..1);
+        })
+    }
+}
+
+// Clear colour is written without going through the shader so it needs to match the output encoding
+pub(crate) fn convert_clear_colour(format: wgpu::TextureFormat, colour: Vector3<u8>) -> wgpu::Color {
+    let is_encoded = is_srgb_encode_required(format);
+    let convert = |v: u8| -> f64 {
+        let v = v as f32 / 255.0;
+        if is_encoded { v as f64 } else { convert_srgb_to_linear(v) as f64 }
+    };
+    wgpu::Color { r: convert(colour.x), g: convert(colour.y), b: convert(colour.z), a: 1.0 }
+}
+
+pub(crate) fn apply_clear_opacity(colour: wgpu::Color, opacity: f32, is_alpha_premultiplied: bool) -> wgpu::Color {
+    let a = opacity as f64;
+    if !is_alpha_premultiplied {
+        return wgpu::Color { a, ..colour };
+    }
+    wgpu::Color { r: colour.r*a, g: colour.g*a, b: colour.b*a, a }
+}
+
+pub(crate) fn convert_texel_to_rgba8(format: wgpu::TextureFormat, texel: &[u8]) -> [u8; 4] {
+    use wgpu::TextureFormat as F;
+    let unorm_to_u8 = |v: f32| -> u8 { (v.clamp(0.0, 1.0)*255.0).round() as u8 };
+    match format {
+        F::Rgba8Unorm | F::Rgba8UnormSrgb => [texel[0], texel[1], texel[2], texel[3]],
+        F::Bgra8Unorm | F::Bgra8UnormSrgb => [texel[2], texel[1], texel[0], texel[3]],
+        F::Rgb10a2Unorm => {
+            let v = u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+            let r = (v & 0x3FF) as f32 / 1023.0;
+            let g = ((v >> 10) & 0x3FF) as f32 / 1023.0;
+            let b = ((v >> 20) & 0x3FF) as f32 / 1023.0;
+            let a = ((v >> 30) & 0x3) as f32 / 3.0;
+            [unorm_to_u8(r), unorm_to_u8(g), unorm_to_u8(b), unorm_to_u8(a)]
+        },
+        F::Rgba16Float => {
+            let mut rgba = [0u8; 4];
+            for (i, v) in rgba.iter_mut().enumerate() {
+                let half = u16::from_le_bytes([texel[2*i], texel[2*i+1]]);
+                let value = convert_f16_to_f32(half);
+                // float targets are linear so colour channels are encoded to match the unorm formats
+                let value = if i < 3 { convert_linear_to_srgb(value.clamp(0.0, 1.0)) } else { value };
+                *v = unorm_to_u8(value);
+            }
+            rgba
+        },
+        _ => {
+            log::error!("Unsupported texture format for readback: {:?}", format);
+            [0, 0, 0, 0]
+        },
+    }
+}
+
+fn convert_f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1F) as i32;
+    let mantissa = (half & 0x3FF) as f32;
+    match exponent {
+        0 => sign * mantissa * 2.0f32.powi(-24),
+        0x1F if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1F => f32::NAN,
+        _ => sign * (1.0 + mantissa/1024.0) * 2.0f32.powi(exponent-15),
     }
 }
 
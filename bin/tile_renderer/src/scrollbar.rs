@@ -0,0 +1,22 @@
+// Position of the scrollbar thumb along its track where 0 is the top and 1 is the bottom
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct ScrollbarThumb {
+    pub start: f32,
+    pub end: f32,
+}
+
+pub fn calculate_scrollbar_thumb(
+    scroll_offset: usize, total_lines: usize, viewport_lines: usize, min_length: f32,
+) -> Option<ScrollbarThumb> {
+    if viewport_lines == 0 || total_lines <= viewport_lines {
+        return None;
+    }
+    let length = (viewport_lines as f32 / total_lines as f32).max(min_length).min(1.0);
+    let max_offset = total_lines - viewport_lines;
+    let position = scroll_offset.min(max_offset) as f32 / max_offset as f32;
+    let start = position*(1.0-length);
+    Some(ScrollbarThumb {
+        start,
+        end: start+length,
+    })
+}
@@ -2,6 +2,22 @@ struct GlobalParameters {
     render_scale: vec2<f32>,
     grid_size: vec2<u32>,
     atlas_size: vec2<u32>,
+    scroll_offset: vec2<f32>,
+    scrollbar_rect: vec4<f32>,
+    scrollbar_colour: vec4<f32>,
+    cursor_colour: vec4<f32>,
+    cursor_style: u32,
+    is_cursor_visible: u32,
+    is_srgb_encode_required: u32,
+    is_focused: u32,
+    unfocused_dim: f32,
+    font_scale: f32,
+    bell_flash: f32,
+    is_hover_visible: u32,
+    hover_start: vec2<u32>,
+    hover_end: vec2<u32>,
+    background_opacity: f32,
+    is_alpha_premultiplied: u32,
 }
 
 struct VertexInput {
@@ -11,45 +27,125 @@ struct VertexInput {
 struct FragmentInput {
     @builtin(position) position: vec4<f32>,
     @location(0) grid_position: vec2<f32>,
+    @location(1) screen_position: vec2<f32>,
 }
 
-// Refer to glyph_grid::GlyphGridData for how this is packed
+// Refer to cell_data.rs for how this is packed
+// CELL_* constants are prepended from there when the shader is loaded
 struct Cell {
     atlas_index: vec2<u32>,
+    atlas_layer: u32,
     colour_foreground: vec4<u32>,
     colour_background: vec4<u32>,
-    is_underline: bool,
+    underline_style: u32,
+    has_underline_colour: bool,
+    colour_underline: vec4<u32>,
+    is_inverse: bool,
+    is_hidden: bool,
+    is_cursor: bool,
+}
+
+const UNDERLINE_NONE: u32 = 0u;
+const UNDERLINE_STRAIGHT: u32 = 1u;
+const UNDERLINE_DOUBLE: u32 = 2u;
+const UNDERLINE_CURLY: u32 = 3u;
+const UNDERLINE_DOTTED: u32 = 4u;
+const UNDERLINE_DASHED: u32 = 5u;
+const PI: f32 = 3.14159265;
+
+const CURSOR_BLOCK: u32 = 0u;
+const CURSOR_UNDERLINE: u32 = 1u;
+const CURSOR_BAR: u32 = 2u;
+
+fn unpack_rgb(v: u32) -> vec4<u32> {
+    return vec4<u32>(v & 0xFFu, (v >> 8u) & 0xFFu, (v >> 16u) & 0xFFu, 255u);
 }
 
 fn unpack_cell_data(data: vec4<u32>) -> Cell {
     var d: Cell;
-    d.atlas_index.x       = (data.r & 0x0000FFFF);
-    d.atlas_index.y       = (data.r & 0xFFFF0000) >> 16;
-    d.colour_foreground.r = (data.g & 0x000000FF);
-    d.colour_foreground.g = (data.g & 0x0000FF00) >> 8;
-    d.colour_foreground.b = (data.g & 0x00FF0000) >> 16;
-    d.colour_foreground.a = (data.g & 0xFF000000) >> 24;
-    d.colour_background.r = (data.b & 0x000000FF);
-    d.colour_background.g = (data.b & 0x0000FF00) >> 8;
-    d.colour_background.b = (data.b & 0x00FF0000) >> 16;
-    d.colour_background.a = (data.b & 0xFF000000) >> 24;
-    d.is_underline =    bool(data.a & 0x00000001);
+    d.atlas_index.x         = data.r & CELL_ATLAS_COLUMN_MASK;
+    d.atlas_index.y         = (data.r >> CELL_ATLAS_ROW_SHIFT) & CELL_ATLAS_ROW_MASK;
+    d.atlas_layer           = (data.r >> CELL_ATLAS_LAYER_SHIFT) & CELL_ATLAS_LAYER_MASK;
+    d.colour_foreground     = unpack_rgb(data.g & CELL_COLOUR_MASK);
+    d.is_inverse            = (data.g & CELL_INVERSE_FLAG) != 0u;
+    d.is_hidden             = (data.g & CELL_HIDDEN_FLAG) != 0u;
+    d.is_cursor             = (data.g & CELL_CURSOR_FLAG) != 0u;
+    d.colour_background     = unpack_rgb(data.b & CELL_COLOUR_MASK);
+    d.underline_style       = (data.b >> CELL_UNDERLINE_STYLE_SHIFT) & CELL_UNDERLINE_STYLE_MASK;
+    d.colour_underline      = unpack_rgb(data.a & CELL_COLOUR_MASK);
+    d.has_underline_colour  = (data.a & CELL_UNDERLINE_COLOUR_FLAG) != 0u;
     return d;
 }
 
+// Colours are blended in linear space
+fn convert_srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let is_low = c <= vec3<f32>(0.04045);
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, is_low);
+}
+
+fn convert_linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let is_low = c <= vec3<f32>(0.0031308);
+    return select(1.055*pow(c, vec3<f32>(1.0/2.4)) - 0.055, c*12.92, is_low);
+}
+
+fn unpack_colour(c: vec4<u32>) -> vec4<f32> {
+    let v = vec4<f32>(c) / 255.0;
+    return vec4<f32>(convert_srgb_to_linear(v.rgb), v.a);
+}
+
+// Returns coverage of underline at pixel position inside cell
+fn get_underline_coverage(style: u32, position: vec2<f32>, cell_size: vec2<f32>) -> f32 {
+    let thickness = max(1.0, floor(cell_size.y / 16.0));
+    let baseline = cell_size.y - 2.0*thickness;
+    let distance = abs(position.y - baseline);
+    let is_line = distance < 0.5*thickness + 0.5;
+    switch style {
+        case UNDERLINE_STRAIGHT: {
+            return select(0.0, 1.0, is_line);
+        }
+        case UNDERLINE_DOUBLE: {
+            let is_upper = abs(position.y - (baseline - 2.0*thickness)) < 0.5*thickness + 0.5;
+            return select(0.0, 1.0, is_line || is_upper);
+        }
+        case UNDERLINE_CURLY: {
+            // one period of sine wave per cell so adjacent cells join up
+            let amplitude = max(thickness, floor(cell_size.y / 12.0));
+            let phase = 2.0*PI*position.x/cell_size.x;
+            let wave_y = baseline - amplitude*0.5*(1.0 + sin(phase));
+            // scale distance by slope so line thickness is constant along the wave
+            let slope = amplitude*0.5*cos(phase)*2.0*PI/cell_size.x;
+            let wave_distance = abs(position.y - wave_y) / sqrt(1.0 + slope*slope);
+            return clamp(0.5*thickness + 0.5 - wave_distance, 0.0, 1.0);
+        }
+        case UNDERLINE_DOTTED: {
+            let is_dot = fract(position.x / (2.0*thickness)) < 0.5;
+            return select(0.0, 1.0, is_line && is_dot);
+        }
+        case UNDERLINE_DASHED: {
+            // two dashes per cell
+            let is_dash = fract(2.0*position.x / cell_size.x) < 0.6;
+            return select(0.0, 1.0, is_line && is_dash);
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
 @group(0) @binding(0) var<uniform> global_params: GlobalParameters;
 @group(0) @binding(1) var atlas_sampler: sampler;
-@group(0) @binding(2) var atlas_texture: texture_2d<f32>;
+@group(0) @binding(2) var atlas_texture: texture_2d_array<f32>;
 @group(0) @binding(3) var grid_texture: texture_2d<u32>;
 
 @vertex
 fn vs_main(vertex: VertexInput) -> FragmentInput {
-    let screen_vertex_position = vertex.position*global_params.render_scale;
+    let screen_vertex_position = vertex.position*global_params.render_scale - global_params.scroll_offset;
     let frag_position = vec4<f32>(screen_vertex_position.x*2.0 - 1.0, -(screen_vertex_position.y*2.0 - 1.0), 0.0, 1.0);
     let grid_position = vertex.position;
     var frag_out: FragmentInput;
     frag_out.position = frag_position;
     frag_out.grid_position = grid_position;
+    frag_out.screen_position = screen_vertex_position;
     return frag_out;
 }
 
@@ -67,14 +163,144 @@ fn fs_main(frag: FragmentInput) -> @location(0) vec4<f32> {
     // determine glyph atlas location
     let atlas_glyph_size = 1.0 / vec2<f32>(global_params.atlas_size);
     let atlas_offset = atlas_glyph_size*vec2<f32>(cell.atlas_index);
-    let atlas_position = absolute_grid_offset*atlas_glyph_size + atlas_offset;
+    // glyph is scaled from the top left corner and clipped to the cell
+    let glyph_offset = absolute_grid_offset / global_params.font_scale;
+    let atlas_position = glyph_offset*atlas_glyph_size + atlas_offset;
 
     // fetch glyph data from atlas 
-    let data = textureSampleLevel(atlas_texture, atlas_sampler, atlas_position, 0.0);
-    let v: f32 = data.r;
-    let foreground_colour = vec4<f32>(cell.colour_foreground) / 255.0;
-    let background_colour = vec4<f32>(cell.colour_background) / 255.0;
-    let output_colour = foreground_colour*v + background_colour*(1-v);
+    let data = textureSampleLevel(atlas_texture, atlas_sampler, atlas_position, i32(cell.atlas_layer), 0.0);
+    var v: f32 = data.r;
+    if (cell.is_hidden || glyph_offset.x >= 1.0 || glyph_offset.y >= 1.0) {
+        v = 0.0;
+    }
+    var foreground_colour = unpack_colour(cell.colour_foreground);
+    var background_colour = unpack_colour(cell.colour_background);
+    if (cell.is_inverse) {
+        let colour = foreground_colour;
+        foreground_colour = background_colour;
+        background_colour = colour;
+    }
+    var output_colour = foreground_colour*v + background_colour*(1-v);
+    // only the background is transparent so glyphs, underlines and the cursor stay opaque
+    var alpha = mix(global_params.background_opacity, 1.0, v);
+
+    let cell_size = vec2<f32>(textureDimensions(atlas_texture)) / vec2<f32>(global_params.atlas_size);
+    let cell_position = absolute_grid_offset*cell_size;
+
+    // underline
+    if (cell.underline_style != UNDERLINE_NONE && !cell.is_hidden) {
+        let coverage = get_underline_coverage(cell.underline_style, cell_position, cell_size);
+        var underline_colour = foreground_colour;
+        if (cell.has_underline_colour) {
+            underline_colour = unpack_colour(cell.colour_underline);
+        }
+        output_colour = mix(output_colour, underline_colour, coverage);
+        alpha = mix(alpha, 1.0, coverage);
+    }
+
+    // hovered hyperlink is underlined in the cursor colour
+    let cell_index = vec2<u32>(absolute_grid_position_floor);
+    let is_after_start = cell_index.y > global_params.hover_start.y ||
+        (cell_index.y == global_params.hover_start.y && cell_index.x >= global_params.hover_start.x);
+    let is_before_end = cell_index.y < global_params.hover_end.y ||
+        (cell_index.y == global_params.hover_end.y && cell_index.x <= global_params.hover_end.x);
+    if (global_params.is_hover_visible != 0u && is_after_start && is_before_end && !cell.is_hidden) {
+        let coverage = get_underline_coverage(UNDERLINE_STRAIGHT, cell_position, cell_size);
+        let hover_colour = vec4<f32>(convert_srgb_to_linear(global_params.cursor_colour.rgb), 1.0);
+        output_colour = mix(output_colour, hover_colour, coverage);
+        alpha = mix(alpha, 1.0, coverage);
+    }
+
+    // dim grid when unfocused except for the cursor
+    let is_cursor_drawn = global_params.is_cursor_visible != 0u && cell.is_cursor;
+    if (global_params.is_focused == 0u && !is_cursor_drawn) {
+        output_colour = vec4<f32>(output_colour.rgb*global_params.unfocused_dim, output_colour.a);
+    }
+
+    // cursor
+    if (is_cursor_drawn) {
+        let cursor_colour = vec4<f32>(convert_srgb_to_linear(global_params.cursor_colour.rgb), global_params.cursor_colour.a);
+        let thickness = max(1.0, floor(cell_size.y / 16.0));
+        switch global_params.cursor_style {
+            case CURSOR_BLOCK: {
+                // draw glyph with cell background over the cursor so it stays readable
+                output_colour = cursor_colour*(1-v) + background_colour*v;
+                alpha = 1.0;
+            }
+            case CURSOR_UNDERLINE: {
+                if (cell_position.y >= cell_size.y - 2.0*thickness) {
+                    output_colour = cursor_colour;
+                    alpha = 1.0;
+                }
+            }
+            case CURSOR_BAR: {
+                if (cell_position.x < 2.0*thickness) {
+                    output_colour = cursor_colour;
+                    alpha = 1.0;
+                }
+            }
+            default: {}
+        }
+    }
+
+    // visual bell inverts the grid colours
+    output_colour = vec4<f32>(mix(output_colour.rgb, 1.0 - output_colour.rgb, global_params.bell_flash), output_colour.a);
+
+    // scrollbar overlay
+    let rect = global_params.scrollbar_rect;
+    let screen = frag.screen_position;
+    if (screen.x >= rect.x && screen.x <= rect.z && screen.y >= rect.y && screen.y <= rect.w) {
+        let colour = global_params.scrollbar_colour;
+        output_colour = mix(output_colour, vec4<f32>(convert_srgb_to_linear(colour.rgb), 1.0), colour.a);
+        alpha = mix(alpha, 1.0, colour.a);
+    }
+    if (global_params.is_srgb_encode_required != 0u) {
+        output_colour = vec4<f32>(convert_linear_to_srgb(output_colour.rgb), output_colour.a);
+    }
+    if (global_params.is_alpha_premultiplied != 0u) {
+        output_colour = vec4<f32>(output_colour.rgb*alpha, alpha);
+    } else {
+        output_colour = vec4<f32>(output_colour.rgb, alpha);
+    }
     return output_colour;
 }
 
+
+// Refer to image_layer.rs where position and size are in cells
+struct ImageParameters {
+    position: vec2<f32>,
+    size: vec2<f32>,
+}
+
+struct ImageFragmentInput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) image_position: vec2<f32>,
+}
+
+@group(1) @binding(0) var<uniform> image_params: ImageParameters;
+@group(1) @binding(1) var image_texture: texture_2d<f32>;
+
+@vertex
+fn vs_image(vertex: VertexInput) -> ImageFragmentInput {
+    // placed in the grid the same way as cells so it scrolls with them
+    let grid_position = (image_params.position + vertex.position*image_params.size) / vec2<f32>(global_params.grid_size);
+    let screen_vertex_position = grid_position*global_params.render_scale - global_params.scroll_offset;
+    var frag_out: ImageFragmentInput;
+    frag_out.position = vec4<f32>(screen_vertex_position.x*2.0 - 1.0, -(screen_vertex_position.y*2.0 - 1.0), 0.0, 1.0);
+    frag_out.image_position = vertex.position;
+    return frag_out;
+}
+
+@fragment
+fn fs_image(frag: ImageFragmentInput) -> @location(0) vec4<f32> {
+    // texture is srgb so it is sampled as linear colours
+    var output_colour = textureSampleLevel(image_texture, atlas_sampler, frag.image_position, 0.0);
+    if (global_params.is_focused == 0u) {
+        output_colour = vec4<f32>(output_colour.rgb*global_params.unfocused_dim, output_colour.a);
+    }
+    output_colour = vec4<f32>(mix(output_colour.rgb, 1.0 - output_colour.rgb, global_params.bell_flash), output_colour.a);
+    if (global_params.is_srgb_encode_required != 0u) {
+        output_colour = vec4<f32>(convert_linear_to_srgb(output_colour.rgb), output_colour.a);
+    }
+    return output_colour;
+}
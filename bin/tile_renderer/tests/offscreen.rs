@@ -0,0 +1,290 @@
+use cgmath::{Vector2, Vector3};
+use tile_renderer::{CellData, CursorData, CursorStyle, FontdueGlyphGenerator, GlyphCache, Renderer, UnderlineStyle};
+
+const FONT_FILENAME: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../res/Iosevka-custom-regular.ttf");
+
+// Software adapters are used when there is no gpu, e.g. in CI
+fn create_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let request_adapter = |force_fallback_adapter: bool| {
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter,
+            compatible_surface: None,
+        }))
+    };
+    let adapter = request_adapter(false).or_else(|| request_adapter(true))?;
+    let device = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        label: None,
+        required_features: wgpu::Features::empty(),
+        required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+    }, None)).ok()?;
+    Some(device)
+}
+
+struct OffscreenTest {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    glyph_cache: GlyphCache,
+    renderer: Renderer,
+}
+
+impl OffscreenTest {
+    fn new() -> Option<Self> {
+        let (device, queue) = create_device()?;
+        let font_data = std::fs::read(FONT_FILENAME).unwrap();
+        let font = fontdue::Font::from_bytes(font_data.as_slice(), fontdue::FontSettings::default()).unwrap();
+        let max_texture_size = device.limits().max_texture_dimension_2d as usize;
+        let glyph_cache = GlyphCache::new(
+            Box::new(FontdueGlyphGenerator::new(font, 16.0)),
+            Vector2::new(max_texture_size, max_texture_size),
+            device.limits().max_texture_array_layers as usize,
+            None,
+        );
+        let renderer = Renderer::new_headless(&device, wgpu::TextureFormat::Rgba8Unorm);
+        Some(Self { device, queue, glyph_cache, renderer })
+    }
+
+    fn create_cell(&mut self, c: char) -> CellData {
+        self.create_coloured_cell(c, Vector3::new(255,255,255), Vector3::new(0,0,0))
+    }
+
+    fn create_coloured_cell(&mut self, c: char, foreground: Vector3<u8>, background: Vector3<u8>) -> CellData {
+        let total_glyphs_in_block = self.glyph_cache.get_glyph_atlas().get_total_glyphs_in_block();
+        let glyph = self.glyph_cache.get_glyph_location(c, 0);
+        let mut cell = CellData::default();
+        cell.set_foreground_colour(foreground);
+        cell.set_background_colour(background);
+        cell.set_atlas_index(glyph.get_atlas_position(total_glyphs_in_block, 0), glyph.layer);
+        cell
+    }
+
+    // Returns tightly packed rgba8 pixels where each cell is one glyph in size
+    fn render_grid(&mut self, cells: &[CellData], grid_size: Vector2<usize>) -> Vec<u8> {
+        assert_eq!(cells.len(), grid_size.x*grid_size.y);
+        self.renderer.update_grid(&self.device, &self.queue, cells, grid_size);
+        self.renderer.update_atlas(&self.device, &self.queue, self.glyph_cache.get_glyph_atlas_mut());
+        self.renderer.update_render_scale(&self.queue, Vector2::new(1.0,1.0));
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let image_size = Vector2::new(grid_size.x*glyph_size.x, grid_size.y*glyph_size.y);
+        let pixels = self.renderer.render_to_buffer(&self.device, &self.queue, image_size);
+        assert_eq!(pixels.len(), image_size.x*image_size.y*4);
+        pixels
+    }
+
+    // Returns the number of non-black pixels in each cell of a single row
+    fn render_row(&mut self, cells: &[CellData]) -> Vec<usize> {
+        let pixels = self.render_grid(cells, Vector2::new(cells.len(),1));
+        let glyph_size = self.glyph_cache.get_glyph_atlas().get_glyph_size();
+        let image_size = Vector2::new(cells.len()*glyph_size.x, glyph_size.y);
+        let mut total_lit = vec![0usize; cells.len()];
+        for y in 0..image_size.y {
+            for x in 0..image_size.x {
+                let i = (y*image_size.x + x)*4;
+                if pixels[i..(i+3)].iter().any(|v| *v > 0) {
+                    total_lit[x / glyph_size.x] += 1;
+                }
+            }
+        }
+        total_lit
+    }
+}
+
+#[test]
+fn render_glyphs_offscreen() {
+    let Some(mut test) = OffscreenTest::new() else {
+        eprintln!("Skipping offscreen render test since no graphics adapter is available");
+        return;
+    };
+    // white glyph followed by blank cell on black background
+    let cells = [test.create_cell('#'), test.create_cell(' ')];
+    let total_lit = test.render_row(&cells);
+    assert!(total_lit[0] > 0, "expected glyph to be drawn in first cell");
+    assert_eq!(total_lit[1], 0, "expected blank cell to be black");
+}
+
+#[test]
+fn render_inverse_and_hidden_offscreen() {
+    let Some(mut test) = OffscreenTest::new() else {
+        eprintln!("Skipping offscreen render test since no graphics adapter is available");
+        return;
+    };
+    let glyph_size = test.glyph_cache.get_glyph_atlas().get_glyph_size();
+    let total_cell_pixels = glyph_size.x*glyph_size.y;
+    let mut hidden = test.create_cell('#');
+    hidden.set_hidden(true);
+    let mut inverse_blank = test.create_cell(' ');
+    inverse_blank.set_inverse(true);
+    let mut hidden_inverse = test.create_cell('#');
+    hidden_inverse.set_hidden(true);
+    hidden_inverse.set_inverse(true);
+    let total_lit = test.render_row(&[hidden, inverse_blank, hidden_inverse]);
+    assert_eq!(total_lit[0], 0, "expected hidden glyph to only draw background");
+    assert_eq!(total_lit[1], total_cell_pixels, "expected inverse blank cell to be filled with foreground");
+    assert_eq!(total_lit[2], total_cell_pixels, "expected hidden inverse cell to draw swapped background");
+}
+
+#[test]
+fn render_known_grid_offscreen() {
+    let Some(mut test) = OffscreenTest::new() else {
+        eprintln!("Skipping offscreen render test since no graphics adapter is available");
+        return;
+    };
+    // full blocks are drawn procedurally so every pixel of the cell is the foreground colour
+    let red = Vector3::new(255,0,0);
+    let green = Vector3::new(0,255,0);
+    let blue = Vector3::new(0,0,255);
+    let white = Vector3::new(255,255,255);
+    let black = Vector3::new(0,0,0);
+    let cells = [
+        test.create_coloured_cell('█', red, black), test.create_coloured_cell(' ', white, blue),
+        test.create_coloured_cell(' ', white, green), test.create_coloured_cell('█', white, black),
+    ];
+    let expected = [red, blue, green, white];
+    let grid_size = Vector2::new(2,2);
+    let pixels = test.render_grid(&cells, grid_size);
+    let glyph_size = test.glyph_cache.get_glyph_atlas().get_glyph_size();
+    let image_width = grid_size.x*glyph_size.x;
+    for (i, pixel) in pixels.chunks(4).enumerate() {
+        let x = (i % image_width) / glyph_size.x;
+        let y = (i / image_width) / glyph_size.y;
+        let colour = expected[y*grid_size.x + x];
+        assert_eq!(pixel, &[colour.x, colour.y, colour.z, 255], "unexpected colour in cell ({},{})", x, y);
+    }
+}
+
+#[test]
+fn render_tab_bar_over_grid_offscreen() {
+    let Some(mut test) = OffscreenTest::new() else {
+        eprintln!("Skipping offscreen render test since no graphics adapter is available");
+        return;
+    };
+    let red = Vector3::new(255,0,0);
+    let blue = Vector3::new(0,0,255);
+    let white = Vector3::new(255,255,255);
+    // grid is 2 rows, tab bar covers the top row
+    let grid = [
+        test.create_coloured_cell(' ', white, red), test.create_coloured_cell(' ', white, red),
+        test.create_coloured_cell(' ', white, red), test.create_coloured_cell(' ', white, red),
+    ];
+    let tab_bar = [test.create_coloured_cell(' ', white, blue), test.create_coloured_cell(' ', white, blue)];
+    let grid_size = Vector2::new(2,2);
+    test.renderer.update_grid(&test.device, &test.queue, &grid, grid_size);
+    test.renderer.update_atlas(&test.device, &test.queue, test.glyph_cache.get_glyph_atlas_mut());
+    test.renderer.update_render_scale(&test.queue, Vector2::new(1.0,1.0));
+    test.renderer.update_tab_bar(&test.device, &test.queue, &tab_bar, Vector2::new(2,1), Vector2::new(1.0,0.5));
+    let glyph_size = test.glyph_cache.get_glyph_atlas().get_glyph_size();
+    let image_size = Vector2::new(grid_size.x*glyph_size.x, grid_size.y*glyph_size.y);
+    let pixels = test.renderer.render_to_buffer(&test.device, &test.queue, image_size);
+    for (i, pixel) in pixels.chunks(4).enumerate() {
+        let y = (i / image_size.x) / glyph_size.y;
+        let colour = if y == 0 { blue } else { red };
+        assert_eq!(pixel, &[colour.x, colour.y, colour.z, 255], "unexpected colour in row {}", y);
+    }
+    // removing the tab bar shows the grid underneath
+    test.renderer.clear_tab_bar();
+    let pixels = test.renderer.render_to_buffer(&test.device, &test.queue, image_size);
+    assert!(pixels.chunks(4).all(|pixel| pixel == [255,0,0,255]));
+}
+
+#[test]
+fn render_cursor_styles_offscreen() {
+    let Some(mut test) = OffscreenTest::new() else {
+        eprintln!("Skipping offscreen render test since no graphics adapter is available");
+        return;
+    };
+    let green = Vector3::new(0,255,0);
+    // cursor is drawn in the middle of three blank cells
+    let mut cells = [test.create_cell(' '), test.create_cell(' '), test.create_cell(' ')];
+    cells[1].set_cursor(true);
+    let glyph_size = test.glyph_cache.get_glyph_atlas().get_glyph_size();
+    let image_width = cells.len()*glyph_size.x;
+    for style in [CursorStyle::Block, CursorStyle::Underline, CursorStyle::Bar] {
+        test.renderer.update_cursor(&test.queue, Some(CursorData { style, colour: green }));
+        let pixels = test.render_grid(&cells, Vector2::new(cells.len(),1));
+        let get_pixel = |x: usize, y: usize| {
+            let i = (y*image_width + x)*4;
+            &pixels[i..(i+4)]
+        };
+        for y in 0..glyph_size.y {
+            for x in 0..glyph_size.x {
+                assert_eq!(get_pixel(x,y), &[0,0,0,255], "expected cell before {:?} cursor to be black", style);
+                assert_eq!(get_pixel(2*glyph_size.x + x,y), &[0,0,0,255], "expected cell after {:?} cursor to be black", style);
+            }
+        }
+        let cursor_x = glyph_size.x;
+        let (first, last) = (0, glyph_size.x-1);
+        let (top, bottom) = (0, glyph_size.y-1);
+        match style {
+            CursorStyle::Block => {
+                for y in 0..glyph_size.y {
+                    for x in 0..glyph_size.x {
+                        assert_eq!(get_pixel(cursor_x + x,y), &[0,255,0,255], "expected block cursor to fill cell");
+                    }
+                }
+            },
+            CursorStyle::Underline => {
+                assert_eq!(get_pixel(cursor_x + first,bottom), &[0,255,0,255], "expected underline cursor on bottom row");
+                assert_eq!(get_pixel(cursor_x + last,bottom), &[0,255,0,255], "expected underline cursor on bottom row");
+                assert_eq!(get_pixel(cursor_x + first,top), &[0,0,0,255], "expected background above underline cursor");
+            },
+            CursorStyle::Bar => {
+                assert_eq!(get_pixel(cursor_x + first,top), &[0,255,0,255], "expected bar cursor on first column");
+                assert_eq!(get_pixel(cursor_x + first,bottom), &[0,255,0,255], "expected bar cursor on first column");
+                assert_eq!(get_pixel(cursor_x + last,top), &[0,0,0,255], "expected background beside bar cursor");
+            },
+        }
+    }
+    // hidden cursor leaves the cell flagged but undrawn
+    test.renderer.update_cursor(&test.queue, None);
+    let total_lit = test.render_row(&cells);
+    assert_eq!(total_lit, vec![0,0,0]);
+}
+
+#[test]
+fn render_underline_styles_offscreen() {
+    let Some(mut test) = OffscreenTest::new() else {
+        eprintln!("Skipping offscreen render test since no graphics adapter is available");
+        return;
+    };
+    let red = Vector3::new(255,0,0);
+    let styles = [UnderlineStyle::Straight, UnderlineStyle::Curly, UnderlineStyle::Dotted, UnderlineStyle::Dashed];
+    let cells: Vec<CellData> = styles.iter().map(|style| {
+        let mut cell = test.create_cell(' ');
+        cell.set_underline(*style, Some(red));
+        cell
+    }).collect();
+    let pixels = test.render_grid(cells.as_slice(), Vector2::new(cells.len(),1));
+    let glyph_size = test.glyph_cache.get_glyph_atlas().get_glyph_size();
+    let image_width = cells.len()*glyph_size.x;
+    // underline colour blends into the black background so any red marks the underline
+    let is_underline = |cell: usize, x: usize, y: usize| {
+        let i = (y*image_width + cell*glyph_size.x + x)*4;
+        pixels[i] > 0
+    };
+    // returns the rows the underline is drawn on and the number of separate segments on the most covered row
+    let get_underline_shape = |cell: usize| {
+        let lit_rows: Vec<usize> = (0..glyph_size.y).filter(|&y| (0..glyph_size.x).any(|x| is_underline(cell,x,y))).collect();
+        let row = *lit_rows.iter().max_by_key(|&&y| (0..glyph_size.x).filter(|&x| is_underline(cell,x,y)).count()).unwrap();
+        let total_lit = (0..glyph_size.x).filter(|&x| is_underline(cell,x,row)).count();
+        let total_segments = (0..glyph_size.x).filter(|&x| is_underline(cell,x,row) && (x == 0 || !is_underline(cell,x-1,row))).count();
+        (lit_rows.len(), total_lit, total_segments)
+    };
+    let (straight_rows, straight_lit, straight_segments) = get_underline_shape(0);
+    assert_eq!(straight_lit, glyph_size.x, "expected straight underline to cover the whole row");
+    assert_eq!(straight_segments, 1);
+    assert!(straight_rows < glyph_size.y/2, "expected straight underline to be thin");
+    // undercurl spreads over more rows than a straight line without filling the cell
+    let (curly_rows, _, _) = get_underline_shape(1);
+    assert!(curly_rows > straight_rows, "expected undercurl to span more rows than a straight underline");
+    assert!(curly_rows < glyph_size.y, "expected undercurl to leave the top of the cell empty");
+    // dotted line is made of many short gaps while a dashed line has two dashes per cell
+    let (dotted_rows, dotted_lit, dotted_segments) = get_underline_shape(2);
+    assert_eq!(dotted_rows, straight_rows);
+    assert!(dotted_lit < glyph_size.x, "expected gaps between dots");
+    assert!(dotted_segments > 2, "expected more than two dots in a cell but got {}", dotted_segments);
+    let (dashed_rows, dashed_lit, dashed_segments) = get_underline_shape(3);
+    assert_eq!(dashed_rows, straight_rows);
+    assert!(dashed_lit < glyph_size.x, "expected gaps between dashes");
+    assert_eq!(dashed_segments, 2, "expected two dashes in a cell");
+}
@@ -0,0 +1,59 @@
+// Compares throughput of the mutex channel against the lock free single producer single consumer channel
+// Run with: cargo bench -p circular_buffer_channel
+use circular_buffer_channel::{channel, spsc_channel};
+use std::time::{Duration, Instant};
+
+const TOTAL_BYTES: usize = 1 << 30;
+const CHUNK_SIZES: [usize; 3] = [64, 4096, 65536];
+
+fn print_result(name: &str, chunk_size: usize, elapsed: Duration) {
+    let throughput = TOTAL_BYTES as f64 / elapsed.as_secs_f64() / (1024.0*1024.0);
+    println!("{:<8} chunk={:<6} {:>10.2} MiB/s", name, chunk_size, throughput);
+}
+
+fn bench_channel(chunk_size: usize) -> Duration {
+    let (tx, rx) = channel::<u8>(0).unwrap();
+    let start = Instant::now();
+    let rx_thread = std::thread::spawn(move || {
+        let mut rx_buf = vec![0u8; chunk_size];
+        let mut total = 0;
+        while let Ok(length) = rx.receive(rx_buf.as_mut_slice()) {
+            total += length;
+        }
+        total
+    });
+    let tx_buf = vec![0u8; chunk_size];
+    for _ in 0..(TOTAL_BYTES/chunk_size) {
+        tx.send_all(tx_buf.as_slice()).unwrap();
+    }
+    drop(tx);
+    assert_eq!(rx_thread.join().unwrap(), TOTAL_BYTES);
+    start.elapsed()
+}
+
+fn bench_spsc_channel(chunk_size: usize) -> Duration {
+    let (tx, rx) = spsc_channel::<u8>(0).unwrap();
+    let start = Instant::now();
+    let rx_thread = std::thread::spawn(move || {
+        let mut rx_buf = vec![0u8; chunk_size];
+        let mut total = 0;
+        while let Ok(length) = rx.receive(rx_buf.as_mut_slice()) {
+            total += length;
+        }
+        total
+    });
+    let tx_buf = vec![0u8; chunk_size];
+    for _ in 0..(TOTAL_BYTES/chunk_size) {
+        tx.send_all(tx_buf.as_slice()).unwrap();
+    }
+    drop(tx);
+    assert_eq!(rx_thread.join().unwrap(), TOTAL_BYTES);
+    start.elapsed()
+}
+
+fn main() {
+    for chunk_size in CHUNK_SIZES {
+        print_result("mutex", chunk_size, bench_channel(chunk_size));
+        print_result("spsc", chunk_size, bench_spsc_channel(chunk_size));
+    }
+}
@@ -182,14 +182,19 @@ fn lowest_common_multiple(a: usize, b: usize) -> usize {
 #[derive(Debug,Clone)]
 pub struct Channel<T>(Arc<Core<T>>);
 
+// Smallest number of elements that is at least the minimum size and fits the allocation granularity
+pub(crate) fn get_aligned_size<T>(minimum_size: usize) -> usize {
+    let allocation_granularity = get_allocation_granularity();
+    let elem_size = std::mem::size_of::<T>();
+    let channel_size_bytes = lowest_common_multiple(allocation_granularity, elem_size);
+    let total_elements_aligned = channel_size_bytes / elem_size;
+    let multiple = minimum_size.div_ceil(total_elements_aligned).max(1);
+    multiple * total_elements_aligned
+}
+
 impl<T: Clone + Sized + Default> Channel<T> {
     pub fn new(minimum_size: usize) -> Result<Self, CreateError> {
-        let allocation_granularity = get_allocation_granularity();
-        let elem_size = std::mem::size_of::<T>();
-        let channel_size_bytes = lowest_common_multiple(allocation_granularity, elem_size);
-        let total_elements_aligned = channel_size_bytes / elem_size;
-        let multiple = minimum_size.div_ceil(total_elements_aligned).max(1);
-        let size = multiple * total_elements_aligned;
+        let size = get_aligned_size::<T>(minimum_size);
         let buffer = CircularBuffer::<T>::new(size)?;
         let length = buffer.len();
         let buffer = Buffer {
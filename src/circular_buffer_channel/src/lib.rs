@@ -1,4 +1,5 @@
 mod channel;
+mod spsc;
 
 pub use channel::{
     channel,
@@ -9,6 +10,13 @@ pub use channel::{
     ReceiveError,
 };
 
+pub use spsc::{
+    spsc_channel,
+    SpscChannel,
+    SpscSender,
+    SpscReceiver,
+};
+
 #[cfg(test)]
 mod test {
     use crate::{Channel, SendError, ReceiveError};
@@ -304,4 +312,118 @@ mod test {
         }
         tx_threads.iter_mut().for_each(|thread| thread.take().unwrap().join().unwrap());
     }
+
+    mod spsc {
+        use crate::{spsc_channel, SendError, ReceiveError};
+        use test_log::test;
+
+        #[test]
+        fn simple() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            let total = tx.get_channel().size();
+
+            let mut tx_buf = vec![0u32; total];
+            let mut rx_buf = vec![0u32; total];
+            tx_buf.iter_mut().enumerate().for_each(|(i,v)| {
+                *v = i as u32;
+            });
+            tx.send_all(tx_buf.as_slice()).unwrap();
+            rx.receive_all(rx_buf.as_mut_slice()).unwrap();
+            assert!(tx_buf.as_slice() == rx_buf.as_slice());
+        }
+
+        #[test]
+        fn with_receiver_in_thread() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            let total = tx.get_channel().size();
+
+            let rx_thread = std::thread::spawn(move || {
+                let mut rx_buf = vec![0u32; total];
+                rx.receive_all(rx_buf.as_mut_slice()).unwrap();
+                let is_equal = rx_buf.iter().enumerate().all(|(i,v)| {
+                    *v == i as u32
+                });
+                is_equal
+            });
+
+            let mut tx_buf = vec![0u32; total];
+            tx_buf.iter_mut().enumerate().for_each(|(i,v)| {
+                *v = i as u32;
+            });
+            tx.send_all(tx_buf.as_slice()).unwrap();
+
+            let is_equal = rx_thread.join().unwrap();
+            assert!(is_equal);
+        }
+
+        #[test]
+        fn with_send_larger_than_buffer() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            let total = tx.get_channel().size()*10;
+
+            let rx_thread = std::thread::spawn(move || {
+                let mut rx_buf = vec![0u32; total];
+                // odd sized chunks so that reads cross the end of the buffer
+                for chunk in rx_buf.as_mut_slice().chunks_mut(37) {
+                    rx.receive_all(chunk).unwrap();
+                }
+                let is_equal = rx_buf.iter().enumerate().all(|(i,v)| {
+                    *v == i as u32
+                });
+                is_equal
+            });
+
+            let mut tx_buf = vec![0u32; total];
+            tx_buf.iter_mut().enumerate().for_each(|(i,v)| {
+                *v = i as u32;
+            });
+            for chunk in tx_buf.as_slice().chunks(53) {
+                tx.send_all(chunk).unwrap();
+            }
+
+            let is_equal = rx_thread.join().unwrap();
+            assert!(is_equal);
+        }
+
+        #[test]
+        fn close_on_no_receiver() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            drop(rx);
+            let res = tx.send(&[10]);
+            assert!(res == Err(SendError::Closed));
+        }
+
+        #[test]
+        fn close_on_no_sender() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            drop(tx);
+            let mut buffer: [u32;1] = [0u32;1];
+            let res = rx.receive(buffer.as_mut_slice());
+            assert!(res == Err(ReceiveError::Closed));
+        }
+
+        #[test]
+        fn receive_remaining_after_close() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            tx.send_all(&[1,2,3]).unwrap();
+            drop(tx);
+            let mut buffer = [0u32;4];
+            assert_eq!(rx.receive(buffer.as_mut_slice()), Ok(3));
+            assert_eq!(&buffer[..3], &[1,2,3]);
+            assert_eq!(rx.receive(buffer.as_mut_slice()), Err(ReceiveError::Closed));
+        }
+
+        #[test]
+        fn blocked_sender_wakes_on_close() {
+            let (tx, rx) = spsc_channel::<u32>(0).unwrap();
+            let total = tx.get_channel().size()*2;
+            let tx_thread = std::thread::spawn(move || {
+                let tx_buf = vec![0u32; total];
+                tx.send_all(tx_buf.as_slice())
+            });
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            drop(rx);
+            assert_eq!(tx_thread.join().unwrap(), Err(SendError::Closed));
+        }
+    }
 }
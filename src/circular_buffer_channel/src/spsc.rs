@@ -0,0 +1,206 @@
+use circular_buffer::{CircularBuffer, CreateError};
+use std::sync::{Arc,Mutex,Condvar};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, fence};
+use crate::channel::{SendError, ReceiveError, get_aligned_size};
+
+// Lock free ring buffer for a single sender and a single receiver
+// - Indices are in the range [0,2*length) so that a full buffer can be told apart from an empty one
+// - The mutex is only taken when one side has to block waiting for the other
+#[derive(Debug)]
+pub struct SpscChannel<T> {
+    data: CircularBuffer<T>,
+    length: usize,
+    write_index: AtomicUsize,
+    read_index: AtomicUsize,
+    is_sender_closed: AtomicBool,
+    is_receiver_closed: AtomicBool,
+    is_sender_waiting: AtomicBool,
+    is_receiver_waiting: AtomicBool,
+    wait_lock: Mutex<()>,
+    signal_sender: Condvar,
+    signal_receiver: Condvar,
+}
+
+unsafe impl<T: Send> Send for SpscChannel<T> {}
+unsafe impl<T: Send> Sync for SpscChannel<T> {}
+
+impl<T: Clone + Sized + Default> SpscChannel<T> {
+    fn new(minimum_size: usize) -> Result<Self, CreateError> {
+        let size = get_aligned_size::<T>(minimum_size);
+        let data = CircularBuffer::<T>::new(size)?;
+        let length = data.len();
+        Ok(Self {
+            data,
+            length,
+            write_index: AtomicUsize::new(0),
+            read_index: AtomicUsize::new(0),
+            is_sender_closed: AtomicBool::new(false),
+            is_receiver_closed: AtomicBool::new(false),
+            is_sender_waiting: AtomicBool::new(false),
+            is_receiver_waiting: AtomicBool::new(false),
+            wait_lock: Mutex::new(()),
+            signal_sender: Condvar::new(),
+            signal_receiver: Condvar::new(),
+        })
+    }
+}
+
+impl<T> SpscChannel<T> {
+    pub fn size(&self) -> usize {
+        self.length
+    }
+
+    // Sender and receiver only ever access disjoint regions of the buffer
+    fn get_data_ptr(&self) -> *mut T {
+        self.data.as_ptr() as *mut T
+    }
+
+    fn get_total_used(&self, write_index: usize, read_index: usize) -> usize {
+        (write_index + 2*self.length - read_index) % (2*self.length)
+    }
+
+    fn advance_index(&self, index: usize, total: usize) -> usize {
+        (index + total) % (2*self.length)
+    }
+
+    // Publishes the new index and wakes up the other side if it is blocked
+    fn publish_index(&self, index: &AtomicUsize, value: usize, is_waiting: &AtomicBool, signal: &Condvar) {
+        index.store(value, Ordering::Release);
+        // pairs with the fence in wait_until so that either we see the waiting flag or they see the new index
+        fence(Ordering::SeqCst);
+        if is_waiting.load(Ordering::Relaxed) {
+            let _lock = self.wait_lock.lock();
+            signal.notify_one();
+        }
+    }
+
+    fn wait_until(&self, is_waiting: &AtomicBool, signal: &Condvar, is_ready: impl Fn() -> bool) -> Result<(), ()> {
+        let mut lock = self.wait_lock.lock().map_err(|_| ())?;
+        is_waiting.store(true, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        while !is_ready() {
+            lock = signal.wait(lock).map_err(|_| ())?;
+        }
+        is_waiting.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn close(&self, is_closed: &AtomicBool, signal: &Condvar) {
+        is_closed.store(true, Ordering::Release);
+        fence(Ordering::SeqCst);
+        match self.wait_lock.lock() {
+            Ok(_lock) => signal.notify_all(),
+            Err(err) => log::error!("Failed to acquire channel: {:?}", err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SpscSender<T>(Arc<SpscChannel<T>>);
+
+impl<T: Sized + Copy> SpscSender<T> {
+    pub fn send(&self, src_buf: &[T]) -> Result<usize, SendError> {
+        let channel = &self.0;
+        // only the sender modifies the write index
+        let write_index = channel.write_index.load(Ordering::Relaxed);
+        let is_full = || channel.get_total_used(write_index, channel.read_index.load(Ordering::Acquire)) == channel.length;
+        let is_closed = || channel.is_receiver_closed.load(Ordering::Acquire);
+        if is_full() && !is_closed() {
+            channel
+                .wait_until(&channel.is_sender_waiting, &channel.signal_sender, || !is_full() || is_closed())
+                .map_err(|_| SendError::Poisoned)?;
+        }
+        if is_closed() {
+            return Err(SendError::Closed);
+        }
+        let read_index = channel.read_index.load(Ordering::Acquire);
+        let total_unused = channel.length - channel.get_total_used(write_index, read_index);
+        let total_send = src_buf.len().min(total_unused);
+        // buffer is mirrored so writes past the end wrap around to the start
+        unsafe {
+            let dst = channel.get_data_ptr().add(write_index % channel.length);
+            std::ptr::copy_nonoverlapping(src_buf.as_ptr(), dst, total_send);
+        }
+        let write_index = channel.advance_index(write_index, total_send);
+        channel.publish_index(&channel.write_index, write_index, &channel.is_receiver_waiting, &channel.signal_receiver);
+        Ok(total_send)
+    }
+
+    pub fn send_all(&self, mut src_buf: &[T]) -> Result<(), SendError> {
+        while !src_buf.is_empty() {
+            let total_send = self.send(src_buf)?;
+            src_buf = &src_buf[total_send..];
+        }
+        Ok(())
+    }
+}
+
+impl<T> SpscSender<T> {
+    pub fn get_channel(&self) -> &SpscChannel<T> {
+        &self.0
+    }
+}
+
+impl<T> Drop for SpscSender<T> {
+    fn drop(&mut self) {
+        self.0.close(&self.0.is_sender_closed, &self.0.signal_receiver);
+    }
+}
+
+#[derive(Debug)]
+pub struct SpscReceiver<T>(Arc<SpscChannel<T>>);
+
+impl<T: Sized + Copy> SpscReceiver<T> {
+    pub fn receive(&self, dst_buf: &mut [T]) -> Result<usize, ReceiveError> {
+        let channel = &self.0;
+        // only the receiver modifies the read index
+        let read_index = channel.read_index.load(Ordering::Relaxed);
+        let is_empty = || channel.get_total_used(channel.write_index.load(Ordering::Acquire), read_index) == 0;
+        let is_closed = || channel.is_sender_closed.load(Ordering::Acquire);
+        if is_empty() && !is_closed() {
+            channel
+                .wait_until(&channel.is_receiver_waiting, &channel.signal_receiver, || !is_empty() || is_closed())
+                .map_err(|_| ReceiveError::Poisoned)?;
+        }
+        // data sent before closing is still received
+        let write_index = channel.write_index.load(Ordering::Acquire);
+        let total_used = channel.get_total_used(write_index, read_index);
+        if total_used == 0 {
+            return Err(ReceiveError::Closed);
+        }
+        let total_receive = dst_buf.len().min(total_used);
+        unsafe {
+            let src = channel.get_data_ptr().add(read_index % channel.length) as *const T;
+            std::ptr::copy_nonoverlapping(src, dst_buf.as_mut_ptr(), total_receive);
+        }
+        let read_index = channel.advance_index(read_index, total_receive);
+        channel.publish_index(&channel.read_index, read_index, &channel.is_sender_waiting, &channel.signal_sender);
+        Ok(total_receive)
+    }
+
+    pub fn receive_all(&self, mut dst_buf: &mut [T]) -> Result<(), ReceiveError> {
+        while !dst_buf.is_empty() {
+            let total_receive = self.receive(dst_buf)?;
+            dst_buf = &mut dst_buf[total_receive..];
+        }
+        Ok(())
+    }
+}
+
+impl<T> SpscReceiver<T> {
+    pub fn get_channel(&self) -> &SpscChannel<T> {
+        &self.0
+    }
+}
+
+impl<T> Drop for SpscReceiver<T> {
+    fn drop(&mut self) {
+        self.0.close(&self.0.is_receiver_closed, &self.0.signal_sender);
+    }
+}
+
+// Like channel but the sender and receiver cannot be cloned
+pub fn spsc_channel<T: Clone + Sized + Default>(size_hint: usize) -> Result<(SpscSender<T>, SpscReceiver<T>), CreateError> {
+    let channel = Arc::new(SpscChannel::<T>::new(size_hint)?);
+    Ok((SpscSender(channel.clone()), SpscReceiver(channel)))
+}
@@ -21,6 +21,7 @@ use windows::{
         },
     },
 };
+use std::collections::BTreeMap;
 use std::process::Command;
 use std::time::Duration;
 use std::ffi::{OsString, OsStr};
@@ -174,6 +175,38 @@ pub enum WaitError {
     FailedGetExitCode(windows::core::Error),
 }
 
+// Windows treats variable names case insensitively so Path and PATH are the same variable
+// Block is sorted by uppercase name as required by CreateProcessW and ends with an extra null terminator
+pub fn create_environment_block<'a>(
+    inherited: impl Iterator<Item = (OsString, OsString)>,
+    overrides: impl Iterator<Item = (&'a OsStr, Option<&'a OsStr>)>,
+) -> Vec<u16> {
+    let get_sort_key = |key: &OsStr| key.to_string_lossy().to_uppercase();
+    let mut variables: BTreeMap<String, (OsString, OsString)> = BTreeMap::new();
+    for (key, value) in inherited {
+        variables.insert(get_sort_key(key.as_os_str()), (key, value));
+    }
+    for (key, value) in overrides {
+        match value {
+            Some(value) => variables.insert(get_sort_key(key), (key.to_owned(), value.to_owned())),
+            None => variables.remove(&get_sort_key(key)),
+        };
+    }
+    let mut environment = Vec::<u16>::new();
+    for (key, value) in variables.into_values() {
+        environment.extend(key.encode_wide());
+        environment.extend("=".encode_utf16());
+        environment.extend(value.encode_wide());
+        environment.push(0);
+    }
+    // empty block still needs both terminators
+    if environment.is_empty() {
+        environment.push(0);
+    }
+    environment.push(0);
+    environment
+}
+
 fn osstr_to_wchar(string: &OsStr) -> Vec<u16> {
     string
         .encode_wide()
@@ -183,8 +216,8 @@ fn osstr_to_wchar(string: &OsStr) -> Vec<u16> {
 
 #[derive(Debug,Clone,Copy,Default,PartialEq,Eq)]
 pub struct ConptyProcessBuilder {
-    window_size: Size,
-    buffer_size: Option<u32>,
+    pub window_size: Size,
+    pub buffer_size: Option<u32>,
 }
 
 impl ConptyProcess {
@@ -215,22 +248,12 @@ impl ConptyProcess {
         let mut command_line = osstr_to_wchar(command_line.as_os_str());
         let current_directory = command.get_current_dir().map(|dir| osstr_to_wchar(dir.as_os_str()));
         // environment string encoded as unicode
-        let mut environment = Vec::<u16>::new();
-        for (key, value) in command.get_envs() {
-            let Some(value) = value else {
-                continue;
-            };
-            environment.extend(key.encode_wide());
-            environment.extend("=".encode_utf16());
-            environment.extend(value.encode_wide());
-            environment.push(0);
-        }
-        // inherit parent process environment if no environment available
-        let environment = if environment.is_empty() {
-            None
+        // variables set on the command are added to the parent environment like std::process::Command
+        // inherit parent process environment if no variables are set
+        let environment = if command.get_envs().next().is_some() {
+            Some(create_environment_block(std::env::vars_os(), command.get_envs()))
         } else {
-            environment.push(0); // terminate environment block
-            Some(&environment)
+            None
         };
         creation_flags |= CREATE_UNICODE_ENVIRONMENT;
 
@@ -252,7 +275,7 @@ impl ConptyProcess {
                 thread_attributes,
                 inherit_handles,
                 creation_flags,
-                environment.map(|buf| buf.as_ptr() as _),
+                environment.as_ref().map(|buf| buf.as_ptr() as _),
                 PCWSTR(current_directory.as_ref().map_or(std::ptr::null(), |buf| buf.as_ptr())),
                 &startup_info.StartupInfo,
                 &mut process_info,
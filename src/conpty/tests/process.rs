@@ -1,7 +1,7 @@
 #![cfg(windows)]
 use std::io::{Read,Write};
 use test_log::test;
-use conpty::process::{ConptyProcess, SpawnError, Size as ProcessSize};
+use conpty::process::{ConptyProcess, SpawnError, Size as ProcessSize, create_environment_block};
 use std::process::Command;
 
 fn assert_exit_code(given: u32, expected: u32) {
@@ -107,3 +107,25 @@ fn resize_console() {
     assert_exit_code(exit_code, 0);
     assert!(!read_buffer.is_empty());
 }
+
+#[test]
+fn environment_block_ignores_case() {
+    use std::ffi::{OsStr, OsString};
+    let inherited = [
+        (OsString::from("windir"), OsString::from("C:\\Windows")),
+        (OsString::from("Path"), OsString::from("C:\\old")),
+        (OsString::from("TEMP"), OsString::from("C:\\Temp")),
+        (OsString::from("_underscore"), OsString::from("1")),
+    ];
+    let overrides = [
+        (OsStr::new("PATH"), Some(OsStr::new("C:\\new"))),
+        (OsStr::new("temp"), None),
+        (OsStr::new("TERM"), Some(OsStr::new("xterm-256color"))),
+    ];
+    let block = create_environment_block(inherited.into_iter(), overrides.into_iter());
+    // variables are null separated and sorted by uppercase name with a trailing null
+    let block = String::from_utf16(block.as_slice()).unwrap();
+    assert_eq!(block, "PATH=C:\\new\0TERM=xterm-256color\0windir=C:\\Windows\0_underscore=1\0\0");
+    let block = create_environment_block(std::iter::empty(), std::iter::empty());
+    assert_eq!(block, vec![0, 0]);
+}
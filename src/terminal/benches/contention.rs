@@ -0,0 +1,77 @@
+// Compares how much a renderer redrawing as fast as possible slows down the parser thread
+// Rendering from the locked display stalls the parser while rendering from the front buffer only waits for swaps
+// Run with: cargo bench -p terminal --bench contention
+use terminal::{Terminal, TerminalBuilder, TerminalSettings};
+use terminal::terminal_renderer::TerminalRenderer;
+use cgmath::Vector2;
+use std::time::{Duration, Instant};
+
+const TOTAL_BYTES: usize = 64 << 20;
+const READ_SIZE: usize = 8192;
+const GRID_SIZE: Vector2<usize> = Vector2::new(200,50);
+
+#[derive(Clone,Copy,Debug)]
+enum RenderMode {
+    Idle,
+    Display,
+    FrontBuffer,
+}
+
+// Mostly printable text with colour changes and newlines like the output of a build log
+fn create_file_contents() -> Vec<u8> {
+    let line = b"\x1b[32mcompiling\x1b[0m crate v0.1.0 (/home/user/project/src/some/long/path/to/a/file.rs)\r\n";
+    line.iter().copied().cycle().take(TOTAL_BYTES).collect()
+}
+
+// Returns the time taken to parse everything and the number of frames rendered meanwhile
+fn bench_render_mode(data: &[u8], mode: RenderMode) -> (Duration, usize) {
+    let mut chunks: Vec<Vec<u8>> = data.chunks(READ_SIZE).map(|chunk| chunk.to_vec()).collect();
+    chunks.reverse();
+    let (resize_tx, resize_rx) = std::sync::mpsc::channel();
+    resize_tx.send(GRID_SIZE).unwrap();
+    let mut renderer = TerminalRenderer::default();
+    let start = Instant::now();
+    let mut terminal = Terminal::new(TerminalBuilder {
+        process_read: Box::new(move |buf: &mut [u8]| {
+            let Some(chunk) = chunks.pop() else {
+                return 0;
+            };
+            buf[..chunk.len()].copy_from_slice(chunk.as_slice());
+            chunk.len()
+        }),
+        process_write: Box::new(|_| {}),
+        process_ioctl: Box::new(|_| {}),
+        window_action: Box::new(|_| {}),
+        is_newline_carriage_return: false,
+        settings: TerminalSettings::default(),
+        record_output: None,
+        process_resize: Some(resize_rx),
+        on_parser_exit: None,
+    });
+    let mut total_frames = 0;
+    while terminal.get_parser_metrics().bytes_parsed < data.len() as u64 {
+        match mode {
+            RenderMode::Idle => std::thread::sleep(Duration::from_millis(1)),
+            RenderMode::Display => renderer.render_display(&terminal.get_display()),
+            RenderMode::FrontBuffer => {
+                let is_rendered = renderer.render_front_buffer(&terminal.get_front_buffer());
+                assert!(is_rendered);
+            },
+        }
+        total_frames += 1;
+    }
+    let elapsed = start.elapsed();
+    terminal.join_parser_thread();
+    (elapsed, total_frames)
+}
+
+fn main() {
+    let data = create_file_contents();
+    println!("grid={}x{}", GRID_SIZE.x, GRID_SIZE.y);
+    for mode in [RenderMode::Idle, RenderMode::Display, RenderMode::FrontBuffer] {
+        let (elapsed, total_frames) = bench_render_mode(data.as_slice(), mode);
+        let throughput = TOTAL_BYTES as f64 / elapsed.as_secs_f64() / (1024.0*1024.0);
+        let fps = total_frames as f64 / elapsed.as_secs_f64();
+        println!("{:<24} {:>10.2} MiB/s {:>10.0} frames/s", format!("render={:?}", mode), throughput, fps);
+    }
+}
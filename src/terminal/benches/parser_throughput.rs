@@ -0,0 +1,67 @@
+// Throughput of the utf8 and vt100 parsers for common kinds of process output
+// Parsed commands are discarded so this excludes the time spent updating the display
+// Run with: cargo bench -p terminal --features bench
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use terminal::bench::{DiscardParser, create_criterion};
+
+const TOTAL_BYTES: usize = 1 << 20;
+const READ_SIZE: usize = 8192;
+
+fn repeat_to_size(pattern: &[u8]) -> Vec<u8> {
+    pattern.iter().copied().cycle().take(TOTAL_BYTES).collect()
+}
+
+// Full screen applications redraw by moving the cursor around and writing short runs of text
+fn create_cursor_movement_stream() -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..64 {
+        data.extend_from_slice(format!("\x1b[{};{}Htext", i % 24 + 1, (i*7) % 80 + 1).as_bytes());
+        data.extend_from_slice(format!("\x1b[{}A\x1b[{}B\x1b[{}C\x1b[{}D", i % 3 + 1, i % 5 + 1, i % 7 + 1, i % 11 + 1).as_bytes());
+        data.extend_from_slice(b"\x1b[K\x1b[2X");
+    }
+    repeat_to_size(data.as_slice())
+}
+
+// Colour changes before every character like the output of a colour test
+fn create_sgr_stream() -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..256 {
+        data.extend_from_slice(format!("\x1b[38;5;{}m\x1b[48;5;{}m#", i, 255-i).as_bytes());
+    }
+    data.extend_from_slice(b"\x1b[0m\r\n");
+    repeat_to_size(data.as_slice())
+}
+
+fn create_ascii_stream() -> Vec<u8> {
+    let line = b"the quick brown fox jumps over the lazy dog 0123456789 ~!@#$%^&*()_+-=[]{}|;':,./<>?\r\n";
+    repeat_to_size(line)
+}
+
+fn bench_parser(c: &mut Criterion) {
+    let streams = [
+        ("cursor_movement", create_cursor_movement_stream(), Throughput::Bytes(TOTAL_BYTES as u64)),
+        ("sgr_256_colour", create_sgr_stream(), Throughput::Bytes(TOTAL_BYTES as u64)),
+        // every byte is a character
+        ("ascii", create_ascii_stream(), Throughput::Elements(TOTAL_BYTES as u64)),
+    ];
+    let mut group = c.benchmark_group("parser_throughput");
+    for (name, data, throughput) in streams {
+        group.throughput(throughput);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &data, |b, data| {
+            let mut parser = DiscardParser::default();
+            b.iter(|| {
+                for chunk in data.chunks(READ_SIZE) {
+                    parser.parse_bytes(chunk);
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = create_criterion();
+    targets = bench_parser
+}
+criterion_main!(benches);
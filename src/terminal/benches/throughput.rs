@@ -0,0 +1,81 @@
+// Compares parsing on the thread that reads from the process against separate reader and parser threads
+// Output of a large file being catted is simulated by reading from memory with an optional delay per read
+// Run with: cargo bench -p terminal --features bench --bench throughput
+use terminal::{Terminal, TerminalBuilder, TerminalSettings};
+use terminal::bench::parse_inline;
+use std::time::{Duration, Instant};
+
+const TOTAL_BYTES: usize = 64 << 20;
+const READ_SIZE: usize = 8192;
+
+// Mostly printable text with colour changes and newlines like the output of a build log
+fn create_file_contents() -> Vec<u8> {
+    let line = b"\x1b[32mcompiling\x1b[0m crate v0.1.0 (/home/user/project/src/some/long/path/to/a/file.rs)\r\n";
+    line.iter().copied().cycle().take(TOTAL_BYTES).collect()
+}
+
+fn print_result(name: &str, elapsed: Duration) {
+    let throughput = TOTAL_BYTES as f64 / elapsed.as_secs_f64() / (1024.0*1024.0);
+    println!("{:<24} {:>10.2} MiB/s", name, throughput);
+}
+
+fn create_terminal(process_read: Box<dyn FnMut(&mut [u8]) -> usize + Send>) -> Terminal {
+    Terminal::new(TerminalBuilder {
+        process_read,
+        process_write: Box::new(|_| {}),
+        process_ioctl: Box::new(|_| {}),
+        window_action: Box::new(|_| {}),
+        is_newline_carriage_return: false,
+        settings: TerminalSettings::default(),
+        record_output: None,
+        process_resize: None,
+        on_parser_exit: None,
+    })
+}
+
+// Each read is parsed before the next read like the parser thread used to do
+fn bench_inline(data: &[u8], read_delay: Duration) -> Duration {
+    let mut terminal = create_terminal(Box::new(|_| 0));
+    terminal.join_parser_thread();
+    let mut chunks = data.chunks(READ_SIZE);
+    let start = Instant::now();
+    parse_inline(&mut terminal, |buf: &mut [u8]| {
+        let Some(chunk) = chunks.next() else {
+            return 0;
+        };
+        if !read_delay.is_zero() {
+            std::thread::sleep(read_delay);
+        }
+        buf[..chunk.len()].copy_from_slice(chunk);
+        chunk.len()
+    });
+    start.elapsed()
+}
+
+fn bench_threaded(data: &[u8], read_delay: Duration) -> Duration {
+    let mut chunks: Vec<Vec<u8>> = data.chunks(READ_SIZE).map(|chunk| chunk.to_vec()).collect();
+    chunks.reverse();
+    let start = Instant::now();
+    let mut terminal = create_terminal(Box::new(move |buf: &mut [u8]| {
+        let Some(chunk) = chunks.pop() else {
+            return 0;
+        };
+        if !read_delay.is_zero() {
+            std::thread::sleep(read_delay);
+        }
+        buf[..chunk.len()].copy_from_slice(chunk.as_slice());
+        chunk.len()
+    }));
+    terminal.join_parser_thread();
+    start.elapsed()
+}
+
+fn main() {
+    let data = create_file_contents();
+    // a process that writes in bursts is only slowed down by parsing when they share a thread
+    for read_delay in [Duration::ZERO, Duration::from_micros(50)] {
+        let suffix = format!("delay={}us", read_delay.as_micros());
+        print_result(format!("inline {}", suffix).as_str(), bench_inline(data.as_slice(), read_delay));
+        print_result(format!("threaded {}", suffix).as_str(), bench_threaded(data.as_slice(), read_delay));
+    }
+}
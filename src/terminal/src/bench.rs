@@ -0,0 +1,54 @@
+// Shared by the criterion benchmarks of this crate and the demo
+use crate::terminal::Terminal;
+use crate::terminal_parser::{TerminalParser, TerminalParserHandler};
+use crate::utf8_parser::ParserError as Utf8ParserError;
+use criterion::Criterion;
+use vt100::command::Command as Vt100Command;
+use vt100::parser::{Parser as Vt100Parser, ParserError as Vt100ParserError};
+use std::time::Duration;
+
+// Small changes are treated as noise so that only regressions past the ci threshold are reported
+pub fn create_criterion() -> Criterion {
+    Criterion::default()
+        .noise_threshold(0.02)
+        .significance_level(0.05)
+        .measurement_time(Duration::from_secs(5))
+}
+
+struct DiscardHandler;
+
+impl TerminalParserHandler for DiscardHandler {
+    fn on_unhandled_byte(&mut self, _byte: u8) {}
+    fn on_ascii_data(&mut self, _buf: &[u8]) {}
+    fn on_utf8(&mut self, _character: char) {}
+    fn on_utf8_error(&mut self, _error: &Utf8ParserError) {}
+    fn on_vt100(&mut self, _command: Vt100Command) {}
+    fn on_vt100_error(&mut self, _error: Vt100ParserError, _parser: &Vt100Parser) {}
+}
+
+// Utf8 and vt100 parsers without a display so only parsing is measured
+#[derive(Default)]
+pub struct DiscardParser {
+    parser: TerminalParser,
+}
+
+impl DiscardParser {
+    pub fn parse_bytes(&mut self, data: &[u8]) {
+        self.parser.parse_bytes(data, &mut DiscardHandler);
+    }
+}
+
+// Reads then parses on the calling thread like the parser thread did before reads were moved to their own thread
+pub fn parse_inline(terminal: &mut Terminal, mut process_read: impl FnMut(&mut [u8]) -> usize) {
+    let mut parser_handler = terminal.create_parser_handler();
+    let mut terminal_parser = TerminalParser::default();
+    let mut buffer = vec![0u8; 8192];
+    loop {
+        let total_read = process_read(buffer.as_mut_slice());
+        if total_read == 0 {
+            break;
+        }
+        terminal_parser.parse_bytes(&buffer[..total_read], &mut parser_handler);
+        terminal.publish_display();
+    }
+}
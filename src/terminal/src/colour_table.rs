@@ -61,3 +61,18 @@ pub fn convert_u32_to_rgb(v: u32) -> Rgb8 {
         b: b as u8,
     }
 }
+
+pub fn get_default_palette() -> [Rgb8; 16] {
+    let mut palette = [Rgb8::default(); 16];
+    for (dst, src) in palette.iter_mut().zip(XTERM_COLOUR_TABLE.iter()) {
+        *dst = convert_u32_to_rgb(*src);
+    }
+    palette
+}
+
+// Only the 16 base colours come from the palette since the colour cube and greyscale ramp are standard
+pub fn create_colour_table(palette: &[Rgb8; 16]) -> [Rgb8; 256] {
+    let mut table = XTERM_COLOUR_TABLE.map(convert_u32_to_rgb);
+    table[..16].copy_from_slice(palette);
+    table
+}
@@ -0,0 +1,84 @@
+use vt100::common::Rgb8;
+
+// https://www.w3.org/TR/WCAG21/#dfn-relative-luminance
+fn get_relative_luminance(colour: Rgb8) -> f32 {
+    let linearise = |v: u8| -> f32 {
+        let v = v as f32 / 255.0;
+        if v <= 0.03928 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126*linearise(colour.r) + 0.7152*linearise(colour.g) + 0.0722*linearise(colour.b)
+}
+
+// https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+pub(crate) fn get_contrast_ratio(a: Rgb8, b: Rgb8) -> f32 {
+    let a = get_relative_luminance(a);
+    let b = get_relative_luminance(b);
+    let (lighter, darker) = if a > b { (a, b) } else { (b, a) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+fn lerp_colour(src: Rgb8, dst: Rgb8, step: i32, total_steps: i32) -> Rgb8 {
+    let lerp = |a: u8, b: u8| -> u8 {
+        let (a, b) = (a as i32, b as i32);
+        (a + (b-a)*step/total_steps) as u8
+    };
+    Rgb8 {
+        r: lerp(src.r, dst.r),
+        g: lerp(src.g, dst.g),
+        b: lerp(src.b, dst.b),
+    }
+}
+
+// Smallest step towards the target that stays on the target's side of the background and meets the ratio
+// Contrast only increases with each step once past the background so the search is monotonic
+fn find_minimum_step(foreground: Rgb8, background: Rgb8, target: Rgb8, minimum_ratio: f32, total_steps: i32) -> Option<i32> {
+    let background_luminance = get_relative_luminance(background);
+    let is_lighter = get_relative_luminance(target) >= background_luminance;
+    let is_valid = |step: i32| -> bool {
+        let colour = lerp_colour(foreground, target, step, total_steps);
+        let luminance = get_relative_luminance(colour);
+        let is_past_background = if is_lighter { luminance >= background_luminance } else { luminance <= background_luminance };
+        is_past_background && get_contrast_ratio(colour, background) >= minimum_ratio
+    };
+    if !is_valid(total_steps) {
+        return None;
+    }
+    // binary search for smallest step that satisfies the ratio
+    let mut lower = 0;
+    let mut upper = total_steps;
+    while lower < upper {
+        let middle = (lower + upper) / 2;
+        if is_valid(middle) {
+            upper = middle;
+        } else {
+            lower = middle+1;
+        }
+    }
+    Some(lower)
+}
+
+// Moves foreground towards black or white until the minimum contrast ratio is met
+// Lightening and darkening are searched separately and the smaller change is used
+pub(crate) fn apply_minimum_contrast(foreground: Rgb8, background: Rgb8, minimum_ratio: f32) -> Rgb8 {
+    if get_contrast_ratio(foreground, background) >= minimum_ratio {
+        return foreground;
+    }
+    const WHITE: Rgb8 = Rgb8 { r: 255, g: 255, b: 255 };
+    const BLACK: Rgb8 = Rgb8 { r: 0, g: 0, b: 0 };
+    const TOTAL_STEPS: i32 = 256;
+    let lighten = find_minimum_step(foreground, background, WHITE, minimum_ratio, TOTAL_STEPS);
+    let darken = find_minimum_step(foreground, background, BLACK, minimum_ratio, TOTAL_STEPS);
+    match (lighten, darken) {
+        (Some(lighten), Some(darken)) if darken < lighten => lerp_colour(foreground, BLACK, darken, TOTAL_STEPS),
+        (Some(lighten), _) => lerp_colour(foreground, WHITE, lighten, TOTAL_STEPS),
+        (None, Some(darken)) => lerp_colour(foreground, BLACK, darken, TOTAL_STEPS),
+        // ratio is unreachable so use whichever extreme has the most contrast
+        (None, None) => {
+            if get_contrast_ratio(WHITE, background) >= get_contrast_ratio(BLACK, background) { WHITE } else { BLACK }
+        },
+    }
+}
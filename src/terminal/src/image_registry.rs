@@ -0,0 +1,179 @@
+use base64::Engine;
+use cgmath::{Vector2, ElementWise};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use thiserror::Error;
+use vt100::common::{ImageDimension, InlineImage};
+
+// Larger files are rejected before they are decoded
+pub const MAX_IMAGE_FILE_BYTES: usize = 16*1024*1024;
+// Oldest images are freed once the decoded pixels of every image exceed this
+pub const MAX_IMAGE_MEMORY_BYTES: usize = 256*1024*1024;
+
+#[derive(Debug,Error)]
+pub enum ImageError {
+    #[error("image is {0} bytes which is over the limit of {} bytes", MAX_IMAGE_FILE_BYTES)]
+    FileTooLarge(usize),
+    #[error("invalid base64 data: {0}")]
+    InvalidBase64(#[from] base64::DecodeError),
+    #[error("failed to decode image: {0}")]
+    InvalidImage(#[from] image::ImageError),
+    #[error("image has no pixels")]
+    Empty,
+}
+
+// Rgba8 pixels already scaled to the cells the image covers
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct DecodedImage {
+    pub size: Vector2<usize>,
+    pub pixels: Vec<u8>,
+}
+
+// Image drawn over the cells starting from a column of a logical line
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct ImagePlacement {
+    // unique across displays so renderers can cache uploaded images
+    pub id: u64,
+    // counted from the oldest line in the scrollback buffer like prompt marks
+    pub line: usize,
+    // rows at the top of the image whose lines were evicted from the scrollback buffer
+    pub clipped_rows: usize,
+    pub column: usize,
+    pub cells: Vector2<usize>,
+    pub image: Arc<DecodedImage>,
+}
+
+fn resolve_dimension(dimension: ImageDimension, cell_size: usize, grid_size: usize) -> Option<usize> {
+    match dimension {
+        ImageDimension::Auto => None,
+        ImageDimension::Cells(n) => Some(n as usize*cell_size),
+        ImageDimension::Pixels(n) => Some(n as usize),
+        ImageDimension::Percent(n) => Some((n as usize).min(100)*cell_size*grid_size/100),
+    }
+}
+
+// Size in pixels that the image is drawn at which is never larger than the grid
+// Auto dimensions follow the aspect ratio if the other dimension is given and the size of the file otherwise
+pub fn calculate_image_size(
+    image_size: Vector2<usize>, image: &InlineImage, cell_size: Vector2<usize>, grid_size: Vector2<usize>,
+) -> Vector2<usize> {
+    let image_size = Vector2::new(image_size.x.max(1), image_size.y.max(1));
+    let width = resolve_dimension(image.width, cell_size.x, grid_size.x);
+    let height = resolve_dimension(image.height, cell_size.y, grid_size.y);
+    let scale_to = |value: usize, from: usize, to: usize| (value*to).div_ceil(from);
+    let mut size = match (width, height) {
+        (None, None) => image_size,
+        (Some(width), None) => Vector2::new(width, scale_to(image_size.y, image_size.x, width)),
+        (None, Some(height)) => Vector2::new(scale_to(image_size.x, image_size.y, height), height),
+        (Some(width), Some(height)) if image.preserve_aspect_ratio => {
+            // fit inside the box
+            if width*image_size.y <= height*image_size.x {
+                Vector2::new(width, scale_to(image_size.y, image_size.x, width))
+            } else {
+                Vector2::new(scale_to(image_size.x, image_size.y, height), height)
+            }
+        },
+        (Some(width), Some(height)) => Vector2::new(width, height),
+    };
+    let max_size = cell_size.mul_element_wise(grid_size);
+    if size.x > max_size.x {
+        size.y = if image.preserve_aspect_ratio { scale_to(size.y, size.x, max_size.x) } else { size.y };
+        size.x = max_size.x;
+    }
+    if size.y > max_size.y {
+        size.x = if image.preserve_aspect_ratio { scale_to(size.x, size.y, max_size.y) } else { size.x };
+        size.y = max_size.y;
+    }
+    Vector2::new(size.x.max(1), size.y.max(1))
+}
+
+pub fn calculate_image_cells(size: Vector2<usize>, cell_size: Vector2<usize>) -> Vector2<usize> {
+    let cell_size = Vector2::new(cell_size.x.max(1), cell_size.y.max(1));
+    Vector2::new(size.x.div_ceil(cell_size.x), size.y.div_ceil(cell_size.y))
+}
+
+// Returns the scaled image with the number of cells it covers
+pub fn decode_inline_image(
+    image: &InlineImage, cell_size: Vector2<usize>, grid_size: Vector2<usize>,
+) -> Result<(DecodedImage, Vector2<usize>), ImageError> {
+    // base64 data can be split across lines
+    let data: Vec<u8> = image.data.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let total_bytes = data.len()/4*3;
+    if total_bytes > MAX_IMAGE_FILE_BYTES {
+        return Err(ImageError::FileTooLarge(total_bytes));
+    }
+    let file = base64::engine::general_purpose::STANDARD.decode(data)?;
+    let decoded = image::load_from_memory(file.as_slice())?;
+    let image_size = Vector2::new(decoded.width() as usize, decoded.height() as usize);
+    if image_size.x == 0 || image_size.y == 0 {
+        return Err(ImageError::Empty);
+    }
+    let size = calculate_image_size(image_size, image, cell_size, grid_size);
+    let cells = calculate_image_cells(size, cell_size);
+    // pixels are padded out to whole cells so the image isn't stretched when drawn over them
+    let cell_area = cells.mul_element_wise(cell_size);
+    let scaled = decoded.resize_exact(size.x as u32, size.y as u32, image::imageops::FilterType::Triangle).into_rgba8();
+    let mut canvas = image::RgbaImage::new(cell_area.x as u32, cell_area.y as u32);
+    image::imageops::overlay(&mut canvas, &scaled, 0, 0);
+    Ok((DecodedImage { size: cell_area, pixels: canvas.into_raw() }, cells))
+}
+
+// Ids are shared by every display so renderers drawing several terminals don't mix up their images
+static NEXT_IMAGE_ID: AtomicU64 = AtomicU64::new(0);
+
+// Images placed in the primary viewport which are shared with the renderer through the display
+#[derive(Clone,Debug,Default)]
+pub struct ImageRegistry {
+    placements: VecDeque<ImagePlacement>,
+    total_bytes: usize,
+}
+
+impl ImageRegistry {
+    // Returns the id of the placement
+    pub fn add(&mut self, line: usize, column: usize, cells: Vector2<usize>, image: DecodedImage) -> u64 {
+        let id = NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed);
+        self.total_bytes += image.pixels.len();
+        self.placements.push_back(ImagePlacement { id, line, clipped_rows: 0, column, cells, image: Arc::new(image) });
+        while self.total_bytes > MAX_IMAGE_MEMORY_BYTES && self.placements.len() > 1 {
+            self.remove_oldest();
+        }
+        id
+    }
+
+    fn remove_oldest(&mut self) {
+        if let Some(placement) = self.placements.pop_front() {
+            self.total_bytes -= placement.image.pixels.len();
+        }
+    }
+
+    // Frees images whose last line was evicted from the scrollback buffer
+    // Images are added in order of their line so the oldest images are at the front
+    pub fn evict_lines_before(&mut self, line: usize) {
+        while self.placements.front().is_some_and(|placement| placement.line + placement.cells.y <= line) {
+            self.remove_oldest();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.placements.clear();
+        self.total_bytes = 0;
+    }
+
+    pub fn get_placements(&self) -> impl Iterator<Item=&ImagePlacement> {
+        self.placements.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.placements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+
+    // Bytes used by the decoded pixels of every image
+    pub fn get_total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+}
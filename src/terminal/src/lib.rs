@@ -1,20 +1,1389 @@
 mod utf8_parser;
-mod colour_table;
+pub mod colour_table;
 pub mod scrollback_buffer;
 mod primitives;
 mod terminal_parser;
 mod viewport;
 pub mod terminal_display;
 pub mod terminal_renderer;
+pub mod url_detector;
+pub mod image_registry;
+pub mod recording;
 mod terminal;
+mod contrast;
+#[cfg(feature = "bench")]
+pub mod bench;
 
 pub use crate::terminal::{
     TerminalIOControl,
     TerminalUserEvent,
     Terminal,
     TerminalBuilder,
+    TerminalSettings,
 };
+pub use crate::terminal_parser::TerminalParserMetrics;
 pub use crate::primitives::{
     Cell,
+    Pen,
     StyleFlags,
 };
+pub use crate::viewport::LineStatus;
+pub use crate::scrollback_buffer::ScrollbackBuffer;
+
+#[cfg(test)]
+mod test {
+    use crate::terminal_display::TerminalDisplay;
+    use crate::terminal_renderer::{TerminalRenderer, RenderPosition};
+    use cgmath::Vector2;
+    use test_log::test;
+
+    const CELL_HEIGHT: usize = 8;
+
+    fn create_display_with_scrollback(size: Vector2<usize>, total_lines: usize) -> (TerminalDisplay, usize) {
+        let mut display = TerminalDisplay::default();
+        display.set_size(size);
+        let viewport = display.get_current_viewport_mut();
+        for i in 0..total_lines {
+            viewport.write_ascii(b'a' + (i % 26) as u8);
+            viewport.write_ascii(b'\r');
+            viewport.write_ascii(b'\n');
+        }
+        let total_rows = viewport.scrollback_buffer.as_ref().unwrap().get_lines().len();
+        (display, total_rows)
+    }
+
+    #[test]
+    fn smooth_scroll_across_row_boundaries() {
+        let size = Vector2::new(4,2);
+        let (display, total_rows) = create_display_with_scrollback(size, 8);
+        assert!(total_rows >= 3);
+        let mut renderer = TerminalRenderer::default();
+        renderer.render_display(&display);
+        assert_eq!(renderer.get_total_render_rows(), size.y);
+
+        renderer.scroll_up_pixels(2*CELL_HEIGHT + 3, CELL_HEIGHT);
+        assert_eq!(renderer.get_position(), RenderPosition::Floating(total_rows-3));
+        assert_eq!(renderer.get_pixel_offset(), 5);
+        // partial scroll renders an extra row
+        renderer.render_display(&display);
+        assert_eq!(renderer.get_total_render_rows(), size.y+1);
+        assert_eq!(renderer.get_cells().len(), size.x*(size.y+1));
+
+        renderer.scroll_down_pixels(4, CELL_HEIGHT);
+        assert_eq!(renderer.get_position(), RenderPosition::Floating(total_rows-2));
+        assert_eq!(renderer.get_pixel_offset(), 1);
+        renderer.scroll_down_pixels(CELL_HEIGHT-1, CELL_HEIGHT);
+        assert_eq!(renderer.get_position(), RenderPosition::Floating(total_rows-1));
+        assert_eq!(renderer.get_pixel_offset(), 0);
+        renderer.scroll_down_pixels(CELL_HEIGHT, CELL_HEIGHT);
+        assert_eq!(renderer.get_position(), RenderPosition::Bottom);
+        assert_eq!(renderer.get_pixel_offset(), 0);
+    }
+
+    #[test]
+    fn read_scrollback_lines() {
+        let (display, total_rows) = create_display_with_scrollback(Vector2::new(4,2), 5);
+        let scrollback = display.get_scrollback();
+        assert_eq!(scrollback.get_line_count(), total_rows);
+        assert_eq!(scrollback.get_line_count(), display.get_total_scrollback_lines());
+        // lines a to d were pushed out of the viewport followed by the unfinished line
+        let text: Vec<String> = (0..scrollback.get_line_count())
+            .map(|i| scrollback.get_line(i).unwrap().0.iter().map(|cell| cell.character).collect())
+            .collect();
+        assert_eq!(text, ["a", "b", "c", "d", ""]);
+        let (_, status) = scrollback.get_line(0).unwrap();
+        assert_eq!(status.length, 1);
+        assert!(status.is_linebreak);
+        let (_, status) = scrollback.get_line(total_rows-1).unwrap();
+        assert!(!status.is_linebreak);
+        assert!(scrollback.get_line(total_rows).is_none());
+    }
+
+    #[test]
+    fn smooth_scroll_clamps_at_top() {
+        let (display, _total_rows) = create_display_with_scrollback(Vector2::new(4,2), 8);
+        let mut renderer = TerminalRenderer::default();
+        renderer.render_display(&display);
+        renderer.scroll_up_pixels(10_000, CELL_HEIGHT);
+        assert_eq!(renderer.get_position(), RenderPosition::Floating(0));
+        assert_eq!(renderer.get_pixel_offset(), 0);
+        // row based scrolling snaps to row boundaries
+        renderer.scroll_down_pixels(3, CELL_HEIGHT);
+        assert_eq!(renderer.get_pixel_offset(), 3);
+        renderer.scroll_down(1);
+        assert_eq!(renderer.get_position(), RenderPosition::Floating(1));
+        assert_eq!(renderer.get_pixel_offset(), 0);
+    }
+
+    fn create_display_with_text(size: Vector2<usize>, text: &[u8]) -> TerminalDisplay {
+        let mut display = TerminalDisplay::default();
+        display.set_size(size);
+        let viewport = display.get_current_viewport_mut();
+        for &b in text {
+            viewport.write_ascii(b);
+        }
+        display
+    }
+
+    #[test]
+    fn zero_sizes_are_clamped() {
+        for size in [Vector2::new(0,0), Vector2::new(1,0), Vector2::new(0,1)] {
+            let mut display = create_display_with_text(size, b"ab\r\ncd\n");
+            assert_eq!(display.get_viewport_size(), Vector2::new(size.x.max(1), size.y.max(1)));
+            // display is still usable
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display);
+            assert!(!renderer.get_cells().is_empty());
+            display.set_size(Vector2::new(3,2));
+            let viewport = display.get_current_viewport_mut();
+            for &b in b"xy" {
+                viewport.write_ascii(b);
+            }
+            assert_eq!(viewport.get_row(0).0[0].character, 'x');
+        }
+    }
+
+    #[test]
+    fn minimum_size_is_enforced() {
+        let mut display = TerminalDisplay::default();
+        display.set_size(Vector2::new(10,5));
+        display.set_minimum_size(Vector2::new(20,2));
+        assert_eq!(display.get_viewport_size(), Vector2::new(20,5));
+        display.set_size(Vector2::new(1,1));
+        assert_eq!(display.get_viewport_size(), Vector2::new(20,2));
+        // minimum can't go below a single cell
+        display.set_minimum_size(Vector2::new(0,0));
+        assert_eq!(display.get_minimum_size(), Vector2::new(1,1));
+        display.set_size(Vector2::new(0,0));
+        assert_eq!(display.get_viewport_size(), Vector2::new(1,1));
+    }
+
+    #[test]
+    fn rows_outside_viewport_are_empty() {
+        let mut display = create_display_with_text(Vector2::new(4,2), b"ab");
+        let viewport = display.get_current_viewport_mut();
+        let (row, status) = viewport.get_row(2);
+        assert!(row.is_empty());
+        assert_eq!(status.length, 0);
+        let (row, status) = viewport.get_row_mut(5);
+        assert!(row.is_empty());
+        status.length = 10;
+        // writes to the sentinel row don't leak
+        assert_eq!(viewport.get_row_mut(5).1.length, 0);
+        assert_eq!(viewport.get_row(0).1.length, 2);
+    }
+
+    // Selection is made in rendered grid coordinates and read back from the display
+    fn get_selection_text(display: &TerminalDisplay, renderer: &TerminalRenderer, start: Vector2<usize>, end: Vector2<usize>) -> String {
+        display.get_selection_text(renderer.get_line_position(start), renderer.get_line_position(end))
+    }
+
+    #[test]
+    fn selection_multiple_lines() {
+        let display = create_display_with_text(Vector2::new(6,2), b"hi  \r\nyo");
+        let mut renderer = TerminalRenderer::default();
+        renderer.render_display(&display);
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(0,0), Vector2::new(6,1)), "hi\nyo");
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(1,0), Vector2::new(1,1)), "i\ny");
+    }
+
+    #[test]
+    fn selection_reversed() {
+        let display = create_display_with_text(Vector2::new(6,2), b"hi  \r\nyo");
+        let mut renderer = TerminalRenderer::default();
+        renderer.render_display(&display);
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(6,1), Vector2::new(0,0)), "hi\nyo");
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(2,0), Vector2::new(0,0)), "hi");
+    }
+
+    #[test]
+    fn selection_empty() {
+        let display = create_display_with_text(Vector2::new(6,2), b"hi  \r\nyo");
+        let mut renderer = TerminalRenderer::default();
+        renderer.render_display(&display);
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(1,1), Vector2::new(1,1)), "");
+        // selection covering only trailing spaces
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(2,0), Vector2::new(6,0)), "");
+    }
+
+    #[test]
+    fn selection_across_scrollback_boundary() {
+        // first row wraps and is ejected into the scrollback buffer
+        let display = create_display_with_text(Vector2::new(4,2), b"abcdef\r\ngh");
+        let mut renderer = TerminalRenderer::default();
+        renderer.scroll_to_top();
+        renderer.render_display(&display);
+        assert_eq!(renderer.get_line_position(Vector2::new(1,1)), Vector2::new(5,0));
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(0,0), Vector2::new(2,1)), "abcdef");
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(2,0), Vector2::new(1,1)), "cde");
+        // line coordinates stay the same once scrolled back to the bottom
+        renderer.scroll_to_bottom();
+        renderer.render_display(&display);
+        assert_eq!(renderer.get_line_position(Vector2::new(1,0)), Vector2::new(5,0));
+        assert_eq!(get_selection_text(&display, &renderer, Vector2::new(0,0), Vector2::new(2,1)), "ef\ngh");
+    }
+
+    fn create_display_with_utf8(size: Vector2<usize>, text: &str) -> TerminalDisplay {
+        let mut display = TerminalDisplay::default();
+        display.set_size(size);
+        let viewport = display.get_current_viewport_mut();
+        for c in text.chars() {
+            if c.is_ascii() {
+                viewport.write_ascii(c as u8);
+            } else {
+                viewport.write_utf8(c);
+            }
+        }
+        display
+    }
+
+    #[test]
+    fn word_bounds_with_word_characters() {
+        use crate::terminal_display::DEFAULT_WORD_CHARACTERS;
+        let display = create_display_with_utf8(Vector2::new(20,2), "ls path/to-file.rs x");
+        let bounds = (Vector2::new(3,0), Vector2::new(17,0));
+        assert_eq!(display.get_word_bounds(Vector2::new(3,0), DEFAULT_WORD_CHARACTERS), bounds);
+        assert_eq!(display.get_word_bounds(Vector2::new(10,0), DEFAULT_WORD_CHARACTERS), bounds);
+        assert_eq!(display.get_word_bounds(Vector2::new(9,0), ""), (Vector2::new(8,0), Vector2::new(9,0)));
+        // non word characters select only themselves
+        assert_eq!(display.get_word_bounds(Vector2::new(2,0), DEFAULT_WORD_CHARACTERS), (Vector2::new(2,0), Vector2::new(2,0)));
+    }
+
+    #[test]
+    fn word_bounds_across_wrapped_lines() {
+        use crate::terminal_display::DEFAULT_WORD_CHARACTERS;
+        // first row is soft wrapped
+        let display = create_display_with_utf8(Vector2::new(6,3), "ab cdefgh ij\r\nkl");
+        let bounds = (Vector2::new(3,0), Vector2::new(2,1));
+        assert_eq!(display.get_word_bounds(Vector2::new(4,0), DEFAULT_WORD_CHARACTERS), bounds);
+        assert_eq!(display.get_word_bounds(Vector2::new(0,1), DEFAULT_WORD_CHARACTERS), bounds);
+        // words don't continue past a linebreak
+        assert_eq!(display.get_word_bounds(Vector2::new(5,1), DEFAULT_WORD_CHARACTERS), (Vector2::new(4,1), Vector2::new(5,1)));
+        assert_eq!(display.get_word_bounds(Vector2::new(0,2), DEFAULT_WORD_CHARACTERS), (Vector2::new(0,2), Vector2::new(1,2)));
+    }
+
+    #[test]
+    fn word_bounds_with_wide_characters() {
+        use crate::terminal_display::DEFAULT_WORD_CHARACTERS;
+        // wide characters are followed by a blank spacer cell
+        let display = create_display_with_utf8(Vector2::new(12,1), "ab \u{4e2d}\u{6587}  cd");
+        let bounds = (Vector2::new(3,0), Vector2::new(6,0));
+        assert_eq!(display.get_word_bounds(Vector2::new(3,0), DEFAULT_WORD_CHARACTERS), bounds);
+        assert_eq!(display.get_word_bounds(Vector2::new(4,0), DEFAULT_WORD_CHARACTERS), bounds);
+        assert_eq!(display.get_word_bounds(Vector2::new(6,0), DEFAULT_WORD_CHARACTERS), bounds);
+        assert_eq!(display.get_word_bounds(Vector2::new(7,0), DEFAULT_WORD_CHARACTERS), (Vector2::new(7,0), Vector2::new(7,0)));
+        // space written over the spacer separates the words
+        let display = create_display_with_utf8(Vector2::new(8,1), "ab \u{4e2d}\x08 cd");
+        assert_eq!(display.get_word_bounds(Vector2::new(3,0), DEFAULT_WORD_CHARACTERS), (Vector2::new(3,0), Vector2::new(3,0)));
+        assert_eq!(display.get_word_bounds(Vector2::new(4,0), DEFAULT_WORD_CHARACTERS), (Vector2::new(4,0), Vector2::new(4,0)));
+        assert_eq!(display.get_word_bounds(Vector2::new(5,0), DEFAULT_WORD_CHARACTERS), (Vector2::new(5,0), Vector2::new(6,0)));
+    }
+
+    #[test]
+    fn wide_characters_are_followed_by_spacer() {
+        use crate::primitives::{Cell, StyleFlags};
+        let display = create_display_with_utf8(Vector2::new(5,2), "a\u{4e2d}b\u{6587}");
+        let viewport = display.get_current_viewport();
+        let is_spacer = |cell: &Cell| cell.pen.style_flags.contains(StyleFlags::WideSpacer);
+        let (row, _) = viewport.get_row(0);
+        assert_eq!(row.iter().map(|cell| cell.character).collect::<String>(), "a\u{4e2d} b ");
+        assert_eq!(row.iter().map(is_spacer).collect::<Vec<_>>(), vec![false, false, true, false, false]);
+        // wide character doesn't fit in the last column so it moves to the next row
+        let (row, _) = viewport.get_row(1);
+        assert_eq!(row[0].character, '\u{6587}');
+        assert!(is_spacer(&row[1]));
+        assert_eq!(viewport.get_cursor(), Vector2::new(2,1));
+    }
+
+    #[test]
+    fn line_bounds_include_wrapped_rows() {
+        let display = create_display_with_utf8(Vector2::new(4,6), "abcdefghij\r\nxy");
+        assert_eq!(display.get_line_bounds(0), (0,2));
+        assert_eq!(display.get_line_bounds(1), (0,2));
+        assert_eq!(display.get_line_bounds(2), (0,2));
+        // unwritten rows below the last line aren't part of it
+        assert_eq!(display.get_line_bounds(3), (3,3));
+        assert_eq!(display.get_line_bounds(5), (5,5));
+    }
+
+    #[test]
+    fn renderer_word_and_line_bounds_in_scrollback() {
+        // first line wraps and is ejected into the scrollback buffer
+        let display = create_display_with_text(Vector2::new(4,2), b"abcdef\r\ngh");
+        let mut renderer = TerminalRenderer::default();
+        renderer.scroll_to_top();
+        renderer.render_display(&display);
+        assert_eq!(renderer.get_word_bounds(Vector2::new(1,0), ""), (Vector2::new(0,0), Vector2::new(1,1)));
+        assert_eq!(renderer.get_line_bounds(0), (0,1));
+        assert_eq!(renderer.get_line_bounds(1), (0,1));
+    }
+
+    #[test]
+    fn default_background_colour() {
+        use crate::primitives::Pen;
+        use vt100::common::Rgb8;
+        let mut display = TerminalDisplay::default();
+        assert_eq!(display.get_default_background_colour(), Rgb8 { r: 0, g: 0, b: 0 });
+        let background_colour = Rgb8 { r: 0, g: 43, b: 54 };
+        display.set_default_pen(Pen { background_colour, ..Pen::default() });
+        assert_eq!(display.get_default_background_colour(), background_colour);
+        // alternate viewport shares the same default pen
+        display.set_is_alternate(true);
+        assert_eq!(display.get_default_background_colour(), background_colour);
+    }
+
+    #[test]
+    fn pen_accessors() {
+        use crate::primitives::{Pen, StyleFlags};
+        use vt100::common::Rgb8;
+        let mut display = TerminalDisplay::default();
+        let default_pen = Pen { foreground_colour: Rgb8 { r: 200, g: 200, b: 200 }, ..Pen::default() };
+        display.set_default_pen(default_pen);
+        assert_eq!(display.get_default_pen(), default_pen);
+        // current pen only takes the default on the next reset
+        assert_eq!(*display.get_pen(), Pen::default());
+        display.get_pen_mut().style_flags |= StyleFlags::Bold;
+        let bold_pen = Pen { style_flags: StyleFlags::Bold, ..Pen::default() };
+        assert_eq!(*display.get_pen(), bold_pen);
+        // each viewport has its own pen
+        display.set_is_alternate(true);
+        assert_eq!(display.get_default_pen(), default_pen);
+        assert_eq!(*display.get_pen(), Pen::default());
+        *display.get_pen_mut() = default_pen;
+        display.set_is_alternate(false);
+        assert_eq!(*display.get_pen(), bold_pen);
+    }
+
+    #[test]
+    fn front_buffer_matches_display() {
+        let (mut display, _total_rows) = create_display_with_scrollback(Vector2::new(4,3), 8);
+        let front_buffer = display.get_front_buffer();
+        // front buffer is only updated when swapped
+        assert_eq!(front_buffer.read().unwrap().get_viewport_size(), Vector2::new(0,0));
+        display.swap_buffers();
+        assert_eq!(front_buffer.read().unwrap().get_viewport_size(), Vector2::new(4,3));
+        assert_eq!(front_buffer.read().unwrap().get_cursor_position(), display.get_cursor_position());
+        let scrollback_lines = display.get_scrollback().get_line_count();
+        assert_eq!(front_buffer.read().unwrap().get_scrollback_line_count(), scrollback_lines);
+        // scrollback of the primary viewport is still counted while the alternate viewport is shown
+        display.set_is_alternate(true);
+        display.swap_buffers();
+        assert_eq!(front_buffer.read().unwrap().get_scrollback_line_count(), scrollback_lines);
+        assert_eq!(front_buffer.read().unwrap().get_total_scrollback_lines(), 0);
+        display.set_is_alternate(false);
+        display.swap_buffers();
+
+        let mut expected = TerminalRenderer::default();
+        expected.render_display(&display);
+        let mut renderer = TerminalRenderer::default();
+        assert!(renderer.render_front_buffer(&front_buffer.read().unwrap()));
+        assert_eq!(renderer.get_cells(), expected.get_cells());
+        assert_eq!(renderer.get_cursor(), expected.get_cursor());
+        assert_eq!(renderer.get_scroll_status(), expected.get_scroll_status());
+
+        // scrollback is only rendered from the display
+        renderer.scroll_up(1);
+        assert!(!renderer.render_front_buffer(&front_buffer.read().unwrap()));
+    }
+
+    fn get_viewport_rows(display: &TerminalDisplay) -> Vec<String> {
+        let viewport = display.get_current_viewport();
+        (0..viewport.get_row_count())
+            .map(|y| viewport.get_row(y).0.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn render_blanks_cells_past_row_length() {
+        // rows are rotated in storage after scrolling
+        let mut display = create_display_with_text(Vector2::new(4,3), b"abcd\r\nefgh\r\nijkl\r\nmnop");
+        // shortened rows keep stale cells past their length
+        let viewport = display.get_current_viewport_mut();
+        viewport.get_row_mut(1).1.length = 2;
+        let mut renderer = TerminalRenderer::default();
+        renderer.render_display(&display);
+        let rows: Vec<String> = renderer.get_cells()
+            .chunks(4)
+            .map(|row| row.iter().map(|cell| cell.character).collect())
+            .collect();
+        assert_eq!(rows, vec!["efgh", "ij  ", "mnop"]);
+    }
+
+    #[test]
+    fn newline_only_moves_down() {
+        let mut display = create_display_with_text(Vector2::new(4,2), b"ab");
+        display.handle_newline();
+        assert_eq!(display.get_cursor_position(), Vector2::new(2,1));
+        // scrolls at the bottom
+        display.handle_newline();
+        assert_eq!(display.get_cursor_position(), Vector2::new(2,1));
+        assert_eq!(get_viewport_rows(&display), vec!["", ""]);
+    }
+
+    #[test]
+    fn newline_with_carriage_return() {
+        let mut display = create_display_with_text(Vector2::new(4,2), b"ab");
+        display.set_is_newline_carriage_return(true);
+        display.handle_newline();
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,1));
+        // applies to bytes written by the process too
+        let viewport = display.get_current_viewport_mut();
+        b"cd\n".iter().for_each(|&b| viewport.write_ascii(b));
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,1));
+        assert_eq!(get_viewport_rows(&display), vec!["cd", ""]);
+    }
+
+    #[test]
+    fn carriage_return_moves_to_first_column() {
+        let mut display = create_display_with_text(Vector2::new(4,2), b"ab\r\ncd");
+        display.handle_carriage_return();
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,1));
+        display.handle_carriage_return();
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,1));
+        // cursor waiting to wrap
+        let mut display = create_display_with_text(Vector2::new(4,2), b"abcd");
+        display.handle_carriage_return();
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,0));
+        assert_eq!(get_viewport_rows(&display), vec!["abcd", ""]);
+    }
+
+    #[test]
+    fn backspace_stops_at_first_column() {
+        let mut display = create_display_with_text(Vector2::new(4,2), b"ab\r\nc");
+        display.handle_backspace();
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,1));
+        // doesn't move to the previous row
+        display.handle_backspace();
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,1));
+        // cursor waiting to wrap moves back onto the last column
+        let mut display = create_display_with_text(Vector2::new(4,2), b"abcd");
+        display.handle_backspace();
+        assert_eq!(display.get_cursor_position(), Vector2::new(3,0));
+    }
+
+    #[test]
+    fn horizontal_tab_advances_to_tab_stop() {
+        use crate::viewport::TAB_STOP_WIDTH;
+        let mut display = create_display_with_text(Vector2::new(20,2), b"");
+        display.handle_horizontal_tab();
+        assert_eq!(display.get_cursor_position(), Vector2::new(TAB_STOP_WIDTH,0));
+        let mut display = create_display_with_text(Vector2::new(20,2), b"abc\tx");
+        assert_eq!(display.get_cursor_position(), Vector2::new(TAB_STOP_WIDTH+1,0));
+        assert_eq!(get_viewport_rows(&display), vec!["abc     x", ""]);
+        // clamped to the last column
+        display.handle_horizontal_tab();
+        assert_eq!(display.get_cursor_position(), Vector2::new(2*TAB_STOP_WIDTH,0));
+        display.handle_horizontal_tab();
+        assert_eq!(display.get_cursor_position(), Vector2::new(19,0));
+        display.handle_horizontal_tab();
+        assert_eq!(display.get_cursor_position(), Vector2::new(19,0));
+    }
+
+    #[test]
+    fn write_string_at_restores_cursor_and_pen() {
+        use crate::primitives::Pen;
+        use vt100::common::Rgb8;
+        let mut display = create_display_with_text(Vector2::new(8,3), b"ab");
+        let old_pen = *display.get_pen();
+        let pen = Pen { foreground_colour: Rgb8 { r: 255, g: 0, b: 0 }, ..Pen::default() };
+        display.write_string_at(Vector2::new(2,1), "hello world", pen);
+        assert_eq!(get_viewport_rows(&display), vec!["ab", "  hello", ""]);
+        assert_eq!(display.get_cursor_position(), Vector2::new(2,0));
+        assert_eq!(*display.get_pen(), old_pen);
+        let (row, _) = display.get_current_viewport().get_row(1);
+        assert!(row[2..].iter().all(|cell| cell.pen == pen));
+        assert_eq!(row[1].pen, Pen::default());
+        // outside of the viewport is ignored
+        display.write_string_at(Vector2::new(0,3), "x", pen);
+        display.write_string_at(Vector2::new(8,0), "x", pen);
+        assert_eq!(get_viewport_rows(&display), vec!["ab", "  hello", ""]);
+    }
+
+    #[test]
+    fn draw_box_outline() {
+        use crate::primitives::Pen;
+        let mut display = create_display_with_text(Vector2::new(6,4), b"");
+        display.draw_box(Vector2::new(1,0), Vector2::new(4,3), Pen::default());
+        assert_eq!(get_viewport_rows(&display), vec![" \u{250C}\u{2500}\u{2500}\u{2510}", " \u{2502}  \u{2502}", " \u{2514}\u{2500}\u{2500}\u{2518}", ""]);
+        // box is clipped to the viewport
+        display.draw_box(Vector2::new(4,2), Vector2::new(4,4), Pen::default());
+        assert_eq!(get_viewport_rows(&display)[3], "    \u{2502}");
+        assert_eq!(display.get_cursor_position(), Vector2::new(0,0));
+    }
+
+    mod viewport {
+        use crate::primitives::Cell;
+        use crate::viewport::{CellDiff, Viewport};
+        use cgmath::Vector2;
+        use test_log::test;
+
+        fn create_viewport(size: Vector2<usize>, text: &[u8]) -> Viewport {
+            let mut viewport = Viewport::default();
+            viewport.set_size(size);
+            text.iter().for_each(|&b| viewport.write_ascii(b));
+            viewport
+        }
+
+        fn get_cell(c: char) -> Cell {
+            Cell { character: c, ..Cell::default() }
+        }
+
+        #[test]
+        fn copy_to_shadow_has_no_diff() {
+            let viewport = create_viewport(Vector2::new(4,3), b"ab\r\ncd");
+            let mut shadow = create_viewport(Vector2::new(8,8), b"stale");
+            viewport.copy_to(&mut shadow);
+            assert_eq!(shadow.get_size(), Vector2::new(4,3));
+            assert_eq!((shadow.get_column_count(), shadow.get_row_count()), (4,3));
+            assert_eq!(shadow.total_cells(), 12);
+            assert_eq!(shadow.get_cursor(), viewport.get_cursor());
+            assert_eq!(shadow.get_row(1).0, viewport.get_row(1).0);
+            assert!(shadow.diff(&viewport).is_empty());
+        }
+
+        #[test]
+        fn flat_cells_in_display_order() {
+            let mut viewport = create_viewport(Vector2::new(2,3), b"ab\r\ncd\r\nef");
+            let (head, tail) = viewport.get_cells_flat();
+            assert!(tail.is_empty());
+            assert_eq!(head.iter().map(|c| c.character).collect::<String>(), "abcdef");
+            // scrolling rotates the stored rows so the grid wraps around
+            b"\r\ngh".iter().for_each(|&b| viewport.write_ascii(b));
+            let (head, tail) = viewport.get_cells_flat();
+            let flat: String = head.iter().chain(tail.iter()).map(|c| c.character).collect();
+            assert_eq!(flat, "cdefgh");
+            let rows: String = (0..3).flat_map(|y| viewport.get_row(y).0.iter().map(|c| c.character)).collect();
+            assert_eq!(flat, rows);
+            let (head, tail) = viewport.get_cells_flat_mut();
+            assert_eq!(head.len()+tail.len(), 6);
+            head[0].character = 'X';
+            assert_eq!(viewport.get_row(0).0[0].character, 'X');
+        }
+
+                #[test]
+        fn diff_reports_changed_cells() {
+            let mut viewport = create_viewport(Vector2::new(4,3), b"ab\r\ncd");
+            let mut shadow = Viewport::default();
+            viewport.copy_to(&mut shadow);
+            b"X\r\nz".iter().for_each(|&b| viewport.write_ascii(b));
+            assert_eq!(shadow.diff(&viewport), vec![
+                CellDiff { pos: Vector2::new(2,1), from: get_cell(' '), to: get_cell('X') },
+                CellDiff { pos: Vector2::new(0,2), from: get_cell(' '), to: get_cell('z') },
+            ]);
+            // scrolling shifts every row up
+            let mut viewport = create_viewport(Vector2::new(2,2), b"a\r\nb");
+            viewport.copy_to(&mut shadow);
+            viewport.write_ascii(b'\n');
+            assert_eq!(shadow.diff(&viewport), vec![
+                CellDiff { pos: Vector2::new(0,0), from: get_cell('a'), to: get_cell('b') },
+                CellDiff { pos: Vector2::new(0,1), from: get_cell('b'), to: get_cell(' ') },
+            ]);
+        }
+
+        #[test]
+        fn diff_after_resize() {
+            let shadow = create_viewport(Vector2::new(2,1), b"ab");
+            let viewport = create_viewport(Vector2::new(3,2), b"ab");
+            // new cells are compared against blanks
+            assert!(shadow.diff(&viewport).is_empty());
+            let viewport = create_viewport(Vector2::new(3,2), b"abc");
+            assert_eq!(shadow.diff(&viewport), vec![
+                CellDiff { pos: Vector2::new(2,0), from: get_cell(' '), to: get_cell('c') },
+            ]);
+        }
+    }
+
+    mod terminal_parser {
+        use crate::terminal_parser::{TerminalParser, TerminalParserHandler, TerminalParserMetrics};
+        use crate::utf8_parser::ParserError as Utf8ParserError;
+        use vt100::command::Command as Vt100Command;
+        use vt100::parser::{Parser as Vt100Parser, ParserError as Vt100ParserError};
+        use test_log::test;
+
+        #[derive(Default)]
+        struct Handler {
+            total_commands: usize,
+            total_errors: usize,
+        }
+
+        impl TerminalParserHandler for Handler {
+            fn on_unhandled_byte(&mut self, _byte: u8) {}
+            fn on_ascii_data(&mut self, _buf: &[u8]) {}
+            fn on_utf8(&mut self, _character: char) {}
+            fn on_utf8_error(&mut self, _error: &Utf8ParserError) {
+                self.total_errors += 1;
+            }
+            fn on_vt100(&mut self, _command: Vt100Command) {
+                self.total_commands += 1;
+            }
+            fn on_vt100_error(&mut self, _error: Vt100ParserError, _parser: &Vt100Parser) {
+                self.total_errors += 1;
+            }
+        }
+
+        #[test]
+        fn metrics_count_bytes_commands_and_errors() {
+            let mut parser = TerminalParser::default();
+            let mut handler = Handler::default();
+            // invalid utf8 body byte followed by an invalid erase mode
+            let data = b"ab\x1b[H\x1b[2J\xc3\x28\x1b[9J";
+            parser.parse_bytes(data, &mut handler);
+            let metrics = *parser.get_metrics();
+            assert_eq!(metrics.bytes_parsed, data.len() as u64);
+            assert_eq!(metrics.commands_emitted, 2);
+            assert_eq!(metrics.errors_encountered, 2);
+            assert_eq!((handler.total_commands, handler.total_errors), (2, 2));
+            // totals accumulate across calls including sequences split between them
+            parser.parse_bytes(b"\x1b[", &mut handler);
+            parser.parse_bytes(b"2J", &mut handler);
+            assert_eq!(parser.get_metrics().bytes_parsed, data.len() as u64 + 4);
+            assert_eq!(parser.get_metrics().commands_emitted, 3);
+            parser.reset_metrics();
+            assert_eq!(*parser.get_metrics(), TerminalParserMetrics::default());
+        }
+    }
+
+    mod url_detector {
+        use crate::terminal::ParserHandler;
+        use crate::terminal_display::TerminalDisplay;
+        use crate::terminal_parser::TerminalParser;
+        use crate::terminal_renderer::TerminalRenderer;
+        use crate::url_detector::{UrlCache, find_urls_in_text, get_hyperlink_span};
+        use cgmath::Vector2;
+        use std::sync::{Arc, Mutex};
+        use vt100::common::WindowAction;
+        use vt100::encoder::Encoder;
+        use test_log::test;
+
+        fn find_urls(text: &str) -> Vec<String> {
+            let text: Vec<char> = text.chars().collect();
+            find_urls_in_text(text.as_slice()).into_iter().map(|range| text[range].iter().collect()).collect()
+        }
+
+        fn create_display(size: Vector2<usize>, data: &[u8]) -> Arc<Mutex<TerminalDisplay>> {
+            let mut display = TerminalDisplay::default();
+            display.set_size(size);
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), data);
+            display
+        }
+
+        fn write_bytes(display: Arc<Mutex<TerminalDisplay>>, data: &[u8]) {
+            let encoder = Arc::new(Mutex::new(Encoder::default()));
+            let mut handler = ParserHandler::new(display, encoder, Box::new(|_: WindowAction| {}));
+            let mut parser = TerminalParser::default();
+            parser.parse_bytes(data, &mut handler);
+        }
+
+        #[test]
+        fn trims_trailing_punctuation() {
+            assert_eq!(find_urls("see https://example.com."), vec!["https://example.com"]);
+            assert_eq!(find_urls("(at https://example.com/a?b=1), then"), vec!["https://example.com/a?b=1"]);
+            assert_eq!(find_urls("'http://example.com/path'!"), vec!["http://example.com/path"]);
+            // balanced brackets are part of the url
+            assert_eq!(
+                find_urls("https://en.wikipedia.org/wiki/Rust_(programming_language)."),
+                vec!["https://en.wikipedia.org/wiki/Rust_(programming_language)"],
+            );
+            assert_eq!(find_urls("<file:///tmp/a.txt> https://b.org"), vec!["file:///tmp/a.txt", "https://b.org"]);
+            // scheme without anything after it
+            assert!(find_urls("https://. ftp://example.com").is_empty());
+        }
+
+        #[test]
+        fn url_wrapped_across_rows() {
+            // first two rows are soft wrapped
+            let display = create_display(Vector2::new(12,3), b"see https://example.com/a ok");
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display.lock().unwrap());
+            let mut cache = UrlCache::default();
+            cache.update(&renderer, &display.lock().unwrap());
+            let url = cache.get_url_at(Vector2::new(2,1)).unwrap();
+            assert_eq!(url.url, "https://example.com/a");
+            assert_eq!(url.start, Vector2::new(4,0));
+            assert_eq!(url.end, Vector2::new(0,2));
+            assert_eq!(cache.get_url_at(Vector2::new(4,1)), Some(url));
+            assert_eq!(cache.get_url_at(Vector2::new(3,0)), None);
+            assert_eq!(cache.get_url_at(Vector2::new(1,2)), None);
+        }
+
+        #[test]
+        fn cache_only_rescans_changed_lines() {
+            let display = create_display(Vector2::new(12,4), b"https://a.org\r\nx");
+            let mut renderer = TerminalRenderer::default();
+            let mut cache = UrlCache::default();
+            renderer.render_display(&display.lock().unwrap());
+            assert_eq!(cache.update(&renderer, &display.lock().unwrap()), 2);
+            renderer.render_display(&display.lock().unwrap());
+            assert_eq!(cache.update(&renderer, &display.lock().unwrap()), 0);
+            write_bytes(display.clone(), b" http://b.org");
+            renderer.render_display(&display.lock().unwrap());
+            assert_eq!(cache.update(&renderer, &display.lock().unwrap()), 1);
+            assert_eq!(cache.get_url_at(Vector2::new(0,0)).unwrap().url, "https://a.org");
+            assert_eq!(cache.get_url_at(Vector2::new(3,2)).unwrap().url, "http://b.org");
+        }
+
+        #[test]
+        fn hyperlink_cells() {
+            let display = create_display(Vector2::new(20,2), b"\x1b]8;id=1;https://a.org\x07li\x1b[0mnk\x1b]8;;\x07 text");
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display.lock().unwrap());
+            let cells = renderer.get_cells();
+            // reset doesn't end the hyperlink
+            assert!(cells[..4].iter().all(|cell| cell.pen.hyperlink.is_some()));
+            assert!(cells[4..].iter().all(|cell| cell.pen.hyperlink.is_none()));
+            let mut cache = UrlCache::default();
+            cache.update(&renderer, &display.lock().unwrap());
+            let url = cache.get_url_at(Vector2::new(3,0)).unwrap();
+            assert_eq!(url.url, "https://a.org");
+            assert_eq!((url.start, url.end), (Vector2::new(0,0), Vector2::new(3,0)));
+            assert_eq!(cache.get_url_at(Vector2::new(4,0)), None);
+        }
+
+        #[test]
+        fn hovered_hyperlink() {
+            let display = create_display(Vector2::new(8,3), b"a \x1b]8;;https://a.org\x07linkedXY\x1b]8;;\x07 b\r\n\x1b]8;;file:///x\x07x");
+            let display = display.lock().unwrap();
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display);
+            // hyperlink continues onto the wrapped row
+            let span = get_hyperlink_span(&renderer, Vector2::new(0,1)).unwrap();
+            assert_eq!(display.get_hyperlink(span.id), Some("https://a.org"));
+            assert_eq!((span.start, span.end), (Vector2::new(2,0), Vector2::new(1,1)));
+            assert_eq!(get_hyperlink_span(&renderer, Vector2::new(5,0)), Some(span));
+            assert_eq!(get_hyperlink_span(&renderer, Vector2::new(1,0)), None);
+            assert_eq!(get_hyperlink_span(&renderer, Vector2::new(0,9)), None);
+            assert_eq!(display.get_cell_hyperlink(Vector2::new(3,0)), Some("https://a.org"));
+            assert_eq!(display.get_cell_hyperlink(Vector2::new(3,1)), None);
+            assert_eq!(display.get_cell_hyperlink(Vector2::new(20,0)), None);
+            // cursor is after the last hyperlinked character
+            assert_eq!(display.get_cursor_position(), Vector2::new(1,2));
+            assert_eq!(display.get_hyperlink_at_cursor(), None);
+            assert_eq!(display.get_cell_hyperlink(Vector2::new(0,2)), Some("file:///x"));
+        }
+    }
+
+    mod colour_table {
+        use crate::terminal::ParserHandler;
+        use crate::terminal_display::TerminalDisplay;
+        use crate::terminal_parser::TerminalParser;
+        use crate::terminal_renderer::TerminalRenderer;
+        use cgmath::Vector2;
+        use std::sync::{Arc, Mutex};
+        use vt100::common::{Rgb8, WindowAction};
+        use vt100::encoder::Encoder;
+        use test_log::test;
+
+        fn render_bytes(display: Arc<Mutex<TerminalDisplay>>, data: &[u8]) -> TerminalRenderer {
+            let encoder = Arc::new(Mutex::new(Encoder::default()));
+            let mut handler = ParserHandler::new(display.clone(), encoder, Box::new(|_: WindowAction| {}));
+            let mut parser = TerminalParser::default();
+            parser.parse_bytes(data, &mut handler);
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display.lock().unwrap());
+            renderer
+        }
+
+        #[test]
+        fn set_and_get_entry() {
+            let mut display = TerminalDisplay::default();
+            let blue = Rgb8 { r: 0, g: 0, b: 255 };
+            assert_ne!(display.get_colour_table_entry(1), blue);
+            display.set_colour_table_entry(1, blue);
+            assert_eq!(display.get_colour_table_entry(1), blue);
+        }
+
+        #[test]
+        fn palette_change_applies_to_foreground_red() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,2));
+            let display = Arc::new(Mutex::new(display));
+            let blue = Rgb8 { r: 0, g: 0, b: 255 };
+            let renderer = render_bytes(display.clone(), b"\x1b]4;1;rgb:00/00/ff\x07\x1b[31mx");
+            assert_eq!(display.lock().unwrap().get_colour_table_entry(1), blue);
+            let cell = renderer.get_cells()[0];
+            assert_eq!(cell.character, 'x');
+            assert_eq!(cell.pen.foreground_colour, blue);
+        }
+
+        #[test]
+        fn palette_change_applies_to_existing_cells() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,2));
+            let display = Arc::new(Mutex::new(display));
+            let blue = Rgb8 { r: 0, g: 0, b: 255 };
+            let rgb = Rgb8 { r: 1, g: 2, b: 3 };
+            let renderer = render_bytes(display.clone(), b"\x1b[31;41m\x1b[58;5;1mx\x1b[38;2;1;2;3m\x1b[58;2;1;2;3my\x1b]4;1;rgb:00/00/ff\x07");
+            let cells = renderer.get_cells();
+            assert_eq!(cells[0].pen.foreground_colour, blue);
+            assert_eq!(cells[0].pen.background_colour, blue);
+            assert_eq!(cells[0].pen.underline_colour, Some(blue));
+            // colours given directly aren't from the table
+            assert_eq!(cells[1].pen.foreground_colour, rgb);
+            assert_eq!(cells[1].pen.underline_colour, Some(rgb));
+            // published front buffer carries the table as well
+            let mut display = display.lock().unwrap();
+            display.swap_buffers();
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_front_buffer(&display.get_front_buffer().read().unwrap());
+            assert_eq!(renderer.get_cells()[0].pen.foreground_colour, blue);
+        }
+
+        #[test]
+        fn table_from_custom_palette() {
+            use crate::colour_table::{create_colour_table, get_default_palette};
+            let mut palette = [Rgb8::default(); 16];
+            for (i, colour) in palette.iter_mut().enumerate() {
+                *colour = Rgb8 { r: i as u8, g: 0x10 + i as u8, b: 0x20 + i as u8 };
+            }
+            let table = create_colour_table(&palette);
+            assert_eq!(table[..16], palette);
+            // colour cube and greyscale ramp are unchanged
+            let default_table = create_colour_table(&get_default_palette());
+            assert_eq!(table[16..], default_table[16..]);
+            assert_eq!(table[16], Rgb8 { r: 0x00, g: 0x00, b: 0x00 });
+            assert_eq!(table[17], Rgb8 { r: 0x00, g: 0x00, b: 0x5f });
+            assert_eq!(table[231], Rgb8 { r: 0xff, g: 0xff, b: 0xff });
+            assert_eq!(table[232], Rgb8 { r: 0x08, g: 0x08, b: 0x08 });
+            assert_eq!(table[255], Rgb8 { r: 0xee, g: 0xee, b: 0xee });
+            assert_eq!(table.len(), 256);
+        }
+    }
+
+    mod cursor {
+        use crate::terminal::ParserHandler;
+        use crate::terminal_display::TerminalDisplay;
+        use crate::terminal_parser::TerminalParser;
+        use crate::terminal_renderer::{TerminalRenderer, RenderCursor};
+        use cgmath::Vector2;
+        use std::sync::{Arc, Mutex};
+        use vt100::common::{CursorStyle, Rgb8, WindowAction};
+        use vt100::encoder::Encoder;
+        use test_log::test;
+
+        fn render_bytes(renderer: &mut TerminalRenderer, display: Arc<Mutex<TerminalDisplay>>, data: &[u8]) {
+            let encoder = Arc::new(Mutex::new(Encoder::default()));
+            let mut handler = ParserHandler::new(display.clone(), encoder, Box::new(|_: WindowAction| {}));
+            let mut parser = TerminalParser::default();
+            parser.parse_bytes(data, &mut handler);
+            renderer.render_display(&display.lock().unwrap());
+        }
+
+        #[test]
+        fn cursor_colour_precedence() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,2));
+            let display = Arc::new(Mutex::new(display));
+            let mut renderer = TerminalRenderer::default();
+            let red = Rgb8 { r: 255, g: 0, b: 0 };
+            let green = Rgb8 { r: 0, g: 255, b: 0 };
+            // defaults to foreground colour of cell under cursor
+            render_bytes(&mut renderer, display.clone(), b"x");
+            let foreground_colour = renderer.get_cells()[1].pen.foreground_colour;
+            let cursor = renderer.get_cursor().unwrap();
+            assert_eq!(cursor, RenderCursor { position: Vector2::new(1,0), style: CursorStyle::Block, colour: foreground_colour });
+            // configured colour
+            renderer.set_default_cursor_colour(Some(green));
+            render_bytes(&mut renderer, display.clone(), b"");
+            assert_eq!(renderer.get_cursor().unwrap().colour, green);
+            // application colour overrides configured colour until reset
+            render_bytes(&mut renderer, display.clone(), b"\x1b]12;#ff0000\x07");
+            assert_eq!(renderer.get_cursor().unwrap().colour, red);
+            render_bytes(&mut renderer, display.clone(), b"\x1b]112\x07");
+            assert_eq!(renderer.get_cursor().unwrap().colour, green);
+        }
+
+        #[test]
+        fn cursor_contrasts_with_cell_background() {
+            use crate::contrast::get_contrast_ratio;
+            use crate::terminal_renderer::MINIMUM_CURSOR_CONTRAST;
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,2));
+            let display = Arc::new(Mutex::new(display));
+            let mut renderer = TerminalRenderer::default();
+            let black = Rgb8 { r: 0, g: 0, b: 0 };
+            // configured colour matches the background
+            renderer.set_default_cursor_colour(Some(black));
+            render_bytes(&mut renderer, display.clone(), b"x");
+            let colour = renderer.get_cursor().unwrap().colour;
+            assert_ne!(colour, black);
+            assert!(get_contrast_ratio(colour, black) >= MINIMUM_CURSOR_CONTRAST);
+            // colours that already stand out are kept
+            let grey = Rgb8 { r: 128, g: 128, b: 128 };
+            renderer.set_default_cursor_colour(Some(grey));
+            render_bytes(&mut renderer, display.clone(), b"");
+            assert_eq!(renderer.get_cursor().unwrap().colour, grey);
+        }
+
+        #[test]
+        fn hidden_cursor_is_not_rendered() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,2));
+            let display = Arc::new(Mutex::new(display));
+            let mut renderer = TerminalRenderer::default();
+            render_bytes(&mut renderer, display.clone(), b"\x1b[?25l");
+            assert_eq!(renderer.get_cursor(), None);
+            render_bytes(&mut renderer, display.clone(), b"\x1b[?25h\x1b[6 q");
+            assert_eq!(renderer.get_cursor().unwrap().style, CursorStyle::Bar);
+        }
+
+        #[test]
+        fn cursor_cell_is_flagged() {
+            use crate::primitives::StyleFlags;
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,2));
+            let display = Arc::new(Mutex::new(display));
+            let mut renderer = TerminalRenderer::default();
+            let get_flagged = |renderer: &TerminalRenderer| -> Vec<usize> {
+                renderer.get_cells().iter().enumerate()
+                    .filter(|(_, cell)| cell.pen.style_flags.contains(StyleFlags::Cursor))
+                    .map(|(i, _)| i)
+                    .collect()
+            };
+            render_bytes(&mut renderer, display.clone(), b"ab\r\nc");
+            assert_eq!(get_flagged(&renderer), vec![5]);
+            // waiting to wrap is drawn on the last column
+            render_bytes(&mut renderer, display.clone(), b"def");
+            assert_eq!(get_flagged(&renderer), vec![7]);
+            render_bytes(&mut renderer, display.clone(), b"\x1b[?25l");
+            assert!(get_flagged(&renderer).is_empty());
+        }
+
+        #[test]
+        fn display_cursor_getters() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(6,3));
+            let display = Arc::new(Mutex::new(display));
+            let mut renderer = TerminalRenderer::default();
+            render_bytes(&mut renderer, display.clone(), b"ab\r\ncd");
+            {
+                let display = display.lock().unwrap();
+                assert_eq!(display.get_viewport_size(), Vector2::new(6,3));
+                assert_eq!(display.get_cursor_position(), Vector2::new(2,1));
+                assert!(display.get_cursor_visible());
+                assert_eq!(display.get_cursor_style(), CursorStyle::Block);
+                assert_eq!(display.get_cursor_colour(), None);
+            }
+            render_bytes(&mut renderer, display.clone(), b"\x1b[?25l\x1b[4 q\x1b[3;5H\x1b]12;#00ff00\x07");
+            let display = display.lock().unwrap();
+            assert_eq!(display.get_cursor_position(), Vector2::new(4,2));
+            assert!(!display.get_cursor_visible());
+            assert_eq!(display.get_cursor_style(), CursorStyle::Underline);
+            assert_eq!(display.get_cursor_colour(), Some(Rgb8 { r: 0, g: 255, b: 0 }));
+        }
+    }
+
+    mod contrast {
+        use crate::contrast::{apply_minimum_contrast, get_contrast_ratio};
+        use vt100::common::Rgb8;
+        use test_log::test;
+
+        const fn rgb(r: u8, g: u8, b: u8) -> Rgb8 {
+            Rgb8 { r, g, b }
+        }
+
+        #[test]
+        fn known_contrast_ratios() {
+            let ratio = get_contrast_ratio(rgb(255,255,255), rgb(0,0,0));
+            assert!((ratio - 21.0).abs() < 1e-3);
+            let ratio = get_contrast_ratio(rgb(0x40,0x40,0x40), rgb(0x40,0x40,0x40));
+            assert!((ratio - 1.0).abs() < 1e-3);
+        }
+
+        #[test]
+        fn disabled_leaves_colour_unchanged() {
+            let fg = rgb(0x28,0x2c,0x34);
+            assert_eq!(apply_minimum_contrast(fg, fg, 1.0), fg);
+            // already meets the ratio
+            assert_eq!(apply_minimum_contrast(rgb(255,255,255), rgb(0,0,0), 4.5), rgb(255,255,255));
+        }
+
+        #[test]
+        fn dark_on_black_moves_towards_white() {
+            let bg = rgb(0,0,0);
+            let fg = apply_minimum_contrast(rgb(0x40,0x40,0x40), bg, 4.5);
+            assert_eq!(fg, rgb(117,117,117));
+            assert!(get_contrast_ratio(fg, bg) >= 4.5);
+        }
+
+        #[test]
+        fn light_on_white_moves_towards_black() {
+            let bg = rgb(255,255,255);
+            let fg = apply_minimum_contrast(rgb(0xc0,0xc0,0xc0), bg, 4.5);
+            assert_eq!(fg, rgb(118,118,118));
+            assert!(get_contrast_ratio(fg, bg) >= 4.5);
+        }
+
+        #[test]
+        fn darkens_instead_of_crossing_background() {
+            // white has more contrast with grey but darkening meets the ratio with a smaller change
+            let bg = rgb(0x70,0x70,0x70);
+            let fg = apply_minimum_contrast(rgb(0x60,0x60,0x60), bg, 2.0);
+            assert_eq!(fg, rgb(66,66,66));
+            assert!(get_contrast_ratio(fg, bg) >= 2.0);
+            // only lightening can reach a high ratio
+            let fg = apply_minimum_contrast(rgb(0x60,0x60,0x60), bg, 4.5);
+            assert!(fg.r > bg.r);
+            assert!(get_contrast_ratio(fg, bg) >= 4.5);
+        }
+
+        #[test]
+        fn identical_colours() {
+            let bg = rgb(0x28,0x2c,0x34);
+            let fg = apply_minimum_contrast(bg, bg, 3.0);
+            assert_eq!(fg, rgb(114,117,122));
+            assert!(get_contrast_ratio(fg, bg) >= 3.0);
+        }
+    }
+
+    mod prompt_marks {
+        use super::create_display_with_scrollback;
+        use crate::terminal::ParserHandler;
+        use crate::terminal_display::{TerminalDisplay, PromptMarkPosition};
+        use crate::terminal_parser::TerminalParser;
+        use crate::terminal_renderer::{TerminalRenderer, RenderPosition};
+        use crate::scrollback_buffer::ScrollbackBuffer;
+        use cgmath::Vector2;
+        use std::sync::{Arc, Mutex};
+        use vt100::common::{PromptMark, WindowAction};
+        use vt100::encoder::Encoder;
+        use test_log::test;
+
+        fn write_bytes(display: Arc<Mutex<TerminalDisplay>>, data: &[u8]) {
+            let encoder = Arc::new(Mutex::new(Encoder::default()));
+            let mut handler = ParserHandler::new(display, encoder, Box::new(|_: WindowAction| {}));
+            let mut parser = TerminalParser::default();
+            parser.parse_bytes(data, &mut handler);
+        }
+
+        const COMMAND: &[u8] = b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07out1\r\nout2\r\n\x1b]133;D;0\x07";
+
+        #[test]
+        fn scroll_between_prompts() {
+            let size = Vector2::new(4,2);
+            let (display, total_rows) = create_display_with_scrollback(size, 20);
+            assert!(total_rows >= 12);
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display);
+            let prompt_lines = [2, 5, 9];
+            // next prompt is only reachable once scrolled back
+            assert_eq!(renderer.scroll_to_next_prompt(&prompt_lines), None);
+            assert_eq!(renderer.scroll_to_previous_prompt(&prompt_lines), Some(9));
+            assert_eq!(renderer.get_position(), RenderPosition::Floating(9));
+            renderer.render_display(&display);
+            assert_eq!(renderer.get_line_render_row(9), Some(0));
+            assert_eq!(renderer.get_line_render_row(10), Some(1));
+            assert_eq!(renderer.get_line_render_row(5), None);
+            assert_eq!(renderer.scroll_to_previous_prompt(&prompt_lines), Some(5));
+            assert_eq!(renderer.scroll_to_previous_prompt(&prompt_lines), Some(2));
+            assert_eq!(renderer.scroll_to_previous_prompt(&prompt_lines), None);
+            assert_eq!(renderer.get_position(), RenderPosition::Floating(2));
+            assert_eq!(renderer.scroll_to_next_prompt(&prompt_lines), Some(5));
+            assert_eq!(renderer.get_position(), RenderPosition::Floating(5));
+            // prompts in the viewport scroll back to the bottom
+            assert_eq!(renderer.scroll_to_next_prompt(&[total_rows]), Some(total_rows));
+            assert_eq!(renderer.get_position(), RenderPosition::Bottom);
+        }
+
+        #[test]
+        fn marks_follow_cursor_line() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,4));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), COMMAND);
+            write_bytes(display.clone(), b"\x1b]133;A\x07$ ");
+            let display = display.lock().unwrap();
+            assert_eq!(display.get_prompt_marks(), vec![
+                PromptMarkPosition { mark: PromptMark::PromptStart, line: 0 },
+                PromptMarkPosition { mark: PromptMark::CommandStart, line: 0 },
+                PromptMarkPosition { mark: PromptMark::OutputStart, line: 1 },
+                PromptMarkPosition { mark: PromptMark::CommandFinished(Some(0)), line: 3 },
+                PromptMarkPosition { mark: PromptMark::PromptStart, line: 3 },
+            ]);
+            assert_eq!(display.get_last_command_output().as_deref(), Some("out1\nout2"));
+        }
+
+        #[test]
+        fn last_command_output_scrolled_into_scrollback() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,2));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), COMMAND);
+            write_bytes(display.clone(), COMMAND);
+            let display = display.lock().unwrap();
+            let marks = display.get_prompt_marks();
+            assert_eq!(marks.len(), 8);
+            assert_eq!(marks[4], PromptMarkPosition { mark: PromptMark::PromptStart, line: 3 });
+            assert_eq!(display.get_lines_text(3, 4), "$ ls");
+            assert_eq!(display.get_last_command_output().as_deref(), Some("out1\nout2"));
+        }
+
+        #[test]
+        fn evicted_marks_are_dropped() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,4));
+            display.set_scrollback_buffer(ScrollbackBuffer::new(1));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), COMMAND);
+            write_bytes(display.clone(), "x\r\n".repeat(2000).as_bytes());
+            assert!(display.lock().unwrap().get_scrollback().get_evicted_line_count() > 0);
+            assert!(display.lock().unwrap().get_prompt_marks().is_empty());
+            assert_eq!(display.lock().unwrap().get_last_command_output(), None);
+            // new marks are relative to the oldest line that is left
+            write_bytes(display.clone(), b"\x1b]133;A\x07$ ");
+            let display = display.lock().unwrap();
+            let marks = display.get_prompt_marks();
+            assert_eq!(marks.len(), 1);
+            assert_eq!(display.get_lines_text(marks[0].line, marks[0].line+1), "$");
+        }
+    }
+
+    mod accessibility {
+        use crate::terminal::ParserHandler;
+        use crate::terminal_display::{AccessibleRow, TerminalDisplay};
+        use crate::terminal_parser::TerminalParser;
+        use cgmath::Vector2;
+        use std::sync::{Arc, Mutex};
+        use vt100::common::WindowAction;
+        use vt100::encoder::Encoder;
+        use test_log::test;
+
+        fn write_bytes(display: Arc<Mutex<TerminalDisplay>>, data: &[u8]) {
+            let encoder = Arc::new(Mutex::new(Encoder::default()));
+            let mut handler = ParserHandler::new(display, encoder, Box::new(|_: WindowAction| {}));
+            let mut parser = TerminalParser::default();
+            parser.parse_bytes(data, &mut handler);
+        }
+
+        fn create_row(text: &str, has_link: bool, is_focused_row: bool) -> AccessibleRow {
+            AccessibleRow { text: text.to_owned(), has_link, is_focused_row }
+        }
+
+        #[test]
+        fn tree_follows_viewport() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,4));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), b"$ ls  \r\nsee \x1b]8;;http://a.b\x1b\\link\x1b]8;;\x1b\\s\r\n> ");
+            assert_eq!(display.lock().unwrap().to_accessibility_tree(), vec![
+                create_row("$ ls", false, false),
+                // link wraps onto the next row
+                create_row("see link", true, false),
+                create_row("s", false, false),
+                create_row(">", false, true),
+            ]);
+            // rows scroll out of the top and the cursor moves with the text
+            write_bytes(display.clone(), b"\r\n\x1b]8;;http://a.b\x1b\\x\x1b]8;;\x1b\\\r\n\x1b[2A");
+            assert_eq!(display.lock().unwrap().to_accessibility_tree(), vec![
+                create_row("s", false, false),
+                create_row(">", false, true),
+                create_row("x", true, false),
+                create_row("", false, false),
+            ]);
+            // alternate screen has its own rows
+            write_bytes(display.clone(), b"\x1b[?1049hvim");
+            assert_eq!(display.lock().unwrap().to_accessibility_tree(), vec![
+                create_row("vim", false, true),
+                create_row("", false, false),
+                create_row("", false, false),
+                create_row("", false, false),
+            ]);
+        }
+
+        #[test]
+        fn tree_skips_wide_spacers() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,2));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), "漢字 ok".as_bytes());
+            assert_eq!(display.lock().unwrap().to_accessibility_tree(), vec![
+                create_row("漢字 ok", false, true),
+                create_row("", false, false),
+            ]);
+        }
+    }
+
+    mod image_registry {
+        use crate::image_registry::{DecodedImage, ImageRegistry, calculate_image_size};
+        use crate::primitives::StyleFlags;
+        use crate::scrollback_buffer::ScrollbackBuffer;
+        use crate::terminal::ParserHandler;
+        use crate::terminal_display::TerminalDisplay;
+        use crate::terminal_parser::TerminalParser;
+        use crate::terminal_renderer::TerminalRenderer;
+        use cgmath::Vector2;
+        use std::sync::{Arc, Mutex};
+        use vt100::common::{ImageDimension, InlineImage, WindowAction};
+        use vt100::encoder::Encoder;
+        use test_log::test;
+
+        // 2x2 red png
+        const PNG_BASE64: &str = "iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAYAAABytg0kAAAAEUlEQVR4nGP4z8DwH4QZYAwAR8oH+WdZbrcAAAAASUVORK5CYII=";
+
+        // Cells are 10x10 pixels
+        fn write_bytes(display: Arc<Mutex<TerminalDisplay>>, data: &[u8]) {
+            let mut encoder = Encoder::default();
+            let grid_size = display.lock().unwrap().get_viewport_size();
+            encoder.grid_size = grid_size;
+            encoder.window_size = grid_size*10;
+            let encoder = Arc::new(Mutex::new(encoder));
+            let mut handler = ParserHandler::new(display, encoder, Box::new(|_: WindowAction| {}));
+            let mut parser = TerminalParser::default();
+            parser.parse_bytes(data, &mut handler);
+        }
+
+        fn create_image(cells: Vector2<usize>) -> DecodedImage {
+            let size = cells*10;
+            DecodedImage { size, pixels: vec![0; size.x*size.y*4] }
+        }
+
+        fn create_inline_image(width: ImageDimension, height: ImageDimension) -> InlineImage {
+            InlineImage { width, height, preserve_aspect_ratio: true, is_inline: true, data: Vec::new() }
+        }
+
+        #[test]
+        fn image_size() {
+            let cell_size = Vector2::new(10,20);
+            let grid_size = Vector2::new(8,4);
+            let image_size = Vector2::new(40,20);
+            let calculate = |width, height| calculate_image_size(image_size, &create_inline_image(width, height), cell_size, grid_size);
+            assert_eq!(calculate(ImageDimension::Auto, ImageDimension::Auto), Vector2::new(40,20));
+            assert_eq!(calculate(ImageDimension::Cells(2), ImageDimension::Auto), Vector2::new(20,10));
+            assert_eq!(calculate(ImageDimension::Auto, ImageDimension::Pixels(40)), Vector2::new(80,40));
+            assert_eq!(calculate(ImageDimension::Percent(50), ImageDimension::Auto), Vector2::new(40,20));
+            // fits inside the requested box
+            assert_eq!(calculate(ImageDimension::Cells(8), ImageDimension::Cells(1)), Vector2::new(40,20));
+            // never larger than the grid
+            assert_eq!(calculate(ImageDimension::Pixels(160), ImageDimension::Auto), Vector2::new(80,40));
+            let image = create_inline_image(ImageDimension::Auto, ImageDimension::Cells(8));
+            assert_eq!(calculate_image_size(Vector2::new(20,40), &image, cell_size, grid_size), Vector2::new(40,80));
+            let mut image = create_inline_image(ImageDimension::Pixels(30), ImageDimension::Pixels(30));
+            image.preserve_aspect_ratio = false;
+            assert_eq!(calculate_image_size(image_size, &image, cell_size, grid_size), Vector2::new(30,30));
+        }
+
+        #[test]
+        fn decode_from_parser() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,4));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), format!("$ \x1b]1337;File=inline=1;width=2:{}\x07", PNG_BASE64).as_bytes());
+            // downloads and invalid images are ignored
+            write_bytes(display.clone(), format!("\x1b]1337;File=width=2:{}\x07", PNG_BASE64).as_bytes());
+            write_bytes(display.clone(), b"\x1b]1337;File=inline=1:aGk=\x07");
+            let display = display.lock().unwrap();
+            let placements = display.get_image_placements();
+            assert_eq!(placements.len(), 1);
+            assert_eq!(placements[0].line, 0);
+            assert_eq!(placements[0].column, 2);
+            assert_eq!(placements[0].cells, Vector2::new(2,2));
+            assert_eq!(placements[0].image.size, Vector2::new(20,20));
+            assert_eq!(&placements[0].image.pixels[..4], &[255,0,0,255]);
+            assert_eq!(display.get_cursor_position(), Vector2::new(0,2));
+            let viewport = display.get_current_viewport();
+            for y in 0..2 {
+                let (row, status) = viewport.get_row(y);
+                assert!(status.is_linebreak);
+                assert_eq!(status.length, 4);
+                assert!(!row[1].pen.style_flags.contains(StyleFlags::Image));
+                assert!(row[2..4].iter().all(|cell| cell.pen.style_flags.contains(StyleFlags::Image)));
+            }
+            assert!(!display.get_pen().style_flags.contains(StyleFlags::Image));
+        }
+
+        #[test]
+        fn image_starts_after_wrapped_line() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(4,4));
+            let display = Arc::new(Mutex::new(display));
+            write_bytes(display.clone(), b"abcdef");
+            let mut display = display.lock().unwrap();
+            display.add_inline_image(create_image(Vector2::new(1,1)), Vector2::new(1,1));
+            let placements = display.get_image_placements();
+            assert_eq!(placements[0].line, 1);
+            assert_eq!(placements[0].column, 0);
+            assert_eq!(display.get_cursor_position(), Vector2::new(0,3));
+        }
+
+        #[test]
+        fn images_scroll_with_text() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,4));
+            let viewport = display.get_current_viewport_mut();
+            viewport.write_ascii(b'a');
+            viewport.write_ascii(b'\r');
+            viewport.write_ascii(b'\n');
+            display.add_inline_image(create_image(Vector2::new(2,2)), Vector2::new(2,2));
+            let mut renderer = TerminalRenderer::default();
+            renderer.render_display(&display);
+            let images = renderer.get_images();
+            assert_eq!(images.len(), 1);
+            assert_eq!((images[0].row, images[0].column, images[0].cells), (1, 0, Vector2::new(2,2)));
+            // image is partially above the first row
+            for _ in 0..2 {
+                display.handle_newline();
+            }
+            renderer.render_display(&display);
+            assert_eq!(renderer.get_images()[0].row, -1);
+            display.swap_buffers();
+            assert!(renderer.render_front_buffer(&display.get_front_buffer().read().unwrap()));
+            assert_eq!(renderer.get_images()[0].row, -1);
+            // scrolled out of view but still in the scrollback
+            for _ in 0..4 {
+                display.handle_newline();
+            }
+            renderer.render_display(&display);
+            assert!(renderer.get_images().is_empty());
+            assert_eq!(display.get_image_placements().len(), 1);
+            renderer.scroll_to_top();
+            renderer.render_display(&display);
+            assert_eq!(renderer.get_images()[0].row, 1);
+            // drawn over the primary screen only
+            display.set_is_alternate(true);
+            renderer.render_display(&display);
+            assert!(renderer.get_images().is_empty());
+            display.add_inline_image(create_image(Vector2::new(1,1)), Vector2::new(1,1));
+            display.set_is_alternate(false);
+            assert_eq!(display.get_image_placements().len(), 1);
+        }
+
+        #[test]
+        fn evicted_images_are_freed() {
+            let mut display = TerminalDisplay::default();
+            display.set_size(Vector2::new(8,4));
+            display.set_scrollback_buffer(ScrollbackBuffer::new(1));
+            let display = Arc::new(Mutex::new(display));
+            display.lock().unwrap().add_inline_image(create_image(Vector2::new(2,2)), Vector2::new(2,2));
+            write_bytes(display.clone(), "x\r\n".repeat(2000).as_bytes());
+            let mut display = display.lock().unwrap();
+            assert!(display.get_scrollback().get_evicted_line_count() > 0);
+            assert!(display.get_image_placements().is_empty());
+            display.swap_buffers();
+            assert!(display.get_front_buffer().read().unwrap().get_image_placements().is_empty());
+            // new images are relative to the oldest line that is left
+            display.add_inline_image(create_image(Vector2::new(1,1)), Vector2::new(1,1));
+            let placements = display.get_image_placements();
+            assert_eq!(placements.len(), 1);
+            assert_eq!(display.get_lines_text(placements[0].line, placements[0].line+1), "");
+            assert_eq!(display.get_lines_text(placements[0].line-1, placements[0].line), "x");
+        }
+
+        #[test]
+        fn registry_frees_oldest_images() {
+            let mut registry = ImageRegistry::default();
+            let first = registry.add(0, 0, Vector2::new(1,2), create_image(Vector2::new(1,2)));
+            let second = registry.add(2, 0, Vector2::new(1,1), create_image(Vector2::new(1,1)));
+            assert_ne!(first, second);
+            assert_eq!(registry.get_total_bytes(), 3*10*10*4);
+            // freed once every row is evicted
+            registry.evict_lines_before(1);
+            assert_eq!(registry.len(), 2);
+            registry.evict_lines_before(2);
+            assert_eq!(registry.get_placements().map(|placement| placement.id).collect::<Vec<_>>(), vec![second]);
+            assert_eq!(registry.get_total_bytes(), 10*10*4);
+            registry.clear();
+            assert!(registry.is_empty());
+            assert_eq!(registry.get_total_bytes(), 0);
+        }
+    }
+}
@@ -1,27 +1,52 @@
 use bitflags::bitflags;
-use vt100::common::Rgb8;
+use vt100::common::{Rgb8, UnderlineStyle};
 
 bitflags! {
     #[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
-    pub struct StyleFlags: u8 {
-        const None          = 0b0000_0000;
-        const Bold          = 0b0000_0001;
-        const Dim           = 0b0000_0010;
-        const Italic        = 0b0000_0100;
-        const Underline     = 0b0000_1000;
-        const Blinking      = 0b0001_0000;
-        const Inverse       = 0b0010_0000;
-        const Hidden        = 0b0100_0000;
-        const Strikethrough = 0b1000_0000;
-        const _ = 0u8;
+    pub struct StyleFlags: u16 {
+        const None          = 0b000_0000_0000;
+        const Bold          = 0b000_0000_0001;
+        const Dim           = 0b000_0000_0010;
+        const Italic        = 0b000_0000_0100;
+        const Underline     = 0b000_0000_1000;
+        const Blinking      = 0b000_0001_0000;
+        const Inverse       = 0b000_0010_0000;
+        const Hidden        = 0b000_0100_0000;
+        const Strikethrough = 0b000_1000_0000;
+        // only set by the renderer on the cell underneath a visible cursor
+        const Cursor        = 0b001_0000_0000;
+        // set on cells covered by an inline image so their text isn't drawn over it
+        const Image         = 0b010_0000_0000;
+        // blank cell after a wide character that the right half of its glyph is drawn into
+        const WideSpacer    = 0b100_0000_0000;
+        const _ = 0u16;
     }
 }
 
+// East asian wide characters take up two cells
+pub(crate) fn is_wide_character(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF |
+        0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF |
+        0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F |
+        0x1F900..=0x1F9FF | 0x20000..=0x3FFFD
+    )
+}
+
 #[derive(Clone,Copy,Debug,PartialEq,Eq)]
 pub struct Pen {
     pub background_colour: Rgb8,
     pub foreground_colour: Rgb8,
+    // colour table entries the colours were set from which are looked up again when rendered
+    pub background_index: Option<u8>,
+    pub foreground_index: Option<u8>,
     pub style_flags: StyleFlags,
+    pub underline_style: UnderlineStyle,
+    // uses foreground colour if not set
+    pub underline_colour: Option<Rgb8>,
+    pub underline_index: Option<u8>,
+    // index into the hyperlink table of the display set by OSC 8
+    pub hyperlink: Option<u32>,
 }
 
 impl Default for Pen {
@@ -29,11 +54,34 @@ impl Default for Pen {
         Self {
             background_colour: Rgb8 { r:0, g:0, b:0 },
             foreground_colour: Rgb8 { r:255, g:255, b:255 },
+            background_index: None,
+            foreground_index: None,
             style_flags: StyleFlags::None,
+            underline_style: UnderlineStyle::None,
+            underline_colour: None,
+            underline_index: None,
+            hyperlink: None,
         }
     }
 }
 
+impl Pen {
+    pub(crate) fn set_background_index(&mut self, index: u8, colour_table: &[Rgb8; 256]) {
+        self.background_colour = colour_table[index as usize];
+        self.background_index = Some(index);
+    }
+
+    pub(crate) fn set_foreground_index(&mut self, index: u8, colour_table: &[Rgb8; 256]) {
+        self.foreground_colour = colour_table[index as usize];
+        self.foreground_index = Some(index);
+    }
+
+    pub(crate) fn set_underline_index(&mut self, index: u8, colour_table: &[Rgb8; 256]) {
+        self.underline_colour = Some(colour_table[index as usize]);
+        self.underline_index = Some(index);
+    }
+}
+
 #[derive(Clone,Copy,Debug,PartialEq,Eq)]
 pub struct Cell {
     pub character: char,
@@ -0,0 +1,166 @@
+use cgmath::Vector2;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+// Every number is little endian
+// Header: magic, version (u32), start time in milliseconds since the unix epoch (u64), columns (u32), rows (u32)
+// Frame: kind (u8), microseconds since the start (u64), then the data of the frame
+pub const RECORDING_MAGIC: [u8; 8] = *b"WGPUTREC";
+pub const RECORDING_VERSION: u32 = 1;
+
+const FRAME_OUTPUT: u8 = 0;
+const FRAME_RESIZE: u8 = 1;
+
+#[derive(Debug,Error)]
+pub enum RecordingError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("file is not a terminal recording")]
+    InvalidMagic,
+    #[error("unsupported recording version {0}")]
+    UnsupportedVersion(u32),
+    #[error("unknown frame kind {0}")]
+    UnknownFrame(u8),
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct RecordingHeader {
+    pub start_time_ms: u64,
+    pub grid_size: Vector2<usize>,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum RecordingEvent {
+    // bytes read from the process before they were parsed
+    Output(Vec<u8>),
+    Resize(Vector2<usize>),
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct RecordingFrame {
+    // time since the recording started
+    pub elapsed: Duration,
+    pub event: RecordingEvent,
+}
+
+// Writes process output and resizes of a terminal as they happen
+pub struct Recorder {
+    // unset after a failed write so a full disk doesn't log every read
+    writer: Option<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn new(mut writer: Box<dyn Write + Send>, grid_size: Vector2<usize>) -> std::io::Result<Self> {
+        let start_time_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis() as u64).unwrap_or(0);
+        writer.write_all(&RECORDING_MAGIC)?;
+        writer.write_all(&RECORDING_VERSION.to_le_bytes())?;
+        writer.write_all(&start_time_ms.to_le_bytes())?;
+        writer.write_all(&(grid_size.x as u32).to_le_bytes())?;
+        writer.write_all(&(grid_size.y as u32).to_le_bytes())?;
+        writer.flush()?;
+        Ok(Self {
+            writer: Some(writer),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record_output(&mut self, data: &[u8]) {
+        self.write_frame(FRAME_OUTPUT, &[&(data.len() as u32).to_le_bytes(), data]);
+    }
+
+    pub fn record_resize(&mut self, grid_size: Vector2<usize>) {
+        self.write_frame(FRAME_RESIZE, &[&(grid_size.x as u32).to_le_bytes(), &(grid_size.y as u32).to_le_bytes()]);
+    }
+
+    // Each frame is flushed so the recording is still readable if the terminal crashes
+    fn write_frame(&mut self, kind: u8, parts: &[&[u8]]) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let elapsed = self.start.elapsed().as_micros() as u64;
+        if let Err(err) = write_frame_parts(writer, kind, elapsed, parts) {
+            log::error!("Failed to write terminal recording, stopping recording: {:?}", err);
+            self.writer = None;
+        }
+    }
+}
+
+fn write_frame_parts(writer: &mut dyn Write, kind: u8, elapsed: u64, parts: &[&[u8]]) -> std::io::Result<()> {
+    writer.write_all(&[kind])?;
+    writer.write_all(&elapsed.to_le_bytes())?;
+    for part in parts {
+        writer.write_all(part)?;
+    }
+    writer.flush()
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut data = [0u8; 4];
+    reader.read_exact(&mut data)?;
+    Ok(u32::from_le_bytes(data))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut data = [0u8; 8];
+    reader.read_exact(&mut data)?;
+    Ok(u64::from_le_bytes(data))
+}
+
+pub struct RecordingReader<R: Read> {
+    reader: R,
+    header: RecordingHeader,
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, RecordingError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).map_err(|err| match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => RecordingError::InvalidMagic,
+            _ => RecordingError::Io(err),
+        })?;
+        if magic != RECORDING_MAGIC {
+            return Err(RecordingError::InvalidMagic);
+        }
+        let version = read_u32(&mut reader)?;
+        if version != RECORDING_VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+        let start_time_ms = read_u64(&mut reader)?;
+        let columns = read_u32(&mut reader)? as usize;
+        let rows = read_u32(&mut reader)? as usize;
+        Ok(Self {
+            reader,
+            header: RecordingHeader { start_time_ms, grid_size: Vector2::new(columns, rows) },
+        })
+    }
+
+    pub fn get_header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    // Returns None at the end of the recording
+    pub fn read_frame(&mut self) -> Result<Option<RecordingFrame>, RecordingError> {
+        let mut kind = [0u8; 1];
+        if self.reader.read(&mut kind)? == 0 {
+            return Ok(None);
+        }
+        let elapsed = Duration::from_micros(read_u64(&mut self.reader)?);
+        let event = match kind[0] {
+            FRAME_OUTPUT => {
+                let length = read_u32(&mut self.reader)? as usize;
+                let mut data = vec![0u8; length];
+                self.reader.read_exact(data.as_mut_slice())?;
+                RecordingEvent::Output(data)
+            },
+            FRAME_RESIZE => {
+                let columns = read_u32(&mut self.reader)? as usize;
+                let rows = read_u32(&mut self.reader)? as usize;
+                RecordingEvent::Resize(Vector2::new(columns, rows))
+            },
+            kind => return Err(RecordingError::UnknownFrame(kind)),
+        };
+        Ok(Some(RecordingFrame { elapsed, event }))
+    }
+}
@@ -1,5 +1,6 @@
 use circular_buffer::{CircularBuffer, get_allocation_granularity};
 use crate::primitives::Cell;
+use crate::viewport::LineStatus;
 
 #[derive(Clone,Copy,Default,Debug,PartialEq,Eq)]
 pub struct Line {
@@ -15,6 +16,8 @@ pub struct ScrollbackBuffer {
     total_lines: usize,
     cells_oldest_index: usize,
     total_cells: usize,
+    // lines dropped from the front so positions recorded outside the buffer can be rebased
+    total_evicted_lines: usize,
 }
 
 fn greatest_common_denominator(a: usize, b: usize) -> usize {
@@ -33,6 +36,9 @@ fn lowest_common_multiple(a: usize, b: usize) -> usize {
     }
 }
 
+// Long lines take up more cells so this only sets the starting ratio of cells to lines
+const AVERAGE_CELLS_PER_LINE: usize = 128;
+
 impl Default for ScrollbackBuffer {
     fn default() -> Self {
         let allocation_granularity = get_allocation_granularity();
@@ -40,10 +46,28 @@ impl Default for ScrollbackBuffer {
         let cell_size = std::mem::size_of::<Cell>();
         let total_line_bytes = lowest_common_multiple(allocation_granularity, line_size) * 16;
         let total_cell_bytes = lowest_common_multiple(allocation_granularity, cell_size) * 128;
-        let total_lines = total_line_bytes / line_size;
-        let total_cells = total_cell_bytes / cell_size;
-        log::info!("[scrollback-buffer] N*sizeof(Line)={}*{}={}", total_lines, line_size, total_line_bytes);
-        log::info!("[scrollback-buffer] N*sizeof(Cell)={}*{}={}", total_cells, cell_size, total_cell_bytes);
+        Self::with_capacity(total_line_bytes / line_size, total_cell_bytes / cell_size)
+    }
+}
+
+impl ScrollbackBuffer {
+    // Capacity is rounded up so that both buffers wrap around on an allocation boundary
+    pub fn new(max_lines: usize) -> Self {
+        let allocation_granularity = get_allocation_granularity();
+        let round_up = |total: usize, element_size: usize| {
+            let block = lowest_common_multiple(allocation_granularity, element_size) / element_size;
+            total.div_ceil(block).max(1) * block
+        };
+        let total_lines = round_up(max_lines, std::mem::size_of::<Line>());
+        let total_cells = round_up(max_lines.saturating_mul(AVERAGE_CELLS_PER_LINE), std::mem::size_of::<Cell>());
+        Self::with_capacity(total_lines, total_cells)
+    }
+
+    fn with_capacity(total_lines: usize, total_cells: usize) -> Self {
+        let line_size = std::mem::size_of::<Line>();
+        let cell_size = std::mem::size_of::<Cell>();
+        log::info!("[scrollback-buffer] N*sizeof(Line)={}*{}={}", total_lines, line_size, total_lines*line_size);
+        log::info!("[scrollback-buffer] N*sizeof(Cell)={}*{}={}", total_cells, cell_size, total_cells*cell_size);
         let lines = CircularBuffer::new(total_lines).unwrap();
         let cells = CircularBuffer::new(total_cells).unwrap();
         Self {
@@ -53,11 +77,15 @@ impl Default for ScrollbackBuffer {
             total_lines: 0,
             cells_oldest_index: 0,
             total_cells: 0,
+            total_evicted_lines: 0,
         }
     }
-}
 
-impl ScrollbackBuffer {
+    // Maximum number of lines before the oldest line is evicted
+    pub fn get_capacity(&self) -> usize {
+        self.lines.len()
+    }
+
     pub fn get_lines(&self) -> &[Line] {
         &self.lines[self.lines_oldest_index..(self.lines_oldest_index+self.total_lines)]
     }
@@ -66,6 +94,33 @@ impl ScrollbackBuffer {
         &self.cells[line.start..(line.start+line.length)] 
     }
 
+    pub fn get_line_count(&self) -> usize {
+        self.total_lines
+    }
+
+    pub fn get_evicted_line_count(&self) -> usize {
+        self.total_evicted_lines
+    }
+
+    // The newest line hasn't ended with a linebreak since it continues into the viewport
+    pub fn get_line(&self, index: usize) -> Option<(&[Cell], LineStatus)> {
+        let line = self.get_lines().get(index)?;
+        let status = LineStatus {
+            length: line.length,
+            is_linebreak: index+1 < self.total_lines,
+        };
+        Some((self.get_row(line), status))
+    }
+
+    pub fn clear(&mut self) {
+        // newest line continues into the viewport so it keeps its position
+        self.total_evicted_lines += self.total_lines.saturating_sub(1);
+        self.lines_oldest_index = 0;
+        self.total_lines = 0;
+        self.cells_oldest_index = 0;
+        self.total_cells = 0;
+    }
+
     pub fn extend_current_line(&mut self, src_buf: &[Cell]) {
         let chunk_length = self.cells.len();
         for chunk in src_buf.chunks(chunk_length) {
@@ -87,6 +142,7 @@ impl ScrollbackBuffer {
         if self.total_lines == self.lines.len() {
             self.lines[self.lines_oldest_index] =  Line::default();
             self.total_lines -= 1;
+            self.total_evicted_lines += 1;
             self.lines_oldest_index = (self.lines_oldest_index + 1) % self.lines.len();
         }
         let line_index = self.get_free_line_index();
@@ -156,6 +212,7 @@ impl ScrollbackBuffer {
             // evict if start of the region lies within override range
             *line = Line::default();
             self.total_lines -= 1;
+            self.total_evicted_lines += 1;
             self.lines_oldest_index = (self.lines_oldest_index + 1) % self.lines.len();
         }
     }
@@ -1,10 +1,14 @@
 #![allow(clippy::type_complexity)]
+use std::collections::VecDeque;
 use std::thread::JoinHandle;
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard};
+use std::sync::mpsc::Receiver;
+use std::sync::atomic::{AtomicU64, Ordering};
 use vt100::{
     command::Command as Vt100Command,
     encoder::{
         Encoder as Vt100Encoder,
+        InputMode,
         KeyCode,
         MouseButton,
         MouseEvent,
@@ -15,24 +19,38 @@ use vt100::{
         ParserError as Vt100ParserError,
     },
     common::{
+        describe_private_mode,
+        CursorStyle,
         EraseMode,
         Rgb8,
         WindowAction,
         GraphicStyle,
+        UnderlineStyle,
     },
 };
 use crate::{
     primitives::{Pen, StyleFlags},
-    colour_table::{XTERM_COLOUR_TABLE, convert_u32_to_rgb},
-    terminal_parser::{TerminalParser, TerminalParserHandler},
-    terminal_display::TerminalDisplay,
+    terminal_parser::{TerminalParser, TerminalParserHandler, TerminalParserMetrics},
+    terminal_display::{FrontBuffer, TerminalDisplay},
+    colour_table::create_colour_table,
+    scrollback_buffer::ScrollbackBuffer,
     utf8_parser::ParserError as Utf8ParserError,
+    image_registry::decode_inline_image,
+    recording::Recorder,
 };
 use cgmath::Vector2;
 use crossbeam_channel::{
     Sender,
     bounded as channel,
 };
+use circular_buffer_channel::spsc_channel;
+
+// Size of each read from the process
+const READ_BUFFER_SIZE: usize = 8192;
+// Bytes read from the process that can be waiting to be parsed before reads are blocked
+const READ_CHANNEL_SIZE: usize = 1 << 20;
+// Up to this many bytes are parsed before the display is published
+const PARSE_BUFFER_SIZE: usize = 1 << 16;
 
 // Some operating systems set/get terminal parameters over a separate pipe instead of stdout/stdin
 // On linux this is ioctl and windows this is conpty
@@ -41,23 +59,53 @@ pub enum TerminalIOControl {
     SetSize(Vector2<usize>),
 }
 
-#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+#[derive(Clone,Debug,PartialEq,Eq)]
 pub enum TerminalUserEvent {
     MousePress(MouseButton),
     MouseRelease(MouseButton),
     MouseMove(Vector2<usize>),
     KeyPress(KeyCode),
     KeyRelease(KeyCode),
+    // Grid and its size in pixels are updated together so that events in between never see a mismatch
+    Resize { window_size: Vector2<usize>, grid_size: Vector2<usize> },
+    #[deprecated(note = "use TerminalUserEvent::Resize which also updates the grid size")]
     WindowResize(Vector2<usize>),
     WindowFocus(bool),
+    #[deprecated(note = "use TerminalUserEvent::Resize which also updates the window size")]
     GridResize(Vector2<usize>),
     SetIsNewlineCarriageReturn(bool),
+    // Refreshes window even if the parser hasn't requested it
+    ForceRedraw,
+    ClearScrollback,
+    // Wrapped in bracketed paste markers if requested by the process
+    PasteText(String),
+    // Text typed without key presses such as committed input method composition
+    InputText(String),
+    // Written to the process as is such as replies to window reports
+    WriteReply(Vec<u8>),
 }
 
 pub struct Terminal {
+    reader_thread: Option<JoinHandle<()>>,
     parser_thread: Option<JoinHandle<()>>,
     user_thread: (Sender<TerminalUserEvent>, JoinHandle<()>),
     display: Arc<Mutex<TerminalDisplay>>,
+    encoder: Arc<Mutex<Vt100Encoder>>,
+    front_buffer: Arc<RwLock<FrontBuffer>>,
+    parser_metrics: Arc<Mutex<TerminalParserMetrics>>,
+    total_bytes_read: Arc<AtomicU64>,
+    window_action: Arc<Mutex<Box<dyn FnMut(WindowAction) + Send>>>,
+}
+
+// Unset values use the terminal's own defaults
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct TerminalSettings {
+    pub scrollback_lines: Option<usize>,
+    // 16 base colours of the 256 colour table
+    pub palette: Option<[Rgb8; 16]>,
+    pub cursor_style: Option<CursorStyle>,
+    pub foreground_colour: Option<Rgb8>,
+    pub background_colour: Option<Rgb8>,
 }
 
 pub struct TerminalBuilder {
@@ -66,53 +114,162 @@ pub struct TerminalBuilder {
     pub process_ioctl: Box<dyn FnMut(TerminalIOControl) + Send>,
     pub window_action: Box<dyn FnMut(WindowAction) + Send>,
     pub is_newline_carriage_return: bool,
+    pub settings: TerminalSettings,
+    // process output and resizes are recorded to this so they can be replayed
+    pub record_output: Option<Box<dyn std::io::Write + Send>>,
+    // sizes chosen by the process itself such as a replayed recording
+    // each is applied before the output of the next read and isn't sent back through process_ioctl
+    pub process_resize: Option<Receiver<Vector2<usize>>>,
+    // called on the parser thread once the process has closed its output and all of it has been parsed
+    pub on_parser_exit: Option<Box<dyn FnOnce() + Send>>,
+}
+
+// Output before this offset is parsed at the old size
+struct ProcessResize {
+    offset: u64,
+    size: Vector2<usize>,
 }
 
 impl Terminal {
     pub fn new(mut builder: TerminalBuilder) -> Self {
         let mut display = TerminalDisplay::default();
         display.set_is_newline_carriage_return(builder.is_newline_carriage_return);
-        // default colour table
-        let colour_table: Vec<Rgb8> = XTERM_COLOUR_TABLE
-            .iter()
-            .map(|c| convert_u32_to_rgb(*c))
-            .collect();
         let is_dark_mode = true;
-        let default_pen = if is_dark_mode {
-            Pen {
-                background_colour: colour_table[0],
-                foreground_colour: colour_table[15],
-                style_flags: StyleFlags::default(),
-            }
-        } else {
-            Pen {
-                background_colour: colour_table[15],
-                foreground_colour: colour_table[0],
-                style_flags: StyleFlags::default(),
-            }
+        let (background_index, foreground_index) = if is_dark_mode { (0, 15) } else { (15, 0) };
+        let settings = builder.settings;
+        if let Some(palette) = settings.palette.as_ref() {
+            display.colour_table = create_colour_table(palette);
+        }
+        let default_pen = Pen {
+            background_colour: settings.background_colour.unwrap_or(display.get_colour_table_entry(background_index)),
+            foreground_colour: settings.foreground_colour.unwrap_or(display.get_colour_table_entry(foreground_index)),
+            ..Pen::default()
         };
         display.set_default_pen(default_pen);
-        display.get_current_viewport_mut().pen = default_pen;
-        assert!(colour_table.len() == 256);
+        *display.get_pen_mut() = default_pen;
+        if let Some(style) = settings.cursor_style {
+            display.set_default_cursor_style(style);
+        }
+        if let Some(max_lines) = settings.scrollback_lines {
+            display.set_scrollback_buffer(ScrollbackBuffer::new(max_lines));
+        }
+        display.swap_buffers();
+        let front_buffer = display.get_front_buffer();
+        // shared by the reader thread for output and the user thread for resizes
+        let recorder = builder.record_output.take().and_then(|writer| {
+            match Recorder::new(writer, display.get_viewport_size()) {
+                Ok(recorder) => Some(Arc::new(Mutex::new(recorder))),
+                Err(err) => {
+                    log::error!("Failed to start terminal recording: {:?}", err);
+                    None
+                },
+            }
+        });
         // parser thread 
         let display = Arc::new(Mutex::new(display));
         let encoder = Arc::new(Mutex::new(Vt100Encoder::default()));
-        let mut parser_handler = ParserHandler {
-            display: display.clone(),
-            encoder: encoder.clone(),
-            window_action: builder.window_action,
-            colour_table,
-        };
-        let parser_thread = std::thread::spawn(move || {
-            let mut buffer = vec![0u8; 8192];
-            let mut terminal_parser = TerminalParser::default();
-            loop {
-                let total_read = (builder.process_read)(buffer.as_mut_slice());
-                if total_read == 0 {
-                    break;
+        // window actions can be sent from both the parser and user threads
+        let window_action = Arc::new(Mutex::new(builder.window_action));
+        let mut parser_handler = ParserHandler::new(display.clone(), encoder.clone(), Box::new({
+            let window_action = window_action.clone();
+            move |action: WindowAction| {
+                let mut window_action = window_action.lock().unwrap();
+                (*window_action)(action);
+            }
+        }));
+        let parser_metrics = Arc::new(Mutex::new(TerminalParserMetrics::default()));
+        // reading is split from parsing so a slow parse doesn't block a fast process from writing
+        let (read_tx, read_rx) = spsc_channel::<u8>(READ_CHANNEL_SIZE).expect("failed to create process read channel");
+        let (resize_tx, resize_rx) = std::sync::mpsc::channel::<ProcessResize>();
+        let total_bytes_read = Arc::new(AtomicU64::new(0));
+        let reader_thread = std::thread::spawn({
+            let total_bytes_read = total_bytes_read.clone();
+            let recorder = recorder.clone();
+            move || {
+                let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+                let mut offset: u64 = 0;
+                loop {
+                    let total_read = (builder.process_read)(buffer.as_mut_slice());
+                    // resizes are sent before the output so the parser has them once it reaches the offset
+                    if let Some(process_resize) = builder.process_resize.as_ref() {
+                        for size in process_resize.try_iter() {
+                            // recorded before the output it is applied before
+                            if let Some(recorder) = recorder.as_ref() {
+                                recorder.lock().unwrap().record_resize(Vector2::new(size.x.max(1), size.y.max(1)));
+                            }
+                            let _ = resize_tx.send(ProcessResize { offset, size });
+                        }
+                    }
+                    if total_read == 0 {
+                        break;
+                    }
+                    offset += total_read as u64;
+                    total_bytes_read.fetch_add(total_read as u64, Ordering::Relaxed);
+                    if let Some(recorder) = recorder.as_ref() {
+                        recorder.lock().unwrap().record_output(&buffer[..total_read]);
+                    }
+                    // parser thread has exited
+                    if read_tx.send_all(&buffer[..total_read]).is_err() {
+                        break;
+                    }
+                }
+                // dropping the sender lets the parser thread finish the remaining data and exit
+            }
+        });
+        let on_parser_exit = builder.on_parser_exit.take();
+        let parser_thread = std::thread::spawn({
+            let display = display.clone();
+            let window_action = window_action.clone();
+            let parser_metrics = parser_metrics.clone();
+            let encoder = encoder.clone();
+            move || {
+                let mut buffer = vec![0u8; PARSE_BUFFER_SIZE];
+                let mut terminal_parser = TerminalParser::default();
+                let mut total_parsed: u64 = 0;
+                let mut pending_resizes: VecDeque<ProcessResize> = VecDeque::new();
+                // drains everything read so far in one go
+                while let Ok(total_read) = read_rx.receive(buffer.as_mut_slice()) {
+                    pending_resizes.extend(resize_rx.try_iter());
+                    let mut src_buf = &buffer[..total_read];
+                    loop {
+                        while pending_resizes.front().is_some_and(|resize| resize.offset <= total_parsed) {
+                            let resize = pending_resizes.pop_front().unwrap();
+                            apply_process_resize(resize.size, &display, &encoder);
+                        }
+                        if src_buf.is_empty() {
+                            break;
+                        }
+                        // output is split at the next resize
+                        let total = match pending_resizes.front() {
+                            Some(resize) => ((resize.offset - total_parsed) as usize).min(src_buf.len()),
+                            None => src_buf.len(),
+                        };
+                        let (chunk, remaining) = src_buf.split_at(total);
+                        terminal_parser.parse_bytes(chunk, &mut parser_handler);
+                        total_parsed += total as u64;
+                        src_buf = remaining;
+                    }
+                    let display_update_start = std::time::Instant::now();
+                    display.lock().unwrap().swap_buffers();
+                    terminal_parser.add_display_update_time(display_update_start.elapsed());
+                    *parser_metrics.lock().unwrap() = *terminal_parser.get_metrics();
+                    // refreshes requested while parsing would have only seen the previous front buffer
+                    let mut window_action = window_action.lock().unwrap();
+                    (*window_action)(WindowAction::Refresh);
+                }
+                // resizes after the last output
+                pending_resizes.extend(resize_rx.try_iter());
+                if !pending_resizes.is_empty() {
+                    for resize in pending_resizes {
+                        apply_process_resize(resize.size, &display, &encoder);
+                    }
+                    display.lock().unwrap().swap_buffers();
+                    let mut window_action = window_action.lock().unwrap();
+                    (*window_action)(WindowAction::Refresh);
+                }
+                if let Some(on_parser_exit) = on_parser_exit {
+                    on_parser_exit();
                 }
-                let src_buf = &buffer[..total_read];
-                terminal_parser.parse_bytes(src_buf, &mut parser_handler);
             }
         });
         // user events thread
@@ -122,7 +279,9 @@ impl Terminal {
             encoder: encoder.clone(),
             process_write: builder.process_write,
             process_ioctl: builder.process_ioctl,
+            window_action: window_action.clone(),
             mouse_position: Vector2::new(0,0),
+            recorder,
         };
         let user_thread = std::thread::spawn(move || {
             while let Ok(event) = user_rx.recv() {
@@ -131,99 +290,202 @@ impl Terminal {
         });
 
         Self {
+            reader_thread: Some(reader_thread),
             parser_thread: Some(parser_thread),
             user_thread: (user_tx, user_thread),
             display,
+            encoder,
+            front_buffer,
+            parser_metrics,
+            total_bytes_read,
+            window_action,
         }
     }
 
+    // Writes data to the display as if it was sent by the process
+    // This should only be used after the process has exited since the parser thread isn't synchronised with it
+    pub fn write_to_display(&mut self, data: &[u8]) {
+        let mut parser_handler = self.create_parser_handler();
+        let mut terminal_parser = TerminalParser::default();
+        terminal_parser.parse_bytes(data, &mut parser_handler);
+        self.publish_display();
+    }
+
+    // Applies the command as if it was parsed from the process without encoding it into bytes
+    // This has the same restriction as write_to_display
+    pub fn apply_command(&mut self, command: Vt100Command) {
+        let mut parser_handler = self.create_parser_handler();
+        parser_handler.on_vt100(command);
+        self.publish_display();
+    }
+
+    pub(crate) fn create_parser_handler(&self) -> ParserHandler {
+        ParserHandler::new(self.display.clone(), self.encoder.clone(), Box::new({
+            let window_action = self.window_action.clone();
+            move |action: WindowAction| {
+                let mut window_action = window_action.lock().unwrap();
+                (*window_action)(action);
+            }
+        }))
+    }
+
+    pub(crate) fn publish_display(&mut self) {
+        self.display.lock().unwrap().swap_buffers();
+        let mut window_action = self.window_action.lock().unwrap();
+        (*window_action)(WindowAction::Refresh);
+    }
+
+    // Returns once the process has closed its output and everything it wrote has been parsed
     pub fn join_parser_thread(&mut self) {
         if let Some(thread) = self.parser_thread.take() {
+            if let Err(err) = thread.join() {
+                log::error!("Failed to join terminal parser thread: {:?}", err);
+            }
+        }
+        // parser thread only exits after the reader thread has closed the channel unless it panicked
+        if let Some(thread) = self.reader_thread.take() {
             if let Err(err) = thread.join() {
                 log::error!("Failed to join terminal read thread: {:?}", err);
             }
         }
     }
 
+    // Copy of the parser metrics as of the last chunk read from the process
+    pub fn get_parser_metrics(&self) -> TerminalParserMetrics {
+        *self.parser_metrics.lock().unwrap()
+    }
+
+    // Bytes read from the process so far including those still waiting to be parsed
+    pub fn get_total_bytes_read(&self) -> u64 {
+        self.total_bytes_read.load(Ordering::Relaxed)
+    }
+
+    // Bytes are written by the user event thread so they stay ordered with encoded key presses
+    pub fn send_raw(&self, data: &[u8]) {
+        if let Err(err) = self.user_thread.0.send(TerminalUserEvent::WriteReply(data.to_vec())) {
+            log::error!("Failed to send raw data to process: {:?}", err);
+        }
+    }
+
     pub fn get_user_event_handler(&self) -> Sender<TerminalUserEvent> {
         self.user_thread.0.clone()
     }
 
-    pub fn get_display(&mut self) -> MutexGuard<'_, TerminalDisplay> {
+    // Mouse events are only reported to the process if tracking is enabled
+    pub fn get_mouse_tracking_mode(&self) -> MouseTrackingMode {
+        self.encoder.lock().unwrap().mouse_tracking_mode
+    }
+
+    pub fn get_display(&self) -> MutexGuard<'_, TerminalDisplay> {
         let display = self.display.lock().unwrap();
         display
     }
+
+    // Latest viewport published by the parser which doesn't require locking the display
+    pub fn get_front_buffer(&self) -> RwLockReadGuard<'_, FrontBuffer> {
+        self.front_buffer.read().unwrap()
+    }
 }
 
-struct ParserHandler {
+pub(crate) struct ParserHandler {
     display: Arc<Mutex<TerminalDisplay>>,
     encoder: Arc<Mutex<Vt100Encoder>>,
     window_action: Box<dyn FnMut(WindowAction) + Send>,
-    colour_table: Vec<Rgb8>,
 }
 
 impl ParserHandler {
+    pub(crate) fn new(
+        display: Arc<Mutex<TerminalDisplay>>,
+        encoder: Arc<Mutex<Vt100Encoder>>,
+        window_action: Box<dyn FnMut(WindowAction) + Send>,
+    ) -> Self {
+        Self {
+            display,
+            encoder,
+            window_action,
+        }
+    }
+
     fn set_graphic_style(&mut self, style: GraphicStyle) {
         let mut display = self.display.lock().unwrap();
-        let viewport = display.get_current_viewport_mut();
+        let colour_table = display.colour_table;
+        let default_pen = display.get_default_pen();
+        let pen = display.get_pen_mut();
         match style {
-            GraphicStyle::ResetAll => { viewport.pen = viewport.default_pen; },
+            // hyperlinks are only ended by OSC 8
+            GraphicStyle::ResetAll => { *pen = Pen { hyperlink: pen.hyperlink, ..default_pen }; },
             // flags
-            GraphicStyle::EnableBold => { viewport.pen.style_flags |= StyleFlags::Bold; },
-            GraphicStyle::EnableDim => { viewport.pen.style_flags |= StyleFlags::Dim; },
-            GraphicStyle::EnableItalic => { viewport.pen.style_flags |= StyleFlags::Italic; },
-            GraphicStyle::EnableUnderline => { viewport.pen.style_flags |= StyleFlags::Underline; },
-            GraphicStyle::EnableBlinking => { viewport.pen.style_flags |= StyleFlags::Blinking; },
-            GraphicStyle::EnableInverse => { viewport.pen.style_flags |= StyleFlags::Inverse; },
-            GraphicStyle::EnableHidden => { viewport.pen.style_flags |= StyleFlags::Hidden; },
-            GraphicStyle::EnableStrikethrough => { viewport.pen.style_flags |= StyleFlags::Strikethrough; },
-            GraphicStyle::DisableWeight => { viewport.pen.style_flags &= !(StyleFlags::Bold | StyleFlags::Dim); },
-            GraphicStyle::DisableItalic => { viewport.pen.style_flags &= !StyleFlags::Italic; },
-            GraphicStyle::DisableUnderline => { viewport.pen.style_flags &= !StyleFlags::Underline; },
-            GraphicStyle::DisableBlinking => { viewport.pen.style_flags &= !StyleFlags::Blinking; },
-            GraphicStyle::DisableInverse => { viewport.pen.style_flags &= !StyleFlags::Inverse; },
-            GraphicStyle::DisableHidden => { viewport.pen.style_flags &= !StyleFlags::Hidden; },
-            GraphicStyle::DisableStrikethrough => { viewport.pen.style_flags &= !StyleFlags::Strikethrough; },
+            GraphicStyle::EnableBold => { pen.style_flags |= StyleFlags::Bold; },
+            GraphicStyle::EnableDim => { pen.style_flags |= StyleFlags::Dim; },
+            GraphicStyle::EnableItalic => { pen.style_flags |= StyleFlags::Italic; },
+            GraphicStyle::EnableUnderline => {
+                pen.style_flags |= StyleFlags::Underline;
+                pen.underline_style = UnderlineStyle::Straight;
+            },
+            GraphicStyle::EnableBlinking => { pen.style_flags |= StyleFlags::Blinking; },
+            GraphicStyle::EnableInverse => { pen.style_flags |= StyleFlags::Inverse; },
+            GraphicStyle::EnableHidden => { pen.style_flags |= StyleFlags::Hidden; },
+            GraphicStyle::EnableStrikethrough => { pen.style_flags |= StyleFlags::Strikethrough; },
+            GraphicStyle::DisableWeight => { pen.style_flags &= !(StyleFlags::Bold | StyleFlags::Dim); },
+            GraphicStyle::DisableItalic => { pen.style_flags &= !StyleFlags::Italic; },
+            GraphicStyle::DisableUnderline => {
+                pen.style_flags &= !StyleFlags::Underline;
+                pen.underline_style = UnderlineStyle::None;
+            },
+            GraphicStyle::DisableBlinking => { pen.style_flags &= !StyleFlags::Blinking; },
+            GraphicStyle::DisableInverse => { pen.style_flags &= !StyleFlags::Inverse; },
+            GraphicStyle::DisableHidden => { pen.style_flags &= !StyleFlags::Hidden; },
+            GraphicStyle::DisableStrikethrough => { pen.style_flags &= !StyleFlags::Strikethrough; },
             // foreground colours
-            GraphicStyle::ForegroundBlack => { viewport.pen.foreground_colour = self.colour_table[0]; },
-            GraphicStyle::ForegroundRed => { viewport.pen.foreground_colour = self.colour_table[1]; },
-            GraphicStyle::ForegroundGreen => { viewport.pen.foreground_colour = self.colour_table[2]; },
-            GraphicStyle::ForegroundYellow => { viewport.pen.foreground_colour = self.colour_table[3]; },
-            GraphicStyle::ForegroundBlue => { viewport.pen.foreground_colour = self.colour_table[4]; },
-            GraphicStyle::ForegroundMagenta => { viewport.pen.foreground_colour = self.colour_table[5]; },
-            GraphicStyle::ForegroundCyan => { viewport.pen.foreground_colour = self.colour_table[6]; },
-            GraphicStyle::ForegroundWhite => { viewport.pen.foreground_colour = self.colour_table[7]; },
+            GraphicStyle::ForegroundBlack => { pen.set_foreground_index(0, &colour_table); },
+            GraphicStyle::ForegroundRed => { pen.set_foreground_index(1, &colour_table); },
+            GraphicStyle::ForegroundGreen => { pen.set_foreground_index(2, &colour_table); },
+            GraphicStyle::ForegroundYellow => { pen.set_foreground_index(3, &colour_table); },
+            GraphicStyle::ForegroundBlue => { pen.set_foreground_index(4, &colour_table); },
+            GraphicStyle::ForegroundMagenta => { pen.set_foreground_index(5, &colour_table); },
+            GraphicStyle::ForegroundCyan => { pen.set_foreground_index(6, &colour_table); },
+            GraphicStyle::ForegroundWhite => { pen.set_foreground_index(7, &colour_table); },
             GraphicStyle::ForegroundExtended => { log::info!("[vt100] GraphicStyle({:?})", style); },
-            GraphicStyle::ForegroundDefault => { viewport.pen.foreground_colour = viewport.default_pen.foreground_colour; },
+            GraphicStyle::ForegroundDefault => {
+                pen.foreground_colour = default_pen.foreground_colour;
+                pen.foreground_index = default_pen.foreground_index;
+            },
             // background colours
-            GraphicStyle::BackgroundBlack => { viewport.pen.background_colour = self.colour_table[0]; },
-            GraphicStyle::BackgroundRed => { viewport.pen.background_colour = self.colour_table[1]; },
-            GraphicStyle::BackgroundGreen => { viewport.pen.background_colour = self.colour_table[2]; },
-            GraphicStyle::BackgroundYellow => { viewport.pen.background_colour = self.colour_table[3]; },
-            GraphicStyle::BackgroundBlue => { viewport.pen.background_colour = self.colour_table[4]; },
-            GraphicStyle::BackgroundMagenta => { viewport.pen.background_colour = self.colour_table[5]; },
-            GraphicStyle::BackgroundCyan => { viewport.pen.background_colour = self.colour_table[6]; },
-            GraphicStyle::BackgroundWhite => { viewport.pen.background_colour = self.colour_table[7]; },
+            GraphicStyle::BackgroundBlack => { pen.set_background_index(0, &colour_table); },
+            GraphicStyle::BackgroundRed => { pen.set_background_index(1, &colour_table); },
+            GraphicStyle::BackgroundGreen => { pen.set_background_index(2, &colour_table); },
+            GraphicStyle::BackgroundYellow => { pen.set_background_index(3, &colour_table); },
+            GraphicStyle::BackgroundBlue => { pen.set_background_index(4, &colour_table); },
+            GraphicStyle::BackgroundMagenta => { pen.set_background_index(5, &colour_table); },
+            GraphicStyle::BackgroundCyan => { pen.set_background_index(6, &colour_table); },
+            GraphicStyle::BackgroundWhite => { pen.set_background_index(7, &colour_table); },
             GraphicStyle::BackgroundExtended => { log::info!("[vt100] GraphicStyle({:?})", style); },
-            GraphicStyle::BackgroundDefault => { viewport.pen.background_colour = viewport.default_pen.background_colour; },
+            GraphicStyle::BackgroundDefault => {
+                pen.background_colour = default_pen.background_colour;
+                pen.background_index = default_pen.background_index;
+            },
+            GraphicStyle::UnderlineColourDefault => {
+                pen.underline_colour = None;
+                pen.underline_index = None;
+            },
             // bright foreground colours
-            GraphicStyle::BrightForegroundBlack => { viewport.pen.foreground_colour = self.colour_table[8]; },
-            GraphicStyle::BrightForegroundRed => { viewport.pen.foreground_colour = self.colour_table[9]; },
-            GraphicStyle::BrightForegroundGreen => { viewport.pen.foreground_colour = self.colour_table[10]; },
-            GraphicStyle::BrightForegroundYellow => { viewport.pen.foreground_colour = self.colour_table[11]; },
-            GraphicStyle::BrightForegroundBlue => { viewport.pen.foreground_colour = self.colour_table[12]; },
-            GraphicStyle::BrightForegroundMagenta => { viewport.pen.foreground_colour = self.colour_table[13]; },
-            GraphicStyle::BrightForegroundCyan => { viewport.pen.foreground_colour = self.colour_table[14]; },
-            GraphicStyle::BrightForegroundWhite => { viewport.pen.foreground_colour = self.colour_table[15]; },
+            GraphicStyle::BrightForegroundBlack => { pen.set_foreground_index(8, &colour_table); },
+            GraphicStyle::BrightForegroundRed => { pen.set_foreground_index(9, &colour_table); },
+            GraphicStyle::BrightForegroundGreen => { pen.set_foreground_index(10, &colour_table); },
+            GraphicStyle::BrightForegroundYellow => { pen.set_foreground_index(11, &colour_table); },
+            GraphicStyle::BrightForegroundBlue => { pen.set_foreground_index(12, &colour_table); },
+            GraphicStyle::BrightForegroundMagenta => { pen.set_foreground_index(13, &colour_table); },
+            GraphicStyle::BrightForegroundCyan => { pen.set_foreground_index(14, &colour_table); },
+            GraphicStyle::BrightForegroundWhite => { pen.set_foreground_index(15, &colour_table); },
             // bright background colours
-            GraphicStyle::BrightBackgroundBlack => { viewport.pen.background_colour = self.colour_table[8]; },
-            GraphicStyle::BrightBackgroundRed => { viewport.pen.background_colour = self.colour_table[9]; },
-            GraphicStyle::BrightBackgroundGreen => { viewport.pen.background_colour = self.colour_table[10]; },
-            GraphicStyle::BrightBackgroundYellow => { viewport.pen.background_colour = self.colour_table[11]; },
-            GraphicStyle::BrightBackgroundBlue => { viewport.pen.background_colour = self.colour_table[12]; },
-            GraphicStyle::BrightBackgroundMagenta => { viewport.pen.background_colour = self.colour_table[13]; },
-            GraphicStyle::BrightBackgroundCyan => { viewport.pen.background_colour = self.colour_table[14]; },
-            GraphicStyle::BrightBackgroundWhite => { viewport.pen.background_colour = self.colour_table[15]; },
+            GraphicStyle::BrightBackgroundBlack => { pen.set_background_index(8, &colour_table); },
+            GraphicStyle::BrightBackgroundRed => { pen.set_background_index(9, &colour_table); },
+            GraphicStyle::BrightBackgroundGreen => { pen.set_background_index(10, &colour_table); },
+            GraphicStyle::BrightBackgroundYellow => { pen.set_background_index(11, &colour_table); },
+            GraphicStyle::BrightBackgroundBlue => { pen.set_background_index(12, &colour_table); },
+            GraphicStyle::BrightBackgroundMagenta => { pen.set_background_index(13, &colour_table); },
+            GraphicStyle::BrightBackgroundCyan => { pen.set_background_index(14, &colour_table); },
+            GraphicStyle::BrightBackgroundWhite => { pen.set_background_index(15, &colour_table); },
         }
     }
 
@@ -237,6 +499,9 @@ impl TerminalParserHandler for ParserHandler {
             viewport.write_ascii(*b);
         }
         let window_action = &mut self.window_action;
+        if buf.contains(&b'\x07') {
+            window_action(WindowAction::Bell);
+        }
         window_action(WindowAction::Refresh);
     }
 
@@ -260,7 +525,35 @@ impl TerminalParserHandler for ParserHandler {
         let window_action = &mut self.window_action;
         match c {
             Vt100Command::SetHyperlink(link) => {
-                log::info!("[vt100] SetHyperlink({})", link); 
+                // ESC ] 8 ; <params> ; <uri> where an empty uri ends the hyperlink
+                let uri = link.split_once(';').map_or("", |(_params, uri)| uri);
+                let mut display = self.display.lock().unwrap();
+                let hyperlink = if uri.is_empty() { None } else { display.add_hyperlink(uri) };
+                display.get_pen_mut().hyperlink = hyperlink;
+            },
+            Vt100Command::SetPromptMark(mark) => {
+                let mut display = self.display.lock().unwrap();
+                display.add_prompt_mark(mark);
+            },
+            Vt100Command::InlineImage(image) => {
+                // files sent with inline=0 are downloads which aren't supported
+                if !image.is_inline {
+                    log::info!("[vt100] Ignoring file download of {} bytes", image.data.len());
+                    return;
+                }
+                let (window_size, grid_size) = {
+                    let encoder = self.encoder.lock().unwrap();
+                    (encoder.window_size, encoder.grid_size)
+                };
+                let cell_size = Vector2::new((window_size.x/grid_size.x).max(1), (window_size.y/grid_size.y).max(1));
+                // decoded before locking the display so the renderer isn't blocked
+                match decode_inline_image(&image, cell_size, grid_size) {
+                    Ok((decoded, cells)) => {
+                        let mut display = self.display.lock().unwrap();
+                        display.add_inline_image(decoded, cells);
+                    },
+                    Err(err) => log::error!("[vt100] Failed to display inline image: {}", err),
+                }
             },
             // display
             Vt100Command::SetGraphicStyle(style) => {
@@ -268,32 +561,58 @@ impl TerminalParserHandler for ParserHandler {
             },
             Vt100Command::SetBackgroundColourRgb(rgb) => {
                 let mut display = self.display.lock().unwrap();
-                let viewport = display.get_current_viewport_mut();
-                viewport.pen.background_colour = rgb;
+                let pen = display.get_pen_mut();
+                pen.background_colour = rgb;
+                pen.background_index = None;
             },
             Vt100Command::SetForegroundColourRgb(rgb) => {
                 let mut display = self.display.lock().unwrap();
-                let viewport = display.get_current_viewport_mut();
-                viewport.pen.foreground_colour = rgb;
+                let pen = display.get_pen_mut();
+                pen.foreground_colour = rgb;
+                pen.foreground_index = None;
             },
             Vt100Command::SetBackgroundColourTable(index) => {
                 let mut display = self.display.lock().unwrap();
-                let viewport = display.get_current_viewport_mut();
-                let colour = self.colour_table[index as usize];
-                viewport.pen.background_colour = colour;
+                let colour_table = display.colour_table;
+                display.get_pen_mut().set_background_index(index, &colour_table);
             },
             Vt100Command::SetForegroundColourTable(index) => {
                 let mut display = self.display.lock().unwrap();
-                let viewport = display.get_current_viewport_mut();
-                let colour = self.colour_table[index as usize];
-                viewport.pen.foreground_colour = colour;
+                let colour_table = display.colour_table;
+                display.get_pen_mut().set_foreground_index(index, &colour_table);
+            },
+            Vt100Command::SetUnderlineStyle(style) => {
+                let mut display = self.display.lock().unwrap();
+                let pen = display.get_pen_mut();
+                pen.underline_style = style;
+                pen.style_flags.set(StyleFlags::Underline, style != UnderlineStyle::None);
+            },
+            Vt100Command::SetUnderlineColourRgb(rgb) => {
+                let mut display = self.display.lock().unwrap();
+                let pen = display.get_pen_mut();
+                pen.underline_colour = Some(rgb);
+                pen.underline_index = None;
+            },
+            Vt100Command::SetUnderlineColourTable(index) => {
+                let mut display = self.display.lock().unwrap();
+                let colour_table = display.colour_table;
+                display.get_pen_mut().set_underline_index(index, &colour_table);
+            },
+            Vt100Command::SetWarningBellVolume(volume) => {
+                let mut display = self.display.lock().unwrap();
+                display.set_bell_volume(volume);
+            },
+            Vt100Command::SetPaletteColour(index, colour) => {
+                let mut display = self.display.lock().unwrap();
+                display.set_colour_table_entry(index, colour);
+                window_action(WindowAction::Refresh);
             },
             // erase data
             Vt100Command::EraseInDisplay(mode) => match mode {
                 EraseMode::FromCursorToEnd => {
                     let mut display = self.display.lock().unwrap();
+                    let pen = *display.get_pen();
                     let viewport = display.get_current_viewport_mut();
-                    let pen = viewport.pen;
                     let size = viewport.get_size();
                     let cursor = viewport.get_cursor();
                     for y in (cursor.y+1)..size.y {
@@ -314,8 +633,8 @@ impl TerminalParserHandler for ParserHandler {
                 },
                 EraseMode::FromCursorToStart => {
                     let mut display = self.display.lock().unwrap();
+                    let pen = *display.get_pen();
                     let viewport = display.get_current_viewport_mut();
-                    let pen = viewport.pen;
                     let size = viewport.get_size();
                     let cursor = viewport.get_cursor();
                     for y in 0..cursor.y {
@@ -334,10 +653,15 @@ impl TerminalParserHandler for ParserHandler {
                     });
                     window_action(WindowAction::Refresh);
                 },
-                EraseMode::EntireDisplay | EraseMode::SavedLines => {
+                EraseMode::SavedLines => {
+                    let mut display = self.display.lock().unwrap();
+                    display.clear_scrollback();
+                    window_action(WindowAction::Refresh);
+                },
+                EraseMode::EntireDisplay => {
                     let mut display = self.display.lock().unwrap();
+                    let pen = *display.get_pen();
                     let viewport = display.get_current_viewport_mut();
-                    let pen = viewport.pen;
                     let size = viewport.get_size();
                     for y in 0..size.y {
                         let (line, status) = viewport.get_row_mut(y);
@@ -354,8 +678,8 @@ impl TerminalParserHandler for ParserHandler {
             Vt100Command::EraseInLine(mode) => match mode {
                 EraseMode::FromCursorToEnd => {
                     let mut display = self.display.lock().unwrap();
+                    let pen = *display.get_pen();
                     let viewport = display.get_current_viewport_mut();
-                    let pen = viewport.pen;
                     let size = viewport.get_size();
                     let cursor = viewport.get_cursor();
                     let (line, status) = viewport.get_row_mut(cursor.y);
@@ -369,8 +693,8 @@ impl TerminalParserHandler for ParserHandler {
                 },
                 EraseMode::FromCursorToStart => {
                     let mut display = self.display.lock().unwrap();
+                    let pen = *display.get_pen();
                     let viewport = display.get_current_viewport_mut();
-                    let pen = viewport.pen;
                     let cursor = viewport.get_cursor();
                     let (line, _) = viewport.get_row_mut(cursor.y);
                     line[..=cursor.x].iter_mut().for_each(|c| {
@@ -381,8 +705,8 @@ impl TerminalParserHandler for ParserHandler {
                 },
                 EraseMode::EntireDisplay | EraseMode::SavedLines => {
                     let mut display = self.display.lock().unwrap();
+                    let pen = *display.get_pen();
                     let viewport = display.get_current_viewport_mut();
-                    let pen = viewport.pen;
                     let size = viewport.get_size();
                     let cursor = viewport.get_cursor();
                     let (line, status) = viewport.get_row_mut(cursor.y);
@@ -397,8 +721,8 @@ impl TerminalParserHandler for ParserHandler {
             },
             Vt100Command::ReplaceWithSpaces(total) => {
                 let mut display = self.display.lock().unwrap();
+                let pen = *display.get_pen();
                 let viewport = display.get_current_viewport_mut();
-                let pen = viewport.pen;
                 let cursor = viewport.get_cursor();
                 let (line, _) = viewport.get_row_mut(cursor.y);
                 let region = &mut line[cursor.x..];
@@ -411,8 +735,8 @@ impl TerminalParserHandler for ParserHandler {
             },
             Vt100Command::InsertSpaces(total) => {
                 let mut display = self.display.lock().unwrap();
+                let pen = *display.get_pen();
                 let viewport = display.get_current_viewport_mut();
-                let pen = viewport.pen;
                 let cursor = viewport.get_cursor();
                 let (line, status) = viewport.get_row_mut(cursor.y);
                 let region = &mut line[cursor.x..];
@@ -442,7 +766,7 @@ impl TerminalParserHandler for ParserHandler {
                 let viewport = display.get_current_viewport_mut();
                 let cursor = viewport.get_cursor();
                 let size = viewport.get_size();
-                let lines_at_cursor = size.y-cursor.y;
+                let lines_at_cursor = size.y.saturating_sub(cursor.y);
                 let total_insert = (total_insert as usize).min(lines_at_cursor);
                 let total_copy = lines_at_cursor-total_insert;
                 viewport.copy_lines_within(cursor.y, cursor.y+total_insert, total_copy);
@@ -458,7 +782,7 @@ impl TerminalParserHandler for ParserHandler {
                 let viewport = display.get_current_viewport_mut();
                 let cursor = viewport.get_cursor();
                 let size = viewport.get_size();
-                let lines_at_cursor = size.y-cursor.y;
+                let lines_at_cursor = size.y.saturating_sub(cursor.y);
                 let total_delete = (total_delete as usize).min(lines_at_cursor);
                 let total_copy = lines_at_cursor-total_delete;
                 viewport.copy_lines_within(cursor.y+total_delete, cursor.y, total_copy);
@@ -583,6 +907,16 @@ impl TerminalParserHandler for ParserHandler {
                 display.cursor_status.style = style;
                 window_action(WindowAction::Refresh);
             },
+            Vt100Command::SetCursorColour(colour) => {
+                let mut display = self.display.lock().unwrap();
+                display.cursor_status.colour = Some(colour);
+                window_action(WindowAction::Refresh);
+            },
+            Vt100Command::ResetCursorColour => {
+                let mut display = self.display.lock().unwrap();
+                display.cursor_status.colour = None;
+                window_action(WindowAction::Refresh);
+            },
             // keyboard
             Vt100Command::SetKeypadMode(input_mode) => {
                 let mut encoder = self.encoder.lock().unwrap();
@@ -592,6 +926,26 @@ impl TerminalParserHandler for ParserHandler {
                 let mut encoder = self.encoder.lock().unwrap();
                 encoder.cursor_key_input_mode = input_mode;
             },
+            Vt100Command::SetAutomaticNewline => {
+                let mut display = self.display.lock().unwrap();
+                display.set_is_newline_carriage_return(true);
+                window_action(WindowAction::Refresh);
+            },
+            Vt100Command::SetNormalLinefeed => {
+                let mut display = self.display.lock().unwrap();
+                display.set_is_newline_carriage_return(false);
+                window_action(WindowAction::Refresh);
+            },
+            Vt100Command::SetInsertMode => {
+                let mut display = self.display.lock().unwrap();
+                display.set_insert_mode();
+                window_action(WindowAction::Refresh);
+            },
+            Vt100Command::SetReplaceMode => {
+                let mut display = self.display.lock().unwrap();
+                display.set_replace_mode();
+                window_action(WindowAction::Refresh);
+            },
             Vt100Command::SetBracketedPasteMode(is_bracketed) => {
                 let mut encoder = self.encoder.lock().unwrap();
                 encoder.is_bracketed_paste_mode = is_bracketed;
@@ -613,12 +967,30 @@ impl TerminalParserHandler for ParserHandler {
                 encoder.is_report_focus = is_report_focus;
             },
             // window
-            Vt100Command::WindowAction(action) => window_action(action),
+            Vt100Command::WindowAction(action) => {
+                if let WindowAction::SetWindowTitle(title) = &action {
+                    let mut display = self.display.lock().unwrap();
+                    display.set_title(title.as_str());
+                }
+                window_action(action);
+            },
             // alternate buffer
             Vt100Command::SetAlternateBuffer(is_alternate) => {
                 let mut display = self.display.lock().unwrap();
                 display.set_is_alternate(is_alternate);
             }
+            Vt100Command::UnhandledPrivateMode(code, is_enable) => match describe_private_mode(code) {
+                Some(description) => log::info!("[vt100] UnhandledPrivateMode({} '{}', {})", code, description, is_enable),
+                None => log::info!("[vt100] UnhandledPrivateMode({}, {})", code, is_enable),
+            },
+            Vt100Command::SoftReset => {
+                let mut display = self.display.lock().unwrap();
+                display.soft_reset();
+                let mut encoder = self.encoder.lock().unwrap();
+                encoder.keypad_input_mode = InputMode::Numeric;
+                encoder.cursor_key_input_mode = InputMode::Numeric;
+                window_action(WindowAction::Refresh);
+            },
             _ => {
                 log::info!("[vt100] Unhandled: {:?}", c);
             },
@@ -626,17 +998,30 @@ impl TerminalParserHandler for ParserHandler {
     }
 
     fn on_vt100_error(&mut self, err: Vt100ParserError, parser: &Vt100Parser) {
-        log::error!("[vt100-error] {:?} {:?}", err, parser);
+        // single line so it can be found with grep in log files
+        let state = parser.debug_state();
+        log::error!("[vt100-error] {:?} state={:?} context={:?} buffer=[{}] numbers={:?} bytes_consumed={}",
+            err, state.state, state.context, state.buffer_hex, state.numbers, state.bytes_consumed,
+        );
     }
 }
 
+// Size chosen by the process so it isn't sent back through process_ioctl
+fn apply_process_resize(size: Vector2<usize>, display: &Mutex<TerminalDisplay>, encoder: &Mutex<Vt100Encoder>) {
+    let size = Vector2::new(size.x.max(1), size.y.max(1));
+    display.lock().unwrap().set_size(size);
+    encoder.lock().unwrap().grid_size = size;
+}
+
 // Terminal user
 struct TerminalUser {
     display: Arc<Mutex<TerminalDisplay>>,
     encoder: Arc<Mutex<Vt100Encoder>>,
     process_write: Box<dyn FnMut(&[u8]) + Send>,
     process_ioctl: Box<dyn FnMut(TerminalIOControl) + Send>,
+    window_action: Arc<Mutex<Box<dyn FnMut(WindowAction) + Send>>>,
     mouse_position: Vector2<usize>,
+    recorder: Option<Arc<Mutex<Recorder>>>,
 }
 
 impl TerminalUser {
@@ -653,17 +1038,36 @@ impl TerminalUser {
                 let mut encoder = self.encoder.lock().unwrap();
                 encoder.on_key_release(key_code, process_write);
             },
+            TerminalUserEvent::Resize { window_size, grid_size } => {
+                let window_size = Vector2::new(window_size.x.max(1), window_size.y.max(1));
+                let grid_size = Vector2::new(grid_size.x.max(1), grid_size.y.max(1));
+                let mut display = self.display.lock().unwrap();
+                display.set_size(grid_size);
+                display.swap_buffers();
+                if let Some(recorder) = self.recorder.as_ref() {
+                    recorder.lock().unwrap().record_resize(grid_size);
+                }
+                process_ioctl(TerminalIOControl::SetSize(grid_size));
+                // Apparently this shouldnt be used when ioctl is available
+                // encoder.set_window_size_characters(size, writer);
+                let mut encoder = self.encoder.lock().unwrap();
+                encoder.grid_size = grid_size;
+                encoder.window_size = window_size;
+            },
+            #[allow(deprecated)]
             TerminalUserEvent::GridResize(size) => {
                 let size = Vector2::new(size.x.max(1), size.y.max(1));
                 let mut display = self.display.lock().unwrap();
                 display.set_size(size);
+                display.swap_buffers();
+                if let Some(recorder) = self.recorder.as_ref() {
+                    recorder.lock().unwrap().record_resize(size);
+                }
                 process_ioctl(TerminalIOControl::SetSize(size));
-                // Apparently this shouldnt be used when ioctl is available
-                // let mut encoder = self.encoder.lock().unwrap();
-                // encoder.set_window_size_characters(size, writer);
                 let mut encoder = self.encoder.lock().unwrap();
                 encoder.grid_size = size;
             },
+            #[allow(deprecated)]
             TerminalUserEvent::WindowResize(size) => {
                 let size = Vector2::new(size.x.max(1), size.y.max(1));
                 let mut encoder = self.encoder.lock().unwrap();
@@ -687,9 +1091,32 @@ impl TerminalUser {
                 encoder.on_mouse_event(MouseEvent::ButtonRelease(button, self.mouse_position), process_write);
             },
             TerminalUserEvent::WindowFocus(is_focus) => {
-                let encoder = self.encoder.lock().unwrap();
+                let mut encoder = self.encoder.lock().unwrap();
                 encoder.on_window_focus(is_focus, process_write);
             },
+            TerminalUserEvent::ForceRedraw => {
+                let mut window_action = self.window_action.lock().unwrap();
+                (*window_action)(WindowAction::Refresh);
+            },
+            TerminalUserEvent::ClearScrollback => {
+                let mut display = self.display.lock().unwrap();
+                display.clear_scrollback();
+                display.swap_buffers();
+                drop(display);
+                let mut window_action = self.window_action.lock().unwrap();
+                (*window_action)(WindowAction::Refresh);
+            },
+            TerminalUserEvent::PasteText(text) => {
+                let mut encoder = self.encoder.lock().unwrap();
+                encoder.paste_text(text.as_bytes(), process_write);
+            },
+            TerminalUserEvent::InputText(text) => {
+                // written as is since held modifiers would turn characters into control codes
+                process_write(text.as_bytes());
+            },
+            TerminalUserEvent::WriteReply(data) => {
+                process_write(data.as_slice());
+            },
         }
     }
 }
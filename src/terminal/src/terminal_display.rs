@@ -1,16 +1,21 @@
 use crate::{
     scrollback_buffer::ScrollbackBuffer,
     viewport::Viewport, 
-    primitives::Pen,
+    primitives::{Cell, Pen, StyleFlags},
+    colour_table::{XTERM_COLOUR_TABLE, convert_u32_to_rgb},
+    image_registry::{DecodedImage, ImagePlacement, ImageRegistry},
 };
 use cgmath::Vector2;
-use vt100::common::CursorStyle;
+use std::sync::{Arc, RwLock};
+use vt100::common::{BellVolume, CursorStyle, PromptMark, Rgb8};
 
 #[derive(Clone,Copy,Debug,PartialEq,Eq)]
 pub struct CursorStatus {
     pub is_visible: bool,
     pub is_blinking: bool,
     pub style: CursorStyle,
+    // overrides default cursor colour when set by OSC 12
+    pub colour: Option<Rgb8>,
 }
 
 impl Default for CursorStatus {
@@ -19,17 +24,175 @@ impl Default for CursorStatus {
             is_visible: true,
             is_blinking: true,
             style: CursorStyle::Block,
+            colour: None,
         }
     }
 }
 
+// Shell integration mark written by OSC 133 at the start of a logical line
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct PromptMarkPosition {
+    pub mark: PromptMark,
+    // counted from the oldest line in the scrollback buffer like the render position
+    pub line: usize,
+}
+
+// Text of a row of the viewport
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct AccessibleRow {
+    // trailing blanks are removed
+    pub text: String,
+    pub has_link: bool,
+    // cursor is on this row
+    pub is_focused_row: bool,
+}
+
+// Hyperlinks are never removed since cells in the scrollback buffer can refer to them
+const MAX_HYPERLINKS: usize = 4096;
+
+// Characters besides alphanumerics that are part of a word when double clicking
+pub const DEFAULT_WORD_CHARACTERS: &str = "_-./";
+
+pub fn is_word_character(c: char, word_characters: &str) -> bool {
+    c.is_alphanumeric() || word_characters.contains(c)
+}
+
+// Returns the inclusive start and end cell of the word at the position
+// Words continue across rows that are soft wrapped (don't end in a linebreak)
+pub(crate) fn find_word_bounds(
+    size: Vector2<usize>, pos: Vector2<usize>, word_characters: &str,
+    get_cell: impl Fn(Vector2<usize>) -> Cell, is_linebreak: impl Fn(usize) -> bool,
+) -> (Vector2<usize>, Vector2<usize>) {
+    let is_word = |p: Vector2<usize>| {
+        let cell = get_cell(p);
+        if is_word_character(cell.character, word_characters) {
+            return true;
+        }
+        // spacer of a wide character is part of the same word but a space written after it isn't
+        if !cell.pen.style_flags.contains(StyleFlags::WideSpacer) || p.x == 0 {
+            return false;
+        }
+        is_word_character(get_cell(Vector2::new(p.x-1, p.y)).character, word_characters)
+    };
+    if !is_word(pos) {
+        return (pos, pos);
+    }
+    let get_prev = |p: Vector2<usize>| {
+        if p.x > 0 {
+            Some(Vector2::new(p.x-1, p.y))
+        } else if p.y > 0 && !is_linebreak(p.y-1) {
+            Some(Vector2::new(size.x-1, p.y-1))
+        } else {
+            None
+        }
+    };
+    let get_next = |p: Vector2<usize>| {
+        if p.x+1 < size.x {
+            Some(Vector2::new(p.x+1, p.y))
+        } else if p.y+1 < size.y && !is_linebreak(p.y) {
+            Some(Vector2::new(0, p.y+1))
+        } else {
+            None
+        }
+    };
+    let mut start = pos;
+    while let Some(p) = get_prev(start).filter(|&p| is_word(p)) {
+        start = p;
+    }
+    let mut end = pos;
+    while let Some(p) = get_next(end).filter(|&p| is_word(p)) {
+        end = p;
+    }
+    (start, end)
+}
+
+// Returns the inclusive first and last row of the logical line containing the row
+pub(crate) fn find_line_bounds(
+    size: Vector2<usize>, row: usize,
+    get_character: impl Fn(Vector2<usize>) -> char, is_linebreak: impl Fn(usize) -> bool,
+) -> (usize, usize) {
+    // rows that haven't been written to yet don't end in a linebreak either
+    let is_blank = |y: usize| (0..size.x).all(|x| get_character(Vector2::new(x,y)) == ' ');
+    let mut start = row;
+    while start > 0 && !is_linebreak(start-1) && !is_blank(start) {
+        start -= 1;
+    }
+    let mut end = row;
+    while end+1 < size.y && !is_linebreak(end) && !is_blank(end+1) {
+        end += 1;
+    }
+    (start, end)
+}
+
+// Copy of the current viewport published by swap_buffers so it can be rendered without locking the display
+// Scrollback isn't copied since it is only needed when scrolled back
+#[derive(Clone,Debug,Default)]
+pub struct FrontBuffer {
+    pub(crate) viewport: Viewport,
+    pub(crate) cursor_status: CursorStatus,
+    pub(crate) has_scrollback: bool,
+    pub(crate) total_scrollback_lines: usize,
+    // first row of the viewport continues the newest line in the scrollback buffer
+    pub(crate) last_scrollback_line_length: usize,
+    // lines are counted from the oldest line in the scrollback buffer
+    pub(crate) image_placements: Vec<ImagePlacement>,
+    // indexed colours of cells are resolved from this when rendered
+    pub(crate) colour_table: Vec<Rgb8>,
+    // lines in the primary scrollback buffer which is kept while the alternate viewport is shown
+    pub(crate) scrollback_line_count: usize,
+}
+
+impl FrontBuffer {
+    pub fn get_viewport_size(&self) -> Vector2<usize> {
+        self.viewport.get_size()
+    }
+
+    pub fn get_cursor_position(&self) -> Vector2<usize> {
+        self.viewport.get_cursor()
+    }
+
+    pub fn get_default_background_colour(&self) -> Rgb8 {
+        self.viewport.default_pen.background_colour
+    }
+
+    pub fn get_default_pen(&self) -> Pen {
+        self.viewport.default_pen
+    }
+
+    pub fn get_total_scrollback_lines(&self) -> usize {
+        self.total_scrollback_lines
+    }
+
+    pub fn get_scrollback_line_count(&self) -> usize {
+        self.scrollback_line_count
+    }
+
+    pub fn get_image_placements(&self) -> &[ImagePlacement] {
+        self.image_placements.as_slice()
+    }
+}
+
 #[derive(Clone,Debug)]
 pub struct TerminalDisplay {
     primary_viewport: Viewport,
     alternate_viewport: Viewport,
     is_alternate_viewport: bool,
     size: Vector2<usize>,
+    // sizes are clamped to this so the viewport always has a row and column
+    minimum_size: Vector2<usize>,
+    pub(crate) colour_table: [Rgb8; 256],
     pub(crate) cursor_status: CursorStatus,
+    // restored by a soft reset
+    default_cursor_style: CursorStyle,
+    hyperlinks: Vec<String>,
+    // lines include those evicted from the scrollback buffer so they don't shift as it fills up
+    prompt_marks: Vec<PromptMarkPosition>,
+    // inline images in the primary viewport with lines counted the same way as prompt marks
+    images: ImageRegistry,
+    title: String,
+    bell_volume: BellVolume,
+    // read by the renderer while the parser writes into the viewports
+    front_buffer: Arc<RwLock<FrontBuffer>>,
 }
 
 impl Default for TerminalDisplay {
@@ -38,31 +201,533 @@ impl Default for TerminalDisplay {
         primary_viewport.scrollback_buffer = Some(ScrollbackBuffer::default());
         Self {
             size: Vector2::new(1,1),
+            minimum_size: Vector2::new(1,1),
+            colour_table: XTERM_COLOUR_TABLE.map(convert_u32_to_rgb),
             cursor_status: CursorStatus::default(),
+            default_cursor_style: CursorStatus::default().style,
             primary_viewport,
             alternate_viewport: Viewport::default(),
             is_alternate_viewport: false,
+            hyperlinks: Vec::new(),
+            prompt_marks: Vec::new(),
+            images: ImageRegistry::default(),
+            title: String::new(),
+            bell_volume: BellVolume::High,
+            front_buffer: Arc::new(RwLock::new(FrontBuffer::default())),
         }
     }
 }
 
 impl TerminalDisplay {
+    // Cells written with the entry are shown with the new colour the next time they are rendered
+    pub fn set_colour_table_entry(&mut self, index: u8, colour: Rgb8) {
+        self.colour_table[index as usize] = colour;
+    }
+
+    pub fn get_colour_table_entry(&self, index: u8) -> Rgb8 {
+        self.colour_table[index as usize]
+    }
+
+    pub fn get_cursor_position(&self) -> Vector2<usize> {
+        self.get_current_viewport().get_cursor()
+    }
+
+    pub fn get_cursor_visible(&self) -> bool {
+        self.cursor_status.is_visible
+    }
+
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor_status.style
+    }
+
+    pub fn get_cursor_colour(&self) -> Option<Rgb8> {
+        self.cursor_status.colour
+    }
+
+    pub fn get_viewport_size(&self) -> Vector2<usize> {
+        self.get_current_viewport().get_size()
+    }
+
+    // Returns 0 if the current viewport has no scrollback (alternate buffer)
+    pub fn get_total_scrollback_lines(&self) -> usize {
+        self.get_current_viewport().scrollback_buffer.as_ref().map_or(0, |buffer| buffer.get_lines().len())
+    }
+
+    // Only the primary viewport has scrollback
+    pub fn get_scrollback(&self) -> &ScrollbackBuffer {
+        self.primary_viewport.scrollback_buffer.as_ref().expect("primary viewport always has a scrollback buffer")
+    }
+
+    // Viewport is left untouched
+    pub fn clear_scrollback(&mut self) {
+        if let Some(scrollback_buffer) = self.primary_viewport.scrollback_buffer.as_mut() {
+            scrollback_buffer.clear();
+        }
+    }
+
+    // The parser keeps writing into the current viewport so it is copied into the front buffer instead of being swapped
+    pub fn swap_buffers(&mut self) {
+        let total_evicted_lines = self.get_scrollback().get_evicted_line_count();
+        self.images.evict_lines_before(total_evicted_lines);
+        // images are only drawn over the primary viewport
+        let image_placements = if self.is_alternate_viewport { Vec::new() } else { self.get_image_placements() };
+        let viewport = self.get_current_viewport();
+        let mut front_buffer = self.front_buffer.write().unwrap();
+        viewport.copy_to(&mut front_buffer.viewport);
+        front_buffer.cursor_status = self.cursor_status;
+        front_buffer.has_scrollback = viewport.scrollback_buffer.is_some();
+        front_buffer.total_scrollback_lines = self.get_total_scrollback_lines();
+        front_buffer.last_scrollback_line_length = self.get_viewport_line_position().x;
+        front_buffer.image_placements = image_placements;
+        front_buffer.colour_table.clear();
+        front_buffer.colour_table.extend_from_slice(&self.colour_table);
+        front_buffer.scrollback_line_count = self.get_scrollback().get_line_count();
+    }
+
+    pub fn get_front_buffer(&self) -> Arc<RwLock<FrontBuffer>> {
+        self.front_buffer.clone()
+    }
+
+    // Used to clear the area outside of the grid
+    pub fn get_default_background_colour(&self) -> Rgb8 {
+        self.get_current_viewport().default_pen.background_colour
+    }
+
+    // Pen that SGR 0 resets to which is shared by both viewports
+    pub fn get_default_pen(&self) -> Pen {
+        self.get_current_viewport().default_pen
+    }
+
+    // Cells written to the viewport in use are given this pen
+    pub fn get_pen(&self) -> &Pen {
+        &self.get_current_viewport().pen
+    }
+
+    pub fn get_pen_mut(&mut self) -> &mut Pen {
+        &mut self.get_current_viewport_mut().pen
+    }
+
+    // Last title set by the process which is empty if it was never set
+    pub fn get_title(&self) -> &str {
+        self.title.as_str()
+    }
+
+    pub(crate) fn set_title(&mut self, title: &str) {
+        self.title.clear();
+        self.title.push_str(title);
+    }
+
+    // Warning bell volume set by the process where off silences an audible bell
+    pub fn get_bell_volume(&self) -> BellVolume {
+        self.bell_volume
+    }
+
+    pub(crate) fn set_bell_volume(&mut self, volume: BellVolume) {
+        self.bell_volume = volume;
+    }
+
+    pub fn get_hyperlink(&self, id: u32) -> Option<&str> {
+        self.hyperlinks.get(id as usize).map(|uri| uri.as_str())
+    }
+
+    // Position is in the current viewport and returns none outside of it
+    pub fn get_cell_hyperlink(&self, pos: Vector2<usize>) -> Option<&str> {
+        let viewport = self.get_current_viewport();
+        if pos.y >= viewport.get_row_count() {
+            return None;
+        }
+        let id = viewport.get_row(pos.y).0.get(pos.x)?.pen.hyperlink?;
+        self.get_hyperlink(id)
+    }
+
+    pub fn get_hyperlink_at_cursor(&self) -> Option<&str> {
+        self.get_cell_hyperlink(self.get_cursor_position())
+    }
+
+    // Returns none if the hyperlink table is full
+    pub(crate) fn add_hyperlink(&mut self, uri: &str) -> Option<u32> {
+        // links are usually repeated across consecutive writes
+        if let Some(index) = self.hyperlinks.iter().rposition(|link| link == uri) {
+            return Some(index as u32);
+        }
+        if self.hyperlinks.len() >= MAX_HYPERLINKS {
+            log::warn!("Ignoring hyperlink since table is full: {}", uri);
+            return None;
+        }
+        self.hyperlinks.push(uri.to_owned());
+        Some((self.hyperlinks.len()-1) as u32)
+    }
+
+    // Writes directly into the current viewport without going through the parser
+    // Text is clipped at the end of the row and the cursor and pen are left untouched
+    pub fn write_string_at(&mut self, pos: Vector2<usize>, text: &str, pen: Pen) {
+        let viewport = self.get_current_viewport_mut();
+        let size = viewport.get_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return;
+        }
+        let old_cursor = viewport.get_cursor();
+        let old_pen = viewport.pen;
+        viewport.set_cursor(pos);
+        viewport.pen = pen;
+        for (character, _) in text.chars().zip(pos.x..size.x) {
+            viewport.write_utf8(character);
+        }
+        viewport.pen = old_pen;
+        viewport.set_cursor(old_cursor);
+    }
+
+    // Outline of a box using line drawing characters where size includes the border
+    pub fn draw_box(&mut self, top_left: Vector2<usize>, size: Vector2<usize>, pen: Pen) {
+        if size.x < 2 || size.y < 2 {
+            return;
+        }
+        let inner_width = size.x-2;
+        let horizontal = "\u{2500}".repeat(inner_width);
+        let top = format!("\u{250C}{}\u{2510}", horizontal);
+        let bottom = format!("\u{2514}{}\u{2518}", horizontal);
+        self.write_string_at(top_left, top.as_str(), pen);
+        for y in 1..(size.y-1) {
+            self.write_string_at(Vector2::new(top_left.x, top_left.y+y), "\u{2502}", pen);
+            self.write_string_at(Vector2::new(top_left.x+size.x-1, top_left.y+y), "\u{2502}", pen);
+        }
+        self.write_string_at(Vector2::new(top_left.x, top_left.y+size.y-1), bottom.as_str(), pen);
+    }
+
+    // Word boundaries in viewport coordinates with an inclusive end
+    pub fn get_word_bounds(&self, pos: Vector2<usize>, word_characters: &str) -> (Vector2<usize>, Vector2<usize>) {
+        let viewport = self.get_current_viewport();
+        let size = viewport.get_size();
+        if size.x == 0 || size.y == 0 {
+            return (pos, pos);
+        }
+        let pos = Vector2::new(pos.x.min(size.x-1), pos.y.min(size.y-1));
+        find_word_bounds(size, pos, word_characters,
+            |p| Self::get_viewport_cell(viewport, p),
+            |y| viewport.get_row(y).1.is_linebreak,
+        )
+    }
+
+    // Rows of the logical line in viewport coordinates including soft wrapped continuations
+    pub fn get_line_bounds(&self, row: usize) -> (usize, usize) {
+        let viewport = self.get_current_viewport();
+        let size = viewport.get_size();
+        if size.x == 0 || size.y == 0 {
+            return (row, row);
+        }
+        find_line_bounds(size, row.min(size.y-1),
+            |p| Self::get_viewport_character(viewport, p),
+            |y| viewport.get_row(y).1.is_linebreak,
+        )
+    }
+
+    fn get_viewport_character(viewport: &Viewport, pos: Vector2<usize>) -> char {
+        Self::get_viewport_cell(viewport, pos).character
+    }
+
+    fn get_viewport_cell(viewport: &Viewport, pos: Vector2<usize>) -> Cell {
+        let (row, status) = viewport.get_row(pos.y);
+        if pos.x < status.length { row[pos.x] } else { Cell::default() }
+    }
+
     pub(crate) fn set_is_newline_carriage_return(&mut self, is_newline_carriage_return: bool) {
         self.primary_viewport.is_newline_carriage_return = is_newline_carriage_return;
         self.alternate_viewport.is_newline_carriage_return = is_newline_carriage_return;
     }
 
-    pub(crate) fn set_default_pen(&mut self, pen: Pen) {
+    // Scrolls at the bottom of the viewport
+    pub fn handle_newline(&mut self) {
+        self.get_current_viewport_mut().handle_newline();
+    }
+
+    pub fn handle_carriage_return(&mut self) {
+        self.get_current_viewport_mut().carriage_return();
+    }
+
+    pub fn handle_backspace(&mut self) {
+        self.get_current_viewport_mut().handle_backspace();
+    }
+
+    pub fn handle_horizontal_tab(&mut self) {
+        self.get_current_viewport_mut().handle_horizontal_tab();
+    }
+
+    // Logical line of the cursor in the primary viewport including lines evicted from the scrollback buffer
+    fn get_absolute_cursor_line(&self) -> usize {
+        let scrollback_buffer = self.get_scrollback();
+        // newest scrollback line continues into the first row of the viewport
+        let first_line = scrollback_buffer.get_evicted_line_count() + scrollback_buffer.get_line_count().saturating_sub(1);
+        let viewport = &self.primary_viewport;
+        let cursor = viewport.get_cursor();
+        let total_linebreaks = (0..cursor.y).filter(|&y| viewport.get_row(y).1.is_linebreak).count();
+        first_line + total_linebreaks
+    }
+
+    // Marks on the alternate screen are ignored since it has no scrollback to jump through
+    pub(crate) fn add_prompt_mark(&mut self, mark: PromptMark) {
+        if self.is_alternate_viewport {
+            return;
+        }
+        let line = self.get_absolute_cursor_line();
+        // drop marks whose lines were evicted from the scrollback buffer
+        let total_evicted_lines = self.get_scrollback().get_evicted_line_count();
+        self.prompt_marks.retain(|position| position.line >= total_evicted_lines);
+        self.prompt_marks.push(PromptMarkPosition { mark, line });
+    }
+
+    // Lines are counted from the oldest line in the scrollback buffer
+    pub fn get_prompt_marks(&self) -> Vec<PromptMarkPosition> {
+        let total_evicted_lines = self.get_scrollback().get_evicted_line_count();
+        self.prompt_marks
+            .iter()
+            .filter(|position| position.line >= total_evicted_lines)
+            .map(|position| PromptMarkPosition { line: position.line - total_evicted_lines, ..*position })
+            .collect()
+    }
+
+    // Cells covered by the image are blanked and marked so the renderer can draw the image over them
+    // Image starts on a new line if the cursor is on a soft wrapped continuation so its line maps to a single row
+    // Cursor is left at the start of the line below the image and images on the alternate screen are ignored
+    pub(crate) fn add_inline_image(&mut self, image: DecodedImage, cells: Vector2<usize>) {
+        if self.is_alternate_viewport || cells.x == 0 || cells.y == 0 {
+            return;
+        }
+        let viewport = &mut self.primary_viewport;
+        let cursor = viewport.get_cursor();
+        let is_continuation = match cursor.y.checked_sub(1) {
+            Some(y) => !viewport.get_row(y).1.is_linebreak,
+            None => viewport.scrollback_buffer.as_ref().and_then(|buffer| {
+                buffer.get_line(buffer.get_line_count().checked_sub(1)?)
+            }).is_some_and(|(row, _)| !row.is_empty()),
+        };
+        if is_continuation {
+            viewport.feed_newline(true);
+            viewport.carriage_return();
+        }
+        let line = self.get_absolute_cursor_line();
+        let viewport = &mut self.primary_viewport;
+        let size = viewport.get_size();
+        let column = viewport.get_cursor().x.min(size.x-1);
+        let old_pen = viewport.pen;
+        viewport.pen.style_flags |= StyleFlags::Image;
+        for _ in 0..cells.y {
+            let y = viewport.get_cursor().y;
+            viewport.set_cursor(Vector2::new(column, y));
+            for _ in column..(column+cells.x).min(size.x) {
+                viewport.write_utf8(' ');
+            }
+            viewport.feed_newline(true);
+            viewport.carriage_return();
+        }
+        viewport.pen = old_pen;
+        let total_evicted_lines = self.get_scrollback().get_evicted_line_count();
+        self.images.evict_lines_before(total_evicted_lines);
+        self.images.add(line, column, cells, image);
+    }
+
+    // Lines are counted from the oldest line in the scrollback buffer
+    // Partially evicted images start from the oldest line with their evicted rows clipped
+    pub fn get_image_placements(&self) -> Vec<ImagePlacement> {
+        let total_evicted_lines = self.get_scrollback().get_evicted_line_count();
+        self.images
+            .get_placements()
+            .filter(|placement| placement.line + placement.cells.y > total_evicted_lines)
+            .map(|placement| ImagePlacement {
+                line: placement.line.saturating_sub(total_evicted_lines),
+                clipped_rows: total_evicted_lines.saturating_sub(placement.line),
+                ..placement.clone()
+            })
+            .collect()
+    }
+
+    // Text of the rows of the current viewport from the top
+    pub fn to_accessibility_tree(&self) -> Vec<AccessibleRow> {
+        let viewport = self.get_current_viewport();
+        let cursor = viewport.get_cursor();
+        (0..viewport.get_row_count())
+            .map(|y| {
+                let (row, status) = viewport.get_row(y);
+                let cells = &row[..status.length];
+                // wide characters are followed by a spacer cell that isn't part of the text
+                let text: String = cells
+                    .iter()
+                    .filter(|cell| !cell.pen.style_flags.contains(StyleFlags::WideSpacer))
+                    .map(|cell| cell.character)
+                    .collect();
+                AccessibleRow {
+                    text: text.trim_end().to_owned(),
+                    has_link: cells.iter().any(|cell| cell.pen.hyperlink.is_some()),
+                    is_focused_row: y == cursor.y,
+                }
+            })
+            .collect()
+    }
+
+    // Line coordinates of the first row of the current viewport which continues the newest line in the scrollback buffer
+    // x is the offset into the logical line and y is the line counted from the oldest line in the scrollback buffer
+    pub fn get_viewport_line_position(&self) -> Vector2<usize> {
+        let Some(scrollback_buffer) = self.get_current_viewport().scrollback_buffer.as_ref() else {
+            return Vector2::new(0,0);
+        };
+        let line = scrollback_buffer.get_line_count().saturating_sub(1);
+        let length = scrollback_buffer.get_line(line).map_or(0, |(cells, _)| cells.len());
+        Vector2::new(length, line)
+    }
+
+    // Selection is in line coordinates of the current viewport and scrollback with an exclusive end
+    // Rows of a logical line are padded to the width of the viewport so offsets match the rendered grid
+    pub fn get_selection_text(&self, start: Vector2<usize>, end: Vector2<usize>) -> String {
+        let get_order = |p: Vector2<usize>| (p.y, p.x);
+        let (start, end) = if get_order(start) > get_order(end) { (end, start) } else { (start, end) };
+        let mut lines = vec![String::new(); end.y-start.y+1];
+        let mut write_cells = |position: Vector2<usize>, cells: &[Cell], width: usize| {
+            if !(start.y..=end.y).contains(&position.y) {
+                return;
+            }
+            let line = &mut lines[position.y-start.y];
+            for x in 0..width {
+                let order = (position.y, position.x+x);
+                if order >= get_order(start) && order < get_order(end) {
+                    line.push(cells.get(x).map_or(' ', |cell| cell.character));
+                }
+            }
+        };
+        let viewport = self.get_current_viewport();
+        if let Some(scrollback_buffer) = viewport.scrollback_buffer.as_ref() {
+            let total_lines = scrollback_buffer.get_line_count();
+            for line in start.y.min(total_lines)..(end.y+1).min(total_lines) {
+                if let Some((cells, _)) = scrollback_buffer.get_line(line) {
+                    write_cells(Vector2::new(0, line), cells, cells.len());
+                }
+            }
+        }
+        let width = viewport.get_size().x;
+        let mut position = self.get_viewport_line_position();
+        for y in 0..viewport.get_row_count() {
+            if position.y > end.y {
+                break;
+            }
+            let (row, status) = viewport.get_row(y);
+            write_cells(position, &row[..status.length], width);
+            position = if status.is_linebreak {
+                Vector2::new(0, position.y+1)
+            } else {
+                Vector2::new(position.x+width, position.y)
+            };
+        }
+        let lines: Vec<&str> = lines.iter().map(|line| line.trim_end()).collect();
+        lines.join("\n").trim_end().to_owned()
+    }
+
+    // Text of logical lines in the primary viewport and scrollback from start up to but excluding end
+    pub fn get_lines_text(&self, start: usize, end: usize) -> String {
+        let mut lines = vec![String::new(); end.saturating_sub(start)];
+        let mut write_line = |line: usize, cells: &[Cell]| {
+            if (start..end).contains(&line) {
+                lines[line-start].extend(cells.iter().map(|cell| cell.character));
+            }
+        };
+        let scrollback_buffer = self.get_scrollback();
+        let total_scrollback_lines = scrollback_buffer.get_line_count();
+        for line in start.min(total_scrollback_lines)..end.min(total_scrollback_lines) {
+            if let Some((cells, _)) = scrollback_buffer.get_line(line) {
+                write_line(line, cells);
+            }
+        }
+        let viewport = &self.primary_viewport;
+        let mut line = total_scrollback_lines.saturating_sub(1);
+        for y in 0..viewport.get_row_count() {
+            if line >= end {
+                break;
+            }
+            let (row, status) = viewport.get_row(y);
+            write_line(line, &row[..status.length]);
+            if status.is_linebreak {
+                line += 1;
+            }
+        }
+        let lines: Vec<&str> = lines.iter().map(|line| line.trim_end()).collect();
+        lines.join("\n").trim_end().to_owned()
+    }
+
+    // Output between the output start and command finished marks of the last command that finished
+    pub fn get_last_command_output(&self) -> Option<String> {
+        let marks = self.get_prompt_marks();
+        let end = marks.iter().rposition(|position| matches!(position.mark, PromptMark::CommandFinished(_)))?;
+        let start = marks[..end]
+            .iter()
+            .rev()
+            .take_while(|position| !matches!(position.mark, PromptMark::CommandFinished(_)))
+            .find(|position| position.mark == PromptMark::OutputStart)?;
+        Some(self.get_lines_text(start.line, marks[end].line))
+    }
+
+    pub fn get_insert_mode(&self) -> bool {
+        self.get_current_viewport().is_insert_mode
+    }
+
+    // IRM where characters are inserted at the cursor instead of overwriting it
+    pub(crate) fn set_insert_mode(&mut self) {
+        self.primary_viewport.is_insert_mode = true;
+        self.alternate_viewport.is_insert_mode = true;
+    }
+
+    pub(crate) fn set_replace_mode(&mut self) {
+        self.primary_viewport.is_insert_mode = false;
+        self.alternate_viewport.is_insert_mode = false;
+    }
+
+    // Existing scrollback is discarded
+    pub(crate) fn set_scrollback_buffer(&mut self, scrollback_buffer: ScrollbackBuffer) {
+        self.primary_viewport.scrollback_buffer = Some(scrollback_buffer);
+        self.prompt_marks.clear();
+        self.images.clear();
+    }
+
+    // The current pen is left untouched until the next reset
+    pub fn set_default_pen(&mut self, pen: Pen) {
         self.primary_viewport.default_pen = pen;
         self.alternate_viewport.default_pen = pen;
     }
 
+    pub(crate) fn set_default_cursor_style(&mut self, style: CursorStyle) {
+        self.default_cursor_style = style;
+        self.cursor_status.style = style;
+    }
+
+    // DECSTR which resets modes without clearing the screen
+    pub(crate) fn soft_reset(&mut self) {
+        self.cursor_status.is_visible = true;
+        self.cursor_status.style = self.default_cursor_style;
+        self.set_replace_mode();
+        let default_pen = self.get_default_pen();
+        let pen = self.get_pen_mut();
+        // hyperlinks are only ended by OSC 8
+        *pen = Pen { hyperlink: pen.hyperlink, ..default_pen };
+        self.get_current_viewport_mut().clear_saved_cursor();
+    }
+
+    // Values below 1 are raised to 1 since the viewport can't be empty
+    pub fn set_minimum_size(&mut self, minimum_size: Vector2<usize>) {
+        self.minimum_size = Vector2::new(minimum_size.x.max(1), minimum_size.y.max(1));
+        self.set_size(self.size);
+    }
+
+    pub fn get_minimum_size(&self) -> Vector2<usize> {
+        self.minimum_size
+    }
+
     pub(crate) fn set_size(&mut self, size: Vector2<usize>) {
+        let size = Vector2::new(size.x.max(self.minimum_size.x), size.y.max(self.minimum_size.y));
         self.size = size;
         let viewport = self.get_current_viewport_mut();
         viewport.set_size(size);
     }
 
+    pub fn is_alternate_viewport(&self) -> bool {
+        self.is_alternate_viewport
+    }
+
     pub(crate) fn set_is_alternate(&mut self, is_alternate: bool) {
         self.is_alternate_viewport = is_alternate;
         let size = self.size;
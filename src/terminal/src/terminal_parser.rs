@@ -11,6 +11,7 @@ use vt100::{
         VT100_ESCAPE_CODE,
     },
 };
+use std::time::{Duration, Instant};
 
 enum State {
     Byte,
@@ -18,10 +19,35 @@ enum State {
     Vt100,
 }
 
+// Totals since the parser was created or last reset
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct TerminalParserMetrics {
+    pub bytes_parsed: u64,
+    // vt100 commands
+    pub commands_emitted: u64,
+    // invalid utf8 and vt100 sequences
+    pub errors_encountered: u64,
+    // includes time spent by the handler updating the display
+    pub parse_time_ns: u64,
+    // time spent publishing the display after each parsed chunk
+    pub display_update_time_ns: u64,
+}
+
+impl std::fmt::Display for TerminalParserMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to_ms = |ns: u64| ns as f64 / 1_000_000.0;
+        write!(f, "bytes={} commands={} errors={} parse={:.2}ms display_update={:.2}ms",
+            self.bytes_parsed, self.commands_emitted, self.errors_encountered,
+            to_ms(self.parse_time_ns), to_ms(self.display_update_time_ns),
+        )
+    }
+}
+
 pub struct TerminalParser {
     state: State,
     utf8_parser: Utf8Parser,
     vt100_parser: Vt100Parser,
+    metrics: TerminalParserMetrics,
 }
 
 impl Default for TerminalParser {
@@ -30,6 +56,7 @@ impl Default for TerminalParser {
             state: State::Byte,
             utf8_parser: Utf8Parser::default(),
             vt100_parser: Vt100Parser::default(),
+            metrics: TerminalParserMetrics::default(),
         }
     }
 }
@@ -43,18 +70,39 @@ pub trait TerminalParserHandler {
     fn on_vt100_error(&mut self, error: Vt100ParserError, parser: &Vt100Parser);
 }
 
-struct ConvertToVt100<'a, T: TerminalParserHandler>(&'a mut T);
+struct ConvertToVt100<'a, T: TerminalParserHandler>(&'a mut T, &'a mut TerminalParserMetrics);
 impl<T: TerminalParserHandler> Vt100ParserHandler for ConvertToVt100<'_, T> {
     fn on_command(&mut self, command: Vt100Command) {
+        self.1.commands_emitted += 1;
         self.0.on_vt100(command);
     }
     fn on_error(&mut self, error: Vt100ParserError, parser: &Vt100Parser) {
+        self.1.errors_encountered += 1;
         self.0.on_vt100_error(error, parser);
     }
 }
 
 impl TerminalParser {
-    pub fn parse_bytes(&mut self, mut buf: &[u8], handler: &mut impl TerminalParserHandler) {
+    pub fn get_metrics(&self) -> &TerminalParserMetrics {
+        &self.metrics
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics = TerminalParserMetrics::default();
+    }
+
+    pub fn add_display_update_time(&mut self, elapsed: Duration) {
+        self.metrics.display_update_time_ns += elapsed.as_nanos() as u64;
+    }
+
+    pub fn parse_bytes(&mut self, buf: &[u8], handler: &mut impl TerminalParserHandler) {
+        let start = Instant::now();
+        self.metrics.bytes_parsed += buf.len() as u64;
+        self.parse_buffer(buf, handler);
+        self.metrics.parse_time_ns += start.elapsed().as_nanos() as u64;
+    }
+
+    fn parse_buffer(&mut self, mut buf: &[u8], handler: &mut impl TerminalParserHandler) {
         while !buf.is_empty() {
             match self.state {
                 State::Byte => {
@@ -91,6 +139,7 @@ impl TerminalParser {
                         match self.utf8_parser.parse_body_byte(b) {
                             Err(Utf8ParserError::Pending) => {},
                             Err(ref err) => {
+                                self.metrics.errors_encountered += 1;
                                 handler.on_utf8_error(err);
                                 self.state = State::Byte;
                                 break;
@@ -108,7 +157,7 @@ impl TerminalParser {
                     let mut total_read = 0;
                     for &b in buf {
                         total_read += 1;
-                        self.vt100_parser.feed_byte(b, &mut ConvertToVt100(handler));
+                        self.vt100_parser.feed_byte(b, &mut ConvertToVt100(handler, &mut self.metrics));
                         if self.vt100_parser.is_terminated() {
                             self.state = State::Byte;
                             break;
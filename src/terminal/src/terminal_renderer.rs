@@ -1,28 +1,93 @@
-use crate::terminal_display::TerminalDisplay;
-use crate::primitives::Cell;
+use crate::terminal_display::{CursorStatus, FrontBuffer, TerminalDisplay, find_word_bounds, find_line_bounds};
+use crate::scrollback_buffer::ScrollbackBuffer;
+use crate::viewport::Viewport;
+use crate::primitives::{Cell, StyleFlags};
+use crate::image_registry::{DecodedImage, ImagePlacement};
+use crate::contrast::apply_minimum_contrast;
 use cgmath::Vector2;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use vt100::common::{CursorStyle, Rgb8};
 
-#[derive(Clone,Copy,Default,Debug)]
+// Cursor colour is lightened or darkened until it stands out from the cell background by this ratio
+pub const MINIMUM_CURSOR_CONTRAST: f32 = 1.5;
+
+#[derive(Clone,Copy,Default,Debug,PartialEq,Eq)]
 pub enum RenderPosition {
     #[default]
     Bottom,
     Floating(usize),
 }
 
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct ScrollStatus {
+    pub scroll_offset: usize,
+    pub total_lines: usize,
+    pub viewport_lines: usize,
+}
+
+// Cursor position is in rendered grid coordinates
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct RenderCursor {
+    pub position: Vector2<usize>,
+    pub style: CursorStyle,
+    pub colour: Rgb8,
+}
+
+// Image anchored to a render row which is negative if the image starts above the first render row
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct RenderImage {
+    pub id: u64,
+    pub row: isize,
+    pub column: usize,
+    pub cells: Vector2<usize>,
+    pub image: Arc<DecodedImage>,
+}
+
 pub struct TerminalRenderer {
     cells: Vec<Cell>,
+    row_is_linebreak: Vec<bool>,
+    // line coordinates of the first cell in each row
+    row_positions: Vec<Vector2<usize>>,
     size: Vector2<usize>,
     position: RenderPosition,
     last_known_total_rows: usize,
+    is_scrollback_visible: bool,
+    // pixels scrolled past the top of the floating row for smooth scrolling
+    pixel_offset: usize,
+    total_render_rows: usize,
+    // 1.0 disables contrast adjustment
+    minimum_contrast: f32,
+    cursor: Option<RenderCursor>,
+    // uses foreground colour of cell under cursor if none
+    default_cursor_colour: Option<Rgb8>,
+    // rows are given a new generation when their text changes so results can be cached per row
+    row_hashes: Vec<u64>,
+    row_generations: Vec<u64>,
+    generation: u64,
+    images: Vec<RenderImage>,
 }
 
 impl Default for TerminalRenderer {
     fn default() -> Self {
         Self {
             cells: Vec::new(),
+            row_is_linebreak: Vec::new(),
+            row_positions: Vec::new(),
             size: Vector2::new(0,0),
             position: RenderPosition::Bottom,
             last_known_total_rows: 0,
+            is_scrollback_visible: false,
+            pixel_offset: 0,
+            total_render_rows: 0,
+            minimum_contrast: 1.0,
+            cursor: None,
+            default_cursor_colour: None,
+            row_hashes: Vec::new(),
+            row_generations: Vec::new(),
+            generation: 0,
+            images: Vec::new(),
         }
     }
 }
@@ -32,28 +97,178 @@ impl TerminalRenderer {
         self.size
     }
 
+    // Includes an extra row below the viewport when partially scrolled
     pub fn get_cells(&self) -> &[Cell] {
         self.cells.as_slice()
     }
 
+    pub fn get_total_render_rows(&self) -> usize {
+        self.total_render_rows
+    }
+
+    pub fn get_pixel_offset(&self) -> usize {
+        self.pixel_offset
+    }
+
+    pub fn get_position(&self) -> RenderPosition {
+        self.position
+    }
+
+    pub fn get_minimum_contrast(&self) -> f32 {
+        self.minimum_contrast
+    }
+
+    pub fn set_minimum_contrast(&mut self, ratio: f32) {
+        self.minimum_contrast = ratio.clamp(1.0, 21.0);
+    }
+
+    // Returns none if the cursor is hidden or outside the rendered rows
+    pub fn get_cursor(&self) -> Option<RenderCursor> {
+        self.cursor
+    }
+
+    pub fn get_default_cursor_colour(&self) -> Option<Rgb8> {
+        self.default_cursor_colour
+    }
+
+    // Cursor colour set by the application with OSC 12 takes precedence
+    pub fn set_default_cursor_colour(&mut self, colour: Option<Rgb8>) {
+        self.default_cursor_colour = colour;
+    }
+
+    // Returns none if the current viewport has no scrollback (alternate buffer)
+    pub fn get_scroll_status(&self) -> Option<ScrollStatus> {
+        if !self.is_scrollback_visible {
+            return None;
+        }
+        let scroll_offset = match self.position {
+            RenderPosition::Bottom => self.last_known_total_rows,
+            RenderPosition::Floating(row) => row,
+        };
+        Some(ScrollStatus {
+            scroll_offset,
+            total_lines: self.last_known_total_rows + self.size.y,
+            viewport_lines: self.size.y,
+        })
+    }
+
     fn set_size(&mut self, size: Vector2<usize>) {
-        let total_cells = size.x*size.y;
+        // render an extra row to fill the gap left by a partial scroll
+        let total_render_rows = if self.pixel_offset > 0 { size.y+1 } else { size.y };
+        let total_cells = size.x*total_render_rows;
         self.size = size;
+        self.total_render_rows = total_render_rows;
         self.cells.resize(total_cells, Cell::default());
+        self.row_is_linebreak.resize(total_render_rows, false);
+        self.row_positions.resize(total_render_rows, Vector2::new(0,0));
+        self.row_hashes.resize(total_render_rows, 0);
+        self.row_generations.resize(total_render_rows, 0);
+    }
+
+    fn update_row_generations(&mut self) {
+        let width = self.size.x;
+        for (y, is_linebreak) in self.row_is_linebreak.iter().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            for cell in &self.cells[y*width..(y+1)*width] {
+                cell.character.hash(&mut hasher);
+                cell.pen.hyperlink.hash(&mut hasher);
+            }
+            is_linebreak.hash(&mut hasher);
+            let hash = hasher.finish();
+            if self.row_hashes[y] != hash {
+                self.row_hashes[y] = hash;
+                self.generation += 1;
+                self.row_generations[y] = self.generation;
+            }
+        }
+    }
+
+    // Generation of each rendered row which changes whenever the text of the row changes
+    pub fn get_row_generations(&self) -> &[u64] {
+        self.row_generations.as_slice()
+    }
+
+    pub fn get_row_is_linebreak(&self) -> &[bool] {
+        self.row_is_linebreak.as_slice()
     }
 
     pub fn render_display(&mut self, display: &TerminalDisplay) {
         let viewport = display.get_current_viewport();
+        let viewport_position = display.get_viewport_line_position();
+        self.render_viewport(
+            viewport, viewport.scrollback_buffer.as_ref(), viewport_position, display.cursor_status, &display.colour_table,
+        );
+        let placements = if display.is_alternate_viewport() { Vec::new() } else { display.get_image_placements() };
+        self.update_images(placements.as_slice());
+    }
+
+    // Returns false if scrolled back since the scrollback buffer is only available from the display
+    pub fn render_front_buffer(&mut self, front_buffer: &FrontBuffer) -> bool {
+        if self.position != RenderPosition::Bottom || self.pixel_offset != 0 {
+            return false;
+        }
+        let viewport_position = if front_buffer.has_scrollback {
+            Vector2::new(front_buffer.last_scrollback_line_length, front_buffer.total_scrollback_lines.saturating_sub(1))
+        } else {
+            Vector2::new(0,0)
+        };
+        self.render_viewport(
+            &front_buffer.viewport, None, viewport_position, front_buffer.cursor_status, &front_buffer.colour_table,
+        );
+        self.is_scrollback_visible = front_buffer.has_scrollback;
+        self.last_known_total_rows = front_buffer.total_scrollback_lines;
+        self.update_images(front_buffer.image_placements.as_slice());
+        true
+    }
+
+    // Images which have any row inside the render rows
+    fn update_images(&mut self, placements: &[ImagePlacement]) {
+        self.images.clear();
+        for placement in placements {
+            // image rows are separate lines so find the first one that is rendered
+            let total_rows = placement.cells.y.saturating_sub(placement.clipped_rows);
+            let row = (0..total_rows).find_map(|i| {
+                let row = self.get_line_render_row(placement.line + i)?;
+                Some(row as isize - (i + placement.clipped_rows) as isize)
+            });
+            if let Some(row) = row {
+                self.images.push(RenderImage {
+                    id: placement.id,
+                    row,
+                    column: placement.column,
+                    cells: placement.cells,
+                    image: placement.image.clone(),
+                });
+            }
+        }
+    }
+
+    // Images overlapping the rendered grid from the last render
+    pub fn get_images(&self) -> &[RenderImage] {
+        self.images.as_slice()
+    }
+
+    // Viewport position is the line coordinates of its first row
+    fn render_viewport(
+        &mut self, viewport: &Viewport, scrollback_buffer: Option<&ScrollbackBuffer>,
+        viewport_position: Vector2<usize>, cursor_status: CursorStatus, colour_table: &[Rgb8],
+    ) {
+        self.is_scrollback_visible = scrollback_buffer.is_some();
+        if !self.is_scrollback_visible {
+            self.pixel_offset = 0;
+        }
         let size = viewport.get_size();
         self.set_size(size);
+        let total_rows = self.total_render_rows;
 
         let default_pen = viewport.default_pen;
         let default_cell = Cell { character: ' ', pen: default_pen };
         self.cells.fill(default_cell);
+        self.row_is_linebreak.fill(false);
 
         let mut cursor: Vector2<usize> = Vector2::new(0,0);
  
-        if let Some(scrollback_buffer) = viewport.scrollback_buffer.as_ref() {
+        if let Some(scrollback_buffer) = scrollback_buffer {
             let scrollback_buffer_lines = scrollback_buffer.get_lines();
             self.last_known_total_rows = scrollback_buffer_lines.len();
             let scrollback_row = match self.position {
@@ -61,6 +276,7 @@ impl TerminalRenderer {
                 RenderPosition::Floating(row) => {
                     if row >= scrollback_buffer_lines.len() {
                         self.position = RenderPosition::Bottom;
+                        self.pixel_offset = 0;
                         scrollback_buffer_lines.len()
                     } else {
                         row
@@ -69,26 +285,33 @@ impl TerminalRenderer {
             };
 
             // render scrollback buffer
-            for line in &scrollback_buffer_lines[scrollback_row..] {
-                if cursor.y >= size.y {
+            // last line is still being written to and continues into the viewport
+            let last_line_index = scrollback_buffer_lines.len().saturating_sub(1);
+            for (line_index, line) in scrollback_buffer_lines.iter().enumerate().skip(scrollback_row) {
+                if cursor.y >= total_rows {
                     break;
                 }
                 let row = scrollback_buffer.get_row(line);
-                for cell in row {
+                self.row_positions[cursor.y] = Vector2::new(0, line_index);
+                for (offset, cell) in row.iter().enumerate() {
                     if cursor.x >= size.x {
                         cursor.x = 0;
                         cursor.y += 1;
+                        if cursor.y < total_rows {
+                            self.row_positions[cursor.y] = Vector2::new(offset, line_index);
+                        }
                     }
-                    if cursor.y >= size.y {
+                    if cursor.y >= total_rows {
                         break;
                     }
                     let dst_index = cursor.y*size.x + cursor.x;
                     self.cells[dst_index] = *cell;
                     cursor.x += 1;
                 }
-                if cursor.y >= size.y {
+                if cursor.y >= total_rows {
                     break;
                 }
+                self.row_is_linebreak[cursor.y] = line_index != last_line_index;
                 cursor.x = 0;
                 cursor.y += 1;
             }
@@ -96,31 +319,144 @@ impl TerminalRenderer {
         // render viewport
         let viewport_offset = cursor;
         let viewport_cursor = viewport.get_cursor();
-        for y in 0..size.y {
-            if cursor.y >= size.y {
-                break;
+        // copy the whole grid at once when it fills the render area then blank the end of each row
+        if cursor.y == 0 && total_rows >= size.y {
+            let (head, tail) = viewport.get_cells_flat();
+            self.cells[..head.len()].copy_from_slice(head);
+            self.cells[head.len()..(head.len()+tail.len())].copy_from_slice(tail);
+            for y in 0..size.y {
+                let (_, status) = viewport.get_row(y);
+                assert!(status.length <= size.x);
+                let dst_index = y*size.x;
+                self.cells[(dst_index+status.length)..(dst_index+size.x)].fill(default_cell);
+                self.row_is_linebreak[y] = status.is_linebreak;
+            }
+        } else {
+            for y in 0..size.y {
+                if cursor.y >= total_rows {
+                    break;
+                }
+                let (src_row, status) = viewport.get_row(y);
+                assert!(status.length <= size.x);
+                let dst_index = cursor.y*size.x;
+                let dst_row = &mut self.cells[dst_index..(dst_index+size.x)];
+                dst_row[..status.length].copy_from_slice(&src_row[..status.length]);
+                dst_row[status.length..].iter_mut().for_each(|c| {
+                    c.character = ' ';
+                    c.pen = default_pen;
+                });
+                self.row_is_linebreak[cursor.y] = status.is_linebreak;
+                cursor.y += 1;
             }
-            let (src_row, status) = viewport.get_row(y);
-            assert!(status.length <= size.x);
-            let dst_index = cursor.y*size.x;
-            let dst_row = &mut self.cells[dst_index..(dst_index+size.x)];
-            dst_row[..status.length].copy_from_slice(&src_row[..status.length]);
-            dst_row[status.length..].iter_mut().for_each(|c| {
-                c.character = ' ';
-                c.pen = default_pen;
+        }
+
+        let mut position = viewport_position;
+        for y in viewport_offset.y..total_rows {
+            self.row_positions[y] = position;
+            position = if self.row_is_linebreak[y] {
+                Vector2::new(0, position.y+1)
+            } else {
+                Vector2::new(position.x+size.x, position.y)
+            };
+        }
+
+        self.update_row_generations();
+
+        // palette changes also apply to cells written before them
+        for c in self.cells.iter_mut() {
+            if let Some(colour) = c.pen.foreground_index.and_then(|index| colour_table.get(index as usize)) {
+                c.pen.foreground_colour = *colour;
+            }
+            if let Some(colour) = c.pen.background_index.and_then(|index| colour_table.get(index as usize)) {
+                c.pen.background_colour = *colour;
+            }
+            if let Some(colour) = c.pen.underline_index.and_then(|index| colour_table.get(index as usize)) {
+                c.pen.underline_colour = Some(*colour);
+            }
+        }
+
+        if self.minimum_contrast > 1.0 {
+            let ratio = self.minimum_contrast;
+            self.cells.iter_mut().for_each(|c| {
+                // inverse is applied when drawn so adjust whichever colour ends up as the foreground
+                if c.pen.style_flags.contains(StyleFlags::Inverse) {
+                    c.pen.background_colour = apply_minimum_contrast(c.pen.background_colour, c.pen.foreground_colour, ratio);
+                } else {
+                    c.pen.foreground_colour = apply_minimum_contrast(c.pen.foreground_colour, c.pen.background_colour, ratio);
+                }
             });
-            cursor.y += 1;
         }
 
+        // cursor is left at the end of the row while waiting to wrap
         let display_cursor = viewport_offset + viewport_cursor;
-        let display_cursor_index = display_cursor.y*size.x + display_cursor.x;
-        if let Some(cell) = self.cells.get_mut(display_cursor_index) {
-            // TODO: render cursor properly with all the different styles
-            std::mem::swap(&mut cell.pen.foreground_colour, &mut cell.pen.background_colour);
+        let display_cursor = Vector2::new(display_cursor.x.min(size.x.saturating_sub(1)), display_cursor.y);
+        self.cursor = None;
+        if cursor_status.is_visible && display_cursor.y < total_rows && size.x > 0 {
+            let cell = &mut self.cells[display_cursor.y*size.x + display_cursor.x];
+            cell.pen.style_flags |= StyleFlags::Cursor;
+            let cell = *cell;
+            let (cell_foreground_colour, cell_background_colour) = if cell.pen.style_flags.contains(StyleFlags::Inverse) {
+                (cell.pen.background_colour, cell.pen.foreground_colour)
+            } else {
+                (cell.pen.foreground_colour, cell.pen.background_colour)
+            };
+            let colour = cursor_status.colour
+                .or(self.default_cursor_colour)
+                .unwrap_or(cell_foreground_colour);
+            // cursor would disappear if it matched the background of the cell underneath
+            let colour = apply_minimum_contrast(colour, cell_background_colour, MINIMUM_CURSOR_CONTRAST);
+            self.cursor = Some(RenderCursor {
+                position: display_cursor,
+                style: cursor_status.style,
+                colour,
+            });
+        }
+    }
+
+    // Word boundaries in rendered grid coordinates with an inclusive end
+    pub fn get_word_bounds(&self, pos: Vector2<usize>, word_characters: &str) -> (Vector2<usize>, Vector2<usize>) {
+        let total_rows = self.row_is_linebreak.len();
+        if self.size.x == 0 || total_rows == 0 {
+            return (pos, pos);
         }
+        let size = Vector2::new(self.size.x, total_rows);
+        let pos = Vector2::new(pos.x.min(size.x-1), pos.y.min(size.y-1));
+        find_word_bounds(size, pos, word_characters,
+            |p| self.cells[p.y*size.x + p.x],
+            |y| self.row_is_linebreak[y],
+        )
+    }
+
+    // Rows of the logical line in rendered grid coordinates including soft wrapped continuations
+    pub fn get_line_bounds(&self, row: usize) -> (usize, usize) {
+        let total_rows = self.row_is_linebreak.len();
+        if self.size.x == 0 || total_rows == 0 {
+            return (row, row);
+        }
+        let size = Vector2::new(self.size.x, total_rows);
+        find_line_bounds(size, row.min(size.y-1),
+            |p| self.cells[p.y*size.x + p.x].character,
+            |y| self.row_is_linebreak[y],
+        )
+    }
+
+    // Line coordinates of a cell in the rendered grid which don't change as the view is scrolled
+    // x is the offset into the logical line and y is the line counted from the oldest line in the scrollback buffer
+    pub fn get_line_position(&self, cell: Vector2<usize>) -> Vector2<usize> {
+        let Some(last_row) = self.row_positions.len().checked_sub(1) else {
+            return Vector2::new(cell.x, 0);
+        };
+        let start = self.row_positions[cell.y.min(last_row)];
+        Vector2::new(start.x + cell.x, start.y)
+    }
+
+    // Line coordinates of the first cell in each rendered row
+    pub fn get_row_positions(&self) -> &[Vector2<usize>] {
+        self.row_positions.as_slice()
     }
 
     pub fn scroll_up(&mut self, total: usize) {
+        self.pixel_offset = 0;
         let position = match self.position {
             RenderPosition::Bottom => {
                 let new_row = self.last_known_total_rows.saturating_sub(total);
@@ -135,6 +471,7 @@ impl TerminalRenderer {
     }
 
     pub fn scroll_down(&mut self, total: usize) {
+        self.pixel_offset = 0;
         let position = match self.position {
             RenderPosition::Bottom => {
                 RenderPosition::Bottom
@@ -153,9 +490,88 @@ impl TerminalRenderer {
 
     pub fn scroll_to_top(&mut self) {
         self.position = RenderPosition::Floating(0);
+        self.pixel_offset = 0;
     }
 
     pub fn scroll_to_bottom(&mut self) {
         self.position = RenderPosition::Bottom;
+        self.pixel_offset = 0;
+    }
+
+    // Logical line shown in the first render row where the newest scrollback line continues into the viewport
+    fn get_top_line(&self) -> usize {
+        match self.position {
+            RenderPosition::Bottom => self.last_known_total_rows.saturating_sub(1),
+            RenderPosition::Floating(row) => row,
+        }
+    }
+
+    // Render row where a logical line from the scrollback buffer starts if it is visible
+    pub fn get_line_render_row(&self, line: usize) -> Option<usize> {
+        let mut current_line = self.get_top_line();
+        if line < current_line {
+            return None;
+        }
+        for (y, is_linebreak) in self.row_is_linebreak.iter().enumerate() {
+            if current_line == line {
+                return Some(y);
+            }
+            if *is_linebreak {
+                current_line += 1;
+            }
+        }
+        None
+    }
+
+    // Returns the line of the prompt that was scrolled to if there is one above the first render row
+    pub fn scroll_to_previous_prompt(&mut self, prompt_lines: &[usize]) -> Option<usize> {
+        let top_line = self.get_top_line();
+        let line = prompt_lines.iter().copied().filter(|&line| line < top_line).max()?;
+        self.position = RenderPosition::Floating(line);
+        self.pixel_offset = 0;
+        Some(line)
+    }
+
+    // Prompts which are still in the viewport scroll back to the bottom
+    pub fn scroll_to_next_prompt(&mut self, prompt_lines: &[usize]) -> Option<usize> {
+        let RenderPosition::Floating(top_line) = self.position else {
+            return None;
+        };
+        let line = prompt_lines.iter().copied().filter(|&line| line > top_line).min()?;
+        if line < self.last_known_total_rows {
+            self.position = RenderPosition::Floating(line);
+            self.pixel_offset = 0;
+        } else {
+            self.scroll_to_bottom();
+        }
+        Some(line)
+    }
+
+    fn get_absolute_pixel_offset(&self, cell_height: usize) -> usize {
+        match self.position {
+            RenderPosition::Bottom => self.last_known_total_rows*cell_height,
+            RenderPosition::Floating(row) => row*cell_height + self.pixel_offset,
+        }
+    }
+
+    pub fn scroll_up_pixels(&mut self, total_pixels: usize, cell_height: usize) {
+        let cell_height = cell_height.max(1);
+        let offset = self.get_absolute_pixel_offset(cell_height).saturating_sub(total_pixels);
+        self.position = RenderPosition::Floating(offset / cell_height);
+        self.pixel_offset = offset % cell_height;
+    }
+
+    pub fn scroll_down_pixels(&mut self, total_pixels: usize, cell_height: usize) {
+        let cell_height = cell_height.max(1);
+        if let RenderPosition::Bottom = self.position {
+            return;
+        }
+        let offset = self.get_absolute_pixel_offset(cell_height) + total_pixels;
+        if offset >= self.last_known_total_rows*cell_height {
+            self.scroll_to_bottom();
+            return;
+        }
+        self.position = RenderPosition::Floating(offset / cell_height);
+        self.pixel_offset = offset % cell_height;
     }
 }
@@ -0,0 +1,178 @@
+use crate::terminal_display::TerminalDisplay;
+use crate::terminal_renderer::TerminalRenderer;
+use cgmath::Vector2;
+use std::ops::Range;
+
+const URL_SCHEMES: [&str; 3] = ["https://", "http://", "file://"];
+
+// Url in rendered grid coordinates with an inclusive end
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct UrlSpan {
+    pub start: Vector2<usize>,
+    pub end: Vector2<usize>,
+    pub url: String,
+}
+
+impl UrlSpan {
+    pub fn contains(&self, pos: Vector2<usize>) -> bool {
+        let get_order = |p: Vector2<usize>| (p.y, p.x);
+        get_order(self.start) <= get_order(pos) && get_order(pos) <= get_order(self.end)
+    }
+}
+
+fn is_url_character(c: char) -> bool {
+    !c.is_whitespace() && !c.is_control() && !matches!(c, '<' | '>' | '"' | '`' | '{' | '}' | '|' | '\\' | '^')
+}
+
+fn starts_with(text: &[char], prefix: &str) -> bool {
+    let mut chars = text.iter();
+    prefix.chars().all(|c| chars.next() == Some(&c))
+}
+
+// Trailing punctuation usually belongs to the surrounding sentence
+// Closing brackets are kept if they balance an opening bracket inside the url
+fn trim_url_end(url: &[char]) -> usize {
+    let count = |text: &[char], c: char| text.iter().filter(|&&x| x == c).count();
+    let mut end = url.len();
+    while end > 0 {
+        let text = &url[..end];
+        let is_trimmed = match text[end-1] {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' => true,
+            ')' => count(text, '(') < count(text, ')'),
+            ']' => count(text, '[') < count(text, ']'),
+            _ => false,
+        };
+        if !is_trimmed {
+            break;
+        }
+        end -= 1;
+    }
+    end
+}
+
+// Returns the character ranges of urls in the text
+pub fn find_urls_in_text(text: &[char]) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let Some(scheme) = URL_SCHEMES.iter().find(|scheme| starts_with(&text[i..], scheme)) else {
+            i += 1;
+            continue;
+        };
+        let start = i;
+        let mut end = start + scheme.len();
+        while end < text.len() && is_url_character(text[end]) {
+            end += 1;
+        }
+        end = start + trim_url_end(&text[start..end]);
+        // scheme by itself isn't a url
+        if end > start + scheme.len() {
+            urls.push(start..end);
+        }
+        i = end.max(start + scheme.len());
+    }
+    urls
+}
+
+// Finds urls in the logical line made up of the soft wrapped rendered rows
+fn find_urls_in_line(renderer: &TerminalRenderer, display: &TerminalDisplay, rows: Range<usize>) -> Vec<UrlSpan> {
+    let width = renderer.get_size().x;
+    let cells = &renderer.get_cells()[rows.start*width..rows.end*width];
+    let get_position = |i: usize| Vector2::new(i % width, rows.start + i/width);
+    let mut urls = Vec::new();
+    // hyperlinks from OSC 8 take precedence over detected urls
+    let mut i = 0;
+    while i < cells.len() {
+        let Some(id) = cells[i].pen.hyperlink else {
+            i += 1;
+            continue;
+        };
+        let start = i;
+        while i < cells.len() && cells[i].pen.hyperlink == Some(id) {
+            i += 1;
+        }
+        if let Some(uri) = display.get_hyperlink(id) {
+            urls.push(UrlSpan { start: get_position(start), end: get_position(i-1), url: uri.to_owned() });
+        }
+    }
+    let text: Vec<char> = cells.iter().map(|cell| cell.character).collect();
+    for range in find_urls_in_text(text.as_slice()) {
+        urls.push(UrlSpan {
+            start: get_position(range.start),
+            end: get_position(range.end-1),
+            url: text[range].iter().collect(),
+        });
+    }
+    urls
+}
+
+// Cells in rendered grid coordinates with the same OSC 8 hyperlink and an inclusive end
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct HyperlinkSpan {
+    pub id: u32,
+    pub start: Vector2<usize>,
+    pub end: Vector2<usize>,
+}
+
+// Cells around the position with the same OSC 8 hyperlink
+// Only the rendered cells are read so this is cheap enough to check every time the mouse moves
+pub fn get_hyperlink_span(renderer: &TerminalRenderer, pos: Vector2<usize>) -> Option<HyperlinkSpan> {
+    let width = renderer.get_size().x;
+    let cells = renderer.get_cells();
+    if pos.x >= width {
+        return None;
+    }
+    let index = pos.y*width + pos.x;
+    let id = cells.get(index)?.pen.hyperlink?;
+    let mut start = index;
+    while start > 0 && cells[start-1].pen.hyperlink == Some(id) {
+        start -= 1;
+    }
+    let mut end = index;
+    while end+1 < cells.len() && cells[end+1].pen.hyperlink == Some(id) {
+        end += 1;
+    }
+    let get_position = |i: usize| Vector2::new(i % width, i / width);
+    Some(HyperlinkSpan { id, start: get_position(start), end: get_position(end) })
+}
+
+// Urls are cached per logical line and only rescanned when the rows of the line change
+#[derive(Clone,Debug,Default)]
+pub struct UrlCache {
+    row_generations: Vec<u64>,
+    // urls are stored in the first row of their logical line
+    line_urls: Vec<Vec<UrlSpan>>,
+}
+
+impl UrlCache {
+    // Returns the number of lines that were rescanned
+    pub fn update(&mut self, renderer: &TerminalRenderer, display: &TerminalDisplay) -> usize {
+        let generations = renderer.get_row_generations();
+        let row_is_linebreak = renderer.get_row_is_linebreak();
+        let total_rows = generations.len().min(row_is_linebreak.len());
+        self.row_generations.resize(total_rows, 0);
+        self.line_urls.resize_with(total_rows, Vec::new);
+        let mut total_scanned = 0;
+        let mut start = 0;
+        while start < total_rows {
+            let mut end = start+1;
+            while end < total_rows && !row_is_linebreak[end-1] {
+                end += 1;
+            }
+            let is_changed = (start..end).any(|y| self.row_generations[y] != generations[y]);
+            if is_changed {
+                self.line_urls[start] = find_urls_in_line(renderer, display, start..end);
+                // rows may have previously been the start of another line
+                self.line_urls[start+1..end].iter_mut().for_each(|urls| urls.clear());
+                total_scanned += 1;
+            }
+            start = end;
+        }
+        self.row_generations.copy_from_slice(&generations[..total_rows]);
+        total_scanned
+    }
+
+    pub fn get_url_at(&self, pos: Vector2<usize>) -> Option<&UrlSpan> {
+        self.line_urls.iter().flatten().find(|url| url.contains(pos))
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
-    primitives::{Cell, Pen},
+    primitives::{Cell, Pen, StyleFlags, is_wide_character},
     scrollback_buffer::ScrollbackBuffer,
 };
 use cgmath::Vector2;
 
+pub(crate) const TAB_STOP_WIDTH: usize = 8;
+
 #[derive(Clone,Copy,Default,Debug)]
 pub struct LineStatus {
     pub length: usize,
@@ -24,8 +26,20 @@ pub struct Viewport {
     pub(crate) pen: Pen,
     pub(crate) default_pen: Pen,
     pub(crate) is_newline_carriage_return: bool, // if true then \n will also set cursor.x = 0
+    pub(crate) is_insert_mode: bool, // if true then written characters shift the rest of the line right
+    overflow_row_status: LineStatus, // returned for rows outside of the viewport
+}
+
+// Cell at a row and column of the viewport that differs between two viewports
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct CellDiff {
+    pub pos: Vector2<usize>,
+    pub from: Cell,
+    pub to: Cell,
 }
 
+static OVERFLOW_ROW_STATUS: LineStatus = LineStatus { length: 0, is_linebreak: false };
+
 pub const DEFAULT_VIEWPORT_SIZE: Vector2<usize> = Vector2::new(128,128);
 
 impl Default for Viewport {
@@ -44,34 +58,91 @@ impl Default for Viewport {
             pen: Pen::default(),
             default_pen: Pen::default(),
             is_newline_carriage_return: false,
+            is_insert_mode: false,
+            overflow_row_status: LineStatus::default(),
         }
     }
 }
 
 impl Viewport {
+    // Copies everything needed to render the viewport except for the scrollback buffer
+    // Existing allocations in the destination are reused
+    pub fn copy_to(&self, dst: &mut Viewport) {
+        dst.cursor = self.cursor;
+        dst.size = self.size;
+        dst.row_offset = self.row_offset;
+        dst.cells.clone_from(&self.cells);
+        dst.row_status.clone_from(&self.row_status);
+        dst.saved_cursor = self.saved_cursor;
+        dst.scrollback_buffer = None;
+        dst.pen = self.pen;
+        dst.default_pen = self.default_pen;
+        dst.is_newline_carriage_return = self.is_newline_carriage_return;
+        dst.is_insert_mode = self.is_insert_mode;
+    }
+
+    // Cells that need to change to turn this viewport into the other one in row then column order
+    // Rows are compared in display order so scrolled viewports with the same content have no differences
+    pub fn diff(&self, other: &Viewport) -> Vec<CellDiff> {
+        let blank = Cell { character: ' ', pen: self.default_pen };
+        let mut diffs = Vec::new();
+        for y in 0..other.size.y {
+            let (to_row, _) = other.get_row(y);
+            let from_row = if y < self.size.y { Some(self.get_row(y).0) } else { None };
+            for (x, to) in to_row.iter().enumerate() {
+                // cells outside of this viewport after a resize are treated as blank
+                let from = from_row.and_then(|row| row.get(x)).copied().unwrap_or(blank);
+                if from != *to {
+                    diffs.push(CellDiff { pos: Vector2::new(x,y), from, to: *to });
+                }
+            }
+        }
+        diffs
+    }
+
     #[inline]
     pub(crate) fn write_utf8(&mut self, character: char) {
         let cell = Cell { character, pen: self.pen };
+        let is_wide = is_wide_character(character) && self.size.x > 1;
+        // wide characters move to the next row instead of being split across the edge
+        if is_wide && self.cursor.x+1 == self.size.x {
+            self.cursor.x = self.size.x;
+        }
+        if self.is_insert_mode {
+            self.wrap_cursor();
+            self.shift_cells_right(if is_wide { 2 } else { 1 });
+        }
         self.write_cell(&cell);
+        if is_wide {
+            let pen = Pen { style_flags: self.pen.style_flags | StyleFlags::WideSpacer, ..self.pen };
+            self.write_cell(&Cell { character: ' ', pen });
+        }
+    }
+
+    // Cells pushed past the end of the row are lost
+    fn shift_cells_right(&mut self, total: usize) {
+        let cursor = self.cursor;
+        let (line, status) = self.get_row_mut(cursor.y);
+        let width = line.len();
+        let region = &mut line[cursor.x..];
+        let total = total.min(region.len());
+        let shift = region.len()-total;
+        region.copy_within(0..shift, total);
+        if status.length > cursor.x {
+            status.length = (status.length+total).min(width);
+        }
     }
 
     #[inline]
     pub(crate) fn write_ascii(&mut self, b: u8) {
         match b {
-            b'\n' => {
-                self.feed_newline(true);
-                if self.is_newline_carriage_return {
-                    self.carriage_return();
-                }
-            }, 
+            b'\n' => self.handle_newline(),
             b'\r' => self.carriage_return(),
-            b'\x08' => { 
-                let mut cursor = self.get_cursor(); 
-                cursor.x = cursor.x.saturating_sub(1);
-                self.set_cursor(cursor);
-            },
+            b'\x08' => self.handle_backspace(),
+            b'\t' => self.handle_horizontal_tab(),
             b' '..=b'~' => { self.write_utf8(b as char); },
-            b'\x07' => { log::info!("Ding ding ding (BELL)"); },
+            // bell is forwarded to the window by the parser handler
+            b'\x07' => {},
             b => { log::error!("Unhandled byte: {}", b); },
         }
     }
@@ -87,6 +158,10 @@ impl Viewport {
         }
     }
 
+    pub(crate) fn clear_saved_cursor(&mut self) {
+        self.saved_cursor = None;
+    }
+
     pub(crate) fn set_size(&mut self, new_size: Vector2<usize>) {
         assert!(new_size.x > 0);
         assert!(new_size.y > 0);
@@ -138,6 +213,18 @@ impl Viewport {
         self.size
     }
 
+    pub fn get_row_count(&self) -> usize {
+        self.size.y
+    }
+
+    pub fn get_column_count(&self) -> usize {
+        self.size.x
+    }
+
+    pub fn total_cells(&self) -> usize {
+        self.size.x*self.size.y
+    }
+
     pub(crate) fn set_cursor(&mut self, cursor: Vector2<usize>) {
         // cursor can overflow the screen apparently without moving to new line
         // newline only occurs when a change is committed at the overflowing location onto the next line
@@ -152,16 +239,39 @@ impl Viewport {
         self.cursor
     }
 
+    // Rows outside of the viewport are empty instead of panicking
     pub fn get_row(&self, row: usize) -> (&[Cell], &LineStatus) {
-        assert!(row < self.size.y);
+        if row >= self.size.y {
+            log::error!("Row {} is outside of viewport with {} rows", row, self.size.y);
+            return (&[], &OVERFLOW_ROW_STATUS);
+        }
         let row = self.get_row_index(row);
         let i = self.size.x*row;
         let line = &self.cells[i..(i+self.size.x)];
         (line, &self.row_status[row])
     }
  
+    // Cells of every row in display order as two slices since rows are stored in a ring like VecDeque::as_slices
+    // Cells past the length of a row are not guaranteed to be blank
+    pub fn get_cells_flat(&self) -> (&[Cell], &[Cell]) {
+        let total_cells = self.size.x*self.size.y;
+        let (tail, head) = self.cells[..total_cells].split_at(self.row_offset*self.size.x);
+        (head, tail)
+    }
+
+    pub(crate) fn get_cells_flat_mut(&mut self) -> (&mut [Cell], &mut [Cell]) {
+        let total_cells = self.size.x*self.size.y;
+        let (tail, head) = self.cells[..total_cells].split_at_mut(self.row_offset*self.size.x);
+        (head, tail)
+    }
+
     pub(crate) fn get_row_mut(&mut self, row: usize) -> (&mut [Cell], &mut LineStatus) {
-        assert!(row < self.size.y);
+        if row >= self.size.y {
+            log::error!("Row {} is outside of viewport with {} rows", row, self.size.y);
+            // writes are discarded
+            self.overflow_row_status = LineStatus::default();
+            return (&mut [], &mut self.overflow_row_status);
+        }
         let row = self.get_row_index(row);
         let i = self.size.x*row;
         let line = &mut self.cells[i..(i+self.size.x)];
@@ -184,6 +294,27 @@ impl Viewport {
         self.cursor.x = 0;
     }
 
+    #[inline]
+    pub(crate) fn handle_backspace(&mut self) {
+        self.cursor.x = self.cursor.x.saturating_sub(1);
+    }
+
+    // Tab stops are fixed at every TAB_STOP_WIDTH columns and the cursor stops at the last column
+    #[inline]
+    pub(crate) fn handle_horizontal_tab(&mut self) {
+        let next_stop = (self.cursor.x/TAB_STOP_WIDTH + 1)*TAB_STOP_WIDTH;
+        self.cursor.x = next_stop.min(self.size.x.saturating_sub(1));
+    }
+
+    // Line feed that also returns to the first column when new line mode is set
+    #[inline]
+    pub(crate) fn handle_newline(&mut self) {
+        self.feed_newline(true);
+        if self.is_newline_carriage_return {
+            self.carriage_return();
+        }
+    }
+
     #[inline]
     pub(crate) fn feed_newline(&mut self, is_linebreak: bool) {
         assert!(self.cursor.y < self.size.y);
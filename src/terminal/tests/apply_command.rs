@@ -0,0 +1,128 @@
+mod common;
+
+use terminal::{Cell, Terminal};
+use terminal::terminal_renderer::TerminalRenderer;
+use vt100::command::Command;
+use vt100::parser::{Parser, ParserError, ParserHandler};
+use cgmath::Vector2;
+
+#[derive(Default)]
+struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl ParserHandler for CommandQueue {
+    fn on_command(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+
+    fn on_error(&mut self, error: ParserError, _parser: &Parser) {
+        panic!("Generated sequence failed to parse: {:?}", error);
+    }
+}
+
+// Sequence without the leading escape byte
+fn parse_commands(sequence: &[u8]) -> Vec<Command> {
+    let mut parser = Parser::default();
+    let mut queue = CommandQueue::default();
+    for &b in sequence {
+        parser.feed_byte(b, &mut queue);
+    }
+    assert!(parser.is_terminated());
+    queue.commands
+}
+
+fn get_state(terminal: &mut Terminal) -> (Vec<Cell>, Vector2<usize>) {
+    let mut renderer = TerminalRenderer::default();
+    let display = terminal.get_display();
+    renderer.render_display(&display);
+    (renderer.get_cells().to_vec(), display.get_cursor_position())
+}
+
+// Xorshift so failures can be reproduced from the seed
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self, max: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % max
+    }
+}
+
+enum Chunk {
+    Text(String),
+    // escape sequence without the leading escape byte
+    Sequence(String),
+}
+
+fn generate_chunk(random: &mut Random) -> Chunk {
+    const GRAPHIC_STYLES: [u64; 22] = [0, 1, 2, 3, 4, 7, 9, 22, 23, 24, 27, 31, 32, 34, 37, 39, 42, 45, 49, 90, 96, 107];
+    let n = random.next(10) + 1;
+    match random.next(18) {
+        0 => Chunk::Text("hello".to_owned()),
+        1 => Chunk::Text("world\r\n".to_owned()),
+        2 => Chunk::Sequence(format!("[{}A", n)),
+        3 => Chunk::Sequence(format!("[{}B", n)),
+        4 => Chunk::Sequence(format!("[{}C", n)),
+        5 => Chunk::Sequence(format!("[{}D", n)),
+        6 => Chunk::Sequence(format!("[{};{}H", random.next(10) + 1, random.next(22) + 1)),
+        7 => Chunk::Sequence(format!("[{}J", random.next(3))),
+        8 => Chunk::Sequence(format!("[{}K", random.next(3))),
+        9 => Chunk::Sequence(format!("[{}L", n)),
+        10 => Chunk::Sequence(format!("[{}M", n)),
+        11 => Chunk::Sequence(format!("[{}P", n)),
+        12 => Chunk::Sequence(format!("[{}@", n)),
+        13 => Chunk::Sequence(format!("[{}X", n)),
+        14 => Chunk::Sequence(format!("[{}S", n)),
+        15 => Chunk::Sequence(format!("[{}m", GRAPHIC_STYLES[random.next(GRAPHIC_STYLES.len() as u64) as usize])),
+        16 => Chunk::Sequence(format!("[38;5;{}m", random.next(256))),
+        _ => Chunk::Sequence(format!("[48;2;{};{};{}m", random.next(256), random.next(256), random.next(256))),
+    }
+}
+
+#[test]
+fn commands_match_parsed_bytes() {
+    for seed in 1..=8u64 {
+        let mut random = Random(seed * 0x9E37_79B9_7F4A_7C15);
+        let chunks: Vec<Chunk> = (0..300).map(|_| generate_chunk(&mut random)).collect();
+        let mut data = Vec::new();
+        for chunk in chunks.iter() {
+            match chunk {
+                Chunk::Text(text) => data.extend_from_slice(text.as_bytes()),
+                Chunk::Sequence(sequence) => {
+                    data.push(b'\x1b');
+                    data.extend_from_slice(sequence.as_bytes());
+                },
+            }
+        }
+        let mut parsed_terminal = common::create_exited_terminal_with_size(data, Vector2::new(20,8)).terminal;
+        let mut applied_terminal = common::create_exited_terminal_with_size(Vec::new(), Vector2::new(20,8)).terminal;
+        for chunk in chunks.iter() {
+            match chunk {
+                Chunk::Text(text) => applied_terminal.write_to_display(text.as_bytes()),
+                Chunk::Sequence(sequence) => {
+                    for command in parse_commands(sequence.as_bytes()) {
+                        applied_terminal.apply_command(command);
+                    }
+                },
+            }
+        }
+        assert!(get_state(&mut parsed_terminal) == get_state(&mut applied_terminal), "state differs for seed {}", seed);
+    }
+}
+
+#[test]
+fn apply_command_refreshes_front_buffer() {
+    use vt100::common::GraphicStyle;
+    let mut terminal = common::create_exited_terminal_with_size(b"abc", Vector2::new(20,8)).terminal;
+    terminal.apply_command(Command::MoveCursorLeft(2));
+    terminal.apply_command(Command::SetGraphicStyle(GraphicStyle::EnableBold));
+    terminal.write_to_display(b"X");
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&terminal.get_front_buffer());
+    let row: String = renderer.get_cells()[..3].iter().map(|cell| cell.character).collect();
+    assert_eq!(row, "aXc");
+    assert!(renderer.get_cells()[1].pen.style_flags.contains(terminal::StyleFlags::Bold));
+}
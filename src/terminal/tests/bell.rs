@@ -0,0 +1,30 @@
+mod common;
+
+use vt100::common::{BellVolume, WindowAction};
+use std::sync::mpsc::Receiver;
+
+fn count_bells(rx: &Receiver<WindowAction>) -> usize {
+    rx.try_iter().filter(|action| *action == WindowAction::Bell).count()
+}
+
+#[test]
+fn bell_is_sent_to_window() {
+    let test = common::create_exited_terminal(b"done\x07");
+    assert_eq!(count_bells(&test.window_actions), 1);
+}
+
+#[test]
+fn osc_terminator_is_not_a_bell() {
+    let test = common::create_exited_terminal(b"\x1b]0;title\x07hello");
+    assert_eq!(count_bells(&test.window_actions), 0);
+}
+
+#[test]
+fn warning_bell_volume_is_stored() {
+    let mut terminal = common::create_exited_terminal(b"hello").terminal;
+    assert_eq!(terminal.get_display().get_bell_volume(), BellVolume::High);
+    let mut terminal = common::create_exited_terminal(b"\x1b[1 t").terminal;
+    assert_eq!(terminal.get_display().get_bell_volume(), BellVolume::Off);
+    let mut terminal = common::create_exited_terminal(b"\x1b[1 t\x1b[3 t").terminal;
+    assert_eq!(terminal.get_display().get_bell_volume(), BellVolume::Low);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::{Terminal, TerminalUserEvent};
+use terminal::terminal_renderer::TerminalRenderer;
+use std::time::Duration;
+
+// Enough lines to scroll past the default viewport so they are ejected into scrollback
+fn create_scrolling_output(suffix: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..200 {
+        data.extend_from_slice(format!("line{}\r\n", i).as_bytes());
+    }
+    data.extend_from_slice(b"last");
+    data.extend_from_slice(suffix);
+    data
+}
+
+fn render_viewport_rows(terminal: &mut Terminal) -> Vec<String> {
+    let display = terminal.get_display();
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_display(&display);
+    let size = renderer.get_size();
+    renderer.get_cells()
+        .chunks(size.x)
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+        .collect()
+}
+
+#[test]
+fn erase_saved_lines_keeps_viewport() {
+    let mut terminal = common::create_exited_terminal(create_scrolling_output(b"")).terminal;
+    assert!(terminal.get_display().get_total_scrollback_lines() > 0);
+    let expected_rows = render_viewport_rows(&mut terminal);
+
+    let mut terminal = common::create_exited_terminal(create_scrolling_output(b"\x1b[3J")).terminal;
+    assert_eq!(terminal.get_display().get_total_scrollback_lines(), 0);
+    let rows = render_viewport_rows(&mut terminal);
+    assert_eq!(rows, expected_rows);
+    assert!(rows.iter().any(|row| row == "last"));
+}
+
+#[test]
+fn erase_entire_display_keeps_scrollback() {
+    let mut terminal = common::create_exited_terminal(create_scrolling_output(b"\x1b[2J")).terminal;
+    assert!(terminal.get_display().get_total_scrollback_lines() > 0);
+    let rows = render_viewport_rows(&mut terminal);
+    assert!(rows.iter().all(|row| row.is_empty()));
+}
+
+#[test]
+fn clear_scrollback_user_event() {
+    let TestTerminal { mut terminal, window_actions: rx, .. } = common::create_exited_terminal(create_scrolling_output(b""));
+    assert!(terminal.get_display().get_total_scrollback_lines() > 0);
+    let expected_rows = render_viewport_rows(&mut terminal);
+    // drain refreshes sent while parsing
+    while rx.try_recv().is_ok() {}
+    terminal.get_user_event_handler().send(TerminalUserEvent::ClearScrollback).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(terminal.get_display().get_total_scrollback_lines(), 0);
+    assert_eq!(render_viewport_rows(&mut terminal), expected_rows);
+}
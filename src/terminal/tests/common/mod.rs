@@ -0,0 +1,132 @@
+// Each test only uses some of the helpers
+#![allow(dead_code)]
+
+use terminal::{Terminal, TerminalBuilder, TerminalIOControl, TerminalSettings, TerminalUserEvent};
+use vt100::common::WindowAction;
+use cgmath::Vector2;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+pub type ProcessRead = Box<dyn FnMut(&mut [u8]) -> usize + Send>;
+
+pub struct TerminalOptions {
+    // process closes its output once this returns 0
+    pub process_read: ProcessRead,
+    pub is_newline_carriage_return: bool,
+    pub settings: TerminalSettings,
+    pub record_output: Option<Box<dyn std::io::Write + Send>>,
+    pub process_resize: Option<Receiver<Vector2<usize>>>,
+    pub on_parser_exit: Option<Box<dyn FnOnce() + Send>>,
+    // all output is parsed before the terminal is returned
+    pub is_joined: bool,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            process_read: Box::new(|_| 0),
+            is_newline_carriage_return: false,
+            settings: TerminalSettings::default(),
+            record_output: None,
+            process_resize: None,
+            on_parser_exit: None,
+            is_joined: false,
+        }
+    }
+}
+
+// Everything the terminal sends to the process and window is received here
+pub struct TestTerminal {
+    pub terminal: Terminal,
+    pub process_writes: Receiver<Vec<u8>>,
+    pub process_ioctls: Receiver<TerminalIOControl>,
+    pub window_actions: Receiver<WindowAction>,
+}
+
+impl TestTerminal {
+    // Output written by the process afterwards is parsed at the new size
+    pub fn resize(&self, grid_size: Vector2<usize>) {
+        let window_size = Vector2::new(grid_size.x*8, grid_size.y*16);
+        let user_events = self.terminal.get_user_event_handler();
+        user_events.send(TerminalUserEvent::Resize { window_size, grid_size }).unwrap();
+        user_events.send(TerminalUserEvent::ForceRedraw).unwrap();
+        while self.window_actions.recv_timeout(Duration::from_secs(5)).unwrap() != WindowAction::Refresh {}
+    }
+}
+
+pub fn create_terminal(options: TerminalOptions) -> TestTerminal {
+    let (write_tx, process_writes) = std::sync::mpsc::channel();
+    let (ioctl_tx, process_ioctls) = std::sync::mpsc::channel();
+    let (action_tx, window_actions) = std::sync::mpsc::channel();
+    let mut terminal = Terminal::new(TerminalBuilder {
+        process_read: options.process_read,
+        process_write: Box::new(move |data: &[u8]| {
+            let _ = write_tx.send(data.to_vec());
+        }),
+        process_ioctl: Box::new(move |ev| {
+            let _ = ioctl_tx.send(ev);
+        }),
+        window_action: Box::new(move |action| {
+            let _ = action_tx.send(action);
+        }),
+        is_newline_carriage_return: options.is_newline_carriage_return,
+        settings: options.settings,
+        record_output: options.record_output,
+        process_resize: options.process_resize,
+        on_parser_exit: options.on_parser_exit,
+    });
+    if options.is_joined {
+        terminal.join_parser_thread();
+    }
+    TestTerminal { terminal, process_writes, process_ioctls, window_actions }
+}
+
+// Process writes the data over as many reads as it takes and then exits
+pub fn read_data(data: impl Into<Vec<u8>>) -> ProcessRead {
+    let data = data.into();
+    let mut offset = 0;
+    Box::new(move |buf: &mut [u8]| {
+        let total = buf.len().min(data.len()-offset);
+        buf[..total].copy_from_slice(&data[offset..offset+total]);
+        offset += total;
+        total
+    })
+}
+
+// Process writes each chunk when it is sent and exits once the sender is dropped
+pub fn read_channel() -> (Sender<Vec<u8>>, ProcessRead) {
+    let (tx, rx) = std::sync::mpsc::channel::<Vec<u8>>();
+    let process_read = Box::new(move |buf: &mut [u8]| {
+        let Ok(src) = rx.recv() else {
+            return 0;
+        };
+        buf[..src.len()].copy_from_slice(src.as_slice());
+        src.len()
+    });
+    (tx, process_read)
+}
+
+// Output is parsed at the default grid size
+pub fn create_exited_terminal(data: impl Into<Vec<u8>>) -> TestTerminal {
+    create_terminal(TerminalOptions {
+        process_read: read_data(data),
+        is_joined: true,
+        ..Default::default()
+    })
+}
+
+// Output is parsed after the grid is resized
+pub fn create_exited_terminal_with_size(data: impl Into<Vec<u8>>, grid_size: Vector2<usize>) -> TestTerminal {
+    let (data_tx, process_read) = read_channel();
+    let mut test = create_terminal(TerminalOptions {
+        process_read,
+        ..Default::default()
+    });
+    test.resize(grid_size);
+    for chunk in data.into().chunks(1024) {
+        data_tx.send(chunk.to_vec()).unwrap();
+    }
+    drop(data_tx);
+    test.terminal.join_parser_thread();
+    test
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::TerminalUserEvent;
+use std::time::Duration;
+
+#[test]
+fn window_focus_reaches_encoder() {
+    // enable focus reporting then exit
+    let TestTerminal { terminal, process_writes: rx, .. } = common::create_exited_terminal(b"\x1b[?1004h");
+    let user_events = terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::WindowFocus(false)).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), b"\x1b[O");
+    user_events.send(TerminalUserEvent::WindowFocus(true)).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), b"\x1b[I");
+}
@@ -0,0 +1,16 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::TerminalUserEvent;
+use vt100::common::WindowAction;
+use std::time::Duration;
+
+#[test]
+fn force_redraw_sends_refresh() {
+    // process exits immediately so the parser never requests a refresh
+    let TestTerminal { terminal, window_actions: rx, .. } = common::create_exited_terminal(b"");
+    assert!(rx.try_recv().is_err());
+    terminal.get_user_event_handler().send(TerminalUserEvent::ForceRedraw).unwrap();
+    let action = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(action, WindowAction::Refresh);
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::TerminalUserEvent;
+use vt100::encoder::{KeyCode, ModifierKey};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+fn read_output(rx: &Receiver<Vec<u8>>) -> Vec<u8> {
+    rx.recv_timeout(Duration::from_secs(5)).unwrap()
+}
+
+#[test]
+fn input_text_is_written_as_utf8() {
+    let TestTerminal { terminal, process_writes: rx, .. } = common::create_exited_terminal(b"");
+    let user_events = terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::InputText("日本語".to_owned())).unwrap();
+    assert_eq!(read_output(&rx), "日本語".as_bytes());
+    user_events.send(TerminalUserEvent::InputText("한a".to_owned())).unwrap();
+    assert_eq!(read_output(&rx), "한a".as_bytes());
+}
+
+#[test]
+fn input_text_ignores_modes_and_modifiers() {
+    // bracketed paste mode only applies to pasted text
+    let TestTerminal { terminal, process_writes: rx, .. } = common::create_exited_terminal(b"\x1b[?2004h");
+    let user_events = terminal.get_user_event_handler();
+    // ctrl would otherwise turn the ascii character into a control code
+    user_events.send(TerminalUserEvent::KeyPress(KeyCode::ModifierKey(ModifierKey::Ctrl))).unwrap();
+    user_events.send(TerminalUserEvent::InputText("c你好".to_owned())).unwrap();
+    assert_eq!(read_output(&rx), "c你好".as_bytes());
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::TerminalOptions;
+use terminal::Terminal;
+use terminal::terminal_renderer::TerminalRenderer;
+use cgmath::Vector2;
+
+fn create_terminal(grid_size: Vector2<usize>) -> Terminal {
+    let test = common::create_terminal(TerminalOptions::default());
+    test.resize(grid_size);
+    test.terminal
+}
+
+fn get_rows(terminal: &Terminal) -> Vec<String> {
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&terminal.get_front_buffer());
+    let size = renderer.get_size();
+    renderer.get_cells()
+        .chunks(size.x)
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+        .collect()
+}
+
+#[test]
+fn insert_mode_shifts_line_right() {
+    let mut terminal = create_terminal(Vector2::new(20,4));
+    terminal.write_to_display(b"hello world\x1b[1;7H\x1b[4hbig ");
+    assert!(terminal.get_display().get_insert_mode());
+    assert_eq!(get_rows(&terminal)[0], "hello big world");
+    assert_eq!(terminal.get_display().get_cursor_position(), Vector2::new(10,0));
+}
+
+#[test]
+fn replace_mode_overwrites() {
+    let mut terminal = create_terminal(Vector2::new(20,4));
+    terminal.write_to_display(b"hello world\x1b[4h\x1b[4l\x1b[1;7Hthere");
+    assert!(!terminal.get_display().get_insert_mode());
+    assert_eq!(get_rows(&terminal)[0], "hello there");
+}
+
+#[test]
+fn insert_mode_drops_characters_past_edge() {
+    let mut terminal = create_terminal(Vector2::new(8,4));
+    terminal.write_to_display(b"abcdefgh\x1b[1;1H\x1b[4hXY");
+    let rows = get_rows(&terminal);
+    assert_eq!(rows[0], "XYabcdef");
+    // shifted characters don't wrap onto the next line
+    assert_eq!(rows[1], "");
+}
+
+#[test]
+fn insert_mode_applies_to_utf8_characters() {
+    let mut terminal = create_terminal(Vector2::new(20,4));
+    terminal.write_to_display("ab\x1b[1;2H\x1b[4héü".as_bytes());
+    assert_eq!(get_rows(&terminal)[0], "aéüb");
+}
+
+#[test]
+fn soft_reset_returns_to_replace_mode() {
+    let mut terminal = create_terminal(Vector2::new(20,4));
+    terminal.write_to_display(b"\x1b[4h\x1b[!pabc\x1b[1;1HX");
+    assert!(!terminal.get_display().get_insert_mode());
+    assert_eq!(get_rows(&terminal)[0], "Xbc");
+}
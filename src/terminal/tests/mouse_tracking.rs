@@ -0,0 +1,11 @@
+mod common;
+
+use vt100::encoder::MouseTrackingMode;
+
+#[test]
+fn mouse_tracking_mode_follows_process() {
+    assert_eq!(common::create_exited_terminal(b"").terminal.get_mouse_tracking_mode(), MouseTrackingMode::Disabled);
+    assert_eq!(common::create_exited_terminal(b"\x1b[?1000h").terminal.get_mouse_tracking_mode(), MouseTrackingMode::Normal);
+    assert_eq!(common::create_exited_terminal(b"\x1b[?1003h").terminal.get_mouse_tracking_mode(), MouseTrackingMode::Any);
+    assert_eq!(common::create_exited_terminal(b"\x1b[?1002h\x1b[?1002l").terminal.get_mouse_tracking_mode(), MouseTrackingMode::Disabled);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::TerminalOptions;
+use terminal::terminal_renderer::TerminalRenderer;
+use cgmath::Vector2;
+
+// Runs a fake process which writes the data and then exits
+fn render_process_output(data: &'static [u8], is_newline_carriage_return: bool) -> (Vec<String>, Vector2<usize>) {
+    let mut terminal = common::create_terminal(TerminalOptions {
+        process_read: common::read_data(data),
+        is_newline_carriage_return,
+        is_joined: true,
+        ..Default::default()
+    }).terminal;
+    let display = terminal.get_display();
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_display(&display);
+    let size = renderer.get_size();
+    let rows = renderer.get_cells()
+        .chunks(size.x)
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+        .collect();
+    (rows, display.get_cursor_position())
+}
+
+#[test]
+fn automatic_newline_mode() {
+    // linefeed only moves down by default
+    let (rows, cursor) = render_process_output(b"ab\ncd", false);
+    assert_eq!(rows[0], "ab");
+    assert_eq!(rows[1], "  cd");
+    assert_eq!(cursor, Vector2::new(4,1));
+    // ESC[20h makes linefeed also return carriage
+    let (rows, cursor) = render_process_output(b"\x1b[20hab\ncd", false);
+    assert_eq!(rows[0], "ab");
+    assert_eq!(rows[1], "cd");
+    assert_eq!(cursor, Vector2::new(2,1));
+    // ESC[20l restores normal linefeed
+    let (rows, cursor) = render_process_output(b"\x1b[20lab\ncd", true);
+    assert_eq!(rows[0], "ab");
+    assert_eq!(rows[1], "  cd");
+    assert_eq!(cursor, Vector2::new(4,1));
+}
@@ -0,0 +1,12 @@
+mod common;
+
+#[test]
+fn metrics_are_published_after_parsing() {
+    let data = b"hello\x1b[1;31mworld\x1b[0m";
+    let terminal = common::create_exited_terminal(data).terminal;
+    let metrics = terminal.get_parser_metrics();
+    assert_eq!(metrics.bytes_parsed, data.len() as u64);
+    // each graphic style is a separate command
+    assert_eq!(metrics.commands_emitted, 3);
+    assert_eq!(metrics.errors_encountered, 0);
+}
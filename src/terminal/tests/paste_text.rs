@@ -0,0 +1,29 @@
+mod common;
+
+use terminal::TerminalUserEvent;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+fn read_output(rx: &Receiver<Vec<u8>>, total_writes: usize) -> Vec<u8> {
+    let mut output = Vec::new();
+    for _ in 0..total_writes {
+        output.extend(rx.recv_timeout(Duration::from_secs(5)).unwrap());
+    }
+    output
+}
+
+#[test]
+fn paste_without_bracketed_mode() {
+    let test = common::create_exited_terminal(b"");
+    let user_events = test.terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::PasteText("hello\nworld".to_owned())).unwrap();
+    assert_eq!(read_output(&test.process_writes, 1), b"hello\nworld");
+}
+
+#[test]
+fn paste_with_bracketed_mode() {
+    let test = common::create_exited_terminal(b"\x1b[?2004h");
+    let user_events = test.terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::PasteText("hello".to_owned())).unwrap();
+    assert_eq!(read_output(&test.process_writes, 3), b"\x1b[200~hello\x1b[201~");
+}
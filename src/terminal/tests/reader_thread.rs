@@ -0,0 +1,91 @@
+mod common;
+
+use common::{ProcessRead, TerminalOptions};
+use terminal::Terminal;
+use terminal::terminal_renderer::TerminalRenderer;
+use std::time::Duration;
+
+fn create_terminal(process_read: ProcessRead) -> Terminal {
+    common::create_terminal(TerminalOptions {
+        process_read,
+        ..Default::default()
+    }).terminal
+}
+
+fn get_last_line(terminal: &mut Terminal) -> String {
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_display(&terminal.get_display());
+    let size = renderer.get_size();
+    renderer.get_cells()
+        .chunks(size.x)
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+        .rev()
+        .find(|row| !row.is_empty())
+        .unwrap_or_default()
+}
+
+#[test]
+fn all_output_is_parsed_before_join() {
+    let mut lines = (0..2000).map(|i| format!("line{}\r\n", i).into_bytes()).collect::<Vec<_>>();
+    let total_bytes: usize = lines.iter().map(|line| line.len()).sum();
+    lines.reverse();
+    let mut terminal = create_terminal(Box::new(move |buf: &mut [u8]| {
+        let Some(line) = lines.pop() else {
+            return 0;
+        };
+        buf[..line.len()].copy_from_slice(line.as_slice());
+        line.len()
+    }));
+    terminal.join_parser_thread();
+    assert_eq!(terminal.get_total_bytes_read(), total_bytes as u64);
+    assert_eq!(terminal.get_parser_metrics().bytes_parsed, total_bytes as u64);
+    assert_eq!(get_last_line(&mut terminal), "line1999");
+}
+
+#[test]
+fn slow_reads_are_parsed_as_they_arrive() {
+    let (tx, process_read) = common::read_channel();
+    let mut terminal = create_terminal(process_read);
+    tx.send(b"first".to_vec()).unwrap();
+    // the reader thread is still waiting on the process while the first read is parsed
+    let mut is_parsed = false;
+    for _ in 0..500 {
+        if terminal.get_parser_metrics().bytes_parsed == 5 {
+            is_parsed = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(is_parsed);
+    assert_eq!(get_last_line(&mut terminal), "first");
+    // closing the process output shuts down both threads
+    drop(tx);
+    terminal.join_parser_thread();
+}
+
+#[test]
+fn parser_exit_is_notified_after_all_output_is_parsed() {
+    let mut lines = (0..2000).map(|i| format!("line{}\r\n", i).into_bytes()).collect::<Vec<_>>();
+    let total_bytes: usize = lines.iter().map(|line| line.len()).sum();
+    lines.reverse();
+    let (exit_tx, exit_rx) = std::sync::mpsc::channel();
+    let mut terminal = common::create_terminal(TerminalOptions {
+        process_read: Box::new(move |buf: &mut [u8]| {
+            let Some(line) = lines.pop() else {
+                return 0;
+            };
+            buf[..line.len()].copy_from_slice(line.as_slice());
+            line.len()
+        }),
+        on_parser_exit: Some(Box::new(move || {
+            let _ = exit_tx.send(());
+        })),
+        ..Default::default()
+    }).terminal;
+    // display can be written to without joining the parser thread
+    exit_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(terminal.get_parser_metrics().bytes_parsed, total_bytes as u64);
+    assert_eq!(get_last_line(&mut terminal), "line1999");
+    terminal.write_to_display(b"exited");
+    assert_eq!(get_last_line(&mut terminal), "exited");
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use common::{TerminalOptions, TestTerminal};
+use terminal::{TerminalIOControl, TerminalUserEvent};
+use terminal::recording::{RecordingError, RecordingEvent, RecordingReader, RECORDING_MAGIC};
+use cgmath::Vector2;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Recording is written on the terminal threads so it is read back through a shared buffer
+#[derive(Clone,Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(data);
+        Ok(data.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn output_and_resizes_are_recorded() {
+    let mut chunks = vec![b"hello\r\n".to_vec(), b"\x1b[31mred\x1b[0m".to_vec()];
+    chunks.reverse();
+    let buffer = SharedBuffer::default();
+    let TestTerminal { mut terminal, process_ioctls: rx, .. } = common::create_terminal(TerminalOptions {
+        process_read: Box::new(move |buf: &mut [u8]| {
+            let Some(chunk) = chunks.pop() else {
+                return 0;
+            };
+            buf[..chunk.len()].copy_from_slice(chunk.as_slice());
+            chunk.len()
+        }),
+        record_output: Some(Box::new(buffer.clone())),
+        ..Default::default()
+    });
+    terminal.join_parser_thread();
+    let initial_size = terminal.get_display().get_viewport_size();
+    let user_events = terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::Resize { window_size: Vector2::new(640,384), grid_size: Vector2::new(80,24) }).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), TerminalIOControl::SetSize(Vector2::new(80,24)));
+
+    let data = buffer.0.lock().unwrap().clone();
+    assert_eq!(&data[..8], &RECORDING_MAGIC);
+    let mut reader = RecordingReader::new(data.as_slice()).unwrap();
+    assert_eq!(reader.get_header().grid_size, initial_size);
+    let mut frames = Vec::new();
+    while let Some(frame) = reader.read_frame().unwrap() {
+        frames.push(frame);
+    }
+    // each read is recorded as its own frame
+    let events: Vec<RecordingEvent> = frames.iter().map(|frame| frame.event.clone()).collect();
+    assert_eq!(events, vec![
+        RecordingEvent::Output(b"hello\r\n".to_vec()),
+        RecordingEvent::Output(b"\x1b[31mred\x1b[0m".to_vec()),
+        RecordingEvent::Resize(Vector2::new(80,24)),
+    ]);
+    assert!(frames.windows(2).all(|pair| pair[0].elapsed <= pair[1].elapsed));
+    // frame kind, timestamp and length come before the output
+    let first_frame = &data[28..];
+    assert_eq!(first_frame[0], 0);
+    assert_eq!(&first_frame[9..13], &7u32.to_le_bytes());
+    assert_eq!(&first_frame[13..20], b"hello\r\n");
+}
+
+#[test]
+fn invalid_recordings_are_rejected() {
+    assert!(matches!(RecordingReader::new(b"not a recording".as_slice()), Err(RecordingError::InvalidMagic)));
+    assert!(matches!(RecordingReader::new(b"".as_slice()), Err(RecordingError::InvalidMagic)));
+    let mut data = RECORDING_MAGIC.to_vec();
+    data.extend_from_slice(&2u32.to_le_bytes());
+    assert!(matches!(RecordingReader::new(data.as_slice()), Err(RecordingError::UnsupportedVersion(2))));
+    // frame cut off part way through
+    let mut data = RECORDING_MAGIC.to_vec();
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&[0u8; 16]);
+    data.extend_from_slice(&[0, 1, 2]);
+    let mut reader = RecordingReader::new(data.as_slice()).unwrap();
+    assert_eq!(reader.get_header().grid_size, Vector2::new(0,0));
+    assert!(matches!(reader.read_frame(), Err(RecordingError::Io(_))));
+}
+
+#[test]
+fn process_resizes_are_applied_between_output() {
+    let (resize_tx, resize_rx) = std::sync::mpsc::channel();
+    let buffer = SharedBuffer::default();
+    resize_tx.send(Vector2::new(40,10)).unwrap();
+    let mut reads = vec![
+        (None, b"hello".as_slice()),
+        (Some(Vector2::new(20,5)), b" world".as_slice()),
+        (Some(Vector2::new(30,6)), b"".as_slice()),
+    ];
+    reads.reverse();
+    let TestTerminal { mut terminal, process_ioctls: ioctl_rx, .. } = common::create_terminal(TerminalOptions {
+        process_read: Box::new(move |buf: &mut [u8]| {
+            let Some((resize, chunk)) = reads.pop() else {
+                return 0;
+            };
+            if let Some(size) = resize {
+                resize_tx.send(size).unwrap();
+            }
+            buf[..chunk.len()].copy_from_slice(chunk);
+            chunk.len()
+        }),
+        record_output: Some(Box::new(buffer.clone())),
+        process_resize: Some(resize_rx),
+        ..Default::default()
+    });
+    terminal.join_parser_thread();
+    assert_eq!(terminal.get_display().get_viewport_size(), Vector2::new(30,6));
+    // process chose the size so it isn't told about it
+    assert!(ioctl_rx.try_recv().is_err());
+
+    let data = buffer.0.lock().unwrap().clone();
+    let mut reader = RecordingReader::new(data.as_slice()).unwrap();
+    let mut events: Vec<RecordingEvent> = Vec::new();
+    // resizes are recorded before the output of the read they were sent during
+    while let Some(frame) = reader.read_frame().unwrap() {
+        events.push(frame.event);
+    }
+    assert_eq!(events, vec![
+        RecordingEvent::Resize(Vector2::new(40,10)),
+        RecordingEvent::Output(b"hello".to_vec()),
+        RecordingEvent::Resize(Vector2::new(20,5)),
+        RecordingEvent::Output(b" world".to_vec()),
+        RecordingEvent::Resize(Vector2::new(30,6)),
+    ]);
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::{TerminalIOControl, TerminalUserEvent};
+use terminal::terminal_renderer::TerminalRenderer;
+use cgmath::Vector2;
+use std::time::Duration;
+
+#[test]
+fn resize_updates_grid_and_process() {
+    let TestTerminal { terminal, process_ioctls: rx, .. } = common::create_exited_terminal(b"");
+    let user_events = terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::Resize { window_size: Vector2::new(640,384), grid_size: Vector2::new(80,24) }).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), TerminalIOControl::SetSize(Vector2::new(80,24)));
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&terminal.get_front_buffer());
+    assert_eq!(renderer.get_size(), Vector2::new(80,24));
+    // empty sizes are clamped to a single cell
+    user_events.send(TerminalUserEvent::Resize { window_size: Vector2::new(0,0), grid_size: Vector2::new(0,0) }).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), TerminalIOControl::SetSize(Vector2::new(1,1)));
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use common::TerminalOptions;
+use terminal::terminal_renderer::TerminalRenderer;
+use vt100::common::WindowAction;
+use std::time::Duration;
+
+// Echoes input like a pty and runs echo commands like a shell
+fn run_fake_shell(input: &[u8]) -> Vec<u8> {
+    let mut output = input.to_vec();
+    if let Some(text) = input.strip_prefix(b"echo ") {
+        output.extend_from_slice(text);
+    }
+    output
+}
+
+#[test]
+fn raw_input_reaches_process() {
+    let (output_tx, process_read) = common::read_channel();
+    let test = common::create_terminal(TerminalOptions {
+        process_read,
+        ..Default::default()
+    });
+    test.terminal.send_raw(b"echo hello\r\n");
+    let input = test.process_writes.recv_timeout(Duration::from_secs(5)).unwrap();
+    output_tx.send(run_fake_shell(input.as_slice())).unwrap();
+    assert_eq!(test.window_actions.recv_timeout(Duration::from_secs(5)).unwrap(), WindowAction::Refresh);
+
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&test.terminal.get_front_buffer());
+    let size = renderer.get_size();
+    let rows: Vec<String> = renderer.get_cells()
+        .chunks(size.x)
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+        .collect();
+    assert_eq!(rows[0], "echo hello");
+    assert_eq!(rows[1], "hello");
+}
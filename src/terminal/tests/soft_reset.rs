@@ -0,0 +1,79 @@
+mod common;
+
+use common::TerminalOptions;
+use terminal::{Terminal, TerminalSettings, TerminalUserEvent, StyleFlags};
+use terminal::terminal_renderer::TerminalRenderer;
+use vt100::common::CursorStyle;
+use vt100::encoder::{ArrowKey, KeyCode};
+use cgmath::Vector2;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+fn create_terminal(settings: TerminalSettings) -> (Terminal, Receiver<Vec<u8>>) {
+    let test = common::create_terminal(TerminalOptions {
+        settings,
+        ..Default::default()
+    });
+    test.resize(Vector2::new(80,24));
+    (test.terminal, test.process_writes)
+}
+
+fn press_up_arrow(terminal: &Terminal, rx: &Receiver<Vec<u8>>) -> Vec<u8> {
+    let user_events = terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::KeyPress(KeyCode::ArrowKey(ArrowKey::Up))).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap()
+}
+
+#[test]
+fn soft_reset_restores_cursor() {
+    let (mut terminal, _rx) = create_terminal(TerminalSettings::default());
+    terminal.write_to_display(b"\x1b[?25l\x1b[6 q");
+    assert!(!terminal.get_display().get_cursor_visible());
+    assert_eq!(terminal.get_display().get_cursor_style(), CursorStyle::Bar);
+    terminal.write_to_display(b"\x1b[!p");
+    let display = terminal.get_display();
+    assert!(display.get_cursor_visible());
+    assert_eq!(display.get_cursor_style(), CursorStyle::Block);
+}
+
+#[test]
+fn soft_reset_restores_configured_cursor_style() {
+    let (mut terminal, _rx) = create_terminal(TerminalSettings {
+        cursor_style: Some(CursorStyle::Underline),
+        ..TerminalSettings::default()
+    });
+    terminal.write_to_display(b"\x1b[6 q\x1b[!p");
+    assert_eq!(terminal.get_display().get_cursor_style(), CursorStyle::Underline);
+}
+
+#[test]
+fn soft_reset_restores_pen() {
+    let (mut terminal, _rx) = create_terminal(TerminalSettings::default());
+    terminal.write_to_display(b"\x1b[1;7;31ma\x1b[!pb");
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&terminal.get_front_buffer());
+    let cells = renderer.get_cells();
+    assert!(cells[0].pen.style_flags.contains(StyleFlags::Bold | StyleFlags::Inverse));
+    assert_eq!(cells[1].character, 'b');
+    assert_eq!(cells[1].pen, terminal.get_front_buffer().get_default_pen());
+}
+
+#[test]
+fn soft_reset_clears_saved_cursor() {
+    let (mut terminal, _rx) = create_terminal(TerminalSettings::default());
+    // restoring without a saved cursor leaves it in place
+    terminal.write_to_display(b"\x1b[3;5H\x1b7\x1b[10;10H\x1b[!p\x1b8");
+    assert_eq!(terminal.get_display().get_cursor_position(), Vector2::new(9,9));
+    // cursor is kept where it was
+    terminal.write_to_display(b"\x1b[3;5H\x1b[!p");
+    assert_eq!(terminal.get_display().get_cursor_position(), Vector2::new(4,2));
+}
+
+#[test]
+fn soft_reset_restores_cursor_keys() {
+    let (mut terminal, rx) = create_terminal(TerminalSettings::default());
+    terminal.write_to_display(b"\x1b[?1h");
+    assert_eq!(press_up_arrow(&terminal, &rx), b"\x1bOA");
+    terminal.write_to_display(b"\x1b[!p");
+    assert_eq!(press_up_arrow(&terminal, &rx), b"\x1b[A");
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::TerminalOptions;
+use terminal::{Terminal, TerminalSettings};
+use vt100::common::{CursorStyle, Rgb8};
+
+fn create_terminal(data: Vec<u8>, settings: TerminalSettings) -> Terminal {
+    common::create_terminal(TerminalOptions {
+        process_read: common::read_data(data),
+        settings,
+        is_joined: true,
+        ..Default::default()
+    }).terminal
+}
+
+#[test]
+fn default_colours_and_cursor_style() {
+    let foreground = Rgb8 { r: 0x10, g: 0x20, b: 0x30 };
+    let background = Rgb8 { r: 0xF0, g: 0xE0, b: 0xD0 };
+    let mut terminal = create_terminal(Vec::new(), TerminalSettings {
+        cursor_style: Some(CursorStyle::Bar),
+        foreground_colour: Some(foreground),
+        background_colour: Some(background),
+        ..TerminalSettings::default()
+    });
+    let display = terminal.get_display();
+    assert_eq!(display.get_default_background_colour(), background);
+    assert_eq!(display.get_cursor_style(), CursorStyle::Bar);
+
+    // colours are restored by a graphics reset
+    let mut terminal = create_terminal(b"\x1b[31;42m\x1b[0ma".to_vec(), TerminalSettings {
+        foreground_colour: Some(foreground),
+        background_colour: Some(background),
+        ..TerminalSettings::default()
+    });
+    let mut renderer = terminal::terminal_renderer::TerminalRenderer::default();
+    renderer.render_display(&terminal.get_display());
+    let cell = renderer.get_cells()[0];
+    assert_eq!(cell.character, 'a');
+    assert_eq!(cell.pen.foreground_colour, foreground);
+    assert_eq!(cell.pen.background_colour, background);
+}
+
+#[test]
+fn palette_sets_base_colours() {
+    let red = Rgb8 { r: 0xCC, g: 0x24, b: 0x1D };
+    let mut palette = [Rgb8::default(); 16];
+    palette[1] = red;
+    palette[15] = Rgb8 { r: 0xEB, g: 0xDB, b: 0xB2 };
+    let mut terminal = create_terminal(b"\x1b[31ma\x1b[38;5;1mb\x1b[38;5;196mc".to_vec(), TerminalSettings {
+        palette: Some(palette),
+        ..TerminalSettings::default()
+    });
+    let display = terminal.get_display();
+    assert_eq!(display.get_colour_table_entry(1), red);
+    // default colours come from the palette unless overridden
+    assert_eq!(display.get_default_background_colour(), palette[0]);
+    let mut renderer = terminal::terminal_renderer::TerminalRenderer::default();
+    renderer.render_display(&display);
+    let cells = renderer.get_cells();
+    assert_eq!(cells[0].pen.foreground_colour, red);
+    assert_eq!(cells[1].pen.foreground_colour, red);
+    assert_eq!(cells[2].pen.foreground_colour, Rgb8 { r: 0xFF, g: 0x00, b: 0x00 });
+}
+
+#[test]
+fn scrollback_lines_limits_history() {
+    let mut data = Vec::new();
+    for _ in 0..5000 {
+        data.extend_from_slice(b"x\r\n");
+    }
+    let mut terminal = create_terminal(data.clone(), TerminalSettings::default());
+    let default_lines = terminal.get_display().get_total_scrollback_lines();
+    let mut terminal = create_terminal(data, TerminalSettings {
+        scrollback_lines: Some(10),
+        ..TerminalSettings::default()
+    });
+    let total_lines = terminal.get_display().get_total_scrollback_lines();
+    // capacity is rounded up to the allocation granularity
+    assert!(total_lines >= 10);
+    assert!(total_lines < default_lines);
+}
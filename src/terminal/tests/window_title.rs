@@ -0,0 +1,21 @@
+mod common;
+
+use vt100::common::WindowAction;
+
+#[test]
+fn title_is_empty_by_default() {
+    let mut terminal = common::create_exited_terminal(b"hello").terminal;
+    assert_eq!(terminal.get_display().get_title(), "");
+}
+
+#[test]
+fn last_title_is_stored_in_display() {
+    let mut test = common::create_exited_terminal(b"\x1b]0;first\x07\x1b]2;vim main.rs\x07");
+    assert_eq!(test.terminal.get_display().get_title(), "vim main.rs");
+    // title is still forwarded to the window
+    let titles: Vec<WindowAction> = test.window_actions.try_iter().filter(|action| matches!(action, WindowAction::SetWindowTitle(_))).collect();
+    assert_eq!(titles, vec![
+        WindowAction::SetWindowTitle("first".to_owned()),
+        WindowAction::SetWindowTitle("vim main.rs".to_owned()),
+    ]);
+}
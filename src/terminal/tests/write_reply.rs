@@ -0,0 +1,14 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::TerminalUserEvent;
+use std::time::Duration;
+
+#[test]
+fn reply_is_written_to_process() {
+    let TestTerminal { terminal, process_writes: rx, .. } = common::create_exited_terminal(b"");
+    let user_events = terminal.get_user_event_handler();
+    user_events.send(TerminalUserEvent::WriteReply(b"\x1b[4;384;640t".to_vec())).unwrap();
+    // written as is without any encoding
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), b"\x1b[4;384;640t");
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::TestTerminal;
+use terminal::Terminal;
+use terminal::terminal_renderer::TerminalRenderer;
+use vt100::common::WindowAction;
+use cgmath::Vector2;
+
+fn get_front_buffer_rows(terminal: &Terminal) -> Vec<String> {
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&terminal.get_front_buffer());
+    let size = renderer.get_size();
+    renderer.get_cells()
+        .chunks(size.x)
+        .map(|row| row.iter().map(|cell| cell.character).collect::<String>().trim_end().to_owned())
+        .collect()
+}
+
+#[test]
+fn exit_message_is_written_after_output() {
+    let TestTerminal { mut terminal, window_actions: rx, .. } = common::create_exited_terminal_with_size(b"$ exit\r\n", Vector2::new(80,24));
+    let _ = rx.try_iter().count();
+    terminal.write_to_display(b"\r\n\x1b[7m[process exited with code 1, press any key to close]\x1b[0m");
+    let rows = get_front_buffer_rows(&terminal);
+    assert_eq!(&rows[..3], &["$ exit", "", "[process exited with code 1, press any key to close]"]);
+    // window is told to redraw the new message
+    assert!(rx.try_iter().any(|action| action == WindowAction::Refresh));
+}
+
+#[test]
+fn exit_message_uses_process_pen() {
+    use terminal::StyleFlags;
+    // process left the pen inverted before exiting
+    let mut terminal = common::create_exited_terminal_with_size(b"\x1b[7m", Vector2::new(80,24)).terminal;
+    terminal.write_to_display(b"x\x1b[0my");
+    let mut renderer = TerminalRenderer::default();
+    renderer.render_front_buffer(&terminal.get_front_buffer());
+    let cells = renderer.get_cells();
+    assert!(cells[0].pen.style_flags.contains(StyleFlags::Inverse));
+    assert!(!cells[1].pen.style_flags.contains(StyleFlags::Inverse));
+}
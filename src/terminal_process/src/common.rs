@@ -21,6 +21,14 @@ impl TerminalProcess for RawProcess {
         Ok(())
     }
 
+    fn is_alive(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(None))
+    }
+
+    fn get_exit_code(&mut self) -> Option<i32> {
+        self.process.try_wait().ok().flatten().and_then(|status| status.code())
+    }
+
     fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
         if cfg!(windows) || cfg!(unix) {
             use std::process::ChildStdin;
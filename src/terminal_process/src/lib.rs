@@ -1,5 +1,8 @@
 mod process;
 mod common;
+mod watchdog;
+mod socket;
+mod replay;
 #[cfg(windows)]
 mod win32;
 #[cfg(unix)]
@@ -7,8 +10,260 @@ mod unix;
 
 pub use process::TerminalProcess;
 pub use common::RawProcess;
+pub use watchdog::{WatchdogProcess, RestartFactory};
+pub use socket::{SocketProcess, SocketError};
+pub use replay::{ReplayProcess, ReplaySpeed};
 #[cfg(windows)]
 pub use win32::ConptyProcess;
 #[cfg(unix)]
 pub use unix::UnixPtyProcess;
 
+
+#[cfg(test)]
+mod test {
+    use crate::{TerminalProcess, WatchdogProcess};
+    use terminal::TerminalIOControl;
+    use std::io::{Read, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    struct MockProcess {
+        output: &'static [u8],
+        is_alive: Arc<AtomicBool>,
+        // None is reported like being killed by a signal
+        exit_code: Option<i32>,
+    }
+
+    impl TerminalProcess for MockProcess {
+        fn on_ioctl(&mut self, _ev: TerminalIOControl) -> anyhow::Result<()> {
+            Ok(())
+        }
+        fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
+            Ok(Box::new(std::io::sink()))
+        }
+        fn get_read_pipe(&mut self) -> anyhow::Result<Box<dyn Read + Send>> {
+            Ok(Box::new(self.output))
+        }
+        fn terminate(&mut self) -> anyhow::Result<()> {
+            self.is_alive.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+        fn is_alive(&mut self) -> bool {
+            self.is_alive.load(Ordering::SeqCst)
+        }
+        fn get_exit_code(&mut self) -> Option<i32> {
+            if self.is_alive() { None } else { self.exit_code }
+        }
+        fn is_newline_carriage_return(&self) -> bool {
+            false
+        }
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            if condition() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn watchdog_restarts_crashed_process() {
+        // initial process has already crashed
+        let process = MockProcess { output: b"first", is_alive: Arc::new(AtomicBool::new(false)), exit_code: Some(1) };
+        let total_spawned = Arc::new(AtomicUsize::new(0));
+        let factory = {
+            let total_spawned = total_spawned.clone();
+            move || -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+                total_spawned.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(MockProcess { output: b"second", is_alive: Arc::new(AtomicBool::new(true)), exit_code: None }))
+            }
+        };
+        let mut watchdog = WatchdogProcess::new(Box::new(process), Box::new(factory), Duration::ZERO);
+        let mut read_pipe = watchdog.get_read_pipe().unwrap();
+        // read pipe reconnects to restarted process after the first closes
+        let mut output = Vec::new();
+        let mut buf = [0u8; 16];
+        while output.len() < b"firstsecond".len() {
+            let total = read_pipe.read(&mut buf).unwrap();
+            assert_ne!(total, 0);
+            output.extend_from_slice(&buf[..total]);
+        }
+        assert_eq!(output.as_slice(), b"firstsecond");
+        assert!(wait_until(|| watchdog.get_total_restarts() == 1));
+        assert!(watchdog.is_alive());
+        // explicit termination does not trigger restart
+        watchdog.terminate().unwrap();
+        assert!(!watchdog.is_alive());
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(total_spawned.load(Ordering::SeqCst), 1);
+        // pipe reports end of stream once stopped
+        assert_eq!(read_pipe.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn watchdog_does_not_restart_after_clean_exit() {
+        let process = MockProcess { output: b"first", is_alive: Arc::new(AtomicBool::new(false)), exit_code: Some(0) };
+        let total_spawned = Arc::new(AtomicUsize::new(0));
+        let factory = {
+            let total_spawned = total_spawned.clone();
+            move || -> anyhow::Result<Box<dyn TerminalProcess + Send>> {
+                total_spawned.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(MockProcess { output: b"second", is_alive: Arc::new(AtomicBool::new(true)), exit_code: None }))
+            }
+        };
+        let mut watchdog = WatchdogProcess::new(Box::new(process), Box::new(factory), Duration::ZERO);
+        let mut read_pipe = watchdog.get_read_pipe().unwrap();
+        let mut output = Vec::new();
+        read_pipe.read_to_end(&mut output).unwrap();
+        assert_eq!(output.as_slice(), b"first");
+        assert!(!watchdog.is_alive());
+        assert_eq!(watchdog.get_exit_code(), Some(0));
+        assert_eq!(total_spawned.load(Ordering::SeqCst), 0);
+        assert_eq!(watchdog.get_total_restarts(), 0);
+    }
+
+    fn assert_echo_round_trip(process: &mut dyn TerminalProcess) {
+        let mut write_pipe = process.get_write_pipe().unwrap();
+        let mut read_pipe = process.get_read_pipe().unwrap();
+        let data = b"echo hello\r\n\x1b[A";
+        write_pipe.write_all(data).unwrap();
+        let mut output = vec![0u8; data.len()];
+        read_pipe.read_exact(output.as_mut_slice()).unwrap();
+        assert_eq!(output.as_slice(), data);
+    }
+
+    fn spawn_echo_server(mut stream: impl Read + Write + Send + 'static) {
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 256];
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(total) => stream.write_all(&buf[..total]).unwrap(),
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn socket_process_tcp_round_trip() {
+        use crate::SocketProcess;
+        use std::net::TcpListener;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut process = SocketProcess::connect_tcp(addr).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        spawn_echo_server(stream);
+        assert!(process.is_alive());
+        assert_echo_round_trip(&mut process);
+        process.terminate().unwrap();
+        assert!(!process.is_alive());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_process_unix_round_trip() {
+        use crate::SocketProcess;
+        use std::os::unix::net::UnixListener;
+        let path = std::env::temp_dir().join(format!("terminal_process_test_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let mut process = SocketProcess::connect_unix(&path).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        spawn_echo_server(stream);
+        assert_echo_round_trip(&mut process);
+        // pipes report end of stream once terminated
+        let mut read_pipe = process.get_read_pipe().unwrap();
+        process.terminate().unwrap();
+        assert_eq!(read_pipe.read(&mut [0u8; 16]).unwrap(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn raw_process_exit_code() {
+        use crate::RawProcess;
+        use std::process::{Command, Stdio};
+        let child = Command::new("sh")
+            .args(["-c", "exit 3"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut process = RawProcess::new(child);
+        assert!(wait_until(|| !process.is_alive()));
+        assert_eq!(process.get_exit_code(), Some(3));
+    }
+
+    #[test]
+    fn socket_process_connect_error() {
+        use crate::{SocketError, SocketProcess};
+        use std::net::TcpListener;
+        // port is released once the listener is dropped
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let result = SocketProcess::connect_tcp(addr);
+        assert!(matches!(result, Err(SocketError::ConnectTcp(error_addr, _)) if error_addr == addr));
+    }
+
+    #[test]
+    fn replay_process_plays_back_recording() {
+        use crate::{ReplayProcess, ReplaySpeed};
+        use cgmath::Vector2;
+        use terminal::recording::Recorder;
+        let path = std::env::temp_dir().join(format!("terminal_process_test_{}.rec", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut recorder = Recorder::new(Box::new(file), Vector2::new(40,10)).unwrap();
+        recorder.record_output(b"hello");
+        recorder.record_resize(Vector2::new(20,5));
+        recorder.record_output(b" world");
+        drop(recorder);
+
+        let mut process = ReplayProcess::open(&path, ReplaySpeed::Max).unwrap();
+        let mut read_pipe = process.get_read_pipe().unwrap();
+        assert!(process.get_read_pipe().is_err());
+        // input is discarded
+        process.get_write_pipe().unwrap().write_all(b"ignored").unwrap();
+        assert!(process.is_alive());
+        let resize_rx = process.get_resize_receiver().unwrap();
+        assert!(process.get_resize_receiver().is_none());
+        // terminal starts at the recorded size
+        assert_eq!(resize_rx.try_recv(), Ok(Vector2::new(40,10)));
+        let mut buffer = [0u8; 64];
+        let total = read_pipe.read(&mut buffer).unwrap();
+        assert_eq!(&buffer[..total], b"hello");
+        assert!(resize_rx.try_recv().is_err());
+        // resize is sent before the output after it is returned
+        let total = read_pipe.read(&mut buffer).unwrap();
+        assert_eq!(&buffer[..total], b" world");
+        assert_eq!(resize_rx.try_recv(), Ok(Vector2::new(20,5)));
+        assert_eq!(read_pipe.read(&mut buffer).unwrap(), 0);
+        assert!(!process.is_alive());
+        assert_eq!(process.get_exit_code(), Some(0));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn replay_process_terminate_stops_playback() {
+        use crate::{ReplayProcess, ReplaySpeed};
+        use cgmath::Vector2;
+        use terminal::recording::Recorder;
+        let path = std::env::temp_dir().join(format!("terminal_process_test_{}_terminate.rec", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut recorder = Recorder::new(Box::new(file), Vector2::new(80,24)).unwrap();
+        recorder.record_output(b"first");
+        drop(recorder);
+        // recording that isn't a file made by --record
+        assert!(ReplayProcess::new(Box::new(b"garbage".as_slice()), ReplaySpeed::Max).is_err());
+
+        let mut process = ReplayProcess::open(&path, ReplaySpeed::Realtime).unwrap();
+        let mut read_pipe = process.get_read_pipe().unwrap();
+        process.terminate().unwrap();
+        assert!(!process.is_alive());
+        assert_eq!(read_pipe.read(&mut [0u8; 64]).unwrap(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,11 +1,22 @@
 use terminal::TerminalIOControl;
+use cgmath::Vector2;
 use std::io::{Read, Write};
+use std::sync::mpsc::Receiver;
 
 pub trait TerminalProcess {
     fn on_ioctl(&mut self, ev: TerminalIOControl) -> anyhow::Result<()>;
     fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>>;
     fn get_read_pipe(&mut self) -> anyhow::Result<Box<dyn Read + Send>>;
+    // Sizes the process decides itself which the terminal applies before the output of the next read
+    fn get_resize_receiver(&mut self) -> Option<Receiver<Vector2<usize>>> {
+        None
+    }
     fn terminate(&mut self) -> anyhow::Result<()>;
+    fn is_alive(&mut self) -> bool;
+    // None while the process is running or if it has no exit code (e.g. killed by a signal)
+    fn get_exit_code(&mut self) -> Option<i32> {
+        None
+    }
     // should \n be treated as \r\n?
     fn is_newline_carriage_return(&self) -> bool;
 }
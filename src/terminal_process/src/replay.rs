@@ -0,0 +1,172 @@
+use crate::process::TerminalProcess;
+use terminal::TerminalIOControl;
+use terminal::recording::{RecordingError, RecordingEvent, RecordingReader};
+use cgmath::Vector2;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, Instant};
+
+// Longest sleep between checks for termination while waiting for the next frame
+const MAX_SLEEP: Duration = Duration::from_millis(50);
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum ReplaySpeed {
+    // frames are written with the same delays as when they were recorded
+    #[default]
+    Realtime,
+    // frames are written as fast as they are read for benchmarking
+    Max,
+}
+
+struct ReplayPipe {
+    reader: RecordingReader<Box<dyn Read + Send>>,
+    speed: ReplaySpeed,
+    start: Option<Instant>,
+    pending: Vec<u8>,
+    offset: usize,
+    resize_tx: Sender<Vector2<usize>>,
+    is_alive: Arc<AtomicBool>,
+}
+
+impl ReplayPipe {
+    // Returns false if terminated while waiting
+    fn wait_until(&self, deadline: Instant) -> bool {
+        loop {
+            if !self.is_alive.load(Ordering::SeqCst) {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            std::thread::sleep((deadline - now).min(MAX_SLEEP));
+        }
+    }
+
+    fn read_next_frame(&mut self) -> Result<bool, RecordingError> {
+        let Some(frame) = self.reader.read_frame()? else {
+            return Ok(false);
+        };
+        if let (ReplaySpeed::Realtime, Some(start)) = (self.speed, self.start) {
+            if !self.wait_until(start + frame.elapsed) {
+                return Ok(false);
+            }
+        }
+        match frame.event {
+            RecordingEvent::Output(data) => self.pending = data,
+            // sent before the read returns the output after it so the terminal applies it first
+            RecordingEvent::Resize(size) => {
+                let _ = self.resize_tx.send(size);
+                self.pending.clear();
+            },
+        }
+        self.offset = 0;
+        Ok(true)
+    }
+}
+
+impl Read for ReplayPipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // timestamps are relative to the first read from the terminal
+        self.start.get_or_insert_with(Instant::now);
+        if !self.is_alive.load(Ordering::SeqCst) {
+            return Ok(0);
+        }
+        while self.offset >= self.pending.len() {
+            match self.read_next_frame() {
+                Ok(true) => {},
+                Ok(false) => {
+                    self.is_alive.store(false, Ordering::SeqCst);
+                    return Ok(0);
+                },
+                Err(err) => {
+                    self.is_alive.store(false, Ordering::SeqCst);
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err));
+                },
+            }
+        }
+        let data = &self.pending[self.offset..];
+        let total = data.len().min(buf.len());
+        buf[..total].copy_from_slice(&data[..total]);
+        self.offset += total;
+        Ok(total)
+    }
+}
+
+// Plays back a recording made with --record instead of running a process
+// Input is discarded and resizes from the terminal are ignored since the recording decides the size
+pub struct ReplayProcess {
+    pipe: Option<ReplayPipe>,
+    resize_rx: Option<Receiver<Vector2<usize>>>,
+    is_alive: Arc<AtomicBool>,
+}
+
+impl ReplayProcess {
+    pub fn new(reader: Box<dyn Read + Send>, speed: ReplaySpeed) -> Result<Self, RecordingError> {
+        let reader = RecordingReader::new(reader)?;
+        let is_alive = Arc::new(AtomicBool::new(true));
+        let (resize_tx, resize_rx) = std::sync::mpsc::channel();
+        // terminal starts at the size it was recorded with
+        let _ = resize_tx.send(reader.get_header().grid_size);
+        let pipe = ReplayPipe {
+            reader,
+            speed,
+            start: None,
+            pending: Vec::new(),
+            offset: 0,
+            resize_tx,
+            is_alive: is_alive.clone(),
+        };
+        Ok(Self { pipe: Some(pipe), resize_rx: Some(resize_rx), is_alive })
+    }
+
+    pub fn open(path: &Path, speed: ReplaySpeed) -> Result<Self, RecordingError> {
+        let file = std::fs::File::open(path)?;
+        Self::new(Box::new(std::io::BufReader::new(file)), speed)
+    }
+}
+
+impl TerminalProcess for ReplayProcess {
+    fn terminate(&mut self) -> anyhow::Result<()> {
+        self.is_alive.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        self.is_alive.load(Ordering::SeqCst)
+    }
+
+    fn get_exit_code(&mut self) -> Option<i32> {
+        (!self.is_alive.load(Ordering::SeqCst)).then_some(0)
+    }
+
+    fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(std::io::sink()))
+    }
+
+    // Recording can only be played back once
+    fn get_read_pipe(&mut self) -> anyhow::Result<Box<dyn Read + Send>> {
+        match self.pipe.take() {
+            Some(pipe) => Ok(Box::new(pipe)),
+            None => Err(anyhow::format_err!("Read pipe of replay was already taken")),
+        }
+    }
+
+    fn get_resize_receiver(&mut self) -> Option<Receiver<Vector2<usize>>> {
+        self.resize_rx.take()
+    }
+
+    fn on_ioctl(&mut self, ev: TerminalIOControl) -> anyhow::Result<()> {
+        match ev {
+            TerminalIOControl::SetSize(size) => log::debug!("Ignoring resize of replay to {:?}", size),
+        }
+        Ok(())
+    }
+
+    fn is_newline_carriage_return(&self) -> bool {
+        false
+    }
+}
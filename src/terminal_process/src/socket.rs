@@ -0,0 +1,132 @@
+use crate::process::TerminalProcess;
+use terminal::TerminalIOControl;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug,Error)]
+pub enum SocketError {
+    #[cfg(unix)]
+    #[error("failed to connect to unix socket {0:?}: {1:?}")]
+    ConnectUnix(PathBuf, std::io::Error),
+    #[error("failed to connect to tcp socket {0}: {1:?}")]
+    ConnectTcp(SocketAddr, std::io::Error),
+}
+
+enum SocketStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+// Process running elsewhere (e.g. a remote machine) that is connected to over a socket
+pub struct SocketProcess {
+    stream: SocketStream,
+    is_shutdown: bool,
+}
+
+impl SocketProcess {
+    #[cfg(unix)]
+    pub fn connect_unix(path: &Path) -> Result<Self, SocketError> {
+        let stream = UnixStream::connect(path).map_err(|err| SocketError::ConnectUnix(path.to_owned(), err))?;
+        Ok(Self { stream: SocketStream::Unix(stream), is_shutdown: false })
+    }
+
+    pub fn connect_tcp(addr: SocketAddr) -> Result<Self, SocketError> {
+        let stream = TcpStream::connect(addr).map_err(|err| SocketError::ConnectTcp(addr, err))?;
+        // keystrokes should be sent immediately
+        let _ = stream.set_nodelay(true);
+        Ok(Self { stream: SocketStream::Tcp(stream), is_shutdown: false })
+    }
+
+    fn try_clone(&self) -> std::io::Result<SocketStream> {
+        match &self.stream {
+            SocketStream::Tcp(stream) => Ok(SocketStream::Tcp(stream.try_clone()?)),
+            #[cfg(unix)]
+            SocketStream::Unix(stream) => Ok(SocketStream::Unix(stream.try_clone()?)),
+        }
+    }
+}
+
+impl Read for SocketStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for SocketStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+impl TerminalProcess for SocketProcess {
+    fn terminate(&mut self) -> anyhow::Result<()> {
+        self.is_shutdown = true;
+        match &self.stream {
+            SocketStream::Tcp(stream) => stream.shutdown(Shutdown::Both)?,
+            #[cfg(unix)]
+            SocketStream::Unix(stream) => stream.shutdown(Shutdown::Both)?,
+        }
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        if self.is_shutdown {
+            return false;
+        }
+        match &self.stream {
+            SocketStream::Tcp(stream) => stream.peer_addr().is_ok(),
+            #[cfg(unix)]
+            SocketStream::Unix(stream) => stream.peer_addr().is_ok(),
+        }
+    }
+
+    fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn get_read_pipe(&mut self) -> anyhow::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn on_ioctl(&mut self, ev: TerminalIOControl) -> anyhow::Result<()> {
+        // a raw byte stream has no side channel to resize the remote pty
+        match ev {
+            TerminalIOControl::SetSize(size) => log::debug!("Ignoring resize of socket process to {:?}", size),
+        }
+        Ok(())
+    }
+
+    fn is_newline_carriage_return(&self) -> bool {
+        false
+    }
+}
+
+impl Drop for SocketProcess {
+    fn drop(&mut self) {
+        if !self.is_shutdown {
+            let _ = self.terminate();
+        }
+    }
+}
@@ -2,7 +2,6 @@ use crate::process::TerminalProcess;
 use terminal::TerminalIOControl;
 use std::io::{Read, Write};
 use unix_pty::process::PtyProcess as Process;
-use cgmath::Vector2;
 
 pub struct UnixPtyProcess {
     process: Process,    
@@ -22,6 +21,14 @@ impl TerminalProcess for UnixPtyProcess {
         Ok(())
     }
 
+    fn is_alive(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(None))
+    }
+
+    fn get_exit_code(&mut self) -> Option<i32> {
+        self.process.try_wait().ok().flatten().and_then(|status| status.code())
+    }
+
     fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
         let master_pty = self.process.get_master_pty().try_clone()?;
         Ok(Box::new(master_pty))
@@ -34,10 +41,7 @@ impl TerminalProcess for UnixPtyProcess {
 
     fn on_ioctl(&mut self, ev: TerminalIOControl) -> anyhow::Result<()> {
         match ev {
-            TerminalIOControl::SetSize(size) => {
-                let size = Vector2::new(size.x as u16, size.y as u16);
-                self.process.get_master_pty().set_window_size(size)?
-            },
+            TerminalIOControl::SetSize(size) => self.process.resize(size)?,
         }
         Ok(())
     }
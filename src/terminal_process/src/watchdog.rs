@@ -0,0 +1,230 @@
+use crate::process::TerminalProcess;
+use terminal::TerminalIOControl;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard, Condvar};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+pub type RestartFactory = Box<dyn Fn() -> anyhow::Result<Box<dyn TerminalProcess + Send>> + Send>;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+struct WatchdogState {
+    process: Box<dyn TerminalProcess + Send>,
+    // incremented each time the process is replaced so pipes know to reconnect
+    generation: usize,
+    is_stopped: bool,
+    last_ioctl: Option<TerminalIOControl>,
+}
+
+struct WatchdogShared {
+    state: Mutex<WatchdogState>,
+    on_change: Condvar,
+}
+
+impl WatchdogShared {
+    // blocks until the process is replaced or the watchdog is stopped
+    fn wait_for_restart(&self, generation: usize) -> Option<MutexGuard<'_, WatchdogState>> {
+        let state = self.state.lock().unwrap();
+        let state = self.on_change
+            .wait_while(state, |state| !state.is_stopped && state.generation == generation)
+            .unwrap();
+        if state.is_stopped {
+            return None;
+        }
+        Some(state)
+    }
+}
+
+// Restarts the wrapped process if it fails or is killed without terminate() being called
+// A process that exits with 0 is left stopped so the terminal sees it exit
+pub struct WatchdogProcess {
+    shared: Arc<WatchdogShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchdogProcess {
+    pub fn new(process: Box<dyn TerminalProcess + Send>, factory: RestartFactory, restart_delay: Duration) -> Self {
+        let shared = Arc::new(WatchdogShared {
+            state: Mutex::new(WatchdogState {
+                process,
+                generation: 0,
+                is_stopped: false,
+                last_ioctl: None,
+            }),
+            on_change: Condvar::new(),
+        });
+        let thread = std::thread::spawn({
+            let shared = shared.clone();
+            move || watchdog_thread(shared, factory, restart_delay)
+        });
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn get_total_restarts(&self) -> usize {
+        self.shared.state.lock().unwrap().generation
+    }
+}
+
+fn watchdog_thread(shared: Arc<WatchdogShared>, factory: RestartFactory, restart_delay: Duration) {
+    loop {
+        {
+            let state = shared.state.lock().unwrap();
+            let (mut state, _) = shared.on_change.wait_timeout(state, POLL_INTERVAL).unwrap();
+            if state.is_stopped {
+                return;
+            }
+            if state.process.is_alive() {
+                continue;
+            }
+            // no exit code means it was killed by a signal
+            let exit_code = state.process.get_exit_code();
+            if exit_code == Some(0) {
+                log::info!("Watchdog process exited successfully, not restarting");
+                state.is_stopped = true;
+                shared.on_change.notify_all();
+                return;
+            }
+            log::info!("Watchdog process exited with code {:?}, restarting in {:?}", exit_code, restart_delay);
+        }
+        std::thread::sleep(restart_delay);
+        let mut process = match factory() {
+            Ok(process) => process,
+            Err(err) => {
+                log::error!("Watchdog failed to restart process: {:?}", err);
+                continue;
+            },
+        };
+        let mut state = shared.state.lock().unwrap();
+        if state.is_stopped {
+            let _ = process.terminate();
+            return;
+        }
+        // restore size of previous process
+        if let Some(ev) = state.last_ioctl {
+            if let Err(err) = process.on_ioctl(ev) {
+                log::error!("Watchdog failed to apply ioctl to restarted process: {:?}", err);
+            }
+        }
+        state.process = process;
+        state.generation += 1;
+        shared.on_change.notify_all();
+    }
+}
+
+impl TerminalProcess for WatchdogProcess {
+    fn terminate(&mut self) -> anyhow::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.is_stopped {
+            return Ok(());
+        }
+        state.is_stopped = true;
+        self.shared.on_change.notify_all();
+        state.process.terminate()
+    }
+
+    fn is_alive(&mut self) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        !state.is_stopped && state.process.is_alive()
+    }
+
+    // Exit code of the current process which may be about to be restarted
+    fn get_exit_code(&mut self) -> Option<i32> {
+        self.shared.state.lock().unwrap().process.get_exit_code()
+    }
+
+    fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
+        let mut state = self.shared.state.lock().unwrap();
+        let pipe = state.process.get_write_pipe()?;
+        Ok(Box::new(WatchdogWritePipe {
+            shared: self.shared.clone(),
+            generation: state.generation,
+            pipe,
+        }))
+    }
+
+    fn get_read_pipe(&mut self) -> anyhow::Result<Box<dyn Read + Send>> {
+        let mut state = self.shared.state.lock().unwrap();
+        let pipe = state.process.get_read_pipe()?;
+        Ok(Box::new(WatchdogReadPipe {
+            shared: self.shared.clone(),
+            generation: state.generation,
+            pipe,
+        }))
+    }
+
+    fn on_ioctl(&mut self, ev: TerminalIOControl) -> anyhow::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.last_ioctl = Some(ev);
+        state.process.on_ioctl(ev)
+    }
+
+    fn is_newline_carriage_return(&self) -> bool {
+        self.shared.state.lock().unwrap().process.is_newline_carriage_return()
+    }
+}
+
+impl Drop for WatchdogProcess {
+    fn drop(&mut self) {
+        let _ = self.terminate();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Reconnects to the restarted process when the current pipe closes
+struct WatchdogReadPipe {
+    shared: Arc<WatchdogShared>,
+    generation: usize,
+    pipe: Box<dyn Read + Send>,
+}
+
+impl Read for WatchdogReadPipe {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.pipe.read(buf) {
+                Ok(0) => {},
+                Ok(total) => return Ok(total),
+                Err(err) => log::info!("Watchdog read pipe closed: {:?}", err),
+            }
+            let Some(mut state) = self.shared.wait_for_restart(self.generation) else {
+                return Ok(0);
+            };
+            self.pipe = state.process.get_read_pipe().map_err(std::io::Error::other)?;
+            self.generation = state.generation;
+        }
+    }
+}
+
+struct WatchdogWritePipe {
+    shared: Arc<WatchdogShared>,
+    generation: usize,
+    pipe: Box<dyn Write + Send>,
+}
+
+impl WatchdogWritePipe {
+    fn reconnect(&mut self) -> std::io::Result<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.generation != self.generation {
+            self.pipe = state.process.get_write_pipe().map_err(std::io::Error::other)?;
+            self.generation = state.generation;
+        }
+        Ok(())
+    }
+}
+
+impl Write for WatchdogWritePipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.reconnect()?;
+        self.pipe.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.reconnect()?;
+        self.pipe.flush()
+    }
+}
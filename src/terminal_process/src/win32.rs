@@ -1,6 +1,7 @@
 use crate::process::TerminalProcess;
 use terminal::TerminalIOControl;
 use std::io::{Read, Write};
+use std::time::Duration;
 use conpty::process::{ConptyProcess as Process, Size};
 
 pub struct ConptyProcess {
@@ -21,6 +22,15 @@ impl TerminalProcess for ConptyProcess {
         Ok(())
     }
 
+    fn is_alive(&mut self) -> bool {
+        self.process.is_alive()
+    }
+
+    fn get_exit_code(&mut self) -> Option<i32> {
+        // windows exit codes are unsigned but are usually shown as signed (e.g. -1073741510)
+        self.process.wait(Some(Duration::ZERO)).ok().map(|code| code as i32)
+    }
+
     fn get_write_pipe(&mut self) -> anyhow::Result<Box<dyn Write + Send>> {
         let write_pipe = self.process.get_write_pipe().try_clone()?;
         Ok(Box::new(write_pipe))
@@ -33,7 +43,11 @@ impl TerminalProcess for ConptyProcess {
 
     fn on_ioctl(&mut self, ev: TerminalIOControl) -> anyhow::Result<()> {
         match ev {
-            TerminalIOControl::SetSize(size) => self.process.set_size(Size::new(size.x as i16, size.y as i16))?,
+            TerminalIOControl::SetSize(size) => {
+                // conpty sizes are signed 16 bit
+                let to_i16 = |v: usize| i16::try_from(v).unwrap_or(i16::MAX);
+                self.process.set_size(Size::new(to_i16(size.x), to_i16(size.y)))?
+            },
         }
         Ok(())
     }
@@ -110,4 +110,21 @@ mod test {
         log::info!("process read buffer: {:?}", std::str::from_utf8(read_data.as_slice()));
         assert!(!read_data.is_empty());
     }
+
+    #[test]
+    fn simple_resize() {
+        let command = Command::new("/usr/bin/sh");
+        let mut process = PtyProcess::spawn(command, None).unwrap();
+        let mut master_pty = process.get_master_pty().try_clone().unwrap();
+        // resize within bounds
+        process.resize(Vector2::new(80usize, 24usize)).unwrap();
+        assert_value(master_pty.get_window_size(), Ok(Vector2::new(80u16, 24u16)));
+        // resize out of bounds is rejected and leaves size untouched
+        assert!(process.resize(Vector2::new(u16::MAX as usize + 1, 24usize)).is_err());
+        assert_value(master_pty.get_window_size(), Ok(Vector2::new(80u16, 24u16)));
+        // close shell
+        master_pty.write_all(b"exit\x0d").unwrap();
+        let status = process.wait().unwrap();
+        assert!(status.success());
+    }
 }
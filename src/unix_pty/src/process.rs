@@ -50,6 +50,14 @@ pub enum SpawnError {
     SpawnProcess(std::io::Error),
 }
 
+#[derive(Debug,Error)]
+pub enum ResizeError {
+    #[error("grid size {0:?} exceeds maximum pty window size")]
+    OutOfBounds(Vector2<usize>),
+    #[error("failed to set window size of pty: {0:?}")]
+    SetWindowSize(Errno),
+}
+
 #[derive(Debug,Error)]
 enum ExecError {
     #[error("failed to create new session and set process group id: {0:?}")]
@@ -123,6 +131,15 @@ impl PtyProcess {
         &self.master_pty
     }
 
+    pub fn resize(&mut self, grid: Vector2<usize>) -> Result<(), ResizeError> {
+        let max_size = u16::MAX as usize;
+        if grid.x > max_size || grid.y > max_size {
+            return Err(ResizeError::OutOfBounds(grid));
+        }
+        let size = Vector2::new(grid.x as u16, grid.y as u16);
+        self.master_pty.set_window_size(size).map_err(ResizeError::SetWindowSize)
+    }
+
     pub fn get_pid(&self) -> Pid {
         Pid::from_raw(self.child.id() as i32)
     }
@@ -5,9 +5,12 @@ use crate::common::{
     CursorStyle,
     EraseMode,
     GraphicStyle,
+    InlineImage,
+    PromptMark,
     Rgb8,
     ScreenMode,
     ScrollRegion,
+    UnderlineStyle,
     WindowAction,
 };
 use crate::encoder::{
@@ -51,6 +54,12 @@ pub enum Command {
     SetBackgroundColourTable(u8),
     SetForegroundColourRgb(Rgb8),
     SetBackgroundColourRgb(Rgb8),
+    SetPaletteColour(u8, Rgb8),
+    SetCursorColour(Rgb8),
+    ResetCursorColour,
+    SetUnderlineStyle(UnderlineStyle),
+    SetUnderlineColourTable(u8),
+    SetUnderlineColourRgb(Rgb8),
     // query state
     QueryCursorPosition,
     QueryTerminalIdentity,
@@ -67,6 +76,8 @@ pub enum Command {
     SetScrollRegion(Option<ScrollRegion>),
     // operating system command 
     SetHyperlink(String),
+    SetPromptMark(PromptMark),
+    InlineImage(InlineImage),
     // common private modes
     SetCursorKeyInputMode(InputMode),
     SetConsoleWidth(u16),
@@ -46,6 +46,8 @@ pub enum WindowAction {
     SendToFront,
     SendToBack,
     Refresh,
+    // Process wrote BEL (0x07) outside of a sequence
+    Bell,
     ResizeTextArea(Vector2<u16>),
     RestoreMaximised,
     Maximise(Vector2<bool>),
@@ -79,6 +81,125 @@ pub enum CursorStyle {
     Bar,
 }
 
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum UnderlineStyle {
+    #[default]
+    None,
+    Straight,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    pub(crate) fn try_from_u16(v: u16) -> Option<Self> {
+        match v {
+            0 => Some(Self::None),
+            1 => Some(Self::Straight),
+            2 => Some(Self::Double),
+            3 => Some(Self::Curly),
+            4 => Some(Self::Dotted),
+            5 => Some(Self::Dashed),
+            _ => None,
+        }
+    }
+}
+
+// Shell integration marks written around each prompt and command
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PromptMark {
+    PromptStart,                    // A
+    CommandStart,                   // B
+    OutputStart,                    // C
+    CommandFinished(Option<i32>),   // D ; <exit code>
+}
+
+impl PromptMark {
+    // Parameters after the mark type besides the exit code are ignored
+    pub(crate) fn try_from_bytes(data: &[u8]) -> Option<Self> {
+        let mut params = data.split(|&b| b == b';');
+        let kind = params.next()?;
+        match kind {
+            b"A" => Some(Self::PromptStart),
+            b"B" => Some(Self::CommandStart),
+            b"C" => Some(Self::OutputStart),
+            b"D" => {
+                let exit_code = params.next()
+                    .and_then(|v| std::str::from_utf8(v).ok())
+                    .and_then(|v| v.parse::<i32>().ok());
+                Some(Self::CommandFinished(exit_code))
+            },
+            _ => None,
+        }
+    }
+}
+
+// Width or height argument of an inline image
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum ImageDimension {
+    #[default]
+    Auto,               // auto
+    Cells(u32),         // <n>
+    Pixels(u32),        // <n>px
+    Percent(u32),       // <n>% of the terminal width or height
+}
+
+impl ImageDimension {
+    pub(crate) fn try_from_bytes(data: &[u8]) -> Option<Self> {
+        let parse = |v: &[u8]| std::str::from_utf8(v).ok()?.parse::<u32>().ok();
+        if data == b"auto" {
+            Some(Self::Auto)
+        } else if let Some(v) = data.strip_suffix(b"px") {
+            parse(v).map(Self::Pixels)
+        } else if let Some(v) = data.strip_suffix(b"%") {
+            parse(v).map(Self::Percent)
+        } else {
+            parse(data).map(Self::Cells)
+        }
+    }
+}
+
+// File transferred by the iTerm2 image protocol
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct InlineImage {
+    pub width: ImageDimension,
+    pub height: ImageDimension,
+    pub preserve_aspect_ratio: bool,
+    // files that aren't inline are downloads which aren't supported
+    pub is_inline: bool,
+    // base64 encoded contents of the file
+    pub data: Vec<u8>,
+}
+
+impl InlineImage {
+    // File = [<key>=<value> [; <key>=<value>]*] : <base64 data>
+    // Unknown keys such as the name and size of the file are ignored
+    pub(crate) fn try_from_bytes(data: &[u8]) -> Option<Self> {
+        let data = data.strip_prefix(b"File=")?;
+        let separator = data.iter().position(|&b| b == b':')?;
+        let mut image = Self {
+            width: ImageDimension::Auto,
+            height: ImageDimension::Auto,
+            preserve_aspect_ratio: true,
+            is_inline: false,
+            data: data[separator+1..].to_vec(),
+        };
+        for arg in data[..separator].split(|&b| b == b';').filter(|arg| !arg.is_empty()) {
+            let equals = arg.iter().position(|&b| b == b'=')?;
+            let (key, value) = (&arg[..equals], &arg[equals+1..]);
+            match key {
+                b"width" => image.width = ImageDimension::try_from_bytes(value)?,
+                b"height" => image.height = ImageDimension::try_from_bytes(value)?,
+                b"preserveAspectRatio" => image.preserve_aspect_ratio = value != b"0",
+                b"inline" => image.is_inline = value == b"1",
+                _ => {},
+            }
+        }
+        Some(image)
+    }
+}
+
 #[derive(Clone,Copy,Debug,PartialEq,Eq)]
 pub enum BellVolume {
     Off,
@@ -232,6 +353,7 @@ pub enum GraphicStyle {
     BackgroundWhite,
     BackgroundExtended,
     BackgroundDefault,
+    UnderlineColourDefault,
     BrightForegroundBlack,
     BrightForegroundRed,
     BrightForegroundGreen,
@@ -289,6 +411,7 @@ impl GraphicStyle {
              47 => Some(Self::BackgroundWhite),
              48 => Some(Self::BackgroundExtended),
              49 => Some(Self::BackgroundDefault),
+             59 => Some(Self::UnderlineColourDefault),
              90 => Some(Self::BrightForegroundBlack),
              91 => Some(Self::BrightForegroundRed),
              92 => Some(Self::BrightForegroundGreen),
@@ -310,3 +433,78 @@ impl GraphicStyle {
     }
 }
 
+
+// Names of DEC private modes set by ESC [ ? <n> h/l used to log modes that aren't supported
+// https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h4-Functions-using-CSI-_-ordered-by-the-final-character-lparen-s-rparen:CSI-?-Pm-h
+pub fn describe_private_mode(code: u16) -> Option<&'static str> {
+    let description = match code {
+           1 => "application cursor keys",
+           2 => "ansi/vt52 mode",
+           3 => "132 column mode",
+           4 => "smooth scroll",
+           5 => "reverse video",
+           6 => "origin mode",
+           7 => "auto wrap",
+           8 => "auto repeat keys",
+           9 => "x10 mouse reporting",
+          10 => "show toolbar",
+          12 => "blinking cursor",
+          13 => "blinking cursor",
+          14 => "xor blinking cursor",
+          18 => "print form feed",
+          19 => "print extent full screen",
+          25 => "show cursor",
+          30 => "show scrollbar",
+          35 => "font shifting functions",
+          38 => "tektronix mode",
+          40 => "allow 80 to 132 column mode",
+          41 => "more fix",
+          42 => "national replacement character sets",
+          44 => "margin bell",
+          45 => "reverse wraparound",
+          46 => "start logging",
+          47 => "alternate screen",
+          66 => "application keypad",
+          67 => "backarrow key sends backspace",
+          69 => "left and right margins",
+          80 => "sixel display mode",
+          95 => "no clear on column change",
+        1000 => "normal mouse tracking",
+        1001 => "highlight mouse tracking",
+        1002 => "button event mouse tracking",
+        1003 => "any event mouse tracking",
+        1004 => "focus events",
+        1005 => "utf8 mouse mode",
+        1006 => "sgr mouse mode",
+        1007 => "alternate scroll",
+        1010 => "scroll to bottom on output",
+        1011 => "scroll to bottom on key press",
+        1015 => "urxvt mouse mode",
+        1016 => "sgr pixel mouse mode",
+        1034 => "interpret meta key",
+        1035 => "alt and num lock modifiers",
+        1036 => "meta sends escape",
+        1037 => "delete sends del",
+        1039 => "alt sends escape",
+        1040 => "keep selection when not highlighted",
+        1041 => "use clipboard selection",
+        1042 => "urgency hint on bell",
+        1043 => "raise window on bell",
+        1044 => "reuse clipboard data",
+        1046 => "alternate screen switching",
+        1047 => "alternate screen",
+        1048 => "save cursor",
+        1049 => "alternate screen with cursor save",
+        1050 => "terminfo function key mode",
+        1051 => "sun function key mode",
+        1052 => "hp function key mode",
+        1053 => "sco function key mode",
+        1060 => "legacy keyboard emulation",
+        1061 => "vt220 keyboard emulation",
+        2004 => "bracketed paste",
+        2026 => "synchronized output",
+        2027 => "grapheme clustering",
+        _ => return None,
+    };
+    Some(description)
+}
@@ -62,6 +62,8 @@ pub enum FunctionKey {
     Delete,
     Home,
     End,
+    PageUp,
+    PageDown,
 }
 
 #[derive(Clone,Copy,Debug,PartialEq,Eq)]
@@ -202,7 +204,30 @@ impl Encoder {
         output(data);
     }
 
+    // xterm adds modifiers as a parameter of 1 + shift + 2*alt + 4*ctrl
+    fn get_modifier_parameter(&self) -> Option<u8> {
+        let mut parameter = 1;
+        if self.modifier_key.contains(ModifierKey::Shift) { parameter += 1; }
+        if self.modifier_key.contains(ModifierKey::Alt) { parameter += 2; }
+        if self.modifier_key.contains(ModifierKey::Ctrl) { parameter += 4; }
+        (parameter > 1).then_some(parameter)
+    }
+
     fn on_function_key(&mut self, key: FunctionKey, output: &mut impl FnMut(&[u8])) {
+        // https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-PC-Style-Function-Keys
+        if let Some(modifier) = self.get_modifier_parameter() {
+            let data = match key {
+                FunctionKey::Home     => Some(format!("\x1b[1;{}H", modifier)),
+                FunctionKey::End      => Some(format!("\x1b[1;{}F", modifier)),
+                FunctionKey::PageUp   => Some(format!("\x1b[5;{}~", modifier)),
+                FunctionKey::PageDown => Some(format!("\x1b[6;{}~", modifier)),
+                _ => None,
+            };
+            if let Some(data) = data {
+                output(data.as_bytes());
+                return;
+            }
+        }
         // Figure C-2: Function key control codes
         let data: &'static [u8] = match key {
             FunctionKey::Escape    => b"\x1b",
@@ -213,6 +238,8 @@ impl Encoder {
             FunctionKey::Delete    => b"\x7f",
             FunctionKey::Home      => b"\x1b[H",
             FunctionKey::End       => b"\x1b[F",
+            FunctionKey::PageUp    => b"\x1b[5~",
+            FunctionKey::PageDown  => b"\x1b[6~",
         };
         output(data);
     }
@@ -450,7 +477,16 @@ impl Encoder {
                 self.encode_buffer.push((grid_pos.y+32).min(255) as u8);
             },
             MouseCoordinateFormat::Utf8 => {
-                // @TODO: figure out how utf8 encoding works for coordinate system
+                // like x10 but values above 95 are encoded as a utf8 character
+                // xterm limits coordinates to 2015 so that it fits into 2 bytes
+                const MAX_CODEPOINT: usize = 0x7FF;
+                for v in [grid_pos.x, grid_pos.y] {
+                    let codepoint = (v+32).min(MAX_CODEPOINT) as u32;
+                    if let Some(c) = char::from_u32(codepoint) {
+                        let data = c.encode_utf8(&mut self.utf8_encode_buffer);
+                        self.encode_buffer.extend_from_slice(data.as_bytes());
+                    }
+                }
             },
             MouseCoordinateFormat::Sgr | MouseCoordinateFormat::Urxvt => {
                 let _ = write!(&mut self.encode_buffer, "{};{}", grid_pos.x, grid_pos.y);
@@ -461,15 +497,27 @@ impl Encoder {
         }
     }
 
-    pub fn on_window_focus(&self, is_focus: bool, output: &mut impl FnMut(&[u8])) {
+    pub fn on_window_focus(&mut self, is_focus: bool, output: &mut impl FnMut(&[u8])) {
         if !self.is_report_focus {
             return;
         }
-        // https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-FocusIn_FocusOut
+        output(Self::encode_focus_event(is_focus));
+    }
+
+    // https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-FocusIn_FocusOut
+    pub fn encode_focus_in() -> &'static [u8] {
+        b"\x1b[I"
+    }
+
+    pub fn encode_focus_out() -> &'static [u8] {
+        b"\x1b[O"
+    }
+
+    pub fn encode_focus_event(is_focus: bool) -> &'static [u8] {
         if is_focus {
-            output(b"\x1b[I");
+            Self::encode_focus_in()
         } else {
-            output(b"\x1b[O");
+            Self::encode_focus_out()
         }
     }
 
@@ -481,12 +529,44 @@ impl Encoder {
         }
     }
 
+    // OSC 0 which also sets the icon title
+    pub fn set_window_title(&mut self, title: &str, output: &mut impl FnMut(&[u8])) {
+        self.encode_title(0, title, output);
+    }
+
+    pub fn set_icon_title(&mut self, title: &str, output: &mut impl FnMut(&[u8])) {
+        self.encode_title(1, title, output);
+    }
+
+    fn encode_title(&mut self, command: u8, title: &str, output: &mut impl FnMut(&[u8])) {
+        // https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-Operating-System-Commands
+        self.encode_buffer.clear();
+        if write!(&mut self.encode_buffer, "\x1b]{};", command).is_err() {
+            return;
+        }
+        // control characters would terminate the sequence early
+        for c in title.chars().filter(|c| !c.is_control()) {
+            let data = c.encode_utf8(&mut self.utf8_encode_buffer);
+            self.encode_buffer.extend_from_slice(data.as_bytes());
+        }
+        self.encode_buffer.push(b'\x07');
+        output(self.encode_buffer.as_slice());
+    }
+
+    // https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-Bracketed-Paste-Mode
+    pub fn encode_paste_start(&self) -> &'static [u8] {
+        if self.is_bracketed_paste_mode { b"\x1b[200~" } else { b"" }
+    }
+
+    pub fn encode_paste_end(&self) -> &'static [u8] {
+        if self.is_bracketed_paste_mode { b"\x1b[201~" } else { b"" }
+    }
+
     pub fn paste_text(&mut self, buf: &[u8], output: &mut impl FnMut(&[u8])) {
-        // https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h2-Bracketed-Paste-Mode
         if self.is_bracketed_paste_mode {
-            output(b"\x1b[200~"); 
+            output(self.encode_paste_start());
             output(buf);
-            output(b"\x1b[201~"); 
+            output(self.encode_paste_end());
         } else {
             output(buf);
         }
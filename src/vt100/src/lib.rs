@@ -8,7 +8,7 @@ mod tests {
     use cgmath::Vector2;
     use crate::{
         command::Command,
-        parser::{Parser,ParserHandler,ParserError},
+        parser::{Parser,ParserHandler,ParserError,ParserState,ParserContext},
         common::*,
         encoder::*,
     };
@@ -227,6 +227,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn private_mode_descriptions() {
+        // every supported mode can be named
+        for (mode, _) in get_private_mode_nonstandard_commands().keys() {
+            assert!(describe_private_mode(*mode).is_some(), "{}", mode);
+        }
+        assert_eq!(describe_private_mode(1049), Some("alternate screen with cursor save"));
+        assert_eq!(describe_private_mode(2026), Some("synchronized output"));
+        assert_eq!(describe_private_mode(9999), None);
+    }
+
     #[test]
     fn valid_multiple_private_modes_nonstandard_enable() {
         let codes = get_private_mode_nonstandard_commands();
@@ -283,7 +294,9 @@ mod tests {
         let window_titles = ["", "hello world", "ĐđĒēĔĕĖėĘęĚěĜĝĞğ"];
         for window_title in &window_titles {
             let command = Command::WindowAction(WindowAction::SetWindowTitle(window_title.to_string()));
-            test_valid_sequence(format!("]0;{}\x07", window_title).as_bytes(), &[command.clone()]);
+            let icon_command = Command::WindowAction(WindowAction::SetIconTitle(window_title.to_string()));
+            test_valid_sequence(format!("]0;{}\x07", window_title).as_bytes(), &[icon_command.clone(), command.clone()]);
+            test_valid_sequence(format!("]1;{}\x07", window_title).as_bytes(), &[icon_command.clone()]);
             test_valid_sequence(format!("]2;{}\x07", window_title).as_bytes(), &[command.clone()]);
         }
     }
@@ -297,6 +310,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn valid_set_underline_style() {
+        let styles = [
+            (0, UnderlineStyle::None),
+            (1, UnderlineStyle::Straight),
+            (2, UnderlineStyle::Double),
+            (3, UnderlineStyle::Curly),
+            (4, UnderlineStyle::Dotted),
+            (5, UnderlineStyle::Dashed),
+        ];
+        for (id, style) in styles {
+            test_valid_sequence(format!("[4:{}m", id).as_bytes(), &[Command::SetUnderlineStyle(style)]);
+        }
+        // semicolon separates independent styles
+        test_valid_sequence(b"[4;3m", &[
+            Command::SetGraphicStyle(GraphicStyle::EnableUnderline),
+            Command::SetGraphicStyle(GraphicStyle::EnableItalic),
+        ]);
+        test_invalid_sequence(b"[4:6m", None, &[ParserError::InvalidUnderlineStyle(6)]);
+    }
+
+    #[test]
+    fn valid_set_underline_colour() {
+        let colour = Rgb8 { r: 10, g: 20, b: 255 };
+        test_valid_sequence(b"[58;2;10;20;300m", &[Command::SetUnderlineColourRgb(colour)]);
+        test_valid_sequence(b"[58:2::10:20:255m", &[Command::SetUnderlineColourRgb(colour)]);
+        test_valid_sequence(b"[58;5;196m", &[Command::SetUnderlineColourTable(196)]);
+        test_valid_sequence(b"[59m", &[Command::SetGraphicStyle(GraphicStyle::UnderlineColourDefault)]);
+    }
+
     #[test]
     fn valid_set_colour_rgb() {
         for r in 0..=260 {
@@ -497,6 +540,73 @@ mod tests {
         test_valid_sequence(b"]8;;\x07", &[Command::SetHyperlink("".to_string())]);
     }
 
+    #[test]
+    fn valid_set_palette_colour() {
+        let blue = Rgb8 { r: 0, g: 0, b: 255 };
+        test_valid_sequence(b"]4;1;rgb:00/00/ff\x07", &[Command::SetPaletteColour(1, blue)]);
+        test_valid_sequence(b"]4;1;rgb:0/0/f\x07", &[Command::SetPaletteColour(1, blue)]);
+        test_valid_sequence(b"]4;1;rgb:0000/0000/ffff\x1b\\", &[Command::SetPaletteColour(1, blue)]);
+        test_valid_sequence(b"]4;255;#0000ff\x07", &[Command::SetPaletteColour(255, blue)]);
+        // queries are passed through
+        test_valid_sequence(b"]4;1;?\x07", &[Command::UnhandledOperatingSystemCommand(4, b"?".to_vec())]);
+    }
+
+    #[test]
+    fn valid_set_cursor_colour() {
+        let red = Rgb8 { r: 255, g: 0, b: 0 };
+        test_valid_sequence(b"]12;#ff0000\x07", &[Command::SetCursorColour(red)]);
+        test_valid_sequence(b"]12;rgb:ff/00/00\x1b\\", &[Command::SetCursorColour(red)]);
+        test_valid_sequence(b"]112\x07", &[Command::ResetCursorColour]);
+        test_valid_sequence(b"]112\x1b\\", &[Command::ResetCursorColour]);
+        // queries are passed through
+        test_valid_sequence(b"]12;?\x07", &[Command::UnhandledOperatingSystemCommand(12, b"?".to_vec())]);
+    }
+
+    #[test]
+    fn valid_set_prompt_mark() {
+        test_valid_sequence(b"]133;A\x07", &[Command::SetPromptMark(PromptMark::PromptStart)]);
+        test_valid_sequence(b"]133;B\x1b\\", &[Command::SetPromptMark(PromptMark::CommandStart)]);
+        test_valid_sequence(b"]133;C\x07", &[Command::SetPromptMark(PromptMark::OutputStart)]);
+        test_valid_sequence(b"]133;D\x07", &[Command::SetPromptMark(PromptMark::CommandFinished(None))]);
+        test_valid_sequence(b"]133;D;127\x07", &[Command::SetPromptMark(PromptMark::CommandFinished(Some(127)))]);
+        // extra parameters are ignored
+        test_valid_sequence(b"]133;A;cl=m;aid=42\x07", &[Command::SetPromptMark(PromptMark::PromptStart)]);
+        test_valid_sequence(b"]133;Z\x07", &[Command::UnhandledOperatingSystemCommand(133, b"Z".to_vec())]);
+    }
+
+    #[test]
+    fn valid_inline_image() {
+        test_valid_sequence(b"]1337;File=inline=1:aGk=\x07", &[Command::InlineImage(InlineImage {
+            width: ImageDimension::Auto,
+            height: ImageDimension::Auto,
+            preserve_aspect_ratio: true,
+            is_inline: true,
+            data: b"aGk=".to_vec(),
+        })]);
+        test_valid_sequence(
+            b"]1337;File=name=YS5wbmc=;size=2;width=10;height=50%;preserveAspectRatio=0:aGk=\x1b\\",
+            &[Command::InlineImage(InlineImage {
+                width: ImageDimension::Cells(10),
+                height: ImageDimension::Percent(50),
+                preserve_aspect_ratio: false,
+                is_inline: false,
+                data: b"aGk=".to_vec(),
+            })],
+        );
+        test_valid_sequence(b"]1337;File=width=32px;height=auto:\x07", &[Command::InlineImage(InlineImage {
+            width: ImageDimension::Pixels(32),
+            height: ImageDimension::Auto,
+            preserve_aspect_ratio: true,
+            is_inline: false,
+            data: Vec::new(),
+        })]);
+        // other iTerm2 commands and invalid arguments
+        test_valid_sequence(b"]1337;SetMark\x07", &[Command::UnhandledOperatingSystemCommand(1337, b"SetMark".to_vec())]);
+        test_valid_sequence(b"]1337;File=width=wide:aGk=\x07", &[
+            Command::UnhandledOperatingSystemCommand(1337, b"File=width=wide:aGk=".to_vec()),
+        ]);
+    }
+
     #[test]
     fn valid_test_window_action() {
         test_valid_sequence(b"[1t", &[Command::WindowAction(WindowAction::SetMinimised(false))]);
@@ -623,4 +733,169 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn remaining_buffer_across_reads() {
+        let mut parser = Parser::default();
+        let mut handler = Handler::default();
+        assert!(parser.remaining_buffer().is_empty());
+        // sequence split across multiple reads
+        for &b in b"[12;" {
+            parser.feed_byte(b, &mut handler);
+        }
+        assert_eq!(parser.remaining_buffer(), b"[12;");
+        for &b in b"3" {
+            parser.feed_byte(b, &mut handler);
+        }
+        assert_eq!(parser.remaining_buffer(), b"[12;3");
+        parser.feed_byte(b'H', &mut handler);
+        assert!(parser.is_terminated());
+        assert!(parser.remaining_buffer().is_empty());
+        assert_eq!(handler.commands, vec![Command::MoveCursorPositionViewport(Vector2::new(3,12))]);
+        parser.reset();
+        assert!(parser.remaining_buffer().is_empty());
+    }
+
+    #[test]
+    fn debug_state_mid_sequence() {
+        let mut parser = Parser::default();
+        let mut handler = Handler::default();
+        for &b in b"[1;2" {
+            parser.feed_byte(b, &mut handler);
+        }
+        assert!(!parser.is_terminated());
+        assert!(handler.commands.is_empty());
+        assert!(handler.errors.is_empty());
+        let state = parser.debug_state();
+        assert_eq!(state.state, ParserState::Numbers);
+        assert_eq!(state.context, ParserContext::ControlSequenceIntroducerNumbers);
+        assert_eq!(state.buffer_hex, "5b 31 3b 32");
+        assert_eq!(state.numbers, vec![1]);
+        assert_eq!(state.bytes_consumed, 4);
+        assert_eq!(
+            format!("{}", state),
+            "state: Numbers\n\
+             context: ControlSequenceIntroducerNumbers\n\
+             buffer: [5b 31 3b 32]\n\
+             numbers: [1]\n\
+             bytes_consumed: 4",
+        );
+    }
+
+    #[test]
+    fn mouse_position_utf8_encoding() {
+        let mut encoder = Encoder {
+            mouse_tracking_mode: MouseTrackingMode::X10,
+            mouse_coordinate_format: MouseCoordinateFormat::Utf8,
+            window_size: Vector2::new(4096,4096),
+            grid_size: Vector2::new(4096,4096),
+            ..Encoder::default()
+        };
+        let mut encode = |x: usize, y: usize| -> Vec<u8> {
+            let mut output = Vec::new();
+            // (0,0) is encoded as grid position (1,1)
+            let event = MouseEvent::ButtonPress(MouseButton::LeftClick, Vector2::new(x-1,y-1));
+            encoder.on_mouse_event(event, &mut |data| output.extend_from_slice(data));
+            output
+        };
+        // single byte up to 95
+        assert_eq!(encode(1,1), b"\x1b[M\x00\x21\x21");
+        assert_eq!(encode(63,95), b"\x1b[M\x00\x5f\x7f");
+        // two bytes from 96 onwards
+        assert_eq!(encode(96,1), b"\x1b[M\x00\xc2\x80\x21");
+        assert_eq!(encode(255,256), b"\x1b[M\x00\xc4\x9f\xc4\xa0");
+        assert_eq!(encode(2015,1), b"\x1b[M\x00\xdf\xbf\x21");
+        // clamped to largest two byte codepoint
+        assert_eq!(encode(2048,4000), b"\x1b[M\x00\xdf\xbf\xdf\xbf");
+    }
+
+    #[test]
+    fn navigation_key_encoding() {
+        let mut encoder = Encoder::default();
+        let mut encode = |key: FunctionKey, modifiers: &[ModifierKey]| -> Vec<u8> {
+            let mut output = Vec::new();
+            for modifier in modifiers {
+                encoder.on_key_press(KeyCode::ModifierKey(*modifier), &mut |_| {});
+            }
+            encoder.on_key_press(KeyCode::FunctionKey(key), &mut |data| output.extend_from_slice(data));
+            for modifier in modifiers {
+                encoder.on_key_release(KeyCode::ModifierKey(*modifier), &mut |_| {});
+            }
+            output
+        };
+        assert_eq!(encode(FunctionKey::PageUp, &[]), b"\x1b[5~");
+        assert_eq!(encode(FunctionKey::PageDown, &[]), b"\x1b[6~");
+        assert_eq!(encode(FunctionKey::Home, &[]), b"\x1b[H");
+        assert_eq!(encode(FunctionKey::End, &[]), b"\x1b[F");
+        // modifiers are added as a parameter
+        assert_eq!(encode(FunctionKey::PageUp, &[ModifierKey::Shift]), b"\x1b[5;2~");
+        assert_eq!(encode(FunctionKey::PageDown, &[ModifierKey::Ctrl]), b"\x1b[6;5~");
+        assert_eq!(encode(FunctionKey::Home, &[ModifierKey::Shift]), b"\x1b[1;2H");
+        assert_eq!(encode(FunctionKey::End, &[ModifierKey::Ctrl, ModifierKey::Shift, ModifierKey::Alt]), b"\x1b[1;8F");
+        // other function keys ignore modifiers
+        assert_eq!(encode(FunctionKey::Enter, &[ModifierKey::Shift]), b"\x0d");
+    }
+
+    #[test]
+    fn window_focus_encoding() {
+        assert_eq!(Encoder::encode_focus_in(), b"\x1b[I");
+        assert_eq!(Encoder::encode_focus_out(), b"\x1b[O");
+        assert_eq!(Encoder::encode_focus_event(true), Encoder::encode_focus_in());
+        assert_eq!(Encoder::encode_focus_event(false), Encoder::encode_focus_out());
+        let mut encoder = Encoder::default();
+        let mut output = Vec::new();
+        // focus is only reported when enabled
+        encoder.on_window_focus(true, &mut |data| output.extend_from_slice(data));
+        assert!(output.is_empty());
+        encoder.is_report_focus = true;
+        encoder.on_window_focus(true, &mut |data| output.extend_from_slice(data));
+        encoder.on_window_focus(false, &mut |data| output.extend_from_slice(data));
+        assert_eq!(output.as_slice(), b"\x1b[I\x1b[O");
+    }
+
+    #[test]
+    fn streaming_paste_encoding() {
+        let mut encoder = Encoder::default();
+        let chunks: [&[u8]; 3] = [b"first ", b"second ", b"third"];
+        let stream = |encoder: &Encoder| -> Vec<u8> {
+            let mut output = Vec::new();
+            output.extend_from_slice(encoder.encode_paste_start());
+            for chunk in chunks {
+                output.extend_from_slice(chunk);
+            }
+            output.extend_from_slice(encoder.encode_paste_end());
+            output
+        };
+        // markers are empty unless bracketed paste mode is enabled
+        assert_eq!(encoder.encode_paste_start(), b"");
+        assert_eq!(encoder.encode_paste_end(), b"");
+        assert_eq!(stream(&encoder), b"first second third");
+        encoder.is_bracketed_paste_mode = true;
+        assert_eq!(stream(&encoder), b"\x1b[200~first second third\x1b[201~");
+        // streamed chunks match a single paste
+        let mut output = Vec::new();
+        encoder.paste_text(b"first second third", &mut |data| output.extend_from_slice(data));
+        assert_eq!(output, stream(&encoder));
+    }
+
+    #[test]
+    fn window_title_encoding() {
+        let mut encoder = Encoder::default();
+        let mut encode = |title: &str, is_icon: bool| -> Vec<u8> {
+            let mut output = Vec::new();
+            if is_icon {
+                encoder.set_icon_title(title, &mut |data| output.extend_from_slice(data));
+            } else {
+                encoder.set_window_title(title, &mut |data| output.extend_from_slice(data));
+            }
+            output
+        };
+        assert_eq!(encode("vim main.rs", false), b"\x1b]0;vim main.rs\x07");
+        assert_eq!(encode("vim main.rs", true), b"\x1b]1;vim main.rs\x07");
+        assert_eq!(encode("", false), b"\x1b]0;\x07");
+        // unicode is utf8 encoded
+        assert_eq!(encode("caf\u{e9} \u{1f600}", false), b"\x1b]0;caf\xc3\xa9 \xf0\x9f\x98\x80\x07");
+        // control characters are removed so the sequence isn't terminated early
+        assert_eq!(encode("a\x07b\x1bc", false), b"\x1b]0;abc\x07");
+    }
 }
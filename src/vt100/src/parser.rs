@@ -10,9 +10,12 @@ use crate::common::{
     CursorStyle,
     EraseMode,
     GraphicStyle,
+    InlineImage,
+    PromptMark,
     Rgb8,
     ScreenMode,
     ScrollRegion,
+    UnderlineStyle,
     WindowAction,
 };
 use crate::encoder::{
@@ -37,6 +40,7 @@ pub enum ParserError {
     InvalidWarningBellVolume(u16),
     InvalidMarginBellVolume(u16),
     InvalidDesignate(u8),
+    InvalidUnderlineStyle(u16),
 }
 
 pub trait ParserHandler {
@@ -45,7 +49,8 @@ pub trait ParserHandler {
 }
 
 #[derive(Clone,Copy,Debug,Default,PartialEq)]
-enum ParserContext {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ParserContext {
     #[default]
     EntryPoint,                         // ESC
     ControlSequenceIntroducer,          // ESC [
@@ -60,7 +65,8 @@ enum ParserContext {
 }
 
 #[derive(Clone,Copy,Debug,Default,PartialEq)]
-enum ParserState {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ParserState {
     #[default]
     Characters,
     Numbers,
@@ -89,6 +95,27 @@ enum OperatingSystemCommandTerminator {
     Backslash, // ESC \\
 }
 
+// Snapshot of parser internals for debugging sequences that fail to parse
+#[derive(Clone,Debug,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParserDebugState {
+    pub state: ParserState,
+    pub context: ParserContext,
+    pub buffer_hex: String,
+    pub numbers: Vec<u16>,
+    pub bytes_consumed: usize,
+}
+
+impl std::fmt::Display for ParserDebugState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "state: {:?}", self.state)?;
+        writeln!(f, "context: {:?}", self.context)?;
+        writeln!(f, "buffer: [{}]", self.buffer_hex)?;
+        writeln!(f, "numbers: {:?}", self.numbers)?;
+        write!(f, "bytes_consumed: {}", self.bytes_consumed)
+    }
+}
+
 pub struct Parser {
     state: ParserState,
     context: ParserContext,
@@ -135,6 +162,25 @@ impl Parser {
         self.state == ParserState::Terminated
     }
 
+    // Bytes of a sequence that is still waiting for more data, empty if the sequence has terminated
+    pub fn remaining_buffer(&self) -> &[u8] {
+        if self.is_terminated() {
+            return &[];
+        }
+        self.buffer.as_slice()
+    }
+
+    pub fn debug_state(&self) -> ParserDebugState {
+        let buffer_hex: Vec<String> = self.buffer.iter().map(|b| format!("{:02x}", b)).collect();
+        ParserDebugState {
+            state: self.state,
+            context: self.context,
+            buffer_hex: buffer_hex.join(" "),
+            numbers: self.numbers.clone(),
+            bytes_consumed: self.buffer.len(),
+        }
+    }
+
     fn parse_byte(&mut self, b: u8, h: &mut impl ParserHandler) {
         match self.state {
             ParserState::Characters => {
@@ -505,7 +551,19 @@ impl Parser {
         let i_end = self.buffer.len()-total_terminator_bytes;
         let data = &self.buffer[i_start..i_end];
         match n {
-            0 | 2 => match String::from_utf8(data.to_vec()) {
+            // @mark: ESC ] 0 ; <title> <terminator> sets both the icon label and window title
+            0 => match String::from_utf8(data.to_vec()) {
+                Ok(title) => {
+                    self.on_success(h, Command::WindowAction(WindowAction::SetIconTitle(title.clone())));
+                    self.on_success(h, Command::WindowAction(WindowAction::SetWindowTitle(title)));
+                },
+                Err(error) => self.on_error(h, ParserError::InvalidUtf8String(error)),
+            },
+            1 => match String::from_utf8(data.to_vec()) {
+                Ok(label) => self.on_success(h, Command::WindowAction(WindowAction::SetIconTitle(label))),
+                Err(error) => self.on_error(h, ParserError::InvalidUtf8String(error)),
+            },
+            2 => match String::from_utf8(data.to_vec()) {
                 Ok(title) => self.on_success(h, Command::WindowAction(WindowAction::SetWindowTitle(title))),
                 Err(error) => self.on_error(h, ParserError::InvalidUtf8String(error)),
             },
@@ -513,6 +571,30 @@ impl Parser {
                 Ok(title) => self.on_success(h, Command::SetHyperlink(title)),
                 Err(error) => self.on_error(h, ParserError::InvalidUtf8String(error)),
             },
+            // @mark: ESC ] 4 ; <index> ; <colour> <terminator>
+            4 => match (self.numbers.as_slice(), try_parse_colour_spec(data)) {
+                ([_, index], Some(colour)) if *index <= 255 => {
+                    self.on_success(h, Command::SetPaletteColour(*index as u8, colour))
+                },
+                _ => self.on_success(h, Command::UnhandledOperatingSystemCommand(n, data.to_vec())),
+            },
+            // @mark: ESC ] 12 ; <colour> <terminator>
+            12 => match try_parse_colour_spec(data) {
+                Some(colour) => self.on_success(h, Command::SetCursorColour(colour)),
+                None => self.on_success(h, Command::UnhandledOperatingSystemCommand(n, data.to_vec())),
+            },
+            // @mark: ESC ] 112 <terminator>
+            112 => self.on_success(h, Command::ResetCursorColour),
+            // @mark: ESC ] 133 ; <A|B|C|D> [; <params>] <terminator>
+            133 => match PromptMark::try_from_bytes(data) {
+                Some(mark) => self.on_success(h, Command::SetPromptMark(mark)),
+                None => self.on_success(h, Command::UnhandledOperatingSystemCommand(n, data.to_vec())),
+            },
+            // @mark: ESC ] 1337 ; File = [<args>] : <base64> <terminator>
+            1337 => match InlineImage::try_from_bytes(data) {
+                Some(image) => self.on_success(h, Command::InlineImage(image)),
+                None => self.on_success(h, Command::UnhandledOperatingSystemCommand(n, data.to_vec())),
+            },
             _ => self.on_success(h, Command::UnhandledOperatingSystemCommand(n, data.to_vec())),
         }
     }
@@ -583,8 +665,27 @@ impl Parser {
     }
 
     fn read_graphics_command(&mut self, h: &mut impl ParserHandler) {
+        // @mark: ESC [ 4 : <n> m
+        if self.is_first_separator_colon() {
+            if let [4, style, ..] = self.numbers.as_slice() {
+                let style = *style;
+                return match UnderlineStyle::try_from_u16(style) {
+                    Some(style) => self.on_success(h, Command::SetUnderlineStyle(style)),
+                    None => self.on_error(h, ParserError::InvalidUnderlineStyle(style)),
+                };
+            }
+        }
         // @mark: ESC [ <n> m
         match self.numbers.as_slice() {
+            [58,5,id,..] => return self.on_success(h, Command::SetUnderlineColourTable((*id).min(255) as u8)),
+            [58,2,r,g,b,..] => {
+                let rgb = Rgb8 {
+                    r: (*r).min(255) as u8,
+                    g: (*g).min(255) as u8,
+                    b: (*b).min(255) as u8,
+                };
+                return self.on_success(h, Command::SetUnderlineColourRgb(rgb));
+            },
             [38,5,id,..] => return self.on_success(h, Command::SetForegroundColourTable((*id).min(255) as u8)),
             [48,5,id,..] => return self.on_success(h, Command::SetBackgroundColourTable((*id).min(255) as u8)),
             [38,2,r,g,b,..] => {
@@ -620,6 +721,11 @@ impl Parser {
         }
     }
 
+    // Sub-parameters are separated by colons instead of semicolons, e.g. ESC [ 4:3 m
+    fn is_first_separator_colon(&self) -> bool {
+        self.buffer.iter().find(|b| [b';', b':'].contains(b)) == Some(&b':')
+    }
+
     fn read_scrolling_region(&self) -> Option<ScrollRegion> {
         match self.numbers.as_slice() {
             [top, bottom, ..] => Some(ScrollRegion::new(*top, *bottom)),
@@ -761,18 +867,41 @@ impl Parser {
 
 impl std::fmt::Debug for Parser {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut res = f.debug_struct("Vt100Parser");
-        res.field("state", &self.state);
-        res.field("context", &self.context);
-        if let Ok(ref str) = std::str::from_utf8(self.buffer.as_slice()) {
-            res.field("buffer", str);
-        } else {
-            res.field("buffer", &self.buffer);
-        };
-        res.field("numbers", &self.numbers);
-        res.field("number_slice", &self.number_slice);
-        res.field("numbers_last_index", &self.numbers_last_index);
-        res.field("osc_terminator", &self.osc_terminator);
-        res.finish()
+        writeln!(f, "Vt100Parser")?;
+        write!(f, "{}", self.debug_state())
+    }
+}
+
+// Parses xparsecolor formats: rgb:<r>/<g>/<b> with 1-4 hex digits per channel or #rrggbb
+fn try_parse_colour_spec(data: &[u8]) -> Option<Rgb8> {
+    let data = std::str::from_utf8(data).ok()?;
+    if let Some(hex) = data.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let v = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Rgb8 {
+            r: ((v >> 16) & 0xFF) as u8,
+            g: ((v >> 8) & 0xFF) as u8,
+            b: (v & 0xFF) as u8,
+        });
+    }
+    let channels = data.strip_prefix("rgb:")?;
+    let parse_channel = |c: &str| -> Option<u8> {
+        if c.is_empty() || c.len() > 4 {
+            return None;
+        }
+        let v = u32::from_str_radix(c, 16).ok()?;
+        // scale to 8bits
+        let max = (1u32 << (4*c.len())) - 1;
+        Some(((v*255 + max/2) / max) as u8)
+    };
+    let mut channels = channels.split('/');
+    let r = parse_channel(channels.next()?)?;
+    let g = parse_channel(channels.next()?)?;
+    let b = parse_channel(channels.next()?)?;
+    if channels.next().is_some() {
+        return None;
     }
+    Some(Rgb8 { r, g, b })
 }
@@ -0,0 +1,348 @@
+#![cfg(feature = "conformance-tests")]
+// Reference sequences are adapted from the vttest screens for cursor movement, screen features and
+// character attributes. Each test drives a minimal screen model so that the parsed commands can be
+// checked against the expected screen contents.
+use vt100::{
+    command::Command,
+    common::{EraseMode, GraphicStyle},
+    parser::{Parser, ParserHandler, ParserError},
+};
+
+const ATTRIBUTE_BOLD: u8 = 0x1;
+const ATTRIBUTE_UNDERLINE: u8 = 0x2;
+const ATTRIBUTE_BLINKING: u8 = 0x4;
+const ATTRIBUTE_INVERSE: u8 = 0x8;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+struct TestCell {
+    character: char,
+    attributes: u8,
+}
+
+impl Default for TestCell {
+    fn default() -> Self {
+        Self { character: ' ', attributes: 0 }
+    }
+}
+
+#[derive(Default)]
+struct CommandQueue {
+    commands: Vec<Command>,
+    errors: Vec<ParserError>,
+}
+
+impl ParserHandler for CommandQueue {
+    fn on_command(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+
+    fn on_error(&mut self, error: ParserError, _parser: &Parser) {
+        self.errors.push(error);
+    }
+}
+
+struct TestTerminal {
+    width: usize,
+    height: usize,
+    cells: Vec<TestCell>,
+    cursor_x: usize,
+    cursor_y: usize,
+    attributes: u8,
+    parser: Parser,
+    is_escape: bool,
+}
+
+impl TestTerminal {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![TestCell::default(); width*height],
+            cursor_x: 0,
+            cursor_y: 0,
+            attributes: 0,
+            parser: Parser::default(),
+            is_escape: false,
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        for &b in data {
+            if self.is_escape {
+                let mut queue = CommandQueue::default();
+                self.parser.feed_byte(b, &mut queue);
+                assert!(queue.errors.is_empty(), "parser errors: {:?}", queue.errors);
+                for command in queue.commands {
+                    self.apply_command(command);
+                }
+                if self.parser.is_terminated() {
+                    self.parser.reset();
+                    self.is_escape = false;
+                }
+                continue;
+            }
+            match b {
+                0x1B => self.is_escape = true,
+                b'\r' => self.cursor_x = 0,
+                b'\n' => self.feed_newline(),
+                0x08 => self.cursor_x = self.cursor_x.saturating_sub(1),
+                b' '..=b'~' => self.write_char(b as char),
+                _ => panic!("unexpected byte in conformance sequence: {:#04x}", b),
+            }
+        }
+    }
+
+    fn write_char(&mut self, c: char) {
+        if self.cursor_x >= self.width {
+            self.cursor_x = 0;
+            self.feed_newline();
+        }
+        let index = self.cursor_y*self.width + self.cursor_x;
+        self.cells[index] = TestCell { character: c, attributes: self.attributes };
+        self.cursor_x += 1;
+    }
+
+    fn feed_newline(&mut self) {
+        if self.cursor_y+1 >= self.height {
+            self.scroll_up(1);
+        } else {
+            self.cursor_y += 1;
+        }
+    }
+
+    fn clear_range(&mut self, start: usize, end: usize) {
+        self.cells[start..end].fill(TestCell::default());
+    }
+
+    fn scroll_up(&mut self, total: usize) {
+        let total = total.min(self.height);
+        let offset = total*self.width;
+        self.cells.copy_within(offset.., 0);
+        let length = self.cells.len();
+        self.clear_range(length-offset, length);
+    }
+
+    fn scroll_down(&mut self, total: usize) {
+        let total = total.min(self.height);
+        let offset = total*self.width;
+        let length = self.cells.len();
+        self.cells.copy_within(..(length-offset), offset);
+        self.clear_range(0, offset);
+    }
+
+    fn insert_lines(&mut self, total: usize) {
+        let total = total.min(self.height-self.cursor_y);
+        let start = self.cursor_y*self.width;
+        let offset = total*self.width;
+        let length = self.cells.len();
+        self.cells.copy_within(start..(length-offset), start+offset);
+        self.clear_range(start, start+offset);
+    }
+
+    fn delete_lines(&mut self, total: usize) {
+        let total = total.min(self.height-self.cursor_y);
+        let start = self.cursor_y*self.width;
+        let offset = total*self.width;
+        let length = self.cells.len();
+        self.cells.copy_within((start+offset).., start);
+        self.clear_range(length-offset, length);
+    }
+
+    fn apply_command(&mut self, command: Command) {
+        let cursor_index = self.cursor_y*self.width + self.cursor_x.min(self.width-1);
+        let line_start = self.cursor_y*self.width;
+        let line_end = line_start + self.width;
+        match command {
+            Command::MoveCursorUp(n) => self.cursor_y = self.cursor_y.saturating_sub(n as usize),
+            Command::MoveCursorDown(n) => self.cursor_y = (self.cursor_y + n as usize).min(self.height-1),
+            Command::MoveCursorLeft(n) => self.cursor_x = self.cursor_x.saturating_sub(n as usize),
+            Command::MoveCursorRight(n) => self.cursor_x = (self.cursor_x + n as usize).min(self.width-1),
+            Command::MoveCursorHorizontalAbsolute(n) => self.cursor_x = (n as usize - 1).min(self.width-1),
+            Command::MoveCursorVerticalAbsolute(n) => self.cursor_y = (n as usize - 1).min(self.height-1),
+            Command::MoveCursorPositionViewport(pos) => {
+                self.cursor_x = (pos.x as usize - 1).min(self.width-1);
+                self.cursor_y = (pos.y as usize - 1).min(self.height-1);
+            },
+            Command::EraseInLine(mode) => match mode {
+                EraseMode::FromCursorToEnd => self.clear_range(cursor_index, line_end),
+                EraseMode::FromCursorToStart => self.clear_range(line_start, cursor_index+1),
+                EraseMode::EntireDisplay | EraseMode::SavedLines => self.clear_range(line_start, line_end),
+            },
+            Command::EraseInDisplay(mode) => match mode {
+                EraseMode::FromCursorToEnd => self.clear_range(cursor_index, self.cells.len()),
+                EraseMode::FromCursorToStart => self.clear_range(0, cursor_index+1),
+                EraseMode::EntireDisplay | EraseMode::SavedLines => self.clear_range(0, self.cells.len()),
+            },
+            Command::ScrollUp(n) => self.scroll_up(n as usize),
+            Command::ScrollDown(n) => self.scroll_down(n as usize),
+            Command::InsertLines(n) => self.insert_lines(n as usize),
+            Command::DeleteLines(n) => self.delete_lines(n as usize),
+            Command::SetGraphicStyle(style) => match style {
+                GraphicStyle::ResetAll => self.attributes = 0,
+                GraphicStyle::EnableBold => self.attributes |= ATTRIBUTE_BOLD,
+                GraphicStyle::EnableUnderline => self.attributes |= ATTRIBUTE_UNDERLINE,
+                GraphicStyle::EnableBlinking => self.attributes |= ATTRIBUTE_BLINKING,
+                GraphicStyle::EnableInverse => self.attributes |= ATTRIBUTE_INVERSE,
+                GraphicStyle::DisableWeight => self.attributes &= !ATTRIBUTE_BOLD,
+                GraphicStyle::DisableUnderline => self.attributes &= !ATTRIBUTE_UNDERLINE,
+                GraphicStyle::DisableBlinking => self.attributes &= !ATTRIBUTE_BLINKING,
+                GraphicStyle::DisableInverse => self.attributes &= !ATTRIBUTE_INVERSE,
+                _ => panic!("unexpected graphic style in conformance sequence: {:?}", style),
+            },
+            _ => panic!("unexpected command in conformance sequence: {:?}", command),
+        }
+    }
+
+    fn get_screen(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.width)
+            .map(|row| row.iter().map(|cell| cell.character).collect())
+            .collect()
+    }
+
+    fn get_attributes(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.width)
+            .map(|row| row.iter().map(|cell| char::from_digit(cell.attributes as u32, 16).unwrap()).collect())
+            .collect()
+    }
+}
+
+fn assert_screen(given: &[String], expected: &[&str]) {
+    if given != expected {
+        panic!("screen mismatch\n  given: {:#?}\n  expected: {:#?}", given, expected);
+    }
+}
+
+fn fill_screen(terminal: &mut TestTerminal, c: char) {
+    let row: String = std::iter::repeat(c).take(terminal.width).collect();
+    for y in 0..terminal.height {
+        terminal.feed(format!("\x1b[{};1H{}", y+1, row).as_bytes());
+    }
+}
+
+#[test]
+fn cursor_absolute_addressing() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "C D     ",
+        "  AE    ",
+        "        ",
+        "       B",
+    ];
+    let mut terminal = TestTerminal::new(8,4);
+    terminal.feed(b"\x1b[2;3HA\x1b[4;8HB\x1b[HC\x1b[3GD\x1b[2dE");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+}
+
+#[test]
+fn cursor_relative_addressing() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "        ",
+        "   U    ",
+        "  X     ",
+        "L   D  R",
+    ];
+    let mut terminal = TestTerminal::new(8,4);
+    terminal.feed(b"\x1b[3;3HX\x1b[AU\x1b[2BD\x1b[5DL\x1b[10CR");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+}
+
+#[test]
+fn cursor_border_frame() {
+    // vttest draws a frame of characters using absolute positioning and backspaces
+    const EXPECTED_SCREEN: &[&str] = &[
+        "********",
+        "*+    +*",
+        "*+    +*",
+        "********",
+    ];
+    let mut terminal = TestTerminal::new(8,4);
+    terminal.feed(b"\x1b[1;1H********\x1b[4;1H********");
+    for row in 2..=3 {
+        terminal.feed(format!("\x1b[{};1H*+\x1b[{};7H+*", row, row).as_bytes());
+    }
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+}
+
+#[test]
+fn erase_in_line() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "EEEEEEEE",
+        "EEE     ",
+        "    EEEE",
+        "        ",
+    ];
+    let mut terminal = TestTerminal::new(8,4);
+    fill_screen(&mut terminal, 'E');
+    terminal.feed(b"\x1b[2;4H\x1b[K\x1b[3;4H\x1b[1K\x1b[4;4H\x1b[2K");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+}
+
+#[test]
+fn erase_in_display() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "        ",
+        "   EEEEE",
+        "EEEEE   ",
+        "        ",
+    ];
+    let mut terminal = TestTerminal::new(8,4);
+    fill_screen(&mut terminal, 'E');
+    terminal.feed(b"\x1b[2;3H\x1b[1J\x1b[3;6H\x1b[0J");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+    terminal.feed(b"\x1b[2J");
+    assert_screen(&terminal.get_screen(), &["        "; 4]);
+}
+
+#[test]
+fn scroll_up_and_down() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "    ",
+        "    ",
+        "2222",
+        "3333",
+    ];
+    let mut terminal = TestTerminal::new(4,4);
+    terminal.feed(b"\x1b[1;1H1111\x1b[2;1H2222\x1b[3;1H3333\x1b[4;1H4444");
+    terminal.feed(b"\x1b[S");
+    assert_screen(&terminal.get_screen(), &["2222", "3333", "4444", "    "]);
+    terminal.feed(b"\x1b[2T");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+}
+
+#[test]
+fn scroll_on_linefeed() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "BBBB",
+        "CCCC",
+        "DDDD",
+        "E   ",
+    ];
+    let mut terminal = TestTerminal::new(4,4);
+    terminal.feed(b"AAAA\r\nBBBB\r\nCCCC\r\nDDDD\r\nE");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+}
+
+#[test]
+fn insert_and_delete_lines() {
+    let mut terminal = TestTerminal::new(4,4);
+    terminal.feed(b"\x1b[1;1HAAAA\x1b[2;1HBBBB\x1b[3;1HCCCC\x1b[4;1HDDDD");
+    terminal.feed(b"\x1b[2;1H\x1b[L");
+    assert_screen(&terminal.get_screen(), &["AAAA", "    ", "BBBB", "CCCC"]);
+    terminal.feed(b"\x1b[1;1H\x1b[2M");
+    assert_screen(&terminal.get_screen(), &["BBBB", "CCCC", "    ", "    "]);
+}
+
+#[test]
+fn graphic_renditions() {
+    const EXPECTED_SCREEN: &[&str] = &[
+        "BUNIRAN",
+    ];
+    const EXPECTED_ATTRIBUTES: &[&str] = &[
+        "1208f00",
+    ];
+    let mut terminal = TestTerminal::new(7,1);
+    terminal.feed(b"\x1b[1mB\x1b[4;22mU\x1b[0mN\x1b[7mI\x1b[1;4;5mR\x1b[22;24;25;27mA\x1b[mN");
+    assert_screen(&terminal.get_screen(), EXPECTED_SCREEN);
+    assert_screen(&terminal.get_attributes(), EXPECTED_ATTRIBUTES);
+}